@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    time::{Duration, UNIX_EPOCH},
+};
 
 use clap::Args;
 use committable::Committable;
@@ -142,12 +145,27 @@ where
                 let view_number = req.integer_param("view_number")?;
                 let signature = try_extract_param(&req, "signature")?;
                 let sender = try_extract_param(&req, "sender")?;
-                state
-                    .available_blocks(&hash, view_number, sender, &signature)
-                    .await
-                    .context(BlockAvailableSnafu {
-                        resource: hash.to_string(),
-                    })
+                match req.opt_integer_param("deadline")? {
+                    Some(deadline) => {
+                        state
+                            .available_blocks_with_deadline(
+                                &hash,
+                                view_number,
+                                sender,
+                                &signature,
+                                UNIX_EPOCH + Duration::from_millis(deadline),
+                            )
+                            .await
+                    }
+                    None => {
+                        state
+                            .available_blocks(&hash, view_number, sender, &signature)
+                            .await
+                    }
+                }
+                .context(BlockAvailableSnafu {
+                    resource: hash.to_string(),
+                })
             }
             .boxed()
         })?
@@ -1,3 +1,5 @@
+use std::time::SystemTime;
+
 use async_trait::async_trait;
 use committable::Commitment;
 use hotshot_types::{
@@ -22,6 +24,22 @@ pub trait BuilderDataSource<TYPES: NodeType> {
         signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
     ) -> Result<Vec<AvailableBlockInfo<TYPES>>, BuildError>;
 
+    /// To get the list of available blocks, when the leader needs one of them by `deadline`.
+    ///
+    /// Builders should respond with the best blocks they can build by the deadline, rather than
+    /// with none at all. By default the deadline is ignored.
+    async fn available_blocks_with_deadline(
+        &self,
+        for_parent: &VidCommitment,
+        view_number: u64,
+        sender: TYPES::SignatureKey,
+        signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+        _deadline: SystemTime,
+    ) -> Result<Vec<AvailableBlockInfo<TYPES>>, BuildError> {
+        self.available_blocks(for_parent, view_number, sender, signature)
+            .await
+    }
+
     /// to claim a block from the list of provided available blocks
     async fn claim_block(
         &self,
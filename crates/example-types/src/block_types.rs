@@ -237,6 +237,12 @@ pub struct TestBlockHeader {
     pub builder_commitment: BuilderCommitment,
     /// Timestamp when this header was created.
     pub timestamp: u64,
+    /// Version of the header format, if recorded. Test headers don't record one unless a test
+    /// sets it.
+    pub app_version: Option<Version>,
+    /// Version the header was built for, if built by [`BlockHeader::new`]. Unlike `app_version`
+    /// this isn't validated, it only lets tests check which version a leader proposed under.
+    pub built_for_version: Option<Version>,
 }
 
 impl<TYPES: NodeType<BlockHeader = Self, BlockPayload = TestBlockPayload>> BlockHeader<TYPES>
@@ -253,7 +259,7 @@ impl<TYPES: NodeType<BlockHeader = Self, BlockPayload = TestBlockPayload>> Block
         _metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
         _builder_fee: BuilderFee<TYPES>,
         _vid_common: VidCommon,
-        _version: Version,
+        version: Version,
     ) -> Result<Self, Self::Error> {
         let parent = parent_leaf.block_header();
 
@@ -268,6 +274,8 @@ impl<TYPES: NodeType<BlockHeader = Self, BlockPayload = TestBlockPayload>> Block
             payload_commitment,
             builder_commitment,
             timestamp,
+            app_version: None,
+            built_for_version: Some(version),
         })
     }
 
@@ -282,6 +290,8 @@ impl<TYPES: NodeType<BlockHeader = Self, BlockPayload = TestBlockPayload>> Block
             payload_commitment,
             builder_commitment,
             timestamp: 0,
+            app_version: None,
+            built_for_version: None,
         }
     }
 
@@ -300,6 +310,10 @@ impl<TYPES: NodeType<BlockHeader = Self, BlockPayload = TestBlockPayload>> Block
     fn builder_commitment(&self) -> BuilderCommitment {
         self.builder_commitment.clone()
     }
+
+    fn app_version(&self) -> Option<Version> {
+        self.app_version
+    }
 }
 
 impl Committable for TestBlockHeader {
@@ -9,3 +9,7 @@ pub mod node_types;
 
 /// storage types for hotshot storage
 pub mod storage_types;
+
+/// durable storage backed by RocksDB
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb_storage;
@@ -0,0 +1,341 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use async_lock::Mutex;
+use async_trait::async_trait;
+use hotshot_types::{
+    consensus::CommitmentMap,
+    data::{DaProposal, Leaf, QuorumProposal, VidDisperseShare},
+    event::HotShotAction,
+    message::Proposal,
+    simple_certificate::QuorumCertificate,
+    simple_vote::DaVote,
+    traits::{
+        node_implementation::{ConsensusTime, NodeType},
+        storage::Storage,
+    },
+    utils::View,
+    vote::Vote,
+};
+use rocksdb::{ColumnFamilyDescriptor, Direction, IteratorMode, Options, WriteBatch, DB};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// VID shares we received, keyed by view and recipient
+const VIDS: &str = "vids";
+/// DA proposals we received, keyed by view
+const DAS: &str = "das";
+/// Quorum proposals we sent, keyed by view
+const PROPOSALS: &str = "proposals";
+/// Share messages of journaled VID dispersals not yet sent, keyed by view and recipient
+const VID_DISPERSALS: &str = "vid_dispersals";
+/// Views we have sent a quorum vote in, keyed by view
+const VOTES: &str = "votes";
+/// DA votes accumulated towards certificates we're forming, keyed by view and voter
+const DA_VOTES: &str = "da_votes";
+/// Singleton values, keyed by name
+const META: &str = "meta";
+
+/// Key in [`META`] of the high QC
+const HIGH_QC_KEY: &[u8] = b"high_qc";
+/// Key in [`META`] of the undecided leaf chain and state
+const UNDECIDED_STATE_KEY: &[u8] = b"undecided_state";
+
+/// The undecided leaf chain and state
+type UndecidedState<TYPES> = (
+    CommitmentMap<Leaf<TYPES>>,
+    BTreeMap<<TYPES as NodeType>::Time, View<TYPES>>,
+);
+
+/// A durable [`Storage`] backed by a RocksDB database, as a reference for production storage.
+///
+/// Every write goes through the database's write-ahead log before it returns, so everything a node
+/// stored survives its process crashing and being restarted with [`RocksDbStorage::open`] on the
+/// same path.
+///
+/// Each kind of data lives in its own column family. Keys start with the big-endian view number,
+/// so iterating a column family visits views in order; values are `bincode` serialized.
+pub struct RocksDbStorage<TYPES: NodeType> {
+    /// The database
+    db: Arc<DB>,
+    /// Serializes checking and recording votes, so we never record two votes in the same view
+    vote_lock: Arc<Mutex<()>>,
+    /// Phantom for the node types
+    _pd: PhantomData<TYPES>,
+}
+
+impl<TYPES: NodeType> Clone for RocksDbStorage<TYPES> {
+    fn clone(&self) -> Self {
+        Self {
+            db: Arc::clone(&self.db),
+            vote_lock: Arc::clone(&self.vote_lock),
+            _pd: PhantomData,
+        }
+    }
+}
+
+impl<TYPES: NodeType> std::fmt::Debug for RocksDbStorage<TYPES> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RocksDbStorage")
+            .field("path", &self.db.path())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<TYPES: NodeType> RocksDbStorage<TYPES> {
+    /// Open the storage at `path`, creating it if it doesn't exist yet, and recovering whatever
+    /// was stored there before otherwise.
+    ///
+    /// # Errors
+    /// if the database can't be opened
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let column_families = [VIDS, DAS, PROPOSALS, VID_DISPERSALS, VOTES, DA_VOTES, META]
+            .into_iter()
+            .map(|name| ColumnFamilyDescriptor::new(name, Options::default()));
+        let db = DB::open_cf_descriptors(&options, path.as_ref(), column_families)
+            .with_context(|| format!("Failed to open RocksDB storage at {:?}", path.as_ref()))?;
+        Ok(Self {
+            db: Arc::new(db),
+            vote_lock: Arc::new(Mutex::new(())),
+            _pd: PhantomData,
+        })
+    }
+
+    /// The latest view we have sent a quorum vote in, if any
+    ///
+    /// # Errors
+    /// if the database can't be read
+    pub fn last_voted_view(&self) -> Result<Option<TYPES::Time>> {
+        self.db
+            .iterator_cf(self.cf(VOTES)?, IteratorMode::End)
+            .next()
+            .transpose()?
+            .map(|(key, _)| decode_view::<TYPES>(&key).map(|(view, _)| view))
+            .transpose()
+    }
+
+    /// The handle of the column family `name`
+    fn cf(&self, name: &str) -> Result<&rocksdb::ColumnFamily> {
+        self.db
+            .cf_handle(name)
+            .with_context(|| format!("Missing column family {name}"))
+    }
+
+    /// Write `value` under `key` in the column family `name`.
+    fn put(&self, name: &str, key: impl AsRef<[u8]>, value: &impl Serialize) -> Result<()> {
+        self.db
+            .put_cf(self.cf(name)?, key, bincode::serialize(value)?)
+            .with_context(|| format!("Failed to write to {name}"))
+    }
+
+    /// Read the value under `key` in the column family `name`, if any.
+    fn get<T: DeserializeOwned>(&self, name: &str, key: impl AsRef<[u8]>) -> Result<Option<T>> {
+        self.db
+            .get_pinned_cf(self.cf(name)?, key)
+            .with_context(|| format!("Failed to read from {name}"))?
+            .map(|value| bincode::deserialize(&value).map_err(Into::into))
+            .transpose()
+    }
+}
+
+/// The key of `view`, which sorts in view order.
+fn view_key<TYPES: NodeType>(view: TYPES::Time) -> [u8; 8] {
+    view.u64().to_be_bytes()
+}
+
+/// The key of `view` and `recipient`, which sorts by view first.
+fn view_recipient_key<TYPES: NodeType>(
+    view: TYPES::Time,
+    recipient: &TYPES::SignatureKey,
+) -> Result<Vec<u8>> {
+    let mut key = view_key::<TYPES>(view).to_vec();
+    key.extend(bincode::serialize(recipient)?);
+    Ok(key)
+}
+
+/// Split a key starting with a view into the view and the rest of the key.
+fn decode_view<TYPES: NodeType>(key: &[u8]) -> Result<(TYPES::Time, &[u8])> {
+    ensure!(key.len() >= 8, "Malformed key {key:?}");
+    let (view, rest) = key.split_at(8);
+    Ok((TYPES::Time::new(u64::from_be_bytes(view.try_into()?)), rest))
+}
+
+#[async_trait]
+impl<TYPES: NodeType> Storage<TYPES> for RocksDbStorage<TYPES> {
+    async fn append_vid(&self, proposal: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> Result<()> {
+        let key =
+            view_recipient_key::<TYPES>(proposal.data.view_number, &proposal.data.recipient_key)?;
+        self.put(VIDS, key, proposal)
+    }
+
+    async fn append_da(&self, proposal: &Proposal<TYPES, DaProposal<TYPES>>) -> Result<()> {
+        self.put(DAS, view_key::<TYPES>(proposal.data.view_number), proposal)
+    }
+
+    async fn append_proposal(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
+    ) -> Result<()> {
+        self.put(
+            PROPOSALS,
+            view_key::<TYPES>(proposal.data.view_number),
+            proposal,
+        )
+    }
+
+    async fn append_vid_dispersal(
+        &self,
+        view: TYPES::Time,
+        messages: &HashMap<TYPES::SignatureKey, Vec<u8>>,
+    ) -> Result<()> {
+        // Journal the whole dispersal atomically, so we never resume only part of it.
+        let cf = self.cf(VID_DISPERSALS)?;
+        let mut batch = WriteBatch::default();
+        for (recipient, message) in messages {
+            batch.put_cf(cf, view_recipient_key::<TYPES>(view, recipient)?, message);
+        }
+        self.db
+            .write(batch)
+            .context("Failed to append VID dispersal to storage")
+    }
+
+    async fn record_vid_share_sent(
+        &self,
+        view: TYPES::Time,
+        recipient: &TYPES::SignatureKey,
+    ) -> Result<()> {
+        self.db
+            .delete_cf(
+                self.cf(VID_DISPERSALS)?,
+                view_recipient_key::<TYPES>(view, recipient)?,
+            )
+            .context("Failed to record VID share sent to storage")
+    }
+
+    async fn load_pending_vid_dispersals(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Time, HashMap<TYPES::SignatureKey, Vec<u8>>>> {
+        let mut dispersals: BTreeMap<_, HashMap<_, _>> = BTreeMap::new();
+        for entry in self
+            .db
+            .iterator_cf(self.cf(VID_DISPERSALS)?, IteratorMode::Start)
+        {
+            let (key, message) = entry?;
+            let (view, recipient) = decode_view::<TYPES>(&key)?;
+            dispersals
+                .entry(view)
+                .or_default()
+                .insert(bincode::deserialize(recipient)?, message.into_vec());
+        }
+        Ok(dispersals)
+    }
+
+    async fn gc_vid_dispersals(&self, view: TYPES::Time) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        batch.delete_range_cf(
+            self.cf(VID_DISPERSALS)?,
+            view_key::<TYPES>(TYPES::Time::genesis()),
+            view_key::<TYPES>(view),
+        );
+        self.db
+            .write(batch)
+            .context("Failed to garbage collect VID dispersals")
+    }
+
+    async fn record_action(&self, view: TYPES::Time, action: HotShotAction) -> Result<()> {
+        if !matches!(action, HotShotAction::Vote) {
+            return Ok(());
+        }
+        let _guard = self.vote_lock.lock().await;
+        let key = view_key::<TYPES>(view);
+        if self.db.get_pinned_cf(self.cf(VOTES)?, key)?.is_some() {
+            bail!("Already voted in view {view:?}");
+        }
+        self.db
+            .put_cf(self.cf(VOTES)?, key, b"")
+            .context("Failed to append Action to storage")
+    }
+
+    async fn update_high_qc(&self, high_qc: QuorumCertificate<TYPES>) -> Result<()> {
+        self.put(META, HIGH_QC_KEY, &high_qc)
+    }
+
+    async fn update_undecided_state(
+        &self,
+        leafs: CommitmentMap<Leaf<TYPES>>,
+        state: BTreeMap<TYPES::Time, View<TYPES>>,
+    ) -> Result<()> {
+        self.put(META, UNDECIDED_STATE_KEY, &(leafs, state))
+    }
+
+    async fn load_high_qc(&self) -> Result<Option<QuorumCertificate<TYPES>>> {
+        self.get(META, HIGH_QC_KEY)
+    }
+
+    async fn load_undecided_state(&self) -> Result<Option<UndecidedState<TYPES>>> {
+        self.get(META, UNDECIDED_STATE_KEY)
+    }
+
+    async fn load_da_proposals(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Time, Proposal<TYPES, DaProposal<TYPES>>>> {
+        self.db
+            .iterator_cf(self.cf(DAS)?, IteratorMode::Start)
+            .map(|entry| {
+                let (key, proposal) = entry?;
+                let (view, _) = decode_view::<TYPES>(&key)?;
+                Ok((view, bincode::deserialize(&proposal)?))
+            })
+            .collect()
+    }
+
+    async fn append_da_votes(&self, view: TYPES::Time, votes: &[DaVote<TYPES>]) -> Result<()> {
+        let cf = self.cf(DA_VOTES)?;
+        let mut batch = WriteBatch::default();
+        for vote in votes {
+            batch.put_cf(
+                cf,
+                view_recipient_key::<TYPES>(view, &vote.signing_key())?,
+                bincode::serialize(vote)?,
+            );
+        }
+        self.db
+            .write(batch)
+            .context("Failed to append DA votes to storage")
+    }
+
+    async fn load_da_votes(&self, view: TYPES::Time) -> Result<Vec<DaVote<TYPES>>> {
+        let start = view_key::<TYPES>(view);
+        let mut votes = Vec::new();
+        for entry in self.db.iterator_cf(
+            self.cf(DA_VOTES)?,
+            IteratorMode::From(&start, Direction::Forward),
+        ) {
+            let (key, vote) = entry?;
+            if decode_view::<TYPES>(&key)?.0 != view {
+                break;
+            }
+            votes.push(bincode::deserialize(&vote)?);
+        }
+        Ok(votes)
+    }
+
+    async fn gc_da_votes(&self, view: TYPES::Time) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        batch.delete_range_cf(
+            self.cf(DA_VOTES)?,
+            view_key::<TYPES>(TYPES::Time::genesis()),
+            view_key::<TYPES>(view),
+        );
+        self.db
+            .write(batch)
+            .context("Failed to garbage collect DA votes")
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     sync::Arc,
 };
 
@@ -9,7 +9,10 @@ use async_trait::async_trait;
 use hotshot_types::{
     consensus::CommitmentMap,
     data::{DaProposal, Leaf, QuorumProposal, VidDisperseShare},
+    event::HotShotAction,
     message::Proposal,
+    simple_certificate::QuorumCertificate,
+    simple_vote::DaVote,
     traits::{node_implementation::NodeType, storage::Storage},
     utils::View,
 };
@@ -19,11 +22,26 @@ type VidShares<TYPES> = HashMap<
     HashMap<<TYPES as NodeType>::SignatureKey, Proposal<TYPES, VidDisperseShare<TYPES>>>,
 >;
 
+type VidDispersals<TYPES> =
+    BTreeMap<<TYPES as NodeType>::Time, HashMap<<TYPES as NodeType>::SignatureKey, Vec<u8>>>;
+
+type UndecidedState<TYPES> = (
+    CommitmentMap<Leaf<TYPES>>,
+    BTreeMap<<TYPES as NodeType>::Time, View<TYPES>>,
+);
+
 #[derive(Clone, Debug)]
 pub struct TestStorageState<TYPES: NodeType> {
     vids: VidShares<TYPES>,
     das: HashMap<TYPES::Time, Proposal<TYPES, DaProposal<TYPES>>>,
     proposals: HashMap<TYPES::Time, Proposal<TYPES, QuorumProposal<TYPES>>>,
+    pending_vid_dispersals: VidDispersals<TYPES>,
+    high_qc: Option<QuorumCertificate<TYPES>>,
+    undecided_state: Option<UndecidedState<TYPES>>,
+    /// Views we have sent a quorum vote in, so that a restarted node never votes twice
+    voted_views: BTreeSet<TYPES::Time>,
+    /// DA votes accumulated towards the certificates we're forming, by view
+    da_votes: BTreeMap<TYPES::Time, Vec<DaVote<TYPES>>>,
 }
 
 impl<TYPES: NodeType> Default for TestStorageState<TYPES> {
@@ -32,6 +50,11 @@ impl<TYPES: NodeType> Default for TestStorageState<TYPES> {
             vids: HashMap::new(),
             das: HashMap::new(),
             proposals: HashMap::new(),
+            pending_vid_dispersals: BTreeMap::new(),
+            high_qc: None,
+            undecided_state: None,
+            voted_views: BTreeSet::new(),
+            da_votes: BTreeMap::new(),
         }
     }
 }
@@ -52,6 +75,24 @@ impl<TYPES: NodeType> Default for TestStorage<TYPES> {
     }
 }
 
+impl<TYPES: NodeType> TestStorage<TYPES> {
+    /// The latest view we have sent a quorum vote in, if any
+    pub async fn last_voted_view(&self) -> Option<TYPES::Time> {
+        self.inner.read().await.voted_views.last().copied()
+    }
+
+    /// The payloads of the DA proposals we have stored, by view
+    pub async fn saved_payloads(&self) -> BTreeMap<TYPES::Time, Arc<[u8]>> {
+        self.inner
+            .read()
+            .await
+            .das
+            .iter()
+            .map(|(view, proposal)| (*view, Arc::clone(&proposal.data.encoded_transactions)))
+            .collect()
+    }
+}
+
 #[async_trait]
 impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
     async fn append_vid(&self, proposal: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> Result<()> {
@@ -91,34 +132,134 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         Ok(())
     }
 
-    async fn record_action(
+    async fn append_vid_dispersal(
         &self,
-        _view: <TYPES as NodeType>::Time,
-        _action: hotshot_types::event::HotShotAction,
+        view: TYPES::Time,
+        messages: &HashMap<TYPES::SignatureKey, Vec<u8>>,
     ) -> Result<()> {
         if self.should_return_err {
-            bail!("Failed to append Action to storage");
+            bail!("Failed to append VID dispersal to storage");
         }
+        self.inner
+            .write()
+            .await
+            .pending_vid_dispersals
+            .insert(view, messages.clone());
         Ok(())
     }
 
-    async fn update_high_qc(
+    async fn record_vid_share_sent(
         &self,
-        _high_qc: hotshot_types::simple_certificate::QuorumCertificate<TYPES>,
+        view: TYPES::Time,
+        recipient: &TYPES::SignatureKey,
     ) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to record VID share sent to storage");
+        }
+        let mut inner = self.inner.write().await;
+        if let Some(messages) = inner.pending_vid_dispersals.get_mut(&view) {
+            messages.remove(recipient);
+            if messages.is_empty() {
+                inner.pending_vid_dispersals.remove(&view);
+            }
+        }
+        Ok(())
+    }
+
+    async fn load_pending_vid_dispersals(&self) -> Result<VidDispersals<TYPES>> {
+        Ok(self.inner.read().await.pending_vid_dispersals.clone())
+    }
+
+    async fn gc_vid_dispersals(&self, view: TYPES::Time) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        inner.pending_vid_dispersals = inner.pending_vid_dispersals.split_off(&view);
+        Ok(())
+    }
+
+    async fn record_action(
+        &self,
+        view: <TYPES as NodeType>::Time,
+        action: HotShotAction,
+    ) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to append Action to storage");
+        }
+        if matches!(action, HotShotAction::Vote)
+            && !self.inner.write().await.voted_views.insert(view)
+        {
+            bail!("Already voted in view {view:?}");
+        }
+        Ok(())
+    }
+
+    async fn update_high_qc(&self, high_qc: QuorumCertificate<TYPES>) -> Result<()> {
         if self.should_return_err {
             bail!("Failed to update high qc to storage");
         }
+        self.inner.write().await.high_qc = Some(high_qc);
         Ok(())
     }
     async fn update_undecided_state(
         &self,
-        _leafs: CommitmentMap<Leaf<TYPES>>,
-        _state: BTreeMap<TYPES::Time, View<TYPES>>,
+        leafs: CommitmentMap<Leaf<TYPES>>,
+        state: BTreeMap<TYPES::Time, View<TYPES>>,
     ) -> Result<()> {
         if self.should_return_err {
             bail!("Failed to update high qc to storage");
         }
+        self.inner.write().await.undecided_state = Some((leafs, state));
+        Ok(())
+    }
+
+    async fn load_high_qc(&self) -> Result<Option<QuorumCertificate<TYPES>>> {
+        Ok(self.inner.read().await.high_qc.clone())
+    }
+
+    async fn load_undecided_state(&self) -> Result<Option<UndecidedState<TYPES>>> {
+        Ok(self.inner.read().await.undecided_state.clone())
+    }
+
+    async fn load_da_proposals(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Time, Proposal<TYPES, DaProposal<TYPES>>>> {
+        Ok(self
+            .inner
+            .read()
+            .await
+            .das
+            .iter()
+            .map(|(view, proposal)| (*view, proposal.clone()))
+            .collect())
+    }
+
+    async fn append_da_votes(&self, view: TYPES::Time, votes: &[DaVote<TYPES>]) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to append DA votes to storage");
+        }
+        self.inner
+            .write()
+            .await
+            .da_votes
+            .entry(view)
+            .or_default()
+            .extend_from_slice(votes);
+        Ok(())
+    }
+
+    async fn load_da_votes(&self, view: TYPES::Time) -> Result<Vec<DaVote<TYPES>>> {
+        Ok(self
+            .inner
+            .read()
+            .await
+            .da_votes
+            .get(&view)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn gc_da_votes(&self, view: TYPES::Time) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        inner.da_votes = inner.da_votes.split_off(&view);
         Ok(())
     }
 }
@@ -54,6 +54,7 @@ use hotshot_types::{
     traits::{
         block_contents::{BlockHeader, TestableBlock},
         election::Membership,
+        metrics::{NoMetrics, PrometheusMetrics},
         network::ConnectedNetwork,
         node_implementation::{ConsensusTime, NodeType},
         states::TestableState,
@@ -375,10 +376,11 @@ pub trait RunDa<
     Leaf<TYPES>: TestableLeaf,
     Self: Sync,
 {
-    /// Initializes networking, returns self
+    /// Initializes networking, recording its metrics in `metrics`, returns self
     async fn initialize_networking(
         config: NetworkConfig<TYPES::SignatureKey>,
         libp2p_advertise_address: Option<SocketAddr>,
+        metrics: &PrometheusMetrics,
     ) -> Self;
 
     /// Initializes the genesis state and HotShot instance; does not start HotShot consensus
@@ -421,10 +423,18 @@ pub trait RunDa<
             config.config.fixed_leader_for_gpuvid,
         );
 
+        // Create the VID membership from all nodes, specifying the committee as the VID storage
+        // nodes
+        let vid_membership = <TYPES as NodeType>::Membership::create_election(
+            known_nodes_with_stake.clone(),
+            config.config.vid_nodes(),
+            config.config.fixed_leader_for_gpuvid,
+        );
+
         let memberships = Memberships {
             quorum_membership: quorum_membership.clone(),
             da_membership,
-            vid_membership: quorum_membership.clone(),
+            vid_membership,
             view_sync_membership: quorum_membership,
         };
 
@@ -436,7 +446,7 @@ pub trait RunDa<
             memberships,
             networks_bundle,
             initializer,
-            ConsensusMetricsValue::default(),
+            ConsensusMetricsValue::new_with_exporter(&NoMetrics, self.metrics()),
             TestStorage::<TYPES>::default(),
         )
         .await
@@ -477,7 +487,11 @@ pub trait RunDa<
         let mut anchor_view: TYPES::Time = <TYPES::Time as ConsensusTime>::genesis();
         let mut num_successful_commits = 0;
 
-        context.hotshot.start_consensus().await;
+        context
+            .hotshot
+            .start_consensus()
+            .await
+            .expect("Failed to start consensus");
 
         loop {
             match event_stream.next().await {
@@ -494,6 +508,7 @@ pub trait RunDa<
                             leaf_chain,
                             qc: _,
                             block_size,
+                            decided_view_count: _,
                         } => {
                             let current_timestamp = Utc::now().timestamp();
                             // this might be a obob
@@ -577,6 +592,10 @@ pub trait RunDa<
         // Output run results
         let total_time_elapsed = start.elapsed(); // in seconds
         println!("[{node_index}]: {rounds} rounds completed in {total_time_elapsed:?} - Total transactions sent: {total_transactions_sent} - Total transactions committed: {total_transactions_committed} - Total commitments: {num_successful_commits}");
+        info!(
+            "[{node_index}]: metrics:\n{}",
+            context.export_metrics_prometheus()
+        );
         if total_transactions_committed != 0 {
             // prevent devision by 0
             let total_time_elapsed_sec = std::cmp::max(total_time_elapsed.as_secs(), 1u64);
@@ -612,6 +631,9 @@ pub trait RunDa<
 
     /// Returns the config for this run
     fn config(&self) -> NetworkConfig<TYPES::SignatureKey>;
+
+    /// Returns the metrics recorded by the networks and consensus of this run
+    fn metrics(&self) -> PrometheusMetrics;
 }
 
 // Push CDN
@@ -624,6 +646,8 @@ pub struct PushCdnDaRun<TYPES: NodeType> {
     quorum_channel: PushCdnNetwork<TYPES>,
     /// The DA channel
     da_channel: PushCdnNetwork<TYPES>,
+    /// The metrics of the run
+    metrics: PrometheusMetrics,
 }
 
 #[async_trait]
@@ -650,6 +674,7 @@ where
     async fn initialize_networking(
         config: NetworkConfig<TYPES::SignatureKey>,
         _libp2p_advertise_address: Option<SocketAddr>,
+        metrics: &PrometheusMetrics,
     ) -> PushCdnDaRun<TYPES> {
         // Get our own key
         let key = config.config.my_own_validator_config.clone();
@@ -674,7 +699,7 @@ where
                 .expect("`cdn_marshal_address` needs to be supplied for a push CDN run"),
             topics,
             keypair,
-            CdnMetricsValue::default(),
+            CdnMetricsValue::new(metrics),
         )
         .expect("failed to create network");
 
@@ -685,6 +710,7 @@ where
             config,
             quorum_channel: network.clone(),
             da_channel: network,
+            metrics: metrics.clone(),
         }
     }
 
@@ -699,6 +725,10 @@ where
     fn config(&self) -> NetworkConfig<TYPES::SignatureKey> {
         self.config.clone()
     }
+
+    fn metrics(&self) -> PrometheusMetrics {
+        self.metrics.clone()
+    }
 }
 
 // Libp2p
@@ -711,6 +741,8 @@ pub struct Libp2pDaRun<TYPES: NodeType> {
     quorum_channel: Libp2pNetwork<TYPES::SignatureKey>,
     /// data availability channel
     da_channel: Libp2pNetwork<TYPES::SignatureKey>,
+    /// the metrics of the run
+    metrics: PrometheusMetrics,
 }
 
 #[async_trait]
@@ -738,6 +770,7 @@ where
     async fn initialize_networking(
         config: NetworkConfig<TYPES::SignatureKey>,
         libp2p_advertise_address: Option<SocketAddr>,
+        metrics: &PrometheusMetrics,
     ) -> Libp2pDaRun<TYPES> {
         // Extrapolate keys for ease of use
         let keys = config.clone().config.my_own_validator_config;
@@ -767,7 +800,7 @@ where
             bind_address,
             &public_key,
             &private_key,
-            Libp2pMetricsValue::default(),
+            Libp2pMetricsValue::new(metrics),
         )
         .await
         .expect("failed to create libp2p network");
@@ -779,6 +812,7 @@ where
             config,
             quorum_channel: libp2p_network.clone(),
             da_channel: libp2p_network,
+            metrics: metrics.clone(),
         }
     }
 
@@ -793,6 +827,10 @@ where
     fn config(&self) -> NetworkConfig<TYPES::SignatureKey> {
         self.config.clone()
     }
+
+    fn metrics(&self) -> PrometheusMetrics {
+        self.metrics.clone()
+    }
 }
 
 // Combined network
@@ -805,6 +843,8 @@ pub struct CombinedDaRun<TYPES: NodeType> {
     quorum_channel: CombinedNetworks<TYPES>,
     /// data availability channel
     da_channel: CombinedNetworks<TYPES>,
+    /// the metrics of the run
+    metrics: PrometheusMetrics,
 }
 
 #[async_trait]
@@ -831,6 +871,7 @@ where
     async fn initialize_networking(
         config: NetworkConfig<TYPES::SignatureKey>,
         libp2p_advertise_address: Option<SocketAddr>,
+        metrics: &PrometheusMetrics,
     ) -> CombinedDaRun<TYPES> {
         // Initialize our Libp2p network
         let libp2p_da_run: Libp2pDaRun<TYPES> =
@@ -839,7 +880,7 @@ where
                 Libp2pNetwork<TYPES::SignatureKey>,
                 Libp2pNetwork<TYPES::SignatureKey>,
                 Libp2pImpl,
-            >>::initialize_networking(config.clone(), libp2p_advertise_address)
+            >>::initialize_networking(config.clone(), libp2p_advertise_address, metrics)
             .await;
 
         // Initialize our CDN network
@@ -849,7 +890,7 @@ where
                 PushCdnNetwork<TYPES>,
                 PushCdnNetwork<TYPES>,
                 PushCdnImpl,
-            >>::initialize_networking(config.clone(), libp2p_advertise_address)
+            >>::initialize_networking(config.clone(), libp2p_advertise_address, metrics)
             .await;
 
         // Create our combined network config
@@ -875,6 +916,7 @@ where
             config,
             quorum_channel,
             da_channel,
+            metrics: metrics.clone(),
         }
     }
 
@@ -889,6 +931,10 @@ where
     fn config(&self) -> NetworkConfig<TYPES::SignatureKey> {
         self.config.clone()
     }
+
+    fn metrics(&self) -> PrometheusMetrics {
+        self.metrics.clone()
+    }
 }
 
 /// Main entry point for validators
@@ -983,7 +1029,9 @@ pub async fn main_entry_point<
     };
 
     info!("Initializing networking");
-    let run = RUNDA::initialize_networking(run_config.clone(), args.advertise_address).await;
+    let metrics = PrometheusMetrics::default();
+    let run =
+        RUNDA::initialize_networking(run_config.clone(), args.advertise_address, &metrics).await;
     let hotshot = run.initialize_state_and_hotshot().await;
 
     if let Some(task) = builder_task {
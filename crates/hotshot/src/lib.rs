@@ -16,7 +16,7 @@ pub mod types;
 pub mod tasks;
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     marker::PhantomData,
     num::NonZeroUsize,
     sync::Arc,
@@ -24,44 +24,67 @@ use std::{
 };
 
 use async_broadcast::{broadcast, InactiveReceiver, Receiver, Sender};
-use async_compatibility_layer::art::async_spawn;
+use async_compatibility_layer::art::{async_spawn, async_timeout};
 use async_lock::RwLock;
+#[cfg(async_executor_impl = "async-std")]
+use async_std::task::spawn_blocking;
 use async_trait::async_trait;
 use committable::Committable;
 use futures::join;
-use hotshot_task::task::{ConsensusTaskRegistry, NetworkTaskRegistry};
-use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event, network};
+use hotshot_task::{
+    cancellation::CancellationToken,
+    task::{ConsensusTaskRegistry, NetworkTaskRegistry},
+};
+use hotshot_task_impls::{
+    consensus::create_and_send_proposal,
+    events::HotShotEvent,
+    helpers::{broadcast_event, ExternalEventSender},
+    network,
+};
 // Internal
 /// Reexport error type
 pub use hotshot_types::error::HotShotError;
 use hotshot_types::{
-    consensus::{Consensus, ConsensusMetricsValue, View, ViewInner},
-    constants::{Base, EVENT_CHANNEL_SIZE, EXTERNAL_EVENT_CHANNEL_SIZE},
-    data::{Leaf, QuorumProposal},
-    event::{EventType, LeafInfo},
-    message::{DataMessage, Message, MessageKind, Proposal, VersionedMessage},
+    consensus::{CommitmentAndMetadata, Consensus, ConsensusMetricsValue, View, ViewInner},
+    constants::{
+        Base, EVENT_CHANNEL_SIZE, EXTERNAL_EVENT_CHANNEL_SIZE, MAX_TRANSACTION_POW_DIFFICULTY,
+    },
+    data::{null_block, Leaf, QuorumProposal, VidDisperse, VidDisperseShare, ViewChangeEvidence},
+    event::{EventFilter, EventType, LeafInfo},
+    message::{
+        version_for_view, DataMessage, Message, MessageKind, Proposal, TransactionSubmission,
+        TxPriority, VersionedMessage,
+    },
+    probe::{PeerProbe, ProbeDescriptor, ProbeMessage, ProbeReport},
     simple_certificate::{QuorumCertificate, UpgradeCertificate},
     traits::{
         consensus_api::ConsensusApi,
         election::Membership,
-        network::ConnectedNetwork,
+        network::{ConnectedNetwork, NetworkStats, ShutdownReason},
         node_implementation::{ConsensusTime, NodeType},
-        signature_key::SignatureKey,
+        signature_key::{InMemorySigner, SignatureKey, Signer},
         states::ValidatedState,
-        EncodeBytes,
+        BlockPayload, EncodeBytes,
     },
-    HotShotConfig,
+    transaction_pow::solve_pow,
+    vote::Certificate,
+    HotShotConfig, ValidationLevel,
 };
 // -- Rexports
 // External
 /// Reexport rand crate
 pub use rand;
-use tasks::{add_request_network_task, add_response_task};
-use tracing::{debug, instrument, trace};
+use tasks::{add_probe_task, add_request_network_task, add_response_task};
+#[cfg(async_executor_impl = "tokio")]
+use tokio::task::spawn_blocking;
+use tracing::{debug, error, instrument, trace, warn};
 use vbs::version::Version;
 
 use crate::{
-    tasks::{add_consensus_tasks, add_network_event_task, add_network_message_task},
+    tasks::{
+        add_consensus_tasks, add_network_change_task, add_network_event_task,
+        add_network_message_task,
+    },
     traits::NodeImplementation,
     types::{Event, SystemContextHandle},
 };
@@ -90,10 +113,28 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> Networks<TYPES, I> {
         self.da_network.wait_for_ready().await;
     }
 
-    /// shut down all networks
-    pub async fn shut_down_networks(&self) {
-        self.quorum_network.shut_down().await;
-        self.da_network.shut_down().await;
+    /// shut down all networks, each logging the same `reason`
+    pub async fn shut_down_networks(&self, reason: ShutdownReason) {
+        self.quorum_network.shut_down_with(reason.clone()).await;
+        self.da_network.shut_down_with(reason).await;
+    }
+
+    /// set the maximum size of a message accepted by all networks
+    pub fn set_max_inbound_message_size(&self, max_size: usize) {
+        self.quorum_network.set_max_inbound_message_size(max_size);
+        self.da_network.set_max_inbound_message_size(max_size);
+    }
+
+    /// the statistics of all networks added together, counting a network used for both quorum
+    /// and DA messages once
+    #[must_use]
+    pub fn stats(&self) -> NetworkStats {
+        if Arc::as_ptr(&self.quorum_network).cast::<()>()
+            == Arc::as_ptr(&self.da_network).cast::<()>()
+        {
+            return self.quorum_network.stats();
+        }
+        self.quorum_network.stats().combine(self.da_network.stats())
     }
 }
 
@@ -110,13 +151,56 @@ pub struct Memberships<TYPES: NodeType> {
     pub view_sync_membership: TYPES::Membership,
 }
 
+impl<TYPES: NodeType> Memberships<TYPES> {
+    /// Check that the VID storage nodes can receive and serve the VID shares consensus relies on.
+    ///
+    /// # Errors
+    /// If a quorum member isn't a VID storage node, since it only votes once it has its own share,
+    /// or if a VID storage node isn't a known node, so couldn't be sent its share.
+    pub fn validate_vid_membership(
+        &self,
+        config: &HotShotConfig<TYPES::SignatureKey>,
+        view: TYPES::Time,
+    ) -> Result<(), HotShotError<TYPES>> {
+        let vid_nodes = self.vid_membership.staked_committee(view);
+        if let Some(voter) = self
+            .quorum_membership
+            .staked_committee(view)
+            .into_iter()
+            .find(|node| !vid_nodes.contains(node))
+        {
+            return Err(HotShotError::StartupFailed {
+                context: format!(
+                    "Quorum member {voter:?} isn't a VID storage node, so can't get the share it needs to vote"
+                ),
+            });
+        }
+
+        let known_nodes: BTreeSet<_> = config
+            .known_nodes_with_stake
+            .iter()
+            .map(|peer| TYPES::SignatureKey::public_key(&peer.stake_table_entry))
+            .chain(config.known_nodes_without_stake.iter().cloned())
+            .collect();
+        if let Some(node) = vid_nodes.iter().find(|node| !known_nodes.contains(node)) {
+            return Err(HotShotError::StartupFailed {
+                context: format!(
+                    "VID storage node {node:?} isn't a known node, so can't be sent its share"
+                ),
+            });
+        }
+
+        Ok(())
+    }
+}
+
 /// Holds the state needed to participate in `HotShot` consensus
 pub struct SystemContext<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// The public key of this node
     public_key: TYPES::SignatureKey,
 
-    /// The private key of this node
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on behalf of this node, holding its private key
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Configuration items for this hotshot instance
     pub config: HotShotConfig<TYPES::SignatureKey>,
@@ -148,6 +232,9 @@ pub struct SystemContext<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// External event stream for communication with the application.
     pub(crate) external_event_stream: (Sender<Event<TYPES>>, InactiveReceiver<Event<TYPES>>),
 
+    /// Decides which events are emitted on the external event stream.
+    event_filter: Arc<RwLock<EventFilter<TYPES>>>,
+
     /// Anchored leaf provided by the initializer.
     anchored_leaf: Leaf<TYPES>,
 
@@ -166,13 +253,19 @@ pub struct SystemContext<TYPES: NodeType, I: NodeImplementation<TYPES>> {
 
     /// a potential upgrade certificate that has been decided on by the consensus tasks.
     pub decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
+
+    /// Transactions submitted before the node was ready to broadcast them.
+    ///
+    /// `Some` until `start_consensus` has run and the networks are ready, at which point the
+    /// queue is flushed and replaced with `None`.
+    pending_transactions: Arc<RwLock<Option<Vec<(TYPES::Transaction, TxPriority)>>>>,
 }
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> Clone for SystemContext<TYPES, I> {
     #![allow(deprecated)]
     fn clone(&self) -> Self {
         Self {
             public_key: self.public_key.clone(),
-            private_key: self.private_key.clone(),
+            signer: Arc::clone(&self.signer),
             config: self.config.clone(),
             networks: Arc::clone(&self.networks),
             memberships: Arc::clone(&self.memberships),
@@ -183,11 +276,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> Clone for SystemContext<TYPE
             start_view: self.start_view,
             output_event_stream: self.output_event_stream.clone(),
             external_event_stream: self.external_event_stream.clone(),
+            event_filter: Arc::clone(&self.event_filter),
             anchored_leaf: self.anchored_leaf.clone(),
             internal_event_stream: self.internal_event_stream.clone(),
             id: self.id,
             storage: Arc::clone(&self.storage),
             decided_upgrade_certificate: Arc::clone(&self.decided_upgrade_certificate),
+            pending_transactions: Arc::clone(&self.pending_transactions),
         }
     }
 }
@@ -210,9 +305,61 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
         initializer: HotShotInitializer<TYPES>,
         metrics: ConsensusMetricsValue,
         storage: I::Storage,
+    ) -> Result<Arc<Self>, HotShotError<TYPES>> {
+        Self::new_with_signer(
+            public_key,
+            Arc::new(InMemorySigner::new(private_key)),
+            nonce,
+            config,
+            memberships,
+            networks,
+            initializer,
+            metrics,
+            storage,
+        )
+        .await
+    }
+
+    /// Creates a new [`Arc<SystemContext>`] which signs everything through `signer`, so that its
+    /// private key can be held outside of the node, such as in a hardware security module.
+    ///
+    /// # Errors
+    /// If the configuration is invalid or the consensus state can't be initialized.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(signer, memberships, networks, initializer, metrics, storage))]
+    pub async fn new_with_signer(
+        public_key: TYPES::SignatureKey,
+        signer: Arc<dyn Signer<TYPES::SignatureKey>>,
+        nonce: u64,
+        config: HotShotConfig<TYPES::SignatureKey>,
+        memberships: Memberships<TYPES>,
+        networks: Networks<TYPES, I>,
+        initializer: HotShotInitializer<TYPES>,
+        metrics: ConsensusMetricsValue,
+        storage: I::Storage,
     ) -> Result<Arc<Self>, HotShotError<TYPES>> {
         debug!("Creating a new hotshot");
 
+        memberships.validate_vid_membership(&config, initializer.start_view)?;
+
+        if config.transaction_pow_difficulty > MAX_TRANSACTION_POW_DIFFICULTY {
+            return Err(HotShotError::StartupFailed {
+                context: format!(
+                    "Transaction proof of work difficulty {} exceeds the maximum of {MAX_TRANSACTION_POW_DIFFICULTY}",
+                    config.transaction_pow_difficulty
+                ),
+            });
+        }
+
+        if config.validation_level != ValidationLevel::Full {
+            error!(
+                "HotShot is running with proposal validation level {:?}; this departs from the protocol's voting rule and must not be used in production!",
+                config.validation_level
+            );
+        }
+
+        networks.set_max_inbound_message_size(config.max_inbound_message_size);
+
         let consensus_metrics = Arc::new(metrics);
         let anchored_leaf = initializer.inner;
         let instance_state = initializer.instance_state;
@@ -251,7 +398,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
         }
 
         let mut saved_leaves = HashMap::new();
-        let mut saved_payloads = BTreeMap::new();
+        let mut saved_payloads = initializer.saved_payloads;
         saved_leaves.insert(anchored_leaf.commit(), anchored_leaf.clone());
 
         for leaf in initializer.undecided_leafs {
@@ -263,7 +410,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
             saved_payloads.insert(anchored_leaf.view_number(), Arc::clone(&encoded_txns));
         }
 
-        let consensus = Consensus::new(
+        let mut consensus = Consensus::new(
             validated_state_map,
             anchored_leaf.view_number(),
             anchored_leaf.view_number(),
@@ -276,6 +423,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
             initializer.high_qc,
             Arc::clone(&consensus_metrics),
         );
+        consensus.set_max_undecided_views(config.max_undecided_views);
+        consensus.set_state_compaction_depth(config.state_compaction_depth);
 
         let consensus = Arc::new(RwLock::new(consensus));
         let version = Arc::new(RwLock::new(Base::VERSION));
@@ -289,7 +438,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
             consensus,
             instance_state: Arc::new(instance_state),
             public_key,
-            private_key,
+            signer,
             config,
             version,
             start_view: initializer.start_view,
@@ -299,9 +448,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
             internal_event_stream: (internal_tx, internal_rx.deactivate()),
             output_event_stream: (external_tx.clone(), external_rx.clone().deactivate()),
             external_event_stream: (external_tx, external_rx.deactivate()),
+            event_filter: Arc::new(RwLock::new(EventFilter::default())),
             anchored_leaf: anchored_leaf.clone(),
             storage: Arc::new(RwLock::new(storage)),
             decided_upgrade_certificate,
+            pending_transactions: Arc::new(RwLock::new(Some(Vec::new()))),
         });
 
         Ok(inner)
@@ -309,9 +460,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
 
     /// "Starts" consensus by sending a `QcFormed`, `ViewChange`, and `ValidatedStateUpdated` events
     ///
-    /// # Panics
-    /// Panics if sending genesis fails
-    pub async fn start_consensus(&self) {
+    /// # Errors
+    /// Returns [`HotShotError::StartupFailed`] if sending the genesis events fails
+    pub async fn start_consensus(&self) -> Result<(), HotShotError<TYPES>> {
         #[cfg(feature = "dependncy-tasks")]
         error!("HotShot is running with the dependency tasks feature enabled!!");
 
@@ -321,21 +472,19 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
         debug!("Starting Consensus");
         let consensus = self.consensus.read().await;
 
-        #[allow(clippy::panic)]
         self.internal_event_stream
             .0
             .broadcast_direct(Arc::new(HotShotEvent::ViewChange(self.start_view)))
             .await
-            .unwrap_or_else(|_| {
-                panic!(
+            .map_err(|_| HotShotError::StartupFailed {
+                context: format!(
                     "Genesis Broadcast failed; event = ViewChange({:?})",
                     self.start_view
-                )
-            });
+                ),
+            })?;
         #[cfg(feature = "dependency-tasks")]
         {
             if let Some(validated_state) = consensus.validated_state_map().get(&self.start_view) {
-                #[allow(clippy::panic)]
                 self.internal_event_stream
                     .0
                     .broadcast_direct(Arc::new(HotShotEvent::ValidatedStateUpdated(
@@ -343,27 +492,26 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
                         validated_state.clone(),
                     )))
                     .await
-                    .unwrap_or_else(|_| {
-                        panic!(
+                    .map_err(|_| HotShotError::StartupFailed {
+                        context: format!(
                             "Genesis Broadcast failed; event = ValidatedStateUpdated({:?})",
                             self.start_view,
-                        )
-                    });
+                        ),
+                    })?;
             }
         }
-        #[allow(clippy::panic)]
         self.internal_event_stream
             .0
             .broadcast_direct(Arc::new(HotShotEvent::QcFormed(either::Left(
                 consensus.high_qc().clone(),
             ))))
             .await
-            .unwrap_or_else(|_| {
-                panic!(
+            .map_err(|_| HotShotError::StartupFailed {
+                context: format!(
                     "Genesis Broadcast failed; event = QcFormed(either::Left({:?}))",
                     consensus.high_qc()
-                )
-            });
+                ),
+            })?;
 
         {
             // Some applications seem to expect a leaf decide event for the genesis leaf,
@@ -386,16 +534,167 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
                                 Arc::new(validated_state),
                                 Some(Arc::new(state_delta)),
                                 None,
+                                // Nobody proposes the genesis leaf, so it's attributed to the
+                                // leader of the genesis view.
+                                Some(
+                                    self.memberships
+                                        .quorum_membership
+                                        .leader(TYPES::Time::genesis()),
+                                ),
                             )]),
                             qc,
                             block_size: None,
+                            decided_view_count: 1,
                         },
                     },
-                    &self.external_event_stream.0,
+                    &self.external_event_sender(),
                 )
                 .await;
             }
         }
+        drop(consensus);
+
+        self.flush_pending_transactions().await;
+
+        Ok(())
+    }
+
+    /// Marks the node as ready to broadcast transactions, once the networks are ready, and
+    /// publishes every transaction that was submitted before then.
+    async fn flush_pending_transactions(&self) {
+        self.networks.wait_for_networks_ready().await;
+
+        let Some(pending_transactions) = self.pending_transactions.write().await.take() else {
+            return;
+        };
+
+        debug!(
+            "Publishing {} transactions queued before start",
+            pending_transactions.len()
+        );
+        for (transaction, priority) in pending_transactions {
+            if let Err(e) = self
+                .publish_transaction_with_priority(
+                    transaction,
+                    priority,
+                    Arc::clone(&self.decided_upgrade_certificate),
+                )
+                .await
+            {
+                error!("Failed to publish queued transaction: {e}");
+            }
+        }
+    }
+
+    /// Whether the node is ready to broadcast transactions, i.e. consensus has started and the
+    /// networks are ready.
+    pub async fn is_ready(&self) -> bool {
+        self.pending_transactions.read().await.is_none()
+    }
+
+    /// Number of transactions queued while waiting for the node to become ready.
+    pub async fn num_pending_transactions(&self) -> usize {
+        self.pending_transactions
+            .read()
+            .await
+            .as_ref()
+            .map_or(0, Vec::len)
+    }
+
+    /// The peers the quorum network currently believes are reachable, or `None` if it can't
+    /// tell. For observability only; consensus doesn't depend on it.
+    pub async fn connected_peers(&self) -> Option<BTreeSet<TYPES::SignatureKey>> {
+        self.networks.quorum_network.connected_peers().await
+    }
+
+    /// What this node must agree on with its peers, see [`probe`](Self::probe).
+    pub(crate) async fn probe_descriptor(&self) -> ProbeDescriptor<TYPES> {
+        ProbeDescriptor::new(
+            &self.instance_state,
+            &self.memberships.quorum_membership,
+            &self.memberships.da_membership,
+        )
+        .await
+    }
+
+    /// Check that every peer in the committee is reachable and agrees with this node on the
+    /// genesis leaf and the committee, waiting up to `timeout` for their replies.
+    ///
+    /// Meant to be run once the tasks are running but before
+    /// [`start_consensus`](Self::start_consensus), so a misconfigured network is caught before
+    /// launch rather than failing to make progress. Peers which haven't replied by the timeout are
+    /// reported unreachable.
+    ///
+    /// # Errors
+    /// Returns an error if the network hasn't upgraded by the current view, since peers only
+    /// understand probes from the [`Upgrade`](hotshot_types::constants::Upgrade) version on.
+    pub async fn probe(
+        &self,
+        timeout: Duration,
+    ) -> Result<ProbeReport<TYPES>, HotShotError<TYPES>> {
+        let view = self.consensus.read().await.cur_view();
+        let version = version_for_view(view, &*self.decided_upgrade_certificate.read().await)
+            .map_err(|e| HotShotError::Misc {
+                context: e.to_string(),
+            })?;
+        if version == Base::VERSION {
+            return Err(HotShotError::Misc {
+                context: format!("Can't probe peers before the network upgrades, in view {view:?}"),
+            });
+        }
+
+        let descriptor = self.probe_descriptor().await;
+        let peers: BTreeSet<_> = self
+            .memberships
+            .quorum_membership
+            .whole_committee(TYPES::Time::genesis())
+            .into_iter()
+            .filter(|peer| *peer != self.public_key)
+            .collect();
+
+        let mut receiver = self.internal_event_stream.1.activate_cloned();
+        let mut replies = BTreeMap::new();
+        let collect_replies = async {
+            self.networks.wait_for_networks_ready().await;
+            broadcast_event(
+                Arc::new(HotShotEvent::ProbeSend(
+                    ProbeMessage::Request(view, descriptor.clone()),
+                    self.public_key.clone(),
+                    None,
+                )),
+                &self.internal_event_stream.0,
+            )
+            .await;
+            while replies.len() < peers.len() {
+                let Ok(event) = receiver.recv_direct().await else {
+                    break;
+                };
+                if let HotShotEvent::ProbeRecv(ProbeMessage::Reply(_, theirs), sender) =
+                    event.as_ref()
+                {
+                    if peers.contains(sender) {
+                        replies.insert(sender.clone(), descriptor.compare(theirs));
+                    }
+                }
+            }
+        };
+        if async_timeout(timeout, collect_replies).await.is_err() {
+            debug!(
+                "Probe timed out after {} of {} peers replied",
+                replies.len(),
+                peers.len()
+            );
+        }
+
+        Ok(ProbeReport {
+            peers: peers
+                .into_iter()
+                .map(|peer| {
+                    let probe = replies.remove(&peer).unwrap_or(PeerProbe::Unreachable);
+                    (peer, probe)
+                })
+                .collect(),
+        })
     }
 
     /// Emit an external event
@@ -403,62 +702,102 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
     // TODO: remove with https://github.com/EspressoSystems/HotShot/issues/2407
     async fn send_external_event(&self, event: Event<TYPES>) {
         debug!(?event, "send_external_event");
-        broadcast_event(event, &self.external_event_stream.0).await;
+        broadcast_event(event, &self.external_event_sender()).await;
+    }
+
+    /// Install `filter` to decide which events are emitted to the application from now on.
+    pub async fn set_event_filter(&self, filter: EventFilter<TYPES>) {
+        *self.event_filter.write().await = filter;
     }
 
-    /// Publishes a transaction asynchronously to the network.
+    /// A sender for events to the application, which applies the installed event filter.
+    pub(crate) fn external_event_sender(&self) -> ExternalEventSender<TYPES> {
+        ExternalEventSender {
+            sender: self.external_event_stream.0.clone(),
+            filter: Arc::clone(&self.event_filter),
+        }
+    }
+
+    /// Publishes a transaction asynchronously to the network, with [`TxPriority::Normal`].
+    ///
+    /// If the node is not ready yet (see [`is_ready`](Self::is_ready)), the transaction is queued
+    /// and published once consensus has started, up to
+    /// [`max_pending_transactions`](HotShotConfig::max_pending_transactions) of them.
     ///
     /// # Errors
     ///
-    /// Always returns Ok; does not return an error if the transaction couldn't be published to the network
-    #[instrument(skip(self), err)]
+    /// Returns [`HotShotError::PendingTransactionsFull`] if the node isn't ready and its queue of
+    /// transactions is full. Otherwise, does not return an error if the transaction couldn't be
+    /// published to the network
     pub async fn publish_transaction_async(
         &self,
         transaction: TYPES::Transaction,
         decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
     ) -> Result<(), HotShotError<TYPES>> {
-        trace!("Adding transaction to our own queue");
-
-        let api = self.clone();
-        let view_number = api.consensus.read().await.cur_view();
+        self.publish_transaction_with_priority(
+            transaction,
+            TxPriority::Normal,
+            decided_upgrade_certificate,
+        )
+        .await
+    }
 
-        // Wrap up a message
-        let message_kind: DataMessage<TYPES> =
-            DataMessage::SubmitTransaction(transaction.clone(), view_number);
-        let message = Message {
-            sender: api.public_key.clone(),
-            kind: MessageKind::from(message_kind),
-        };
+    /// Publishes a transaction asynchronously to the network, with a `priority` builders can use
+    /// to order it within a block. Other nodes only receive the priority once the network has
+    /// upgraded, see [`TxPriority`]. Otherwise the same as
+    /// [`publish_transaction_async`](Self::publish_transaction_async).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HotShotError::PendingTransactionsFull`] if the node isn't ready and its queue of
+    /// transactions is full. Otherwise, does not return an error if the transaction couldn't be
+    /// published to the network
+    #[instrument(skip(self), err)]
+    pub async fn publish_transaction_with_priority(
+        &self,
+        transaction: TYPES::Transaction,
+        priority: TxPriority,
+        decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
+    ) -> Result<(), HotShotError<TYPES>> {
+        if self.queue_if_not_ready(&transaction, priority).await? {
+            return Ok(());
+        }
 
-        let cert = decided_upgrade_certificate.read().await.clone();
+        trace!("Adding transaction to our own queue");
 
-        let serialized_message = message
-            .serialize(&cert)
-            .map_err(|_| HotShotError::FailedToSerialize)?;
+        let api = self.clone();
+        let (view_number, serialized_message) = self
+            .transaction_message(&transaction, priority, &decided_upgrade_certificate)
+            .await?;
 
+        // A broadcast still going after a whole view is given up on, rather than left hanging
+        let broadcast_timeout = Duration::from_millis(api.config.next_view_timeout);
         async_spawn(async move {
             let da_membership = &api.memberships.da_membership.clone();
             join! {
-                // TODO We should have a function that can return a network error if there is one
-                // but first we'd need to ensure our network implementations can support that
-                // (and not hang instead)
-
                 // version <0, 1> currently fixed; this is the same as VERSION_0_1,
                 // and will be updated to be part of SystemContext. I wanted to use associated
                 // constants in NodeType, but that seems to be unavailable in the current Rust.
-                api
-                    .networks
-                    .da_network
-                    .broadcast_message(
-                        serialized_message,
-                        da_membership.whole_committee(view_number),
-                        BroadcastDelay::None,
-                    ),
+                async {
+                    if let Err(e) = api
+                        .networks
+                        .da_network
+                        .broadcast_message_timeout(
+                            serialized_message,
+                            da_membership.whole_committee(view_number),
+                            BroadcastDelay::None,
+                            broadcast_timeout,
+                        )
+                        .await
+                    {
+                        warn!(?e, "Failed to broadcast transaction");
+                    }
+                },
                 api
                     .send_external_event(Event {
                         view_number,
                         event: EventType::Transactions {
-                            transactions: vec![transaction],
+                            transactions: vec![(transaction, priority)],
                         },
                     }),
             }
@@ -466,12 +805,387 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
         Ok(())
     }
 
+    /// Publishes a transaction to the network with [`TxPriority::Normal`], waiting for the
+    /// broadcast to the DA committee to finish so that an application can retry a submission that
+    /// didn't go out.
+    ///
+    /// Prefer [`publish_transaction_async`](Self::publish_transaction_async) for throughput. As
+    /// there, if the node is not ready yet, the transaction is queued and published once consensus
+    /// has started, without reporting whether that broadcast succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HotShotError::NetworkBroadcast`] if the broadcast failed or didn't finish within
+    /// a view, or an error if the transaction message couldn't be built
+    pub async fn publish_transaction_blocking(
+        &self,
+        transaction: TYPES::Transaction,
+        decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
+    ) -> Result<(), HotShotError<TYPES>> {
+        self.publish_transaction_blocking_with_priority(
+            transaction,
+            TxPriority::Normal,
+            decided_upgrade_certificate,
+        )
+        .await
+    }
+
+    /// Publishes a transaction to the network with a `priority` builders can use to order it
+    /// within a block, waiting for the broadcast to finish. Otherwise the same as
+    /// [`publish_transaction_blocking`](Self::publish_transaction_blocking).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HotShotError::NetworkBroadcast`] if the broadcast failed or didn't finish within
+    /// a view, [`HotShotError::PendingTransactionsFull`] if the node isn't ready and its queue of
+    /// transactions is full, or an error if the transaction message couldn't be built
+    #[instrument(skip(self), err)]
+    pub async fn publish_transaction_blocking_with_priority(
+        &self,
+        transaction: TYPES::Transaction,
+        priority: TxPriority,
+        decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
+    ) -> Result<(), HotShotError<TYPES>> {
+        if self.queue_if_not_ready(&transaction, priority).await? {
+            return Ok(());
+        }
+
+        let (view_number, serialized_message) = self
+            .transaction_message(&transaction, priority, &decided_upgrade_certificate)
+            .await?;
+        self.networks
+            .da_network
+            .broadcast_message_timeout(
+                serialized_message,
+                self.memberships.da_membership.whole_committee(view_number),
+                BroadcastDelay::None,
+                Duration::from_millis(self.config.next_view_timeout),
+            )
+            .await
+            .map_err(|source| HotShotError::NetworkBroadcast {
+                view_number,
+                source,
+            })?;
+
+        self.send_external_event(Event {
+            view_number,
+            event: EventType::Transactions {
+                transactions: vec![(transaction, priority)],
+            },
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Queue `transaction` to be published with `priority` once consensus has started, if the
+    /// node isn't ready yet. Returns whether it was queued.
+    ///
+    /// # Errors
+    /// [`HotShotError::PendingTransactionsFull`] if the node isn't ready, but already has the
+    /// configured maximum number of transactions queued.
+    async fn queue_if_not_ready(
+        &self,
+        transaction: &TYPES::Transaction,
+        priority: TxPriority,
+    ) -> Result<bool, HotShotError<TYPES>> {
+        let mut pending_transactions = self.pending_transactions.write().await;
+        let Some(queue) = pending_transactions.as_mut() else {
+            return Ok(false);
+        };
+        let max = self.config.max_pending_transactions;
+        if queue.len() >= max {
+            return Err(HotShotError::PendingTransactionsFull { max });
+        }
+        trace!("Node not ready yet, queueing transaction");
+        queue.push((transaction.clone(), priority));
+        Ok(true)
+    }
+
+    /// The view we're submitting `transaction` in, and the serialized message submitting it with
+    /// `priority`. Once the network has upgraded, the message carries the proof of work other
+    /// nodes then require to accept it.
+    async fn transaction_message(
+        &self,
+        transaction: &TYPES::Transaction,
+        priority: TxPriority,
+        decided_upgrade_certificate: &RwLock<Option<UpgradeCertificate<TYPES>>>,
+    ) -> Result<(TYPES::Time, Vec<u8>), HotShotError<TYPES>> {
+        let view_number = self.consensus.read().await.cur_view();
+        let cert = decided_upgrade_certificate.read().await.clone();
+        let version = version_for_view(view_number, &cert).map_err(|source| {
+            HotShotError::FailedToSerialize {
+                view_number,
+                source,
+            }
+        })?;
+
+        // Wrap up a message
+        let message_kind: DataMessage<TYPES> = if version == Base::VERSION {
+            DataMessage::SubmitTransaction(transaction.clone(), view_number)
+        } else {
+            // Prove enough work for other nodes to accept the transaction. This takes about
+            // `2^difficulty` hashes, so do it off the executor.
+            let difficulty = self.config.transaction_pow_difficulty;
+            let nonce = {
+                let transaction = transaction.clone();
+                spawn_blocking(move || solve_pow(&transaction, difficulty)).await
+            };
+            #[cfg(async_executor_impl = "tokio")]
+            let nonce = nonce.unwrap();
+            let nonce = nonce.ok_or_else(|| HotShotError::Misc {
+                context: format!(
+                    "No proof of work for the transaction has {difficulty} leading zero bits; \
+                     lower the transaction proof of work difficulty"
+                ),
+            })?;
+            DataMessage::SubmitTransactionWithPow(TransactionSubmission {
+                transaction: transaction.clone(),
+                view: view_number,
+                nonce,
+                priority,
+            })
+        };
+        let message = Message {
+            sender: self.public_key.clone(),
+            kind: MessageKind::from(message_kind),
+        };
+
+        let serialized_message =
+            message
+                .serialize(&cert)
+                .map_err(|source| HotShotError::FailedToSerialize {
+                    view_number,
+                    source,
+                })?;
+        Ok((view_number, serialized_message))
+    }
+
+    /// Proposes a null block for the next view immediately, without waiting for the usual
+    /// proposal cascade. The next view is the one after the later of the current view and the
+    /// last view we proposed for, and the proposal extends our high QC.
+    ///
+    /// This is meant for single-node and controlled test setups.
+    ///
+    /// # Errors
+    /// Returns an error if we are not the leader of the next view, if we have already proposed
+    /// for it, or if the proposal cannot be built.
+    pub async fn propose_now(&self) -> Result<TYPES::Time, HotShotError<TYPES>> {
+        let consensus = self.consensus.read().await;
+        let view = std::cmp::max(
+            consensus.cur_view(),
+            consensus
+                .last_proposals()
+                .last_key_value()
+                .map_or(TYPES::Time::genesis(), |(view, _)| *view),
+        ) + 1;
+
+        if self.memberships.quorum_membership.leader(view) != self.public_key {
+            return Err(HotShotError::InvalidState {
+                context: format!("Not the leader of the next view {view:?}"),
+            });
+        }
+
+        let justify_qc = consensus.high_qc().clone();
+        let parent_view = justify_qc.view_number;
+        let (Some(parent_leaf), Some(parent_state)) = (
+            consensus
+                .saved_leaves()
+                .get(&justify_qc.data.leaf_commit)
+                .cloned(),
+            consensus.state(parent_view).cloned(),
+        ) else {
+            return Err(HotShotError::InvalidState {
+                context: format!(
+                    "Missing the leaf or state for the high QC in view {parent_view:?}"
+                ),
+            });
+        };
+        drop(consensus);
+
+        let (payload, metadata) = <TYPES::BlockPayload as BlockPayload<TYPES>>::empty();
+        let num_nodes = self.memberships.vid_membership.total_nodes();
+        let vid_disperse = VidDisperse::calculate_vid_disperse(
+            payload.encode(),
+            &Arc::new(self.memberships.vid_membership.clone()),
+            view,
+            None,
+        )
+        .await;
+        let builder_fee =
+            null_block::builder_fee::<TYPES>(num_nodes).ok_or_else(|| HotShotError::Misc {
+                context: "Failed to sign the null block builder fee".to_string(),
+            })?;
+
+        let commitment_and_metadata = CommitmentAndMetadata {
+            commitment: vid_disperse.payload_commitment,
+            builder_commitment: payload.builder_commitment(&metadata),
+            metadata,
+            fee: builder_fee,
+            block_view: view,
+        };
+
+        // The proposal is built from the VID common data of our own share
+        let Some(vid_share) = VidDisperseShare::from_vid_disperse(vid_disperse)
+            .into_iter()
+            .find(|share| share.recipient_key == self.public_key)
+        else {
+            return Err(HotShotError::InvalidState {
+                context: format!("No VID share of our own for view {view:?}"),
+            });
+        };
+        let vid_share = vid_share
+            .to_proposal_with_signer(self.signer.as_ref())
+            .await
+            .ok_or_else(|| HotShotError::Misc {
+                context: "Failed to sign our VID share".to_string(),
+            })?;
+        self.consensus
+            .write()
+            .await
+            .update_vid_shares(view, vid_share);
+
+        create_and_send_proposal(
+            self.public_key.clone(),
+            Arc::clone(&self.signer),
+            Arc::clone(&self.consensus),
+            self.internal_event_stream.0.clone(),
+            view,
+            commitment_and_metadata,
+            parent_leaf,
+            parent_state,
+            None,
+            None,
+            0,
+            Arc::clone(&self.instance_state),
+            *self.version.read().await,
+        )
+        .await;
+
+        // The proposal is only recorded once it's been sent
+        if self
+            .consensus
+            .read()
+            .await
+            .last_proposals()
+            .contains_key(&view)
+        {
+            Ok(view)
+        } else {
+            Err(HotShotError::InvalidState {
+                context: format!("Failed to propose for view {view:?}"),
+            })
+        }
+    }
+
+    /// Pauses this node's consensus tasks, with or without `dependency-tasks`, freezing its
+    /// consensus without touching its networks. The tasks hold back the events they receive while
+    /// paused, and handle them once [`resume`](Self::resume)d. Past
+    /// [`MAX_PAUSED_EVENTS`](hotshot_task_impls::helpers::MAX_PAUSED_EVENTS) the oldest are
+    /// dropped, which is logged, so a long pause can leave the node to catch up.
+    ///
+    /// Proposals and votes already waiting on their dependencies aren't paused. This is meant for
+    /// chaos testing.
+    pub async fn pause(&self) {
+        debug!("Pausing consensus");
+        broadcast_event(Arc::new(HotShotEvent::Pause), &self.internal_event_stream.0).await;
+    }
+
+    /// Resumes the tasks paused by [`pause`](Self::pause), which first handle the events they
+    /// held back while paused.
+    pub async fn resume(&self) {
+        debug!("Resuming consensus");
+        broadcast_event(
+            Arc::new(HotShotEvent::Resume),
+            &self.internal_event_stream.0,
+        )
+        .await;
+    }
+
+    /// Revokes the decided upgrade certificate, if any, rolling the protocol version back to the
+    /// base version, e.g. if the upgrade turns out to be buggy.
+    ///
+    /// This is only safe before the certificate's `new_version_first_view`. After that, messages
+    /// have already been sent under the new version, and nodes which don't revoke it too will
+    /// reject ours.
+    pub async fn revoke_upgrade_certificate(&self) {
+        let Some(cert) = self.decided_upgrade_certificate.write().await.take() else {
+            return;
+        };
+        warn!(?cert, "Revoking the decided upgrade certificate");
+
+        *self.version.write().await = Base::VERSION;
+        broadcast_event(
+            Arc::new(HotShotEvent::VersionUpgrade(Base::VERSION)),
+            &self.internal_event_stream.0,
+        )
+        .await;
+    }
+
+    /// Advances this node to view `to`, to recover it if it is stuck in an earlier view.
+    ///
+    /// The view change must be justified by `evidence`: a timeout certificate for the view before
+    /// `to`, or a view sync certificate for `to`. This keeps a node from being moved to an
+    /// arbitrary view.
+    ///
+    /// # Errors
+    /// Returns an error if `to` is not after the current view, or if the evidence is not for `to`
+    /// or is not a valid certificate.
+    pub async fn force_view_change(
+        &self,
+        to: TYPES::Time,
+        evidence: ViewChangeEvidence<TYPES>,
+    ) -> Result<(), HotShotError<TYPES>> {
+        let cur_view = self.consensus.read().await.cur_view();
+        if to <= cur_view {
+            return Err(HotShotError::InvalidState {
+                context: format!(
+                    "Can't force a view change from view {cur_view:?} back to view {to:?}"
+                ),
+            });
+        }
+
+        if !evidence.is_valid_for_view(&to) {
+            return Err(HotShotError::InvalidState {
+                context: format!("The view change evidence is not for view {to:?}"),
+            });
+        }
+
+        let membership = &self.memberships.quorum_membership;
+        let is_valid_cert = match &evidence {
+            ViewChangeEvidence::Timeout(cert) => cert.is_valid_cert(membership),
+            ViewChangeEvidence::ViewSync(cert) => cert.is_valid_cert(membership),
+        };
+        if !is_valid_cert {
+            return Err(HotShotError::InvalidState {
+                context: format!(
+                    "The view change evidence for view {to:?} is not a valid certificate"
+                ),
+            });
+        }
+
+        debug!("Forcing a view change from view {cur_view:?} to view {to:?}");
+        broadcast_event(
+            Arc::new(HotShotEvent::ViewChange(to)),
+            &self.internal_event_stream.0,
+        )
+        .await;
+
+        Ok(())
+    }
+
     /// Returns a copy of the consensus struct
     #[must_use]
     pub fn consensus(&self) -> Arc<RwLock<Consensus<TYPES>>> {
         Arc::clone(&self.consensus)
     }
 
+    /// Render the current consensus metrics, and any network metrics registered with them, in the
+    /// Prometheus text exposition format.
+    #[must_use]
+    pub fn export_metrics_prometheus(&self) -> String {
+        self.metrics.exporter.render()
+    }
+
     /// Returns a copy of the instance state
     pub fn instance_state(&self) -> Arc<TYPES::InstanceState> {
         Arc::clone(&self.instance_state)
@@ -591,6 +1305,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
         let mut handle = SystemContextHandle {
             consensus_registry,
             network_registry,
+            cancellation: CancellationToken::new(),
             output_event_stream: output_event_stream.clone(),
             internal_event_stream: internal_event_stream.clone(),
             hotshot: self.clone().into(),
@@ -599,6 +1314,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
 
         add_network_message_task(&mut handle, Arc::clone(&quorum_network)).await;
         add_network_message_task(&mut handle, Arc::clone(&da_network)).await;
+        add_network_change_task(&mut handle, Arc::clone(&quorum_network)).await;
 
         if let Some(request_receiver) = da_network.spawn_request_receiver_task().await {
             add_request_network_task(&mut handle).await;
@@ -640,6 +1356,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> SystemContext<TYPES, I> {
             network::vid_filter,
         )
         .await;
+        add_probe_task(&mut handle).await;
+        // Finish sending the VID shares of any dispersal we were partway through when we stopped
+        let vid_network = Arc::clone(&quorum_network);
+        let storage = Arc::clone(&self.storage);
+        handle.network_registry.register(async_spawn(async move {
+            vid_network.wait_for_ready().await;
+            network::resume_vid_dispersals(&*vid_network, &storage).await;
+        }));
         add_consensus_tasks::<TYPES, I, Base>(&mut handle).await;
         handle
     }
@@ -659,15 +1383,19 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusApi<TYPES, I>
 
     async fn send_event(&self, event: Event<TYPES>) {
         debug!(?event, "send_event");
-        broadcast_event(event, &self.hotshot.external_event_stream.0).await;
+        broadcast_event(event, &self.hotshot.external_event_sender()).await;
     }
 
     fn public_key(&self) -> &TYPES::SignatureKey {
         &self.hotshot.public_key
     }
 
-    fn private_key(&self) -> &<TYPES::SignatureKey as SignatureKey>::PrivateKey {
-        &self.hotshot.private_key
+    fn signer(&self) -> Arc<dyn Signer<TYPES::SignatureKey>> {
+        Arc::clone(&self.hotshot.signer)
+    }
+
+    async fn cur_view(&self) -> TYPES::Time {
+        self.hotshot.consensus.read().await.cur_view()
     }
 }
 
@@ -703,6 +1431,8 @@ pub struct HotShotInitializer<TYPES: NodeType> {
     undecided_state: BTreeMap<TYPES::Time, View<TYPES>>,
     /// Proposals we have sent out to provide to others for catchup
     saved_proposals: BTreeMap<TYPES::Time, Proposal<TYPES, QuorumProposal<TYPES>>>,
+    /// Encoded payloads we had, to answer VID requests for their views right away
+    saved_payloads: BTreeMap<TYPES::Time, Arc<[u8]>>,
 }
 
 impl<TYPES: NodeType> HotShotInitializer<TYPES> {
@@ -721,6 +1451,7 @@ impl<TYPES: NodeType> HotShotInitializer<TYPES> {
             state_delta: Some(Arc::new(state_delta)),
             start_view: TYPES::Time::new(0),
             saved_proposals: BTreeMap::new(),
+            saved_payloads: BTreeMap::new(),
             high_qc,
             undecided_leafs: Vec::new(),
             undecided_state: BTreeMap::new(),
@@ -745,6 +1476,38 @@ impl<TYPES: NodeType> HotShotInitializer<TYPES> {
         high_qc: QuorumCertificate<TYPES>,
         undecided_leafs: Vec<Leaf<TYPES>>,
         undecided_state: BTreeMap<TYPES::Time, View<TYPES>>,
+    ) -> Self {
+        Self::from_snapshot(
+            anchor_leaf,
+            instance_state,
+            validated_state,
+            start_view,
+            saved_proposals,
+            high_qc,
+            undecided_leafs,
+            undecided_state,
+            BTreeMap::new(),
+        )
+    }
+
+    /// Reload previous state as [`from_reload`](Self::from_reload) does, also restoring the
+    /// encoded payloads we had so that we can answer VID requests for their views right after
+    /// boot.
+    ///
+    /// # Arguments
+    /// * `saved_payloads` - The encoded payloads we had, by view. The payload of `anchor_leaf`,
+    /// if it has one, is added to them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_snapshot(
+        anchor_leaf: Leaf<TYPES>,
+        instance_state: TYPES::InstanceState,
+        validated_state: Option<Arc<TYPES::ValidatedState>>,
+        start_view: TYPES::Time,
+        saved_proposals: BTreeMap<TYPES::Time, Proposal<TYPES, QuorumProposal<TYPES>>>,
+        high_qc: QuorumCertificate<TYPES>,
+        undecided_leafs: Vec<Leaf<TYPES>>,
+        undecided_state: BTreeMap<TYPES::Time, View<TYPES>>,
+        saved_payloads: BTreeMap<TYPES::Time, Arc<[u8]>>,
     ) -> Self {
         Self {
             inner: anchor_leaf,
@@ -753,6 +1516,7 @@ impl<TYPES: NodeType> HotShotInitializer<TYPES> {
             state_delta: None,
             start_view,
             saved_proposals,
+            saved_payloads,
             high_qc,
             undecided_leafs,
             undecided_state,
@@ -3,9 +3,10 @@
 /// Provides trait to create task states from a `SystemContextHandle`
 pub mod task_state;
 
-use std::{sync::Arc, time::Duration};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use async_compatibility_layer::art::{async_sleep, async_spawn};
+use futures::StreamExt;
 use hotshot_task::task::Task;
 #[cfg(not(feature = "dependency-tasks"))]
 use hotshot_task_impls::consensus::ConsensusTaskState;
@@ -19,16 +20,19 @@ use hotshot_task_impls::{
 use hotshot_task_impls::{
     da::DaTaskState,
     events::HotShotEvent,
+    helpers::broadcast_event,
     network::{NetworkEventTaskState, NetworkMessageTaskState},
+    probe::ProbeTaskState,
     request::NetworkRequestState,
-    response::{run_response_task, NetworkResponseState, RequestReceiver},
+    response::{run_response_task_until_cancelled, NetworkResponseState, RequestReceiver},
     transactions::TransactionTaskState,
     upgrade::UpgradeTaskState,
     vid::VidTaskState,
     view_sync::ViewSyncTaskState,
 };
 use hotshot_types::{
-    message::{Messages, VersionedMessage},
+    event::{Event, EventType},
+    message::{LenientMessage, Message, Messages, VersionedMessage},
     traits::{
         network::ConnectedNetwork,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
@@ -51,12 +55,19 @@ pub enum GlobalEvent {
 pub async fn add_request_network_task<TYPES: NodeType, I: NodeImplementation<TYPES>>(
     handle: &mut SystemContextHandle<TYPES, I>,
 ) {
-    let state = NetworkRequestState::<TYPES, I>::create_from(handle).await;
+    let mut state = NetworkRequestState::<TYPES, I>::create_from(handle).await;
+    state
+        .backfill_proposals(
+            handle.hotshot.start_view,
+            handle.hotshot.config.proposal_backfill_views,
+        )
+        .await;
 
     let task = Task::new(
         state,
         handle.internal_event_stream.0.clone(),
         handle.internal_event_stream.1.activate_cloned(),
+        handle.cancellation.clone(),
     );
     handle.consensus_registry.run_task(task);
 }
@@ -66,17 +77,21 @@ pub async fn add_response_task<TYPES: NodeType, I: NodeImplementation<TYPES>>(
     handle: &mut SystemContextHandle<TYPES, I>,
     request_receiver: RequestReceiver,
 ) {
-    let state = NetworkResponseState::<TYPES>::new(
+    let state = NetworkResponseState::<TYPES>::with_signer(
         handle.hotshot.consensus(),
-        request_receiver,
         handle.hotshot.memberships.quorum_membership.clone().into(),
+        handle.hotshot.memberships.vid_membership.clone().into(),
         handle.public_key().clone(),
-        handle.private_key().clone(),
-    );
-    handle.network_registry.register(run_response_task::<TYPES>(
-        state,
-        handle.internal_event_stream.1.activate_cloned(),
-    ));
+        handle.signer(),
+    )
+    .with_decided_upgrade_certificate(Arc::clone(&handle.hotshot.decided_upgrade_certificate));
+    handle
+        .network_registry
+        .register(run_response_task_until_cancelled::<TYPES>(
+            state,
+            request_receiver,
+            handle.cancellation.clone(),
+        ));
 }
 /// Add the network task to handle messages and publish events.
 pub async fn add_network_message_task<
@@ -90,6 +105,9 @@ pub async fn add_network_message_task<
     let net = Arc::clone(&channel);
     let network_state: NetworkMessageTaskState<_> = NetworkMessageTaskState {
         event_stream: handle.internal_event_stream.0.clone(),
+        metrics: Arc::clone(&handle.hotshot.metrics),
+        transaction_pow_difficulty: handle.hotshot.config.transaction_pow_difficulty,
+        decided_upgrade_certificate: None,
     };
 
     let decided_upgrade_certificate = Arc::clone(&handle.hotshot.decided_upgrade_certificate);
@@ -99,23 +117,45 @@ pub async fn add_network_message_task<
     let task_handle = async_spawn(async move {
         loop {
             let decided_upgrade_certificate_lock = decided_upgrade_certificate.read().await.clone();
-            let msgs = match network.recv_msgs().await {
+            // Messages whose senders are waiting on an acknowledgement only get one once they've
+            // been handled, so anything we fail to decode is never acknowledged.
+            let mut acks = Vec::new();
+            let msgs = match network.recv_msgs_with_acks().await {
                 Ok(msgs) => {
                     let mut deserialized_messages = Vec::new();
 
-                    for msg in msgs {
+                    for (msg, ack) in msgs {
                         let deserialized_message = match VersionedMessage::deserialize(
                             &msg,
                             &decided_upgrade_certificate_lock,
                         ) {
                             Ok(deserialized) => deserialized,
-                            Err(e) => {
-                                tracing::error!("Failed to deserialize message: {}", e);
-                                return;
-                            }
+                            // A message from a node a version ahead of us isn't worth stopping
+                            // for, so it is dropped.
+                            Err(e) => match VersionedMessage::deserialize_lenient(
+                                &msg,
+                                &decided_upgrade_certificate_lock,
+                            ) {
+                                Ok(LenientMessage {
+                                    message: Message::<TYPES> { sender, .. },
+                                    stale_version: true,
+                                }) => {
+                                    tracing::warn!(
+                                        ?sender,
+                                        "Dropping message with a newer version: {}",
+                                        e
+                                    );
+                                    continue;
+                                }
+                                _ => {
+                                    tracing::error!("Failed to deserialize message: {}", e);
+                                    return;
+                                }
+                            },
                         };
 
                         deserialized_messages.push(deserialized_message);
+                        acks.extend(ack);
                     }
 
                     Messages(deserialized_messages)
@@ -131,12 +171,49 @@ pub async fn add_network_message_task<
                 // TODO: Stop sleeping here: https://github.com/EspressoSystems/HotShot/issues/2558
                 async_sleep(Duration::from_millis(100)).await;
             } else {
+                state.decided_upgrade_certificate = decided_upgrade_certificate_lock;
                 state.handle_messages(msgs.0).await;
+                for ack in acks {
+                    // The sender may have already given up waiting
+                    let _ = ack.sender.send(());
+                }
             }
         }
     });
     handle.network_registry.register(task_handle);
 }
+/// Add a task which reports the network's connectivity changes on the external event stream, if
+/// the network can report them.
+pub async fn add_network_change_task<
+    TYPES: NodeType,
+    I: NodeImplementation<TYPES>,
+    NET: ConnectedNetwork<TYPES::SignatureKey>,
+>(
+    handle: &mut SystemContextHandle<TYPES, I>,
+    channel: Arc<NET>,
+) {
+    let Some(mut changes) = channel.network_change_receiver().await else {
+        return;
+    };
+    let output_event_stream = handle.hotshot.external_event_sender();
+    let consensus = handle.hotshot.consensus();
+
+    let task_handle = async_spawn(async move {
+        while let Some(change) = changes.next().await {
+            let view_number = consensus.read().await.cur_view();
+            broadcast_event(
+                Event {
+                    view_number,
+                    event: EventType::ConnectionChange { change },
+                },
+                &output_event_stream,
+            )
+            .await;
+        }
+    });
+    handle.network_registry.register(task_handle);
+}
+
 /// Add the network task to handle events and send messages.
 pub async fn add_network_event_task<
     TYPES: NodeType,
@@ -155,15 +232,26 @@ pub async fn add_network_event_task<
         filter,
         storage: Arc::clone(&handle.storage()),
         decided_upgrade_certificate: None,
+        // A broadcast still going after a whole view is given up on, rather than left hanging
+        broadcast_timeout: Duration::from_millis(handle.hotshot.config.next_view_timeout),
+        parent_da_certificates: BTreeMap::new(),
     };
     let task = Task::new(
         network_state,
         handle.internal_event_stream.0.clone(),
         handle.internal_event_stream.1.activate_cloned(),
+        handle.cancellation.clone(),
     );
     handle.consensus_registry.run_task(task);
 }
 
+/// Add a task which answers the probes of peers checking they agree with us.
+pub async fn add_probe_task<TYPES: NodeType, I: NodeImplementation<TYPES>>(
+    handle: &mut SystemContextHandle<TYPES, I>,
+) {
+    handle.add_task(ProbeTaskState::<TYPES>::create_from(handle).await);
+}
+
 /// Adds consensus-related tasks to a `SystemContextHandle`.
 pub async fn add_consensus_tasks<
     TYPES: NodeType,
@@ -1,6 +1,6 @@
 use std::{
     collections::{BTreeMap, HashMap},
-    sync::{atomic::AtomicBool, Arc},
+    sync::Arc,
 };
 
 use async_trait::async_trait;
@@ -8,11 +8,20 @@ use chrono::Utc;
 #[cfg(feature = "rewind")]
 use hotshot_task_impls::rewind::RewindTaskState;
 use hotshot_task_impls::{
-    builder::BuilderClient, consensus::ConsensusTaskState, consensus2::Consensus2TaskState,
-    da::DaTaskState, quorum_proposal::QuorumProposalTaskState,
-    quorum_proposal_recv::QuorumProposalRecvTaskState, quorum_vote::QuorumVoteTaskState,
-    request::NetworkRequestState, transactions::TransactionTaskState, upgrade::UpgradeTaskState,
-    vid::VidTaskState, view_sync::ViewSyncTaskState,
+    builder::BuilderClient,
+    consensus::ConsensusTaskState,
+    consensus2::Consensus2TaskState,
+    da::DaTaskState,
+    helpers::SeenVidShares,
+    probe::ProbeTaskState,
+    quorum_proposal::QuorumProposalTaskState,
+    quorum_proposal_recv::QuorumProposalRecvTaskState,
+    quorum_vote::QuorumVoteTaskState,
+    request::{CatchupLimiter, NetworkRequestState},
+    transactions::TransactionTaskState,
+    upgrade::UpgradeTaskState,
+    vid::VidTaskState,
+    view_sync::ViewSyncTaskState,
 };
 use hotshot_types::traits::{
     consensus_api::ConsensusApi,
@@ -46,10 +55,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
             da_membership: handle.hotshot.memberships.da_membership.clone(),
             quorum_membership: handle.hotshot.memberships.quorum_membership.clone(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
-            shutdown_flag: Arc::new(AtomicBool::new(false)),
+            cancellation: handle.cancellation.clone(),
             spawned_tasks: BTreeMap::new(),
+            backfill_tasks: BTreeMap::new(),
+            catchup_limiter: Arc::new(CatchupLimiter::new(
+                handle.hotshot.config.catchup_requests_per_second,
+            )),
+            decided_upgrade_certificate: Arc::clone(&handle.hotshot.decided_upgrade_certificate),
         }
     }
 }
@@ -61,13 +75,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
     async fn create_from(handle: &SystemContextHandle<TYPES, I>) -> UpgradeTaskState<TYPES, I> {
         #[cfg(not(feature = "example-upgrade"))]
         return UpgradeTaskState {
-            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            output_event_stream: handle.hotshot.external_event_sender(),
             cur_view: handle.cur_view().await,
             quorum_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
             quorum_network: Arc::clone(&handle.hotshot.networks.quorum_network),
             vote_collector: None.into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
             start_proposing_view: handle.hotshot.config.start_proposing_view,
             stop_proposing_view: handle.hotshot.config.stop_proposing_view,
@@ -77,13 +91,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
 
         #[cfg(feature = "example-upgrade")]
         return UpgradeTaskState {
-            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            output_event_stream: handle.hotshot.external_event_sender(),
             cur_view: handle.cur_view().await,
             quorum_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
             quorum_network: Arc::clone(&handle.hotshot.networks.quorum_network),
             vote_collector: None.into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
             start_proposing_view: 5,
             stop_proposing_view: 10,
@@ -105,7 +119,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
             network: Arc::clone(&handle.hotshot.networks.quorum_network),
             membership: handle.hotshot.memberships.vid_membership.clone().into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
         }
     }
@@ -118,16 +132,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
     async fn create_from(handle: &SystemContextHandle<TYPES, I>) -> DaTaskState<TYPES, I> {
         DaTaskState {
             consensus: handle.hotshot.consensus(),
-            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            output_event_stream: handle.hotshot.external_event_sender(),
             da_membership: handle.hotshot.memberships.da_membership.clone().into(),
             da_network: Arc::clone(&handle.hotshot.networks.da_network),
-            quorum_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
+            vid_membership: handle.hotshot.memberships.vid_membership.clone().into(),
             cur_view: handle.cur_view().await,
             vote_collector: None.into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
             storage: Arc::clone(&handle.storage),
+            max_block_size: handle.hotshot.config.max_block_size,
+            transaction_priorities: BTreeMap::new(),
         }
     }
 }
@@ -149,7 +165,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
                 .clone()
                 .into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             num_timeouts_tracked: 0,
             replica_task_map: HashMap::default().into(),
             pre_commit_relay_map: HashMap::default().into(),
@@ -171,13 +187,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
     ) -> TransactionTaskState<TYPES, I, Ver> {
         TransactionTaskState {
             builder_timeout: handle.builder_timeout(),
-            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            output_event_stream: handle.hotshot.external_event_sender(),
             consensus: handle.hotshot.consensus(),
             cur_view: handle.cur_view().await,
             network: Arc::clone(&handle.hotshot.networks.quorum_network),
             membership: handle.hotshot.memberships.quorum_membership.clone().into(),
+            vid_membership: handle.hotshot.memberships.vid_membership.clone().into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             instance_state: handle.hotshot.instance_state(),
             id: handle.hotshot.id,
             builder_clients: handle
@@ -189,6 +206,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
                 .map(BuilderClient::new)
                 .collect(),
             decided_upgrade_certificate: None,
+            builder_prefetch_depth: handle.hotshot.config.builder_prefetch_depth,
+            prefetched_blocks: Arc::default(),
+            prefetch_tasks: BTreeMap::new(),
+            max_block_size: handle.hotshot.config.max_block_size,
         }
     }
 }
@@ -217,18 +238,23 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
             proposal_cert: None,
             decided_upgrade_cert: None,
             version: Arc::clone(&handle.hotshot.version),
-            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            output_event_stream: handle.hotshot.external_event_sender(),
             current_proposal: None,
+            seen_vid_shares: SeenVidShares::default(),
             id: handle.hotshot.id,
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             quorum_network: Arc::clone(&handle.hotshot.networks.quorum_network),
             da_network: Arc::clone(&handle.hotshot.networks.da_network),
             timeout_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
             quorum_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
+            vid_membership: handle.hotshot.memberships.vid_membership.clone().into(),
             da_membership: handle.hotshot.memberships.da_membership.clone().into(),
             storage: Arc::clone(&handle.storage),
             decided_upgrade_certificate: Arc::clone(&handle.hotshot.decided_upgrade_certificate),
+            validation_level: handle.hotshot.config.validation_level,
+            proposal_send_times: BTreeMap::new(),
+            paused_events: None,
         }
     }
 }
@@ -242,19 +268,23 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
 
         QuorumVoteTaskState {
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             consensus,
             instance_state: handle.hotshot.instance_state(),
             latest_voted_view: handle.cur_view().await,
             vote_dependencies: HashMap::new(),
+            seen_vid_shares: SeenVidShares::default(),
             quorum_network: Arc::clone(&handle.hotshot.networks.quorum_network),
             da_network: Arc::clone(&handle.hotshot.networks.da_network),
             quorum_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
+            vid_membership: handle.hotshot.memberships.vid_membership.clone().into(),
             da_membership: handle.hotshot.memberships.da_membership.clone().into(),
-            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            output_event_stream: handle.hotshot.external_event_sender(),
             id: handle.hotshot.id,
             storage: Arc::clone(&handle.storage),
             version: *handle.hotshot.version.read().await,
+            paused_events: None,
+            cancellation: handle.cancellation.clone(),
         }
     }
 }
@@ -274,19 +304,24 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
             proposal_dependencies: HashMap::new(),
             quorum_network: Arc::clone(&handle.hotshot.networks.quorum_network),
             da_network: Arc::clone(&handle.hotshot.networks.da_network),
-            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            output_event_stream: handle.hotshot.external_event_sender(),
             consensus,
             instance_state: handle.hotshot.instance_state(),
             timeout_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
             quorum_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             storage: Arc::clone(&handle.storage),
             timeout: handle.hotshot.config.next_view_timeout,
             timeout_task,
             round_start_delay: handle.hotshot.config.round_start_delay,
             id: handle.hotshot.id,
             version: *handle.hotshot.version.read().await,
+            paused_events: None,
+            wait_for_dac_to_propose: handle.hotshot.config.wait_for_dac_to_propose,
+            pending_high_qc: Arc::default(),
+            high_qc_write_task: None,
+            cancellation: handle.cancellation.clone(),
         }
     }
 }
@@ -303,17 +338,19 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
 
         QuorumProposalRecvTaskState {
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             consensus,
             cur_view: handle.cur_view().await,
             cur_view_time: Utc::now().timestamp(),
             quorum_network: Arc::clone(&handle.hotshot.networks.quorum_network),
             quorum_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
+            vid_membership: handle.hotshot.memberships.vid_membership.clone().into(),
             timeout_membership: handle.hotshot.memberships.quorum_membership.clone().into(),
+            da_membership: handle.hotshot.memberships.da_membership.clone().into(),
             timeout_task,
             timeout: handle.hotshot.config.next_view_timeout,
             round_start_delay: handle.hotshot.config.round_start_delay,
-            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            output_event_stream: handle.hotshot.external_event_sender(),
             storage: Arc::clone(&handle.storage),
             formed_upgrade_certificate: None,
             proposal_cert: None,
@@ -322,6 +359,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
             instance_state: handle.hotshot.instance_state(),
             id: handle.hotshot.id,
             version: *handle.hotshot.version.read().await,
+            validation_level: handle.hotshot.config.validation_level,
+            seen_proposals: BTreeMap::new(),
+            paused_events: None,
         }
     }
 }
@@ -336,7 +376,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
 
         Consensus2TaskState {
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             instance_state: handle.hotshot.instance_state(),
             quorum_network: Arc::clone(&handle.hotshot.networks.quorum_network),
             da_network: Arc::clone(&handle.hotshot.networks.da_network),
@@ -348,12 +388,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
             storage: Arc::clone(&handle.storage),
             cur_view: handle.cur_view().await,
             cur_view_time: Utc::now().timestamp(),
-            output_event_stream: handle.hotshot.external_event_stream.0.clone(),
+            output_event_stream: handle.hotshot.external_event_sender(),
             timeout_task,
             timeout: handle.hotshot.config.next_view_timeout,
             consensus,
             last_decided_view: handle.cur_view().await,
             id: handle.hotshot.id,
+            paused_events: None,
         }
     }
 }
@@ -370,3 +411,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
         }
     }
 }
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>> CreateTaskState<TYPES, I>
+    for ProbeTaskState<TYPES>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I>) -> ProbeTaskState<TYPES> {
+        ProbeTaskState {
+            descriptor: handle.hotshot.probe_descriptor().await,
+            public_key: handle.public_key().clone(),
+        }
+    }
+}
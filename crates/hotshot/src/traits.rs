@@ -11,7 +11,7 @@ pub use node_implementation::{NodeImplementation, TestableNodeImplementation};
 /// Module for publicly usable implementations of the traits
 pub mod implementations {
     pub use super::networking::{
-        combined_network::{CombinedNetworks, UnderlyingCombinedNetworks},
+        combined_network::{union_connected_peers, CombinedNetworks, UnderlyingCombinedNetworks},
         libp2p_network::{
             derive_libp2p_keypair, derive_libp2p_peer_id, Libp2pMetricsValue, Libp2pNetwork,
             PeerInfoVec,
@@ -21,5 +21,7 @@ pub mod implementations {
             CdnMetricsValue, KeyPair, ProductionDef, PushCdnNetwork, TestingDef, Topic,
             WrappedSignatureKey,
         },
+        unreliable_network::UnreliableNetwork,
+        MessageCounts, NetworkingMetricsValue,
     };
 }
@@ -1,15 +1,24 @@
-use std::{marker::PhantomData, num::NonZeroU64};
+use std::{collections::BTreeMap, marker::PhantomData, num::NonZeroU64};
 
 use ethereum_types::U256;
 // use ark_bls12_381::Parameters as Param381;
 use hotshot_types::traits::signature_key::StakeTableEntryType;
 use hotshot_types::{
     signature_key::BLSPubKey,
-    traits::{election::Membership, node_implementation::NodeType, signature_key::SignatureKey},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+    },
     PeerConfig,
 };
 #[cfg(feature = "randomized-leader-election")]
 use rand::{rngs::StdRng, Rng};
+#[cfg(not(any(
+    feature = "randomized-leader-election",
+    feature = "fixed-leader-election"
+)))]
+use sha2::{Digest, Sha256};
 use tracing::debug;
 
 /// Dummy implementation of [`Membership`]
@@ -24,6 +33,10 @@ pub struct GeneralStaticCommittee<T, PUBKEY: SignatureKey> {
     committee_nodes_without_stake: Vec<PUBKEY>,
     /// the number of fixed leader for gpuvid
     fixed_leader_for_gpuvid: usize,
+    /// Whether every node in `all_nodes_with_stake` has the same stake, so they take turns leading
+    equal_stake: bool,
+    /// The group tag of each node which has one
+    node_groups: BTreeMap<PUBKEY, String>,
     /// Node type phantom
     _type_phantom: PhantomData<T>,
 }
@@ -41,13 +54,42 @@ impl<T, PUBKEY: SignatureKey> GeneralStaticCommittee<T, PUBKEY> {
         fixed_leader_for_gpuvid: usize,
     ) -> Self {
         Self {
+            equal_stake: has_equal_stake(&nodes_with_stake),
             all_nodes_with_stake: nodes_with_stake.clone(),
             committee_nodes_with_stake: nodes_with_stake,
             committee_nodes_without_stake: nodes_without_stake,
             fixed_leader_for_gpuvid,
+            node_groups: BTreeMap::new(),
             _type_phantom: PhantomData,
         }
     }
+
+    /// Tag nodes with the groups they belong to, such as their data centers, which DA committees
+    /// are spread across.
+    #[must_use]
+    pub fn with_node_groups(mut self, groups: impl IntoIterator<Item = (PUBKEY, String)>) -> Self {
+        self.node_groups.extend(groups);
+        self
+    }
+
+    /// Creates a new dummy elector in which every node in `nodes_with_stake` has the same stake
+    #[must_use]
+    pub fn new_equal_stake(
+        nodes_with_stake: &[PUBKEY],
+        nodes_without_stake: Vec<PUBKEY>,
+        fixed_leader_for_gpuvid: usize,
+    ) -> Self {
+        let entries = nodes_with_stake
+            .iter()
+            .map(|node| node.stake_table_entry(1))
+            .collect();
+        Self::new(
+            nodes_with_stake,
+            entries,
+            nodes_without_stake,
+            fixed_leader_for_gpuvid,
+        )
+    }
 }
 
 impl<TYPES, PUBKEY: SignatureKey + 'static> Membership<TYPES>
@@ -64,11 +106,24 @@ where
         feature = "randomized-leader-election",
         feature = "fixed-leader-election"
     )))]
-    /// Index the vector of public keys with the current view number
+    /// Pick a node with probability proportional to its stake, using the hash of the current view
+    /// number. When every node has the same stake, the nodes simply take turns.
     fn leader(&self, view_number: TYPES::Time) -> PUBKEY {
-        let index = usize::try_from(*view_number % self.all_nodes_with_stake.len() as u64).unwrap();
-        let res = self.all_nodes_with_stake[index].clone();
-        TYPES::SignatureKey::public_key(&res)
+        if self.equal_stake {
+            let index =
+                usize::try_from(*view_number % self.all_nodes_with_stake.len() as u64).unwrap();
+            return TYPES::SignatureKey::public_key(&self.all_nodes_with_stake[index]);
+        }
+
+        let hash = Sha256::digest((*view_number).to_le_bytes());
+        let mut target = U256::from_big_endian(&hash) % total_stake(&self.all_nodes_with_stake);
+        for entry in &self.all_nodes_with_stake {
+            if target < entry.stake() {
+                return TYPES::SignatureKey::public_key(entry);
+            }
+            target -= entry.stake();
+        }
+        unreachable!("The target is less than the total stake")
     }
 
     #[cfg(feature = "fixed-leader-election")]
@@ -95,21 +150,19 @@ where
         TYPES::SignatureKey::public_key(&res)
     }
 
+    fn node_group(&self, pub_key: &PUBKEY) -> Option<&str> {
+        self.node_groups.get(pub_key).map(String::as_str)
+    }
+
     fn has_stake(&self, pub_key: &PUBKEY) -> bool {
-        let entry = pub_key.stake_table_entry(1u64);
-        self.committee_nodes_with_stake.contains(&entry)
+        self.committee_entry(pub_key).is_some()
     }
 
     fn stake(
         &self,
         pub_key: &<TYPES as NodeType>::SignatureKey,
     ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
-        let entry = pub_key.stake_table_entry(1u64);
-        if self.committee_nodes_with_stake.contains(&entry) {
-            Some(entry)
-        } else {
-            None
-        }
+        self.committee_entry(pub_key).cloned()
     }
 
     fn create_election(
@@ -142,14 +195,18 @@ where
             committee_nodes_with_stake.len()
         );
 
+        let all_nodes_with_stake: Vec<_> = all_nodes
+            .into_iter()
+            .map(|entry| entry.stake_table_entry)
+            .collect();
+
         Self {
-            all_nodes_with_stake: all_nodes
-                .into_iter()
-                .map(|entry| entry.stake_table_entry)
-                .collect(),
+            equal_stake: has_equal_stake(&all_nodes_with_stake),
+            all_nodes_with_stake,
             committee_nodes_with_stake,
             committee_nodes_without_stake,
             fixed_leader_for_gpuvid,
+            node_groups: BTreeMap::new(),
             _type_phantom: PhantomData,
         }
     }
@@ -159,15 +216,15 @@ where
     }
 
     fn success_threshold(&self) -> NonZeroU64 {
-        NonZeroU64::new(((self.committee_nodes_with_stake.len() as u64 * 2) / 3) + 1).unwrap()
+        self.stake_threshold(2, 3)
     }
 
     fn failure_threshold(&self) -> NonZeroU64 {
-        NonZeroU64::new(((self.committee_nodes_with_stake.len() as u64) / 3) + 1).unwrap()
+        self.stake_threshold(1, 3)
     }
 
     fn upgrade_threshold(&self) -> NonZeroU64 {
-        NonZeroU64::new(((self.committee_nodes_with_stake.len() as u64 * 9) / 10) + 1).unwrap()
+        self.stake_threshold(9, 10)
     }
 
     fn staked_committee(
@@ -201,6 +258,17 @@ impl<TYPES, PUBKEY: SignatureKey + 'static> GeneralStaticCommittee<TYPES, PUBKEY
 where
     TYPES: NodeType<SignatureKey = PUBKEY>,
 {
+    /// Choose a DA committee of `size` out of the staked committee members, spread across as many
+    /// node groups as possible (see [`Membership::diverse_committee`]). The choice only depends on
+    /// the members and their groups, so every node chooses the same committee.
+    #[must_use]
+    pub fn with_diverse_committee(mut self, size: usize) -> Self {
+        let members = self.diverse_committee(TYPES::Time::genesis(), size);
+        self.committee_nodes_with_stake
+            .retain(|entry| members.contains(&PUBKEY::public_key(entry)));
+        self
+    }
+
     #[allow(clippy::must_use_candidate)]
     /// get the non-staked builder nodes
     pub fn non_staked_nodes_count(&self) -> usize {
@@ -211,4 +279,33 @@ where
     pub fn non_staked_nodes(&self) -> Vec<PUBKEY> {
         self.committee_nodes_without_stake.clone()
     }
+
+    /// The stake table entry of `pub_key` on the committee, if it has stake
+    fn committee_entry(&self, pub_key: &PUBKEY) -> Option<&PUBKEY::StakeTableEntry> {
+        self.committee_nodes_with_stake
+            .iter()
+            .find(|entry| PUBKEY::public_key(entry) == *pub_key)
+    }
+
+    /// More than `numerator / denominator` of the committee's total stake, saturating at
+    /// `u64::MAX`
+    fn stake_threshold(&self, numerator: u64, denominator: u64) -> NonZeroU64 {
+        let stake = total_stake(&self.committee_nodes_with_stake) * U256::from(numerator)
+            / U256::from(denominator);
+        NonZeroU64::new(stake.min(U256::from(u64::MAX - 1)).as_u64() + 1).unwrap()
+    }
+}
+
+/// Whether every entry in `entries` has the same stake.
+fn has_equal_stake<ENTRY: StakeTableEntryType>(entries: &[ENTRY]) -> bool {
+    entries
+        .windows(2)
+        .all(|pair| pair[0].stake() == pair[1].stake())
+}
+
+/// The total stake of `entries`.
+fn total_stake<ENTRY: StakeTableEntryType>(entries: &[ENTRY]) -> U256 {
+    entries
+        .iter()
+        .fold(U256::zero(), |total, entry| total + entry.stake())
 }
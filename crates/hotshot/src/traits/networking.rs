@@ -4,11 +4,84 @@
 //! trait. Currently this includes
 //! - [`MemoryNetwork`](memory_network::MemoryNetwork), an in memory testing-only implementation
 //! - [`Libp2pNetwork`](libp2p_network::Libp2pNetwork), a production-ready networking implementation built on top of libp2p-rs.
+//! - [`UnreliableNetwork`](unreliable_network::UnreliableNetwork), a wrapper injecting loss and latency into any other implementation
 
 pub mod combined_network;
 pub mod libp2p_network;
 pub mod memory_network;
 /// The Push CDN network
 pub mod push_cdn_network;
+pub mod unreliable_network;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub use hotshot_types::traits::network::{NetworkError, NetworkReliability};
+use hotshot_types::traits::{
+    metrics::{Counter, Metrics, NoMetrics},
+    network::NetworkStats,
+};
+
+/// Metrics shared by all networking implementations
+#[derive(Clone, Debug)]
+pub struct NetworkingMetricsValue {
+    /// The number of received messages dropped for being too large
+    pub num_oversized_messages: Box<dyn Counter>,
+}
+
+impl NetworkingMetricsValue {
+    /// Populate the metrics with the networking-wide ones
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        let subgroup = metrics.subgroup("networking".into());
+
+        Self {
+            num_oversized_messages: subgroup.create_counter("num_oversized_messages".into(), None),
+        }
+    }
+}
+
+impl Default for NetworkingMetricsValue {
+    /// Initialize with empty metrics
+    fn default() -> Self {
+        Self::new(&*NoMetrics::boxed())
+    }
+}
+
+/// Running counts of the messages a network has handled, which it reports in its
+/// [`NetworkStats`]. Unlike metrics, these can be read back.
+#[derive(Debug, Default)]
+pub struct MessageCounts {
+    /// Messages sent, one per recipient
+    sent: AtomicUsize,
+    /// Messages received
+    received: AtomicUsize,
+    /// Sends which failed
+    failed: AtomicUsize,
+}
+
+impl MessageCounts {
+    /// Count a message sent to `recipients` nodes
+    pub fn sent(&self, recipients: usize) {
+        self.sent.fetch_add(recipients, Ordering::Relaxed);
+    }
+
+    /// Count `messages` received messages
+    pub fn received(&self, messages: usize) {
+        self.received.fetch_add(messages, Ordering::Relaxed);
+    }
+
+    /// Count a send which failed
+    pub fn failed(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The statistics of the counted messages, with the others unknown
+    #[must_use]
+    pub fn stats(&self) -> NetworkStats {
+        NetworkStats {
+            messages_sent: Some(self.sent.load(Ordering::Relaxed)),
+            messages_received: Some(self.received.load(Ordering::Relaxed)),
+            messages_failed: Some(self.failed.load(Ordering::Relaxed)),
+            ..NetworkStats::default()
+        }
+    }
+}
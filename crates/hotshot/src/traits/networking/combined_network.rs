@@ -32,7 +32,10 @@ use hotshot_types::{
     },
     data::ViewNumber,
     traits::{
-        network::{BroadcastDelay, ConnectedNetwork, ResponseChannel},
+        network::{
+            acknowledge_on_receipt, AckedMessage, BroadcastDelay, ConnectedNetwork, NetworkChange,
+            NetworkStats, ResponseChannel, ShutdownReason,
+        },
         node_implementation::NodeType,
     },
     BoxSyncFuture,
@@ -50,6 +53,22 @@ pub fn calculate_hash_of<T: Hash>(t: &T) -> u64 {
     s.finish()
 }
 
+/// The peers reachable on either of two networks, or `None` if neither can tell. A network that
+/// can't tell doesn't hide the peers the other one reports.
+#[must_use]
+pub fn union_connected_peers<K: Ord>(
+    primary: Option<BTreeSet<K>>,
+    secondary: Option<BTreeSet<K>>,
+) -> Option<BTreeSet<K>> {
+    match (primary, secondary) {
+        (Some(mut primary), Some(secondary)) => {
+            primary.extend(secondary);
+            Some(primary)
+        }
+        (peers, None) | (None, peers) => peers,
+    }
+}
+
 /// Thread-safe ref counted lock to a map of channels to the delayed tasks
 type DelayedTasksChannelsMap = Arc<RwLock<BTreeMap<u64, (Sender<()>, InactiveReceiver<()>)>>>;
 
@@ -130,6 +149,8 @@ impl<TYPES: NodeType> CombinedNetworks<TYPES> {
         secondary_future: impl Future<Output = Result<(), NetworkError>> + Send + 'static,
         broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
+        broadcast_delay.warn_if_unscheduled("CombinedNetworks");
+
         // A local variable used to decide whether to delay this message or not
         let mut primary_failed = false;
         if self.primary_down.load(Ordering::Relaxed) {
@@ -350,6 +371,12 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         self.secondary().spawn_request_receiver_task().await
     }
 
+    async fn network_change_receiver(
+        &self,
+    ) -> Option<mpsc::Receiver<NetworkChange<TYPES::SignatureKey>>> {
+        self.secondary().network_change_receiver().await
+    }
+
     fn pause(&self) {
         self.networks.0.pause();
     }
@@ -358,6 +385,11 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         self.networks.0.resume();
     }
 
+    fn set_max_inbound_message_size(&self, max_size: usize) {
+        self.primary().set_max_inbound_message_size(max_size);
+        self.secondary().set_max_inbound_message_size(max_size);
+    }
+
     async fn wait_for_ready(&self) {
         join!(
             self.primary().wait_for_ready(),
@@ -376,6 +408,20 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         boxed_sync(closure)
     }
 
+    fn shut_down_with<'a, 'b>(&'a self, reason: ShutdownReason) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        let closure = async move {
+            join!(
+                self.primary().shut_down_with(reason.clone()),
+                self.secondary().shut_down_with(reason)
+            );
+        };
+        boxed_sync(closure)
+    }
+
     async fn broadcast_message(
         &self,
         message: Vec<u8>,
@@ -404,6 +450,45 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         .await
     }
 
+    async fn broadcast_message_timeout(
+        &self,
+        message: Vec<u8>,
+        recipients: BTreeSet<TYPES::SignatureKey>,
+        broadcast_delay: BroadcastDelay,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        let primary = self.primary().clone();
+        let secondary = self.secondary().clone();
+        let primary_message = message.clone();
+        let secondary_message = message.clone();
+        let primary_recipients = recipients.clone();
+        self.send_both_networks(
+            message,
+            async move {
+                primary
+                    .broadcast_message_timeout(
+                        primary_message,
+                        primary_recipients,
+                        BroadcastDelay::None,
+                        timeout,
+                    )
+                    .await
+            },
+            async move {
+                secondary
+                    .broadcast_message_timeout(
+                        secondary_message,
+                        recipients,
+                        BroadcastDelay::None,
+                        timeout,
+                    )
+                    .await
+            },
+            broadcast_delay,
+        )
+        .await
+    }
+
     async fn da_broadcast_message(
         &self,
         message: Vec<u8>,
@@ -432,6 +517,45 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         .await
     }
 
+    async fn da_broadcast_message_timeout(
+        &self,
+        message: Vec<u8>,
+        recipients: BTreeSet<TYPES::SignatureKey>,
+        broadcast_delay: BroadcastDelay,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        let primary = self.primary().clone();
+        let secondary = self.secondary().clone();
+        let primary_message = message.clone();
+        let secondary_message = message.clone();
+        let primary_recipients = recipients.clone();
+        self.send_both_networks(
+            message,
+            async move {
+                primary
+                    .da_broadcast_message_timeout(
+                        primary_message,
+                        primary_recipients,
+                        BroadcastDelay::None,
+                        timeout,
+                    )
+                    .await
+            },
+            async move {
+                secondary
+                    .da_broadcast_message_timeout(
+                        secondary_message,
+                        recipients,
+                        BroadcastDelay::None,
+                        timeout,
+                    )
+                    .await
+            },
+            broadcast_delay,
+        )
+        .await
+    }
+
     async fn direct_message(
         &self,
         message: Vec<u8>,
@@ -455,6 +579,32 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         .await
     }
 
+    async fn direct_message_ack(
+        &self,
+        message: Vec<u8>,
+        recipient: TYPES::SignatureKey,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        // Delivery only has to be confirmed on one network, so the secondary is only tried if the
+        // primary is down or doesn't confirm it
+        if !self.primary_down.load(Ordering::Relaxed) {
+            match self
+                .primary()
+                .direct_message_ack(message.clone(), recipient.clone(), timeout)
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    warn!("Error on primary network: {}", e);
+                    self.primary_fail_counter.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        self.secondary()
+            .direct_message_ack(message, recipient, timeout)
+            .await
+    }
+
     async fn vid_broadcast_message(
         &self,
         messages: HashMap<TYPES::SignatureKey, Vec<u8>>,
@@ -467,10 +617,14 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
     /// # Errors
     /// Does not error
     async fn recv_msgs(&self) -> Result<Vec<Vec<u8>>, NetworkError> {
+        Ok(acknowledge_on_receipt(self.recv_msgs_with_acks().await?))
+    }
+
+    async fn recv_msgs_with_acks(&self) -> Result<Vec<AckedMessage>, NetworkError> {
         // recv on both networks because nodes may be accessible only on either. discard duplicates
         // TODO: improve this algorithm: https://github.com/EspressoSystems/HotShot/issues/2089
-        let mut primary_fut = self.primary().recv_msgs().fuse();
-        let mut secondary_fut = self.secondary().recv_msgs().fuse();
+        let mut primary_fut = self.primary().recv_msgs_with_acks().fuse();
+        let mut secondary_fut = self.secondary().recv_msgs_with_acks().fuse();
 
         let msgs = select! {
             p = primary_fut => p?,
@@ -480,17 +634,20 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         let mut filtered_msgs = Vec::with_capacity(msgs.len());
 
         // For each message,
-        for msg in msgs {
+        for (msg, ack) in msgs {
             // Calculate hash of the message
             let message_hash = calculate_hash_of(&msg);
 
             // Add the hash to the cache
             if !self.message_cache.read().await.contains(&message_hash) {
                 // If the message is not in the cache, process it
-                filtered_msgs.push(msg.clone());
+                filtered_msgs.push((msg, ack));
 
                 // Add it to the cache
                 self.message_cache.write().await.put(message_hash, ());
+            } else if let Some(ack) = ack {
+                // The copy we already received is the one that gets processed
+                let _ = ack.sender.send(());
             }
         }
 
@@ -535,4 +692,16 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
     fn is_primary_down(&self) -> bool {
         self.primary_down.load(Ordering::Relaxed)
     }
+
+    async fn connected_peers(&self) -> Option<BTreeSet<TYPES::SignatureKey>> {
+        let (primary, secondary) = join!(
+            self.primary().connected_peers(),
+            self.secondary().connected_peers()
+        );
+        union_connected_peers(primary, secondary)
+    }
+
+    fn stats(&self) -> NetworkStats {
+        self.primary().stats().combine(self.secondary().stats())
+    }
 }
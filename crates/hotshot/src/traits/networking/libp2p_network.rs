@@ -4,12 +4,12 @@
 #[cfg(feature = "hotshot-testing")]
 use std::str::FromStr;
 use std::{
-    collections::{BTreeSet, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     fmt::Debug,
     net::SocketAddr,
     num::NonZeroUsize,
     sync::{
-        atomic::{AtomicBool, AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
     time::Duration,
@@ -17,7 +17,7 @@ use std::{
 
 use anyhow::anyhow;
 use async_compatibility_layer::{
-    art::{async_sleep, async_spawn},
+    art::{async_sleep, async_spawn, async_timeout},
     channel::{self, bounded, unbounded, UnboundedReceiver, UnboundedSendError, UnboundedSender},
 };
 use async_lock::{Mutex, RwLock};
@@ -35,13 +35,13 @@ use hotshot_types::traits::network::{
 };
 use hotshot_types::{
     boxed_sync,
-    constants::LOOK_AHEAD,
+    constants::{DEFAULT_MAX_INBOUND_MESSAGE_SIZE, LOOK_AHEAD},
     data::ViewNumber,
-    message::{DataMessage::DataResponse, Message, MessageKind},
+    message::{DataMessage, Message, MessageKind},
     traits::{
         election::Membership,
         metrics::{Counter, Gauge, Metrics, NoMetrics},
-        network::{self, ConnectedNetwork, NetworkError, ResponseMessage},
+        network::{self, ConnectedNetwork, NetworkChange, NetworkError, NetworkStats},
         node_implementation::{ConsensusTime, NodeType},
         signature_key::SignatureKey,
     },
@@ -65,6 +65,7 @@ use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
 use serde::Serialize;
 use tracing::{debug, error, info, instrument, trace, warn};
 
+use super::MessageCounts;
 use crate::BroadcastDelay;
 
 /// Libp2p-specific metrics
@@ -74,6 +75,8 @@ pub struct Libp2pMetricsValue {
     pub num_connected_peers: Box<dyn Gauge>,
     /// The number of failed messages
     pub num_failed_messages: Box<dyn Counter>,
+    /// The number of received messages dropped for being too large
+    pub num_oversized_messages: Box<dyn Counter>,
 }
 
 impl Libp2pMetricsValue {
@@ -86,6 +89,7 @@ impl Libp2pMetricsValue {
         Self {
             num_connected_peers: subgroup.create_gauge("num_connected_peers".into(), None),
             num_failed_messages: subgroup.create_counter("num_failed_messages".into(), None),
+            num_oversized_messages: subgroup.create_counter("num_oversized_messages".into(), None),
         }
     }
 }
@@ -128,6 +132,9 @@ impl<K: SignatureKey + 'static> Debug for Libp2pNetwork<K> {
 /// Locked Option of a receiver for moving the value out of the option
 type TakeReceiver = Mutex<Option<Receiver<(Vec<u8>, ResponseChannel<Response>)>>>;
 
+/// Type alias for a shared map from the peer ids we've looked up to their public keys
+type PeerKeys<K> = Arc<RwLock<HashMap<PeerId, K>>>;
+
 /// Type alias for a shared collection of peerid, multiaddrs
 pub type PeerInfoVec = Arc<RwLock<Vec<(PeerId, Multiaddr)>>>;
 
@@ -144,6 +151,13 @@ struct Libp2pNetworkInner<K: SignatureKey + 'static> {
     /// Lock should only be used once to take the channel and move it into the request
     /// handler task
     requests_rx: TakeReceiver,
+    /// The public keys of the peers we've looked up, so connectivity changes can be reported by
+    /// key. Changes for peers we've never looked up aren't reported.
+    peer_keys: PeerKeys<K>,
+    /// Sender for connectivity changes
+    network_changes_tx: Sender<NetworkChange<K>>,
+    /// Receiver for connectivity changes, taken once by `network_change_receiver`
+    network_changes_rx: Mutex<Option<Receiver<NetworkChange<K>>>>,
     /// Sender for broadcast messages
     sender: UnboundedSender<Vec<u8>>,
     /// Sender for node lookup (relevant view number, key of node) (None for shutdown)
@@ -159,6 +173,12 @@ struct Libp2pNetworkInner<K: SignatureKey + 'static> {
     is_bootstrapped: Arc<AtomicBool>,
     /// The Libp2p metrics we're managing
     metrics: Libp2pMetricsValue,
+    /// The messages we've sent and received, for our statistics
+    message_counts: Arc<MessageCounts>,
+    /// The number of peers we're connected to, as last reported by the network node
+    num_connected_peers: AtomicUsize,
+    /// maximum size of a message we accept
+    max_inbound_message_size: AtomicUsize,
     /// topic map
     /// hash(hashset) -> topic
     /// btreemap ordered so is hashable
@@ -518,6 +538,7 @@ impl<K: SignatureKey + 'static> Libp2pNetwork<K> {
         // if bounded figure out a way to log dropped msgs
         let (sender, receiver) = unbounded();
         let (requests_tx, requests_rx) = channel(100);
+        let (network_changes_tx, network_changes_rx) = channel(100);
         let (node_lookup_send, node_lookup_recv) = unbounded();
         let (kill_tx, kill_rx) = bounded(1);
         rx.set_kill_switch(kill_rx);
@@ -527,6 +548,9 @@ impl<K: SignatureKey + 'static> Libp2pNetwork<K> {
                 handle: Arc::new(network_handle),
                 receiver,
                 requests_rx: Mutex::new(Some(requests_rx)),
+                peer_keys: Arc::default(),
+                network_changes_tx,
+                network_changes_rx: Mutex::new(Some(network_changes_rx)),
                 sender: sender.clone(),
                 pk,
                 bootstrap_addrs,
@@ -536,6 +560,9 @@ impl<K: SignatureKey + 'static> Libp2pNetwork<K> {
                 dht_timeout: Duration::from_secs(120),
                 is_bootstrapped: Arc::new(AtomicBool::new(false)),
                 metrics,
+                message_counts: Arc::default(),
+                num_connected_peers: AtomicUsize::new(0),
+                max_inbound_message_size: AtomicUsize::new(DEFAULT_MAX_INBOUND_MESSAGE_SIZE),
                 topic_map,
                 node_lookup_send,
                 // Start the latest view from 0. "Latest" refers to "most recent view we are polling for
@@ -562,6 +589,7 @@ impl<K: SignatureKey + 'static> Libp2pNetwork<K> {
         let handle = Arc::clone(&self.inner.handle);
         let dht_timeout = self.inner.dht_timeout;
         let latest_seen_view = Arc::clone(&self.inner.latest_seen_view);
+        let peer_keys = Arc::clone(&self.inner.peer_keys);
 
         // deals with handling lookup queue. should be infallible
         async_spawn(async move {
@@ -583,8 +611,13 @@ impl<K: SignatureKey + 'static> Libp2pNetwork<K> {
                         }
                     };
                     // look up
-                    if let Err(err) = handle.lookup_node(&pk_bytes, dht_timeout).await {
-                        error!("Failed to perform lookup for key {:?}: {}", pk, err);
+                    match handle.lookup_node(&pk_bytes, dht_timeout).await {
+                        Ok(pid) => {
+                            peer_keys.write().await.insert(pid, pk);
+                        }
+                        Err(err) => {
+                            error!("Failed to perform lookup for key {:?}: {}", pk, err);
+                        }
                     };
                 }
             }
@@ -718,11 +751,29 @@ impl<K: SignatureKey + 'static> Libp2pNetwork<K> {
                 let res = request_tx.try_send((msg, chan));
                 res.map_err(|_| NetworkError::ChannelSend)?;
             }
-            NetworkEvent::ConnectedPeersUpdate(_) => {}
+            NetworkEvent::ConnectedPeersUpdate(_)
+            | NetworkEvent::PeerConnected(_)
+            | NetworkEvent::PeerDisconnected(_) => {}
         }
         Ok::<(), NetworkError>(())
     }
 
+    /// Report a change in our connectivity to `peer_id`, if we know its public key
+    async fn report_network_change(&self, peer_id: &PeerId, change: fn(K) -> NetworkChange<K>) {
+        let Some(key) = self.inner.peer_keys.read().await.get(peer_id).cloned() else {
+            return;
+        };
+        if self
+            .inner
+            .network_changes_tx
+            .clone()
+            .try_send(change(key))
+            .is_err()
+        {
+            trace!("Dropped a connectivity change for {:?}", peer_id);
+        }
+    }
+
     /// task to propagate messages to handlers
     /// terminates on shut down of network
     fn handle_event_generator(
@@ -761,6 +812,20 @@ impl<K: SignatureKey + 'static> Libp2pNetwork<K> {
                             }
                             NetworkEvent::ConnectedPeersUpdate(num_peers) => {
                                 handle.inner.metrics.num_connected_peers.set(*num_peers);
+                                handle
+                                    .inner
+                                    .num_connected_peers
+                                    .store(*num_peers, Ordering::Relaxed);
+                            }
+                            NetworkEvent::PeerConnected(peer_id) => {
+                                handle
+                                    .report_network_change(peer_id, NetworkChange::NodeConnected)
+                                    .await;
+                            }
+                            NetworkEvent::PeerDisconnected(peer_id) => {
+                                handle
+                                    .report_network_change(peer_id, NetworkChange::NodeDisconnected)
+                                    .await;
                             }
                         }
                         // re-set the `kill_switch` for the next loop
@@ -801,7 +866,14 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
             )
             .await
         {
-            Ok(pid) => pid,
+            Ok(pid) => {
+                self.inner
+                    .peer_keys
+                    .write()
+                    .await
+                    .insert(pid, recipient.clone());
+                pid
+            }
             Err(err) => {
                 self.inner.metrics.num_failed_messages.add(1);
                 error!(
@@ -824,12 +896,17 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
                     let res: Message<TYPES> = bincode::deserialize(&msg.0)
                         .map_err(|e| NetworkError::FailedToDeserialize { source: e.into() })?;
 
+                    // The requester decides whether the response needs the responder's
+                    // signature, so pass on either kind of response as is.
                     match res.kind {
-                        MessageKind::Data(DataResponse(data)) => data,
-                        _ => ResponseMessage::NotFound,
+                        MessageKind::Data(
+                            data @ (DataMessage::DataResponse(_)
+                            | DataMessage::SignedDataResponse(_)),
+                        ) => data,
+                        _ => return Err(NetworkError::NotFound),
                     }
                 }
-                None => ResponseMessage::NotFound,
+                None => return Err(NetworkError::NotFound),
             },
             Err(e) => {
                 self.inner.metrics.num_failed_messages.add(1);
@@ -840,6 +917,34 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
         Ok(bincode::serialize(&result).map_err(|e| NetworkError::Libp2p { source: e.into() })?)
     }
 
+    async fn network_change_receiver(&self) -> Option<Receiver<NetworkChange<K>>> {
+        self.inner.network_changes_rx.lock().await.take()
+    }
+
+    fn stats(&self) -> NetworkStats {
+        NetworkStats {
+            connected_peers: Some(self.inner.num_connected_peers.load(Ordering::Relaxed)),
+            ..self.inner.message_counts.stats()
+        }
+    }
+
+    /// The connected peers whose public keys we know, which are those we've looked up
+    async fn connected_peers(&self) -> Option<BTreeSet<K>> {
+        let pids = self
+            .inner
+            .handle
+            .connected_pids()
+            .await
+            .inspect_err(|e| warn!("Failed to get the connected peers: {e:?}"))
+            .ok()?;
+        let peer_keys = self.inner.peer_keys.read().await;
+        Some(
+            pids.iter()
+                .filter_map(|pid| peer_keys.get(pid).cloned())
+                .collect(),
+        )
+    }
+
     async fn spawn_request_receiver_task(
         &self,
     ) -> Option<mpsc::Receiver<(Vec<u8>, network::ResponseChannel<Vec<u8>>)>> {
@@ -883,6 +988,12 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
         unimplemented!("Resuming not implemented for the Libp2p network");
     }
 
+    fn set_max_inbound_message_size(&self, max_size: usize) {
+        self.inner
+            .max_inbound_message_size
+            .store(max_size, Ordering::Relaxed);
+    }
+
     #[instrument(name = "Libp2pNetwork::shut_down", skip_all)]
     fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
     where
@@ -902,8 +1013,9 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
         &self,
         message: Vec<u8>,
         recipients: BTreeSet<K>,
-        _broadcast_delay: BroadcastDelay,
+        broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
+        broadcast_delay.warn_if_unscheduled("Libp2pNetwork");
         self.wait_for_ready().await;
         trace!(
             "broadcasting msg: {:?} with nodes: {:?} connected",
@@ -920,6 +1032,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
             .clone();
         trace!("broadcasting to topic: {}", topic);
 
+        self.inner.message_counts.sent(recipients.len());
         // gossip doesn't broadcast from itself, so special case
         if recipients.contains(&self.inner.pk) {
             // send to self
@@ -934,6 +1047,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
         #[cfg(feature = "hotshot-testing")]
         {
             let metrics = self.inner.metrics.clone();
+            let message_counts = Arc::clone(&self.inner.message_counts);
             if let Some(ref config) = &self.inner.reliability_config {
                 let handle = Arc::clone(&self.inner.handle);
 
@@ -943,9 +1057,11 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
                         let topic_2 = topic.clone();
                         let handle_2 = Arc::clone(&handle);
                         let metrics_2 = metrics.clone();
+                        let message_counts_2 = Arc::clone(&message_counts);
                         boxed_sync(async move {
                             if let Err(e) = handle_2.gossip_no_serialize(topic_2, msg).await {
                                 metrics_2.num_failed_messages.add(1);
+                                message_counts_2.failed();
                                 warn!("Failed to broadcast to libp2p: {:?}", e);
                             }
                         })
@@ -958,6 +1074,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
 
         if let Err(e) = self.inner.handle.gossip(topic, &message).await {
             self.inner.metrics.num_failed_messages.add(1);
+            self.inner.message_counts.failed();
             return Err(e.into());
         }
 
@@ -969,8 +1086,9 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
         &self,
         message: Vec<u8>,
         recipients: BTreeSet<K>,
-        _broadcast_delay: BroadcastDelay,
+        broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
+        broadcast_delay.warn_if_unscheduled("Libp2pNetwork");
         let future_results = recipients
             .into_iter()
             .map(|r| self.direct_message(message.clone(), r));
@@ -991,8 +1109,59 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
         }
     }
 
+    /// Sends the message to each DA committee member directly, giving each send `timeout` of its
+    /// own, so that a member that is slow to look up doesn't cost the others the message.
+    ///
+    /// Gossip broadcasts only queue the message with the swarm, so they keep the default
+    /// [`ConnectedNetwork::broadcast_message_timeout`].
+    #[instrument(name = "Libp2pNetwork::da_broadcast_message_timeout", skip_all)]
+    async fn da_broadcast_message_timeout(
+        &self,
+        message: Vec<u8>,
+        recipients: BTreeSet<K>,
+        broadcast_delay: BroadcastDelay,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        broadcast_delay.warn_if_unscheduled("Libp2pNetwork");
+        let future_results = recipients
+            .into_iter()
+            .map(|r| async_timeout(timeout, self.direct_message(message.clone(), r)));
+
+        // Report the sends that timed out as a single error counting them, alongside any others.
+        let mut timeout_source = None;
+        let mut num_timed_out = 0;
+        let mut errors = Vec::new();
+        for result in join_all(future_results).await {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => errors.push(Box::new(e)),
+                Err(source) => {
+                    timeout_source = Some(source);
+                    num_timed_out += 1;
+                }
+            }
+        }
+        if let Some(source) = timeout_source {
+            let timeout_error = NetworkError::Timeout {
+                source,
+                num_recipients: Some(num_timed_out),
+            };
+            if errors.is_empty() {
+                return Err(timeout_error);
+            }
+            errors.push(Box::new(timeout_error));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(NetworkError::MultipleErrors { errors })
+        }
+    }
+
     #[instrument(name = "Libp2pNetwork::direct_message", skip_all)]
     async fn direct_message(&self, message: Vec<u8>, recipient: K) -> Result<(), NetworkError> {
+        self.inner.message_counts.sent(1);
         // short circuit if we're dming ourselves
         if recipient == self.inner.pk {
             // panic if we already shut down?
@@ -1016,9 +1185,17 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
             )
             .await
         {
-            Ok(pid) => pid,
+            Ok(pid) => {
+                self.inner
+                    .peer_keys
+                    .write()
+                    .await
+                    .insert(pid, recipient.clone());
+                pid
+            }
             Err(err) => {
                 self.inner.metrics.num_failed_messages.add(1);
+                self.inner.message_counts.failed();
                 error!(
                     "Failed to message {:?} because could not find recipient peer id for pk {:?}",
                     message, recipient
@@ -1032,6 +1209,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
         #[cfg(feature = "hotshot-testing")]
         {
             let metrics = self.inner.metrics.clone();
+            let message_counts = Arc::clone(&self.inner.message_counts);
             if let Some(ref config) = &self.inner.reliability_config {
                 let handle = Arc::clone(&self.inner.handle);
 
@@ -1040,9 +1218,11 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
                     Arc::new(move |msg: Vec<u8>| {
                         let handle_2 = Arc::clone(&handle);
                         let metrics_2 = metrics.clone();
+                        let message_counts_2 = Arc::clone(&message_counts);
                         boxed_sync(async move {
                             if let Err(e) = handle_2.direct_request_no_serialize(pid, msg).await {
                                 metrics_2.num_failed_messages.add(1);
+                                message_counts_2.failed();
                                 warn!("Failed to broadcast to libp2p: {:?}", e);
                             }
                         })
@@ -1055,7 +1235,10 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
 
         match self.inner.handle.direct_request(pid, &message).await {
             Ok(()) => Ok(()),
-            Err(e) => Err(e.into()),
+            Err(e) => {
+                self.inner.message_counts.failed();
+                Err(e.into())
+            }
         }
     }
 
@@ -1065,13 +1248,24 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for Libp2pNetwork<K> {
     /// If there is a network-related failure.
     #[instrument(name = "Libp2pNetwork::recv_msgs", skip_all)]
     async fn recv_msgs(&self) -> Result<Vec<Vec<u8>>, NetworkError> {
-        let result = self
+        let mut result = self
             .inner
             .receiver
             .drain_at_least_one()
             .await
             .map_err(|_x| NetworkError::ShutDown)?;
 
+        let max_size = self.inner.max_inbound_message_size.load(Ordering::Relaxed);
+        result.retain(|message| {
+            if message.len() > max_size {
+                warn!(size = message.len(), "Dropping oversized message");
+                self.inner.metrics.num_oversized_messages.add(1);
+                return false;
+            }
+            true
+        });
+        self.inner.message_counts.received(result.len());
+
         Ok(result)
     }
 
@@ -11,21 +11,28 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
+    time::SystemTime,
 };
 
 use async_compatibility_layer::{
-    art::async_spawn,
+    art::{async_sleep, async_spawn, async_timeout},
     channel::{bounded, BoundedStream, Receiver, SendError, Sender},
 };
 use async_lock::{Mutex, RwLock};
 use async_trait::async_trait;
 use dashmap::DashMap;
-use futures::StreamExt;
+use futures::{
+    channel::{mpsc, oneshot},
+    StreamExt,
+};
 use hotshot_types::{
     boxed_sync,
+    constants::DEFAULT_MAX_INBOUND_MESSAGE_SIZE,
+    message::{DataMessage, Message, MessageKind},
     traits::{
         network::{
-            AsyncGenerator, BroadcastDelay, ConnectedNetwork, TestableNetworkingImplementation,
+            acknowledge_on_receipt, AckedMessage, AsyncGenerator, BroadcastDelay, ConnectedNetwork,
+            NetworkStats, ResponseChannel, TestableNetworkingImplementation,
         },
         node_implementation::NodeType,
         signature_key::SignatureKey,
@@ -35,7 +42,7 @@ use hotshot_types::{
 use rand::Rng;
 use tracing::{debug, error, info, info_span, instrument, trace, warn, Instrument};
 
-use super::{NetworkError, NetworkReliability};
+use super::{NetworkError, NetworkReliability, NetworkingMetricsValue};
 
 /// Shared state for in-memory mock networking.
 ///
@@ -46,6 +53,13 @@ pub struct MasterMap<K: SignatureKey> {
     /// The list of `MemoryNetwork`s
     #[debug(skip)]
     map: DashMap<K, MemoryNetwork<K>>,
+    /// The index of each key in the group
+    indices: DashMap<K, usize>,
+    /// The index the next key joining without one is given
+    next_index: AtomicUsize,
+    /// The channels over which each node that handles requests receives them
+    #[debug(skip)]
+    request_senders: DashMap<K, mpsc::Sender<(Vec<u8>, ResponseChannel<Vec<u8>>)>>,
     /// The id of this `MemoryNetwork` cluster
     id: u64,
 }
@@ -56,26 +70,62 @@ impl<K: SignatureKey> MasterMap<K> {
     pub fn new() -> Arc<MasterMap<K>> {
         Arc::new(MasterMap {
             map: DashMap::new(),
+            indices: DashMap::new(),
+            next_index: AtomicUsize::new(0),
+            request_senders: DashMap::new(),
             id: rand::thread_rng().gen(),
         })
     }
+
+    /// The index of `key` in the group: the one it had before if it's rejoining, or else the next
+    /// one in the order of joining.
+    fn index_of(&self, key: &K) -> usize {
+        *self
+            .indices
+            .entry(key.clone())
+            .or_insert_with(|| self.next_index.fetch_add(1, Ordering::Relaxed))
+    }
 }
 
+/// A message in flight, along with an optional channel used to acknowledge it
+type Delivery = AckedMessage;
+
 /// Internal state for a `MemoryNetwork` instance
 #[derive(Debug)]
 struct MemoryNetworkInner<K: SignatureKey> {
     /// Input for messages
-    input: RwLock<Option<Sender<Vec<u8>>>>,
+    input: RwLock<Option<Sender<Delivery>>>,
     /// Output for messages
-    output: Mutex<Receiver<Vec<u8>>>,
+    output: Mutex<Receiver<Delivery>>,
     /// The master map
     master_map: Arc<MasterMap<K>>,
 
+    /// Our public key, under which we receive requests
+    pub_key: K,
+
+    /// Our index in the group, which the reliability config uses to tell nodes apart
+    index: usize,
+
     /// Count of messages that are in-flight (send but not processed yet)
     in_flight_message_count: AtomicUsize,
 
     /// config to introduce unreliability to the network
     reliability_config: Option<Box<dyn NetworkReliability>>,
+
+    /// Maximum size of a message we accept
+    max_inbound_message_size: AtomicUsize,
+
+    /// The networking metrics we're managing
+    metrics: NetworkingMetricsValue,
+
+    /// Count of messages we sent, one per recipient
+    num_sent_messages: AtomicUsize,
+
+    /// Count of messages we received
+    num_received_messages: AtomicUsize,
+
+    /// Count of messages we failed to send
+    num_failed_messages: AtomicUsize,
 }
 
 /// In memory only network simulator.
@@ -105,6 +155,36 @@ impl<K: SignatureKey> MemoryNetwork<K> {
         pub_key: K,
         master_map: &Arc<MasterMap<K>>,
         reliability_config: Option<Box<dyn NetworkReliability>>,
+    ) -> MemoryNetwork<K> {
+        Self::new_with_metrics(
+            pub_key,
+            master_map,
+            reliability_config,
+            NetworkingMetricsValue::default(),
+        )
+    }
+
+    /// Creates a new `MemoryNetwork` reporting to the given metrics, and hooks it up to the group
+    /// through the provided `MasterMap`
+    pub fn new_with_metrics(
+        pub_key: K,
+        master_map: &Arc<MasterMap<K>>,
+        reliability_config: Option<Box<dyn NetworkReliability>>,
+        metrics: NetworkingMetricsValue,
+    ) -> MemoryNetwork<K> {
+        let index = master_map.index_of(&pub_key);
+        Self::new_with_index(pub_key, index, master_map, reliability_config, metrics)
+    }
+
+    /// Creates a new `MemoryNetwork` as node `index` of the group, which the reliability config
+    /// tells nodes apart by, and hooks it up to the group through the provided `MasterMap`. The
+    /// index should be the node's id, and not be mixed with indices given in the order of joining.
+    pub fn new_with_index(
+        pub_key: K,
+        index: usize,
+        master_map: &Arc<MasterMap<K>>,
+        reliability_config: Option<Box<dyn NetworkReliability>>,
+        metrics: NetworkingMetricsValue,
     ) -> MemoryNetwork<K> {
         info!("Attaching new MemoryNetwork");
         let (input, task_recv) = bounded(128);
@@ -115,13 +195,13 @@ impl<K: SignatureKey> MemoryNetwork<K> {
         async_spawn(
             async move {
                 debug!("Starting background task");
-                let mut task_stream: BoundedStream<Vec<u8>> = task_recv.into_stream();
+                let mut task_stream: BoundedStream<Delivery> = task_recv.into_stream();
                 trace!("Entering processing loop");
-                while let Some(vec) = task_stream.next().await {
-                    trace!(?vec, "Incoming message");
+                while let Some(delivery) = task_stream.next().await {
+                    trace!(message = ?delivery.0, "Incoming message");
                     // Attempt to decode message
                     let ts = task_send.clone();
-                    let res = ts.send(vec).await;
+                    let res = ts.send(delivery).await;
                     if res.is_ok() {
                         trace!("Passed message to output queue");
                     } else {
@@ -132,6 +212,7 @@ impl<K: SignatureKey> MemoryNetwork<K> {
             }
             .instrument(info_span!("MemoryNetwork Background task", map = ?master_map)),
         );
+        master_map.indices.insert(pub_key.clone(), index);
         trace!("Notifying other networks of the new connected peer");
         trace!("Task spawned, creating MemoryNetwork");
         let mn = MemoryNetwork {
@@ -139,8 +220,15 @@ impl<K: SignatureKey> MemoryNetwork<K> {
                 input: RwLock::new(Some(input)),
                 output: Mutex::new(output),
                 master_map: Arc::clone(master_map),
+                pub_key: pub_key.clone(),
+                index,
                 in_flight_message_count,
                 reliability_config,
+                max_inbound_message_size: AtomicUsize::new(DEFAULT_MAX_INBOUND_MESSAGE_SIZE),
+                metrics,
+                num_sent_messages: AtomicUsize::new(0),
+                num_received_messages: AtomicUsize::new(0),
+                num_failed_messages: AtomicUsize::new(0),
             }),
         };
         master_map.map.insert(pub_key, mn.clone());
@@ -149,16 +237,41 @@ impl<K: SignatureKey> MemoryNetwork<K> {
         mn
     }
 
+    /// Count a message sent to another node, and whether it failed
+    fn count_sent_message(&self, failed: bool) {
+        self.inner.num_sent_messages.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.inner
+                .num_failed_messages
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether this network was shut down, after which it neither sends nor receives
+    async fn is_shut_down(&self) -> bool {
+        self.inner.input.read().await.is_none()
+    }
+
     /// Send a [`Vec<u8>`] message to the inner `input`
-    async fn input(&self, message: Vec<u8>) -> Result<(), SendError<Vec<u8>>> {
+    async fn input(&self, message: Vec<u8>) -> Result<(), SendError<Delivery>> {
+        self.input_with_ack(message, None).await
+    }
+
+    /// Send a [`Vec<u8>`] message to the inner `input`, with an optional channel that is notified
+    /// once the message has been received
+    async fn input_with_ack(
+        &self,
+        message: Vec<u8>,
+        ack: Option<ResponseChannel<()>>,
+    ) -> Result<(), SendError<Delivery>> {
         self.inner
             .in_flight_message_count
             .fetch_add(1, Ordering::Relaxed);
         let input = self.inner.input.read().await;
         if let Some(input) = &*input {
-            input.send(message).await
+            input.send((message, ack)).await
         } else {
-            Err(SendError(message))
+            Err(SendError((message, ack)))
         }
     }
 }
@@ -180,7 +293,13 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
         Box::pin(move |node_id| {
             let privkey = TYPES::SignatureKey::generated_from_seed_indexed([0u8; 32], node_id).1;
             let pubkey = TYPES::SignatureKey::from_private(&privkey);
-            let net = MemoryNetwork::new(pubkey, &master, reliability_config.clone());
+            let net = MemoryNetwork::new_with_index(
+                pubkey,
+                usize::try_from(node_id).unwrap(),
+                &master,
+                reliability_config.clone(),
+                NetworkingMetricsValue::default(),
+            );
             Box::pin(async move { (net.clone().into(), net.into()) })
         })
     }
@@ -204,6 +323,12 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         unimplemented!("Resuming not implemented for the Memory network");
     }
 
+    fn set_max_inbound_message_size(&self, max_size: usize) {
+        self.inner
+            .max_inbound_message_size
+            .store(max_size, Ordering::Relaxed);
+    }
+
     #[instrument(name = "MemoryNetwork::shut_down")]
     fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
     where
@@ -221,8 +346,28 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         &self,
         message: Vec<u8>,
         recipients: BTreeSet<K>,
-        _broadcast_delay: BroadcastDelay,
+        broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
+        if self.is_shut_down().await {
+            return Err(NetworkError::ShutDown);
+        }
+        if let BroadcastDelay::Until(time) = broadcast_delay {
+            // A time that has already passed is sent straight away
+            if let Ok(delay) = time.duration_since(SystemTime::now()) {
+                trace!(?delay, "Scheduling broadcast");
+                let network = self.clone();
+                async_spawn(async move {
+                    async_sleep(delay).await;
+                    if let Err(e) = network
+                        .broadcast_message(message, recipients, BroadcastDelay::None)
+                        .await
+                    {
+                        warn!(?e, "Failed to send scheduled broadcast");
+                    }
+                });
+                return Ok(());
+            }
+        }
         trace!(?message, "Broadcasting message");
         for node in &self.inner.master_map.map {
             // TODO delay/drop etc here
@@ -232,9 +377,12 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
             }
             trace!(?key, "Sending message to node");
             if let Some(ref config) = &self.inner.reliability_config {
+                self.count_sent_message(false);
                 {
                     let node2 = node.clone();
-                    let fut = config.chaos_send_msg(
+                    let fut = config.chaos_send_msg_between(
+                        self.inner.index,
+                        node.inner.index,
                         message.clone(),
                         Arc::new(move |msg: Vec<u8>| {
                             let node3 = (node2).clone();
@@ -249,6 +397,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
                 }
             } else {
                 let res = node.input(message.clone()).await;
+                self.count_sent_message(res.is_err());
                 match res {
                     Ok(()) => {
                         trace!(?key, "Delivered message to remote");
@@ -275,14 +424,20 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
 
     #[instrument(name = "MemoryNetwork::direct_message")]
     async fn direct_message(&self, message: Vec<u8>, recipient: K) -> Result<(), NetworkError> {
+        if self.is_shut_down().await {
+            return Err(NetworkError::ShutDown);
+        }
         // debug!(?message, ?recipient, "Sending direct message");
         // Bincode the message
         trace!("Message bincoded, finding recipient");
         if let Some(node) = self.inner.master_map.map.get(&recipient) {
             let node = node.value().clone();
             if let Some(ref config) = &self.inner.reliability_config {
+                self.count_sent_message(false);
                 {
-                    let fut = config.chaos_send_msg(
+                    let fut = config.chaos_send_msg_between(
+                        self.inner.index,
+                        node.inner.index,
                         message.clone(),
                         Arc::new(move |msg: Vec<u8>| {
                             let node2 = node.clone();
@@ -298,6 +453,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
                 Ok(())
             } else {
                 let res = node.input(message).await;
+                self.count_sent_message(res.is_err());
                 match res {
                     Ok(()) => {
                         trace!(?recipient, "Delivered message to remote");
@@ -314,17 +470,72 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
                 "{:#?} {:#?} {:#?}",
                 recipient, self.inner.master_map.map, "Node does not exist in map"
             );
+            self.count_sent_message(true);
             Err(NetworkError::NoSuchNode)
         }
     }
 
-    /// Receive one or many messages from the underlying network.
+    #[instrument(name = "MemoryNetwork::direct_message_ack", skip(message))]
+    async fn direct_message_ack(
+        &self,
+        message: Vec<u8>,
+        recipient: K,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        if self.is_shut_down().await {
+            return Err(NetworkError::ShutDown);
+        }
+        let Some(node) = self
+            .inner
+            .master_map
+            .map
+            .get(&recipient)
+            .map(|node| node.value().clone())
+        else {
+            warn!(?recipient, "Node does not exist in map");
+            self.count_sent_message(true);
+            return Err(NetworkError::NoSuchNode);
+        };
+
+        // Acknowledged messages bypass the reliability config, since the sender waits on them.
+        let (ack_sender, ack_receiver) = oneshot::channel();
+        let ack = ResponseChannel { sender: ack_sender };
+        if let Err(e) = node.input_with_ack(message, Some(ack)).await {
+            warn!(?e, ?recipient, "Error delivering direct message");
+            self.count_sent_message(true);
+            return Err(NetworkError::CouldNotDeliver);
+        }
+
+        let res = match async_timeout(timeout, ack_receiver).await {
+            Ok(Ok(())) => Ok(()),
+            // The recipient dropped the message without acknowledging it
+            Ok(Err(_)) => Err(NetworkError::CouldNotDeliver),
+            Err(source) => Err(NetworkError::Timeout {
+                source,
+                num_recipients: None,
+            }),
+        };
+        self.count_sent_message(res.is_err());
+        res
+    }
+
+    /// Receive one or many messages from the underlying network, acknowledging them on receipt.
     ///
     /// # Errors
     /// If the other side of the channel is closed
     #[instrument(name = "MemoryNetwork::recv_msgs", skip_all)]
     async fn recv_msgs(&self) -> Result<Vec<Vec<u8>>, NetworkError> {
-        let ret = self
+        Ok(acknowledge_on_receipt(self.recv_msgs_with_acks().await?))
+    }
+
+    /// Receive one or many messages from the underlying network, leaving them for the caller to
+    /// acknowledge.
+    ///
+    /// # Errors
+    /// If the other side of the channel is closed
+    #[instrument(name = "MemoryNetwork::recv_msgs_with_acks", skip_all)]
+    async fn recv_msgs_with_acks(&self) -> Result<Vec<AckedMessage>, NetworkError> {
+        let deliveries = self
             .inner
             .output
             .lock()
@@ -334,7 +545,93 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
             .map_err(|_x| NetworkError::ShutDown)?;
         self.inner
             .in_flight_message_count
-            .fetch_sub(ret.len(), Ordering::Relaxed);
+            .fetch_sub(deliveries.len(), Ordering::Relaxed);
+        let max_size = self.inner.max_inbound_message_size.load(Ordering::Relaxed);
+        let ret = deliveries
+            .into_iter()
+            .filter_map(|(message, ack)| {
+                if message.len() > max_size {
+                    warn!(size = message.len(), "Dropping oversized message");
+                    self.inner.metrics.num_oversized_messages.add(1);
+                    return None;
+                }
+                Some((message, ack))
+            })
+            .collect::<Vec<_>>();
+        self.inner
+            .num_received_messages
+            .fetch_add(ret.len(), Ordering::Relaxed);
         Ok(ret)
     }
+
+    #[instrument(name = "MemoryNetwork::request_data", skip(request))]
+    async fn request_data<TYPES: NodeType>(
+        &self,
+        request: Vec<u8>,
+        recipient: &K,
+    ) -> Result<Vec<u8>, NetworkError> {
+        let Some(mut sender) = self
+            .inner
+            .master_map
+            .request_senders
+            .get(recipient)
+            .map(|sender| sender.value().clone())
+        else {
+            warn!(?recipient, "Node does not handle requests");
+            self.count_sent_message(true);
+            return Err(NetworkError::NoSuchNode);
+        };
+
+        let (response_sender, response_receiver) = oneshot::channel();
+        let sent = sender.try_send((
+            request,
+            ResponseChannel {
+                sender: response_sender,
+            },
+        ));
+        self.count_sent_message(sent.is_err());
+        if sent.is_err() {
+            return Err(NetworkError::CouldNotDeliver);
+        }
+        // The responder dropping the channel means it had nothing to send back
+        let response = response_receiver
+            .await
+            .map_err(|_| NetworkError::NotFound)?;
+        self.inner
+            .num_received_messages
+            .fetch_add(1, Ordering::Relaxed);
+
+        // Responses are wrapped just like they are on the wire, so unwrap them the same way
+        let message: Message<TYPES> = bincode::deserialize(&response)
+            .map_err(|e| NetworkError::FailedToDeserialize { source: e.into() })?;
+        match message.kind {
+            MessageKind::Data(
+                data @ (DataMessage::DataResponse(_) | DataMessage::SignedDataResponse(_)),
+            ) => bincode::serialize(&data)
+                .map_err(|e| NetworkError::FailedToSerialize { source: e.into() }),
+            _ => Err(NetworkError::NotFound),
+        }
+    }
+
+    async fn spawn_request_receiver_task(
+        &self,
+    ) -> Option<mpsc::Receiver<(Vec<u8>, ResponseChannel<Vec<u8>>)>> {
+        let (sender, receiver) = mpsc::channel(100);
+        self.inner
+            .master_map
+            .request_senders
+            .insert(self.inner.pub_key.clone(), sender);
+        Some(receiver)
+    }
+
+    fn stats(&self) -> NetworkStats {
+        NetworkStats {
+            messages_sent: Some(self.inner.num_sent_messages.load(Ordering::Relaxed)),
+            messages_received: Some(self.inner.num_received_messages.load(Ordering::Relaxed)),
+            messages_failed: Some(self.inner.num_failed_messages.load(Ordering::Relaxed)),
+            in_flight_messages: Some(self.inner.in_flight_message_count.load(Ordering::Relaxed)),
+            // Every other member of the group is reachable
+            connected_peers: Some(self.inner.master_map.map.len().saturating_sub(1)),
+        }
+    }
 }
@@ -1,6 +1,13 @@
 #[cfg(feature = "hotshot-testing")]
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::{collections::BTreeSet, marker::PhantomData, sync::Arc};
+use std::sync::atomic::AtomicBool;
+use std::{
+    collections::BTreeSet,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 #[cfg(feature = "hotshot-testing")]
 use std::{path::Path, time::Duration};
 
@@ -33,10 +40,11 @@ use hotshot_types::traits::network::{
 };
 use hotshot_types::{
     boxed_sync,
+    constants::DEFAULT_MAX_INBOUND_MESSAGE_SIZE,
     data::ViewNumber,
     traits::{
         metrics::{Counter, Metrics, NoMetrics},
-        network::{BroadcastDelay, ConnectedNetwork, PushCdnNetworkError},
+        network::{BroadcastDelay, ConnectedNetwork, NetworkStats, PushCdnNetworkError},
         node_implementation::NodeType,
         signature_key::SignatureKey,
     },
@@ -46,15 +54,17 @@ use hotshot_types::{
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 #[cfg(feature = "hotshot-testing")]
 use rand::{rngs::StdRng, RngCore, SeedableRng};
-use tracing::error;
+use tracing::{error, warn};
 
-use super::NetworkError;
+use super::{MessageCounts, NetworkError};
 
 /// CDN-specific metrics
 #[derive(Clone)]
 pub struct CdnMetricsValue {
     /// The number of failed messages
     pub num_failed_messages: Box<dyn Counter>,
+    /// The number of received messages dropped for being too large
+    pub num_oversized_messages: Box<dyn Counter>,
 }
 
 impl CdnMetricsValue {
@@ -66,6 +76,7 @@ impl CdnMetricsValue {
         // Create the CDN-specific metrics
         Self {
             num_failed_messages: subgroup.create_counter("num_failed_messages".into(), None),
+            num_oversized_messages: subgroup.create_counter("num_oversized_messages".into(), None),
         }
     }
 }
@@ -172,6 +183,10 @@ pub struct PushCdnNetwork<TYPES: NodeType> {
     client: Client<ClientDef<TYPES>>,
     /// The CDN-specific metrics
     metrics: Arc<CdnMetricsValue>,
+    /// The messages we've sent and received, for our statistics
+    message_counts: Arc<MessageCounts>,
+    /// The maximum size of a message we accept
+    max_inbound_message_size: Arc<AtomicUsize>,
     /// Whether or not the underlying network is supposed to be paused
     #[cfg(feature = "hotshot-testing")]
     is_paused: Arc<AtomicBool>,
@@ -218,6 +233,8 @@ impl<TYPES: NodeType> PushCdnNetwork<TYPES> {
         Ok(Self {
             client,
             metrics: Arc::from(metrics),
+            message_counts: Arc::default(),
+            max_inbound_message_size: Arc::new(AtomicUsize::new(DEFAULT_MAX_INBOUND_MESSAGE_SIZE)),
             // Start unpaused
             #[cfg(feature = "hotshot-testing")]
             is_paused: Arc::from(AtomicBool::new(false)),
@@ -407,6 +424,10 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES> for PushCdnNetwork
                     let client = Arc::new(PushCdnNetwork {
                         client: Client::new(client_config),
                         metrics: Arc::new(CdnMetricsValue::default()),
+                        message_counts: Arc::default(),
+                        max_inbound_message_size: Arc::new(AtomicUsize::new(
+                            DEFAULT_MAX_INBOUND_MESSAGE_SIZE,
+                        )),
                         #[cfg(feature = "hotshot-testing")]
                         is_paused: Arc::from(AtomicBool::new(false)),
                     });
@@ -437,6 +458,12 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for PushCdnNetwork<T
         self.is_paused.store(false, Ordering::Relaxed);
     }
 
+    /// Drop received messages larger than `max_size`.
+    fn set_max_inbound_message_size(&self, max_size: usize) {
+        self.max_inbound_message_size
+            .store(max_size, Ordering::Relaxed);
+    }
+
     /// Wait for the client to initialize the connection
     async fn wait_for_ready(&self) {
         self.client.ensure_initialized().await;
@@ -453,19 +480,25 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for PushCdnNetwork<T
 
     /// Broadcast a message to all members of the quorum.
     ///
+    /// Sending only hands the message to our broker connection, so this and the DA broadcast keep
+    /// the default timeout variants, which bound them as closely as the CDN allows.
+    ///
     /// # Errors
     /// - If we fail to serialize the message
     /// - If we fail to send the broadcast message.
     async fn broadcast_message(
         &self,
         message: Vec<u8>,
-        _recipients: BTreeSet<TYPES::SignatureKey>,
-        _broadcast_delay: BroadcastDelay,
+        recipients: BTreeSet<TYPES::SignatureKey>,
+        broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
+        broadcast_delay.warn_if_unscheduled("PushCdnNetwork");
+        self.message_counts.sent(recipients.len());
         self.broadcast_message(message, Topic::Global)
             .await
             .map_err(|e| {
                 self.metrics.num_failed_messages.add(1);
+                self.message_counts.failed();
                 e
             })
     }
@@ -478,13 +511,16 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for PushCdnNetwork<T
     async fn da_broadcast_message(
         &self,
         message: Vec<u8>,
-        _recipients: BTreeSet<TYPES::SignatureKey>,
-        _broadcast_delay: BroadcastDelay,
+        recipients: BTreeSet<TYPES::SignatureKey>,
+        broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
+        broadcast_delay.warn_if_unscheduled("PushCdnNetwork");
+        self.message_counts.sent(recipients.len());
         self.broadcast_message(message, Topic::Da)
             .await
             .map_err(|e| {
                 self.metrics.num_failed_messages.add(1);
+                self.message_counts.failed();
                 e
             })
     }
@@ -506,6 +542,7 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for PushCdnNetwork<T
 
         // Send the message
         // TODO: check if we need to print this error
+        self.message_counts.sent(1);
         if self
             .client
             .send_direct_message(&WrappedSignatureKey(recipient), message)
@@ -513,6 +550,7 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for PushCdnNetwork<T
             .is_err()
         {
             self.metrics.num_failed_messages.add(1);
+            self.message_counts.failed();
             return Err(NetworkError::CouldNotDeliver);
         };
 
@@ -555,9 +593,22 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for PushCdnNetwork<T
             return Ok(vec![]);
         };
 
+        // Drop oversized messages before they are deserialized
+        if message.len() > self.max_inbound_message_size.load(Ordering::Relaxed) {
+            warn!(size = message.len(), "Dropping oversized message");
+            self.metrics.num_oversized_messages.add(1);
+            return Ok(vec![]);
+        }
+        self.message_counts.received(1);
+
         Ok(vec![message])
     }
 
+    /// The CDN relays our messages through brokers, so it has no peers of its own to count.
+    fn stats(&self) -> NetworkStats {
+        self.message_counts.stats()
+    }
+
     /// Do nothing here, as we don't need to look up nodes.
     async fn queue_node_lookup(
         &self,
@@ -0,0 +1,356 @@
+//! A wrapper that injects a [`NetworkReliability`] model into any [`ConnectedNetwork`], so that
+//! loss and latency can be introduced into production networks for chaos testing.
+use std::{
+    collections::{BTreeSet, HashMap},
+    marker::PhantomData,
+    time::Duration,
+};
+
+use async_compatibility_layer::{
+    art::{async_sleep, async_spawn},
+    channel::UnboundedSendError,
+};
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{
+        network::{
+            AckedMessage, BroadcastDelay, ConnectedNetwork, NetworkChange, NetworkReliability,
+            NetworkStats, ResponseChannel, ShutdownReason,
+        },
+        node_implementation::NodeType,
+        signature_key::SignatureKey,
+    },
+    BoxSyncFuture,
+};
+use tracing::warn;
+
+use super::NetworkError;
+
+/// A network whose sends are sampled from a reliability model before reaching the underlying
+/// network. Messages may be dropped, delayed, duplicated or scrambled, as decided by the model.
+///
+/// Without a reliability model, every call is passed straight through.
+#[derive(Clone)]
+pub struct UnreliableNetwork<K: SignatureKey + 'static, N: ConnectedNetwork<K>> {
+    /// The network messages are sent and received on
+    network: N,
+    /// The reliability model applied to outgoing messages
+    reliability_config: Option<Box<dyn NetworkReliability>>,
+    /// Phantom for the signature key
+    _pd: PhantomData<K>,
+}
+
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> UnreliableNetwork<K, N> {
+    /// Wrap `network`, applying `reliability_config` to every message sent on it
+    pub fn new(network: N, reliability_config: Option<Box<dyn NetworkReliability>>) -> Self {
+        Self {
+            network,
+            reliability_config,
+            _pd: PhantomData,
+        }
+    }
+
+    /// The wrapped network
+    #[must_use]
+    pub fn inner(&self) -> &N {
+        &self.network
+    }
+}
+
+/// Sample from `config` how `message` is sent: `None` if it's dropped, otherwise the delay before
+/// sending and the (possibly repeated and scrambled) copies to send.
+fn sample(config: &dyn NetworkReliability, message: &[u8]) -> Option<(Duration, Vec<Vec<u8>>)> {
+    if !config.sample_keep() {
+        return None;
+    }
+    let messages = (0..config.sample_repeat())
+        .map(|_| config.scramble(message.to_vec()))
+        .collect();
+    Some((config.sample_delay(), messages))
+}
+
+#[async_trait]
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> ConnectedNetwork<K>
+    for UnreliableNetwork<K, N>
+{
+    fn pause(&self) {
+        self.network.pause();
+    }
+
+    fn resume(&self) {
+        self.network.resume();
+    }
+
+    fn set_max_inbound_message_size(&self, max_size: usize) {
+        self.network.set_max_inbound_message_size(max_size);
+    }
+
+    async fn wait_for_ready(&self) {
+        self.network.wait_for_ready().await;
+    }
+
+    fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        self.network.shut_down()
+    }
+
+    fn shut_down_with<'a, 'b>(&'a self, reason: ShutdownReason) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        self.network.shut_down_with(reason)
+    }
+
+    async fn broadcast_message(
+        &self,
+        message: Vec<u8>,
+        recipients: BTreeSet<K>,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        let Some(ref config) = self.reliability_config else {
+            return self
+                .network
+                .broadcast_message(message, recipients, broadcast_delay)
+                .await;
+        };
+        let Some((delay, messages)) = sample(config.as_ref(), &message) else {
+            return Ok(());
+        };
+        let network = self.network.clone();
+        async_spawn(async move {
+            async_sleep(delay).await;
+            for message in messages {
+                if let Err(e) = network
+                    .broadcast_message(message, recipients.clone(), broadcast_delay.clone())
+                    .await
+                {
+                    warn!("Failed to broadcast on the unreliable network: {e:?}");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn broadcast_message_timeout(
+        &self,
+        message: Vec<u8>,
+        recipients: BTreeSet<K>,
+        broadcast_delay: BroadcastDelay,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        let Some(ref config) = self.reliability_config else {
+            return self
+                .network
+                .broadcast_message_timeout(message, recipients, broadcast_delay, timeout)
+                .await;
+        };
+        let Some((delay, messages)) = sample(config.as_ref(), &message) else {
+            return Ok(());
+        };
+        let network = self.network.clone();
+        async_spawn(async move {
+            async_sleep(delay).await;
+            for message in messages {
+                if let Err(e) = network
+                    .broadcast_message_timeout(
+                        message,
+                        recipients.clone(),
+                        broadcast_delay.clone(),
+                        timeout,
+                    )
+                    .await
+                {
+                    warn!("Failed to broadcast on the unreliable network: {e:?}");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn da_broadcast_message(
+        &self,
+        message: Vec<u8>,
+        recipients: BTreeSet<K>,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        let Some(ref config) = self.reliability_config else {
+            return self
+                .network
+                .da_broadcast_message(message, recipients, broadcast_delay)
+                .await;
+        };
+        let Some((delay, messages)) = sample(config.as_ref(), &message) else {
+            return Ok(());
+        };
+        let network = self.network.clone();
+        async_spawn(async move {
+            async_sleep(delay).await;
+            for message in messages {
+                if let Err(e) = network
+                    .da_broadcast_message(message, recipients.clone(), broadcast_delay.clone())
+                    .await
+                {
+                    warn!("Failed to broadcast on the unreliable network: {e:?}");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn da_broadcast_message_timeout(
+        &self,
+        message: Vec<u8>,
+        recipients: BTreeSet<K>,
+        broadcast_delay: BroadcastDelay,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        let Some(ref config) = self.reliability_config else {
+            return self
+                .network
+                .da_broadcast_message_timeout(message, recipients, broadcast_delay, timeout)
+                .await;
+        };
+        let Some((delay, messages)) = sample(config.as_ref(), &message) else {
+            return Ok(());
+        };
+        let network = self.network.clone();
+        async_spawn(async move {
+            async_sleep(delay).await;
+            for message in messages {
+                if let Err(e) = network
+                    .da_broadcast_message_timeout(
+                        message,
+                        recipients.clone(),
+                        broadcast_delay.clone(),
+                        timeout,
+                    )
+                    .await
+                {
+                    warn!("Failed to broadcast on the unreliable network: {e:?}");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn vid_broadcast_message(
+        &self,
+        messages: HashMap<K, Vec<u8>>,
+    ) -> Result<(), NetworkError> {
+        let Some(ref config) = self.reliability_config else {
+            return self.network.vid_broadcast_message(messages).await;
+        };
+        // Each share is its own message, so each is sampled on its own.
+        for (recipient, message) in messages {
+            let Some((delay, messages)) = sample(config.as_ref(), &message) else {
+                continue;
+            };
+            let network = self.network.clone();
+            async_spawn(async move {
+                async_sleep(delay).await;
+                for message in messages {
+                    if let Err(e) = network
+                        .vid_broadcast_message(HashMap::from([(recipient.clone(), message)]))
+                        .await
+                    {
+                        warn!("Failed to send a VID share on the unreliable network: {e:?}");
+                    }
+                }
+            });
+        }
+        Ok(())
+    }
+
+    async fn direct_message(&self, message: Vec<u8>, recipient: K) -> Result<(), NetworkError> {
+        let Some(ref config) = self.reliability_config else {
+            return self.network.direct_message(message, recipient).await;
+        };
+        let Some((delay, messages)) = sample(config.as_ref(), &message) else {
+            return Ok(());
+        };
+        let network = self.network.clone();
+        async_spawn(async move {
+            async_sleep(delay).await;
+            for message in messages {
+                if let Err(e) = network.direct_message(message, recipient.clone()).await {
+                    warn!("Failed to send a direct message on the unreliable network: {e:?}");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn direct_message_ack(
+        &self,
+        message: Vec<u8>,
+        recipient: K,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        // An acknowledgement can't be promised for a message we might drop
+        if self.reliability_config.is_some() {
+            return self.direct_message(message, recipient).await;
+        }
+        self.network
+            .direct_message_ack(message, recipient, timeout)
+            .await
+    }
+
+    async fn recv_msgs(&self) -> Result<Vec<Vec<u8>>, NetworkError> {
+        self.network.recv_msgs().await
+    }
+
+    async fn recv_msgs_with_acks(&self) -> Result<Vec<AckedMessage>, NetworkError> {
+        self.network.recv_msgs_with_acks().await
+    }
+
+    async fn request_data<TYPES: NodeType>(
+        &self,
+        request: Vec<u8>,
+        recipient: &K,
+    ) -> Result<Vec<u8>, NetworkError> {
+        self.network.request_data::<TYPES>(request, recipient).await
+    }
+
+    async fn spawn_request_receiver_task(
+        &self,
+    ) -> Option<mpsc::Receiver<(Vec<u8>, ResponseChannel<Vec<u8>>)>> {
+        self.network.spawn_request_receiver_task().await
+    }
+
+    async fn network_change_receiver(&self) -> Option<mpsc::Receiver<NetworkChange<K>>> {
+        self.network.network_change_receiver().await
+    }
+
+    async fn queue_node_lookup(
+        &self,
+        view_number: ViewNumber,
+        pk: K,
+    ) -> Result<(), UnboundedSendError<Option<(ViewNumber, K)>>> {
+        self.network.queue_node_lookup(view_number, pk).await
+    }
+
+    async fn update_view<'a, TYPES>(&'a self, view: u64, membership: &TYPES::Membership)
+    where
+        TYPES: NodeType<SignatureKey = K> + 'a,
+    {
+        self.network.update_view::<TYPES>(view, membership).await;
+    }
+
+    fn is_primary_down(&self) -> bool {
+        self.network.is_primary_down()
+    }
+
+    async fn connected_peers(&self) -> Option<BTreeSet<K>> {
+        self.network.connected_peers().await
+    }
+
+    fn stats(&self) -> NetworkStats {
+        self.network.stats()
+    }
+}
@@ -1,25 +1,42 @@
 //! Provides an event-streaming handle for a [`SystemContext`] running in the background
 
-use std::{sync::Arc, time::Duration};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
+use anyhow::Result;
 use async_broadcast::{InactiveReceiver, Receiver, Sender};
-use async_compatibility_layer::art::{async_sleep, async_spawn};
+use async_compatibility_layer::art::{async_sleep, async_spawn, async_timeout};
 use async_lock::RwLock;
 #[cfg(async_executor_impl = "async-std")]
 use async_std::task::JoinHandle;
 use futures::Stream;
-use hotshot_task::task::{ConsensusTaskRegistry, NetworkTaskRegistry, Task, TaskState};
+use hotshot_task::{
+    cancellation::CancellationToken,
+    task::{ConsensusTaskRegistry, NetworkTaskRegistry, Task, TaskState},
+};
 use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event};
 use hotshot_types::{
-    consensus::Consensus,
-    data::Leaf,
+    consensus::{CommitmentMap, Consensus},
+    data::{DaProposal, Leaf, ViewChangeEvidence},
     error::HotShotError,
-    traits::{election::Membership, node_implementation::NodeType},
+    event::EventFilter,
+    message::{Proposal, TxPriority},
+    simple_certificate::QuorumCertificate,
+    traits::{
+        election::Membership,
+        network::{NetworkStats, ShutdownReason},
+        node_implementation::NodeType,
+        storage::Storage,
+    },
+    vote::HasViewNumber,
 };
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::JoinHandle;
 
-use crate::{traits::NodeImplementation, types::Event, SystemContext};
+use crate::{
+    traits::NodeImplementation,
+    types::{Event, EventType},
+    SystemContext,
+};
 
 /// Event streaming handle for a [`SystemContext`] instance running in the background
 ///
@@ -43,6 +60,9 @@ pub struct SystemContextHandle<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// registry for controlling network tasks
     pub(crate) network_registry: NetworkTaskRegistry,
 
+    /// Cancelled to stop the consensus tasks and everything they've spawned
+    pub(crate) cancellation: CancellationToken,
+
     /// Internal reference to the underlying [`SystemContext`]
     pub hotshot: Arc<SystemContext<TYPES, I>>,
 
@@ -57,11 +77,25 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> SystemContextHandl
             task_state,
             self.internal_event_stream.0.clone(),
             self.internal_event_stream.1.activate_cloned(),
+            self.cancellation.clone(),
         );
 
         self.consensus_registry.run_task(task);
     }
 
+    /// The token which stops this handle's tasks, and the subtasks they've spawned, when
+    /// cancelled. Cancelling it doesn't wait for them to stop, unlike [`Self::shut_down`].
+    #[must_use]
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Install `filter` to decide which events are emitted to the application from now on.
+    /// See [`SystemContext::set_event_filter`].
+    pub async fn set_event_filter(&self, filter: EventFilter<TYPES>) {
+        self.hotshot.set_event_filter(filter).await;
+    }
+
     /// obtains a stream to expose to the user
     pub fn event_stream(&self) -> impl Stream<Item = Event<TYPES>> {
         self.output_event_stream.1.activate_cloned()
@@ -123,6 +157,69 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> SystemContextHandl
         self.hotshot.try_decided_leaf()
     }
 
+    /// Waits until `view` is decided and returns the leaf decided in it, returning immediately if
+    /// `view` has already been decided.
+    ///
+    /// # Errors
+    /// Returns [`HotShotError::TimeoutError`] if `view` isn't decided within `timeout`, and
+    /// [`HotShotError::LeafNotFound`] if a later view is decided without `view` on the decided
+    /// chain, or if the leaf decided in `view` is no longer retained.
+    pub async fn wait_for_decide_on_view(
+        &self,
+        view: TYPES::Time,
+        timeout: Duration,
+    ) -> Result<Leaf<TYPES>, HotShotError<TYPES>> {
+        // Subscribe before checking consensus, so we can't miss a decide in between.
+        let mut events = self.event_stream_known_impl();
+        if let Some(leaf) = self.leaf_decided_at(view).await? {
+            return Ok(leaf);
+        }
+
+        async_timeout(timeout, async {
+            while let Ok(event) = events.recv_direct().await {
+                let EventType::Decide { leaf_chain, .. } = event.event else {
+                    continue;
+                };
+                if let Some(info) = leaf_chain
+                    .iter()
+                    .find(|info| info.leaf.view_number() == view)
+                {
+                    return Ok(info.leaf.clone());
+                }
+                // The leaf chain may be incomplete while catching up, so fall back to consensus.
+                if let Some(leaf) = self.leaf_decided_at(view).await? {
+                    return Ok(leaf);
+                }
+            }
+            Err(HotShotError::Misc {
+                context: format!("Event stream closed before view {view:?} was decided"),
+            })
+        })
+        .await
+        .map_err(|source| HotShotError::TimeoutError {
+            context: format!("waiting for view {view:?} to be decided"),
+            source,
+        })?
+    }
+
+    /// The leaf decided in `view`, or `None` if `view` hasn't been decided yet.
+    async fn leaf_decided_at(
+        &self,
+        view: TYPES::Time,
+    ) -> Result<Option<Leaf<TYPES>>, HotShotError<TYPES>> {
+        let consensus = self.hotshot.consensus();
+        let consensus = consensus.read().await;
+        if view > consensus.last_decided_view() {
+            return Ok(None);
+        }
+        consensus
+            .decided_leaf_at(view)
+            .map(Some)
+            .ok_or_else(|| HotShotError::LeafNotFound {
+                context: format!("the leaf decided in view {view:?} is no longer retained"),
+            })
+    }
+
     /// Submits a transaction to the backing [`SystemContext`] instance.
     ///
     /// The current node broadcasts the transaction to all nodes on the network.
@@ -140,12 +237,104 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> SystemContextHandl
             .await
     }
 
+    /// Submits a transaction to the backing [`SystemContext`] instance with a `priority` builders
+    /// can use to order it within a block. See [`SystemContext::publish_transaction_with_priority`].
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`HotShotError`] if some error occurs in the underlying
+    /// [`SystemContext`] instance.
+    pub async fn submit_transaction_with_priority(
+        &self,
+        tx: TYPES::Transaction,
+        priority: TxPriority,
+    ) -> Result<(), HotShotError<TYPES>> {
+        self.hotshot
+            .publish_transaction_with_priority(
+                tx,
+                priority,
+                Arc::clone(&self.hotshot.decided_upgrade_certificate),
+            )
+            .await
+    }
+
+    /// Submits a transaction to the backing [`SystemContext`] instance, waiting for the broadcast
+    /// to finish. See [`SystemContext::publish_transaction_blocking`].
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`HotShotError`] if the transaction couldn't be broadcast, so the submission
+    /// can be retried.
+    pub async fn submit_transaction_blocking(
+        &self,
+        tx: TYPES::Transaction,
+    ) -> Result<(), HotShotError<TYPES>> {
+        self.hotshot
+            .publish_transaction_blocking(tx, Arc::clone(&self.hotshot.decided_upgrade_certificate))
+            .await
+    }
+
+    /// Submits a transaction to the backing [`SystemContext`] instance with a `priority`, waiting
+    /// for the broadcast to finish. See
+    /// [`SystemContext::publish_transaction_blocking_with_priority`].
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`HotShotError`] if the transaction couldn't be broadcast, so the submission
+    /// can be retried.
+    pub async fn submit_transaction_blocking_with_priority(
+        &self,
+        tx: TYPES::Transaction,
+        priority: TxPriority,
+    ) -> Result<(), HotShotError<TYPES>> {
+        self.hotshot
+            .publish_transaction_blocking_with_priority(
+                tx,
+                priority,
+                Arc::clone(&self.hotshot.decided_upgrade_certificate),
+            )
+            .await
+    }
+
+    /// Proposes for the next view immediately, returning the view we proposed for.
+    /// See [`SystemContext::propose_now`].
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`HotShotError`] if we are not the leader of the next view, or have already
+    /// proposed for it.
+    pub async fn propose_now(&self) -> Result<TYPES::Time, HotShotError<TYPES>> {
+        self.hotshot.propose_now().await
+    }
+
+    /// Advances the node to view `to`, justified by `evidence`.
+    /// See [`SystemContext::force_view_change`].
+    ///
+    /// # Errors
+    ///
+    /// Will return a [`HotShotError`] if `to` is not after the current view, or if `evidence` is
+    /// not a valid certificate for it.
+    pub async fn force_view_change(
+        &self,
+        to: TYPES::Time,
+        evidence: ViewChangeEvidence<TYPES>,
+    ) -> Result<(), HotShotError<TYPES>> {
+        self.hotshot.force_view_change(to, evidence).await
+    }
+
     /// Get the underlying consensus state for this [`SystemContext`]
     #[must_use]
     pub fn consensus(&self) -> Arc<RwLock<Consensus<TYPES>>> {
         self.hotshot.consensus()
     }
 
+    /// Render the current metrics of the underlying [`SystemContext`] for a Prometheus scrape.
+    /// See [`SystemContext::export_metrics_prometheus`].
+    #[must_use]
+    pub fn export_metrics_prometheus(&self) -> String {
+        self.hotshot.export_metrics_prometheus()
+    }
+
     /// Block the underlying quorum (and DA) networking interfaces until node is
     /// successfully initialized into the networks.
     pub async fn wait_for_networks_ready(&self) {
@@ -154,20 +343,32 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> SystemContextHandl
 
     /// Shut down the the inner hotshot and wait until all background threads are closed.
     pub async fn shut_down(&mut self) {
-        // this is required because `SystemContextHandle` holds an inactive receiver and
-        // `broadcast_direct` below can wait indefinitely
-        self.internal_event_stream.0.set_await_active(false);
-        let _ = self
-            .internal_event_stream
-            .0
-            .broadcast_direct(Arc::new(HotShotEvent::Shutdown))
-            .await
-            .inspect_err(|err| tracing::error!("Failed to send shutdown event: {err}"));
+        self.shut_down_with(ShutdownReason::Graceful).await;
+    }
+
+    /// Shut down the inner hotshot for `reason` and wait until all background threads are
+    /// closed. The networks log the reason, so a shutdown after a fault can be told apart from a
+    /// graceful one.
+    pub async fn shut_down_with(&mut self, reason: ShutdownReason) {
+        self.shut_down_inner(Some(reason)).await;
+    }
+
+    /// Shut down the tasks of the inner hotshot and wait until they are closed, leaving the
+    /// networks running so that a new instance can be started on them, as when restarting a node.
+    pub async fn shut_down_tasks(&mut self) {
+        self.shut_down_inner(None).await;
+    }
+
+    /// Shut down the tasks of the inner hotshot, and its networks for `reason` if there is one.
+    async fn shut_down_inner(&mut self, reason: Option<ShutdownReason>) {
+        self.cancellation.cancel();
         tracing::error!("Shutting down network tasks!");
         self.network_registry.shutdown().await;
 
-        tracing::error!("Shutting down networks!");
-        self.hotshot.networks.shut_down_networks().await;
+        if let Some(reason) = reason {
+            tracing::error!(?reason, "Shutting down networks!");
+            self.hotshot.networks.shut_down_networks(reason).await;
+        }
 
         tracing::error!("Shutting down consensus!");
         self.consensus_registry.shutdown().await;
@@ -188,6 +389,22 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> SystemContextHandl
             .leader(view_number)
     }
 
+    /// The leader schedule for the `count` views starting at `from`, as `(view, leader)` pairs.
+    ///
+    /// The schedule is deterministic, so external infrastructure such as builders and relays can
+    /// use it to route blocks and transactions to upcoming leaders ahead of time.
+    #[must_use]
+    pub fn leader_schedule(
+        &self,
+        from: TYPES::Time,
+        count: usize,
+    ) -> Vec<(TYPES::Time, TYPES::SignatureKey)> {
+        self.hotshot
+            .memberships
+            .quorum_membership
+            .leaders_ahead(from, count)
+    }
+
     // Below is for testing only:
     /// Wrapper to get this node's public key
     #[cfg(feature = "hotshot-testing")]
@@ -196,11 +413,33 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> SystemContextHandl
         self.hotshot.public_key.clone()
     }
 
+    /// Decide `cert` on this node as if consensus had decided it, to test what depends on the
+    /// version in effect without running an upgrade.
+    #[cfg(feature = "hotshot-testing")]
+    pub async fn decide_upgrade_certificate(
+        &self,
+        cert: hotshot_types::simple_certificate::UpgradeCertificate<TYPES>,
+    ) {
+        *self.hotshot.decided_upgrade_certificate.write().await = Some(cert.clone());
+        broadcast_event(
+            Arc::new(HotShotEvent::UpgradeDecided(cert)),
+            &self.internal_event_stream.0,
+        )
+        .await;
+    }
+
     /// Wrapper to get the view number this node is on.
     pub async fn cur_view(&self) -> TYPES::Time {
         self.hotshot.consensus.read().await.cur_view()
     }
 
+    /// Get the transport statistics of the quorum and DA networks added together, for a single
+    /// view of the node's networking.
+    #[must_use]
+    pub fn network_stats(&self) -> NetworkStats {
+        self.hotshot.networks.stats()
+    }
+
     /// Provides a reference to the underlying storage for this [`SystemContext`], allowing access to
     /// historical data
     #[must_use]
@@ -208,6 +447,39 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> SystemContextHandl
         Arc::clone(&self.storage)
     }
 
+    /// Get the high QC persisted to storage, if one has been written.
+    ///
+    /// # Errors
+    /// If the storage backend fails to load the high QC.
+    pub async fn stored_high_qc(&self) -> Result<Option<QuorumCertificate<TYPES>>> {
+        self.storage.read().await.load_high_qc().await
+    }
+
+    /// Get the undecided leaf chain persisted to storage, which is empty if none has been written.
+    ///
+    /// # Errors
+    /// If the storage backend fails to load the undecided state.
+    pub async fn stored_undecided_leaves(&self) -> Result<CommitmentMap<Leaf<TYPES>>> {
+        Ok(self
+            .storage
+            .read()
+            .await
+            .load_undecided_state()
+            .await?
+            .map(|(leaves, _)| leaves)
+            .unwrap_or_default())
+    }
+
+    /// Get the DA proposals persisted to storage, keyed by view.
+    ///
+    /// # Errors
+    /// If the storage backend fails to load the DA proposals.
+    pub async fn stored_da_proposals(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Time, Proposal<TYPES, DaProposal<TYPES>>>> {
+        self.storage.read().await.load_da_proposals().await
+    }
+
     /// A helper function to spawn the initial timeout task from a given `SystemContextHandle`.
     #[must_use]
     pub fn spawn_initial_timeout_task(&self) -> JoinHandle<()> {
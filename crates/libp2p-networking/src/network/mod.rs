@@ -172,6 +172,10 @@ pub enum NetworkEvent {
     IsBootstrapped,
     /// The number of connected peers has possibly changed
     ConnectedPeersUpdate(usize),
+    /// We established our first connection to a peer
+    PeerConnected(PeerId),
+    /// We closed our last connection to a peer
+    PeerDisconnected(PeerId),
 }
 
 #[derive(Debug)]
@@ -561,6 +561,13 @@ impl NetworkNode {
                     .send(NetworkEvent::ConnectedPeersUpdate(self.num_connected()))
                     .await
                     .map_err(|_e| NetworkError::StreamClosed)?;
+
+                if num_established.get() == 1 {
+                    send_to_client
+                        .send(NetworkEvent::PeerConnected(peer_id))
+                        .await
+                        .map_err(|_e| NetworkError::StreamClosed)?;
+                }
             }
             SwarmEvent::ConnectionClosed {
                 connection_id: _,
@@ -583,6 +590,13 @@ impl NetworkNode {
                     .send(NetworkEvent::ConnectedPeersUpdate(self.num_connected()))
                     .await
                     .map_err(|_e| NetworkError::StreamClosed)?;
+
+                if num_established == 0 {
+                    send_to_client
+                        .send(NetworkEvent::PeerDisconnected(peer_id))
+                        .await
+                        .map_err(|_e| NetworkError::StreamClosed)?;
+                }
             }
             SwarmEvent::Dialing {
                 peer_id,
@@ -554,9 +554,10 @@ impl From<NetworkNodeHandleError> for HotshotNetworkError {
             NetworkNodeHandleError::DeserializationError { source } => {
                 HotshotNetworkError::FailedToDeserialize { source }
             }
-            NetworkNodeHandleError::TimeoutError { source } => {
-                HotshotNetworkError::Timeout { source }
-            }
+            NetworkNodeHandleError::TimeoutError { source } => HotshotNetworkError::Timeout {
+                source,
+                num_recipients: None,
+            },
             NetworkNodeHandleError::Killed => HotshotNetworkError::ShutDown,
             source => HotshotNetworkError::Libp2p {
                 source: Box::new(source),
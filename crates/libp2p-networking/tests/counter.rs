@@ -73,7 +73,9 @@ pub async fn counter_handle_network_event(
     match event {
         IsBootstrapped
         | NetworkEvent::ResponseRequested(..)
-        | NetworkEvent::ConnectedPeersUpdate(..) => {}
+        | NetworkEvent::ConnectedPeersUpdate(..)
+        | NetworkEvent::PeerConnected(..)
+        | NetworkEvent::PeerDisconnected(..) => {}
         GossipMsg(m) | DirectResponse(m, _) => {
             if let Ok(msg) = bincode::deserialize::<CounterMessage>(&m) {
                 match msg {
@@ -10,9 +10,17 @@ use std::{
 
 use clap::ValueEnum;
 use hotshot_types::{
-    traits::signature_key::SignatureKey, ExecutionType, HotShotConfig, PeerConfig, ValidatorConfig,
+    constants::{
+        DEFAULT_BUILDER_PREFETCH_DEPTH, DEFAULT_CATCHUP_REQUESTS_PER_SECOND,
+        DEFAULT_MAX_BLOCK_SIZE, DEFAULT_MAX_INBOUND_MESSAGE_SIZE, DEFAULT_MAX_PENDING_TRANSACTIONS,
+        DEFAULT_MAX_UNDECIDED_VIEWS, DEFAULT_PROPOSAL_BACKFILL_VIEWS,
+        DEFAULT_TRANSACTION_POW_DIFFICULTY, MAX_TRANSACTION_POW_DIFFICULTY,
+    },
+    traits::signature_key::SignatureKey,
+    ExecutionType, HotShotConfig, PeerConfig, ValidationLevel, ValidatorConfig,
 };
 use libp2p::{Multiaddr, PeerId};
+use serde::{de::Error as _, Deserialize, Deserializer};
 use serde_inline_default::serde_inline_default;
 use surf_disco::Url;
 use thiserror::Error;
@@ -549,6 +557,59 @@ fn default_builder_urls() -> Vec1<Url> {
     vec1::vec1![Url::parse("http://0.0.0.0:3311").unwrap()]
 }
 
+/// Default maximum size of a message accepted from the network
+fn default_max_inbound_message_size() -> usize {
+    DEFAULT_MAX_INBOUND_MESSAGE_SIZE
+}
+
+/// Default maximum rate of catch-up data requests
+fn default_catchup_requests_per_second() -> u64 {
+    DEFAULT_CATCHUP_REQUESTS_PER_SECOND
+}
+
+/// Default number of views ahead to prefetch blocks for
+fn default_builder_prefetch_depth() -> usize {
+    DEFAULT_BUILDER_PREFETCH_DEPTH
+}
+
+/// Default number of views before the start view to backfill proposals for
+fn default_proposal_backfill_views() -> u64 {
+    DEFAULT_PROPOSAL_BACKFILL_VIEWS
+}
+
+/// Default proof of work difficulty of submitted transactions
+fn default_transaction_pow_difficulty() -> u32 {
+    DEFAULT_TRANSACTION_POW_DIFFICULTY
+}
+
+/// Deserialize a proof of work difficulty, rejecting one above `MAX_TRANSACTION_POW_DIFFICULTY`
+fn deserialize_transaction_pow_difficulty<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u32, D::Error> {
+    let difficulty = u32::deserialize(deserializer)?;
+    if difficulty > MAX_TRANSACTION_POW_DIFFICULTY {
+        return Err(D::Error::custom(format!(
+            "transaction proof of work difficulty {difficulty} exceeds the maximum of {MAX_TRANSACTION_POW_DIFFICULTY}"
+        )));
+    }
+    Ok(difficulty)
+}
+
+/// Default maximum number of undecided views kept
+fn default_max_undecided_views() -> usize {
+    DEFAULT_MAX_UNDECIDED_VIEWS
+}
+
+/// Default maximum size of a proposed block payload
+fn default_max_block_size() -> u64 {
+    DEFAULT_MAX_BLOCK_SIZE
+}
+
+/// Default maximum number of transactions queued before the node is ready
+fn default_max_pending_transactions() -> usize {
+    DEFAULT_MAX_PENDING_TRANSACTIONS
+}
+
 /// Holds configuration for a `HotShot`
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(bound(deserialize = ""))]
@@ -576,6 +637,9 @@ pub struct HotShotConfigFile<KEY: SignatureKey> {
     pub staked_da_nodes: usize,
     /// Number of non-staking DA nodes
     pub non_staked_da_nodes: usize,
+    /// Number of staked nodes which are VID storage nodes, or 0 for all of them
+    #[serde(default)]
+    pub vid_storage_nodes: usize,
     /// Number of fixed leaders for GPU VID
     pub fixed_leader_for_gpuvid: usize,
     /// Base duration for next-view timeout, in milliseconds
@@ -599,6 +663,44 @@ pub struct HotShotConfigFile<KEY: SignatureKey> {
     pub builder_urls: Vec1<Url>,
     /// Upgrade config
     pub upgrade: UpgradeConfig,
+    /// Maximum size in bytes of a message accepted from the network
+    #[serde(default = "default_max_inbound_message_size")]
+    pub max_inbound_message_size: usize,
+    /// Maximum number of requests per second for data from views we're catching up on
+    #[serde(default = "default_catchup_requests_per_second")]
+    pub catchup_requests_per_second: u64,
+    /// Number of views beyond the next one to prefetch our blocks from the builder for
+    #[serde(default = "default_builder_prefetch_depth")]
+    pub builder_prefetch_depth: usize,
+    /// How thoroughly incoming quorum proposals are validated before voting
+    #[serde(default)]
+    pub validation_level: ValidationLevel,
+    /// Number of views before the start view to backfill proposals for on startup
+    #[serde(default = "default_proposal_backfill_views")]
+    pub proposal_backfill_views: u64,
+    /// Number of leading zero bits required of the proof of work on a submitted transaction once
+    /// the network has upgraded, at most `MAX_TRANSACTION_POW_DIFFICULTY`
+    #[serde(
+        default = "default_transaction_pow_difficulty",
+        deserialize_with = "deserialize_transaction_pow_difficulty"
+    )]
+    pub transaction_pow_difficulty: u32,
+    /// Whether a leader waits for the DA certificate of its view before proposing, with
+    /// `dependency-tasks` only
+    #[serde(default)]
+    pub wait_for_dac_to_propose: bool,
+    /// Maximum number of undecided views kept before the oldest are pruned
+    #[serde(default = "default_max_undecided_views")]
+    pub max_undecided_views: usize,
+    /// Number of views below the high QC beyond which undecided states are compacted, if any
+    #[serde(default)]
+    pub state_compaction_depth: Option<u64>,
+    /// Maximum size, in bytes, of an encoded block payload the DA leader proposes
+    #[serde(default = "default_max_block_size")]
+    pub max_block_size: u64,
+    /// Maximum number of transactions queued while the node isn't ready to broadcast them
+    #[serde(default = "default_max_pending_transactions")]
+    pub max_pending_transactions: usize,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -690,11 +792,13 @@ impl<KEY: SignatureKey> From<HotShotConfigFile<KEY>> for HotShotConfig<KEY> {
             num_nodes_with_stake: val.num_nodes_with_stake,
             num_nodes_without_stake: val.num_nodes_without_stake,
             known_da_nodes: val.known_da_nodes,
+            known_vid_nodes: vec![],
             known_nodes_with_stake: val.known_nodes_with_stake,
             known_nodes_without_stake: val.known_nodes_without_stake,
             my_own_validator_config: val.my_own_validator_config,
             da_staked_committee_size: val.staked_da_nodes,
             da_non_staked_committee_size: val.non_staked_da_nodes,
+            vid_storage_committee_size: val.vid_storage_nodes,
             fixed_leader_for_gpuvid: val.fixed_leader_for_gpuvid,
             next_view_timeout: val.next_view_timeout,
             view_sync_timeout: val.view_sync_timeout,
@@ -709,6 +813,17 @@ impl<KEY: SignatureKey> From<HotShotConfigFile<KEY>> for HotShotConfig<KEY> {
             stop_proposing_view: val.upgrade.stop_proposing_view,
             start_voting_view: val.upgrade.start_voting_view,
             stop_voting_view: val.upgrade.stop_voting_view,
+            max_inbound_message_size: val.max_inbound_message_size,
+            catchup_requests_per_second: val.catchup_requests_per_second,
+            builder_prefetch_depth: val.builder_prefetch_depth,
+            validation_level: val.validation_level,
+            proposal_backfill_views: val.proposal_backfill_views,
+            transaction_pow_difficulty: val.transaction_pow_difficulty,
+            wait_for_dac_to_propose: val.wait_for_dac_to_propose,
+            max_undecided_views: val.max_undecided_views,
+            state_compaction_depth: val.state_compaction_depth,
+            max_block_size: val.max_block_size,
+            max_pending_transactions: val.max_pending_transactions,
         }
     }
 }
@@ -768,6 +883,7 @@ impl<KEY: SignatureKey> Default for HotShotConfigFile<KEY> {
             staked_da_nodes,
             known_da_nodes,
             non_staked_da_nodes: 0,
+            vid_storage_nodes: 0,
             fixed_leader_for_gpuvid: 1,
             next_view_timeout: 10000,
             view_sync_timeout: Duration::from_millis(1000),
@@ -779,6 +895,17 @@ impl<KEY: SignatureKey> Default for HotShotConfigFile<KEY> {
             data_request_delay: Duration::from_millis(200),
             builder_urls: default_builder_urls(),
             upgrade: UpgradeConfig::default(),
+            max_inbound_message_size: DEFAULT_MAX_INBOUND_MESSAGE_SIZE,
+            catchup_requests_per_second: DEFAULT_CATCHUP_REQUESTS_PER_SECOND,
+            builder_prefetch_depth: DEFAULT_BUILDER_PREFETCH_DEPTH,
+            validation_level: ValidationLevel::default(),
+            proposal_backfill_views: DEFAULT_PROPOSAL_BACKFILL_VIEWS,
+            transaction_pow_difficulty: DEFAULT_TRANSACTION_POW_DIFFICULTY,
+            wait_for_dac_to_propose: false,
+            max_undecided_views: DEFAULT_MAX_UNDECIDED_VIEWS,
+            state_compaction_depth: None,
+            max_block_size: DEFAULT_MAX_BLOCK_SIZE,
+            max_pending_transactions: DEFAULT_MAX_PENDING_TRANSACTIONS,
         }
     }
 }
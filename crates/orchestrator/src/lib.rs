@@ -304,6 +304,15 @@ where
             .known_nodes_with_stake
             .push(staked_pubkey.clone());
 
+        // The first nodes to register are the VID storage nodes.
+        if self.config.config.known_vid_nodes.len() < self.config.config.vid_storage_committee_size
+        {
+            self.config
+                .config
+                .known_vid_nodes
+                .push(staked_pubkey.clone());
+        }
+
         let mut added_to_da = false;
 
         let da_full =
@@ -616,6 +625,7 @@ where
 
     network_config.config.known_nodes_with_stake = vec![];
     network_config.config.known_da_nodes = vec![];
+    network_config.config.known_vid_nodes = vec![];
 
     let web_api =
         define_api().map_err(|_e| io::Error::new(ErrorKind::Other, "Failed to define api"));
@@ -1,4 +1,4 @@
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use async_compatibility_layer::art::async_sleep;
 use hotshot_builder_api::{
@@ -14,6 +14,7 @@ use serde::{Deserialize, Serialize};
 use snafu::Snafu;
 use surf_disco::{client::HealthStatus, Client, Url};
 use tagged_base64::TaggedBase64;
+use tide_disco::StatusCode;
 use vbs::version::StaticVersionType;
 
 #[derive(Debug, Snafu, Serialize, Deserialize)]
@@ -65,6 +66,15 @@ pub struct BuilderClient<TYPES: NodeType, Ver: StaticVersionType> {
     _marker: std::marker::PhantomData<TYPES>,
 }
 
+impl<TYPES: NodeType, Ver: StaticVersionType> Clone for BuilderClient<TYPES, Ver> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
 impl<TYPES: NodeType, Ver: StaticVersionType> BuilderClient<TYPES, Ver> {
     /// Construct a new client from base url
     ///
@@ -99,7 +109,8 @@ impl<TYPES: NodeType, Ver: StaticVersionType> BuilderClient<TYPES, Ver> {
         false
     }
 
-    /// Query builder for available blocks
+    /// Query builder for available blocks, which we need by `deadline`. A builder that doesn't
+    /// take deadlines yet is queried without one.
     ///
     /// # Errors
     /// - [`BuilderClientError::NotFound`] if blocks aren't available for this parent
@@ -110,15 +121,23 @@ impl<TYPES: NodeType, Ver: StaticVersionType> BuilderClient<TYPES, Ver> {
         view_number: u64,
         sender: TYPES::SignatureKey,
         signature: &<<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+        deadline: SystemTime,
     ) -> Result<Vec<AvailableBlockInfo<TYPES>>, BuilderClientError> {
         let encoded_signature: TaggedBase64 = signature.clone().into();
-        self.inner
-            .get(&format!(
-                "availableblocks/{parent}/{view_number}/{sender}/{encoded_signature}"
-            ))
-            .send()
-            .await
-            .map_err(Into::into)
+        let deadline = deadline.duration_since(UNIX_EPOCH).map_or(0, |deadline| {
+            u64::try_from(deadline.as_millis()).unwrap_or(u64::MAX)
+        });
+        let route = format!("availableblocks/{parent}/{view_number}/{sender}/{encoded_signature}");
+        let result: Result<Vec<AvailableBlockInfo<TYPES>>, BuilderApiError> =
+            self.inner.get(&format!("{route}/{deadline}")).send().await;
+        match result {
+            // A builder without the route taking a deadline doesn't know it at all, rather than
+            // answering that it has no blocks
+            Err(BuilderApiError::Custom { status, .. }) if status == StatusCode::NOT_FOUND => {
+                self.inner.get(&route).send().await.map_err(Into::into)
+            }
+            result => result.map_err(Into::into),
+        }
     }
 
     /// Claim block
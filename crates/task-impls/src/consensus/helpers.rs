@@ -1,33 +1,43 @@
 use std::{
     collections::{HashMap, HashSet},
+    marker::PhantomData,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use crate::{events::ProposalMissing, request::REQUEST_TIMEOUT};
 use anyhow::bail;
 use anyhow::{ensure, Context, Result};
 use async_broadcast::{broadcast, Sender};
-use async_compatibility_layer::art::async_timeout;
+use async_compatibility_layer::art::{async_sleep, async_timeout};
 use async_lock::RwLock;
 #[cfg(async_executor_impl = "async-std")]
-use async_std::task::JoinHandle;
+use async_std::task::{spawn_blocking, JoinHandle};
 use committable::{Commitment, Committable};
 use hotshot_types::{
-    consensus::{Consensus, View},
-    data::{Leaf, QuorumProposal, ViewChangeEvidence},
+    consensus::{CommitmentAndMetadata, Consensus, View},
+    constants::Base,
+    data::{null_block, Leaf, ParentDaCertificate, QuorumProposal, ViewChangeEvidence},
+    error::HotShotError,
     event::{Event, EventType, LeafInfo},
-    message::Proposal,
+    message::{version_for_view, Proposal},
     simple_certificate::{QuorumCertificate, UpgradeCertificate},
     traits::{
-        block_contents::BlockHeader, election::Membership, node_implementation::NodeType,
-        signature_key::SignatureKey, states::ValidatedState, BlockPayload,
+        block_contents::BlockHeader,
+        election::Membership,
+        node_implementation::NodeType,
+        signature_key::{SignatureKey, Signer},
+        states::ValidatedState,
+        BlockPayload,
     },
     utils::{Terminator, ViewInner},
     vote::{Certificate, HasViewNumber},
+    ValidationLevel,
 };
 #[cfg(async_executor_impl = "tokio")]
-use tokio::task::JoinHandle;
-use tracing::{debug, info, warn};
+use tokio::task::{spawn_blocking, JoinHandle};
+use tracing::{debug, error, info, warn};
+use vbs::version::{StaticVersionType, Version};
 #[cfg(not(feature = "dependency-tasks"))]
 use {
     super::ConsensusTaskState,
@@ -35,24 +45,58 @@ use {
         consensus::{update_view, view_change::SEND_VIEW_CHANGE_EVENT},
         helpers::AnyhowTracing,
     },
-    async_compatibility_layer::art::{async_sleep, async_spawn},
+    async_compatibility_layer::art::async_spawn,
     chrono::Utc,
-    core::time::Duration,
     futures::FutureExt,
-    hotshot_types::{
-        consensus::CommitmentAndMetadata,
-        traits::{
-            node_implementation::{ConsensusTime, NodeImplementation},
-            storage::Storage,
-        },
+    hotshot_types::traits::{
+        node_implementation::{ConsensusTime, NodeImplementation},
+        storage::Storage,
     },
-    hotshot_types::{data::null_block, message::GeneralConsensusMessage, simple_vote::QuorumData},
-    std::marker::PhantomData,
-    tracing::error,
-    vbs::version::Version,
+    hotshot_types::{message::GeneralConsensusMessage, simple_vote::QuorumData},
+};
+
+use crate::{
+    events::HotShotEvent,
+    helpers::{broadcast_event, ExternalEventSender},
 };
 
-use crate::{events::HotShotEvent, helpers::broadcast_event};
+/// If we already have the payload from the DA proposal for the leaf's view, make sure the
+/// header's builder and VID commitments both refer to it.
+///
+/// The payload is cloned out of consensus so the lock isn't held while it is decoded and hashed,
+/// which happens off the executor.
+///
+/// # Errors
+/// If either commitment doesn't match the saved payload.
+pub async fn verify_saved_payload<TYPES: NodeType>(
+    leaf: &Leaf<TYPES>,
+    consensus: &RwLock<Consensus<TYPES>>,
+    num_storage_nodes: usize,
+) -> Result<()> {
+    let Some(encoded_txns) = consensus
+        .read()
+        .await
+        .saved_payloads()
+        .get(&leaf.view_number())
+        .cloned()
+    else {
+        return Ok(());
+    };
+
+    let mut filled_leaf = leaf.clone();
+    let verification = spawn_blocking(move || {
+        let metadata = filled_leaf.block_header().metadata().clone();
+        filled_leaf
+            .fill_block_payload_unchecked(BlockPayload::from_bytes(&encoded_txns, &metadata));
+        filled_leaf.verify_commitments(num_storage_nodes)
+    })
+    .await;
+    #[cfg(async_executor_impl = "tokio")]
+    let verification = verification.unwrap();
+    verification?;
+
+    Ok(())
+}
 
 /// Validate the state and safety and liveness of a proposal then emit
 /// a `QuorumProposalValidated` event.
@@ -67,25 +111,45 @@ pub async fn validate_proposal_safety_and_liveness<TYPES: NodeType>(
     consensus: Arc<RwLock<Consensus<TYPES>>>,
     decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
     quorum_membership: Arc<TYPES::Membership>,
+    vid_membership: Arc<TYPES::Membership>,
     view_leader_key: TYPES::SignatureKey,
     event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
     sender: TYPES::SignatureKey,
-    event_sender: Sender<Event<TYPES>>,
+    event_sender: ExternalEventSender<TYPES>,
+    validation_level: ValidationLevel,
 ) -> Result<()> {
     let view_number = proposal.data.view_number();
 
-    let proposed_leaf = Leaf::from_quorum_proposal(&proposal.data);
+    // Hashing the proposed leaf and checking the leader's signature on it are CPU-bound, so do
+    // them off the executor.
+    let validation = {
+        let proposal = proposal.clone();
+        let view_leader_key = view_leader_key.clone();
+        spawn_blocking(move || {
+            let leaf = Leaf::from_quorum_proposal(&proposal.data);
+            let commitment = leaf.commit();
+            let is_valid = view_leader_key.validate(&proposal.signature, commitment.as_ref());
+            (leaf, commitment, is_valid)
+        })
+        .await
+    };
+    #[cfg(async_executor_impl = "tokio")]
+    let validation = validation.unwrap();
+    let (proposed_leaf, leaf_commitment, signature_is_valid) = validation;
     ensure!(
         proposed_leaf.parent_commitment() == parent_leaf.commit(),
         "Proposed leaf does not extend the parent leaf."
     );
+    validate_header_app_version(&proposal.data, &decided_upgrade_certificate)?;
+
+    verify_saved_payload(&proposed_leaf, &consensus, vid_membership.total_nodes()).await?;
 
     let state = Arc::new(
         <TYPES::ValidatedState as ValidatedState<TYPES>>::from_header(&proposal.data.block_header),
     );
     let view = View {
         view_inner: ViewInner::Leaf {
-            leaf: proposed_leaf.commit(),
+            leaf: leaf_commitment,
             state,
             delta: None, // May be updated to `Some` in the vote task.
         },
@@ -102,6 +166,15 @@ pub async fn validate_proposal_safety_and_liveness<TYPES: NodeType>(
         .write()
         .await
         .update_saved_leaves(proposed_leaf.clone());
+    let pruned = consensus.write().await.prune_undecided();
+    if let Some(pruned_view) = pruned {
+        warn!("Pruned the undecided views up to {pruned_view:?}, as too many are undecided");
+        broadcast_event(
+            Arc::new(HotShotEvent::UndecidedPruned(pruned_view)),
+            &event_stream,
+        )
+        .await;
+    }
 
     // Broadcast that we've updated our consensus state so that other tasks know it's safe to grab.
     broadcast_event(
@@ -117,10 +190,11 @@ pub async fn validate_proposal_safety_and_liveness<TYPES: NodeType>(
     //    proposal.validate_signature(&quorum_membership)?;
     //
     // in a future PR.
-    ensure!(
-        view_leader_key.validate(&proposal.signature, proposed_leaf.commit().as_ref()),
-        "Could not verify proposal."
-    );
+    ensure!(signature_is_valid, "Could not verify proposal.");
+    consensus
+        .write()
+        .await
+        .update_proposers(view_number, view_leader_key);
 
     UpgradeCertificate::validate(&proposal.data.upgrade_certificate, &quorum_membership)?;
 
@@ -128,29 +202,12 @@ pub async fn validate_proposal_safety_and_liveness<TYPES: NodeType>(
     proposed_leaf.extends_upgrade(&parent_leaf, &decided_upgrade_certificate)?;
 
     let justify_qc = proposal.data.justify_qc.clone();
-    // Create a positive vote if either liveness or safety check
-    // passes.
-
-    // Liveness check.
     let read_consensus = consensus.read().await;
-    let liveness_check = justify_qc.view_number() > read_consensus.locked_view();
+    let checks =
+        check_safety_and_liveness(&read_consensus, justify_qc.view_number(), validation_level);
 
-    // Safety check.
-    // Check if proposal extends from the locked leaf.
-    let outcome = read_consensus.visit_leaf_ancestors(
-        justify_qc.view_number(),
-        Terminator::Inclusive(read_consensus.locked_view()),
-        false,
-        |leaf, _, _| {
-            // if leaf view no == locked view no then we're done, report success by
-            // returning true
-            leaf.view_number() != read_consensus.locked_view()
-        },
-    );
-    let safety_check = outcome.is_ok();
-
-    ensure!(safety_check || liveness_check, {
-        if let Err(e) = outcome {
+    ensure!(checks.accepted(), {
+        if let Some(Err(e)) = checks.safety {
             broadcast_event(
                 Event {
                     view_number,
@@ -193,10 +250,9 @@ pub async fn validate_proposal_safety_and_liveness<TYPES: NodeType>(
 /// Create the header for a proposal, build the proposal, and broadcast
 /// the proposal send evnet.
 #[allow(clippy::too_many_arguments)]
-#[cfg(not(feature = "dependency-tasks"))]
 pub async fn create_and_send_proposal<TYPES: NodeType>(
     public_key: TYPES::SignatureKey,
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     consensus: Arc<RwLock<Consensus<TYPES>>>,
     event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
     view: TYPES::Time,
@@ -252,12 +308,19 @@ pub async fn create_and_send_proposal<TYPES: NodeType>(
         return;
     }
 
-    let Ok(signature) = TYPES::SignatureKey::sign(&private_key, proposed_leaf.commit().as_ref())
-    else {
+    let Ok(signature) = signer.sign(proposed_leaf.commit().as_ref()).await else {
         // This should never happen.
         error!("Failed to sign proposed_leaf.commit()!");
         return;
     };
+    let parent_da = parent_da_certificate(
+        &proposal,
+        &parent_leaf,
+        &consensus,
+        signer.as_ref(),
+        version,
+    )
+    .await;
 
     let message = Proposal {
         data: proposal,
@@ -277,6 +340,13 @@ pub async fn create_and_send_proposal<TYPES: NodeType>(
         return;
     }
     async_sleep(Duration::from_millis(round_start_delay)).await;
+    if let Some(parent_da) = parent_da {
+        broadcast_event(
+            Arc::new(HotShotEvent::ParentDaCertificateSend(view, parent_da)),
+            &event_stream,
+        )
+        .await;
+    }
     broadcast_event(
         Arc::new(HotShotEvent::QuorumProposalSend(
             message.clone(),
@@ -287,6 +357,65 @@ pub async fn create_and_send_proposal<TYPES: NodeType>(
     .await;
 }
 
+/// The DA certificate for the view of `parent_leaf`, signed together with `proposal`, for the
+/// leader to attach to the proposal. We only attach it once the network has upgraded, i.e. if
+/// `version`, the version of the proposal's view, isn't the base version, and only if we have a
+/// DA certificate certifying the parent's payload.
+pub async fn parent_da_certificate<TYPES: NodeType>(
+    proposal: &QuorumProposal<TYPES>,
+    parent_leaf: &Leaf<TYPES>,
+    consensus: &RwLock<Consensus<TYPES>>,
+    signer: &dyn Signer<TYPES::SignatureKey>,
+    version: Version,
+) -> Option<ParentDaCertificate<TYPES>> {
+    if version == Base::VERSION {
+        return None;
+    }
+    let certificate = consensus
+        .read()
+        .await
+        .saved_da_certs()
+        .get(&parent_leaf.view_number())
+        .filter(|cert| cert.date().payload_commit == parent_leaf.payload_commitment())
+        .cloned()?;
+    ParentDaCertificate::new(proposal, certificate, signer)
+        .await
+        .inspect_err(|e| error!("Failed to sign the parent DA certificate: {e}"))
+        .ok()
+}
+
+/// Checks the DA certificate the leader attached to `proposal` for the view of its parent, and
+/// saves it, so we don't have to wait for the parent's DA certificate separately. If we have the
+/// parent's leaf, the certificate must certify its payload too.
+///
+/// # Errors
+/// If the attached certificate is invalid, in which case the proposal is rejected.
+pub async fn save_parent_da_certificate<TYPES: NodeType>(
+    proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
+    parent_da: &ParentDaCertificate<TYPES>,
+    quorum_membership: &TYPES::Membership,
+    da_membership: &TYPES::Membership,
+    consensus: &RwLock<Consensus<TYPES>>,
+) -> Result<()> {
+    let leader = quorum_membership.leader(proposal.data.view_number());
+    parent_da.verify(&proposal.data, &leader, da_membership)?;
+
+    let certificate = &parent_da.certificate;
+    let mut consensus = consensus.write().await;
+    if let Some(parent_leaf) = consensus
+        .saved_leaves()
+        .get(&proposal.data.justify_qc.date().leaf_commit)
+    {
+        ensure!(
+            certificate.date().payload_commit == parent_leaf.payload_commitment(),
+            "DA certificate for view {:?} does not certify the parent's payload",
+            certificate.view_number
+        );
+    }
+    consensus.update_saved_da_certs(certificate.view_number, certificate.clone());
+    Ok(())
+}
+
 /// Validates, from a given `proposal` that the view that it is being submitted for is valid when
 /// compared to `cur_view` which is the highest proposed view (so far) for the caller. If the proposal
 /// is for a view that's later than expected, that the proposal includes a timeout or view sync certificate.
@@ -355,6 +484,142 @@ pub fn validate_proposal_view_and_certs<TYPES: NodeType>(
     Ok(())
 }
 
+/// Validates the parent of a proposal for `proposal_view` under the version of the parent's own
+/// view. A proposal for the first views of a new version may extend a parent proposed under the
+/// old one, and that parent must still have followed the old version's rules: in particular, a
+/// parent proposed while we were between versions must carry a null block.
+///
+/// # Errors
+/// If the parent is from a later version than the proposal, or breaks the rules of its version.
+pub fn validate_parent_version<TYPES: NodeType>(
+    parent_leaf: &Leaf<TYPES>,
+    proposal_view: TYPES::Time,
+    decided_upgrade_certificate: &Option<UpgradeCertificate<TYPES>>,
+    vid_membership: &TYPES::Membership,
+) -> Result<()> {
+    let parent_view = parent_leaf.view_number();
+    let parent_version = version_for_view(parent_view, decided_upgrade_certificate)?;
+    let proposal_version = version_for_view(proposal_view, decided_upgrade_certificate)?;
+    if parent_version == proposal_version {
+        return Ok(());
+    }
+
+    ensure!(
+        parent_version < proposal_version,
+        "Proposal for view {} at version {proposal_version} extends a parent from a later version {parent_version}",
+        *proposal_view
+    );
+    if let Some(cert) = decided_upgrade_certificate {
+        ensure!(
+            !cert.upgrading_in(parent_view)
+                || Some(parent_leaf.payload_commitment())
+                    == null_block::commitment(vid_membership.total_nodes()),
+            "Parent for view {} was proposed between versions without a null block",
+            *parent_view
+        );
+    }
+
+    Ok(())
+}
+
+/// Validates that the header of a proposal was built under the application version in effect for
+/// the proposal's view, so that header formats don't get mixed across an upgrade. Headers which
+/// don't record a version aren't checked.
+///
+/// # Errors
+/// If the header records a version other than the one in effect for the proposal's view.
+pub fn validate_header_app_version<TYPES: NodeType>(
+    proposal: &QuorumProposal<TYPES>,
+    decided_upgrade_certificate: &Option<UpgradeCertificate<TYPES>>,
+) -> Result<()> {
+    let Some(app_version) = proposal.block_header.app_version() else {
+        return Ok(());
+    };
+    let expected_version = version_for_view(proposal.view_number, decided_upgrade_certificate)?;
+    ensure!(
+        app_version == expected_version,
+        "Proposal for view {} has a header for app version {app_version}, but expected {expected_version}",
+        *proposal.view_number
+    );
+
+    Ok(())
+}
+
+/// Validates a contiguous run of `proposals` received while catching up, in a single pass and
+/// without reading consensus state. Each proposal must extend the one before it, the first
+/// extending `starting_parent`, and must carry a valid QC for its parent and the signature of its
+/// view's leader.
+///
+/// Validation stops at the first proposal that is invalid or doesn't extend its predecessor, and
+/// the leaves of the valid prefix are returned.
+///
+/// # Errors
+/// If the first proposal is invalid, so that no prefix of the chain is valid.
+pub fn validate_proposal_chain<TYPES: NodeType>(
+    proposals: &[Proposal<TYPES, QuorumProposal<TYPES>>],
+    starting_parent: &Leaf<TYPES>,
+    quorum_membership: &TYPES::Membership,
+) -> Result<Vec<Leaf<TYPES>>> {
+    let mut leaves: Vec<Leaf<TYPES>> = Vec::with_capacity(proposals.len());
+    for proposal in proposals {
+        let parent = leaves.last().unwrap_or(starting_parent);
+        match validate_chain_link(proposal, parent, quorum_membership) {
+            Ok(leaf) => leaves.push(leaf),
+            Err(e) if leaves.is_empty() => return Err(e),
+            Err(e) => {
+                debug!(
+                    "Proposal chain broken at view {:?}: {e:#}",
+                    proposal.data.view_number()
+                );
+                break;
+            }
+        }
+    }
+    Ok(leaves)
+}
+
+/// Validates that `proposal` directly extends `parent`, returning the proposed leaf.
+fn validate_chain_link<TYPES: NodeType>(
+    proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
+    parent: &Leaf<TYPES>,
+    quorum_membership: &TYPES::Membership,
+) -> Result<Leaf<TYPES>> {
+    let view = proposal.data.view_number();
+    let justify_qc = &proposal.data.justify_qc;
+    ensure!(
+        view > parent.view_number(),
+        "Proposal for view {} does not come after its parent's view {}",
+        *view,
+        *parent.view_number()
+    );
+    ensure!(
+        justify_qc.view_number() == parent.view_number(),
+        "Proposal for view {} does not justify its parent",
+        *view
+    );
+
+    let leaf = Leaf::from_quorum_proposal(&proposal.data);
+    ensure!(
+        leaf.parent_commitment() == parent.commit(),
+        "Proposal for view {} does not extend its parent",
+        *view
+    );
+    ensure!(
+        justify_qc.is_valid_cert(quorum_membership),
+        "Proposal for view {} has an invalid QC",
+        *view
+    );
+    ensure!(
+        quorum_membership
+            .leader(view)
+            .validate(&proposal.signature, leaf.commit().as_ref()),
+        "Proposal for view {} is not signed by its leader",
+        *view
+    );
+
+    Ok(leaf)
+}
+
 /// Gets the parent leaf and state from the parent of a proposal, returning an [`anyhow::Error`] if not.
 pub(crate) async fn parent_leaf_and_state<TYPES: NodeType>(
     next_proposal_view_number: TYPES::Time,
@@ -421,7 +686,7 @@ pub async fn publish_proposal_from_commitment_and_metadata<TYPES: NodeType>(
     sender: Sender<Arc<HotShotEvent<TYPES>>>,
     quorum_membership: Arc<TYPES::Membership>,
     public_key: TYPES::SignatureKey,
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     consensus: Arc<RwLock<Consensus<TYPES>>>,
     delay: u64,
     formed_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
@@ -477,7 +742,7 @@ pub async fn publish_proposal_from_commitment_and_metadata<TYPES: NodeType>(
     let create_and_send_proposal_handle = async_spawn(async move {
         create_and_send_proposal(
             public_key,
-            private_key,
+            signer,
             consensus,
             sender,
             view,
@@ -505,7 +770,7 @@ pub async fn publish_proposal_if_able<TYPES: NodeType>(
     sender: Sender<Arc<HotShotEvent<TYPES>>>,
     quorum_membership: Arc<TYPES::Membership>,
     public_key: TYPES::SignatureKey,
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     consensus: Arc<RwLock<Consensus<TYPES>>>,
     delay: u64,
     formed_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
@@ -520,7 +785,7 @@ pub async fn publish_proposal_if_able<TYPES: NodeType>(
         sender,
         quorum_membership,
         public_key,
-        private_key,
+        signer,
         consensus,
         delay,
         formed_upgrade_certificate,
@@ -533,6 +798,29 @@ pub async fn publish_proposal_if_able<TYPES: NodeType>(
     .await
 }
 
+/// Record that we're voting on the proposal for `view` with only its liveness checked, as its
+/// parent is missing, and tell the application that our validation is degraded.
+pub(crate) async fn report_liveness_only_vote<TYPES: NodeType>(
+    view: TYPES::Time,
+    consensus: &RwLock<Consensus<TYPES>>,
+    output_event_stream: &ExternalEventSender<TYPES>,
+) {
+    consensus
+        .read()
+        .await
+        .metrics
+        .liveness_only_vote_count
+        .add(1);
+    broadcast_event(
+        Event {
+            view_number: view,
+            event: EventType::DegradedValidation { view },
+        },
+        output_event_stream,
+    )
+    .await;
+}
+
 /// Trigger a request to the network for a proposal for a view and wait for the response
 pub(crate) async fn fetch_proposal<TYPES: NodeType>(
     view: TYPES::Time,
@@ -559,7 +847,11 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType>(
     if !justify_qc.is_valid_cert(quorum_membership.as_ref()) {
         bail!("Invalid justify_qc in proposal for view {}", *view_number);
     }
+    proposal
+        .validate_signature(&quorum_membership)
+        .context("Fetched proposal is not signed by its view's leader")?;
     let mut consensus_write = consensus.write().await;
+    consensus_write.update_proposers(view_number, quorum_membership.leader(view_number));
     let leaf = Leaf::from_quorum_proposal(&proposal.data);
     let state = Arc::new(
         <TYPES::ValidatedState as ValidatedState<TYPES>>::from_header(&proposal.data.block_header),
@@ -585,6 +877,26 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType>(
     Ok(leaf)
 }
 
+/// Update the high QC to `high_qc` if it's newer than the current one, announcing the transition
+/// with [`HotShotEvent::HighQcUpdated`].
+///
+/// # Errors
+/// If `high_qc` isn't newer than the current high QC, in which case nothing is announced.
+pub async fn update_high_qc<TYPES: NodeType>(
+    consensus: &RwLock<Consensus<TYPES>>,
+    high_qc: QuorumCertificate<TYPES>,
+    event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+) -> Result<()> {
+    let to = high_qc.view_number;
+    let from = consensus.write().await.update_high_qc(high_qc)?;
+    broadcast_event(
+        Arc::new(HotShotEvent::HighQcUpdated { from, to }),
+        event_stream,
+    )
+    .await;
+    Ok(())
+}
+
 /// Handle the received quorum proposal.
 ///
 /// Returns the proposal that should be used to set the `cur_proposal` for other tasks.
@@ -603,8 +915,6 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
         *proposal.data.view_number
     );
 
-    let cur_view = task_state.cur_view;
-
     validate_proposal_view_and_certs(
         proposal,
         &sender,
@@ -635,7 +945,8 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
         &mut task_state.timeout_task,
         &task_state.output_event_stream,
         SEND_VIEW_CHANGE_EVENT,
-        task_state.quorum_membership.leader(cur_view) == task_state.public_key,
+        task_state.quorum_membership.as_ref(),
+        &task_state.public_key,
     )
     .await
     {
@@ -675,6 +986,17 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
         None => None,
     };
 
+    // The parent may be from before an upgrade took effect, in which case it's held to the
+    // rules of its own version rather than the proposal's.
+    if let Some((ref leaf, _)) = parent {
+        validate_parent_version(
+            leaf,
+            view,
+            &task_state.decided_upgrade_cert,
+            task_state.vid_membership.as_ref(),
+        )?;
+    }
+
     if justify_qc.view_number() > consensus_read.high_qc().view_number {
         if let Err(e) = task_state
             .storage
@@ -688,11 +1010,11 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
     }
 
     drop(consensus_read);
-    let mut consensus_write = task_state.consensus.write().await;
 
-    if let Err(e) = consensus_write.update_high_qc(justify_qc.clone()) {
+    if let Err(e) = update_high_qc(&task_state.consensus, justify_qc.clone(), &event_stream).await {
         tracing::trace!("{e:?}");
     }
+    let mut consensus_write = task_state.consensus.write().await;
 
     // Justify qc's leaf commitment is not the same as the parent's leaf commitment, but it should be (in this case)
     let Some((parent_leaf, _parent_state)) = parent else {
@@ -721,6 +1043,9 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
             tracing::trace!("{e:?}");
         }
 
+        if view_leader_key.validate(&proposal.signature, leaf.commit().as_ref()) {
+            consensus_write.update_proposers(view, view_leader_key.clone());
+        }
         consensus_write.update_saved_leaves(leaf.clone());
         let new_leaves = consensus_write.saved_leaves().clone();
         let new_state = consensus_write.validated_state_map().clone();
@@ -750,6 +1075,12 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
 
             let mut current_proposal = None;
             if liveness_check {
+                report_liveness_only_vote(
+                    view,
+                    &task_state.consensus,
+                    &task_state.output_event_stream,
+                )
+                .await;
                 current_proposal = Some(proposal.data.clone());
                 let new_view = proposal.data.view_number + 1;
 
@@ -769,7 +1100,7 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
                         event_stream,
                         Arc::clone(&task_state.quorum_membership),
                         task_state.public_key.clone(),
-                        task_state.private_key.clone(),
+                        Arc::clone(&task_state.signer),
                         Arc::clone(&task_state.consensus),
                         task_state.round_start_delay,
                         task_state.formed_upgrade_certificate.clone(),
@@ -809,16 +1140,73 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
                 Arc::clone(&task_state.consensus),
                 task_state.decided_upgrade_cert.clone(),
                 Arc::clone(&task_state.quorum_membership),
+                Arc::clone(&task_state.vid_membership),
                 view_leader_key,
                 event_stream.clone(),
                 sender,
                 task_state.output_event_stream.clone(),
+                task_state.validation_level,
             )
             .map(AnyhowTracing::err_as_debug),
         ));
     Ok(None)
 }
 
+/// Outcome of the liveness and safety checks on a proposal's justify QC.
+#[derive(Debug)]
+pub struct SafetyAndLiveness<TYPES: NodeType> {
+    /// The validation level the checks were run at.
+    pub validation_level: ValidationLevel,
+    /// Whether the justify QC is newer than our locked view.
+    pub liveness: bool,
+    /// The result of walking back from the justify QC to the locked leaf, or `None` if the
+    /// validation level skips the safety check.
+    pub safety: Option<std::result::Result<(), HotShotError<TYPES>>>,
+}
+
+impl<TYPES: NodeType> SafetyAndLiveness<TYPES> {
+    /// Whether the proposal passes at the validation level the checks were run at.
+    #[must_use]
+    pub fn accepted(&self) -> bool {
+        self.validation_level.accepts(
+            self.liveness,
+            self.safety.as_ref().map(std::result::Result::is_ok),
+        )
+    }
+}
+
+/// Run the liveness check and, unless `validation_level` skips it, the safety check for a
+/// proposal whose justify QC is for `justify_qc_view`.
+pub fn check_safety_and_liveness<TYPES: NodeType>(
+    consensus: &Consensus<TYPES>,
+    justify_qc_view: TYPES::Time,
+    validation_level: ValidationLevel,
+) -> SafetyAndLiveness<TYPES> {
+    // Liveness check.
+    let liveness = justify_qc_view > consensus.locked_view();
+
+    // Safety check.
+    // Check if proposal extends from the locked leaf.
+    let safety = validation_level.checks_safety().then(|| {
+        consensus.visit_leaf_ancestors(
+            justify_qc_view,
+            Terminator::Inclusive(consensus.locked_view()),
+            false,
+            |leaf, _, _| {
+                // if leaf view no == locked view no then we're done, report success by
+                // returning true
+                leaf.view_number() != consensus.locked_view()
+            },
+        )
+    });
+
+    SafetyAndLiveness {
+        validation_level,
+        liveness,
+        safety,
+    }
+}
+
 /// Helper type to give names and to the output values of the leaf chain traversal operation.
 #[derive(Debug)]
 pub struct LeafChainTraversalOutcome<TYPES: NodeType> {
@@ -980,12 +1368,16 @@ pub async fn decide_from_proposal<TYPES: NodeType>(
                     .cloned()
                     .map(|prop| prop.data);
 
+                // The signer we verified when we accepted the leaf's proposal, if we did.
+                let proposer = consensus_reader.proposer(leaf.view_number()).cloned();
+
                 // Add our data into a new `LeafInfo`
                 res.leaf_views.push(LeafInfo::new(
                     leaf.clone(),
                     Arc::clone(&state),
                     delta.clone(),
                     vid_share,
+                    proposer,
                 ));
                 res.leaves_decided.push(leaf.clone());
                 if let Some(ref payload) = leaf.block_payload() {
@@ -1078,7 +1470,22 @@ pub async fn handle_quorum_proposal_validated<TYPES: NodeType, I: NodeImplementa
 
     #[allow(clippy::cast_precision_loss)]
     if let Some(new_anchor_view) = res.new_decided_view_number {
+        // Report how long each of our proposals decided here took, and forget those decided.
+        let now = Instant::now();
+        let metrics = Arc::clone(&task_state.consensus.read().await.metrics);
+        for info in &res.leaf_views {
+            if let Some(sent) = task_state.proposal_send_times.get(&info.leaf.view_number()) {
+                metrics
+                    .proposal_to_decide_ms
+                    .add_point(now.duration_since(*sent).as_millis() as f64);
+            }
+        }
+        task_state.proposal_send_times = task_state
+            .proposal_send_times
+            .split_off(&(new_anchor_view + 1));
+
         let block_size = res.included_txns.map(|set| set.len().try_into().unwrap());
+        let decided_view_count = res.leaf_views.len();
         let decide_sent = broadcast_event(
             Event {
                 view_number: new_anchor_view,
@@ -1086,12 +1493,19 @@ pub async fn handle_quorum_proposal_validated<TYPES: NodeType, I: NodeImplementa
                     leaf_chain: Arc::new(res.leaf_views),
                     qc: Arc::new(res.new_decide_qc.unwrap()),
                     block_size,
+                    decided_view_count,
                 },
             },
             &task_state.output_event_stream,
         );
         let mut consensus = task_state.consensus.write().await;
 
+        // Never emit a second `Decide` for a view, e.g. if this event was handled twice.
+        let emit_decide = consensus
+            .update_last_emitted_decide_view(new_anchor_view)
+            .inspect_err(|e| warn!("Suppressing duplicate decide; error = {e:?}"))
+            .is_ok();
+
         let old_anchor_view = consensus.last_decided_view();
         consensus.collect_garbage(old_anchor_view, new_anchor_view);
         if let Err(e) = consensus.update_last_decided_view(new_anchor_view) {
@@ -1119,7 +1533,9 @@ pub async fn handle_quorum_proposal_validated<TYPES: NodeType, I: NodeImplementa
         );
         drop(consensus);
         debug!("Decided txns len {:?}", block_size);
-        decide_sent.await;
+        if emit_decide {
+            decide_sent.await;
+        }
         broadcast_event(
             Arc::new(HotShotEvent::LeafDecided(res.leaves_decided)),
             &event_stream,
@@ -1131,11 +1547,11 @@ pub async fn handle_quorum_proposal_validated<TYPES: NodeType, I: NodeImplementa
     Ok(())
 }
 
-/// Private key, latest decided upgrade certificate, committee membership, and event stream, for
+/// Signer, latest decided upgrade certificate, committee membership, and event stream, for
 /// sending the vote.
 #[cfg(not(feature = "dependency-tasks"))]
 type VoteInfo<TYPES> = (
-    <<TYPES as NodeType>::SignatureKey as SignatureKey>::PrivateKey,
+    Arc<dyn Signer<<TYPES as NodeType>::SignatureKey>>,
     Option<UpgradeCertificate<TYPES>>,
     Arc<<TYPES as NodeType>::Membership>,
     Sender<Arc<HotShotEvent<TYPES>>>,
@@ -1154,14 +1570,15 @@ pub async fn update_state_and_vote_if_able<TYPES: NodeType, I: NodeImplementatio
     consensus: Arc<RwLock<Consensus<TYPES>>>,
     storage: Arc<RwLock<I::Storage>>,
     quorum_membership: Arc<TYPES::Membership>,
+    vid_membership: Arc<TYPES::Membership>,
     instance_state: Arc<TYPES::InstanceState>,
     vote_info: VoteInfo<TYPES>,
     version: Version,
 ) -> bool {
     use hotshot_types::simple_vote::QuorumVote;
 
-    if !quorum_membership.has_stake(&public_key) {
-        debug!("We were not chosen for quorum committee on {:?}", cur_view);
+    if quorum_membership.is_observer(&public_key) {
+        debug!("We are an observer, so we don't vote on {:?}", cur_view);
         return false;
     }
 
@@ -1182,9 +1599,9 @@ pub async fn update_state_and_vote_if_able<TYPES: NodeType, I: NodeImplementatio
     if let Some(upgrade_cert) = &vote_info.1 {
         if upgrade_cert.upgrading_in(cur_view)
             && Some(proposal.block_header.payload_commitment())
-                != null_block::commitment(quorum_membership.total_nodes())
+                != null_block::commitment(vid_membership.total_nodes())
         {
-            info!("Refusing to vote on proposal because it does not have a null commitment, and we are between versions. Expected:\n\n{:?}\n\nActual:{:?}", null_block::commitment(quorum_membership.total_nodes()), Some(proposal.block_header.payload_commitment()));
+            info!("Refusing to vote on proposal because it does not have a null commitment, and we are between versions. Expected:\n\n{:?}\n\nActual:{:?}", null_block::commitment(vid_membership.total_nodes()), Some(proposal.block_header.payload_commitment()));
             return false;
         }
     }
@@ -1266,14 +1683,16 @@ pub async fn update_state_and_vote_if_able<TYPES: NodeType, I: NodeImplementatio
             );
             return false;
         }
-        if let Ok(vote) = QuorumVote::<TYPES>::create_signed_vote(
+        if let Ok(vote) = QuorumVote::<TYPES>::create_vote_with_signer(
             QuorumData {
                 leaf_commit: proposed_leaf.commit(),
             },
             view,
             &public_key,
-            &vote_info.0,
-        ) {
+            vote_info.0.as_ref(),
+        )
+        .await
+        {
             GeneralConsensusMessage::<TYPES>::Vote(vote)
         } else {
             error!("Unable to sign quorum vote!");
@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, sync::Arc};
+use std::{collections::BTreeMap, sync::Arc, time::Instant};
 
 use anyhow::Result;
 use async_broadcast::{Receiver, Sender};
@@ -17,9 +17,10 @@ use hotshot_types::{
     traits::{
         election::Membership,
         node_implementation::{NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
     },
     vote::HasViewNumber,
+    ValidationLevel,
 };
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::JoinHandle;
@@ -29,21 +30,26 @@ use vbs::version::Version;
 use {
     crate::consensus::helpers::{
         handle_quorum_proposal_recv, handle_quorum_proposal_validated, publish_proposal_if_able,
-        update_state_and_vote_if_able,
+        save_parent_da_certificate, update_state_and_vote_if_able,
     },
+    crate::helpers::{is_valid_vid_share, valid_vid_shares},
     async_compatibility_layer::art::async_spawn,
     hotshot_types::data::VidDisperseShare,
     hotshot_types::message::Proposal,
-    hotshot_types::vid::vid_scheme,
-    hotshot_types::{traits::storage::Storage, vote::Certificate},
-    jf_vid::VidScheme,
+    hotshot_types::{
+        traits::{node_implementation::ConsensusTime, storage::Storage},
+        vote::Certificate,
+    },
     tracing::info,
 };
 
 use crate::{
     consensus::view_change::{update_view, DONT_SEND_VIEW_CHANGE_EVENT},
     events::{HotShotEvent, HotShotTaskCompleted},
-    helpers::{broadcast_event, cancel_task},
+    helpers::{
+        broadcast_event, cancel_task, gate_paused_events, ExternalEventSender, PausedEvents,
+        SeenVidShares,
+    },
     vote_collection::{
         create_vote_accumulator, AccumulatorInfo, HandleVoteEvent, VoteCollectionTaskState,
     },
@@ -51,6 +57,11 @@ use crate::{
 
 /// Helper functions to handle proposal-related functionality.
 pub(crate) mod helpers;
+pub use helpers::{
+    check_safety_and_liveness, create_and_send_proposal, update_high_qc,
+    validate_header_app_version, validate_parent_version, validate_proposal_chain,
+    SafetyAndLiveness,
+};
 
 /// Handles view-change related functionality.
 pub(crate) mod view_change;
@@ -63,8 +74,8 @@ type VoteCollectorOption<TYPES, VOTE, CERT> = Option<VoteCollectionTaskState<TYP
 pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Our public key
     pub public_key: TYPES::SignatureKey,
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// Reference to consensus. The replica will require a write lock on this.
     pub consensus: Arc<RwLock<Consensus<TYPES>>>,
     /// Immutable instance state
@@ -94,6 +105,9 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Membership for Quorum Certs/votes
     pub quorum_membership: Arc<TYPES::Membership>,
 
+    /// Membership of the VID storage nodes, for verifying VID shares and commitments
+    pub vid_membership: Arc<TYPES::Membership>,
+
     /// Membership for DA committee Votes/certs
     pub da_membership: Arc<TYPES::Membership>,
 
@@ -130,12 +144,15 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     pub version: Arc<RwLock<Version>>,
 
     /// Output events to application
-    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+    pub output_event_stream: ExternalEventSender<TYPES>,
 
     /// The most recent proposal we have, will correspond to the current view if Some()
     /// Will be none if the view advanced through timeout/view_sync
     pub current_proposal: Option<QuorumProposal<TYPES>>,
 
+    /// VID shares already validated for the recent views
+    pub seen_vid_shares: SeenVidShares<TYPES>,
+
     // ED Should replace this with config information since we need it anyway
     /// The node's id
     pub id: u64,
@@ -145,6 +162,16 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
 
     /// an upgrade certificate that has been decided on, if any
     pub decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
+
+    /// How thoroughly incoming proposals are validated
+    pub validation_level: ValidationLevel,
+
+    /// When we sent each of our proposals which isn't decided yet, to report how long it takes
+    /// to decide
+    pub proposal_send_times: BTreeMap<TYPES::Time, Instant>,
+
+    /// Events received while paused, to handle once resumed; `None` while running
+    pub paused_events: Option<PausedEvents<TYPES>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I> {
@@ -163,47 +190,55 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
     /// Validate the VID disperse is correctly signed and has the correct share.
     #[cfg(not(feature = "dependency-tasks"))]
     fn validate_disperse(&self, disperse: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> bool {
-        let view = disperse.data.view_number();
-        let payload_commitment = disperse.data.payload_commitment;
-
-        // Check whether the data satisfies one of the following.
-        // * From the right leader for this view.
-        // * Calculated and signed by the current node.
-        // * Signed by one of the staked DA committee members.
-        if !self
-            .quorum_membership
-            .leader(view)
-            .validate(&disperse.signature, payload_commitment.as_ref())
-            && !self
-                .public_key
-                .validate(&disperse.signature, payload_commitment.as_ref())
-        {
-            let mut validated = false;
-            for da_member in self.da_membership.staked_committee(view) {
-                if da_member.validate(&disperse.signature, payload_commitment.as_ref()) {
-                    validated = true;
-                    break;
-                }
-            }
-            if !validated {
-                return false;
-            }
-        }
+        is_valid_vid_share(
+            disperse,
+            &self.public_key,
+            &self.quorum_membership,
+            &self.da_membership,
+            self.vid_membership.total_nodes(),
+        )
+    }
 
-        // Validate the VID share.
-        if vid_scheme(self.quorum_membership.total_nodes())
-            .verify_share(
-                &disperse.data.share,
-                &disperse.data.common,
-                &payload_commitment,
-            )
-            .is_err()
-        {
-            debug!("Invalid VID share.");
-            return false;
+    /// Whether a VID share for `view` is recent enough to keep.
+    #[cfg(not(feature = "dependency-tasks"))]
+    fn is_recent_disperse(&self, view: TYPES::Time) -> bool {
+        // Allow VID disperse date that is one view older, in case we have updated the
+        // view.
+        // Adding `+ 1` on the LHS rather than `- 1` on the RHS, to avoid the overflow
+        // error due to subtracting the genesis view number.
+        view + 1 >= self.cur_view
+    }
+
+    /// Store a validated VID share, and vote if it completes what we need to for its view.
+    #[cfg(not(feature = "dependency-tasks"))]
+    async fn handle_valid_disperse(
+        &mut self,
+        disperse: &Proposal<TYPES, VidDisperseShare<TYPES>>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        // Shares more than one view older are thrown away on receipt, so stop tracking them.
+        self.seen_vid_shares
+            .prune_before(TYPES::Time::new(self.cur_view.saturating_sub(1)));
+        if !self.seen_vid_shares.insert(disperse) {
+            debug!("Dropping a duplicate VID share");
+            return;
         }
 
-        true
+        let view = disperse.data.view_number();
+        self.consensus
+            .write()
+            .await
+            .update_vid_shares(view, disperse.clone());
+        if disperse.data.recipient_key != self.public_key {
+            return;
+        }
+        let Some(proposal) = self.current_proposal.clone() else {
+            return;
+        };
+        if proposal.view_number() != view {
+            return;
+        }
+        self.spawn_vote_task(view, event_stream.clone()).await;
     }
 
     #[cfg(not(feature = "dependency-tasks"))]
@@ -218,7 +253,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
             event_stream,
             Arc::clone(&self.quorum_membership),
             self.public_key.clone(),
-            self.private_key.clone(),
+            Arc::clone(&self.signer),
             Arc::clone(&self.consensus),
             self.round_start_delay,
             self.formed_upgrade_certificate.clone(),
@@ -254,10 +289,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
         }
         let upgrade = self.decided_upgrade_cert.clone();
         let pub_key = self.public_key.clone();
-        let priv_key = self.private_key.clone();
+        let signer = Arc::clone(&self.signer);
         let consensus = Arc::clone(&self.consensus);
         let storage = Arc::clone(&self.storage);
         let quorum_mem = Arc::clone(&self.quorum_membership);
+        let vid_mem = Arc::clone(&self.vid_membership);
         let da_mem = Arc::clone(&self.da_membership);
         let instance_state = Arc::clone(&self.instance_state);
         let version = *self.version.read().await;
@@ -269,8 +305,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
                 consensus,
                 storage,
                 quorum_mem,
+                vid_mem,
                 instance_state,
-                (priv_key, upgrade, da_mem, event_stream),
+                (signer, upgrade, da_mem, event_stream),
                 version,
             )
             .await;
@@ -318,7 +355,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
                     warn!("Failed to handle QuorumProposalValidated event {e:#}");
                 }
             }
-            HotShotEvent::QuorumVoteRecv(ref vote) => {
+            HotShotEvent::QuorumVoteRecv(ref vote)
+            | HotShotEvent::ExtendedQuorumVoteRecv(ref vote, _) => {
                 debug!("Received quorum vote: {:?}", vote.view_number());
                 if self.quorum_membership.leader(vote.view_number() + 1) != self.public_key {
                     error!(
@@ -358,6 +396,19 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
                     }
                 }
             }
+            HotShotEvent::QuorumVoteExtensions(view_number, extensions) => {
+                broadcast_event(
+                    Event {
+                        view_number: *view_number,
+                        event: EventType::QuorumVoteExtensions {
+                            view_number: *view_number,
+                            extensions: extensions.clone(),
+                        },
+                    },
+                    &self.output_event_stream,
+                )
+                .await;
+            }
             HotShotEvent::TimeoutVoteRecv(ref vote) => {
                 if self.timeout_membership.leader(vote.view_number() + 1) != self.public_key {
                     error!(
@@ -419,7 +470,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
                         error!("Failed to store High QC of QC we formed. Error: {:?}", e);
                     }
 
-                    if let Err(e) = self.consensus.write().await.update_high_qc(qc.clone()) {
+                    if let Err(e) = update_high_qc(&self.consensus, qc.clone(), &event_stream).await
+                    {
                         tracing::error!("{e:?}");
                     }
                     debug!(
@@ -466,6 +518,44 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
                 self.spawn_vote_task(view, event_stream).await;
             }
             #[cfg(not(feature = "dependency-tasks"))]
+            HotShotEvent::QuorumProposalWithParentDaRecv(proposal, parent_da, sender) => {
+                if let Err(e) = save_parent_da_certificate(
+                    proposal,
+                    parent_da,
+                    &self.quorum_membership,
+                    &self.da_membership,
+                    &self.consensus,
+                )
+                .await
+                {
+                    warn!(
+                        "Rejecting proposal for view {:?}: {e:#}",
+                        proposal.data.view_number()
+                    );
+                    return;
+                }
+
+                // The parent's DAC may be all we were missing to vote on the parent.
+                let parent_view = parent_da.certificate.view_number;
+                if self
+                    .current_proposal
+                    .as_ref()
+                    .is_some_and(|current| current.view_number() == parent_view)
+                {
+                    self.spawn_vote_task(parent_view, event_stream.clone())
+                        .await;
+                }
+
+                broadcast_event(
+                    Arc::new(HotShotEvent::QuorumProposalRecv(
+                        proposal.clone(),
+                        sender.clone(),
+                    )),
+                    &event_stream,
+                )
+                .await;
+            }
+            #[cfg(not(feature = "dependency-tasks"))]
             HotShotEvent::VidShareRecv(disperse) => {
                 let view = disperse.data.view_number();
 
@@ -474,36 +564,74 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
                     view
                 );
 
-                // Allow VID disperse date that is one view older, in case we have updated the
-                // view.
-                // Adding `+ 1` on the LHS rather than `- 1` on the RHS, to avoid the overflow
-                // error due to subtracting the genesis view number.
-                if view + 1 < self.cur_view {
+                if !self.is_recent_disperse(view) {
                     info!("Throwing away VID disperse data that is more than one view older");
                     return;
                 }
 
                 debug!("VID disperse data is not more than one view older.");
 
+                if self.seen_vid_shares.contains(disperse) {
+                    debug!("Dropping a duplicate VID share for view {:?}", view);
+                    return;
+                }
+
                 if !self.validate_disperse(disperse) {
                     warn!("Failed to validated the VID dispersal/share sig.");
                     return;
                 }
 
-                self.consensus
-                    .write()
-                    .await
-                    .update_vid_shares(view, disperse.clone());
-                if disperse.data.recipient_key != self.public_key {
-                    return;
+                self.handle_valid_disperse(disperse, &event_stream).await;
+            }
+            #[cfg(not(feature = "dependency-tasks"))]
+            HotShotEvent::VidSharesRecv(disperses) => {
+                debug!(
+                    "{} VID disperse shares received in consensus task",
+                    disperses.len()
+                );
+
+                let (recent, old): (Vec<_>, Vec<_>) = disperses
+                    .iter()
+                    .cloned()
+                    .filter(|disperse| !self.seen_vid_shares.contains(disperse))
+                    .partition(|disperse| self.is_recent_disperse(disperse.data.view_number()));
+                if !old.is_empty() {
+                    info!(
+                        "Throwing away {} VID disperse shares that are more than one view older",
+                        old.len()
+                    );
                 }
-                let Some(proposal) = self.current_proposal.clone() else {
-                    return;
-                };
-                if proposal.view_number() != view {
-                    return;
+
+                let num_recent = recent.len();
+                let valid = valid_vid_shares(
+                    recent,
+                    &self.public_key,
+                    &self.quorum_membership,
+                    &self.da_membership,
+                    self.vid_membership.total_nodes(),
+                )
+                .await;
+                if valid.len() < num_recent {
+                    warn!(
+                        "Failed to validate {} of the VID dispersal/share sigs.",
+                        num_recent - valid.len()
+                    );
+                }
+
+                for disperse in &valid {
+                    self.handle_valid_disperse(disperse, &event_stream).await;
+                }
+            }
+            HotShotEvent::VersionUpgrade(version) => {
+                // Moving to a version other than the decided upgrade's revokes it.
+                if self
+                    .decided_upgrade_cert
+                    .as_ref()
+                    .is_some_and(|cert| cert.data.new_version != *version)
+                {
+                    warn!("Revoking the decided upgrade certificate");
+                    self.decided_upgrade_cert = None;
                 }
-                self.spawn_vote_task(view, event_stream.clone()).await;
             }
             HotShotEvent::ViewChange(new_view) => {
                 let new_view = *new_view;
@@ -549,7 +677,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
                     &mut self.timeout_task,
                     &self.output_event_stream,
                     DONT_SEND_VIEW_CHANGE_EVENT,
-                    self.quorum_membership.leader(old_view_number) == self.public_key,
+                    self.quorum_membership.as_ref(),
+                    &self.public_key,
                 )
                 .await
                 {
@@ -571,12 +700,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
                     return;
                 }
 
-                let Ok(vote) = TimeoutVote::create_signed_vote(
+                let Ok(vote) = TimeoutVote::create_vote_with_signer(
                     TimeoutData { view },
                     view,
                     &self.public_key,
-                    &self.private_key,
-                ) else {
+                    self.signer.as_ref(),
+                )
+                .await
+                else {
                     error!("Failed to sign TimeoutData!");
                     return;
                 };
@@ -713,6 +844,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ConsensusTaskState<TYPES, I>
                 }
             }
             HotShotEvent::QuorumProposalSend(proposal, _) => {
+                self.proposal_send_times
+                    .insert(proposal.data.view_number(), Instant::now());
                 if self
                     .payload_commitment_and_metadata
                     .as_ref()
@@ -745,7 +878,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for ConsensusTaskS
         sender: &Sender<Arc<Self::Event>>,
         _receiver: &Receiver<Arc<Self::Event>>,
     ) -> Result<()> {
-        self.handle(event, sender.clone()).await;
+        for event in gate_paused_events(&mut self.paused_events, event) {
+            self.handle(event, sender.clone()).await;
+        }
 
         Ok(())
     }
@@ -11,7 +11,10 @@ use chrono::Utc;
 use hotshot_types::{
     consensus::Consensus,
     event::{Event, EventType},
-    traits::node_implementation::{ConsensusTime, NodeType},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+    },
 };
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::JoinHandle;
@@ -19,7 +22,7 @@ use tracing::{debug, error};
 
 use crate::{
     events::HotShotEvent,
-    helpers::{broadcast_event, cancel_task},
+    helpers::{broadcast_event, cancel_task, ExternalEventSender},
 };
 
 /// Constant which tells [`update_view`] to send a view change event when called.
@@ -28,6 +31,32 @@ pub(crate) const SEND_VIEW_CHANGE_EVENT: bool = true;
 /// Constant which tells [`update_view`] to not send a view change event when called.
 pub(crate) const DONT_SEND_VIEW_CHANGE_EVENT: bool = false;
 
+/// The [`EventType::LeaderHandoff`] for moving from `old_view` to `new_view`.
+///
+/// The outgoing leader counts as having proposed if we validated its proposal for `old_view`, or
+/// sent one ourselves.
+pub(crate) fn leader_handoff_event<TYPES: NodeType>(
+    consensus: &Consensus<TYPES>,
+    quorum_membership: &TYPES::Membership,
+    old_view: TYPES::Time,
+    new_view: TYPES::Time,
+) -> Event<TYPES> {
+    let outgoing_proposed = consensus
+        .validated_state_map()
+        .get(&old_view)
+        .is_some_and(|view| view.leaf_commitment().is_some())
+        || consensus.last_proposals().contains_key(&old_view);
+    Event {
+        view_number: old_view,
+        event: EventType::LeaderHandoff {
+            view: old_view,
+            outgoing: quorum_membership.leader(old_view),
+            incoming: quorum_membership.leader(new_view),
+            outgoing_proposed,
+        },
+    }
+}
+
 /// Update the view if it actually changed, takes a mutable reference to the `cur_view` and the
 /// `timeout_task` which are updated during the operation of the function.
 ///
@@ -43,9 +72,10 @@ pub(crate) async fn update_view<TYPES: NodeType>(
     cur_view: &mut TYPES::Time,
     cur_view_time: &mut i64,
     timeout_task: &mut JoinHandle<()>,
-    output_event_stream: &Sender<Event<TYPES>>,
+    output_event_stream: &ExternalEventSender<TYPES>,
     send_view_change_event: bool,
-    is_old_view_leader: bool,
+    quorum_membership: &TYPES::Membership,
+    public_key: &TYPES::SignatureKey,
 ) -> Result<()> {
     ensure!(
         new_view > *cur_view,
@@ -103,12 +133,15 @@ pub(crate) async fn update_view<TYPES: NodeType>(
     cancel_task(std::mem::replace(timeout_task, new_timeout_task)).await;
 
     let consensus = consensus.upgradable_read().await;
+    // With the dependency tasks, the consensus task handling the `ViewChange` reports the handoff.
+    #[cfg(not(feature = "dependency-tasks"))]
+    let leader_handoff = leader_handoff_event(&consensus, quorum_membership, old_view, new_view);
     consensus
         .metrics
         .current_view
         .set(usize::try_from(cur_view.u64()).unwrap());
     let new_view_time = Utc::now().timestamp();
-    if is_old_view_leader {
+    if quorum_membership.leader(old_view) == *public_key {
         #[allow(clippy::cast_precision_loss)]
         consensus
             .metrics
@@ -127,11 +160,19 @@ pub(crate) async fn update_view<TYPES: NodeType>(
                 - usize::try_from(consensus.last_decided_view().u64()).unwrap(),
         );
     }
+    consensus
+        .metrics
+        .seconds_since_last_decide
+        .set(usize::try_from(consensus.time_since_last_decide().as_secs()).unwrap_or(usize::MAX));
     let mut consensus = RwLockUpgradableReadGuard::upgrade(consensus).await;
     if let Err(e) = consensus.update_view(new_view) {
         tracing::trace!("{e:?}");
     }
     tracing::trace!("View updated successfully");
+    drop(consensus);
+
+    #[cfg(not(feature = "dependency-tasks"))]
+    broadcast_event(leader_handoff, output_event_stream).await;
 
     Ok(())
 }
@@ -18,6 +18,7 @@ use tracing::debug;
 
 use super::Consensus2TaskState;
 use crate::{
+    consensus::view_change::leader_handoff_event,
     events::{HotShotEvent, HotShotTaskCompleted},
     helpers::{broadcast_event, cancel_task},
     vote_collection::{create_vote_accumulator, AccumulatorInfo, HandleVoteEvent},
@@ -158,6 +159,12 @@ pub(crate) async fn handle_view_change<TYPES: NodeType, I: NodeImplementation<TY
     .await;
 
     let consensus = task_state.consensus.read().await;
+    let leader_handoff = leader_handoff_event(
+        &consensus,
+        &task_state.quorum_membership,
+        old_view_number,
+        new_view_number,
+    );
     consensus
         .metrics
         .current_view
@@ -182,6 +189,10 @@ pub(crate) async fn handle_view_change<TYPES: NodeType, I: NodeImplementation<TY
                 - usize::try_from(task_state.last_decided_view.u64()).unwrap(),
         );
     }
+    consensus
+        .metrics
+        .seconds_since_last_decide
+        .set(usize::try_from(consensus.time_since_last_decide().as_secs()).unwrap_or(usize::MAX));
 
     broadcast_event(
         Event {
@@ -193,6 +204,7 @@ pub(crate) async fn handle_view_change<TYPES: NodeType, I: NodeImplementation<TY
         &task_state.output_event_stream,
     )
     .await;
+    broadcast_event(leader_handoff, &task_state.output_event_stream).await;
     Ok(())
 }
 
@@ -214,12 +226,13 @@ pub(crate) async fn handle_timeout<TYPES: NodeType, I: NodeImplementation<TYPES>
         format!("We were not chosen for the consensus committee for view {view_number:?}")
     );
 
-    let vote = TimeoutVote::create_signed_vote(
+    let vote = TimeoutVote::create_vote_with_signer(
         TimeoutData::<TYPES> { view: view_number },
         view_number,
         &task_state.public_key,
-        &task_state.private_key,
+        task_state.signer.as_ref(),
     )
+    .await
     .context("Failed to sign TimeoutData")?;
 
     broadcast_event(Arc::new(HotShotEvent::TimeoutVoteSend(vote)), sender).await;
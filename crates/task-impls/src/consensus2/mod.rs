@@ -9,12 +9,12 @@ use async_trait::async_trait;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
     consensus::Consensus,
-    event::Event,
+    event::{Event, EventType},
     simple_certificate::{QuorumCertificate, TimeoutCertificate},
     simple_vote::{QuorumVote, TimeoutVote},
     traits::{
         node_implementation::{NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
     },
 };
 #[cfg(async_executor_impl = "tokio")]
@@ -24,7 +24,11 @@ use tracing::instrument;
 use self::handlers::{
     handle_quorum_vote_recv, handle_timeout, handle_timeout_vote_recv, handle_view_change,
 };
-use crate::{events::HotShotEvent, vote_collection::VoteCollectionTaskState};
+use crate::{
+    events::HotShotEvent,
+    helpers::{broadcast_event, gate_paused_events, ExternalEventSender, PausedEvents},
+    vote_collection::VoteCollectionTaskState,
+};
 
 /// Alias for Optional type for Vote Collectors
 type VoteCollectorOption<TYPES, VOTE, CERT> = Option<VoteCollectionTaskState<TYPES, VOTE, CERT>>;
@@ -37,8 +41,8 @@ pub struct Consensus2TaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Immutable instance state
     pub instance_state: Arc<TYPES::InstanceState>,
@@ -76,7 +80,7 @@ pub struct Consensus2TaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     pub cur_view_time: i64,
 
     /// Output events to application
-    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+    pub output_event_stream: ExternalEventSender<TYPES>,
 
     /// Timeout task handle
     pub timeout_task: JoinHandle<()>,
@@ -92,6 +96,9 @@ pub struct Consensus2TaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
 
     /// The node's id
     pub id: u64,
+
+    /// Events received while paused, to handle once resumed; `None` while running
+    pub paused_events: Option<PausedEvents<TYPES>>,
 }
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> Consensus2TaskState<TYPES, I> {
     /// Handles a consensus event received on the event stream
@@ -102,13 +109,27 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> Consensus2TaskState<TYPES, I
         sender: Sender<Arc<HotShotEvent<TYPES>>>,
     ) {
         match event.as_ref() {
-            HotShotEvent::QuorumVoteRecv(ref vote) => {
+            HotShotEvent::QuorumVoteRecv(ref vote)
+            | HotShotEvent::ExtendedQuorumVoteRecv(ref vote, _) => {
                 if let Err(e) =
                     handle_quorum_vote_recv(vote, Arc::clone(&event), &sender, self).await
                 {
                     tracing::debug!("Failed to handle QuorumVoteRecv event; error = {e}");
                 }
             }
+            HotShotEvent::QuorumVoteExtensions(view_number, extensions) => {
+                broadcast_event(
+                    Event {
+                        view_number: *view_number,
+                        event: EventType::QuorumVoteExtensions {
+                            view_number: *view_number,
+                            extensions: extensions.clone(),
+                        },
+                    },
+                    &self.output_event_stream,
+                )
+                .await;
+            }
             HotShotEvent::TimeoutVoteRecv(ref vote) => {
                 if let Err(e) =
                     handle_timeout_vote_recv(vote, Arc::clone(&event), &sender, self).await
@@ -156,7 +177,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for Consensus2Task
         sender: &Sender<Arc<Self::Event>>,
         _receiver: &Receiver<Arc<Self::Event>>,
     ) -> Result<()> {
-        self.handle(event, sender.clone()).await;
+        for event in gate_paused_events(&mut self.paused_events, event) {
+            self.handle(event, sender.clone()).await;
+        }
 
         Ok(())
     }
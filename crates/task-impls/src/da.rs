@@ -1,4 +1,8 @@
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+    sync::Arc,
+};
 
 use anyhow::Result;
 use async_broadcast::{Receiver, Sender};
@@ -7,20 +11,21 @@ use async_lock::RwLock;
 #[cfg(async_executor_impl = "async-std")]
 use async_std::task::spawn_blocking;
 use async_trait::async_trait;
+use committable::{Commitment, Committable};
 use hotshot_task::task::TaskState;
 use hotshot_types::{
     consensus::{Consensus, LockedConsensusState, View},
     data::DaProposal,
     event::{Event, EventType},
-    message::Proposal,
+    message::{Proposal, TxPriority},
     simple_certificate::DaCertificate,
     simple_vote::{DaData, DaVote},
     traits::{
-        block_contents::vid_commitment,
+        block_contents::{vid_commitment, BlockPayload},
         election::Membership,
         network::ConnectedNetwork,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::{SignatureKey, Signer},
         storage::Storage,
     },
     utils::ViewInner,
@@ -33,19 +38,27 @@ use tracing::{debug, error, instrument, warn};
 
 use crate::{
     events::{HotShotEvent, HotShotTaskCompleted},
-    helpers::broadcast_event,
+    helpers::{broadcast_event, ExternalEventSender},
     vote_collection::{
-        create_vote_accumulator, AccumulatorInfo, HandleVoteEvent, VoteCollectionTaskState,
+        create_persisted_vote_accumulator, AccumulatorInfo, HandleVoteEvent,
+        VoteCollectionTaskState,
     },
 };
 
+/// How many views the priorities of the transactions submitted to us are kept for, to report with
+/// the DA proposals including them
+const TRANSACTION_PRIORITY_VIEWS: u64 = 10;
+
+/// How many DA votes are accumulated before they're persisted in one write
+const DA_VOTE_FLUSH_BATCH: usize = 10;
+
 /// Alias for Optional type for Vote Collectors
 type VoteCollectorOption<TYPES, VOTE, CERT> = Option<VoteCollectionTaskState<TYPES, VOTE, CERT>>;
 
 /// Tracks state of a DA task
 pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Output events to application
-    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+    pub output_event_stream: ExternalEventSender<TYPES>,
 
     /// View number this view is executing in.
     pub cur_view: TYPES::Time,
@@ -56,10 +69,10 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Membership for the DA committee
     pub da_membership: Arc<TYPES::Membership>,
 
-    /// Membership for the quorum committee
+    /// Membership of the VID storage nodes
     /// We need this only for calculating the proper VID scheme
-    /// from the number of nodes in the quorum.
-    pub quorum_membership: Arc<TYPES::Membership>,
+    /// from the number of VID storage nodes.
+    pub vid_membership: Arc<TYPES::Membership>,
 
     /// Network for DA
     pub da_network: Arc<I::DaNetwork>,
@@ -70,14 +83,22 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// This Nodes public key
     pub public_key: TYPES::SignatureKey,
 
-    /// This Nodes private key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on this node's behalf with its private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// This state's ID
     pub id: u64,
 
     /// This node's storage ref
     pub storage: Arc<RwLock<I::Storage>>,
+
+    /// Maximum size, in bytes, of an encoded block payload we propose
+    pub max_block_size: u64,
+
+    /// The priorities of the transactions submitted to us, by the view we received them in
+    #[allow(clippy::type_complexity)]
+    pub transaction_priorities:
+        BTreeMap<TYPES::Time, HashMap<Commitment<TYPES::Transaction>, TxPriority>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
@@ -153,6 +174,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
                         event: EventType::DaProposal {
                             proposal: proposal.clone(),
                             sender: sender.clone(),
+                            priorities: self.proposal_priorities(&proposal.data),
                         },
                     },
                     &self.output_event_stream,
@@ -173,23 +195,38 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
                     );
                     return None;
                 }
-                let txns = Arc::clone(&proposal.data.encoded_transactions);
-                let num_nodes = self.quorum_membership.total_nodes();
-                let payload_commitment =
-                    spawn_blocking(move || vid_commitment(&txns, num_nodes)).await;
-                #[cfg(async_executor_impl = "tokio")]
-                let payload_commitment = payload_commitment.unwrap();
-
                 let view_number = proposal.data.view_number();
+                // Reuse the commitment we computed when dispersing a payload we submitted
+                // ourselves, only computing it on a miss.
+                let submitted_commitment = self
+                    .consensus
+                    .read()
+                    .await
+                    .submitted_payload_commitment(view_number, &proposal.data.encoded_transactions);
+                let payload_commitment = if let Some(payload_commitment) = submitted_commitment {
+                    payload_commitment
+                } else {
+                    let txns = Arc::clone(&proposal.data.encoded_transactions);
+                    let num_nodes = self.vid_membership.total_nodes();
+                    let payload_commitment =
+                        spawn_blocking(move || vid_commitment(&txns, num_nodes)).await;
+                    #[cfg(async_executor_impl = "tokio")]
+                    let payload_commitment = payload_commitment.unwrap();
+                    debug!("Computed the payload commitment for view {view_number:?}");
+                    payload_commitment
+                };
+
                 // Generate and send vote
-                let Ok(vote) = DaVote::create_signed_vote(
+                let Ok(vote) = DaVote::create_vote_with_signer(
                     DaData {
                         payload_commit: payload_commitment,
                     },
                     view_number,
                     &self.public_key,
-                    &self.private_key,
-                ) else {
+                    self.signer.as_ref(),
+                )
+                .await
+                else {
                     error!("Failed to sign DA Vote!");
                     return None;
                 };
@@ -214,17 +251,25 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
                 ) {
                     tracing::trace!("{e:?}");
                 }
-                // Optimistically calculate and update VID if we know that the primary network is down.
-                if self.da_network.is_primary_down() {
+                // Keep the proposal to serve to peers which missed it.
+                consensus.update_last_da_proposals(proposal.clone());
+                consensus.update_da_payload_commitments(view_number, payload_commitment);
+                let have_paired_vid_share = consensus
+                    .paired_vid_share(view_number, &self.public_key)
+                    .is_some();
+                drop(consensus);
+                // Optimistically calculate and update VID if we know that the primary network is down,
+                // unless the leader's share for this proposal has already reached us.
+                if self.da_network.is_primary_down() && !have_paired_vid_share {
                     let consensus = Arc::clone(&self.consensus);
-                    let membership = Arc::clone(&self.quorum_membership);
-                    let pk = self.private_key.clone();
+                    let membership = Arc::clone(&self.vid_membership);
+                    let signer = Arc::clone(&self.signer);
                     async_spawn(async move {
                         Consensus::calculate_and_update_vid(
                             consensus,
                             view_number,
                             membership,
-                            &pk,
+                            signer.as_ref(),
                         )
                         .await;
                     });
@@ -240,7 +285,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
                 }
                 let mut collector = self.vote_collector.write().await;
 
-                if collector.is_none() || vote.view_number() > collector.as_ref().unwrap().view {
+                let formed_view = if collector.is_none()
+                    || vote.view_number() > collector.as_ref().unwrap().view
+                {
                     debug!("Starting vote handle for view {:?}", vote.view_number());
                     let info = AccumulatorInfo {
                         public_key: self.public_key.clone(),
@@ -248,13 +295,25 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
                         view: vote.view_number(),
                         id: self.id,
                     };
-                    *collector = create_vote_accumulator::<
+                    // Resume from the votes we persisted before restarting, if any.
+                    let restored_votes = match self.storage.read().await.load_da_votes(view).await {
+                        Ok(votes) => votes,
+                        Err(e) => {
+                            warn!("Failed to load DA votes for view {view:?} from storage: {e:?}");
+                            Vec::new()
+                        }
+                    };
+                    *collector = create_persisted_vote_accumulator::<
                         TYPES,
                         DaVote<TYPES>,
                         DaCertificate<TYPES>,
-                    >(&info, vote.clone(), event, &event_stream)
+                    >(
+                        &info, restored_votes, vote.clone(), event, &event_stream
+                    )
                     .await;
+                    collector.is_none().then_some(view)
                 } else {
+                    let collector_view = collector.as_ref().unwrap().view;
                     let result = collector
                         .as_mut()
                         .unwrap()
@@ -263,9 +322,26 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
 
                     if result == Some(HotShotTaskCompleted) {
                         *collector = None;
-                        // The protocol has finished
-                        return None;
+                        Some(collector_view)
+                    } else {
+                        None
+                    }
+                };
+
+                if let Some(formed_view) = formed_view {
+                    // The protocol has finished, so the votes we persisted are no longer needed.
+                    if let Err(e) = self.storage.read().await.gc_da_votes(formed_view + 1).await {
+                        warn!("Failed to garbage collect DA votes: {e:?}");
                     }
+                    return None;
+                }
+
+                if collector
+                    .as_ref()
+                    .and_then(|collector| collector.unflushed_votes.as_ref())
+                    .is_some_and(|votes| votes.len() >= DA_VOTE_FLUSH_BATCH)
+                {
+                    self.flush_da_votes(collector.as_mut().unwrap()).await;
                 }
             }
             HotShotEvent::ViewChange(view) => {
@@ -279,6 +355,19 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
                 }
                 self.cur_view = view;
 
+                // Persist the votes still being collected, and drop those of views which are over.
+                if let Some(collector) = self.vote_collector.write().await.as_mut() {
+                    self.flush_da_votes(collector).await;
+                }
+                if let Err(e) = self.storage.read().await.gc_da_votes(view).await {
+                    warn!("Failed to garbage collect DA votes: {e:?}");
+                }
+
+                // Transactions submitted long ago have been proposed or dropped by now
+                self.transaction_priorities = self.transaction_priorities.split_off(
+                    &TYPES::Time::new(view.saturating_sub(TRANSACTION_PRIORITY_VIEWS)),
+                );
+
                 // If we are not the next leader (DA leader for this view) immediately exit
                 if self.da_membership.leader(self.cur_view + 1) != self.public_key {
                     return None;
@@ -287,16 +376,44 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
 
                 return None;
             }
+            HotShotEvent::TransactionsRecv(transactions) => {
+                self.transaction_priorities
+                    .entry(self.cur_view)
+                    .or_default()
+                    .extend(
+                        transactions
+                            .iter()
+                            .map(|(transaction, priority)| (transaction.commit(), *priority)),
+                    );
+            }
             HotShotEvent::BlockRecv(encoded_transactions, metadata, view, _fee, _vid_precomp) => {
                 let view = *view;
 
+                let size = encoded_transactions.len() as u64;
+                if size > self.max_block_size {
+                    warn!(
+                        "Not proposing a {size} byte block payload for view {view:?}, larger than the {} byte limit",
+                        self.max_block_size
+                    );
+                    broadcast_event(
+                        Arc::new(HotShotEvent::BlockRejected(
+                            view,
+                            format!(
+                                "payload of {size} bytes exceeds the maximum block size of {} bytes",
+                                self.max_block_size
+                            ),
+                        )),
+                        &event_stream,
+                    )
+                    .await;
+                    return None;
+                }
+
                 // quick hash the encoded txns with sha256
                 let encoded_transactions_hash = Sha256::digest(encoded_transactions);
 
                 // sign the encoded transactions as opposed to the VID commitment
-                let Ok(signature) =
-                    TYPES::SignatureKey::sign(&self.private_key, &encoded_transactions_hash)
-                else {
+                let Ok(signature) = self.signer.sign(&encoded_transactions_hash).await else {
                     error!("Failed to sign block payload!");
                     return None;
                 };
@@ -314,6 +431,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
                     _pd: PhantomData,
                 };
 
+                // Keep our own proposal to serve to peers which miss it, as we may not receive it
+                // back from the network.
+                self.consensus
+                    .write()
+                    .await
+                    .update_last_da_proposals(message.clone());
+
                 broadcast_event(
                     Arc::new(HotShotEvent::DaProposalSend(
                         message.clone(),
@@ -323,15 +447,53 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DaTaskState<TYPES, I> {
                 )
                 .await;
             }
-
-            HotShotEvent::Shutdown => {
-                error!("Shutting down because of shutdown signal!");
-                return Some(HotShotTaskCompleted);
-            }
             _ => {}
         }
         None
     }
+
+    /// Persist the DA votes `collector` has accumulated since they were last persisted.
+    async fn flush_da_votes(
+        &self,
+        collector: &mut VoteCollectionTaskState<TYPES, DaVote<TYPES>, DaCertificate<TYPES>>,
+    ) {
+        let votes = collector.take_unflushed_votes();
+        if votes.is_empty() {
+            return;
+        }
+        if let Err(e) = self
+            .storage
+            .read()
+            .await
+            .append_da_votes(collector.view, &votes)
+            .await
+        {
+            warn!("Failed to append DA votes to storage: {e:?}");
+        }
+    }
+
+    /// The priorities the transactions in `proposal` were submitted to us with, so that builders
+    /// can order the transactions they still have to include
+    fn proposal_priorities(
+        &self,
+        proposal: &DaProposal<TYPES>,
+    ) -> Vec<(Commitment<TYPES::Transaction>, TxPriority)> {
+        if self.transaction_priorities.is_empty() {
+            return Vec::new();
+        }
+        let payload =
+            TYPES::BlockPayload::from_bytes(&proposal.encoded_transactions, &proposal.metadata);
+        payload
+            .transaction_commitments(&proposal.metadata)
+            .into_iter()
+            .filter_map(|commitment| {
+                self.transaction_priorities
+                    .values()
+                    .find_map(|priorities| priorities.get(&commitment))
+                    .map(|priority| (commitment, *priority))
+            })
+            .collect()
+    }
 }
 
 #[async_trait]
@@ -350,5 +512,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for DaTaskState<TY
         Ok(())
     }
 
-    async fn cancel_subtasks(&mut self) {}
+    async fn cancel_subtasks(&mut self) {
+        if let Some(collector) = self.vote_collector.write().await.as_mut() {
+            self.flush_da_votes(collector).await;
+        }
+    }
 }
@@ -1,18 +1,22 @@
-use std::{fmt::Display, sync::Arc};
+use std::{collections::BTreeMap, fmt::Display, sync::Arc};
 
 use async_broadcast::Sender;
 use either::Either;
 use hotshot_task::task::TaskEvent;
 use hotshot_types::{
-    data::{DaProposal, Leaf, QuorumProposal, UpgradeProposal, VidDisperse, VidDisperseShare},
-    message::Proposal,
+    data::{
+        DaProposal, Leaf, ParentDaCertificate, QuorumProposal, UpgradeProposal, VidDisperse,
+        VidDisperseShare,
+    },
+    message::{EquivocationProof, Proposal, TxPriority},
+    probe::ProbeMessage,
     simple_certificate::{
         DaCertificate, QuorumCertificate, TimeoutCertificate, UpgradeCertificate,
         ViewSyncCommitCertificate2, ViewSyncFinalizeCertificate2, ViewSyncPreCommitCertificate2,
     },
     simple_vote::{
         DaVote, QuorumVote, TimeoutVote, UpgradeVote, ViewSyncCommitVote, ViewSyncFinalizeVote,
-        ViewSyncPreCommitVote,
+        ViewSyncPreCommitVote, VoteExtension,
     },
     traits::{block_contents::BuilderFee, node_implementation::NodeType, BlockPayload},
     utils::{BuilderCommitment, View},
@@ -57,10 +61,17 @@ pub struct HotShotTaskCompleted;
 pub enum HotShotEvent<TYPES: NodeType> {
     /// Shutdown the task
     Shutdown,
+    /// Pause the quorum proposal and vote tasks, which hold back the events they receive until
+    /// resumed; internal event only
+    Pause,
+    /// Resume the paused tasks, which then handle the events they held back; internal event only
+    Resume,
     /// A quorum proposal has been received from the network; handled by the consensus task
     QuorumProposalRecv(Proposal<TYPES, QuorumProposal<TYPES>>, TYPES::SignatureKey),
     /// A quorum vote has been received from the network; handled by the consensus task
     QuorumVoteRecv(QuorumVote<TYPES>),
+    /// A quorum vote carrying application data has been received from the network; handled by the consensus task
+    ExtendedQuorumVoteRecv(QuorumVote<TYPES>, VoteExtension<TYPES>),
     /// A timeout vote recevied from the network; handled by consensus task
     TimeoutVoteRecv(TimeoutVote<TYPES>),
     /// Send a timeout vote to the network; emitted by consensus task replicas
@@ -79,10 +90,15 @@ pub enum HotShotEvent<TYPES: NodeType> {
     QuorumProposalSend(Proposal<TYPES, QuorumProposal<TYPES>>, TYPES::SignatureKey),
     /// Send a quorum vote to the next leader; emitted by a replica in the consensus task after seeing a valid quorum proposal
     QuorumVoteSend(QuorumVote<TYPES>),
+    /// Send a quorum vote with application data attached to the next leader; the network task drops the data before the upgrade
+    ExtendedQuorumVoteSend(QuorumVote<TYPES>, VoteExtension<TYPES>),
     /// All dependencies for the quorum vote are validated.
     QuorumVoteDependenciesValidated(TYPES::Time),
     /// A quorum proposal with the given parent leaf is validated.
     QuorumProposalValidated(QuorumProposal<TYPES>, Leaf<TYPES>),
+    /// The leader of the view signed two conflicting quorum proposals for it, neither of which
+    /// we vote on; emitted by the quorum proposal recv task
+    Equivocation(TYPES::Time, TYPES::SignatureKey, EquivocationProof<TYPES>),
     /// A quorum proposal is missing for a view that we meed
     QuorumProposalRequest(ProposalMissing<TYPES>),
     /// Send a DA proposal to the DA committee; emitted by the DA leader (which is the same node as the leader of view v + 1) in the DA task
@@ -91,6 +107,8 @@ pub enum HotShotEvent<TYPES: NodeType> {
     DaVoteSend(DaVote<TYPES>),
     /// The next leader has collected enough votes to form a QC; emitted by the next leader in the consensus task; an internal event only
     QcFormed(Either<QuorumCertificate<TYPES>, TimeoutCertificate<TYPES>>),
+    /// The next leader has formed a QC from votes carrying application data; emitted alongside `QcFormed` with the extensions by signer
+    QuorumVoteExtensions(TYPES::Time, BTreeMap<TYPES::SignatureKey, Vec<u8>>),
     /// The DA leader has collected enough votes to form a DAC; emitted by the DA leader in the DA task; sent to the entire network via the networking task
     DacSend(DaCertificate<TYPES>, TYPES::SignatureKey),
     /// The current view has changed; emitted by the replica in the consensus task or replica in the view sync task; received by almost all other tasks
@@ -130,8 +148,8 @@ pub enum HotShotEvent<TYPES: NodeType> {
     ViewSyncTrigger(TYPES::Time),
     /// A consensus view has timed out; emitted by a replica in the consensus task; received by the view sync task; internal event only
     Timeout(TYPES::Time),
-    /// Receive transactions from the network
-    TransactionsRecv(Vec<TYPES::Transaction>),
+    /// Receive transactions from the network, with the priority each was submitted with
+    TransactionsRecv(Vec<(TYPES::Transaction, TxPriority)>),
     /// Send transactions to the network
     TransactionSend(TYPES::Transaction, TYPES::SignatureKey),
     /// Event to send block payload commitment and metadata from DA leader to the quorum; internal event only
@@ -150,6 +168,9 @@ pub enum HotShotEvent<TYPES: NodeType> {
         BuilderFee<TYPES>,
         VidPrecomputeData,
     ),
+    /// The DA leader rejected the block payload it received for the view, for the given reason,
+    /// rather than proposing it; emitted by the DA task
+    BlockRejected(TYPES::Time, String),
     /// Event when the transactions task has a block formed
     BlockReady(VidDisperse<TYPES>, TYPES::Time),
     /// Event when consensus decided on a leaf
@@ -162,6 +183,9 @@ pub enum HotShotEvent<TYPES: NodeType> {
     ///
     /// Like [`HotShotEvent::DaProposalRecv`].
     VidShareRecv(Proposal<TYPES, VidDisperseShare<TYPES>>),
+    /// A batch of VID disperse shares has been received, such as when catching up; the shares are
+    /// validated in parallel, and handled like [`HotShotEvent::VidShareRecv`] otherwise
+    VidSharesRecv(Vec<Proposal<TYPES, VidDisperseShare<TYPES>>>),
     /// VID share data is validated.
     VidShareValidated(Proposal<TYPES, VidDisperseShare<TYPES>>),
     /// Upgrade proposal has been received from the network
@@ -182,10 +206,19 @@ pub enum HotShotEvent<TYPES: NodeType> {
     /// Initiate a vote right now for the designated view.
     VoteNow(TYPES::Time, VoteDependencyData<TYPES>),
 
+    /// The dependencies for proposing in the view weren't met in time, so we gave up on
+    /// proposing; emitted and handled by the quorum proposal task
+    ProposalDependencyTimeout(TYPES::Time),
+
     /* Consensus State Update Events */
     /// A undecided view has been created and added to the validated state storage.
     ValidatedStateUpdated(TYPES::Time, View<TYPES>),
 
+    /// The states and leaves of undecided views up to this one were pruned, as more views than
+    /// `max_undecided_views` were undecided, so their data can no longer be served. Views which
+    /// may still be decided are kept.
+    UndecidedPruned(TYPES::Time),
+
     /// A new locked view has been created (2-chain)
     LockedViewUpdated(TYPES::Time),
 
@@ -194,6 +227,35 @@ pub enum HotShotEvent<TYPES: NodeType> {
 
     /// A new high_qc has been reached by this node.
     UpdateHighQc(QuorumCertificate<TYPES>),
+
+    /// The high QC of this node advanced.
+    HighQcUpdated {
+        /// The view of the previous high QC, or `None` if it was the genesis QC
+        from: Option<TYPES::Time>,
+        /// The view of the new high QC
+        to: TYPES::Time,
+    },
+
+    /// Receive a probe message from a peer, with its sender
+    ProbeRecv(ProbeMessage<TYPES>, TYPES::SignatureKey),
+    /// Send a probe message with the given sender, directly to the recipient if there is one and
+    /// to the whole committee otherwise
+    ProbeSend(
+        ProbeMessage<TYPES>,
+        TYPES::SignatureKey,
+        Option<TYPES::SignatureKey>,
+    ),
+    /// A quorum proposal with the DA certificate for its parent's view attached has been received
+    /// by the network; handled by the consensus task, or the quorum proposal recv task, which
+    /// checks the certificate before handling the proposal as a `QuorumProposalRecv`
+    QuorumProposalWithParentDaRecv(
+        Proposal<TYPES, QuorumProposal<TYPES>>,
+        ParentDaCertificate<TYPES>,
+        TYPES::SignatureKey,
+    ),
+    /// Attach the DA certificate for the parent's view to our quorum proposal for the view;
+    /// emitted by the leader just before the `QuorumProposalSend`, once the network has upgraded
+    ParentDaCertificateSend(TYPES::Time, ParentDaCertificate<TYPES>),
 }
 
 impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
@@ -201,6 +263,8 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             HotShotEvent::Shutdown => write!(f, "Shutdown"),
+            HotShotEvent::Pause => write!(f, "Pause"),
+            HotShotEvent::Resume => write!(f, "Resume"),
             HotShotEvent::QuorumProposalRecv(proposal, _) => write!(
                 f,
                 "QuorumProposalRecv(view_number={:?})",
@@ -209,6 +273,13 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
             HotShotEvent::QuorumVoteRecv(v) => {
                 write!(f, "QuorumVoteRecv(view_number={:?})", v.view_number())
             }
+            HotShotEvent::ExtendedQuorumVoteRecv(v, _) => {
+                write!(
+                    f,
+                    "ExtendedQuorumVoteRecv(view_number={:?})",
+                    v.view_number()
+                )
+            }
             HotShotEvent::TimeoutVoteRecv(v) => {
                 write!(f, "TimeoutVoteRecv(view_number={:?})", v.view_number())
             }
@@ -244,6 +315,11 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
             HotShotEvent::QuorumVoteSend(vote) => {
                 write!(f, "QuorumVoteSend(view_number={:?})", vote.view_number())
             }
+            HotShotEvent::ExtendedQuorumVoteSend(vote, _) => write!(
+                f,
+                "ExtendedQuorumVoteSend(view_number={:?})",
+                vote.view_number()
+            ),
             HotShotEvent::QuorumVoteDependenciesValidated(view_number) => {
                 write!(
                     f,
@@ -255,6 +331,9 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 "QuorumProposalValidated(view_number={:?})",
                 proposal.view_number()
             ),
+            HotShotEvent::Equivocation(view_number, ..) => {
+                write!(f, "Equivocation(view_number={view_number:?})")
+            }
             HotShotEvent::DaProposalSend(proposal, _) => write!(
                 f,
                 "DaProposalSend(view_number={:?})",
@@ -267,6 +346,11 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 either::Left(qc) => write!(f, "QcFormed(view_number={:?})", qc.view_number()),
                 either::Right(tc) => write!(f, "QcFormed(view_number={:?})", tc.view_number()),
             },
+            HotShotEvent::QuorumVoteExtensions(view_number, extensions) => write!(
+                f,
+                "QuorumVoteExtensions(view_number={view_number:?}, num_extensions={})",
+                extensions.len()
+            ),
             HotShotEvent::DacSend(cert, _) => {
                 write!(f, "DacSend(view_number={:?})", cert.view_number())
             }
@@ -363,6 +447,9 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
             HotShotEvent::BlockRecv(_, _, view_number, ..) => {
                 write!(f, "BlockRecv(view_number={view_number:?})")
             }
+            HotShotEvent::BlockRejected(view_number, _) => {
+                write!(f, "BlockRejected(view_number={view_number:?})")
+            }
             HotShotEvent::BlockReady(_, view_number) => {
                 write!(f, "BlockReady(view_number={view_number:?})")
             }
@@ -381,6 +468,14 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 "VIDShareRecv(view_number={:?})",
                 proposal.data.view_number()
             ),
+            HotShotEvent::VidSharesRecv(proposals) => write!(
+                f,
+                "VidSharesRecv(view_numbers={:?})",
+                proposals
+                    .iter()
+                    .map(|proposal| proposal.data.view_number())
+                    .collect::<Vec<_>>()
+            ),
             HotShotEvent::VidShareValidated(proposal) => write!(
                 f,
                 "VIDShareValidated(view_number={:?})",
@@ -417,9 +512,15 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
             HotShotEvent::VoteNow(view_number, _) => {
                 write!(f, "VoteNow(view_number={view_number:?})")
             }
+            HotShotEvent::ProposalDependencyTimeout(view_number) => {
+                write!(f, "ProposalDependencyTimeout(view_number={view_number:?})")
+            }
             HotShotEvent::ValidatedStateUpdated(view_number, _) => {
                 write!(f, "ValidatedStateUpdated(view_number={view_number:?})")
             }
+            HotShotEvent::UndecidedPruned(view_number) => {
+                write!(f, "UndecidedPruned(view_number={view_number:?})")
+            }
             HotShotEvent::LockedViewUpdated(view_number) => {
                 write!(f, "LockedViewUpdated(view_number={view_number:?})")
             }
@@ -429,6 +530,21 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
             HotShotEvent::UpdateHighQc(cert) => {
                 write!(f, "UpdateHighQc(view_number={:?})", cert.view_number())
             }
+            HotShotEvent::HighQcUpdated { from, to } => {
+                write!(f, "HighQcUpdated(from={from:?}, to={to:?})")
+            }
+            HotShotEvent::ProbeRecv(_, sender) => write!(f, "ProbeRecv(sender={sender:?})"),
+            HotShotEvent::ProbeSend(_, _, recipient) => {
+                write!(f, "ProbeSend(recipient={recipient:?})")
+            }
+            HotShotEvent::QuorumProposalWithParentDaRecv(proposal, _, _) => write!(
+                f,
+                "QuorumProposalWithParentDaRecv(view_number={:?})",
+                proposal.data.view_number()
+            ),
+            HotShotEvent::ParentDaCertificateSend(view_number, _) => {
+                write!(f, "ParentDaCertificateSend(view_number={view_number:?})")
+            }
         }
     }
 }
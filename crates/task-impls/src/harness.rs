@@ -2,7 +2,10 @@ use std::{sync::Arc, time::Duration};
 
 use async_broadcast::broadcast;
 use async_compatibility_layer::art::async_timeout;
-use hotshot_task::task::{ConsensusTaskRegistry, Task, TaskState};
+use hotshot_task::{
+    cancellation::CancellationToken,
+    task::{ConsensusTaskRegistry, Task, TaskState},
+};
 use hotshot_types::traits::node_implementation::NodeType;
 
 use crate::events::{HotShotEvent, HotShotTaskCompleted};
@@ -44,7 +47,12 @@ pub async fn run_harness<TYPES, S: TaskState<Event = HotShotEvent<TYPES>> + Send
         allow_extra_output,
     };
 
-    let task = Task::new(state, to_test.clone(), from_test.clone());
+    let task = Task::new(
+        state,
+        to_test.clone(),
+        from_test.clone(),
+        CancellationToken::new(),
+    );
 
     let handle = task.run();
     let test_future = async move {
@@ -1,8 +1,29 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    future::Future,
+    sync::Arc,
+};
+
 use async_broadcast::{SendError, Sender};
+use async_lock::{RwLock, Semaphore};
 #[cfg(async_executor_impl = "async-std")]
-use async_std::task::JoinHandle;
+use async_std::task::{spawn_blocking, JoinHandle};
+use hotshot_types::{
+    data::VidDisperseShare,
+    event::{Event, EventFilter},
+    message::Proposal,
+    traits::{election::Membership, node_implementation::NodeType, signature_key::SignatureKey},
+    vid::vid_scheme,
+    vote::HasViewNumber,
+};
+use jf_vid::VidScheme;
 #[cfg(async_executor_impl = "tokio")]
-use tokio::task::JoinHandle;
+use tokio::task::{spawn_blocking, JoinHandle};
+
+use crate::events::HotShotEvent;
+
+/// Maximum number of VID shares being verified at once
+pub const MAX_CONCURRENT_VID_SHARE_VERIFICATIONS: usize = 8;
 
 /// Cancel a task
 pub async fn cancel_task<T>(task: JoinHandle<T>) {
@@ -12,9 +33,61 @@ pub async fn cancel_task<T>(task: JoinHandle<T>) {
     task.abort();
 }
 
+/// A channel [`broadcast_event`] can send events on.
+pub trait EventSink {
+    /// The events sent on the channel.
+    type Event;
+
+    /// The underlying channel.
+    fn channel(&self) -> &Sender<Self::Event>;
+
+    /// Whether `event` should be sent, rather than dropped.
+    fn admits(&self, event: &Self::Event) -> impl Future<Output = bool> + Send;
+}
+
+impl<E> EventSink for Sender<E> {
+    type Event = E;
+
+    fn channel(&self) -> &Sender<E> {
+        self
+    }
+
+    fn admits(&self, _event: &E) -> impl Future<Output = bool> + Send {
+        std::future::ready(true)
+    }
+}
+
+/// Sends events to the application, dropping those rejected by the installed [`EventFilter`]
+/// before they reach the channel.
+#[derive(Clone, Debug)]
+pub struct ExternalEventSender<TYPES: NodeType> {
+    /// The channel to the application
+    pub sender: Sender<Event<TYPES>>,
+    /// The filter events must pass to be sent
+    pub filter: Arc<RwLock<EventFilter<TYPES>>>,
+}
+
+impl<TYPES: NodeType> EventSink for ExternalEventSender<TYPES> {
+    type Event = Event<TYPES>;
+
+    fn channel(&self) -> &Sender<Event<TYPES>> {
+        &self.sender
+    }
+
+    async fn admits(&self, event: &Event<TYPES>) -> bool {
+        self.filter.read().await.allows(&event.event)
+    }
+}
+
 /// Helper function to send events and log errors
-pub async fn broadcast_event<E: Clone + std::fmt::Debug>(event: E, sender: &Sender<E>) {
-    match sender.broadcast_direct(event).await {
+pub async fn broadcast_event<S: EventSink + ?Sized>(event: S::Event, sink: &S)
+where
+    S::Event: Clone + std::fmt::Debug,
+{
+    if !sink.admits(&event).await {
+        return;
+    }
+    match sink.channel().broadcast_direct(event).await {
         Ok(None) => (),
         Ok(Some(overflowed)) => {
             tracing::error!(
@@ -31,6 +104,212 @@ pub async fn broadcast_event<E: Clone + std::fmt::Debug>(event: E, sender: &Send
     }
 }
 
+/// Whether `share` is a valid share of the payload it commits to, signed by the leader of its view,
+/// by us, or by one of the staked DA committee members.
+pub fn is_valid_vid_share<TYPES: NodeType>(
+    share: &Proposal<TYPES, VidDisperseShare<TYPES>>,
+    public_key: &TYPES::SignatureKey,
+    quorum_membership: &TYPES::Membership,
+    da_membership: &TYPES::Membership,
+    num_storage_nodes: usize,
+) -> bool {
+    let view = share.data.view_number();
+    let payload_commitment = share.data.payload_commitment;
+
+    let is_signed_by =
+        |key: &TYPES::SignatureKey| key.validate(&share.signature, payload_commitment.as_ref());
+    if !is_signed_by(&quorum_membership.leader(view))
+        && !is_signed_by(public_key)
+        && !da_membership
+            .staked_committee(view)
+            .iter()
+            .any(is_signed_by)
+    {
+        return false;
+    }
+
+    if vid_scheme(num_storage_nodes)
+        .verify_share(&share.data.share, &share.data.common, &payload_commitment)
+        .is_err()
+    {
+        tracing::debug!("Invalid VID share.");
+        return false;
+    }
+
+    true
+}
+
+/// Validates a batch of VID shares, as [`is_valid_vid_share`] does, verifying up to
+/// [`MAX_CONCURRENT_VID_SHARE_VERIFICATIONS`] of them at once off the executor.
+///
+/// Returns the valid shares, in order. Each invalid share is dropped on its own, without affecting
+/// the others.
+pub async fn valid_vid_shares<TYPES: NodeType>(
+    shares: Vec<Proposal<TYPES, VidDisperseShare<TYPES>>>,
+    public_key: &TYPES::SignatureKey,
+    quorum_membership: &Arc<TYPES::Membership>,
+    da_membership: &Arc<TYPES::Membership>,
+    num_storage_nodes: usize,
+) -> Vec<Proposal<TYPES, VidDisperseShare<TYPES>>> {
+    let public_key = public_key.clone();
+    let quorum_membership = Arc::clone(quorum_membership);
+    let da_membership = Arc::clone(da_membership);
+    verify_concurrently(
+        shares,
+        MAX_CONCURRENT_VID_SHARE_VERIFICATIONS,
+        move |share| {
+            is_valid_vid_share(
+                share,
+                &public_key,
+                &quorum_membership,
+                &da_membership,
+                num_storage_nodes,
+            )
+        },
+    )
+    .await
+}
+
+/// The VID shares a task has already validated, by view and recipient, so that a share received
+/// again isn't verified or handled again.
+///
+/// Only valid shares are recorded, so an invalid share can't shadow the genuine one for its
+/// recipient.
+pub struct SeenVidShares<TYPES: NodeType>(BTreeMap<TYPES::Time, HashSet<TYPES::SignatureKey>>);
+
+impl<TYPES: NodeType> Default for SeenVidShares<TYPES> {
+    fn default() -> Self {
+        Self(BTreeMap::new())
+    }
+}
+
+impl<TYPES: NodeType> SeenVidShares<TYPES> {
+    /// Whether a share for the view and recipient of `share` has already been validated
+    #[must_use]
+    pub fn contains(&self, share: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> bool {
+        self.0
+            .get(&share.data.view_number())
+            .is_some_and(|recipients| recipients.contains(&share.data.recipient_key))
+    }
+
+    /// Records `share` as validated, returning whether it's the first for its view and recipient
+    pub fn insert(&mut self, share: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> bool {
+        self.0
+            .entry(share.data.view_number())
+            .or_default()
+            .insert(share.data.recipient_key.clone())
+    }
+
+    /// Forgets the shares for views before `view`
+    pub fn prune_before(&mut self, view: TYPES::Time) {
+        self.0 = self.0.split_off(&view);
+    }
+}
+
+/// Checks each of `items` with `verify` off the executor, running up to `max_concurrent` checks at
+/// once, and returns the items that pass, in order.
+pub async fn verify_concurrently<T: Send + 'static>(
+    items: Vec<T>,
+    max_concurrent: usize,
+    verify: impl Fn(&T) -> bool + Send + Sync + 'static,
+) -> Vec<T> {
+    let permits = Arc::new(Semaphore::new(max_concurrent));
+    let verify = Arc::new(verify);
+    let verifications = items.into_iter().map(|item| {
+        let permits = Arc::clone(&permits);
+        let verify = Arc::clone(&verify);
+        async move {
+            let permit = permits.acquire_arc().await;
+            spawn_blocking(move || {
+                let _permit = permit;
+                verify(&item).then_some(item)
+            })
+            .await
+        }
+    });
+
+    let verified = futures::future::join_all(verifications).await.into_iter();
+    // Tokio's JoinHandle's `Output` is `Result<T, JoinError>`, while in async-std it's just `T`
+    #[cfg(async_executor_impl = "tokio")]
+    let verified = verified.filter_map(Result::ok);
+    verified.flatten().collect()
+}
+
+/// Maximum number of events a paused task holds back
+pub const MAX_PAUSED_EVENTS: usize = 10_000;
+
+/// The events a paused task holds back, to handle once it's resumed
+pub struct PausedEvents<TYPES: NodeType> {
+    /// The held back events, oldest first
+    held_back: VecDeque<Arc<HotShotEvent<TYPES>>>,
+    /// How many events were dropped because [`MAX_PAUSED_EVENTS`] were already held back
+    dropped: usize,
+}
+
+impl<TYPES: NodeType> Default for PausedEvents<TYPES> {
+    fn default() -> Self {
+        Self {
+            held_back: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+}
+
+impl<TYPES: NodeType> PausedEvents<TYPES> {
+    /// How many events were dropped while paused
+    #[must_use]
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+/// Gates a task's events on [`HotShotEvent::Pause`] and [`HotShotEvent::Resume`], returning the
+/// events the task should handle now.
+///
+/// While paused, `paused_events` is `Some` and every event is held back in it. Resuming returns
+/// the held back events in the order they arrived. At most [`MAX_PAUSED_EVENTS`] are held back,
+/// after which the oldest are dropped, as if the node had missed them: a paused task can't push
+/// back on the event stream it shares with the running tasks without stalling them too. The first
+/// drop is logged as it happens, and the total number dropped once the task resumes.
+pub fn gate_paused_events<TYPES: NodeType>(
+    paused_events: &mut Option<PausedEvents<TYPES>>,
+    event: Arc<HotShotEvent<TYPES>>,
+) -> Vec<Arc<HotShotEvent<TYPES>>> {
+    match (event.as_ref(), paused_events.as_mut()) {
+        (HotShotEvent::Pause, _) => {
+            paused_events.get_or_insert_with(PausedEvents::default);
+            Vec::new()
+        }
+        (HotShotEvent::Resume, _) => {
+            let Some(paused) = paused_events.take() else {
+                return Vec::new();
+            };
+            if paused.dropped > 0 {
+                tracing::error!(
+                    "Dropped {} events while paused, as more than {MAX_PAUSED_EVENTS} arrived",
+                    paused.dropped
+                );
+            }
+            Vec::from(paused.held_back)
+        }
+        (_, Some(paused)) => {
+            if paused.held_back.len() == MAX_PAUSED_EVENTS {
+                if let Some(dropped) = paused.held_back.pop_front() {
+                    if paused.dropped == 0 {
+                        tracing::warn!(
+                            "Holding back {MAX_PAUSED_EVENTS} events while paused, so dropping the oldest, starting with {dropped}"
+                        );
+                    }
+                    paused.dropped += 1;
+                }
+            }
+            paused.held_back.push_back(event);
+            Vec::new()
+        }
+        (_, None) => vec![event],
+    }
+}
+
 /// Utilities to print anyhow logs.
 pub trait AnyhowTracing {
     /// Print logs as debug
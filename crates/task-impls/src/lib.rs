@@ -50,6 +50,9 @@ pub mod response;
 /// Task for requesting the network for things
 pub mod request;
 
+/// Task which answers the probes of peers before consensus starts
+pub mod probe;
+
 /// Task for handling logic for quorum proposals
 pub mod quorum_proposal;
 
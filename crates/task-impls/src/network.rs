@@ -1,17 +1,24 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Result;
 use async_broadcast::{Receiver, Sender};
 use async_compatibility_layer::art::async_spawn;
 use async_lock::RwLock;
 use async_trait::async_trait;
+use futures::future::join_all;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
-    data::{VidDisperse, VidDisperseShare},
+    consensus::ConsensusMetricsValue,
+    constants::Base,
+    data::{ParentDaCertificate, VidDisperse, VidDisperseShare},
     event::HotShotAction,
     message::{
-        DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message, MessageKind, Proposal,
-        SequencingMessage, VersionedMessage,
+        version_for_view, DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message,
+        MessageKind, Proposal, SequencingMessage, TxPriority, VersionedMessage,
     },
     simple_certificate::UpgradeCertificate,
     traits::{
@@ -20,9 +27,10 @@ use hotshot_types::{
         node_implementation::{ConsensusTime, NodeType},
         storage::Storage,
     },
+    transaction_pow::is_valid_pow,
     vote::{HasViewNumber, Vote},
 };
-use tracing::{error, instrument, warn};
+use tracing::{debug, error, instrument, warn};
 
 use crate::{
     events::{HotShotEvent, HotShotTaskCompleted},
@@ -34,10 +42,14 @@ pub fn quorum_filter<TYPES: NodeType>(event: &Arc<HotShotEvent<TYPES>>) -> bool
     !matches!(
         event.as_ref(),
         HotShotEvent::QuorumProposalSend(_, _)
+            | HotShotEvent::ParentDaCertificateSend(_, _)
             | HotShotEvent::QuorumVoteSend(_)
+            | HotShotEvent::ExtendedQuorumVoteSend(_, _)
             | HotShotEvent::DacSend(_, _)
             | HotShotEvent::TimeoutVoteSend(_)
+            | HotShotEvent::ProbeSend(..)
             | HotShotEvent::UpgradeDecided(_)
+            | HotShotEvent::VersionUpgrade(_)
             | HotShotEvent::ViewChange(_)
     )
 }
@@ -49,6 +61,7 @@ pub fn upgrade_filter<TYPES: NodeType>(event: &Arc<HotShotEvent<TYPES>>) -> bool
         HotShotEvent::UpgradeProposalSend(_, _)
             | HotShotEvent::UpgradeVoteSend(_)
             | HotShotEvent::UpgradeDecided(_)
+            | HotShotEvent::VersionUpgrade(_)
             | HotShotEvent::ViewChange(_)
     )
 }
@@ -60,6 +73,7 @@ pub fn da_filter<TYPES: NodeType>(event: &Arc<HotShotEvent<TYPES>>) -> bool {
         HotShotEvent::DaProposalSend(_, _)
             | HotShotEvent::DaVoteSend(_)
             | HotShotEvent::UpgradeDecided(_)
+            | HotShotEvent::VersionUpgrade(_)
             | HotShotEvent::ViewChange(_)
     )
 }
@@ -70,6 +84,7 @@ pub fn vid_filter<TYPES: NodeType>(event: &Arc<HotShotEvent<TYPES>>) -> bool {
         event.as_ref(),
         HotShotEvent::VidDisperseSend(_, _)
             | HotShotEvent::UpgradeDecided(_)
+            | HotShotEvent::VersionUpgrade(_)
             | HotShotEvent::ViewChange(_)
     )
 }
@@ -85,14 +100,83 @@ pub fn view_sync_filter<TYPES: NodeType>(event: &Arc<HotShotEvent<TYPES>>) -> bo
             | HotShotEvent::ViewSyncCommitVoteSend(_)
             | HotShotEvent::ViewSyncFinalizeVoteSend(_)
             | HotShotEvent::UpgradeDecided(_)
+            | HotShotEvent::VersionUpgrade(_)
             | HotShotEvent::ViewChange(_)
     )
 }
+
+/// Send each VID share in `messages` to its recipient, recording in `storage` which shares of the
+/// dispersal for `view` have been sent.
+///
+/// Shares that fail to send are left in the journal, to be resent by [`resume_vid_dispersals`].
+pub async fn send_vid_shares<
+    TYPES: NodeType,
+    NET: ConnectedNetwork<TYPES::SignatureKey>,
+    S: Storage<TYPES>,
+>(
+    net: &NET,
+    storage: &RwLock<S>,
+    view: TYPES::Time,
+    messages: HashMap<TYPES::SignatureKey, Vec<u8>>,
+) {
+    let sends = messages.into_iter().map(|(recipient, message)| async move {
+        // Shares are sent one recipient at a time, so we can record each one as it's sent.
+        if let Err(e) = net
+            .vid_broadcast_message(HashMap::from([(recipient.clone(), message)]))
+            .await
+        {
+            error!("Failed to send VID share to {recipient:?}: {e:?}");
+            return;
+        }
+        if let Err(e) = storage
+            .write()
+            .await
+            .record_vid_share_sent(view, &recipient)
+            .await
+        {
+            warn!("Failed to record VID share sent to {recipient:?}: {e:?}");
+        }
+    });
+    join_all(sends).await;
+}
+
+/// Resend the VID shares journaled in `storage` which weren't sent before we last stopped, such as
+/// when we crashed partway through a dispersal.
+///
+/// A crash between sending a share and recording it means that share is sent twice.
+pub async fn resume_vid_dispersals<
+    TYPES: NodeType,
+    NET: ConnectedNetwork<TYPES::SignatureKey>,
+    S: Storage<TYPES>,
+>(
+    net: &NET,
+    storage: &RwLock<S>,
+) {
+    let pending = match storage.read().await.load_pending_vid_dispersals().await {
+        Ok(pending) => pending,
+        Err(e) => {
+            error!("Failed to load pending VID dispersals: {e:?}");
+            return;
+        }
+    };
+    for (view, messages) in pending {
+        send_vid_shares(net, storage, view, messages).await;
+    }
+}
+
 /// the network message task state
 #[derive(Clone)]
 pub struct NetworkMessageTaskState<TYPES: NodeType> {
     /// Sender to send internal events this task generates to other tasks
     pub event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
+    /// Metrics for the messages received by this task
+    pub metrics: Arc<ConsensusMetricsValue>,
+    /// Number of leading zero bits the proof of work on a received transaction must have. 0
+    /// accepts every transaction.
+    pub transaction_pow_difficulty: u32,
+    /// Decided upgrade certificate. Transactions are accepted without a proof of work until the
+    /// network upgrades.
+    pub decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
 }
 
 impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
@@ -103,6 +187,10 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
         let mut transactions = Vec::new();
         for message in messages {
             tracing::trace!("Received message from network:\n\n{message:?}");
+            self.metrics
+                .inbound_messages
+                .create(vec![format!("{:?}", message.kind.purpose())])
+                .add(1);
             let sender = message.sender;
             match message.kind {
                 MessageKind::Consensus(consensus_message) => {
@@ -111,9 +199,17 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
                             GeneralConsensusMessage::Proposal(proposal) => {
                                 HotShotEvent::QuorumProposalRecv(proposal, sender)
                             }
+                            GeneralConsensusMessage::ProposalWithParentDa(proposal, parent_da) => {
+                                HotShotEvent::QuorumProposalWithParentDaRecv(
+                                    proposal, parent_da, sender,
+                                )
+                            }
                             GeneralConsensusMessage::Vote(vote) => {
                                 HotShotEvent::QuorumVoteRecv(vote.clone())
                             }
+                            GeneralConsensusMessage::ExtendedVote(vote, extension) => {
+                                HotShotEvent::ExtendedQuorumVoteRecv(vote, extension)
+                            }
                             GeneralConsensusMessage::ViewSyncPreCommitVote(view_sync_message) => {
                                 HotShotEvent::ViewSyncPreCommitVoteRecv(view_sync_message)
                             }
@@ -167,12 +263,43 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
                     broadcast_event(Arc::new(event), &self.event_stream).await;
                 }
                 MessageKind::Data(message) => match message {
-                    DataMessage::SubmitTransaction(transaction, _) => {
-                        transactions.push(transaction);
+                    DataMessage::SubmitTransaction(transaction, view) => {
+                        if self.transaction_pow_difficulty == 0
+                            || matches!(
+                                version_for_view(view, &self.decided_upgrade_certificate),
+                                Ok(version) if version == Base::VERSION
+                            )
+                        {
+                            transactions.push((transaction, TxPriority::Normal));
+                        } else {
+                            debug!("Dropping transaction without a proof of work");
+                            self.metrics.invalid_pow_transactions.add(1);
+                        }
                     }
-                    DataMessage::DataResponse(_) | DataMessage::RequestData(_) => {
+                    DataMessage::SubmitTransactionWithPow(submission) => {
+                        if is_valid_pow(
+                            &submission.transaction,
+                            submission.nonce,
+                            self.transaction_pow_difficulty,
+                        ) {
+                            transactions.push((submission.transaction, submission.priority));
+                        } else {
+                            debug!("Dropping transaction without a valid proof of work");
+                            self.metrics.invalid_pow_transactions.add(1);
+                        }
+                    }
+                    DataMessage::DataResponse(_)
+                    | DataMessage::SignedDataResponse(_)
+                    | DataMessage::RequestData(_) => {
                         warn!("Request and Response messages should not be received in the NetworkMessage task");
                     }
+                    DataMessage::Probe(message) => {
+                        broadcast_event(
+                            Arc::new(HotShotEvent::ProbeRecv(message, sender)),
+                            &self.event_stream,
+                        )
+                        .await;
+                    }
                 },
             };
         }
@@ -205,6 +332,10 @@ pub struct NetworkEventTaskState<
     pub storage: Arc<RwLock<S>>,
     /// Decided upgrade certificate
     pub decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
+    /// How long a broadcast may take before it's given up on
+    pub broadcast_timeout: Duration,
+    /// The parent DA certificates to attach to our quorum proposals, by the view of the proposal
+    pub parent_da_certificates: BTreeMap<TYPES::Time, ParentDaCertificate<TYPES>>,
 }
 
 #[async_trait]
@@ -255,14 +386,27 @@ impl<
             match event.as_ref().clone() {
                 HotShotEvent::QuorumProposalSend(proposal, sender) => {
                     maybe_action = Some(HotShotAction::Propose);
+                    let message = match self
+                        .parent_da_certificates
+                        .remove(&proposal.data.view_number)
+                    {
+                        Some(parent_da) => {
+                            GeneralConsensusMessage::ProposalWithParentDa(proposal, parent_da)
+                        }
+                        None => GeneralConsensusMessage::Proposal(proposal),
+                    };
                     (
                         sender,
                         MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
-                            GeneralConsensusMessage::Proposal(proposal),
+                            message,
                         )),
                         TransmitType::Broadcast,
                     )
                 }
+                HotShotEvent::ParentDaCertificateSend(view, parent_da) => {
+                    self.parent_da_certificates.insert(view, parent_da);
+                    return;
+                }
 
                 // ED Each network task is subscribed to all these message types.  Need filters per network task
                 HotShotEvent::QuorumVoteSend(vote) => {
@@ -275,6 +419,25 @@ impl<
                         TransmitType::Direct(membership.leader(vote.view_number() + 1)),
                     )
                 }
+                HotShotEvent::ExtendedQuorumVoteSend(vote, extension) => {
+                    maybe_action = Some(HotShotAction::Vote);
+                    let message = if matches!(
+                        version_for_view(vote.view_number(), &self.decided_upgrade_certificate),
+                        Ok(version) if version == Base::VERSION
+                    ) {
+                        debug!("Dropping the vote extension before the upgrade");
+                        GeneralConsensusMessage::Vote(vote.clone())
+                    } else {
+                        GeneralConsensusMessage::ExtendedVote(vote.clone(), extension)
+                    };
+                    (
+                        vote.signing_key(),
+                        MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
+                            message,
+                        )),
+                        TransmitType::Direct(membership.leader(vote.view_number() + 1)),
+                    )
+                }
                 HotShotEvent::VidDisperseSend(proposal, sender) => {
                     self.handle_vid_disperse_proposal(proposal, &sender);
                     return;
@@ -376,8 +539,15 @@ impl<
                         TransmitType::Direct(membership.leader(vote.view_number())),
                     )
                 }
+                HotShotEvent::ProbeSend(message, sender, recipient) => (
+                    sender,
+                    MessageKind::<TYPES>::Data(DataMessage::Probe(message)),
+                    recipient.map_or(TransmitType::Broadcast, TransmitType::Direct),
+                ),
                 HotShotEvent::ViewChange(view) => {
                     self.view = view;
+                    // Anything attached to a proposal for an earlier view was never sent
+                    self.parent_da_certificates = self.parent_da_certificates.split_off(&view);
                     self.channel
                         .update_view::<TYPES>(self.view.u64(), membership)
                         .await;
@@ -387,13 +557,26 @@ impl<
                     self.decided_upgrade_certificate = Some(cert.clone());
                     return;
                 }
+                HotShotEvent::VersionUpgrade(version) => {
+                    // Moving to a version other than the decided upgrade's revokes it.
+                    if self
+                        .decided_upgrade_certificate
+                        .as_ref()
+                        .is_some_and(|cert| cert.data.new_version != version)
+                    {
+                        self.decided_upgrade_certificate = None;
+                    }
+                    return;
+                }
                 _ => {
                     return;
                 }
             };
         let broadcast_delay = match &message_kind {
             MessageKind::Consensus(
-                SequencingMessage::General(GeneralConsensusMessage::Vote(_))
+                SequencingMessage::General(
+                    GeneralConsensusMessage::Vote(_) | GeneralConsensusMessage::ExtendedVote(..),
+                )
                 | SequencingMessage::Da(_),
             ) => BroadcastDelay::View(*message_kind.view_number()),
             _ => BroadcastDelay::None,
@@ -407,6 +590,7 @@ impl<
         let net = Arc::clone(&self.channel);
         let storage = Arc::clone(&self.storage);
         let decided_upgrade_certificate = self.decided_upgrade_certificate.clone();
+        let broadcast_timeout = self.broadcast_timeout;
         async_spawn(async move {
             if NetworkEventTaskState::<TYPES, COMMCHANNEL, S>::maybe_record_action(
                 maybe_action,
@@ -440,12 +624,22 @@ impl<
                     net.direct_message(serialized_message, recipient).await
                 }
                 TransmitType::Broadcast => {
-                    net.broadcast_message(serialized_message, committee, broadcast_delay)
-                        .await
+                    net.broadcast_message_timeout(
+                        serialized_message,
+                        committee,
+                        broadcast_delay,
+                        broadcast_timeout,
+                    )
+                    .await
                 }
                 TransmitType::DaCommitteeBroadcast => {
-                    net.da_broadcast_message(serialized_message, committee, broadcast_delay)
-                        .await
+                    net.da_broadcast_message_timeout(
+                        serialized_message,
+                        committee,
+                        broadcast_delay,
+                        broadcast_timeout,
+                    )
+                    .await
                 }
             };
 
@@ -490,7 +684,7 @@ impl<
         async_spawn(async move {
             if NetworkEventTaskState::<TYPES, COMMCHANNEL, S>::maybe_record_action(
                 Some(HotShotAction::VidDisperse),
-                storage,
+                Arc::clone(&storage),
                 view,
             )
             .await
@@ -498,10 +692,20 @@ impl<
             {
                 return;
             }
-            match net.vid_broadcast_message(messages).await {
-                Ok(()) => {}
-                Err(e) => error!("Failed to send message from network task: {:?}", e),
+            // Shares of earlier dispersals still unsent aren't worth resending anymore
+            if let Err(e) = storage.write().await.gc_vid_dispersals(view).await {
+                warn!("Failed to garbage collect VID dispersals: {e:?}");
+            }
+            if let Err(e) = storage
+                .write()
+                .await
+                .append_vid_dispersal(view, &messages)
+                .await
+            {
+                warn!("Not sending VID dispersal because of storage error: {e:?}");
+                return;
             }
+            send_vid_shares(&*net, &storage, view, messages).await;
         });
 
         None
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    probe::{ProbeDescriptor, ProbeMessage},
+    traits::node_implementation::NodeType,
+};
+use tracing::warn;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// Answers the probes of peers checking they agree with us before starting consensus.
+pub struct ProbeTaskState<TYPES: NodeType> {
+    /// Our descriptor, which we reply to each probe with
+    pub descriptor: ProbeDescriptor<TYPES>,
+
+    /// Our public key
+    pub public_key: TYPES::SignatureKey,
+}
+
+impl<TYPES: NodeType> ProbeTaskState<TYPES> {
+    /// Reply to a probe request from `sender`, unless it is our own.
+    pub async fn handle(
+        &self,
+        event: &HotShotEvent<TYPES>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let HotShotEvent::ProbeRecv(ProbeMessage::Request(view, theirs), sender) = event else {
+            return;
+        };
+        if *sender == self.public_key {
+            return;
+        }
+        if !self.descriptor.compare(theirs).passed() {
+            warn!(?sender, "Probed by a peer which disagrees with us");
+        }
+        broadcast_event(
+            Arc::new(HotShotEvent::ProbeSend(
+                ProbeMessage::Reply(*view, self.descriptor.clone()),
+                self.public_key.clone(),
+                Some(sender.clone()),
+            )),
+            event_stream,
+        )
+        .await;
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> TaskState for ProbeTaskState<TYPES> {
+    type Event = HotShotEvent<TYPES>;
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.handle(&event, sender).await;
+        Ok(())
+    }
+
+    async fn cancel_subtasks(&mut self) {}
+}
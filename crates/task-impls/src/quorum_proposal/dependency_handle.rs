@@ -16,15 +16,13 @@ use hotshot_types::{
     consensus::{CommitmentAndMetadata, Consensus},
     data::{Leaf, QuorumProposal, VidDisperse, ViewChangeEvidence},
     message::Proposal,
-    traits::{
-        block_contents::BlockHeader, node_implementation::NodeType, signature_key::SignatureKey,
-    },
+    traits::{block_contents::BlockHeader, node_implementation::NodeType, signature_key::Signer},
 };
 use tracing::{debug, error};
 use vbs::version::Version;
 
 use crate::{
-    consensus::helpers::{fetch_proposal, parent_leaf_and_state},
+    consensus::helpers::{fetch_proposal, parent_da_certificate, parent_leaf_and_state},
     events::HotShotEvent,
     helpers::broadcast_event,
 };
@@ -49,6 +47,9 @@ pub(crate) enum ProposalDependency {
 
     /// For the `VidShareValidated` event.
     VidShare,
+
+    /// For the `DaCertificateRecv` and `DacSend` events.
+    Dac,
 }
 
 /// Handler for the proposal dependency
@@ -74,8 +75,8 @@ pub struct ProposalDependencyHandle<TYPES: NodeType> {
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Round start delay from config, in milliseconds.
     pub round_start_delay: u64,
@@ -83,7 +84,7 @@ pub struct ProposalDependencyHandle<TYPES: NodeType> {
     /// Shared consensus task state
     pub consensus: Arc<RwLock<Consensus<TYPES>>>,
 
-    /// The current version of consensus
+    /// The current version of consensus, which the block header is built for
     pub version: Version,
 }
 
@@ -143,9 +144,19 @@ impl<TYPES: NodeType> ProposalDependencyHandle<TYPES> {
             "Proposed leaf parent does not equal high qc"
         );
 
-        let signature =
-            TYPES::SignatureKey::sign(&self.private_key, proposed_leaf.commit().as_ref())
-                .context("Failed to compute proposed_leaf.commit()")?;
+        let signature = self
+            .signer
+            .sign(proposed_leaf.commit().as_ref())
+            .await
+            .context("Failed to compute proposed_leaf.commit()")?;
+        let parent_da = parent_da_certificate(
+            &proposal,
+            &parent_leaf,
+            &self.consensus,
+            self.signer.as_ref(),
+            self.version,
+        )
+        .await;
 
         let message = Proposal {
             data: proposal,
@@ -162,6 +173,16 @@ impl<TYPES: NodeType> ProposalDependencyHandle<TYPES> {
             .await
             .update_last_proposed_view(message.clone())?;
         async_sleep(Duration::from_millis(self.round_start_delay)).await;
+        if let Some(parent_da) = parent_da {
+            broadcast_event(
+                Arc::new(HotShotEvent::ParentDaCertificateSend(
+                    self.view_number,
+                    parent_da,
+                )),
+                &self.sender,
+            )
+            .await;
+        }
         broadcast_event(
             Arc::new(HotShotEvent::QuorumProposalSend(
                 message.clone(),
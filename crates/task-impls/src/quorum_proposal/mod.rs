@@ -1,24 +1,26 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use async_broadcast::{Receiver, Sender};
+use async_compatibility_layer::art::{async_sleep, async_spawn};
 use async_lock::RwLock;
 #[cfg(async_executor_impl = "async-std")]
 use async_std::task::JoinHandle;
 use async_trait::async_trait;
 use either::Either;
 use hotshot_task::{
+    cancellation::CancellationToken,
     dependency::{AndDependency, EventDependency, OrDependency},
     dependency_task::DependencyTask,
     task::TaskState,
 };
 use hotshot_types::{
     consensus::Consensus,
-    event::Event,
+    simple_certificate::QuorumCertificate,
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
         storage::Storage,
     },
     vote::{Certificate, HasViewNumber},
@@ -30,8 +32,11 @@ use vbs::version::Version;
 
 use self::dependency_handle::{ProposalDependency, ProposalDependencyHandle};
 use crate::{
+    consensus::update_high_qc,
     events::HotShotEvent,
-    helpers::{broadcast_event, cancel_task},
+    helpers::{
+        broadcast_event, cancel_task, gate_paused_events, ExternalEventSender, PausedEvents,
+    },
 };
 
 mod dependency_handle;
@@ -51,7 +56,7 @@ pub struct QuorumProposalTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>
     pub da_network: Arc<I::DaNetwork>,
 
     /// Output events to application
-    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+    pub output_event_stream: ExternalEventSender<TYPES>,
 
     /// Immutable instance state
     pub instance_state: Arc<TYPES::InstanceState>,
@@ -65,8 +70,8 @@ pub struct QuorumProposalTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// View timeout from config.
     pub timeout: u64,
@@ -88,9 +93,33 @@ pub struct QuorumProposalTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>
 
     /// Current version of consensus
     pub version: Version,
+
+    /// Events received while paused, to handle once resumed; `None` while running
+    pub paused_events: Option<PausedEvents<TYPES>>,
+
+    /// Whether to wait for the DA certificate of a view before proposing for it
+    pub wait_for_dac_to_propose: bool,
+
+    /// The highest QC waiting to be written to storage. Writes are coalesced over
+    /// `round_start_delay`, and `Some` while a write is scheduled.
+    pub pending_high_qc: Arc<RwLock<Option<QuorumCertificate<TYPES>>>>,
+
+    /// The scheduled write of `pending_high_qc`, flushed when the task shuts down
+    pub high_qc_write_task: Option<JoinHandle<()>>,
+
+    /// Stops the proposal dependency tasks along with this task
+    pub cancellation: CancellationToken,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalTaskState<TYPES, I> {
+    /// Start the task at `version`, e.g. to test proposing after an upgrade without replaying it.
+    /// Later `VersionUpgrade` events still override it.
+    #[must_use]
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
     /// Create an event dependency
     #[instrument(skip_all, fields(id = self.id, latest_proposed_view = *self.latest_proposed_view), name = "Create event dependency", level = "info")]
     fn create_event_dependency(
@@ -155,6 +184,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalTaskState<TYPE
                             return false;
                         }
                     }
+                    ProposalDependency::Dac => {
+                        if let HotShotEvent::DaCertificateRecv(cert)
+                        | HotShotEvent::DacSend(cert, _) = event
+                        {
+                            cert.view_number()
+                        } else {
+                            return false;
+                        }
+                    }
                 };
                 let valid = event_view == view_number;
                 if valid {
@@ -208,6 +246,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalTaskState<TYPE
             event_receiver.clone(),
         );
 
+        let mut dac_dependency = self.create_event_dependency(
+            ProposalDependency::Dac,
+            view_number,
+            event_receiver.clone(),
+        );
+
         match event.as_ref() {
             HotShotEvent::SendPayloadCommitmentAndMetadata(..) => {
                 payload_commitment_dependency.mark_as_completed(Arc::clone(&event));
@@ -232,6 +276,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalTaskState<TYPE
             HotShotEvent::UpdateHighQc(_) => {
                 qc_dependency.mark_as_completed(event);
             }
+            HotShotEvent::DaCertificateRecv(_) | HotShotEvent::DacSend(..) => {
+                dac_dependency.mark_as_completed(event);
+            }
             _ => {}
         };
 
@@ -252,7 +299,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalTaskState<TYPE
             secondary_deps.push(AndDependency::from_deps(vec![qc_dependency]));
         }
 
-        let primary_deps = vec![payload_commitment_dependency, vid_share_dependency];
+        let mut primary_deps = vec![payload_commitment_dependency, vid_share_dependency];
+        if self.wait_for_dac_to_propose {
+            primary_deps.push(dac_dependency);
+        }
 
         AndDependency::from_deps(vec![OrDependency::from_deps(vec![
             AndDependency::from_deps(vec![
@@ -296,6 +346,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalTaskState<TYPE
         let dependency_chain =
             self.create_and_complete_dependencies(view_number, &event_receiver, event);
 
+        // Give up on dependencies which aren't met within a view, e.g. if the VID share never
+        // arrives, rather than leaving the task waiting until the view changes.
+        let timeout_sender = event_sender.clone();
+        let on_timeout = async move {
+            warn!("Timed out waiting for the dependencies to propose for view {view_number:?}");
+            broadcast_event(
+                Arc::new(HotShotEvent::ProposalDependencyTimeout(view_number)),
+                &timeout_sender,
+            )
+            .await;
+        };
+
         let dependency_task = DependencyTask::new(
             dependency_chain,
             ProposalDependencyHandle {
@@ -305,15 +367,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalTaskState<TYPE
                 receiver: event_receiver,
                 quorum_membership: Arc::clone(&self.quorum_membership),
                 public_key: self.public_key.clone(),
-                private_key: self.private_key.clone(),
+                signer: Arc::clone(&self.signer),
                 round_start_delay: self.round_start_delay,
                 instance_state: Arc::clone(&self.instance_state),
                 consensus: Arc::clone(&self.consensus),
                 version: self.version,
             },
+        )
+        .with_cancellation(self.cancellation.clone());
+        self.proposal_dependencies.insert(
+            view_number,
+            dependency_task.run_with_timeout(Duration::from_millis(self.timeout), on_timeout),
         );
-        self.proposal_dependencies
-            .insert(view_number, dependency_task.run());
     }
 
     /// Update the latest proposed view number.
@@ -340,6 +405,55 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalTaskState<TYPE
         false
     }
 
+    /// Schedule `qc` to be written to storage, replacing any lower QC still waiting to be. The
+    /// first QC scheduled opens a window of `round_start_delay`, at the end of which only the
+    /// highest QC seen within it is written.
+    async fn persist_high_qc(&mut self, qc: QuorumCertificate<TYPES>) {
+        let mut pending = self.pending_high_qc.write().await;
+        match pending.as_ref() {
+            Some(pending_qc) if pending_qc.view_number() >= qc.view_number() => return,
+            Some(_) => {
+                *pending = Some(qc);
+                return;
+            }
+            None => *pending = Some(qc),
+        }
+        drop(pending);
+
+        let pending = Arc::clone(&self.pending_high_qc);
+        let storage = Arc::clone(&self.storage);
+        let delay = Duration::from_millis(self.round_start_delay);
+        self.high_qc_write_task = Some(async_spawn(async move {
+            async_sleep(delay).await;
+            // Take the QC while holding the storage lock, so a write scheduled after this one
+            // can't land first.
+            let mut storage = storage.write().await;
+            let Some(qc) = pending.write().await.take() else {
+                return;
+            };
+            if let Err(e) = storage.update_high_qc(qc).await {
+                warn!("Failed to store High QC of QC we formed; error = {:?}", e);
+            }
+        }));
+    }
+
+    /// Write the pending high QC to storage now, rather than at the end of its window, and stop
+    /// the scheduled write.
+    pub async fn flush_high_qc(&mut self) {
+        let mut storage = self.storage.write().await;
+        if let Some(qc) = self.pending_high_qc.write().await.take() {
+            if let Err(e) = storage.update_high_qc(qc).await {
+                warn!("Failed to store High QC of QC we formed; error = {:?}", e);
+            }
+        }
+        drop(storage);
+
+        // With the QC taken, the scheduled write has nothing left to do.
+        if let Some(task) = self.high_qc_write_task.take() {
+            cancel_task(task).await;
+        }
+    }
+
     /// Handles a consensus event received on the event stream
     #[instrument(skip_all, fields(id = self.id, latest_proposed_view = *self.latest_proposed_view), name = "handle method", level = "error")]
     pub async fn handle(
@@ -444,14 +558,30 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalTaskState<TYPE
                     Arc::clone(&event),
                 );
             }
-            HotShotEvent::UpdateHighQc(qc) => {
-                // First, update the high QC.
-                if let Err(e) = self.consensus.write().await.update_high_qc(qc.clone()) {
-                    tracing::trace!("Failed to update high qc; error = {e}");
+            // We receive the certificate from the DA leader, or form it ourselves when we lead
+            // the DA committee too.
+            HotShotEvent::DaCertificateRecv(cert) | HotShotEvent::DacSend(cert, _) => {
+                if !self.wait_for_dac_to_propose {
+                    return;
                 }
 
-                if let Err(e) = self.storage.write().await.update_high_qc(qc.clone()).await {
-                    warn!("Failed to store High QC of QC we formed; error = {:?}", e);
+                self.create_dependency_task_if_new(
+                    cert.view_number(),
+                    event_receiver,
+                    event_sender,
+                    Arc::clone(&event),
+                );
+            }
+            HotShotEvent::ProposalDependencyTimeout(view_number) => {
+                // The timed out task has already finished.
+                self.proposal_dependencies.remove(view_number);
+            }
+            HotShotEvent::UpdateHighQc(qc) => {
+                // First, update the high QC. Only a QC which raised it is persisted, so we never
+                // write a lower QC than one already written.
+                match update_high_qc(&self.consensus, qc.clone(), &event_sender).await {
+                    Ok(()) => self.persist_high_qc(qc.clone()).await,
+                    Err(e) => tracing::trace!("Failed to update high qc; error = {e}"),
                 }
 
                 let view_number = qc.view_number() + 1;
@@ -479,12 +609,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState
         sender: &Sender<Arc<Self::Event>>,
         receiver: &Receiver<Arc<Self::Event>>,
     ) -> Result<()> {
-        self.handle(event, receiver.clone(), sender.clone()).await;
+        for event in gate_paused_events(&mut self.paused_events, event) {
+            self.handle(event, receiver.clone(), sender.clone()).await;
+        }
 
         Ok(())
     }
 
     async fn cancel_subtasks(&mut self) {
+        self.flush_high_qc().await;
+
         for handle in self
             .proposal_dependencies
             .drain()
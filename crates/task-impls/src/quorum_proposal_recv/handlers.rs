@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use std::sync::Arc;
+use std::{collections::btree_map::Entry, sync::Arc};
 
 use anyhow::{bail, Context, Result};
 use async_broadcast::{broadcast, Sender};
@@ -8,7 +8,7 @@ use async_lock::RwLockUpgradableReadGuard;
 use committable::Committable;
 use hotshot_types::{
     data::{Leaf, QuorumProposal},
-    message::Proposal,
+    message::{EquivocationProof, Proposal},
     simple_certificate::QuorumCertificate,
     traits::{
         election::Membership,
@@ -25,7 +25,8 @@ use super::QuorumProposalRecvTaskState;
 use crate::{
     consensus::{
         helpers::{
-            fetch_proposal, validate_proposal_safety_and_liveness, validate_proposal_view_and_certs,
+            fetch_proposal, report_liveness_only_vote, update_high_qc, validate_parent_version,
+            validate_proposal_safety_and_liveness, validate_proposal_view_and_certs,
         },
         view_change::{update_view, SEND_VIEW_CHANGE_EVENT},
     },
@@ -68,6 +69,15 @@ async fn validate_proposal_liveness<TYPES: NodeType, I: NodeImplementation<TYPES
     if let Err(e) = consensus_write.update_validated_state_map(view_number, view.clone()) {
         tracing::trace!("{e:?}");
     }
+    if proposal
+        .validate_signature(&task_state.quorum_membership)
+        .is_ok()
+    {
+        consensus_write.update_proposers(
+            view_number,
+            task_state.quorum_membership.leader(view_number),
+        );
+    }
     consensus_write.update_saved_leaves(leaf.clone());
 
     if let Err(e) = task_state
@@ -99,9 +109,66 @@ async fn validate_proposal_liveness<TYPES: NodeType, I: NodeImplementation<TYPES
         bail!("Liveness invalid.");
     }
 
+    report_liveness_only_vote(
+        view_number,
+        &task_state.consensus,
+        &task_state.output_event_stream,
+    )
+    .await;
+
     Ok(QuorumProposalValidity::Liveness)
 }
 
+/// Remember the first validly signed `proposal` for its view, and fail if the view's leader
+/// already signed a different one, broadcasting the proof that it equivocated. Proposals are
+/// tracked by the leader who signed them rather than whoever sent them over the network.
+async fn check_equivocation<TYPES: NodeType, I: NodeImplementation<TYPES>>(
+    proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
+    event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    task_state: &mut QuorumProposalRecvTaskState<TYPES, I>,
+) -> Result<()> {
+    let view_number = proposal.data.view_number();
+    let leader = task_state.quorum_membership.leader(view_number);
+    let cur_view = task_state.cur_view;
+    task_state
+        .seen_proposals
+        .retain(|(view, _), _| *view >= cur_view);
+
+    match task_state
+        .seen_proposals
+        .entry((view_number, leader.clone()))
+    {
+        Entry::Vacant(entry) => {
+            if proposal
+                .validate_signature(&task_state.quorum_membership)
+                .is_ok()
+            {
+                entry.insert(proposal.clone());
+            }
+        }
+        Entry::Occupied(entry) => {
+            let proof = EquivocationProof {
+                first: entry.get().clone(),
+                second: proposal.clone(),
+            };
+            if proof.verify(&task_state.quorum_membership) {
+                broadcast_event(
+                    Arc::new(HotShotEvent::Equivocation(
+                        view_number,
+                        leader.clone(),
+                        proof,
+                    )),
+                    event_sender,
+                )
+                .await;
+                bail!("Leader {leader} equivocated in view {}", *view_number);
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Handles the `QuorumProposalRecv` event by first validating the cert itself for the view, and then
 /// updating the states, which runs when the proposal cannot be found in the internal state map.
 ///
@@ -117,7 +184,6 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
     task_state: &mut QuorumProposalRecvTaskState<TYPES, I>,
 ) -> Result<QuorumProposalValidity> {
     let sender = sender.clone();
-    let cur_view = task_state.cur_view;
 
     validate_proposal_view_and_certs(
         proposal,
@@ -128,6 +194,8 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
     )
     .context("Failed to validate proposal view or attached certs")?;
 
+    check_equivocation(proposal, event_sender, task_state).await?;
+
     let view_number = proposal.data.view_number();
     let view_leader_key = task_state.quorum_membership.leader(view_number);
     let justify_qc = proposal.data.justify_qc.clone();
@@ -149,7 +217,8 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
         &mut task_state.timeout_task,
         &task_state.output_event_stream,
         SEND_VIEW_CHANGE_EVENT,
-        task_state.quorum_membership.leader(cur_view) == task_state.public_key,
+        task_state.quorum_membership.as_ref(),
+        &task_state.public_key,
     )
     .await
     {
@@ -189,6 +258,17 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
         None => None,
     };
 
+    // The parent may be from before an upgrade took effect, in which case it's held to the
+    // rules of its own version rather than the proposal's.
+    if let Some((ref leaf, _)) = parent {
+        validate_parent_version(
+            leaf,
+            view_number,
+            &task_state.decided_upgrade_cert,
+            task_state.vid_membership.as_ref(),
+        )?;
+    }
+
     if justify_qc.view_number() > consensus_read.high_qc().view_number {
         if let Err(e) = task_state
             .storage
@@ -202,11 +282,9 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
     }
     drop(consensus_read);
 
-    let mut consensus_write = task_state.consensus.write().await;
-    if let Err(e) = consensus_write.update_high_qc(justify_qc.clone()) {
+    if let Err(e) = update_high_qc(&task_state.consensus, justify_qc.clone(), event_sender).await {
         tracing::trace!("{e:?}");
     }
-    drop(consensus_write);
 
     broadcast_event(
         HotShotEvent::UpdateHighQc(justify_qc.clone()).into(),
@@ -229,10 +307,12 @@ pub(crate) async fn handle_quorum_proposal_recv<TYPES: NodeType, I: NodeImplemen
         Arc::clone(&task_state.consensus),
         None,
         Arc::clone(&task_state.quorum_membership),
+        Arc::clone(&task_state.vid_membership),
         view_leader_key,
         event_sender.clone(),
         sender,
         task_state.output_event_stream.clone(),
+        task_state.validation_level,
     )
     .await?;
 
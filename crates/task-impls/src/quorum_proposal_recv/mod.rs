@@ -12,14 +12,15 @@ use futures::future::join_all;
 use hotshot_task::task::{Task, TaskState};
 use hotshot_types::{
     consensus::Consensus,
-    data::ViewChangeEvidence,
-    event::Event,
+    data::{QuorumProposal, ViewChangeEvidence},
+    message::Proposal,
     simple_certificate::UpgradeCertificate,
     traits::{
         node_implementation::{NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
     },
     vote::{HasViewNumber, VoteDependencyData},
+    ValidationLevel,
 };
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::JoinHandle;
@@ -28,9 +29,11 @@ use vbs::version::Version;
 
 use self::handlers::handle_quorum_proposal_recv;
 use crate::{
-    consensus::helpers::parent_leaf_and_state,
+    consensus::helpers::{parent_leaf_and_state, save_parent_da_certificate},
     events::HotShotEvent,
-    helpers::{broadcast_event, cancel_task},
+    helpers::{
+        broadcast_event, cancel_task, gate_paused_events, ExternalEventSender, PausedEvents,
+    },
     quorum_proposal_recv::handlers::QuorumProposalValidity,
 };
 
@@ -43,8 +46,8 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Reference to consensus. The replica will require a write lock on this.
     pub consensus: Arc<RwLock<Consensus<TYPES>>>,
@@ -61,9 +64,15 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
     /// Membership for Quorum Certs/votes
     pub quorum_membership: Arc<TYPES::Membership>,
 
+    /// Membership of the VID storage nodes, for verifying VID shares and commitments
+    pub vid_membership: Arc<TYPES::Membership>,
+
     /// Membership for Timeout votes/certs
     pub timeout_membership: Arc<TYPES::Membership>,
 
+    /// Membership for DA committee Votes/certs
+    pub da_membership: Arc<TYPES::Membership>,
+
     /// timeout task handle
     pub timeout_task: JoinHandle<()>,
 
@@ -74,7 +83,7 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
     pub round_start_delay: u64,
 
     /// Output events to application
-    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+    pub output_event_stream: ExternalEventSender<TYPES>,
 
     /// This node's storage ref
     pub storage: Arc<RwLock<I::Storage>>,
@@ -105,6 +114,17 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
 
     /// Current version of consensus
     pub version: Version,
+
+    /// How thoroughly incoming proposals are validated
+    pub validation_level: ValidationLevel,
+
+    /// The first validly signed proposal we received for each current view, keyed by the view and
+    /// its leader, to catch the leader equivocating
+    pub seen_proposals:
+        BTreeMap<(TYPES::Time, TYPES::SignatureKey), Proposal<TYPES, QuorumProposal<TYPES>>>,
+
+    /// Events received while paused, to handle once resumed; `None` while running
+    pub paused_events: Option<PausedEvents<TYPES>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalRecvTaskState<TYPES, I> {
@@ -128,6 +148,63 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumProposalRecvTaskState<
         event: Arc<HotShotEvent<TYPES>>,
         event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
     ) {
+        match event.as_ref() {
+            HotShotEvent::UpgradeDecided(cert) => {
+                self.decided_upgrade_cert = Some(cert.clone());
+            }
+            HotShotEvent::VersionUpgrade(version) => {
+                // Moving to a version other than the decided upgrade's revokes it.
+                if self
+                    .decided_upgrade_cert
+                    .as_ref()
+                    .is_some_and(|cert| cert.data.new_version != *version)
+                {
+                    warn!("Revoking the decided upgrade certificate");
+                    self.decided_upgrade_cert = None;
+                }
+            }
+            _ => {}
+        }
+
+        #[cfg(feature = "dependency-tasks")]
+        if let HotShotEvent::QuorumProposalWithParentDaRecv(proposal, parent_da, sender) =
+            event.as_ref()
+        {
+            if let Err(e) = save_parent_da_certificate(
+                proposal,
+                parent_da,
+                &self.quorum_membership,
+                &self.da_membership,
+                &self.consensus,
+            )
+            .await
+            {
+                warn!(
+                    "Rejecting proposal for view {:?}: {e:#}",
+                    proposal.data.view_number()
+                );
+                return;
+            }
+
+            // Let a vote on the parent that is still waiting for its DAC go ahead.
+            broadcast_event(
+                Arc::new(HotShotEvent::DaCertificateValidated(
+                    parent_da.certificate.clone(),
+                )),
+                &event_stream,
+            )
+            .await;
+            broadcast_event(
+                Arc::new(HotShotEvent::QuorumProposalRecv(
+                    proposal.clone(),
+                    sender.clone(),
+                )),
+                &event_stream,
+            )
+            .await;
+            return;
+        }
+
         #[cfg(feature = "dependency-tasks")]
         if let HotShotEvent::QuorumProposalRecv(proposal, sender) = event.as_ref() {
             match handle_quorum_proposal_recv(proposal, sender, &event_stream, self).await {
@@ -204,7 +281,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState
         sender: &Sender<Arc<Self::Event>>,
         _receiver: &Receiver<Arc<Self::Event>>,
     ) -> Result<()> {
-        self.handle(event, sender.clone()).await;
+        for event in gate_paused_events(&mut self.paused_events, event) {
+            self.handle(event, sender.clone()).await;
+        }
 
         Ok(())
     }
@@ -9,7 +9,7 @@ use hotshot_types::{
     traits::node_implementation::{ConsensusTime, NodeImplementation, NodeType},
     vote::HasViewNumber,
 };
-use tracing::debug;
+use tracing::{debug, warn};
 
 use super::QuorumVoteTaskState;
 use crate::{
@@ -93,23 +93,33 @@ pub(crate) async fn handle_quorum_proposal_validated<
             consensus_writer.last_decided_view()
         );
 
+        // Never emit a second `Decide` for a view, e.g. if this event was handled twice.
+        let emit_decide = consensus_writer
+            .update_last_emitted_decide_view(decided_view_number)
+            .inspect_err(|e| warn!("Suppressing duplicate decide; error = {e:?}"))
+            .is_ok();
+
         // We don't need to hold this while we broadcast
         drop(consensus_writer);
 
         // First, send an update to everyone saying that we've reached a decide
-        broadcast_event(
-            Event {
-                view_number: decided_view_number,
-                event: EventType::Decide {
-                    leaf_chain: Arc::new(leaf_views),
-                    // This is never *not* none if we've reached a new decide, so this is safe to unwrap.
-                    qc: Arc::new(new_decide_qc.unwrap()),
-                    block_size: included_txns.map(|txns| txns.len().try_into().unwrap()),
+        if emit_decide {
+            let decided_view_count = leaf_views.len();
+            broadcast_event(
+                Event {
+                    view_number: decided_view_number,
+                    event: EventType::Decide {
+                        leaf_chain: Arc::new(leaf_views),
+                        // This is never *not* none if we've reached a new decide, so this is safe to unwrap.
+                        qc: Arc::new(new_decide_qc.unwrap()),
+                        block_size: included_txns.map(|txns| txns.len().try_into().unwrap()),
+                        decided_view_count,
+                    },
                 },
-            },
-            &task_state.output_event_stream,
-        )
-        .await;
+                &task_state.output_event_stream,
+            )
+            .await;
+        }
 
         broadcast_event(Arc::new(HotShotEvent::LeafDecided(leaves_decided)), sender).await;
         debug!("Successfully sent decide event");
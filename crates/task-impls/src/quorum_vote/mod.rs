@@ -8,6 +8,7 @@ use async_std::task::JoinHandle;
 use async_trait::async_trait;
 use committable::Committable;
 use hotshot_task::{
+    cancellation::CancellationToken,
     dependency::{AndDependency, Dependency, EventDependency, OrDependency},
     dependency_task::{DependencyTask, HandleDepOutput},
     task::TaskState,
@@ -15,31 +16,31 @@ use hotshot_task::{
 use hotshot_types::{
     consensus::Consensus,
     data::{Leaf, VidDisperseShare},
-    event::Event,
     message::Proposal,
     simple_vote::{QuorumData, QuorumVote},
     traits::{
         block_contents::BlockHeader,
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
         storage::Storage,
         ValidatedState,
     },
     utils::{View, ViewInner},
-    vid::vid_scheme,
     vote::{Certificate, HasViewNumber},
 };
-use jf_vid::VidScheme;
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::JoinHandle;
 use tracing::{debug, error, instrument, trace, warn};
 use vbs::version::Version;
 
 use crate::{
-    consensus::helpers::fetch_proposal,
+    consensus::helpers::{fetch_proposal, verify_saved_payload},
     events::HotShotEvent,
-    helpers::{broadcast_event, cancel_task},
+    helpers::{
+        broadcast_event, cancel_task, gate_paused_events, is_valid_vid_share, valid_vid_shares,
+        ExternalEventSender, PausedEvents, SeenVidShares,
+    },
     quorum_vote::handlers::handle_quorum_proposal_validated,
 };
 
@@ -64,8 +65,8 @@ enum VoteDependency {
 struct VoteDependencyHandle<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Public key.
     pub public_key: TYPES::SignatureKey,
-    /// Private Key.
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key.
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// Reference to consensus. The replica will require a write lock on this.
     consensus: Arc<RwLock<Consensus<TYPES>>>,
     /// Immutable instance state
@@ -189,22 +190,23 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> VoteDependencyHand
         vid_share: Proposal<TYPES, VidDisperseShare<TYPES>>,
     ) -> Result<()> {
         ensure!(
-            self.quorum_membership.has_stake(&self.public_key),
+            !self.quorum_membership.is_observer(&self.public_key),
             format!(
-                "We were not chosen for quorum committee on {:?}",
+                "We are an observer, so we don't vote on {:?}",
                 self.view_number
             ),
         );
 
         // Create and send the vote.
-        let vote = QuorumVote::<TYPES>::create_signed_vote(
+        let vote = QuorumVote::<TYPES>::create_vote_with_signer(
             QuorumData {
                 leaf_commit: leaf.commit(),
             },
             self.view_number,
             &self.public_key,
-            &self.private_key,
+            self.signer.as_ref(),
         )
+        .await
         .context("Failed to sign vote")?;
         debug!(
             "sending vote to next quorum leader {:?}",
@@ -332,6 +334,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static> HandleDepOutput
             return;
         };
 
+        // The DA proposal may have arrived after the quorum proposal was validated, so check the
+        // header against its payload again before voting.
+        if let Err(e) =
+            verify_saved_payload(&leaf, &self.consensus, self.quorum_membership.total_nodes()).await
+        {
+            error!("Proposal doesn't commit to the DA payload; error = {e:#}");
+            return;
+        }
+
         // Update internal state
         if let Err(e) = self.update_shared_state(&leaf, &vid_share).await {
             error!("Failed to update shared consensus state; error = {e:#}");
@@ -351,8 +362,8 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Public key.
     pub public_key: TYPES::SignatureKey,
 
-    /// Private Key.
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key.
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Reference to consensus. The replica will require a write lock on this.
     pub consensus: Arc<RwLock<Consensus<TYPES>>>,
@@ -366,6 +377,9 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Table for the in-progress dependency tasks.
     pub vote_dependencies: HashMap<TYPES::Time, JoinHandle<()>>,
 
+    /// VID shares already validated for the views we haven't voted in yet.
+    pub seen_vid_shares: SeenVidShares<TYPES>,
+
     /// Network for all nodes
     pub quorum_network: Arc<I::QuorumNetwork>,
 
@@ -375,11 +389,14 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Membership for Quorum certs/votes.
     pub quorum_membership: Arc<TYPES::Membership>,
 
+    /// Membership of the VID storage nodes, for verifying VID shares and commitments
+    pub vid_membership: Arc<TYPES::Membership>,
+
     /// Membership for DA committee certs/votes.
     pub da_membership: Arc<TYPES::Membership>,
 
     /// Output events to application
-    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+    pub output_event_stream: ExternalEventSender<TYPES>,
 
     /// The node's id
     pub id: u64,
@@ -389,6 +406,12 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
 
     /// The curent version of HotShot
     pub version: Version,
+
+    /// Events received while paused, to handle once resumed; `None` while running
+    pub paused_events: Option<PausedEvents<TYPES>>,
+
+    /// Stops the vote dependency tasks along with this task
+    pub cancellation: CancellationToken,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumVoteTaskState<TYPES, I> {
@@ -502,7 +525,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumVoteTaskState<TYPES, I
             dependency_chain,
             VoteDependencyHandle::<TYPES, I> {
                 public_key: self.public_key.clone(),
-                private_key: self.private_key.clone(),
+                signer: Arc::clone(&self.signer),
                 consensus: Arc::clone(&self.consensus),
                 instance_state: Arc::clone(&self.instance_state),
                 quorum_membership: Arc::clone(&self.quorum_membership),
@@ -513,7 +536,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumVoteTaskState<TYPES, I
                 version: self.version,
                 id: self.id,
             },
-        );
+        )
+        .with_cancellation(self.cancellation.clone());
         self.vote_dependencies
             .insert(view_number, dependency_task.run());
     }
@@ -536,12 +560,44 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumVoteTaskState<TYPES, I
             }
 
             self.latest_voted_view = new_view;
+            self.seen_vid_shares.prune_before(new_view + 1);
 
             return true;
         }
         false
     }
 
+    /// Store a validated VID share, and start voting on its view if the share is ours.
+    async fn handle_valid_vid_share(
+        &mut self,
+        disperse: &Proposal<TYPES, VidDisperseShare<TYPES>>,
+        event_receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
+        event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        if !self.seen_vid_shares.insert(disperse) {
+            debug!("Dropping a duplicate VID share");
+            return;
+        }
+
+        let view = disperse.data.view_number();
+        self.consensus
+            .write()
+            .await
+            .update_vid_shares(view, disperse.clone());
+
+        if disperse.data.recipient_key != self.public_key {
+            debug!("Got a Valid VID share but it's not for our key");
+            return;
+        }
+
+        broadcast_event(
+            Arc::new(HotShotEvent::VidShareValidated(disperse.clone())),
+            &event_sender.clone(),
+        )
+        .await;
+        self.create_dependency_task_if_new(view, event_receiver, event_sender, None);
+    }
+
     /// Handle a vote dependent event received on the event stream
     #[instrument(skip_all, fields(id = self.id, latest_voted_view = *self.latest_voted_view), name = "Quorum vote handle", level = "error")]
     pub async fn handle(
@@ -606,59 +662,57 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> QuorumVoteTaskState<TYPES, I
                     return;
                 }
 
-                // Validate the VID share.
-                let payload_commitment = disperse.data.payload_commitment;
-                // Check whether the data satisfies one of the following.
-                // * From the right leader for this view.
-                // * Calculated and signed by the current node.
-                // * Signed by one of the staked DA committee members.
-                if !self
-                    .quorum_membership
-                    .leader(view)
-                    .validate(&disperse.signature, payload_commitment.as_ref())
-                    && !self
-                        .public_key
-                        .validate(&disperse.signature, payload_commitment.as_ref())
-                {
-                    let mut validated = false;
-                    for da_member in self.da_membership.staked_committee(view) {
-                        if da_member.validate(&disperse.signature, payload_commitment.as_ref()) {
-                            validated = true;
-                            break;
-                        }
-                    }
-                    if !validated {
-                        return;
-                    }
-                }
-                if vid_scheme(self.quorum_membership.total_nodes())
-                    .verify_share(
-                        &disperse.data.share,
-                        &disperse.data.common,
-                        &payload_commitment,
-                    )
-                    .is_err()
-                {
-                    debug!("Invalid VID share.");
+                if self.seen_vid_shares.contains(disperse) {
+                    debug!("Dropping a duplicate VID share for view {}", *view);
                     return;
                 }
 
-                self.consensus
-                    .write()
-                    .await
-                    .update_vid_shares(view, disperse.clone());
-
-                if disperse.data.recipient_key != self.public_key {
-                    debug!("Got a Valid VID share but it's not for our key");
+                if !is_valid_vid_share(
+                    disperse,
+                    &self.public_key,
+                    &self.quorum_membership,
+                    &self.da_membership,
+                    self.vid_membership.total_nodes(),
+                ) {
+                    debug!("Invalid VID share.");
                     return;
                 }
 
-                broadcast_event(
-                    Arc::new(HotShotEvent::VidShareValidated(disperse.clone())),
-                    &event_sender.clone(),
+                self.handle_valid_vid_share(disperse, event_receiver, &event_sender)
+                    .await;
+            }
+            HotShotEvent::VidSharesRecv(disperses) => {
+                trace!("Received {} VID shares", disperses.len());
+                let latest_voted_view = self.latest_voted_view;
+                let disperses = disperses
+                    .iter()
+                    .filter(|disperse| {
+                        disperse.data.view_number() > latest_voted_view
+                            && !self.seen_vid_shares.contains(disperse)
+                    })
+                    .cloned()
+                    .collect();
+
+                let valid = valid_vid_shares(
+                    disperses,
+                    &self.public_key,
+                    &self.quorum_membership,
+                    &self.da_membership,
+                    self.vid_membership.total_nodes(),
                 )
                 .await;
-                self.create_dependency_task_if_new(view, event_receiver, &event_sender, None);
+
+                for disperse in &valid {
+                    self.handle_valid_vid_share(disperse, event_receiver.clone(), &event_sender)
+                        .await;
+                }
+            }
+            HotShotEvent::Equivocation(view_number, ..) => {
+                // Don't vote on either of the leader's proposals, if we haven't yet.
+                if let Some(dependency) = self.vote_dependencies.remove(view_number) {
+                    cancel_task(dependency).await;
+                    debug!("Vote dependency removed for equivocated view {view_number:?}");
+                }
             }
             HotShotEvent::QuorumVoteDependenciesValidated(view_number) => {
                 debug!("All vote dependencies verified for view {:?}", view_number);
@@ -682,7 +736,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for QuorumVoteTask
         sender: &Sender<Arc<Self::Event>>,
         receiver: &Receiver<Arc<Self::Event>>,
     ) -> Result<()> {
-        self.handle(event, receiver.clone(), sender.clone()).await;
+        for event in gate_paused_events(&mut self.paused_events, event) {
+            self.handle(event, receiver.clone(), sender.clone()).await;
+        }
 
         Ok(())
     }
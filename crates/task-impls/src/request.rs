@@ -1,40 +1,40 @@
 use std::{
     collections::BTreeMap,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-    time::Duration,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
-use anyhow::Result;
-use async_broadcast::{Receiver, Sender};
+use anyhow::{ensure, Result};
+use async_broadcast::{broadcast, Receiver, Sender};
 use async_compatibility_layer::art::{async_sleep, async_spawn, async_timeout};
-use async_lock::RwLock;
+use async_lock::{Mutex, RwLock};
 #[cfg(async_executor_impl = "async-std")]
 use async_std::task::JoinHandle;
 use async_trait::async_trait;
-use hotshot_task::task::TaskState;
+use hotshot_task::{cancellation::CancellationToken, task::TaskState};
 use hotshot_types::{
     consensus::Consensus,
-    data::QuorumProposal,
+    constants::Base,
+    data::{DaProposal, QuorumProposal},
     message::{
-        DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message, MessageKind, Proposal,
-        SequencingMessage,
+        version_for_view, DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message,
+        MessageKind, Proposal, SequencingMessage,
     },
+    simple_certificate::UpgradeCertificate,
     traits::{
         election::Membership,
-        network::{ConnectedNetwork, DataRequest, RequestKind, ResponseMessage},
-        node_implementation::{NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        network::{ConnectedNetwork, DataRequest, RequestKind, ResponseMessage, MAX_VID_RANGE},
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType},
+        signature_key::{SignatureKey, Signer},
     },
-    vote::HasViewNumber,
+    vote::{Certificate, HasViewNumber},
 };
 use rand::{prelude::SliceRandom, thread_rng};
 use sha2::{Digest, Sha256};
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, instrument, warn};
+use vbs::version::StaticVersionType;
 
 use crate::{
     events::{HotShotEvent, ProposalMissing},
@@ -44,10 +44,53 @@ use crate::{
 /// Amount of time to try for a request before timing out.
 pub const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
 
+/// The longest a requester waits between two full cycles of requests to its peers.
+pub const MAX_REQUEST_BACKOFF: Duration = Duration::from_secs(4);
+
+/// Requests for data from views more than this many views behind the latest view we've seen are
+/// catch-up requests, and are paced by the [`CatchupLimiter`].
+pub const CATCHUP_VIEW_THRESHOLD: u64 = 5;
+
+/// Paces catch-up requests to a maximum rate, so a node far behind doesn't flood itself and its
+/// peers while it syncs. Requests for live views don't go through the limiter.
+#[derive(Debug)]
+pub struct CatchupLimiter {
+    /// Minimum time between two catch-up requests
+    interval: Duration,
+    /// The earliest time the next catch-up request may be sent
+    next_slot: Mutex<Instant>,
+}
+
+impl CatchupLimiter {
+    /// Create a limiter allowing `requests_per_second` catch-up requests per second, or any
+    /// number if it's 0.
+    #[must_use]
+    pub fn new(requests_per_second: u64) -> Self {
+        let requests_per_second = u32::try_from(requests_per_second).unwrap_or(u32::MAX);
+        Self {
+            interval: Duration::from_secs(1)
+                .checked_div(requests_per_second)
+                .unwrap_or_default(),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until another catch-up request may be sent.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        async_sleep(slot.saturating_duration_since(Instant::now())).await;
+    }
+}
+
 /// Long running task which will request information after a proposal is received.
 /// The task will wait a it's `delay` and then send a request iteratively to peers
-/// for any data they don't have related to the proposal.  For now it's just requesting VID
-/// shares.
+/// for any data they don't have related to the proposal: our VID share, and the DA proposal
+/// carrying the view's payload.
 pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Network to send requests over
     pub network: Arc<I::QuorumNetwork>,
@@ -64,14 +107,22 @@ pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     pub quorum_membership: TYPES::Membership,
     /// This nodes public key
     pub public_key: TYPES::SignatureKey,
-    /// This nodes private/signign key, used to sign requests.
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs requests on this node's behalf with its private key.
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// The node's id
     pub id: u64,
-    /// A flag indicating that `HotShotEvent::Shutdown` has been received
-    pub shutdown_flag: Arc<AtomicBool>,
-    /// A flag indicating that `HotShotEvent::Shutdown` has been received
+    /// Stops the spawned requesters when cancelled
+    pub cancellation: CancellationToken,
+    /// The requesters spawned for each view
     pub spawned_tasks: BTreeMap<TYPES::Time, Vec<JoinHandle<()>>>,
+    /// The tasks backfilling the proposals of views before the start view. These are kept apart
+    /// from `spawned_tasks`, since pruning undecided views mustn't cancel them and they don't mean
+    /// a view's data is already being requested.
+    pub backfill_tasks: BTreeMap<TYPES::Time, JoinHandle<()>>,
+    /// Paces requests for views we're catching up on
+    pub catchup_limiter: Arc<CatchupLimiter>,
+    /// Decided upgrade certificate. Responses must be signed once the network has upgraded.
+    pub decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> Drop for NetworkRequestState<TYPES, I> {
@@ -109,12 +160,27 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequest
                 }
                 Ok(())
             }
+            HotShotEvent::UndecidedPruned(view) => {
+                // The pruned views can't be voted on anymore, so stop requesting their data.
+                let retained = self.spawned_tasks.split_off(&(*view + 1));
+                for handle in std::mem::replace(&mut self.spawned_tasks, retained)
+                    .into_values()
+                    .flatten()
+                {
+                    #[cfg(async_executor_impl = "async-std")]
+                    handle.cancel().await;
+                    #[cfg(async_executor_impl = "tokio")]
+                    handle.abort();
+                }
+                Ok(())
+            }
             HotShotEvent::QuorumProposalRequest(missing) => {
                 let ProposalMissing {
                     view,
                     response_chan: chan,
                 } = missing;
-                self.run_proposal(&RequestKind::Proposal(*view), chan.clone(), *view);
+                self.run_proposal(&RequestKind::Proposal(*view), chan.clone(), *view)
+                    .await;
                 Ok(())
             }
             _ => Ok(()),
@@ -122,8 +188,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequest
     }
 
     async fn cancel_subtasks(&mut self) {
-        self.set_shutdown_flag();
-
         while !self.spawned_tasks.is_empty() {
             let Some((_, handles)) = self.spawned_tasks.pop_first() else {
                 break;
@@ -136,10 +200,58 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequest
                 handle.abort();
             }
         }
+        while let Some((_, handle)) = self.backfill_tasks.pop_first() {
+            #[cfg(async_executor_impl = "async-std")]
+            handle.cancel().await;
+            #[cfg(async_executor_impl = "tokio")]
+            handle.abort();
+        }
     }
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I> {
+    /// Requests the proposals we haven't saved for the `views` views before `start_view` from
+    /// their leaders, and saves the valid ones so we can serve them to peers catching up. A node
+    /// restarting without its saved proposals uses this to repopulate them. Views before the
+    /// oldest one garbage collection retains are skipped.
+    pub async fn backfill_proposals(&mut self, start_view: TYPES::Time, views: u64) {
+        let missing: Vec<_> = {
+            let consensus = self.state.read().await;
+            let first_view = TYPES::Time::new(start_view.saturating_sub(views).max(1))
+                .max(consensus.oldest_retained_view());
+            std::iter::successors(Some(first_view), |view| Some(*view + 1))
+                .take_while(|view| *view < start_view)
+                .filter(|view| !consensus.last_proposals().contains_key(view))
+                .collect()
+        };
+        for view in missing {
+            let (response_chan, mut responses) = broadcast(1);
+            self.run_proposal(&RequestKind::Proposal(view), response_chan, view)
+                .await;
+
+            let state = Arc::clone(&self.state);
+            let quorum_membership = self.quorum_membership.clone();
+            let cancellation = self.cancellation.clone();
+            let handle = async_spawn(async move {
+                cancellation
+                    .run_until_cancelled(async move {
+                        let Ok(Some(proposal)) = responses.recv_direct().await else {
+                            info!("Failed to backfill the proposal for view {view:?}");
+                            return;
+                        };
+                        if let Err(e) =
+                            save_backfilled_proposal(&state, &quorum_membership, view, proposal)
+                                .await
+                        {
+                            warn!("Discarding backfilled proposal for view {view:?}: {e}");
+                        }
+                    })
+                    .await;
+            });
+            self.backfill_tasks.insert(view, handle);
+        }
+    }
+
     /// Spawns tasks for a given view to retrieve any data needed.
     async fn spawn_requests(
         &mut self,
@@ -150,23 +262,58 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
         if requests.is_empty() {
             return;
         }
-        requests
-            .into_iter()
-            .for_each(|r| self.run_delay(r, sender.clone(), view));
+        for request in requests {
+            self.run_delay(request, sender.clone(), view).await;
+        }
     }
 
     /// Creates the srequest structures for all types that are needed.
-    async fn build_requests(&self, view: TYPES::Time) -> Vec<RequestKind<TYPES>> {
+    pub async fn build_requests(&self, view: TYPES::Time) -> Vec<RequestKind<TYPES>> {
         let mut reqs = Vec::new();
-        if !self.state.read().await.vid_shares().contains_key(&view) {
-            reqs.push(RequestKind::Vid(view, self.public_key.clone()));
+        let state = self.state.read().await;
+        if !state.vid_shares().contains_key(&view) {
+            // Coalesce the views just before this one which we're also missing our share for, and
+            // haven't requested yet, into a single ranged request. Peers only understand ranged
+            // requests once the network has upgraded, so only coalesce views from the upgrade on.
+            let decided_upgrade_certificate = self.decided_upgrade_certificate.read().await;
+            let upgraded = |view| {
+                version_for_view(view, &decided_upgrade_certificate)
+                    .is_ok_and(|version| version != Base::VERSION)
+            };
+            let mut start = view;
+            while *view - *start + 1 < MAX_VID_RANGE && *start > 1 && upgraded(start - 1) {
+                let previous = start - 1;
+                if state.vid_shares().contains_key(&previous)
+                    || self.spawned_tasks.contains_key(&previous)
+                {
+                    break;
+                }
+                start = previous;
+            }
+            if start == view {
+                reqs.push(RequestKind::Vid(view, self.public_key.clone()));
+            } else {
+                reqs.push(RequestKind::VidRange(start, view, self.public_key.clone()));
+            }
+        }
+        // Only DA members are sent the payload, so others only need it when they can't get it
+        // from their VID share, and can only count on it being available once a DAC certifies it.
+        let needs_payload = self.da_membership.has_stake(&self.public_key)
+            || (state.saved_da_certs().contains_key(&view)
+                && !state.vid_shares().contains_key(&view));
+        if needs_payload && !state.saved_payloads().contains_key(&view) {
+            reqs.push(RequestKind::DaProposal(view));
         }
-        // TODO request other things
         reqs
     }
 
+    /// The limiter to pace a request for data from `view` with, if it's a catch-up request.
+    fn catchup_limiter_for(&self, view: TYPES::Time) -> Option<Arc<CatchupLimiter>> {
+        (view + CATCHUP_VIEW_THRESHOLD < self.view).then(|| Arc::clone(&self.catchup_limiter))
+    }
+
     /// Sign the serialized version of the request
-    fn serialize_and_sign(
+    async fn serialize_and_sign(
         &self,
         request: &RequestKind<TYPES>,
     ) -> Option<<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType> {
@@ -174,8 +321,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
             tracing::error!("Failed to serialize request!");
             return None;
         };
-        let Ok(signature) = TYPES::SignatureKey::sign(&self.private_key, &Sha256::digest(data))
-        else {
+        let Ok(signature) = self.signer.sign(&Sha256::digest(data)).await else {
             error!("Failed to sign Data Request");
             return None;
         };
@@ -184,7 +330,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
     /// run a delayed request task for a request.  The first response
     /// received will be sent over `sender`
     #[instrument(skip_all, fields(id = self.id, view = *self.view), name = "NetworkRequestState run_delay", level = "error")]
-    fn run_delay(
+    async fn run_delay(
         &mut self,
         request: RequestKind<TYPES>,
         sender: Sender<Arc<HotShotEvent<TYPES>>>,
@@ -204,9 +350,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
             sender,
             delay: self.delay,
             recipients,
-            shutdown_flag: Arc::clone(&self.shutdown_flag),
+            membership: self.da_membership.clone(),
+            public_key: self.public_key.clone(),
+            cancellation: self.cancellation.clone(),
+            catchup_limiter: self.catchup_limiter_for(view),
+            current_backoff: REQUEST_TIMEOUT,
+            decided_upgrade_certificate: Arc::clone(&self.decided_upgrade_certificate),
         };
-        let Some(signature) = self.serialize_and_sign(&request) else {
+        let Some(signature) = self.serialize_and_sign(&request).await else {
             return;
         };
         debug!("Requesting data: {:?}", request);
@@ -216,7 +367,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
     }
 
     /// Spawns a task to send a request for the proposal and send the response on a channel
-    fn run_proposal(
+    async fn run_proposal(
         &mut self,
         request: &RequestKind<TYPES>,
         response_chan: Sender<Option<Proposal<TYPES, QuorumProposal<TYPES>>>>,
@@ -227,21 +378,22 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
             network: Arc::clone(&self.network),
             sender: response_chan,
             leader,
+            membership: self.da_membership.clone(),
+            catchup_limiter: self.catchup_limiter_for(view),
+            decided_upgrade_certificate: Arc::clone(&self.decided_upgrade_certificate),
         };
-        let Some(signature) = self.serialize_and_sign(request) else {
+        let Some(signature) = self.serialize_and_sign(request).await else {
             return;
         };
 
         let pub_key = self.public_key.clone();
+        let cancellation = self.cancellation.clone();
         async_spawn(async move {
-            requester.do_proposal(view, signature, pub_key).await;
+            cancellation
+                .run_until_cancelled(requester.do_proposal(view, signature, pub_key))
+                .await;
         });
     }
-
-    /// Signals delayed requesters to finish
-    pub fn set_shutdown_flag(&self) {
-        self.shutdown_flag.store(true, Ordering::Relaxed);
-    }
 }
 
 /// A short lived task that waits a delay and starts trying peers until it completes
@@ -258,8 +410,19 @@ struct DelayedRequester<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     delay: Duration,
     /// The peers we will request in a random order
     recipients: Vec<TYPES::SignatureKey>,
-    /// A flag indicating that `HotShotEvent::Shutdown` has been received
-    shutdown_flag: Arc<AtomicBool>,
+    /// Membership the peers which respond must belong to
+    membership: TYPES::Membership,
+    /// Our public key, which requests are sent from
+    public_key: TYPES::SignatureKey,
+    /// Stops the requester, even mid-request, when cancelled
+    cancellation: CancellationToken,
+    /// Paces each attempt, if this is a catch-up request
+    catchup_limiter: Option<Arc<CatchupLimiter>>,
+    /// How long to wait after asking every peer once before asking them again. Doubles after
+    /// each full cycle, up to [`MAX_REQUEST_BACKOFF`], and resets once a peer has the data.
+    current_backoff: Duration,
+    /// Decided upgrade certificate, deciding whether responses must be signed
+    decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
 }
 
 /// A task the requests some data immediately from one peer
@@ -271,6 +434,12 @@ struct ProposalRequester<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     sender: Sender<Option<Proposal<TYPES, QuorumProposal<TYPES>>>>,
     /// Leader for the view of the request
     leader: TYPES::SignatureKey,
+    /// Membership the leader must belong to for its response to be accepted
+    membership: TYPES::Membership,
+    /// Paces the request, if this is a catch-up request
+    catchup_limiter: Option<Arc<CatchupLimiter>>,
+    /// Decided upgrade certificate, deciding whether responses must be signed
+    decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ProposalRequester<TYPES, I> {
@@ -282,7 +451,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ProposalRequester<TYPES, I>
         signature: Signature<TYPES>,
         key: TYPES::SignatureKey,
     ) {
-        let response = match bincode::serialize(&make_proposal_req::<TYPES>(view, signature, key)) {
+        if let Some(ref limiter) = self.catchup_limiter {
+            limiter.acquire().await;
+        }
+        let message = make_request::<TYPES>(RequestKind::Proposal(view), signature, key);
+        let response = match bincode::serialize(&message) {
             Ok(serialized_msg) => {
                 async_timeout(
                     REQUEST_TIMEOUT,
@@ -300,7 +473,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ProposalRequester<TYPES, I>
             }
         };
         if let Ok(Ok(serialized_response)) = response {
-            if let Ok(ResponseMessage::Found(msg)) = bincode::deserialize(&serialized_response) {
+            let verified = verify_response(
+                &serialized_response,
+                &RequestKind::Proposal(view),
+                &self.leader,
+                &self.membership,
+                &*self.decided_upgrade_certificate.read().await,
+            );
+            if let Some(ResponseMessage::Found(msg)) = verified {
                 let SequencingMessage::General(GeneralConsensusMessage::Proposal(prop)) = msg
                 else {
                     error!("Requested Proposal but received a non-proposal in response.  Response was {:?}", msg);
@@ -316,28 +496,28 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ProposalRequester<TYPES, I>
     }
 }
 
-/// Wrapper for the info in a VID request
-struct VidRequest<TYPES: NodeType>(TYPES::Time, TYPES::SignatureKey);
-
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DelayedRequester<TYPES, I> {
     /// Wait the delay, then try to complete the request.  Iterates over peers
-    /// until the request is completed, or the data is no longer needed.
-    async fn run(self, request: RequestKind<TYPES>, signature: Signature<TYPES>) {
-        match request {
-            RequestKind::Vid(view, key) => {
+    /// until the request is completed, the data is no longer needed, or we're cancelled.
+    async fn run(mut self, request: RequestKind<TYPES>, signature: Signature<TYPES>) {
+        if let RequestKind::Proposal(..) = request {
+            return;
+        }
+        let cancellation = self.cancellation.clone();
+        cancellation
+            .run_until_cancelled(async {
                 // Do the delay only if primary is up and then start sending
                 if !self.network.is_primary_down() {
                     async_sleep(self.delay).await;
                 }
-                self.do_vid(VidRequest(view, key), signature).await;
-            }
-            RequestKind::Proposal(..) | RequestKind::DaProposal(..) => {}
-        }
+                self.do_request(request, signature).await;
+            })
+            .await;
     }
-    /// Handle sending a VID Share request, runs the loop until the data exists
-    async fn do_vid(&self, req: VidRequest<TYPES>, signature: Signature<TYPES>) {
-        let message = make_vid(&req, signature);
-        let mut recipients_it = self.recipients.iter().cycle();
+    /// Handle sending a request, runs the loop until the data exists
+    async fn do_request(&mut self, request: RequestKind<TYPES>, signature: Signature<TYPES>) {
+        let message = make_request(request.clone(), signature, self.public_key.clone());
+        let mut attempt = 0;
 
         let serialized_msg = match bincode::serialize(&message) {
             Ok(serialized_msg) => serialized_msg,
@@ -350,30 +530,46 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DelayedRequester<TYPES, I> {
             }
         };
 
-        while !self.cancel_vid(&req).await {
+        while !self.cancel_request(&request).await {
+            if let Some(ref limiter) = self.catchup_limiter {
+                limiter.acquire().await;
+            }
+            let recipient = &self.recipients[attempt % self.recipients.len()];
+            attempt += 1;
             match async_timeout(
                 REQUEST_TIMEOUT,
                 self.network
-                    .request_data::<TYPES>(serialized_msg.clone(), recipients_it.next().unwrap()),
+                    .request_data::<TYPES>(serialized_msg.clone(), recipient),
             )
             .await
             {
                 Ok(Ok(response)) => {
-                    match bincode::deserialize(&response) {
-                        Ok(ResponseMessage::Found(data)) => {
-                            self.handle_response_message(data).await;
+                    let verified = verify_response(
+                        &response,
+                        &request,
+                        recipient,
+                        &self.membership,
+                        &*self.decided_upgrade_certificate.read().await,
+                    );
+                    match verified {
+                        Some(ResponseMessage::Found(data)) => {
+                            self.current_backoff = REQUEST_TIMEOUT;
+                            self.handle_response_messages(&request, vec![data]).await;
                             // keep trying, but expect the map to be populated, or view to increase
                             async_sleep(REQUEST_TIMEOUT).await;
                         }
-                        Ok(ResponseMessage::NotFound) => {
+                        Some(ResponseMessage::FoundMany(data)) => {
+                            self.current_backoff = REQUEST_TIMEOUT;
+                            self.handle_response_messages(&request, data).await;
+                            async_sleep(REQUEST_TIMEOUT).await;
+                        }
+                        Some(ResponseMessage::NotFound) => {
                             info!("Peer Responded they did not have the data");
                         }
-                        Ok(ResponseMessage::Denied) => {
+                        Some(ResponseMessage::Denied) => {
                             error!("Request for data was denied by the receiver");
                         }
-                        Err(e) => {
-                            error!("Failed to deserialize response: {e}");
-                        }
+                        None => {}
                     }
                 }
                 Ok(Err(e)) => {
@@ -384,56 +580,180 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DelayedRequester<TYPES, I> {
                     warn!("Request to other node timed out");
                 }
             }
+            if attempt % self.recipients.len() == 0 {
+                self.back_off().await;
+            }
         }
     }
-    /// Returns true if we got the data we wanted, or the view has moved on.
-    async fn cancel_vid(&self, req: &VidRequest<TYPES>) -> bool {
-        let view = req.0;
+    /// Wait out the backoff after asking every peer once, and double it for the next cycle.
+    async fn back_off(&mut self) {
+        async_sleep(self.current_backoff).await;
+        self.current_backoff = (self.current_backoff * 2).min(MAX_REQUEST_BACKOFF);
+    }
+    /// Returns true if we got the data we wanted, the view has moved on, or we're cancelled.
+    async fn cancel_request(&self, request: &RequestKind<TYPES>) -> bool {
         let state = self.state.read().await;
-        self.shutdown_flag.load(Ordering::Relaxed)
-            || state.vid_shares().contains_key(&view)
-            || state.cur_view() > view
+        self.cancellation.is_cancelled()
+            || match request {
+                RequestKind::Vid(view, _) => {
+                    state.vid_shares().contains_key(view) || state.cur_view() > *view
+                }
+                RequestKind::VidRange(start, end, _) => {
+                    state.cur_view() > *end
+                        || std::iter::successors(Some(*start), |view| Some(*view + 1))
+                            .take_while(|view| view <= end)
+                            .all(|view| state.vid_shares().contains_key(&view))
+                }
+                RequestKind::DaProposal(view) => {
+                    state.saved_payloads().contains_key(view) || state.cur_view() > *view
+                }
+                RequestKind::Proposal(..) => true,
+            }
     }
 
-    /// Transform a response into a `HotShotEvent`
-    async fn handle_response_message(&self, message: SequencingMessage<TYPES>) {
-        let event = match message {
-            SequencingMessage::Da(DaConsensusMessage::VidDisperseMsg(prop)) => {
-                HotShotEvent::VidShareRecv(prop)
+    /// Handle the data we received in response to `request`: VID shares are passed on as
+    /// `HotShotEvent`s, and a DA proposal is validated and its payload saved.
+    async fn handle_response_messages(
+        &self,
+        request: &RequestKind<TYPES>,
+        messages: Vec<SequencingMessage<TYPES>>,
+    ) {
+        let mut shares = Vec::new();
+        for message in messages {
+            match (request, message) {
+                (
+                    RequestKind::Vid(..) | RequestKind::VidRange(..),
+                    SequencingMessage::Da(DaConsensusMessage::VidDisperseMsg(share)),
+                ) => shares.push(share),
+                (
+                    RequestKind::DaProposal(view),
+                    SequencingMessage::Da(DaConsensusMessage::DaProposal(proposal)),
+                ) => {
+                    if let Err(e) =
+                        save_requested_da_proposal(&self.state, &self.membership, *view, proposal)
+                            .await
+                    {
+                        warn!("Discarding requested DA proposal for view {view:?}: {e}");
+                    }
+                }
+                (_, message) => {
+                    error!("Received data which doesn't match the request {request:?}.  Response was {message:?}");
+                }
             }
-            _ => return,
+        }
+        let event = match shares.len() {
+            0 => return,
+            1 => HotShotEvent::VidShareRecv(shares.remove(0)),
+            _ => HotShotEvent::VidSharesRecv(shares),
         };
         broadcast_event(Arc::new(event), &self.sender).await;
     }
 }
 
-/// Make a VID Request Message to send
-fn make_vid<TYPES: NodeType>(
-    req: &VidRequest<TYPES>,
-    signature: Signature<TYPES>,
-) -> Message<TYPES> {
-    let kind = RequestKind::Vid(req.0, req.1.clone());
-    let data_request = DataRequest {
-        view: req.0,
-        request: kind,
-        signature,
-    };
-    Message {
-        sender: req.1.clone(),
-        kind: MessageKind::Data(DataMessage::RequestData(data_request)),
-    }
+/// Validate a proposal backfilled for `view` and save it for serving to peers catching up.
+///
+/// # Errors
+/// If the proposal isn't for `view`, isn't signed by the view's leader, or its justify QC is
+/// invalid.
+pub async fn save_backfilled_proposal<TYPES: NodeType>(
+    consensus: &RwLock<Consensus<TYPES>>,
+    quorum_membership: &TYPES::Membership,
+    view: TYPES::Time,
+    proposal: Proposal<TYPES, QuorumProposal<TYPES>>,
+) -> Result<()> {
+    ensure!(
+        proposal.data.view_number() == view,
+        "Requested the proposal for view {view:?}, but got one for view {:?}",
+        proposal.data.view_number()
+    );
+    proposal.validate_signature(quorum_membership)?;
+    ensure!(
+        proposal.data.justify_qc.is_valid_cert(quorum_membership),
+        "Invalid justify QC in the proposal for view {view:?}"
+    );
+    let mut consensus = consensus.write().await;
+    consensus.update_proposers(view, quorum_membership.leader(view));
+    consensus.backfill_proposal(proposal);
+    Ok(())
 }
 
-/// Build a request for a Proposal
-fn make_proposal_req<TYPES: NodeType>(
+/// Validate a DA proposal requested for `view`, and save its payload as if we'd received the
+/// proposal from the DA leader.
+///
+/// # Errors
+/// If the proposal isn't for `view`, isn't signed by the view's DA leader, or we already have
+/// the payload for the view.
+pub async fn save_requested_da_proposal<TYPES: NodeType>(
+    consensus: &RwLock<Consensus<TYPES>>,
+    da_membership: &TYPES::Membership,
     view: TYPES::Time,
+    proposal: Proposal<TYPES, DaProposal<TYPES>>,
+) -> Result<()> {
+    ensure!(
+        proposal.data.view_number() == view,
+        "Requested the DA proposal for view {view:?}, but got one for view {:?}",
+        proposal.data.view_number()
+    );
+    let encoded_transactions_hash = Sha256::digest(&proposal.data.encoded_transactions);
+    ensure!(
+        da_membership
+            .leader(view)
+            .validate(&proposal.signature, &encoded_transactions_hash),
+        "The DA proposal for view {view:?} isn't signed by the view's DA leader"
+    );
+    let mut consensus = consensus.write().await;
+    consensus.update_saved_payloads(view, Arc::clone(&proposal.data.encoded_transactions))?;
+    consensus.update_last_da_proposals(proposal);
+    Ok(())
+}
+
+/// Deserialize a response to `request` from `responder`, returning the response only if it
+/// verifies. Once the network has upgraded by the view of the request, according to
+/// `decided_upgrade_certificate`, the response must be signed by `responder`, who must be a
+/// member of `membership`. Before that, unsigned responses are accepted as they always were.
+#[must_use]
+pub fn verify_response<TYPES: NodeType>(
+    serialized_response: &[u8],
+    request: &RequestKind<TYPES>,
+    responder: &TYPES::SignatureKey,
+    membership: &TYPES::Membership,
+    decided_upgrade_certificate: &Option<UpgradeCertificate<TYPES>>,
+) -> Option<ResponseMessage<TYPES>> {
+    let response: DataMessage<TYPES> = bincode::deserialize(serialized_response)
+        .inspect_err(|e| error!("Failed to deserialize response: {e}"))
+        .ok()?;
+    match response {
+        DataMessage::SignedDataResponse(signed) => signed
+            .verify(request, responder, membership)
+            .inspect_err(|e| error!("Rejecting response: {e}"))
+            .ok(),
+        DataMessage::DataResponse(response) => {
+            let version = version_for_view(request.view(), decided_upgrade_certificate)
+                .inspect_err(|e| error!("Rejecting response: {e}"))
+                .ok()?;
+            if version == Base::VERSION {
+                Some(response)
+            } else {
+                error!("Rejecting unsigned response after the upgrade");
+                None
+            }
+        }
+        _ => {
+            error!("Received a non-response in response to a request");
+            None
+        }
+    }
+}
+
+/// Build the message for a request to send to peers
+fn make_request<TYPES: NodeType>(
+    request: RequestKind<TYPES>,
     signature: Signature<TYPES>,
     key: TYPES::SignatureKey,
 ) -> Message<TYPES> {
-    let kind = RequestKind::Proposal(view);
     let data_request = DataRequest {
-        view,
-        request: kind,
+        view: request.view(),
+        request,
         signature,
     };
     Message {
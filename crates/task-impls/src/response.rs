@@ -2,27 +2,37 @@ use std::{sync::Arc, time::Duration};
 
 use async_broadcast::Receiver;
 use async_compatibility_layer::art::{async_sleep, async_spawn};
+use async_lock::{RwLock, Semaphore};
 #[cfg(async_executor_impl = "async-std")]
-use async_std::task::JoinHandle;
+use async_std::task::{spawn_blocking, JoinHandle};
 use futures::{channel::mpsc, FutureExt, StreamExt};
-use hotshot_task::dependency::{Dependency, EventDependency};
+use hotshot_task::{
+    cancellation::CancellationToken,
+    dependency::{Dependency, EventDependency},
+};
 use hotshot_types::{
     consensus::{Consensus, LockedConsensusState},
+    constants::Base,
     data::VidDisperseShare,
     message::{
-        DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message, MessageKind, Proposal,
-        SequencingMessage,
+        version_for_view, DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message,
+        MessageKind, Proposal, SequencingMessage,
     },
+    simple_certificate::UpgradeCertificate,
     traits::{
         election::Membership,
-        network::{DataRequest, RequestKind, ResponseChannel, ResponseMessage},
+        network::{
+            DataRequest, RequestKind, ResponseChannel, ResponseMessage, SignedResponse,
+            MAX_VID_RANGE,
+        },
         node_implementation::NodeType,
-        signature_key::SignatureKey,
+        signature_key::{InMemorySigner, SignatureKey, Signer},
     },
 };
 use sha2::{Digest, Sha256};
 #[cfg(async_executor_impl = "tokio")]
-use tokio::task::JoinHandle;
+use tokio::task::{spawn_blocking, JoinHandle};
+use vbs::version::StaticVersionType;
 
 use crate::events::HotShotEvent;
 
@@ -32,20 +42,35 @@ pub type RequestReceiver = mpsc::Receiver<(Vec<u8>, ResponseChannel<Vec<u8>>)>;
 /// Time to wait for txns before sending `ResponseMessage::NotFound`
 const TXNS_TIMEOUT: Duration = Duration::from_millis(100);
 
+/// Maximum number of request signatures being verified at once
+const MAX_CONCURRENT_VERIFICATIONS: usize = 8;
+
+/// Maximum number of requests being handled at once
+const MAX_CONCURRENT_REQUESTS: usize = 64;
+
 /// Task state for the Network Request Task. The task is responsible for handling
 /// requests sent to this node by the network.  It will validate the sender,
 /// parse the request, and try to find the data request in the consensus stores.
 pub struct NetworkResponseState<TYPES: NodeType> {
     /// Locked consensus state
     consensus: LockedConsensusState<TYPES>,
-    /// Receiver for requests
-    receiver: RequestReceiver,
+    /// Receiver for requests, taken by [`run_response_task`]
+    receiver: Option<RequestReceiver>,
     /// Quorum membership for checking if requesters have state
     quorum: Arc<TYPES::Membership>,
+    /// VID storage node membership, for calculating VID shares and letting VID storage nodes
+    /// request their shares
+    vid: Arc<TYPES::Membership>,
     /// This replicas public key
     pub_key: TYPES::SignatureKey,
-    /// This replicas private key
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on this replica's behalf with its private key
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
+    /// Permits bounding the number of request signatures being verified at once
+    verification_permits: Arc<Semaphore>,
+    /// Permits bounding the number of requests being handled at once
+    request_permits: Arc<Semaphore>,
+    /// Decided upgrade certificate. Responses are only signed once the network has upgraded.
+    decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
 }
 
 impl<TYPES: NodeType> NetworkResponseState<TYPES> {
@@ -56,25 +81,83 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
         quorum: Arc<TYPES::Membership>,
         pub_key: TYPES::SignatureKey,
         private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    ) -> Self {
+        Self {
+            receiver: Some(receiver),
+            ..Self::with_signer(
+                consensus,
+                Arc::clone(&quorum),
+                quorum,
+                pub_key,
+                Arc::new(InMemorySigner::new(private_key)),
+            )
+        }
+    }
+
+    /// Create the network request state for serving VID shares to the `vid` storage nodes, and
+    /// signing with `signer`. The requests are received by [`run_response_task_until_cancelled`].
+    pub fn with_signer(
+        consensus: LockedConsensusState<TYPES>,
+        quorum: Arc<TYPES::Membership>,
+        vid: Arc<TYPES::Membership>,
+        pub_key: TYPES::SignatureKey,
+        signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     ) -> Self {
         Self {
             consensus,
-            receiver,
+            receiver: None,
             quorum,
+            vid,
             pub_key,
-            private_key,
+            signer,
+            verification_permits: Arc::new(Semaphore::new(MAX_CONCURRENT_VERIFICATIONS)),
+            request_permits: Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS)),
+            decided_upgrade_certificate: Arc::default(),
         }
     }
 
-    /// Run the request response loop until a `HotShotEvent::Shutdown` is received.
-    /// Or the stream is closed.
-    async fn run_loop(mut self, shutdown: EventDependency<Arc<HotShotEvent<TYPES>>>) {
-        let mut shutdown = Box::pin(shutdown.completed().fuse());
+    /// Use the decided upgrade certificate in `decided_upgrade_certificate`, so that responses
+    /// are signed once the network has upgraded.
+    #[must_use]
+    pub fn with_decided_upgrade_certificate(
+        mut self,
+        decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
+    ) -> Self {
+        self.decided_upgrade_certificate = decided_upgrade_certificate;
+        self
+    }
+
+    /// Run the request response loop until `cancellation` is cancelled, or the stream is closed.
+    /// Each request is handled on its own task, so that slow requests don't hold up the ones
+    /// behind them, and is abandoned if `cancellation` is cancelled. At most
+    /// `MAX_CONCURRENT_REQUESTS` requests are handled at once; further requests wait in the
+    /// channel until one finishes.
+    async fn run_loop(
+        self: Arc<Self>,
+        mut receiver: RequestReceiver,
+        cancellation: CancellationToken,
+    ) {
+        let mut shutdown = Box::pin(cancellation.cancelled().fuse());
         loop {
+            let permit = futures::select! {
+                permit = self.request_permits.acquire_arc().fuse() => permit,
+                _ = shutdown => {
+                    return;
+                }
+            };
             futures::select! {
-                req = self.receiver.next() => {
+                req = receiver.next() => {
                     match req {
-                        Some((msg, chan)) => self.handle_message(msg, chan).await,
+                        Some((msg, chan)) => {
+                            let state = Arc::clone(&self);
+                            let cancellation = cancellation.clone();
+                            async_spawn(async move {
+                                let _permit = permit;
+                                cancellation
+                                    .run_until_cancelled(state.handle_message(msg, chan))
+                                    .await;
+                            });
+                        }
                         None => return,
                     }
                 },
@@ -99,21 +182,30 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
 
         match req.kind {
             MessageKind::Data(DataMessage::RequestData(request)) => {
-                if !self.valid_sender(&sender) || !valid_signature::<TYPES>(&request, &sender) {
-                    let serialized_msg = match bincode::serialize(
-                        &self.make_msg(ResponseMessage::Denied),
-                    ) {
-                        Ok(serialized) => serialized,
-                        Err(e) => {
-                            tracing::error!("Failed to serialize outgoing message: this should never happen. Error: {e}");
-                            return;
-                        }
-                    };
-                    let _ = chan.sender.send(serialized_msg);
+                if !self.valid_sender(&sender, request.request.view()) {
+                    self.deny(&request.request, chan).await;
+                    return;
+                }
+
+                // Verify the signature off the executor, waiting for a permit first so that a
+                // flood of requests can't tie up more than `MAX_CONCURRENT_VERIFICATIONS` threads.
+                let permit = self.verification_permits.acquire_arc().await;
+                let signed_request = request.clone();
+                let valid = spawn_blocking(move || {
+                    let _permit = permit;
+                    valid_signature::<TYPES>(&signed_request, &sender)
+                })
+                .await;
+                #[cfg(async_executor_impl = "tokio")]
+                let valid = valid.unwrap_or(false);
+                if !valid {
+                    self.deny(&request.request, chan).await;
                     return;
                 }
 
-                let response = self.handle_request(request).await;
+                let Some(response) = self.handle_request(request).await else {
+                    return;
+                };
                 let serialized_response = match bincode::serialize(&response) {
                     Ok(serialized) => serialized,
                     Err(e) => {
@@ -130,6 +222,23 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
         }
     }
 
+    /// Respond to a request we won't serve with `ResponseMessage::Denied`
+    async fn deny(&self, request: &RequestKind<TYPES>, chan: ResponseChannel<Vec<u8>>) {
+        let Some(msg) = self.make_msg(request, ResponseMessage::Denied).await else {
+            return;
+        };
+        let serialized_msg = match bincode::serialize(&msg) {
+            Ok(serialized) => serialized,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to serialize outgoing message: this should never happen. Error: {e}"
+                );
+                return;
+            }
+        };
+        let _ = chan.sender.send(serialized_msg);
+    }
+
     /// Get the VID share from consensus storage, or calculate it from the payload for
     /// the view, if we have the payload.  Stores all the shares calculated from the payload
     /// if the calculation was done
@@ -149,8 +258,8 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
             if Consensus::calculate_and_update_vid(
                 Arc::clone(&self.consensus),
                 view,
-                Arc::clone(&self.quorum),
-                &self.private_key,
+                Arc::clone(&self.vid),
+                self.signer.as_ref(),
             )
             .await
             .is_none()
@@ -160,8 +269,8 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
                 Consensus::calculate_and_update_vid(
                     Arc::clone(&self.consensus),
                     view,
-                    Arc::clone(&self.quorum),
-                    &self.private_key,
+                    Arc::clone(&self.vid),
+                    self.signer.as_ref(),
                 )
                 .await?;
             }
@@ -183,35 +292,69 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
             .cloned()
     }
 
-    /// Handle the request contained in the message. Returns the response we should send
+    /// Handle the request contained in the message. Returns the response we should send, or
+    /// `None` if we failed to sign it.
     /// First parses the kind and passes to the appropriate handler for the specific type
     /// of the request.
-    async fn handle_request(&self, req: DataRequest<TYPES>) -> Message<TYPES> {
-        match req.request {
-            RequestKind::Vid(view, pub_key) => {
-                let Some(share) = self.get_or_calc_vid_share(view, &pub_key).await else {
-                    return self.make_msg(ResponseMessage::NotFound);
-                };
-                let seq_msg = SequencingMessage::Da(DaConsensusMessage::VidDisperseMsg(share));
-                self.make_msg(ResponseMessage::Found(seq_msg))
+    async fn handle_request(&self, req: DataRequest<TYPES>) -> Option<Message<TYPES>> {
+        let response = match req.request {
+            RequestKind::Vid(view, ref pub_key) => {
+                match self.get_or_calc_vid_share(view, pub_key).await {
+                    Some(share) => ResponseMessage::Found(SequencingMessage::Da(
+                        DaConsensusMessage::VidDisperseMsg(share),
+                    )),
+                    None => ResponseMessage::NotFound,
+                }
             }
-            // TODO impl for DA Proposal: https://github.com/EspressoSystems/HotShot/issues/2651
-            RequestKind::DaProposal(_view) => self.make_msg(ResponseMessage::NotFound),
-            RequestKind::Proposal(view) => self.make_msg(self.respond_with_proposal(view).await),
-        }
+            RequestKind::DaProposal(view) => self.respond_with_da_proposal(view).await,
+            RequestKind::Proposal(view) => self.respond_with_proposal(view).await,
+            RequestKind::VidRange(start, end, ref pub_key) => {
+                // Ranged requests, and their responses, only exist once the network has upgraded
+                let version =
+                    version_for_view(start, &*self.decided_upgrade_certificate.read().await);
+                if version.is_ok_and(|version| version != Base::VERSION) {
+                    self.respond_with_vid_range(start, end, pub_key).await
+                } else {
+                    ResponseMessage::Denied
+                }
+            }
+        };
+        self.make_msg(&req.request, response).await
     }
 
-    /// Helper to turn a `ResponseMessage` into a `Message` by filling
-    /// in the surrounding feilds and creating the `MessageKind`
-    fn make_msg(&self, msg: ResponseMessage<TYPES>) -> Message<TYPES> {
-        Message {
+    /// Helper to turn a `ResponseMessage` to `request` into a `Message`, filling in the
+    /// surrounding feilds and creating the `MessageKind`. Once the network has upgraded by the
+    /// view of the request, the response is signed. Returns `None` if we failed to sign it.
+    async fn make_msg(
+        &self,
+        request: &RequestKind<TYPES>,
+        msg: ResponseMessage<TYPES>,
+    ) -> Option<Message<TYPES>> {
+        let version = version_for_view(
+            request.view(),
+            &*self.decided_upgrade_certificate.read().await,
+        )
+        .inspect_err(|e| tracing::error!("Not responding to a request: {e}"))
+        .ok()?;
+        let kind = if version == Base::VERSION {
+            DataMessage::DataResponse(msg)
+        } else {
+            let signed = SignedResponse::new(request, msg, self.signer.as_ref())
+                .await
+                .inspect_err(|e| tracing::error!("Failed to sign response: {e}"))
+                .ok()?;
+            DataMessage::SignedDataResponse(signed)
+        };
+        Some(Message {
             sender: self.pub_key.clone(),
-            kind: MessageKind::Data(DataMessage::DataResponse(msg)),
-        }
+            kind: MessageKind::Data(kind),
+        })
     }
     /// Makes sure the sender is allowed to send a request.
-    fn valid_sender(&self, sender: &TYPES::SignatureKey) -> bool {
+    fn valid_sender(&self, sender: &TYPES::SignatureKey, view: TYPES::Time) -> bool {
         self.quorum.has_stake(sender)
+            || self.quorum.observers(view).contains(sender)
+            || self.vid.has_stake(sender)
     }
     /// Lookup the proposal for the view and respond if it's found/not found
     async fn respond_with_proposal(&self, view: TYPES::Time) -> ResponseMessage<TYPES> {
@@ -222,6 +365,43 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
             None => ResponseMessage::NotFound,
         }
     }
+    /// Lookup the VID shares we already have for each view in the range and respond with those we
+    /// found.  Unlike single view requests we never calculate shares here, so a range request
+    /// can't make us run the VID disperse for many views at once.
+    async fn respond_with_vid_range(
+        &self,
+        start: TYPES::Time,
+        end: TYPES::Time,
+        key: &TYPES::SignatureKey,
+    ) -> ResponseMessage<TYPES> {
+        let end = end.min(start + (MAX_VID_RANGE - 1));
+        let consensus = self.consensus.read().await;
+        let mut shares = Vec::new();
+        let mut view = start;
+        while view <= end {
+            if let Some(share) = consensus.vid_shares().get(&view).and_then(|m| m.get(key)) {
+                shares.push(SequencingMessage::Da(DaConsensusMessage::VidDisperseMsg(
+                    share.clone(),
+                )));
+            }
+            view += 1;
+        }
+        drop(consensus);
+        if shares.is_empty() {
+            ResponseMessage::NotFound
+        } else {
+            ResponseMessage::FoundMany(shares)
+        }
+    }
+    /// Lookup the DA proposal for the view and respond if it's found/not found
+    async fn respond_with_da_proposal(&self, view: TYPES::Time) -> ResponseMessage<TYPES> {
+        match self.consensus.read().await.last_da_proposals().get(&view) {
+            Some(prop) => ResponseMessage::Found(SequencingMessage::Da(
+                DaConsensusMessage::DaProposal(prop.clone()),
+            )),
+            None => ResponseMessage::NotFound,
+        }
+    }
 }
 
 /// Check the signature
@@ -239,12 +419,35 @@ fn valid_signature<TYPES: NodeType>(
 /// from other nodes.  It will shutdown when it gets `HotshotEvent::Shutdown`
 /// on the `event_stream` arg.
 pub fn run_response_task<TYPES: NodeType>(
-    task_state: NetworkResponseState<TYPES>,
+    mut task_state: NetworkResponseState<TYPES>,
     event_stream: Receiver<Arc<HotShotEvent<TYPES>>>,
 ) -> JoinHandle<()> {
     let dep = EventDependency::new(
         event_stream,
         Box::new(|e| matches!(e.as_ref(), HotShotEvent::Shutdown)),
     );
-    async_spawn(task_state.run_loop(dep))
+    let Some(request_receiver) = task_state.receiver.take() else {
+        tracing::error!("The response task state has no request receiver to serve");
+        return async_spawn(async {});
+    };
+    let cancellation = CancellationToken::new();
+    let run_loop = Arc::new(task_state).run_loop(request_receiver, cancellation.clone());
+    async_spawn(async move {
+        let mut run_loop = Box::pin(run_loop.fuse());
+        let mut shutdown = Box::pin(dep.completed().fuse());
+        futures::select! {
+            () = run_loop => {},
+            _ = shutdown => cancellation.cancel(),
+        }
+    })
+}
+
+/// Spawn the network response task to handle the requests for data from other nodes received on
+/// `request_receiver`.  It will shutdown when `cancellation` is cancelled.
+pub fn run_response_task_until_cancelled<TYPES: NodeType>(
+    task_state: NetworkResponseState<TYPES>,
+    request_receiver: RequestReceiver,
+    cancellation: CancellationToken,
+) -> JoinHandle<()> {
+    async_spawn(Arc::new(task_state).run_loop(request_receiver, cancellation))
 }
@@ -1,12 +1,15 @@
 use std::{
+    collections::BTreeMap,
     sync::Arc,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
 use anyhow::{bail, Result};
 use async_broadcast::{Receiver, Sender};
-use async_compatibility_layer::art::async_sleep;
-use async_lock::RwLock;
+use async_compatibility_layer::art::{async_sleep, async_spawn};
+use async_lock::{Mutex, RwLock};
+#[cfg(async_executor_impl = "async-std")]
+use async_std::task::JoinHandle;
 use async_trait::async_trait;
 use futures::{stream::FuturesUnordered, StreamExt};
 use hotshot_builder_api::block_info::{
@@ -22,19 +25,21 @@ use hotshot_types::{
         block_contents::{precompute_vid_commitment, BuilderFee, EncodeBytes},
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::{BuilderSignatureKey, SignatureKey},
+        signature_key::{BuilderSignatureKey, SignatureKey, Signer},
         BlockPayload,
     },
     utils::ViewInner,
     vid::VidCommitment,
 };
+#[cfg(async_executor_impl = "tokio")]
+use tokio::task::JoinHandle;
 use tracing::{debug, error, instrument, warn};
 use vbs::version::StaticVersionType;
 
 use crate::{
     builder::BuilderClient,
     events::{HotShotEvent, HotShotTaskCompleted},
-    helpers::broadcast_event,
+    helpers::{broadcast_event, cancel_task, ExternalEventSender},
 };
 
 // Parameters for builder querying algorithm
@@ -64,6 +69,11 @@ pub struct BuilderResponses<TYPES: NodeType> {
     pub block_header: AvailableBlockHeaderInput<TYPES>,
 }
 
+/// Blocks fetched ahead of time, by the view they're for, along with the commitment of the
+/// payload they build on
+pub type PrefetchedBlocks<TYPES> =
+    BTreeMap<<TYPES as NodeType>::Time, (VidCommitment, BuilderResponses<TYPES>)>;
+
 /// Tracks state of a Transaction task
 pub struct TransactionTaskState<
     TYPES: NodeType,
@@ -74,7 +84,7 @@ pub struct TransactionTaskState<
     pub builder_timeout: Duration,
 
     /// Output events to application
-    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+    pub output_event_stream: ExternalEventSender<TYPES>,
 
     /// View number this view is executing in.
     pub cur_view: TYPES::Time,
@@ -88,22 +98,33 @@ pub struct TransactionTaskState<
     /// Membership for the quorum
     pub membership: Arc<TYPES::Membership>,
 
+    /// Membership of the VID storage nodes, which a null block's VID commitment is for
+    pub vid_membership: Arc<TYPES::Membership>,
+
     /// Builder API client
     pub builder_clients: Vec<BuilderClient<TYPES, Ver>>,
 
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// InstanceState
     pub instance_state: Arc<TYPES::InstanceState>,
     /// This state's ID
     pub id: u64,
     /// Decided upgrade certificate
     pub decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
+    /// Number of views beyond the next one for which we fetch our blocks ahead of time
+    pub builder_prefetch_depth: usize,
+    /// Blocks fetched ahead of time for views we'll be leader in
+    pub prefetched_blocks: Arc<Mutex<PrefetchedBlocks<TYPES>>>,
+    /// Tasks fetching blocks ahead of time, by the view the block is for
+    pub prefetch_tasks: BTreeMap<TYPES::Time, JoinHandle<()>>,
+    /// Maximum size, in bytes, of an encoded block payload we propose
+    pub max_block_size: u64,
 }
 
-impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType + 'static>
     TransactionTaskState<TYPES, I, Ver>
 {
     /// main task event handler
@@ -131,6 +152,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
             HotShotEvent::UpgradeDecided(cert) => {
                 self.decided_upgrade_certificate = Some(cert.clone());
             }
+            HotShotEvent::VersionUpgrade(version) => {
+                // Moving to a version other than the decided upgrade's revokes it.
+                if self
+                    .decided_upgrade_certificate
+                    .as_ref()
+                    .is_some_and(|cert| cert.data.new_version != *version)
+                {
+                    self.decided_upgrade_certificate = None;
+                }
+            }
             HotShotEvent::ViewChange(view) => {
                 let view = *view;
                 debug!("view change in transactions to view {:?}", view);
@@ -145,9 +176,21 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
                 }
                 self.cur_view = view;
 
+                // Blocks prefetched for views we've skipped past will never be proposed
+                let prefetch_tasks = self.prefetch_tasks.split_off(&view);
+                let skipped_tasks = std::mem::replace(&mut self.prefetch_tasks, prefetch_tasks);
+                for task in skipped_tasks.into_values() {
+                    cancel_task(task).await;
+                }
+                {
+                    let mut prefetched_blocks = self.prefetched_blocks.lock().await;
+                    *prefetched_blocks = prefetched_blocks.split_off(&view);
+                }
+
                 // return if we aren't the next leader or we skipped last view and aren't the current leader.
                 if !make_block && self.membership.leader(self.cur_view + 1) != self.public_key {
                     debug!("Not next leader for view {:?}", self.cur_view);
+                    self.prefetch_blocks().await;
                     return None;
                 }
                 let block_view = if make_block { view } else { view + 1 };
@@ -161,19 +204,46 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
                     {
                         None
                     } else {
-                        self.wait_for_block().await
+                        // Find commitment to the block we want to build upon
+                        let (parent_view, parent_comm) = self.latest_known_vid_commitment().await;
+                        match self.take_prefetched_block(block_view, parent_comm).await {
+                            Some(block) => Some(block),
+                            None => {
+                                self.block_query()
+                                    .wait_for_block(parent_view, parent_comm)
+                                    .await
+                            }
+                        }
                     }
                 };
 
-                if let Some(BuilderResponses {
-                    block_data,
-                    blocks_initial_info,
-                    block_header,
-                }) = block
+                // A block over the size limit can't be proposed, so we propose the null block
+                // instead, before its payload reaches the VID and DA tasks.
+                let block = block
+                    .map(|block| (block.block_data.block_payload.encode(), block))
+                    .filter(|(encoded_transactions, _)| {
+                        let size = encoded_transactions.len() as u64;
+                        if size > self.max_block_size {
+                            warn!(
+                                "Builder block of {size} bytes for view {block_view:?} is larger than the {} byte limit",
+                                self.max_block_size
+                            );
+                        }
+                        size <= self.max_block_size
+                    });
+
+                if let Some((
+                    encoded_transactions,
+                    BuilderResponses {
+                        block_data,
+                        blocks_initial_info,
+                        block_header,
+                    },
+                )) = block
                 {
                     broadcast_event(
                         Arc::new(HotShotEvent::BlockRecv(
-                            block_data.block_payload.encode(),
+                            encoded_transactions,
                             block_data.metadata,
                             block_view,
                             BuilderFee {
@@ -201,7 +271,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
                         .number_of_empty_blocks_proposed
                         .add(1);
 
-                    let membership_total_nodes = self.membership.total_nodes();
+                    let membership_total_nodes = self.vid_membership.total_nodes();
 
                     // Calculate the builder fee for the empty block
                     let Some(builder_fee) = null_block::builder_fee(membership_total_nodes) else {
@@ -229,16 +299,80 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
                     .await;
                 };
 
+                self.prefetch_blocks().await;
                 return None;
             }
-            HotShotEvent::Shutdown => {
-                return Some(HotShotTaskCompleted);
-            }
             _ => {}
         }
         None
     }
 
+    /// Fetch blocks in the background for the views within `builder_prefetch_depth` of the next
+    /// view in which we'll be leader, so they're ready as soon as we need to propose.
+    ///
+    /// The blocks are built on the latest payload we know of, like the block for the next view.
+    async fn prefetch_blocks(&mut self) {
+        if self.builder_prefetch_depth == 0 {
+            return;
+        }
+        let (parent_view, parent_comm) = self.latest_known_vid_commitment().await;
+        for block_view in (*self.cur_view + 2..).take(self.builder_prefetch_depth) {
+            let block_view = TYPES::Time::new(block_view);
+            if self.membership.leader(block_view) != self.public_key
+                || self.prefetch_tasks.contains_key(&block_view)
+                || self
+                    .decided_upgrade_certificate
+                    .as_ref()
+                    .is_some_and(|cert| cert.upgrading_in(block_view))
+            {
+                continue;
+            }
+
+            debug!("Prefetching a block for view {:?}", block_view);
+            let query = self.block_query();
+            let prefetched_blocks = Arc::clone(&self.prefetched_blocks);
+            let task = async_spawn(async move {
+                if let Some(block) = query.wait_for_block(parent_view, parent_comm).await {
+                    prefetched_blocks
+                        .lock()
+                        .await
+                        .insert(block_view, (parent_comm, block));
+                }
+            });
+            self.prefetch_tasks.insert(block_view, task);
+        }
+    }
+
+    /// Take the block prefetched for `view`, if it's been fetched and builds on the payload with
+    /// commitment `parent_comm`. A prefetch still in flight is abandoned.
+    async fn take_prefetched_block(
+        &mut self,
+        view: TYPES::Time,
+        parent_comm: VidCommitment,
+    ) -> Option<BuilderResponses<TYPES>> {
+        if let Some(task) = self.prefetch_tasks.remove(&view) {
+            cancel_task(task).await;
+        }
+        let (prefetched_parent_comm, block) = self.prefetched_blocks.lock().await.remove(&view)?;
+        if prefetched_parent_comm != parent_comm {
+            debug!("Discarding the block prefetched for view {view:?}, as its parent is stale");
+            return None;
+        }
+        Some(block)
+    }
+
+    /// The state needed to query the builders for a block in the current view
+    fn block_query(&self) -> BlockQuery<TYPES, Ver> {
+        BlockQuery {
+            builder_timeout: self.builder_timeout,
+            cur_view: self.cur_view,
+            builder_clients: self.builder_clients.clone(),
+            public_key: self.public_key.clone(),
+            signer: Arc::clone(&self.signer),
+            id: self.id,
+        }
+    }
+
     /// Get last known builder commitment from consensus.
     async fn latest_known_vid_commitment(&self) -> (TYPES::Time, VidCommitment) {
         let consensus = self.consensus.read().await;
@@ -269,17 +403,40 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
         // If not found, return commitment for last decided block
         (prev_view, consensus.decided_leaf().payload_commitment())
     }
+}
+
+/// The state needed to query the builders for a block, detached from the task so that blocks can
+/// be fetched in the background
+struct BlockQuery<TYPES: NodeType, Ver: StaticVersionType> {
+    /// Time we give the builders to respond
+    builder_timeout: Duration,
+    /// View the query is made in
+    cur_view: TYPES::Time,
+    /// Builder API clients
+    builder_clients: Vec<BuilderClient<TYPES, Ver>>,
+    /// This Nodes Public Key
+    public_key: TYPES::SignatureKey,
+    /// Signs on our behalf with our private key
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
+    /// ID of the task state making the query
+    id: u64,
+}
 
+impl<TYPES: NodeType, Ver: StaticVersionType> BlockQuery<TYPES, Ver> {
+    /// Query the builders for a block built on the payload with commitment `parent_comm`, from
+    /// `view_num`.
     #[instrument(skip_all, fields(id = self.id, view = *self.cur_view), name = "wait_for_block", level = "error")]
-    async fn wait_for_block(&self) -> Option<BuilderResponses<TYPES>> {
+    async fn wait_for_block(
+        &self,
+        view_num: TYPES::Time,
+        parent_comm: VidCommitment,
+    ) -> Option<BuilderResponses<TYPES>> {
         let task_start_time = Instant::now();
+        // Past this point we give up on the builders and propose an empty block, so the builders
+        // are asked for their best block by then.
+        let deadline = SystemTime::now() + self.builder_timeout;
 
-        // Find commitment to the block we want to build upon
-        let (view_num, parent_comm) = self.latest_known_vid_commitment().await;
-        let parent_comm_sig = match <<TYPES as NodeType>::SignatureKey as SignatureKey>::sign(
-            &self.private_key,
-            parent_comm.as_ref(),
-        ) {
+        let parent_comm_sig = match self.signer.sign(parent_comm.as_ref()).await {
             Ok(sig) => sig,
             Err(err) => {
                 error!(%err, "Failed to sign block hash");
@@ -291,7 +448,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
             match async_compatibility_layer::art::async_timeout(
                 self.builder_timeout
                     .saturating_sub(task_start_time.elapsed()),
-                self.block_from_builder(parent_comm, view_num, &parent_comm_sig),
+                self.block_from_builder(parent_comm, view_num, &parent_comm_sig, deadline),
             )
             .await
             {
@@ -320,13 +477,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
         None
     }
 
-    /// Query the builders for available blocks. Queries only fraction of the builders
-    /// based on the response time.
+    /// Query the builders for available blocks, which we need by `deadline`. Queries only fraction
+    /// of the builders based on the response time.
     async fn get_available_blocks(
         &self,
         parent_comm: VidCommitment,
         view_number: TYPES::Time,
         parent_comm_sig: &<<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+        deadline: SystemTime,
     ) -> Vec<(AvailableBlockInfo<TYPES>, usize)> {
         // Create a collection of futures that call available_blocks endpoint for every builder
         let tasks = self
@@ -340,6 +498,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
                         view_number.u64(),
                         self.public_key.clone(),
                         parent_comm_sig,
+                        deadline,
                     )
                     .await
                     .map(move |blocks| {
@@ -410,9 +569,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
         parent_comm: VidCommitment,
         view_number: TYPES::Time,
         parent_comm_sig: &<<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+        deadline: SystemTime,
     ) -> anyhow::Result<BuilderResponses<TYPES>> {
         let mut available_blocks = self
-            .get_available_blocks(parent_comm, view_number, parent_comm_sig)
+            .get_available_blocks(parent_comm, view_number, parent_comm_sig, deadline)
             .await;
 
         available_blocks.sort_by(|(l, _), (r, _)| {
@@ -445,10 +605,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType>
                 continue;
             }
 
-            let request_signature = match <<TYPES as NodeType>::SignatureKey as SignatureKey>::sign(
-                &self.private_key,
-                block_info.block_hash.as_ref(),
-            ) {
+            let request_signature = match self.signer.sign(block_info.block_hash.as_ref()).await {
                 Ok(request_signature) => request_signature,
                 Err(err) => {
                     tracing::warn!(%err, "Failed to sign block hash");
@@ -540,5 +697,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, Ver: StaticVersionType + 'st
         Ok(())
     }
 
-    async fn cancel_subtasks(&mut self) {}
+    async fn cancel_subtasks(&mut self) {
+        while let Some((_, task)) = self.prefetch_tasks.pop_first() {
+            cancel_task(task).await;
+        }
+    }
 }
@@ -16,7 +16,7 @@ use hotshot_types::{
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
     },
     vote::HasViewNumber,
 };
@@ -25,7 +25,7 @@ use vbs::version::StaticVersionType;
 
 use crate::{
     events::{HotShotEvent, HotShotTaskCompleted},
-    helpers::broadcast_event,
+    helpers::{broadcast_event, ExternalEventSender},
     vote_collection::{
         create_vote_accumulator, AccumulatorInfo, HandleVoteEvent, VoteCollectionTaskState,
     },
@@ -37,7 +37,7 @@ type VoteCollectorOption<TYPES, VOTE, CERT> = Option<VoteCollectionTaskState<TYP
 /// Tracks state of a DA task
 pub struct UpgradeTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// Output events to application
-    pub output_event_stream: async_broadcast::Sender<Event<TYPES>>,
+    pub output_event_stream: ExternalEventSender<TYPES>,
 
     /// View number this view is executing in.
     pub cur_view: TYPES::Time,
@@ -54,8 +54,8 @@ pub struct UpgradeTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// This Nodes public key
     pub public_key: TYPES::SignatureKey,
 
-    /// This Nodes private key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on this Nodes behalf with its private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// This state's ID
     pub id: u64,
@@ -154,12 +154,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> UpgradeTaskState<TYPES, I> {
                 .await;
 
                 // If everything is fine up to here, we generate and send a vote on the proposal.
-                let Ok(vote) = UpgradeVote::create_signed_vote(
+                let Ok(vote) = UpgradeVote::create_vote_with_signer(
                     proposal.data.upgrade_proposal.clone(),
                     view,
                     &self.public_key,
-                    &self.private_key,
-                ) else {
+                    self.signer.as_ref(),
+                )
+                .await
+                else {
                     error!("Failed to sign UpgradeVote!");
                     return None;
                 };
@@ -243,11 +245,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> UpgradeTaskState<TYPES, I> {
                         view_number: view + 5,
                     };
 
-                    let signature = TYPES::SignatureKey::sign(
-                        &self.private_key,
-                        upgrade_proposal_data.commit().as_ref(),
-                    )
-                    .expect("Failed to sign upgrade proposal commitment!");
+                    let signature = self
+                        .signer
+                        .sign(upgrade_proposal_data.commit().as_ref())
+                        .await
+                        .expect("Failed to sign upgrade proposal commitment!");
 
                     let message = Proposal {
                         data: upgrade_proposal,
@@ -267,10 +269,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> UpgradeTaskState<TYPES, I> {
 
                 return None;
             }
-            HotShotEvent::Shutdown => {
-                error!("Shutting down because of shutdown signal!");
-                return Some(HotShotTaskCompleted);
-            }
             _ => {}
         }
         None
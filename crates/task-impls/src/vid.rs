@@ -12,7 +12,7 @@ use hotshot_types::{
     traits::{
         election::Membership,
         node_implementation::{NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
         BlockPayload,
     },
 };
@@ -35,8 +35,8 @@ pub struct VidTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     pub membership: Arc<TYPES::Membership>,
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// The view and ID of the current vote collection task, if there is one.
     pub vote_collector: Option<(TYPES::Time, usize, usize)>,
     /// This state's ID
@@ -71,12 +71,25 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                 .await;
                 let payload_commitment = vid_disperse.payload_commitment;
                 let shares = VidDisperseShare::from_vid_disperse(vid_disperse.clone());
-                let mut consensus = self.consensus.write().await;
+                // Sign the shares before taking the lock, as the signer may be remote.
+                let mut disperses = Vec::new();
                 for share in shares {
-                    if let Some(disperse) = share.to_proposal(&self.private_key) {
-                        consensus.update_vid_shares(*view_number, disperse);
+                    if let Some(disperse) =
+                        share.to_proposal_with_signer(self.signer.as_ref()).await
+                    {
+                        disperses.push(disperse);
                     }
                 }
+                let mut consensus = self.consensus.write().await;
+                for disperse in disperses {
+                    consensus.update_vid_shares(*view_number, disperse);
+                }
+                // Spare the DA task recomputing the commitment when our own proposal comes back.
+                consensus.update_submitted_payloads(
+                    *view_number,
+                    Arc::clone(encoded_transactions),
+                    payload_commitment,
+                );
                 drop(consensus);
 
                 // send the commitment and metadata to consensus for block building
@@ -102,10 +115,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
 
             HotShotEvent::BlockReady(vid_disperse, view_number) => {
                 let view_number = *view_number;
-                let Ok(signature) = TYPES::SignatureKey::sign(
-                    &self.private_key,
-                    vid_disperse.payload_commitment.as_ref(),
-                ) else {
+                let Ok(signature) = self
+                    .signer
+                    .sign(vid_disperse.payload_commitment.as_ref())
+                    .await
+                else {
                     error!("VID: failed to sign dispersal payload");
                     return None;
                 };
@@ -143,10 +157,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
 
                 return None;
             }
-
-            HotShotEvent::Shutdown => {
-                return Some(HotShotTaskCompleted);
-            }
             _ => {}
         }
         None
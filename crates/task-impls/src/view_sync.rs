@@ -26,7 +26,7 @@ use hotshot_types::{
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
     },
     vote::{Certificate, HasViewNumber, Vote},
 };
@@ -70,8 +70,8 @@ pub struct ViewSyncTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     pub membership: Arc<TYPES::Membership>,
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// Our node id; for logging
     pub id: u64,
 
@@ -141,8 +141,8 @@ pub struct ViewSyncReplicaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES
     pub membership: Arc<TYPES::Membership>,
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs on our behalf with our private key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 }
 
 #[async_trait]
@@ -211,7 +211,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ViewSyncTaskState<TYPES, I>
             membership: Arc::clone(&self.membership),
             network: Arc::clone(&self.network),
             public_key: self.public_key.clone(),
-            private_key: self.private_key.clone(),
+            signer: Arc::clone(&self.signer),
             view_sync_timeout: self.view_sync_timeout,
             id: self.id,
         };
@@ -497,15 +497,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ViewSyncReplicaTaskState<TYP
                     self.relay = certificate.date().relay;
                 }
 
-                let Ok(vote) = ViewSyncCommitVote::<TYPES>::create_signed_vote(
+                let Ok(vote) = ViewSyncCommitVote::<TYPES>::create_vote_with_signer(
                     ViewSyncCommitData {
                         relay: certificate.date().relay,
                         round: self.next_view,
                     },
                     self.next_view,
                     &self.public_key,
-                    &self.private_key,
-                ) else {
+                    self.signer.as_ref(),
+                )
+                .await
+                else {
                     error!("Failed to sign ViewSyncCommitData!");
                     return None;
                 };
@@ -573,15 +575,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ViewSyncReplicaTaskState<TYP
                     self.relay = certificate.date().relay;
                 }
 
-                let Ok(vote) = ViewSyncFinalizeVote::<TYPES>::create_signed_vote(
+                let Ok(vote) = ViewSyncFinalizeVote::<TYPES>::create_vote_with_signer(
                     ViewSyncFinalizeData {
                         relay: certificate.date().relay,
                         round: self.next_view,
                     },
                     self.next_view,
                     &self.public_key,
-                    &self.private_key,
-                ) else {
+                    self.signer.as_ref(),
+                )
+                .await
+                else {
                     error!("Failed to sign view sync finalized vote!");
                     return None;
                 };
@@ -684,15 +688,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ViewSyncReplicaTaskState<TYP
                     return None;
                 }
 
-                let Ok(vote) = ViewSyncPreCommitVote::<TYPES>::create_signed_vote(
+                let Ok(vote) = ViewSyncPreCommitVote::<TYPES>::create_vote_with_signer(
                     ViewSyncPreCommitData {
                         relay: 0,
                         round: view_number,
                     },
                     view_number,
                     &self.public_key,
-                    &self.private_key,
-                ) else {
+                    self.signer.as_ref(),
+                )
+                .await
+                else {
                     error!("Failed to sign pre commit vote!");
                     return None;
                 };
@@ -739,15 +745,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> ViewSyncReplicaTaskState<TYP
                     self.relay += 1;
                     match last_seen_certificate {
                         ViewSyncPhase::None | ViewSyncPhase::PreCommit | ViewSyncPhase::Commit => {
-                            let Ok(vote) = ViewSyncPreCommitVote::<TYPES>::create_signed_vote(
+                            let Ok(vote) = ViewSyncPreCommitVote::<TYPES>::create_vote_with_signer(
                                 ViewSyncPreCommitData {
                                     relay: self.relay,
                                     round: self.next_view,
                                 },
                                 self.next_view,
                                 &self.public_key,
-                                &self.private_key,
-                            ) else {
+                                self.signer.as_ref(),
+                            )
+                            .await
+                            else {
                                 error!("Failed to sign ViewSyncPreCommitData!");
                                 return None;
                             };
@@ -1,4 +1,9 @@
-use std::{collections::HashMap, fmt::Debug, marker::PhantomData, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    marker::PhantomData,
+    sync::Arc,
+};
 
 use async_broadcast::Sender;
 use async_trait::async_trait;
@@ -10,7 +15,7 @@ use hotshot_types::{
     },
     simple_vote::{
         DaVote, QuorumVote, TimeoutVote, UpgradeVote, ViewSyncCommitVote, ViewSyncFinalizeVote,
-        ViewSyncPreCommitVote,
+        ViewSyncPreCommitVote, VoteExtension,
     },
     traits::{election::Membership, node_implementation::NodeType},
     vote::{Certificate, HasViewNumber, Vote, VoteAccumulator},
@@ -42,6 +47,10 @@ pub struct VoteCollectionTaskState<
 
     /// Node id
     pub id: u64,
+
+    /// Votes accumulated since they were last taken to be persisted, or `None` if the votes
+    /// collected by this task aren't persisted
+    pub unflushed_votes: Option<Vec<VOTE>>,
 }
 
 /// Describes the functions a vote must implement for it to be aggregatable by the generic vote collection task
@@ -56,21 +65,49 @@ pub trait AggregatableVote<
 
     /// return the Hotshot event for the completion of this CERT
     fn make_cert_event(certificate: CERT, key: &TYPES::SignatureKey) -> HotShotEvent<TYPES>;
+
+    /// return the Hotshot event surfacing the vote extensions of this CERT, if this vote type supports them
+    fn make_extensions_event(
+        _view: TYPES::Time,
+        _extensions: BTreeMap<TYPES::SignatureKey, Vec<u8>>,
+    ) -> Option<HotShotEvent<TYPES>> {
+        None
+    }
 }
 
 impl<
         TYPES: NodeType,
-        VOTE: Vote<TYPES> + AggregatableVote<TYPES, VOTE, CERT>,
+        VOTE: Vote<TYPES> + AggregatableVote<TYPES, VOTE, CERT> + Clone,
         CERT: Certificate<TYPES, Voteable = VOTE::Commitment> + Debug,
     > VoteCollectionTaskState<TYPES, VOTE, CERT>
 {
     /// Take one vote and accumultate it. Returns either the cert or the updated state
     /// after the vote is accumulated
-    #[allow(clippy::question_mark)]
     pub async fn accumulate_vote(
         &mut self,
         vote: &VOTE,
         event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Option<HotShotTaskCompleted> {
+        self.accumulate(vote, None, event_stream).await
+    }
+
+    /// Like [`Self::accumulate_vote`], for a vote carrying application data
+    pub async fn accumulate_extended_vote(
+        &mut self,
+        vote: &VOTE,
+        extension: &VoteExtension<TYPES>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Option<HotShotTaskCompleted> {
+        self.accumulate(vote, Some(extension), event_stream).await
+    }
+
+    /// Accumulate one vote, with its extension if it has one
+    #[allow(clippy::question_mark)]
+    async fn accumulate(
+        &mut self,
+        vote: &VOTE,
+        extension: Option<&VoteExtension<TYPES>>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
     ) -> Option<HotShotTaskCompleted> {
         if vote.leader(&self.membership) != self.public_key {
             error!("Received vote for a view in which we were not the leader.");
@@ -87,11 +124,34 @@ impl<
         }
 
         let accumulator = self.accumulator.as_mut()?;
-        match accumulator.accumulate(vote, &self.membership) {
-            Either::Left(()) => None,
+        let (commitment, key) = (vote.date_commitment(), vote.signing_key());
+        let is_new = !accumulator.contains(&commitment, &key);
+        let result = match extension {
+            Some(extension) => {
+                accumulator.accumulate_with_extension(vote, extension, &self.membership)
+            }
+            None => accumulator.accumulate(vote, &self.membership),
+        };
+        match result {
+            Either::Left(()) => {
+                if let Some(unflushed) = &mut self.unflushed_votes {
+                    if is_new && accumulator.contains(&commitment, &key) {
+                        unflushed.push(vote.clone());
+                    }
+                }
+                None
+            }
             Either::Right(cert) => {
                 debug!("Certificate Formed! {:?}", cert);
 
+                let extensions = accumulator.extensions(&cert.date_commitment());
+                if !extensions.is_empty() {
+                    if let Some(event) = VOTE::make_extensions_event(cert.view_number(), extensions)
+                    {
+                        broadcast_event(Arc::new(event), event_stream).await;
+                    }
+                }
+
                 broadcast_event(
                     Arc::new(VOTE::make_cert_event(cert, &self.public_key)),
                     event_stream,
@@ -102,6 +162,14 @@ impl<
             }
         }
     }
+
+    /// Take the votes accumulated since this was last called, to be persisted.
+    pub fn take_unflushed_votes(&mut self) -> Vec<VOTE> {
+        self.unflushed_votes
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
 }
 
 /// Trait for types which will handle a vote event.
@@ -166,19 +234,62 @@ where
         );
         return None;
     }
-    let new_accumulator = VoteAccumulator {
-        vote_outcomes: HashMap::new(),
-        signers: HashMap::new(),
-        phantom: PhantomData,
-    };
+    let mut state = new_vote_collection_state(info, None);
 
-    let mut state = VoteCollectionTaskState::<TYPES, VOTE, CERT> {
-        membership: Arc::clone(&info.membership),
-        public_key: info.public_key.clone(),
-        accumulator: Some(new_accumulator),
-        view: info.view,
-        id: info.id,
-    };
+    let result = state.handle_vote_event(Arc::clone(&event), sender).await;
+
+    if result == Some(HotShotTaskCompleted) {
+        // The protocol has finished
+        return None;
+    }
+
+    Some(state)
+}
+
+/// Like [`create_vote_accumulator`], but for votes which are persisted: the votes restored from
+/// storage are accumulated first, and the votes accumulated afterwards are kept until taken with
+/// [`VoteCollectionTaskState::take_unflushed_votes`].
+/// # Panics
+/// Calls unwrap but should never panic.
+pub async fn create_persisted_vote_accumulator<TYPES, VOTE, CERT>(
+    info: &AccumulatorInfo<TYPES>,
+    restored_votes: Vec<VOTE>,
+    vote: VOTE,
+    event: Arc<HotShotEvent<TYPES>>,
+    sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+) -> Option<VoteCollectionTaskState<TYPES, VOTE, CERT>>
+where
+    TYPES: NodeType,
+    VOTE: Vote<TYPES>
+        + AggregatableVote<TYPES, VOTE, CERT>
+        + Clone
+        + std::marker::Send
+        + std::marker::Sync
+        + 'static,
+    CERT: Certificate<TYPES, Voteable = VOTE::Commitment>
+        + Debug
+        + std::marker::Send
+        + std::marker::Sync
+        + 'static,
+    VoteCollectionTaskState<TYPES, VOTE, CERT>: HandleVoteEvent<TYPES, VOTE, CERT>,
+{
+    if vote.view_number() != info.view {
+        error!(
+            "Vote view does not match! vote view is {} current view is {}",
+            *vote.view_number(),
+            *info.view
+        );
+        return None;
+    }
+    let mut state = new_vote_collection_state(info, Some(Vec::new()));
+
+    for restored_vote in &restored_votes {
+        if state.accumulate_vote(restored_vote, sender).await == Some(HotShotTaskCompleted) {
+            return None;
+        }
+    }
+    // The restored votes are already persisted.
+    state.take_unflushed_votes();
 
     let result = state.handle_vote_event(Arc::clone(&event), sender).await;
 
@@ -190,6 +301,31 @@ where
     Some(state)
 }
 
+/// A vote collection task state for `info` with nothing accumulated yet.
+fn new_vote_collection_state<TYPES, VOTE, CERT>(
+    info: &AccumulatorInfo<TYPES>,
+    unflushed_votes: Option<Vec<VOTE>>,
+) -> VoteCollectionTaskState<TYPES, VOTE, CERT>
+where
+    TYPES: NodeType,
+    VOTE: Vote<TYPES>,
+    CERT: Certificate<TYPES, Voteable = VOTE::Commitment> + Debug,
+{
+    VoteCollectionTaskState {
+        membership: Arc::clone(&info.membership),
+        public_key: info.public_key.clone(),
+        accumulator: Some(VoteAccumulator {
+            vote_outcomes: HashMap::new(),
+            signers: HashMap::new(),
+            extensions: HashMap::new(),
+            phantom: PhantomData,
+        }),
+        view: info.view,
+        id: info.id,
+        unflushed_votes,
+    }
+}
+
 /// Alias for Quorum vote accumulator
 type QuorumVoteState<TYPES> =
     VoteCollectionTaskState<TYPES, QuorumVote<TYPES>, QuorumCertificate<TYPES>>;
@@ -229,6 +365,12 @@ impl<TYPES: NodeType> AggregatableVote<TYPES, QuorumVote<TYPES>, QuorumCertifica
     ) -> HotShotEvent<TYPES> {
         HotShotEvent::QcFormed(Left(certificate))
     }
+    fn make_extensions_event(
+        view: TYPES::Time,
+        extensions: BTreeMap<TYPES::SignatureKey, Vec<u8>>,
+    ) -> Option<HotShotEvent<TYPES>> {
+        Some(HotShotEvent::QuorumVoteExtensions(view, extensions))
+    }
 }
 
 impl<TYPES: NodeType> AggregatableVote<TYPES, UpgradeVote<TYPES>, UpgradeCertificate<TYPES>>
@@ -330,11 +472,17 @@ impl<TYPES: NodeType> HandleVoteEvent<TYPES, QuorumVote<TYPES>, QuorumCertificat
     ) -> Option<HotShotTaskCompleted> {
         match event.as_ref() {
             HotShotEvent::QuorumVoteRecv(vote) => self.accumulate_vote(vote, sender).await,
+            HotShotEvent::ExtendedQuorumVoteRecv(vote, extension) => {
+                self.accumulate_extended_vote(vote, extension, sender).await
+            }
             _ => None,
         }
     }
     fn filter(event: Arc<HotShotEvent<TYPES>>) -> bool {
-        matches!(event.as_ref(), HotShotEvent::QuorumVoteRecv(_))
+        matches!(
+            event.as_ref(),
+            HotShotEvent::QuorumVoteRecv(_) | HotShotEvent::ExtendedQuorumVoteRecv(..)
+        )
     }
 }
 
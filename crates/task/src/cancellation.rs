@@ -0,0 +1,72 @@
+use std::{fmt, future::Future, pin::pin};
+
+use async_broadcast::{broadcast, InactiveReceiver, Sender};
+use futures::future::{select, Either};
+
+/// A token which cancels a group of tasks, and the subtasks they spawn, all at once.
+///
+/// Clones of a token share its state, so cancelling any clone cancels them all.
+#[derive(Clone)]
+pub struct CancellationToken {
+    /// Closed to cancel the token. Nothing is ever sent on it.
+    sender: Sender<()>,
+    /// Used to wait for the channel to be closed
+    receiver: InactiveReceiver<()>,
+}
+
+impl CancellationToken {
+    /// Create a new token, which isn't cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        let (sender, receiver) = broadcast(1);
+        Self {
+            sender,
+            receiver: receiver.deactivate(),
+        }
+    }
+
+    /// Cancel the token, and with it every clone of the token.
+    pub fn cancel(&self) {
+        self.sender.close();
+    }
+
+    /// Whether the token has been cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    /// Wait until the token is cancelled, returning immediately if it already is.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.receiver.activate_cloned();
+        // Nothing is ever sent, so this only finishes once the channel is closed
+        while receiver.recv_direct().await.is_ok() {}
+    }
+
+    /// Run `future` to completion, unless the token is cancelled first.
+    ///
+    /// Returns `None` if the token was cancelled before `future` completed.
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        if self.is_cancelled() {
+            return None;
+        }
+        match select(pin!(future), pin!(self.cancelled())).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(((), _)) => None,
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
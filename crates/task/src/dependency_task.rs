@@ -1,10 +1,13 @@
+use std::time::Duration;
+
+use async_compatibility_layer::art::async_timeout;
 #[cfg(async_executor_impl = "async-std")]
 use async_std::task::{spawn, JoinHandle};
 use futures::Future;
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::{spawn, JoinHandle};
 
-use crate::dependency::Dependency;
+use crate::{cancellation::CancellationToken, dependency::Dependency};
 
 /// Defines a type that can handle the result of a dependency
 pub trait HandleDepOutput: Send + Sized + Sync + 'static {
@@ -21,13 +24,26 @@ pub struct DependencyTask<D: Dependency<H::Output> + Send, H: HandleDepOutput +
     pub(crate) dep: D,
     /// Handles the results returned from `self.dep.completed().await`
     pub(crate) handle: H,
+    /// Stops the task, whether it's still waiting for the dependency or handling its result
+    pub(crate) cancellation: CancellationToken,
 }
 
 impl<D: Dependency<H::Output> + Send, H: HandleDepOutput + Send> DependencyTask<D, H> {
     /// Create a new `DependencyTask`
     #[must_use]
     pub fn new(dep: D, handle: H) -> Self {
-        Self { dep, handle }
+        Self {
+            dep,
+            handle,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Stop the task when `cancellation` is cancelled, like the task which spawned it
+    #[must_use]
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
     }
 }
 
@@ -38,9 +54,46 @@ impl<D: Dependency<H::Output> + Send + 'static, H: HandleDepOutput> DependencyTa
         Self: Sized,
     {
         spawn(async move {
-            if let Some(completed) = self.dep.completed().await {
-                self.handle.handle_dep_result(completed).await;
-            }
+            let Self {
+                dep,
+                handle,
+                cancellation,
+            } = self;
+            cancellation
+                .run_until_cancelled(async move {
+                    if let Some(completed) = dep.completed().await {
+                        handle.handle_dep_result(completed).await;
+                    }
+                })
+                .await;
+        })
+    }
+
+    /// Spawn the dependency task, giving up on the dependency if it doesn't complete within
+    /// `timeout` and running `on_timeout` instead
+    pub fn run_with_timeout(
+        self,
+        timeout: Duration,
+        on_timeout: impl Future<Output = ()> + Send + 'static,
+    ) -> JoinHandle<()>
+    where
+        Self: Sized,
+    {
+        spawn(async move {
+            let Self {
+                dep,
+                handle,
+                cancellation,
+            } = self;
+            cancellation
+                .run_until_cancelled(async move {
+                    match async_timeout(timeout, dep.completed()).await {
+                        Ok(Some(completed)) => handle.handle_dep_result(completed).await,
+                        Ok(None) => {}
+                        Err(_) => on_timeout.await,
+                    }
+                })
+                .await;
         })
     }
 }
@@ -48,8 +101,6 @@ impl<D: Dependency<H::Output> + Send + 'static, H: HandleDepOutput> DependencyTa
 #[cfg(test)]
 mod test {
 
-    use std::time::Duration;
-
     use async_broadcast::{broadcast, Receiver, Sender};
     #[cfg(async_executor_impl = "async-std")]
     use async_std::task::sleep;
@@ -92,7 +143,7 @@ mod test {
         let (res_tx, mut res_rx) = broadcast(10);
         let dep = eq_dep(rx, 2);
         let handle = DummyHandle { sender: res_tx };
-        let join_handle = DependencyTask { dep, handle }.run();
+        let join_handle = DependencyTask::new(dep, handle).run();
         tx.broadcast(2).await.unwrap();
         assert_eq!(res_rx.recv().await.unwrap(), TaskResult::Success(2));
 
@@ -111,7 +162,7 @@ mod test {
             let handle = DummyHandle {
                 sender: res_tx.clone(),
             };
-            handles.push(DependencyTask { dep, handle }.run());
+            handles.push(DependencyTask::new(dep, handle).run());
         }
         let tx2 = tx.clone();
         spawn(async move {
@@ -126,4 +177,26 @@ mod test {
         tx2.broadcast(100).await.unwrap();
         FuturesOrdered::from_iter(handles).collect::<Vec<_>>().await;
     }
+
+    #[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+    #[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+    // allow unused for tokio because it's a test
+    #[allow(unused_must_use)]
+    async fn times_out() {
+        let (_tx, rx) = broadcast(10);
+        let (res_tx, mut res_rx) = broadcast(10);
+        let (timeout_tx, mut timeout_rx) = broadcast(10);
+        let dep = eq_dep(rx, 2);
+        let handle = DummyHandle { sender: res_tx };
+        let join_handle = DependencyTask::new(dep, handle).run_with_timeout(
+            Duration::from_millis(10),
+            async move {
+                timeout_tx.broadcast(()).await.unwrap();
+            },
+        );
+
+        timeout_rx.recv().await.unwrap();
+        join_handle.await;
+        assert!(res_rx.try_recv().is_err());
+    }
 }
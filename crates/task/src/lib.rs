@@ -1,5 +1,8 @@
 //! Task primitives for `HotShot`
 
+/// Cancellation of tasks and their subtasks
+pub mod cancellation;
+
 /// Simple Dependency types
 pub mod dependency;
 /// Task which can uses dependencies
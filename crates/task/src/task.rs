@@ -12,6 +12,8 @@ use futures::future::try_join_all;
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::{spawn, JoinHandle};
 
+use crate::cancellation::CancellationToken;
+
 /// Trait for events that long-running tasks handle
 pub trait TaskEvent: PartialEq {
     /// The shutdown signal for this event type
@@ -52,15 +54,23 @@ pub struct Task<S: TaskState> {
     sender: Sender<Arc<S::Event>>,
     /// Receives events that are broadcast from any task, including itself
     receiver: Receiver<Arc<S::Event>>,
+    /// Stops the task, and its subtasks, when cancelled
+    cancellation: CancellationToken,
 }
 
 impl<S: TaskState + Send + 'static> Task<S> {
-    /// Create a new task
-    pub fn new(state: S, sender: Sender<Arc<S::Event>>, receiver: Receiver<Arc<S::Event>>) -> Self {
+    /// Create a new task, which runs until `cancellation` is cancelled
+    pub fn new(
+        state: S,
+        sender: Sender<Arc<S::Event>>,
+        receiver: Receiver<Arc<S::Event>>,
+        cancellation: CancellationToken,
+    ) -> Self {
         Task {
             state,
             sender,
             receiver,
+            cancellation,
         }
     }
 
@@ -69,12 +79,21 @@ impl<S: TaskState + Send + 'static> Task<S> {
         Box::new(self.state) as Box<dyn TaskState<Event = S::Event>>
     }
 
-    /// Spawn the task loop, consuming self.  Will continue until
-    /// the task reaches some shutdown condition
+    /// Spawn the task loop, consuming self.  Will continue until the task's cancellation token
+    /// is cancelled or it receives the shutdown event, at which point its subtasks are cancelled
     pub fn run(mut self) -> JoinHandle<Box<dyn TaskState<Event = S::Event>>> {
         spawn(async move {
             loop {
-                match self.receiver.recv_direct().await {
+                let Some(received) = self
+                    .cancellation
+                    .run_until_cancelled(self.receiver.recv_direct())
+                    .await
+                else {
+                    self.state.cancel_subtasks().await;
+
+                    break self.boxed_state();
+                };
+                match received {
                     Ok(input) => {
                         if *input == S::Event::shutdown_event() {
                             self.state.cancel_subtasks().await;
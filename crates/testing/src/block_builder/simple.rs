@@ -25,6 +25,7 @@ use hotshot_builder_api::{
 };
 use hotshot_types::{
     constants::Base,
+    message::TxPriority,
     traits::{
         block_contents::BlockHeader, node_implementation::NodeType,
         signature_key::BuilderSignatureKey,
@@ -150,7 +151,8 @@ where
             .transactions
             .read(|txns| {
                 Box::pin(async {
-                    txns.values()
+                    let mut txns = txns
+                        .values()
                         .filter(|txn| {
                             // We want transactions that are either unclaimed, or claimed long ago
                             // and thus probably not included, or they would've been decided on
@@ -160,6 +162,10 @@ where
                                 .unwrap_or(true)
                         })
                         .cloned()
+                        .collect::<Vec<_>>();
+                    // Higher priority transactions go first in the block
+                    txns.sort_by_key(|txn| txn.priority);
+                    txns.into_iter()
                         .map(|txn| txn.transaction)
                         .collect::<Vec<TYPES::Transaction>>()
                 })
@@ -268,6 +274,7 @@ impl<TYPES: NodeType> SimpleBuilderSource<TYPES> {
 #[derive(Debug, Clone)]
 struct SubmittedTransaction<TYPES: NodeType> {
     claimed: Option<Instant>,
+    priority: TxPriority,
     transaction: TYPES::Transaction,
 }
 
@@ -343,13 +350,14 @@ impl<TYPES: NodeType> BuilderTask<TYPES> for SimpleBuilderTask<TYPES> {
                         }
                         EventType::Transactions { transactions } if should_build_blocks => {
                             let mut queue = self.transactions.write().await;
-                            for transaction in transactions {
+                            for (transaction, priority) in transactions {
                                 if !self.decided_transactions.contains(&transaction.commit()) {
                                     queue.insert(
                                         transaction.commit(),
                                         SubmittedTransaction {
                                             claimed: None,
-                                            transaction: transaction.clone(),
+                                            priority,
+                                            transaction,
                                         },
                                     );
                                 }
@@ -17,10 +17,11 @@ use hotshot_example_types::{
 use hotshot_task_impls::events::HotShotEvent;
 use hotshot_types::{
     consensus::ConsensusMetricsValue,
+    constants::{Base, Upgrade},
     data::{Leaf, QuorumProposal, VidDisperse, VidDisperseShare, ViewNumber},
     message::{GeneralConsensusMessage, Proposal},
-    simple_certificate::DaCertificate,
-    simple_vote::{DaData, DaVote, QuorumData, QuorumVote, SimpleVote},
+    simple_certificate::{DaCertificate, UpgradeCertificate},
+    simple_vote::{DaData, DaVote, QuorumData, QuorumVote, SimpleVote, UpgradeProposalData},
     traits::{
         block_contents::vid_commitment,
         consensus_api::ConsensusApi,
@@ -30,11 +31,13 @@ use hotshot_types::{
     utils::{View, ViewInner},
     vid::{vid_scheme, VidCommitment, VidSchemeType},
     vote::{Certificate, HasViewNumber, Vote},
+    ValidatorConfig,
 };
 use jf_vid::VidScheme;
 use serde::Serialize;
+use vbs::version::StaticVersionType;
 
-use crate::test_builder::TestDescription;
+use crate::{test_builder::TestDescription, test_launcher::TestLauncher};
 
 /// create the [`SystemContextHandle`] from a node id
 /// # Panics
@@ -46,13 +49,45 @@ pub async fn build_system_handle(
     Sender<Arc<HotShotEvent<TestTypes>>>,
     Receiver<Arc<HotShotEvent<TestTypes>>>,
 ) {
-    let builder = TestDescription::default_multiple_rounds();
+    build_system_handle_from_description(TestDescription::default_multiple_rounds(), node_id).await
+}
 
+/// create the [`SystemContextHandle`] from a node id, for a network described by `builder`
+/// # Panics
+/// if cannot create a [`HotShotInitializer`]
+pub async fn build_system_handle_from_description(
+    builder: TestDescription,
+    node_id: u64,
+) -> (
+    SystemContextHandle<TestTypes, MemoryImpl>,
+    Sender<Arc<HotShotEvent<TestTypes>>>,
+    Receiver<Arc<HotShotEvent<TestTypes>>>,
+) {
     let launcher = builder.gen_launcher::<TestTypes, MemoryImpl>(node_id);
+    build_system_handle_from_launcher(&launcher, node_id).await
+}
 
+/// create the [`SystemContextHandle`] of node `node_id` on the network of `launcher`, so that
+/// the nodes built from one launcher can reach each other
+/// # Panics
+/// if cannot create a [`HotShotInitializer`]
+pub async fn build_system_handle_from_launcher(
+    launcher: &TestLauncher<TestTypes, MemoryImpl>,
+    node_id: u64,
+) -> (
+    SystemContextHandle<TestTypes, MemoryImpl>,
+    Sender<Arc<HotShotEvent<TestTypes>>>,
+    Receiver<Arc<HotShotEvent<TestTypes>>>,
+) {
     let networks = (launcher.resource_generator.channel_generator)(node_id).await;
     let storage = (launcher.resource_generator.storage)(node_id);
-    let config = launcher.resource_generator.config.clone();
+    let mut config = launcher.resource_generator.config.clone();
+    config.my_own_validator_config = ValidatorConfig::generated_from_seed_indexed(
+        [0u8; 32],
+        node_id,
+        1,
+        node_id < config.da_staked_committee_size as u64,
+    );
 
     let initializer = HotShotInitializer::<TestTypes>::from_genesis(TestInstanceState {})
         .await
@@ -83,7 +118,7 @@ pub async fn build_system_handle(
         ),
         vid_membership: <TestTypes as NodeType>::Membership::create_election(
             known_nodes_with_stake.clone(),
-            known_nodes_with_stake.clone(),
+            config.vid_nodes(),
             config.fixed_leader_for_gpuvid,
         ),
         view_sync_membership: <TestTypes as NodeType>::Membership::create_election(
@@ -321,7 +356,7 @@ pub async fn build_vote(
         },
         view,
         &handle.public_key(),
-        handle.private_key(),
+        &key_pair_for_id(handle.hotshot.id).0,
     )
     .expect("Failed to create quorum vote");
     GeneralConsensusMessage::<TestTypes>::Vote(vote)
@@ -362,3 +397,27 @@ pub fn build_fake_view_with_leaf_and_state(
         },
     }
 }
+
+/// An unsigned certificate upgrading the network to the [`Upgrade`] version from
+/// `new_version_first_view`, for testing what depends on the version in effect in a view.
+#[must_use]
+pub fn build_upgrade_certificate(
+    new_version_first_view: ViewNumber,
+) -> UpgradeCertificate<TestTypes> {
+    let old_version_last_view = ViewNumber::new((*new_version_first_view).saturating_sub(1));
+    let data = UpgradeProposalData {
+        old_version: Base::VERSION,
+        new_version: Upgrade::VERSION,
+        decide_by: old_version_last_view,
+        new_version_hash: vec![0u8; 12],
+        old_version_last_view,
+        new_version_first_view,
+    };
+    UpgradeCertificate {
+        vote_commitment: data.commit(),
+        data,
+        view_number: old_version_last_view,
+        signatures: None,
+        _pd: PhantomData,
+    }
+}
@@ -0,0 +1,108 @@
+use std::{fmt, time::Duration};
+
+use hotshot::traits::{NodeImplementation, TestableNodeImplementation};
+use hotshot_example_types::{
+    state_types::{TestInstanceState, TestValidatedState},
+    storage_types::TestStorage,
+};
+use hotshot_types::traits::{
+    network::{ConnectedNetwork, SynchronousNetwork},
+    node_implementation::NodeType,
+};
+use tracing::info;
+
+use crate::{
+    block_builder::TestBuilderImplementation, test_builder::TestDescription,
+    test_runner::TestRunStats,
+};
+
+/// Runs the same test once for each of a sweep of synchronous network delays, to characterize how
+/// consensus throughput and latency scale with the delay.
+#[derive(Clone, Debug)]
+pub struct LatencySweep {
+    /// The test to run at each delay. Its unreliable network is replaced on every run.
+    pub description: TestDescription,
+    /// The delays to run the test with, each applied to every message
+    pub delays: Vec<Duration>,
+}
+
+/// The results of a single run of a [`LatencySweep`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LatencySweepRow {
+    /// The network delay of the run
+    pub delay: Duration,
+    /// How far consensus progressed during the run
+    pub stats: TestRunStats,
+}
+
+/// The results of a [`LatencySweep`], one row per delay in the order they were run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencySweepReport {
+    /// The results of each run
+    pub rows: Vec<LatencySweepRow>,
+}
+
+impl LatencySweep {
+    /// Run the test at each delay in turn.
+    ///
+    /// # Panics
+    /// if any of the runs fails
+    pub async fn run<TYPES, I, N, B>(&self) -> LatencySweepReport
+    where
+        TYPES: NodeType<InstanceState = TestInstanceState, ValidatedState = TestValidatedState>,
+        I: TestableNodeImplementation<TYPES>
+            + NodeImplementation<
+                TYPES,
+                QuorumNetwork = N,
+                DaNetwork = N,
+                Storage = TestStorage<TYPES>,
+            >,
+        N: ConnectedNetwork<TYPES::SignatureKey>,
+        B: TestBuilderImplementation<TYPES>,
+    {
+        let mut report = LatencySweepReport::default();
+        for &delay in &self.delays {
+            let delay_ms = u64::try_from(delay.as_millis()).unwrap_or(u64::MAX);
+            let description = TestDescription {
+                unreliable_network: Some(Box::new(SynchronousNetwork {
+                    delay_high_ms: delay_ms,
+                    delay_low_ms: delay_ms,
+                    ..Default::default()
+                })),
+                ..self.description.clone()
+            };
+            let stats = description
+                .gen_launcher::<TYPES, I>(0)
+                .launch()
+                .run_test::<B>()
+                .await;
+            info!("Latency sweep at {delay:?}: {stats:?}");
+            report.rows.push(LatencySweepRow { delay, stats });
+        }
+        report
+    }
+}
+
+impl fmt::Display for LatencySweepReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>10} {:>14} {:>12} {:>16} {:>14}",
+            "delay", "decided views", "elapsed", "views per second", "view latency"
+        )?;
+        for LatencySweepRow { delay, stats } in &self.rows {
+            writeln!(
+                f,
+                "{:>10} {:>14} {:>12} {:>16.3} {:>14}",
+                format!("{delay:?}"),
+                stats.decided_views,
+                format!("{:.3?}", stats.elapsed),
+                stats.throughput(),
+                stats
+                    .view_latency()
+                    .map_or_else(|| "-".to_string(), |latency| format!("{latency:.3?}")),
+            )?;
+        }
+        Ok(())
+    }
+}
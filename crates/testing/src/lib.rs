@@ -18,6 +18,9 @@ pub mod test_launcher;
 /// runner
 pub mod test_runner;
 
+/// harness running a test across a sweep of network delays
+pub mod latency_sweep;
+
 /// task that's consuming events and asserting safety
 pub mod overall_safety_task;
 
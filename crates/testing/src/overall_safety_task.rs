@@ -1,19 +1,25 @@
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
     sync::Arc,
+    time::Instant,
 };
 
 use anyhow::Result;
 use async_broadcast::Sender;
 use async_lock::RwLock;
 use async_trait::async_trait;
+use committable::{Commitment, Committable};
 use hotshot::{traits::TestableNodeImplementation, HotShotError};
 use hotshot_types::{
     data::Leaf,
     error::RoundTimedoutState,
     event::{Event, EventType, LeafChain},
     simple_certificate::QuorumCertificate,
-    traits::node_implementation::{ConsensusTime, NodeType},
+    traits::{
+        block_contents::BlockHeader,
+        node_implementation::{ConsensusTime, NodeType},
+        BlockPayload,
+    },
     vid::VidCommitment,
 };
 use snafu::Snafu;
@@ -60,11 +66,42 @@ pub enum OverallSafetyTaskErr<TYPES: NodeType> {
         got: usize,
     },
     /// mismatched leaves for a view
-    MismatchedLeaf,
+    MismatchedLeaf {
+        /// the view
+        view: TYPES::Time,
+        /// each distinct leaf decided -> the nodes which decided it
+        leaves: HashMap<Commitment<Leaf<TYPES>>, Vec<u64>>,
+    },
     /// mismatched states for a view
     InconsistentStates,
     /// mismatched blocks for a view
     InconsistentBlocks,
+    /// a transaction was decided in more than one block
+    DuplicateTransaction {
+        /// the transaction
+        transaction: Commitment<TYPES::Transaction>,
+    },
+    /// a transaction was not decided in time
+    MissingTransaction {
+        /// the transaction
+        transaction: Commitment<TYPES::Transaction>,
+        /// the view it was submitted in
+        submitted_view: TYPES::Time,
+    },
+    /// a node running from the start didn't decide a view decided by the others
+    NodeMissedView {
+        /// the node
+        node_id: u64,
+        /// the view
+        view: TYPES::Time,
+    },
+    /// views were decided more slowly than required
+    TooSlow {
+        /// observed number of views decided per second
+        observed: f64,
+        /// required number of views decided per second
+        required: f64,
+    },
 }
 
 /// Data availability task state
@@ -96,9 +133,22 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestTaskState
             num_successful_views,
             threshold_calculator,
             transaction_threshold,
+            expected_failed_views: _,
+            transaction_inclusion_views,
+            min_views_per_second: _,
+            require_all_nodes_decide,
         }: OverallSafetyPropertiesDescription = self.properties.clone();
         let Event { view_number, event } = message;
         let key = match event {
+            EventType::Transactions { transactions } => {
+                for (transaction, _) in transactions {
+                    self.ctx
+                        .submitted_transactions
+                        .entry(transaction.commit())
+                        .or_insert(view_number);
+                }
+                return Ok(());
+            }
             EventType::Error { error } => {
                 self.ctx
                     .insert_error_to_context(view_number, id, error.clone());
@@ -108,11 +158,25 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestTaskState
                 leaf_chain,
                 qc,
                 block_size: maybe_block_size,
+                decided_view_count: _,
             } => {
                 // Skip the genesis leaf.
                 if leaf_chain.last().unwrap().leaf.view_number() == TYPES::Time::genesis() {
                     return Ok(());
                 }
+                self.ctx
+                    .decide_times
+                    .entry(view_number)
+                    .or_insert_with(Instant::now);
+                if let Some(transaction) = self.ctx.record_decided_transactions(&leaf_chain) {
+                    if transaction_inclusion_views.is_some() {
+                        let _ = self.test_sender.broadcast(TestEvent::Shutdown).await;
+                        self.error = Some(Box::new(
+                            OverallSafetyTaskErr::<TYPES>::DuplicateTransaction { transaction },
+                        ));
+                        return Ok(());
+                    }
+                }
                 let paired_up = (leaf_chain.to_vec(), (*qc).clone());
                 match self.ctx.round_results.entry(view_number) {
                     Entry::Occupied(mut o) => {
@@ -143,6 +207,7 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestTaskState
         // update view count
         let threshold = (threshold_calculator)(len, len);
 
+        let expected_nodes = require_all_nodes_decide.then_some(&self.ctx.expected_nodes);
         let view = self.ctx.round_results.get_mut(&view_number).unwrap();
         if let Some(key) = key {
             view.update_status(
@@ -152,6 +217,7 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestTaskState
                 check_leaf,
                 check_block,
                 transaction_threshold,
+                expected_nodes,
             );
             match view.status.clone() {
                 ViewStatus::Ok => {
@@ -163,7 +229,7 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestTaskState
                 }
                 ViewStatus::Failed => {
                     self.ctx.failed_views.insert(view_number);
-                    if self.ctx.failed_views.len() > num_failed_views {
+                    if self.num_unexpected_failed_views() > num_failed_views {
                         let _ = self.test_sender.broadcast(TestEvent::Shutdown).await;
                         self.error =
                             Some(Box::new(OverallSafetyTaskErr::<TYPES>::TooManyFailures {
@@ -184,7 +250,7 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestTaskState
         } else if view.check_if_failed(threshold, len) {
             view.status = ViewStatus::Failed;
             self.ctx.failed_views.insert(view_number);
-            if self.ctx.failed_views.len() > num_failed_views {
+            if self.num_unexpected_failed_views() > num_failed_views {
                 let _ = self.test_sender.broadcast(TestEvent::Shutdown).await;
                 self.error = Some(Box::new(OverallSafetyTaskErr::<TYPES>::TooManyFailures {
                     failed_views: self.ctx.failed_views.clone(),
@@ -207,11 +273,48 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestTaskState
             num_successful_views,
             threshold_calculator: _,
             transaction_threshold: _,
+            expected_failed_views,
+            transaction_inclusion_views,
+            min_views_per_second,
+            require_all_nodes_decide: _,
         }: OverallSafetyPropertiesDescription = self.properties.clone();
 
-        let num_incomplete_views = self.ctx.round_results.len()
-            - self.ctx.successful_views.len()
-            - self.ctx.failed_views.len();
+        // Views we expect to fail are still tracked, but don't count towards the failure budget.
+        let num_incomplete_views = self
+            .ctx
+            .round_results
+            .keys()
+            .filter(|view| {
+                !self.ctx.successful_views.contains(view)
+                    && !self.ctx.failed_views.contains(view)
+                    && !expected_failed_views.contains(&view.u64())
+            })
+            .count();
+
+        // A view decided by enough nodes, but still waiting on one which was there from the start,
+        // though every node has decided a later view since. Views still in flight when the test
+        // ended aren't held against the stragglers.
+        let last_ok_view = self
+            .ctx
+            .round_results
+            .iter()
+            .filter(|(_, result)| matches!(result.status, ViewStatus::Ok))
+            .map(|(view, _)| *view)
+            .max();
+        if let Some((view, node_id)) = self
+            .ctx
+            .round_results
+            .iter()
+            .filter(|(view, result)| {
+                Some(**view) < last_ok_view && !matches!(result.status, ViewStatus::Ok)
+            })
+            .find_map(|(view, result)| result.straggler.map(|node_id| (*view, node_id)))
+        {
+            return TestResult::Fail(Box::new(OverallSafetyTaskErr::<TYPES>::NodeMissedView {
+                node_id,
+                view,
+            }));
+        }
 
         if self.ctx.successful_views.len() < num_successful_views {
             return TestResult::Fail(Box::new(OverallSafetyTaskErr::<TYPES>::NotEnoughDecides {
@@ -220,15 +323,48 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> TestTaskState
             }));
         }
 
-        if self.ctx.failed_views.len() + num_incomplete_views > num_failed_rounds_total {
+        if self.num_unexpected_failed_views() + num_incomplete_views > num_failed_rounds_total {
             return TestResult::Fail(Box::new(OverallSafetyTaskErr::<TYPES>::TooManyFailures {
                 failed_views: self.ctx.failed_views.clone(),
             }));
         }
+
+        if let Some((transaction, submitted_view)) =
+            transaction_inclusion_views.and_then(|views| self.ctx.missing_transaction(views))
+        {
+            return TestResult::Fail(Box::new(
+                OverallSafetyTaskErr::<TYPES>::MissingTransaction {
+                    transaction,
+                    submitted_view,
+                },
+            ));
+        }
+
+        if let Some(required) = min_views_per_second {
+            if let Some(observed) = self.ctx.views_per_second() {
+                if observed < required {
+                    return TestResult::Fail(Box::new(OverallSafetyTaskErr::<TYPES>::TooSlow {
+                        observed,
+                        required,
+                    }));
+                }
+            }
+        }
         TestResult::Pass
     }
 }
 
+impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> OverallSafetyTask<TYPES, I> {
+    /// Number of failed views, excluding those listed in `expected_failed_views`.
+    fn num_unexpected_failed_views(&self) -> usize {
+        self.ctx
+            .failed_views
+            .iter()
+            .filter(|view| !self.properties.expected_failed_views.contains(&view.u64()))
+            .count()
+    }
+}
+
 /// Result of running a round of consensus
 #[derive(Debug)]
 pub struct RoundResult<TYPES: NodeType> {
@@ -255,6 +391,9 @@ pub struct RoundResult<TYPES: NodeType> {
 
     /// number of transactions -> number of nodes reporting that number
     pub num_txns_map: HashMap<u64, usize>,
+
+    /// a node expected to decide this round which hasn't yet, though enough others have
+    pub straggler: Option<u64>,
 }
 
 impl<TYPES: NodeType> Default for RoundResult<TYPES> {
@@ -265,6 +404,7 @@ impl<TYPES: NodeType> Default for RoundResult<TYPES> {
             leaf_map: HashMap::default(),
             block_map: HashMap::default(),
             num_txns_map: HashMap::default(),
+            straggler: None,
             status: ViewStatus::InProgress,
         }
     }
@@ -278,6 +418,11 @@ impl<TYPES: NodeType> Default for RoundCtx<TYPES> {
             round_results: HashMap::default(),
             failed_views: HashSet::default(),
             successful_views: HashSet::default(),
+            submitted_transactions: HashMap::default(),
+            decided_transactions: HashMap::default(),
+            last_decided_view: None,
+            decide_times: BTreeMap::default(),
+            expected_nodes: HashSet::default(),
         }
     }
 }
@@ -295,9 +440,82 @@ pub struct RoundCtx<TYPES: NodeType> {
     pub failed_views: HashSet<TYPES::Time>,
     /// successful views
     pub successful_views: HashSet<TYPES::Time>,
+    /// transaction -> earliest view it was submitted in
+    pub submitted_transactions: HashMap<Commitment<TYPES::Transaction>, TYPES::Time>,
+    /// transaction -> leaf it was decided in
+    pub decided_transactions: HashMap<Commitment<TYPES::Transaction>, Commitment<Leaf<TYPES>>>,
+    /// latest view decided by any node
+    pub last_decided_view: Option<TYPES::Time>,
+    /// view -> when it was first decided by any node
+    pub decide_times: BTreeMap<TYPES::Time, Instant>,
+    /// ids of the nodes running from the start, which must decide every successful view if
+    /// `require_all_nodes_decide` is set
+    pub expected_nodes: HashSet<u64>,
 }
 
 impl<TYPES: NodeType> RoundCtx<TYPES> {
+    /// records the transactions in the blocks of a decided leaf chain, returning a transaction
+    /// that was already decided in a different block, if any
+    pub fn record_decided_transactions(
+        &mut self,
+        leaf_chain: &LeafChain<TYPES>,
+    ) -> Option<Commitment<TYPES::Transaction>> {
+        let mut duplicate = None;
+        for leaf_info in leaf_chain {
+            let leaf = &leaf_info.leaf;
+            self.last_decided_view = self.last_decided_view.max(Some(leaf.view_number()));
+            // Decides during catchup may be missing the payload.
+            let Some(payload) = leaf.block_payload() else {
+                continue;
+            };
+            let leaf_commitment = leaf.commit();
+            for transaction in payload.transaction_commitments(leaf.block_header().metadata()) {
+                match self.decided_transactions.entry(transaction) {
+                    Entry::Occupied(o) => {
+                        if *o.get() != leaf_commitment {
+                            error!("Transaction {transaction} decided in more than one block");
+                            duplicate = duplicate.or(Some(transaction));
+                        }
+                    }
+                    Entry::Vacant(v) => {
+                        v.insert(leaf_commitment);
+                    }
+                }
+            }
+        }
+        duplicate
+    }
+
+    /// a submitted transaction that was not decided within `inclusion_views` views of being
+    /// submitted, along with the view it was submitted in, if any
+    #[must_use]
+    pub fn missing_transaction(
+        &self,
+        inclusion_views: u64,
+    ) -> Option<(Commitment<TYPES::Transaction>, TYPES::Time)> {
+        let last_decided_view = self.last_decided_view?;
+        self.submitted_transactions
+            .iter()
+            .find(|(transaction, submitted_view)| {
+                submitted_view.u64().saturating_add(inclusion_views) <= last_decided_view.u64()
+                    && !self.decided_transactions.contains_key(transaction)
+            })
+            .map(|(transaction, submitted_view)| (*transaction, *submitted_view))
+    }
+
+    /// number of views decided per second, from the first to the last decided view, if at least
+    /// two views were decided at different times
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn views_per_second(&self) -> Option<f64> {
+        let (first_view, first_time) = self.decide_times.first_key_value()?;
+        let (last_view, last_time) = self.decide_times.last_key_value()?;
+        let elapsed = last_time
+            .saturating_duration_since(*first_time)
+            .as_secs_f64();
+        (elapsed > 0.0).then(|| (last_view.u64() - first_view.u64()) as f64 / elapsed)
+    }
+
     /// inserts an error into the context
     pub fn insert_error_to_context(
         &mut self,
@@ -388,6 +606,7 @@ impl<TYPES: NodeType> RoundResult<TYPES> {
         check_leaf: bool,
         check_block: bool,
         transaction_threshold: u64,
+        expected_nodes: Option<&HashSet<u64>>,
     ) {
         let num_decided = self.success_nodes.len();
         let num_failed = self.failed_nodes.len();
@@ -402,7 +621,10 @@ impl<TYPES: NodeType> RoundResult<TYPES> {
                 for leaf in self.leaf_map.keys() {
                     if leaf.view_number() > quorum_leaf.view_number() {
                         error!("LEAF MAP (that is mismatched) IS: {:?}", self.leaf_map);
-                        self.status = ViewStatus::Err(OverallSafetyTaskErr::MismatchedLeaf);
+                        self.status = ViewStatus::Err(OverallSafetyTaskErr::MismatchedLeaf {
+                            view: key.view_number(),
+                            leaves: self.leaf_report(),
+                        });
                         return;
                     }
                 }
@@ -439,10 +661,27 @@ impl<TYPES: NodeType> RoundResult<TYPES> {
 
             let block_key = key.payload_commitment();
 
-            if *self.block_map.get(&block_key).unwrap() == threshold
-                && *self.leaf_map.get(key).unwrap() == threshold
+            if *self.block_map.get(&block_key).unwrap() >= threshold
+                && *self.leaf_map.get(key).unwrap() >= threshold
             {
-                self.status = ViewStatus::Ok;
+                // Every expected node must decide too, and one which failed never will.
+                self.straggler = expected_nodes.and_then(|nodes| {
+                    nodes
+                        .iter()
+                        .filter(|node_id| !self.success_nodes.contains_key(node_id))
+                        .min()
+                        .copied()
+                });
+                match self.straggler {
+                    None => self.status = ViewStatus::Ok,
+                    Some(node_id) if self.failed_nodes.contains_key(&node_id) => {
+                        self.status = ViewStatus::Err(OverallSafetyTaskErr::NodeMissedView {
+                            node_id,
+                            view: key.view_number(),
+                        });
+                    }
+                    Some(_) => {}
+                }
                 return;
             }
         }
@@ -453,6 +692,25 @@ impl<TYPES: NodeType> RoundResult<TYPES> {
         }
     }
 
+    /// the nodes which decided each distinct leaf, in order of node id
+    #[must_use]
+    pub fn leaf_report(&self) -> HashMap<Commitment<Leaf<TYPES>>, Vec<u64>> {
+        let mut leaves = HashMap::<Commitment<Leaf<TYPES>>, Vec<u64>>::new();
+
+        for (node_id, (leaf_vec, _)) in &self.success_nodes {
+            if let Some(leaf_info) = leaf_vec.first() {
+                leaves
+                    .entry(leaf_info.leaf.commit())
+                    .or_default()
+                    .push(*node_id);
+            }
+        }
+        for node_ids in leaves.values_mut() {
+            node_ids.sort_unstable();
+        }
+        leaves
+    }
+
     /// generate leaves
     #[must_use]
     pub fn gen_leaves(&self) -> HashMap<Leaf<TYPES>, usize> {
@@ -494,6 +752,20 @@ pub struct OverallSafetyPropertiesDescription {
     /// threshold calculator. Given number of live and total nodes, provide number of successes
     /// required to mark view as successful
     pub threshold_calculator: Arc<dyn Fn(usize, usize) -> usize + Send + Sync>,
+    /// views that are expected to fail (e.g. because their leader was deliberately shut down).
+    /// These are still tracked, but are not counted against `num_failed_views`.
+    pub expected_failed_views: HashSet<u64>,
+    /// whether or not to check that every submitted transaction is decided in exactly one block
+    /// if None: don't check
+    /// if Some(n), a transaction submitted in view v must be decided by the time view v + n is
+    pub transaction_inclusion_views: Option<u64>,
+    /// minimum number of views decided per second, measured from the first to the last decided
+    /// view
+    /// if None: don't check
+    pub min_views_per_second: Option<f64>,
+    /// whether or not to check that every node running from the start decides every view which
+    /// succeeds, catching a single straggler
+    pub require_all_nodes_decide: bool,
 }
 
 impl std::fmt::Debug for OverallSafetyPropertiesDescription {
@@ -504,6 +776,13 @@ impl std::fmt::Debug for OverallSafetyPropertiesDescription {
             .field("check_block", &self.check_block)
             .field("num_failed_rounds_total", &self.num_failed_views)
             .field("transaction_threshold", &self.transaction_threshold)
+            .field("expected_failed_views", &self.expected_failed_views)
+            .field(
+                "transaction_inclusion_views",
+                &self.transaction_inclusion_views,
+            )
+            .field("min_views_per_second", &self.min_views_per_second)
+            .field("require_all_nodes_decide", &self.require_all_nodes_decide)
             .finish_non_exhaustive()
     }
 }
@@ -518,6 +797,10 @@ impl Default for OverallSafetyPropertiesDescription {
             transaction_threshold: 0,
             // very strict
             threshold_calculator: Arc::new(|_num_live, num_total| 2 * num_total / 3 + 1),
+            expected_failed_views: HashSet::new(),
+            transaction_inclusion_views: None,
+            min_views_per_second: None,
+            require_all_nodes_decide: false,
         }
     }
 }
@@ -2,11 +2,13 @@ use std::sync::Arc;
 
 use async_lock::RwLock;
 use async_trait::async_trait;
+use hotshot_example_types::node_types::TestTypes;
 use hotshot_task_impls::events::{HotShotEvent, HotShotEvent::*};
 use hotshot_types::{
     data::null_block,
     traits::{block_contents::BlockHeader, node_implementation::NodeType},
 };
+use vbs::version::Version;
 
 use crate::predicates::{Predicate, PredicateResult};
 
@@ -229,6 +231,18 @@ where
     Box::new(EventPredicate { check, info })
 }
 
+pub fn quorum_proposal_send_with_version(version: Version) -> Box<EventPredicate<TestTypes>> {
+    let info = format!("QuorumProposalSend with a block header built for version {version}");
+    let check: EventCallback<TestTypes> =
+        Arc::new(move |e: Arc<HotShotEvent<TestTypes>>| match e.as_ref() {
+            QuorumProposalSend(proposal, _) => {
+                proposal.data.block_header.built_for_version == Some(version)
+            }
+            _ => false,
+        });
+    Box::new(EventPredicate { info, check })
+}
+
 pub fn quorum_proposal_send_with_null_block<TYPES>(
     num_storage_nodes: usize,
 ) -> Box<EventPredicate<TYPES>>
@@ -1,13 +1,20 @@
 use std::{
+    cmp,
     collections::{BTreeMap, HashMap},
     sync::Arc,
 };
 
 use anyhow::Result;
+use async_broadcast::{Receiver, Sender};
+use async_compatibility_layer::art::async_spawn;
 use async_lock::RwLock;
 use async_trait::async_trait;
 use futures::future::Either::{Left, Right};
-use hotshot::{traits::TestableNodeImplementation, types::EventType, HotShotInitializer};
+use hotshot::{
+    traits::TestableNodeImplementation,
+    types::{EventType, SystemContextHandle},
+    HotShotInitializer,
+};
 use hotshot_example_types::{
     state_types::{TestInstanceState, TestValidatedState},
     storage_types::TestStorage,
@@ -19,13 +26,15 @@ use hotshot_types::{
     traits::{
         network::ConnectedNetwork,
         node_implementation::{NodeImplementation, NodeType},
+        storage::Storage,
     },
     vote::HasViewNumber,
-    ValidatorConfig,
+    HotShotConfig, ValidatorConfig,
 };
 use snafu::Snafu;
 
 use crate::{
+    test_launcher::Networks,
     test_runner::{LateStartNode, Node, TestRunner},
     test_task::{TestResult, TestTaskState},
 };
@@ -35,7 +44,13 @@ pub type StateAndBlock<S, B> = (Vec<S>, Vec<B>);
 
 /// error for the spinning task
 #[derive(Snafu, Debug)]
-pub struct SpinningTaskErr {}
+pub enum SpinningTaskErr {
+    /// restarted nodes that never decided a view after rejoining
+    NotCaughtUp {
+        /// the indices of the nodes
+        nodes: Vec<usize>,
+    },
+}
 
 /// Spinning task state
 pub struct SpinningTask<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
@@ -51,6 +66,113 @@ pub struct SpinningTask<TYPES: NodeType, I: TestableNodeImplementation<TYPES>> {
     pub(crate) last_decided_leaf: Leaf<TYPES>,
     /// Highest qc seen in the test for restarting nodes
     pub(crate) high_qc: QuorumCertificate<TYPES>,
+    /// senders that the events of restarted nodes are forwarded to, by node index
+    pub(crate) restart_event_senders: HashMap<usize, Sender<Event<TYPES>>>,
+    /// restarted nodes that have not yet decided a view since rejoining, mapped to the view they
+    /// were restarted in
+    pub(crate) awaiting_catchup: HashMap<usize, TYPES::Time>,
+}
+
+/// Forward every event from `receiver` to `sender`, until either end is closed
+pub(crate) fn forward_events<TYPES: NodeType>(
+    mut receiver: Receiver<Event<TYPES>>,
+    sender: Sender<Event<TYPES>>,
+) {
+    async_spawn(async move {
+        while let Ok(event) = receiver.recv().await {
+            if sender.broadcast(event).await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// The validator config of the node with `node_id`
+fn validator_config<TYPES: NodeType>(
+    node_id: u64,
+    config: &HotShotConfig<TYPES::SignatureKey>,
+) -> ValidatorConfig<TYPES::SignatureKey> {
+    // We assign node's public key and stake value rather than read from config file since it's a test
+    ValidatorConfig::generated_from_seed_indexed(
+        [0u8; 32],
+        node_id,
+        1,
+        // For tests, make the node DA based on its index
+        node_id < config.da_staked_committee_size as u64,
+    )
+}
+
+impl<
+        TYPES: NodeType<InstanceState = TestInstanceState, ValidatedState = TestValidatedState>,
+        I: TestableNodeImplementation<TYPES>,
+        N: ConnectedNetwork<TYPES::SignatureKey>,
+    > SpinningTask<TYPES, I>
+where
+    I: NodeImplementation<TYPES, QuorumNetwork = N, DaNetwork = N, Storage = TestStorage<TYPES>>,
+{
+    /// Shut down the node with `handle` and start it again in `view_number`, reloading its state
+    /// from its storage while its networks keep running. Returns the handle to the new instance.
+    async fn restart_node(
+        &self,
+        node_id: u64,
+        networks: Networks<TYPES, I>,
+        handle: &mut SystemContextHandle<TYPES, I>,
+        view_number: TYPES::Time,
+    ) -> SystemContextHandle<TYPES, I> {
+        handle.shut_down_tasks().await;
+
+        // Reuse the node's storage, as a real node would reload it from disk.
+        let storage = handle.storage().read().await.clone();
+        let anchor_view = self.last_decided_leaf.view_number();
+        let mut high_qc = self.high_qc.clone();
+        if let Some(stored_qc) = storage.load_high_qc().await.ok().flatten() {
+            if stored_qc.view_number() > high_qc.view_number() {
+                high_qc = stored_qc;
+            }
+        }
+        let (undecided_leafs, undecided_state) = match storage.load_undecided_state().await {
+            Ok(Some((leafs, mut state))) => (
+                leafs
+                    .into_values()
+                    .filter(|leaf| leaf.view_number() > anchor_view)
+                    .collect(),
+                state.split_off(&(anchor_view + 1)),
+            ),
+            _ => (Vec::new(), BTreeMap::new()),
+        };
+        // Never start in a view we have already voted in.
+        let start_view = match storage.last_voted_view().await {
+            Some(voted) => cmp::max(view_number, voted + 1),
+            None => view_number,
+        };
+        // Restore the payloads we stored, so we can answer VID requests for them right away.
+        let saved_payloads = storage.saved_payloads().await.split_off(&anchor_view);
+        let initializer = HotShotInitializer::<TYPES>::from_snapshot(
+            self.last_decided_leaf.clone(),
+            TestInstanceState {},
+            None,
+            start_view,
+            BTreeMap::new(),
+            high_qc,
+            undecided_leafs,
+            undecided_state,
+            saved_payloads,
+        );
+
+        let config = handle.hotshot.config.clone();
+        let context = TestRunner::add_node_with_config(
+            node_id,
+            networks,
+            (*handle.hotshot.memberships).clone(),
+            initializer,
+            config.clone(),
+            validator_config::<TYPES>(node_id, &config),
+            storage,
+        )
+        .await;
+
+        context.run_tasks().await
+    }
 }
 
 #[async_trait]
@@ -65,16 +187,25 @@ where
 {
     type Event = Event<TYPES>;
 
-    async fn handle_event(&mut self, (message, _id): (Self::Event, usize)) -> Result<()> {
+    async fn handle_event(&mut self, (message, id): (Self::Event, usize)) -> Result<()> {
         let Event { view_number, event } = message;
 
         if let EventType::Decide {
             leaf_chain,
             qc: _,
             block_size: _,
+            decided_view_count: _,
         } = event
         {
             let leaf = leaf_chain.first().unwrap().leaf.clone();
+            if self
+                .awaiting_catchup
+                .get(&id)
+                .is_some_and(|restart_view| leaf.view_number() > *restart_view)
+            {
+                tracing::error!("Node {} caught up after restarting", id);
+                self.awaiting_catchup.remove(&id);
+            }
             if leaf.view_number() > self.last_decided_leaf.view_number() {
                 self.last_decided_leaf = leaf;
             }
@@ -114,15 +245,8 @@ where
                                             Vec::new(),
                                             BTreeMap::new(),
                                         );
-                                        // We assign node's public key and stake value rather than read from config file since it's a test
                                         let validator_config =
-                                            ValidatorConfig::generated_from_seed_indexed(
-                                                [0u8; 32],
-                                                node_id,
-                                                1,
-                                                // For tests, make the node DA based on its index
-                                                node_id < config.da_staked_committee_size as u64,
-                                            );
+                                            validator_config::<TYPES>(node_id, &config);
                                         TestRunner::add_node_with_config(
                                             node_id,
                                             node.networks.clone(),
@@ -146,7 +270,11 @@ where
                                     networks: node.networks,
                                     handle,
                                 };
-                                node.handle.hotshot.start_consensus().await;
+                                node.handle
+                                    .hotshot
+                                    .start_consensus()
+                                    .await
+                                    .expect("Failed to start consensus");
 
                                 self.handles.write().await.push(node);
                             }
@@ -157,6 +285,33 @@ where
                                 node.handle.shut_down().await;
                             }
                         }
+                        UpDown::Restart => {
+                            let mut handles = self.handles.write().await;
+                            if let Some(node) = handles.get_mut(idx) {
+                                tracing::error!("Node {} restarting", idx);
+                                let handle = self
+                                    .restart_node(
+                                        node.node_id,
+                                        node.networks.clone(),
+                                        &mut node.handle,
+                                        view_number,
+                                    )
+                                    .await;
+                                if let Some(sender) = self.restart_event_senders.get(&idx) {
+                                    forward_events(
+                                        handle.event_stream_known_impl(),
+                                        sender.clone(),
+                                    );
+                                }
+                                handle
+                                    .hotshot
+                                    .start_consensus()
+                                    .await
+                                    .expect("Failed to start consensus");
+                                node.handle = handle;
+                                self.awaiting_catchup.insert(idx, view_number);
+                            }
+                        }
                         UpDown::NetworkUp => {
                             if let Some(handle) = self.handles.write().await.get(idx) {
                                 tracing::error!("Node {} networks resuming", idx);
@@ -183,7 +338,12 @@ where
     }
 
     fn check(&self) -> TestResult {
-        TestResult::Pass
+        if self.awaiting_catchup.is_empty() {
+            return TestResult::Pass;
+        }
+        let mut nodes: Vec<usize> = self.awaiting_catchup.keys().copied().collect();
+        nodes.sort_unstable();
+        TestResult::Fail(Box::new(SpinningTaskErr::NotCaughtUp { nodes }))
     }
 }
 
@@ -198,6 +358,8 @@ pub enum UpDown {
     NetworkUp,
     /// spin the node's network down
     NetworkDown,
+    /// restart the node from its storage, keeping its networks up
+    Restart,
 }
 
 /// denotes a change in node state
@@ -1,9 +1,21 @@
-use std::{collections::HashMap, num::NonZeroUsize, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroUsize,
+    sync::Arc,
+    time::Duration,
+};
 
 use hotshot::traits::{NetworkReliability, TestableNodeImplementation};
 use hotshot_example_types::{state_types::TestInstanceState, storage_types::TestStorage};
 use hotshot_types::{
-    traits::node_implementation::NodeType, ExecutionType, HotShotConfig, ValidatorConfig,
+    constants::{
+        DEFAULT_BUILDER_PREFETCH_DEPTH, DEFAULT_CATCHUP_REQUESTS_PER_SECOND,
+        DEFAULT_MAX_BLOCK_SIZE, DEFAULT_MAX_INBOUND_MESSAGE_SIZE, DEFAULT_MAX_PENDING_TRANSACTIONS,
+        DEFAULT_MAX_UNDECIDED_VIEWS, DEFAULT_PROPOSAL_BACKFILL_VIEWS,
+        DEFAULT_TRANSACTION_POW_DIFFICULTY,
+    },
+    traits::node_implementation::NodeType,
+    ExecutionType, HotShotConfig, ValidationLevel, ValidatorConfig,
 };
 use tide_disco::Url;
 use vec1::Vec1;
@@ -57,6 +69,12 @@ pub struct TestDescription {
     pub da_staked_committee_size: usize,
     /// Size of the non-staked DA committee for the test
     pub da_non_staked_committee_size: usize,
+    /// Number of unstaked nodes which are VID storage nodes alongside the staked nodes. If 0,
+    /// the staked nodes are the only VID storage nodes.
+    pub vid_only_nodes: usize,
+    /// Number of nodes which join the quorum committee without stake, so they follow consensus
+    /// as observers. They are started after the staked nodes.
+    pub observer_nodes: usize,
     /// overall safety property description
     pub overall_safety_properties: OverallSafetyPropertiesDescription,
     /// spinning properties
@@ -129,6 +147,10 @@ impl TestDescription {
                 num_failed_views: 15,
                 transaction_threshold: 0,
                 threshold_calculator: Arc::new(|_active, total| (2 * total / 3 + 1)),
+                expected_failed_views: HashSet::new(),
+                transaction_inclusion_views: None,
+                min_views_per_second: None,
+                require_all_nodes_decide: false,
             },
             timing_data: TimingData {
                 next_view_timeout: 2000,
@@ -162,6 +184,10 @@ impl TestDescription {
                 num_failed_views: 8,
                 transaction_threshold: 0,
                 threshold_calculator: Arc::new(|_active, total| (2 * total / 3 + 1)),
+                expected_failed_views: HashSet::new(),
+                transaction_inclusion_views: None,
+                min_views_per_second: None,
+                require_all_nodes_decide: false,
             },
             timing_data: TimingData {
                 start_delay: 120_000,
@@ -224,6 +250,8 @@ impl Default for TestDescription {
             num_bootstrap_nodes: num_nodes_with_stake,
             da_staked_committee_size: num_nodes_with_stake,
             da_non_staked_committee_size: num_nodes_without_stake,
+            vid_only_nodes: 0,
+            observer_nodes: 0,
             spinning_properties: SpinningTaskDescription {
                 node_changes: vec![],
             },
@@ -269,6 +297,8 @@ impl TestDescription {
             timing_data,
             da_staked_committee_size,
             da_non_staked_committee_size,
+            vid_only_nodes,
+            observer_nodes,
             unreliable_network,
             ..
         } = self.clone();
@@ -276,7 +306,7 @@ impl TestDescription {
         let mut known_da_nodes = Vec::new();
 
         // We assign known_nodes' public key and stake value here rather than read from config file since it's a test.
-        let known_nodes_with_stake = (0..num_nodes_with_stake)
+        let mut known_nodes_with_stake: Vec<_> = (0..num_nodes_with_stake)
             .map(|node_id_| {
                 let cur_validator_config: ValidatorConfig<TYPES::SignatureKey> =
                     ValidatorConfig::generated_from_seed_indexed(
@@ -294,6 +324,40 @@ impl TestDescription {
                 cur_validator_config.public_config()
             })
             .collect();
+        // VID-only nodes are known to the network without stake, but store VID shares like the
+        // staked nodes.
+        let vid_only_configs: Vec<ValidatorConfig<TYPES::SignatureKey>> = (num_nodes_with_stake
+            + observer_nodes
+            ..num_nodes_with_stake + observer_nodes + vid_only_nodes)
+            .map(|node_id_| {
+                ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id_ as u64, 1, false)
+            })
+            .collect();
+        let known_nodes_without_stake = vid_only_configs
+            .iter()
+            .map(|config| config.public_key.clone())
+            .collect();
+        let known_vid_nodes = if vid_only_configs.is_empty() {
+            vec![]
+        } else {
+            known_nodes_with_stake
+                .iter()
+                .cloned()
+                .chain(vid_only_configs.iter().map(ValidatorConfig::public_config))
+                .collect()
+        };
+        // Observers are committee members with zero stake.
+        known_nodes_with_stake.extend(
+            (num_nodes_with_stake..num_nodes_with_stake + observer_nodes).map(|node_id_| {
+                ValidatorConfig::<TYPES::SignatureKey>::generated_from_seed_indexed(
+                    [0u8; 32],
+                    node_id_ as u64,
+                    0,
+                    false,
+                )
+                .public_config()
+            }),
+        );
         // But now to test validator's config, we input the info of my_own_validator from config file when node_id == 0.
         let my_own_validator_config = ValidatorConfig::generated_from_seed_indexed(
             [0u8; 32],
@@ -310,13 +374,15 @@ impl TestDescription {
             num_nodes_with_stake: NonZeroUsize::new(num_nodes_with_stake).unwrap(),
             // Currently making this zero for simplicity
             known_da_nodes,
+            known_vid_nodes,
             num_nodes_without_stake: 0,
             num_bootstrap: num_bootstrap_nodes,
             known_nodes_with_stake,
-            known_nodes_without_stake: vec![],
+            known_nodes_without_stake,
             my_own_validator_config,
             da_staked_committee_size,
             da_non_staked_committee_size,
+            vid_storage_committee_size: 0,
             fixed_leader_for_gpuvid: 1,
             next_view_timeout: 500,
             view_sync_timeout: Duration::from_millis(250),
@@ -331,6 +397,17 @@ impl TestDescription {
             stop_proposing_view: 0,
             start_voting_view: 0,
             stop_voting_view: 0,
+            max_inbound_message_size: DEFAULT_MAX_INBOUND_MESSAGE_SIZE,
+            catchup_requests_per_second: DEFAULT_CATCHUP_REQUESTS_PER_SECOND,
+            builder_prefetch_depth: DEFAULT_BUILDER_PREFETCH_DEPTH,
+            validation_level: ValidationLevel::Full,
+            proposal_backfill_views: DEFAULT_PROPOSAL_BACKFILL_VIEWS,
+            transaction_pow_difficulty: DEFAULT_TRANSACTION_POW_DIFFICULTY,
+            wait_for_dac_to_propose: false,
+            max_undecided_views: DEFAULT_MAX_UNDECIDED_VIEWS,
+            state_compaction_depth: None,
+            max_block_size: DEFAULT_MAX_BLOCK_SIZE,
+            max_pending_transactions: DEFAULT_MAX_PENDING_TRANSACTIONS,
         };
         let TimingData {
             next_view_timeout,
@@ -3,6 +3,7 @@ use std::{
     collections::{BTreeMap, HashMap, HashSet},
     marker::PhantomData,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use async_broadcast::broadcast;
@@ -42,7 +43,7 @@ use super::{
 use crate::{
     block_builder::TestBuilderImplementation,
     completion_task::CompletionTaskDescription,
-    spinning_task::{ChangeNode, SpinningTask, UpDown},
+    spinning_task::{forward_events, ChangeNode, SpinningTask, UpDown},
     test_launcher::{Networks, TestLauncher},
     test_task::{TestResult, TestTask},
     txn_task::TxnTaskDescription,
@@ -52,6 +53,31 @@ use crate::{
 pub trait TaskErr: std::error::Error + Sync + Send + 'static {}
 impl<T: std::error::Error + Sync + Send + 'static> TaskErr for T {}
 
+/// How far consensus progressed during a test run, and how long it took.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TestRunStats {
+    /// The highest view decided by any node
+    pub decided_views: u64,
+    /// Time from starting consensus until the test tasks completed
+    pub elapsed: Duration,
+}
+
+impl TestRunStats {
+    /// The number of views decided per second.
+    #[must_use]
+    pub fn throughput(&self) -> f64 {
+        f64::from(u32::try_from(self.decided_views).unwrap_or(u32::MAX))
+            / self.elapsed.as_secs_f64()
+    }
+
+    /// The average time it took to decide a view, if any view was decided.
+    #[must_use]
+    pub fn view_latency(&self) -> Option<Duration> {
+        let decided_views = u32::try_from(self.decided_views).ok()?;
+        self.elapsed.checked_div(decided_views)
+    }
+}
+
 impl<
         TYPES: NodeType<InstanceState = TestInstanceState, ValidatedState = TestValidatedState>,
         I: TestableNodeImplementation<TYPES>,
@@ -61,12 +87,12 @@ where
     I: TestableNodeImplementation<TYPES>,
     I: NodeImplementation<TYPES, QuorumNetwork = N, DaNetwork = N, Storage = TestStorage<TYPES>>,
 {
-    /// execute test
+    /// execute test, returning how far consensus progressed
     ///
     /// # Panics
     /// if the test fails
     #[allow(clippy::too_many_lines)]
-    pub async fn run_test<B: TestBuilderImplementation<TYPES>>(mut self) {
+    pub async fn run_test<B: TestBuilderImplementation<TYPES>>(mut self) -> TestRunStats {
         let (test_sender, test_receiver) = broadcast(EVENT_CHANNEL_SIZE);
         let spinning_changes = self
             .launcher
@@ -76,25 +102,44 @@ where
             .clone();
 
         let mut late_start_nodes: HashSet<u64> = HashSet::new();
+        let mut restarted_nodes: HashSet<usize> = HashSet::new();
         for (_, changes) in &spinning_changes {
             for change in changes {
-                if matches!(change.updown, UpDown::Up) {
-                    late_start_nodes.insert(change.idx.try_into().unwrap());
+                match change.updown {
+                    UpDown::Up => {
+                        late_start_nodes.insert(change.idx.try_into().unwrap());
+                    }
+                    UpDown::Restart => {
+                        restarted_nodes.insert(change.idx);
+                    }
+                    _ => {}
                 }
             }
         }
 
         self.add_nodes::<B>(
-            self.launcher.metadata.num_nodes_with_stake,
+            self.launcher.metadata.num_nodes_with_stake + self.launcher.metadata.observer_nodes,
             &late_start_nodes,
         )
         .await;
         let mut event_rxs = vec![];
         let mut internal_event_rxs = vec![];
+        // Events of nodes that will be restarted are forwarded through a channel of our own, so
+        // that the test tasks keep receiving them from each new instance of the node.
+        let mut restart_event_senders = HashMap::new();
 
-        for node in &self.nodes {
+        for (idx, node) in self.nodes.iter().enumerate() {
             let r = node.handle.event_stream_known_impl();
-            event_rxs.push(r);
+            if restarted_nodes.contains(&idx) {
+                let (mut sender, receiver) = broadcast(EVENT_CHANNEL_SIZE);
+                sender.set_overflow(true);
+                sender.set_await_active(false);
+                forward_events(r, sender.clone());
+                restart_event_senders.insert(idx, sender);
+                event_rxs.push(receiver);
+            } else {
+                event_rxs.push(r);
+            }
         }
         for node in &self.nodes {
             let r = node.handle.internal_event_stream_known_impl();
@@ -159,6 +204,8 @@ where
                 &TestInstanceState {},
             )
             .await,
+            restart_event_senders,
+            awaiting_catchup: HashMap::new(),
         };
         let spinning_task = TestTask::<SpinningTask<TYPES, I>>::new(
             spinning_task_state,
@@ -166,9 +213,18 @@ where
             test_receiver.clone(),
         );
         // add safety task
+        let expected_nodes = handles
+            .read()
+            .await
+            .iter()
+            .map(|node| node.node_id)
+            .collect();
         let overall_safety_task_state = OverallSafetyTask {
             handles: Arc::clone(&handles),
-            ctx: RoundCtx::default(),
+            ctx: RoundCtx {
+                expected_nodes,
+                ..RoundCtx::default()
+            },
             properties: self.launcher.metadata.overall_safety_properties,
             error: None,
             test_sender,
@@ -204,11 +260,16 @@ where
         // Start hotshot
         for node in &*nodes {
             if !late_start_nodes.contains(&node.node_id) {
-                node.handle.hotshot.start_consensus().await;
+                node.handle
+                    .hotshot
+                    .start_consensus()
+                    .await
+                    .expect("Failed to start consensus");
             }
         }
 
         drop(nodes);
+        let started = Instant::now();
 
         task_futs.push(safety_task.run());
         task_futs.push(view_sync_task.run());
@@ -262,10 +323,13 @@ where
             }
             completion_handle.abort();
         }
+        let elapsed = started.elapsed();
 
         let mut nodes = handles.write().await;
 
+        let mut decided_views = 0;
         for node in &mut *nodes {
+            decided_views = decided_views.max(node.handle.decided_leaf().await.view_number().u64());
             node.handle.shut_down().await;
         }
 
@@ -273,6 +337,11 @@ where
             error_list.is_empty(),
             "TEST FAILED! Results: {error_list:?}"
         );
+
+        TestRunStats {
+            decided_views,
+            elapsed,
+        }
     }
 
     /// Add nodes.
@@ -291,8 +360,9 @@ where
         let mut builder_tasks = Vec::new();
         let mut builder_urls = Vec::new();
         for metadata in &self.launcher.metadata.builders {
+            // Builders commit to their blocks for the VID storage nodes
             let (builder_task, builder_url) = B::start(
-                config.num_nodes_with_stake.into(),
+                config.vid_nodes().len(),
                 B::Config::default(),
                 metadata.changes.clone(),
             )
@@ -324,7 +394,7 @@ where
                 ),
                 vid_membership: <TYPES as NodeType>::Membership::create_election(
                     known_nodes_with_stake.clone(),
-                    known_nodes_with_stake.clone(),
+                    config.vid_nodes(),
                     config.fixed_leader_for_gpuvid,
                 ),
                 view_sync_membership: <TYPES as NodeType>::Membership::create_election(
@@ -366,9 +436,17 @@ where
                 // See whether or not we should be DA
                 let is_da = node_id < config.da_staked_committee_size as u64;
 
+                // Observers, which come after the staked nodes, have no stake
+                let stake_value =
+                    u64::from(node_id < self.launcher.metadata.num_nodes_with_stake as u64);
+
                 // We assign node's public key and stake value rather than read from config file since it's a test
-                let validator_config =
-                    ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, is_da);
+                let validator_config = ValidatorConfig::generated_from_seed_indexed(
+                    [0u8; 32],
+                    node_id,
+                    stake_value,
+                    is_da,
+                );
                 let hotshot = Self::add_node_with_config(
                     node_id,
                     networks.clone(),
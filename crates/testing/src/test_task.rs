@@ -9,6 +9,7 @@ use async_trait::async_trait;
 use futures::future::select_all;
 use hotshot_task_impls::{events::HotShotEvent, network::NetworkMessageTaskState};
 use hotshot_types::{
+    consensus::ConsensusMetricsValue,
     message::{Messages, VersionedMessage},
     traits::{network::ConnectedNetwork, node_implementation::NodeType},
 };
@@ -110,6 +111,9 @@ pub async fn add_network_message_test_task<
     let net = Arc::clone(&channel);
     let network_state: NetworkMessageTaskState<_> = NetworkMessageTaskState {
         event_stream: event_stream.clone(),
+        metrics: Arc::new(ConsensusMetricsValue::default()),
+        transaction_pow_difficulty: 0,
+        decided_upgrade_certificate: None,
     };
 
     let network = Arc::clone(&net);
@@ -34,6 +34,7 @@ use hotshot_types::{
         BlockPayload,
     },
 };
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use sha2::{Digest, Sha256};
 
 use crate::helpers::{
@@ -44,6 +45,9 @@ use crate::helpers::{
 pub struct TestView {
     pub da_proposal: Proposal<TestTypes, DaProposal<TestTypes>>,
     pub quorum_proposal: Proposal<TestTypes, QuorumProposal<TestTypes>>,
+    /// A second quorum proposal for this view, signed by the same leader, when the generator was
+    /// told to equivocate in this view.
+    pub quorum_proposal_conflicting: Option<Proposal<TestTypes, QuorumProposal<TestTypes>>>,
     pub leaf: Leaf<TestTypes>,
     pub view_number: ViewNumber,
     pub quorum_membership: <TestTypes as NodeType>::Membership,
@@ -112,6 +116,8 @@ impl TestView {
             timestamp: 1,
             payload_commitment,
             builder_commitment,
+            app_version: None,
+            built_for_version: None,
         };
 
         let quorum_proposal_inner = QuorumProposal::<TestTypes> {
@@ -160,6 +166,7 @@ impl TestView {
 
         TestView {
             quorum_proposal,
+            quorum_proposal_conflicting: None,
             leaf,
             view_number: genesis_view,
             quorum_membership: quorum_membership.clone(),
@@ -317,6 +324,8 @@ impl TestView {
             timestamp: *next_view,
             payload_commitment,
             builder_commitment,
+            app_version: None,
+            built_for_version: None,
         };
 
         let proposal = QuorumProposal::<TestTypes> {
@@ -361,6 +370,7 @@ impl TestView {
 
         TestView {
             quorum_proposal,
+            quorum_proposal_conflicting: None,
             leaf,
             view_number: next_view,
             quorum_membership: quorum_membership.clone(),
@@ -386,6 +396,24 @@ impl TestView {
         self.next_view_from_ancestor(self.clone()).await
     }
 
+    /// Builds a quorum proposal for the same view and parent as this view's, proposing a different
+    /// leaf, and signed by the same leader.
+    pub fn conflicting_quorum_proposal(&self) -> Proposal<TestTypes, QuorumProposal<TestTypes>> {
+        let mut proposal = self.quorum_proposal.data.clone();
+        proposal.block_header.timestamp += 1;
+
+        let (private_key, _) = key_pair_for_id(*proposal.view_number);
+        let leaf = Leaf::from_quorum_proposal(&proposal);
+        let signature = <BLSPubKey as SignatureKey>::sign(&private_key, leaf.commit().as_ref())
+            .expect("Failed to sign leaf commitment.");
+
+        Proposal {
+            data: proposal,
+            signature,
+            _pd: PhantomData,
+        }
+    }
+
     pub fn create_quorum_vote(
         &self,
         handle: &SystemContextHandle<TestTypes, MemoryImpl>,
@@ -396,7 +424,7 @@ impl TestView {
             },
             self.view_number,
             &handle.public_key(),
-            handle.private_key(),
+            &key_pair_for_id(handle.hotshot.id).0,
         )
         .expect("Failed to generate a signature on QuorumVote")
     }
@@ -410,7 +438,7 @@ impl TestView {
             data,
             self.view_number,
             &handle.public_key(),
-            handle.private_key(),
+            &key_pair_for_id(handle.hotshot.id).0,
         )
         .expect("Failed to generate a signature on UpgradVote")
     }
@@ -424,7 +452,7 @@ impl TestView {
             data,
             self.view_number,
             &handle.public_key(),
-            handle.private_key(),
+            &key_pair_for_id(handle.hotshot.id).0,
         )
         .expect("Failed to sign DaData")
     }
@@ -434,17 +462,58 @@ pub struct TestViewGenerator {
     pub current_view: Option<TestView>,
     pub quorum_membership: <TestTypes as NodeType>::Membership,
     pub da_membership: <TestTypes as NodeType>::Membership,
+    /// Source of all randomness in the generated views.
+    rng: StdRng,
+    /// The views in which the leader also signs a conflicting quorum proposal.
+    equivocate_at: Vec<ViewNumber>,
 }
 
 impl TestViewGenerator {
     pub fn generate(
         quorum_membership: <TestTypes as NodeType>::Membership,
         da_membership: <TestTypes as NodeType>::Membership,
+    ) -> Self {
+        Self::with_rng(quorum_membership, da_membership, StdRng::from_entropy())
+    }
+
+    /// Creates a generator whose randomness is driven by `seed`, so that two generators with the
+    /// same seed (and the same sequence of calls) produce identical views.
+    pub fn generate_seeded(
+        quorum_membership: <TestTypes as NodeType>::Membership,
+        da_membership: <TestTypes as NodeType>::Membership,
+        seed: u64,
+    ) -> Self {
+        Self::with_rng(
+            quorum_membership,
+            da_membership,
+            StdRng::seed_from_u64(seed),
+        )
+    }
+
+    /// Creates a generator whose views in `equivocate_at` also carry a conflicting quorum
+    /// proposal, in `quorum_proposal_conflicting`.
+    pub fn generate_with_equivocation(
+        quorum_membership: <TestTypes as NodeType>::Membership,
+        da_membership: <TestTypes as NodeType>::Membership,
+        equivocate_at: Vec<ViewNumber>,
+    ) -> Self {
+        TestViewGenerator {
+            equivocate_at,
+            ..Self::generate(quorum_membership, da_membership)
+        }
+    }
+
+    fn with_rng(
+        quorum_membership: <TestTypes as NodeType>::Membership,
+        da_membership: <TestTypes as NodeType>::Membership,
+        rng: StdRng,
     ) -> Self {
         TestViewGenerator {
             current_view: None,
             quorum_membership,
             da_membership,
+            rng,
+            equivocate_at: Vec::new(),
         }
     }
 
@@ -470,6 +539,17 @@ impl TestViewGenerator {
         }
     }
 
+    /// Attaches `count` transactions with contents drawn from the generator's randomness.
+    pub fn add_random_transactions(&mut self, count: usize) {
+        let transactions = (0..count)
+            .map(|_| {
+                let len = self.rng.gen_range(1..=32);
+                TestTransaction::new((0..len).map(|_| self.rng.gen()).collect())
+            })
+            .collect();
+        self.add_transactions(transactions);
+    }
+
     pub fn add_view_sync_finalize(
         &mut self,
         view_sync_finalize_data: ViewSyncFinalizeData<TestTypes>,
@@ -532,7 +612,11 @@ impl Stream for TestViewGenerator {
         };
 
         match fut.as_mut().poll(cx) {
-            Poll::Ready(test_view) => {
+            Poll::Ready(mut test_view) => {
+                if self.equivocate_at.contains(&test_view.view_number) {
+                    test_view.quorum_proposal_conflicting =
+                        Some(test_view.conflicting_quorum_proposal());
+                }
                 self.current_view = Some(test_view.clone());
                 Poll::Ready(Some(test_view))
             }
@@ -1,10 +1,19 @@
 use std::{
     collections::HashMap,
-    time::{Duration, Instant},
+    marker::PhantomData,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
 
-use async_compatibility_layer::art::async_sleep;
-use hotshot_builder_api::block_info::AvailableBlockData;
+use async_compatibility_layer::art::{async_sleep, async_spawn, async_timeout};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use hotshot_builder_api::{
+    block_info::{AvailableBlockData, AvailableBlockHeaderInput, AvailableBlockInfo},
+    builder::{BuildError, Error, Options},
+    data_source::BuilderDataSource,
+};
 use hotshot_example_types::{
     block_types::{TestBlockPayload, TestMetadata, TestTransaction},
     node_types::TestTypes,
@@ -12,16 +21,25 @@ use hotshot_example_types::{
 use hotshot_orchestrator::config::RandomBuilderConfig;
 use hotshot_task_impls::builder::{BuilderClient, BuilderClientError};
 use hotshot_testing::block_builder::{
-    BuilderTask, RandomBuilderImplementation, TestBuilderImplementation,
+    BuilderTask, RandomBuilderImplementation, SimpleBuilderImplementation,
+    TestBuilderImplementation,
 };
 use hotshot_types::{
     constants::Base,
+    data::ViewNumber,
+    event::{Event, EventType},
+    message::TxPriority,
     traits::{
-        block_contents::vid_commitment, node_implementation::NodeType, signature_key::SignatureKey,
+        block_contents::{vid_commitment, EncodeBytes},
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::{BuilderSignatureKey, SignatureKey},
         BlockPayload,
     },
+    utils::BuilderCommitment,
+    vid::VidCommitment,
 };
-use tide_disco::Url;
+use tide_disco::{method::ReadState, App, Url};
+use vbs::version::StaticVersionType;
 
 #[cfg(test)]
 #[cfg_attr(
@@ -61,6 +79,7 @@ async fn test_random_block_builder() {
                 dummy_view_number,
                 pub_key,
                 &signature,
+                SystemTime::now() + Duration::from_secs(1),
             )
             .await
             .expect("Failed to get available blocks");
@@ -106,3 +125,302 @@ async fn test_random_block_builder() {
         .await;
     assert!(matches!(result, Err(BuilderClientError::NotFound)));
 }
+
+/// The simple builder puts higher priority transactions first in the blocks it builds, whatever
+/// order they were submitted in.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_simple_builder_orders_by_priority() {
+    let (change_sender, _change_receiver) = async_broadcast::broadcast(1);
+    let (source, task) =
+        SimpleBuilderImplementation::create::<TestTypes>(1, HashMap::new(), change_sender).await;
+
+    let low = TestTransaction::new(vec![0]);
+    let normal = TestTransaction::new(vec![1]);
+    let high = TestTransaction::new(vec![2]);
+    let submitted = Event {
+        view_number: ViewNumber::new(1),
+        event: EventType::Transactions {
+            transactions: vec![
+                (low.clone(), TxPriority::Low),
+                (high.clone(), TxPriority::High),
+                (normal.clone(), TxPriority::Normal),
+            ],
+        },
+    };
+    Box::new(task).start(Box::new(futures::stream::iter([submitted])));
+
+    let (pub_key, private_key) =
+        <TestTypes as NodeType>::SignatureKey::generated_from_seed_indexed([0_u8; 32], 0);
+    let signature = <TestTypes as NodeType>::SignatureKey::sign(&private_key, &[0_u8; 32])
+        .expect("Failed to create dummy signature");
+
+    // The builder takes in all the transactions at once, so its first block has all of them.
+    let block_hash = async_timeout(Duration::from_secs(2), async {
+        loop {
+            let blocks = source
+                .available_blocks(&vid_commitment(&[], 1), 0, pub_key, &signature)
+                .await
+                .expect("Failed to get available blocks");
+            if let Some(block) = blocks.into_iter().next() {
+                return block.block_hash;
+            }
+            async_sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("Builder failed to provide a block in two seconds");
+    let block = source
+        .claim_block(&block_hash, 0, pub_key, &signature)
+        .await
+        .expect("Failed to claim block");
+
+    assert_eq!(block.block_payload.transactions, vec![high, normal, low]);
+}
+
+/// How long [`DeadlineBuilderSource`] takes to build a block out of all of its transactions
+const FULL_BLOCK_BUILD_TIME: Duration = Duration::from_millis(1000);
+
+/// A builder which takes [`FULL_BLOCK_BUILD_TIME`] to build a block out of all of its
+/// transactions, but right away offers a block with just the first of them if it's needed sooner.
+#[derive(Clone)]
+struct DeadlineBuilderSource {
+    transactions: Vec<TestTransaction>,
+    pub_key: <TestTypes as NodeType>::BuilderSignatureKey,
+    priv_key:
+        <<TestTypes as NodeType>::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
+    /// The deadlines of the requests made so far
+    deadlines: Arc<RwLock<Vec<SystemTime>>>,
+}
+
+impl DeadlineBuilderSource {
+    fn block_info(&self, transactions: &[TestTransaction]) -> AvailableBlockInfo<TestTypes> {
+        let payload = TestBlockPayload {
+            transactions: transactions.to_vec(),
+        };
+        let block_hash = payload.builder_commitment(&TestMetadata);
+        let block_size = u64::try_from(payload.encode().len()).unwrap();
+        let offered_fee = 1;
+        AvailableBlockInfo {
+            signature: <TestTypes as NodeType>::BuilderSignatureKey::sign_block_info(
+                &self.priv_key,
+                block_size,
+                offered_fee,
+                &block_hash,
+            )
+            .unwrap(),
+            block_hash,
+            block_size,
+            offered_fee,
+            sender: self.pub_key,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl ReadState for DeadlineBuilderSource {
+    type State = Self;
+
+    async fn read<T>(
+        &self,
+        op: impl Send + for<'a> FnOnce(&'a Self::State) -> BoxFuture<'a, T> + 'async_trait,
+    ) -> T {
+        op(self).await
+    }
+}
+
+#[async_trait]
+impl BuilderDataSource<TestTypes> for DeadlineBuilderSource {
+    async fn available_blocks(
+        &self,
+        _for_parent: &VidCommitment,
+        _view_number: u64,
+        _sender: <TestTypes as NodeType>::SignatureKey,
+        _signature: &<<TestTypes as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<Vec<AvailableBlockInfo<TestTypes>>, BuildError> {
+        async_sleep(FULL_BLOCK_BUILD_TIME).await;
+        Ok(vec![self.block_info(&self.transactions)])
+    }
+
+    async fn available_blocks_with_deadline(
+        &self,
+        for_parent: &VidCommitment,
+        view_number: u64,
+        sender: <TestTypes as NodeType>::SignatureKey,
+        signature: &<<TestTypes as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+        deadline: SystemTime,
+    ) -> Result<Vec<AvailableBlockInfo<TestTypes>>, BuildError> {
+        self.deadlines.write().await.push(deadline);
+        if deadline
+            .duration_since(SystemTime::now())
+            .map_or(true, |remaining| remaining < FULL_BLOCK_BUILD_TIME)
+        {
+            return Ok(vec![self.block_info(&self.transactions[..1])]);
+        }
+        self.available_blocks(for_parent, view_number, sender, signature)
+            .await
+    }
+
+    async fn claim_block(
+        &self,
+        _block_hash: &BuilderCommitment,
+        _view_number: u64,
+        _sender: <TestTypes as NodeType>::SignatureKey,
+        _signature: &<<TestTypes as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<AvailableBlockData<TestTypes>, BuildError> {
+        Err(BuildError::NotFound)
+    }
+
+    async fn claim_block_header_input(
+        &self,
+        _block_hash: &BuilderCommitment,
+        _view_number: u64,
+        _sender: <TestTypes as NodeType>::SignatureKey,
+        _signature: &<<TestTypes as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ) -> Result<AvailableBlockHeaderInput<TestTypes>, BuildError> {
+        Err(BuildError::NotFound)
+    }
+
+    async fn builder_address(
+        &self,
+    ) -> Result<<TestTypes as NodeType>::BuilderSignatureKey, BuildError> {
+        Ok(self.pub_key)
+    }
+}
+
+/// Serve the builder API over `source`, as specified by `options`, and connect a client to it.
+async fn serve_deadline_builder(
+    source: DeadlineBuilderSource,
+    options: &Options,
+) -> BuilderClient<TestTypes, Base> {
+    let api_url = Url::parse(&format!(
+        "http://localhost:{}",
+        portpicker::pick_unused_port().expect("No free ports")
+    ))
+    .unwrap();
+    let builder_api =
+        hotshot_builder_api::builder::define_api::<DeadlineBuilderSource, TestTypes, Base>(options)
+            .expect("Failed to construct the builder API");
+    let mut app: App<DeadlineBuilderSource, Error> = App::with_state(source);
+    app.register_module("block_info", builder_api)
+        .expect("Failed to register the builder API");
+    async_spawn(app.serve(api_url.clone(), Base::instance()));
+
+    let client: BuilderClient<TestTypes, Base> = BuilderClient::new(api_url);
+    assert!(client.connect(Duration::from_secs(1)).await);
+    client
+}
+
+/// The deadline for a block request reaches the builder, which offers a smaller block in time for
+/// a near deadline rather than none at all.
+#[cfg(test)]
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_block_request_deadline() {
+    let (pub_key, priv_key) =
+        <TestTypes as NodeType>::BuilderSignatureKey::generated_from_seed_indexed([1; 32], 0);
+    let source = DeadlineBuilderSource {
+        transactions: (0..10).map(|i| TestTransaction::new(vec![i; 16])).collect(),
+        pub_key,
+        priv_key,
+        deadlines: Arc::default(),
+    };
+    let client = serve_deadline_builder(source.clone(), &Options::default()).await;
+
+    let (pub_key, private_key) =
+        <TestTypes as NodeType>::SignatureKey::generated_from_seed_indexed([0_u8; 32], 0);
+    let signature = <TestTypes as NodeType>::SignatureKey::sign(&private_key, &[0_u8; 32])
+        .expect("Failed to create dummy signature");
+    let request = |timeout: Duration| {
+        let deadline = SystemTime::now() + timeout;
+        let (client, pub_key, signature) = (&client, pub_key, &signature);
+        async move {
+            let blocks = async_timeout(
+                timeout,
+                client.available_blocks(vid_commitment(&[], 1), 0, pub_key, signature, deadline),
+            )
+            .await
+            .expect("Builder didn't respond by the deadline")
+            .expect("Failed to get available blocks");
+            (deadline, blocks)
+        }
+    };
+
+    // With plenty of time, we get a block of all the transactions.
+    let (far_deadline, blocks) = request(FULL_BLOCK_BUILD_TIME * 2).await;
+    let full_block = source.block_info(&source.transactions);
+    assert_eq!(blocks, vec![full_block.clone()]);
+
+    // With less time than that takes, we still get a block in time, just a smaller one.
+    let (near_deadline, blocks) = request(FULL_BLOCK_BUILD_TIME / 2).await;
+    assert_eq!(blocks.len(), 1);
+    assert!(blocks[0].block_size < full_block.block_size);
+
+    // The builder got both deadlines, down to the millisecond.
+    let deadlines = source.deadlines.read().await;
+    assert_eq!(deadlines.len(), 2);
+    for (received, sent) in deadlines.iter().zip([far_deadline, near_deadline]) {
+        let error = received
+            .duration_since(sent)
+            .unwrap_or_else(|err| err.duration());
+        assert!(error < Duration::from_millis(1));
+    }
+}
+
+/// A builder whose API predates deadlines is still asked for blocks, just without the deadline.
+#[cfg(test)]
+#[cfg_attr(
+    async_executor_impl = "tokio",
+    tokio::test(flavor = "multi_thread", worker_threads = 2)
+)]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_block_request_without_deadline_route() {
+    let (pub_key, priv_key) =
+        <TestTypes as NodeType>::BuilderSignatureKey::generated_from_seed_indexed([1; 32], 0);
+    let source = DeadlineBuilderSource {
+        transactions: (0..10).map(|i| TestTransaction::new(vec![i; 16])).collect(),
+        pub_key,
+        priv_key,
+        deadlines: Arc::default(),
+    };
+
+    // The builder API as it was before the route taking a deadline was added.
+    let api = include_str!("../../../builder-api/api/builder.toml").replace(
+        "    \"availableblocks/:parent_hash/:view_number/:sender/:signature/:deadline\",\n",
+        "",
+    );
+    let api_path = std::env::temp_dir().join(format!(
+        "builder-without-deadline-{}.toml",
+        portpicker::pick_unused_port().expect("No free ports")
+    ));
+    std::fs::write(&api_path, api).expect("Failed to write the builder API");
+    let options = Options {
+        api_path: Some(api_path.clone()),
+        ..Default::default()
+    };
+    let client = serve_deadline_builder(source.clone(), &options).await;
+    std::fs::remove_file(api_path).expect("Failed to remove the builder API");
+
+    let (pub_key, private_key) =
+        <TestTypes as NodeType>::SignatureKey::generated_from_seed_indexed([0_u8; 32], 0);
+    let signature = <TestTypes as NodeType>::SignatureKey::sign(&private_key, &[0_u8; 32])
+        .expect("Failed to create dummy signature");
+    let blocks = client
+        .available_blocks(
+            vid_commitment(&[], 1),
+            0,
+            pub_key,
+            &signature,
+            SystemTime::now() + FULL_BLOCK_BUILD_TIME * 2,
+        )
+        .await
+        .expect("Failed to get available blocks");
+
+    assert_eq!(blocks, vec![source.block_info(&source.transactions)]);
+    assert!(source.deadlines.read().await.is_empty());
+}
@@ -0,0 +1,150 @@
+use bitvec::vec::BitVec;
+use ethereum_types::U256;
+use futures::StreamExt;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_testing::{
+    helpers::{build_system_handle, key_pair_for_id},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    signature_key::BLSPubKey,
+    simple_certificate::QuorumCertificate,
+    simple_vote::{QuorumData, QuorumVote},
+    traits::{election::Membership, signature_key::SignatureKey},
+    vote::{Certificate, Vote},
+};
+
+/// A compactly serialized certificate round trips losslessly, is still valid, and is smaller than
+/// the regular serialization, whether every node or only some of them signed it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_certificate_compact_bytes_round_trip() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    generator.next().await;
+    let full_qc = generator
+        .next()
+        .await
+        .unwrap()
+        .quorum_proposal
+        .data
+        .justify_qc;
+    assert!(full_qc.signatures.is_some());
+
+    // Reassemble the same certificate, signed only by a non-contiguous quorum of the nodes.
+    let signer_ids = [0, 2, 3, 5, 6, 8, 9];
+    let stake_table = quorum_membership.committee_qc_stake_table();
+    let mut signers = BitVec::repeat(false, stake_table.len());
+    let mut signatures = Vec::new();
+    for id in signer_ids {
+        let (private_key, public_key) = key_pair_for_id(id);
+        let vote = QuorumVote::<TestTypes>::create_signed_vote(
+            full_qc.data.clone(),
+            full_qc.view_number,
+            &public_key,
+            &private_key,
+        )
+        .unwrap();
+        signers.set(usize::try_from(id).unwrap(), true);
+        signatures.push(vote.signature());
+    }
+    let qc_params = BLSPubKey::public_parameter(
+        stake_table,
+        U256::from(QuorumCertificate::<TestTypes>::threshold(
+            &quorum_membership,
+        )),
+    );
+    let partial_qc = QuorumCertificate::<TestTypes>::create_signed_certificate(
+        full_qc.vote_commitment,
+        QuorumData {
+            leaf_commit: full_qc.data.leaf_commit,
+        },
+        BLSPubKey::assemble(&qc_params, &signers, &signatures),
+        full_qc.view_number,
+    );
+    assert!(partial_qc.is_valid_cert(&quorum_membership));
+
+    for qc in [full_qc, partial_qc] {
+        let bytes = qc.compact_bytes().unwrap();
+        assert!(bytes.len() < bincode::serialize(&qc).unwrap().len());
+
+        let decoded = QuorumCertificate::<TestTypes>::from_compact_bytes(&bytes).unwrap();
+        assert_eq!(decoded, qc);
+        assert!(decoded.is_valid_cert(&quorum_membership));
+
+        // Truncated or padded bytes are rejected.
+        assert!(
+            QuorumCertificate::<TestTypes>::from_compact_bytes(&bytes[..bytes.len() - 1]).is_err()
+        );
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert!(QuorumCertificate::<TestTypes>::from_compact_bytes(&padded).is_err());
+    }
+}
+
+/// Signatures are aggregated only once their signers reach the threshold, and the aggregate
+/// verifies only if every one of the signatures is valid for the message.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_signature_aggregation_threshold() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let membership = handle.hotshot.memberships.quorum_membership.clone();
+    let threshold = U256::from(QuorumCertificate::<TestTypes>::threshold(&membership));
+    let message = b"aggregate me";
+    let sign = |id: u64, message: &[u8]| {
+        let (private_key, public_key) = key_pair_for_id(id);
+        (public_key, BLSPubKey::sign(&private_key, message).unwrap())
+    };
+
+    // A threshold of valid signatures aggregates and verifies, regardless of their order.
+    let signatures: Vec<_> = [9, 1, 4, 0, 7, 2, 5]
+        .into_iter()
+        .map(|id| sign(id, message))
+        .collect();
+    let aggregate =
+        BLSPubKey::aggregate::<TestTypes, _>(&signatures, &membership, threshold).unwrap();
+    assert!(BLSPubKey::verify_aggregate::<TestTypes, _>(
+        &aggregate,
+        message,
+        &membership,
+        threshold
+    ));
+    assert!(!BLSPubKey::verify_aggregate::<TestTypes, _>(
+        &aggregate,
+        b"something else",
+        &membership,
+        threshold
+    ));
+
+    // One fewer signature doesn't reach the threshold.
+    assert!(
+        BLSPubKey::aggregate::<TestTypes, _>(&signatures[1..], &membership, threshold).is_err()
+    );
+
+    // Neither does a duplicated signature.
+    let mut duplicated = signatures[1..].to_vec();
+    duplicated.push(signatures[1].clone());
+    assert!(BLSPubKey::aggregate::<TestTypes, _>(&duplicated, &membership, threshold).is_err());
+
+    // An aggregate including a signature over a different message doesn't verify.
+    let mut invalid = signatures.clone();
+    invalid[0] = sign(9, b"something else");
+    let aggregate = BLSPubKey::aggregate::<TestTypes, _>(&invalid, &membership, threshold).unwrap();
+    assert!(!BLSPubKey::verify_aggregate::<TestTypes, _>(
+        &aggregate,
+        message,
+        &membership,
+        threshold
+    ));
+}
@@ -0,0 +1,753 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use async_compatibility_layer::art::async_sleep;
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes},
+    state_types::{TestStateDelta, TestValidatedState},
+};
+use hotshot_task_impls::{
+    consensus::{check_safety_and_liveness, update_high_qc},
+    events::HotShotEvent,
+};
+use hotshot_testing::{
+    helpers::{
+        build_system_handle, build_system_handle_from_launcher, da_payload_commitment, vid_share,
+    },
+    test_builder::TestDescription,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    consensus::{Consensus, GarbageCollected},
+    data::{Leaf, ViewNumber},
+    traits::{election::Membership, node_implementation::ConsensusTime, ValidatedState},
+    utils::{Terminator, View, ViewInner},
+    vid::vid_recovery_threshold,
+    ValidationLevel,
+};
+
+/// The time since the last decide grows across view changes and only resets on a decide.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_time_since_last_decide() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let consensus = handle.consensus();
+
+    async_sleep(Duration::from_millis(200)).await;
+
+    // A view change does not count as a decide.
+    consensus
+        .write()
+        .await
+        .update_view(ViewNumber::new(1))
+        .unwrap();
+    assert!(consensus.read().await.time_since_last_decide() >= Duration::from_millis(200));
+
+    // A decide resets the timer.
+    consensus
+        .write()
+        .await
+        .update_last_decided_view(ViewNumber::new(1))
+        .unwrap();
+    assert!(consensus.read().await.time_since_last_decide() < Duration::from_millis(200));
+
+    // A rejected decide leaves the timer untouched.
+    async_sleep(Duration::from_millis(200)).await;
+    assert!(consensus
+        .write()
+        .await
+        .update_last_decided_view(ViewNumber::new(1))
+        .is_err());
+    assert!(consensus.read().await.time_since_last_decide() >= Duration::from_millis(200));
+}
+
+/// A VID share is paired with the DA proposal for its view whichever arrives first, and only if
+/// they commit to the same payload.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_vid_share_pairs_with_da_proposal() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+    let public_key = handle.public_key();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let mut shares = Vec::new();
+    let mut payload_commitments = Vec::new();
+    for transactions in [vec![], vec![TestTransaction::new(vec![0])]] {
+        generator.add_transactions(transactions);
+        let view = generator.next().await.unwrap();
+        shares.push(vid_share(&view.vid_proposal.0, handle.public_key()));
+        payload_commitments.push(da_payload_commitment(
+            &quorum_membership,
+            view.transactions.clone(),
+        ));
+    }
+
+    let consensus = handle.consensus();
+    let mut consensus = consensus.write().await;
+
+    // The DA proposal arrives before the share.
+    let view = ViewNumber::new(1);
+    consensus.update_da_payload_commitments(view, payload_commitments[0]);
+    assert!(consensus.paired_vid_share(view, &public_key).is_none());
+    consensus.update_vid_shares(view, shares[0].clone());
+    assert_eq!(
+        consensus.paired_vid_share(view, &public_key),
+        Some(&shares[0])
+    );
+
+    // The share arrives before the DA proposal.
+    let view = ViewNumber::new(2);
+    consensus.update_vid_shares(view, shares[1].clone());
+    assert!(consensus.paired_vid_share(view, &public_key).is_none());
+    consensus.update_da_payload_commitments(view, payload_commitments[1]);
+    assert_eq!(
+        consensus.paired_vid_share(view, &public_key),
+        Some(&shares[1])
+    );
+
+    // A share for a different payload isn't paired with the proposal.
+    let view = ViewNumber::new(3);
+    consensus.update_vid_shares(view, shares[0].clone());
+    consensus.update_da_payload_commitments(view, payload_commitments[1]);
+    assert!(consensus.paired_vid_share(view, &public_key).is_none());
+}
+
+/// A range query returns exactly the resolved states in the range.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_states_in_range() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let consensus = handle.consensus();
+    let mut consensus = consensus.write().await;
+    let decided_leaf = consensus.decided_leaf();
+
+    let states: BTreeMap<u64, _> = [1, 2, 4]
+        .map(|view| (view, Arc::new(TestValidatedState::default())))
+        .into();
+    for view in 1..=5 {
+        let view_inner = match view {
+            3 => ViewInner::Da {
+                payload_commitment: decided_leaf.payload_commitment(),
+            },
+            5 => ViewInner::Failed,
+            _ => ViewInner::Leaf {
+                leaf: decided_leaf.commit(),
+                state: Arc::clone(&states[&view]),
+                delta: None,
+            },
+        };
+        consensus
+            .update_validated_state_map(ViewNumber::new(view), View { view_inner })
+            .unwrap();
+    }
+
+    let in_range = consensus.states_in_range(ViewNumber::new(2), ViewNumber::new(5));
+    assert_eq!(
+        in_range.iter().map(|(view, _)| **view).collect::<Vec<_>>(),
+        vec![2, 4]
+    );
+    for (view, state) in &in_range {
+        assert!(Arc::ptr_eq(state, &states[&**view]));
+    }
+
+    assert_eq!(
+        consensus
+            .states_in_range(ViewNumber::new(1), ViewNumber::new(1))
+            .len(),
+        1
+    );
+    assert!(consensus
+        .states_in_range(ViewNumber::new(4), ViewNumber::new(2))
+        .is_empty());
+}
+
+/// A payload is recovered once a threshold of VID shares is stored, and only if the shares commit
+/// to the payload we expect.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_try_reconstruct_payload() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+    let threshold =
+        vid_recovery_threshold(u32::try_from(quorum_membership.total_nodes()).unwrap()) as usize;
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let mut views = Vec::new();
+    for transactions in [
+        vec![TestTransaction::new(vec![0; 100])],
+        vec![TestTransaction::new(vec![1])],
+    ] {
+        generator.add_transactions(transactions);
+        views.push(generator.next().await.unwrap());
+    }
+
+    let consensus = handle.consensus();
+    let mut consensus = consensus.write().await;
+
+    // Shares trickle in until there are enough to recover the payload.
+    let view = ViewNumber::new(1);
+    assert!(consensus.try_reconstruct_payload(view).is_none());
+    for share in &views[0].vid_proposal.0[..threshold - 1] {
+        consensus.update_vid_shares(view, share.clone());
+        assert!(consensus.try_reconstruct_payload(view).is_none());
+    }
+    consensus.update_vid_shares(view, views[0].vid_proposal.0[threshold - 1].clone());
+    let payload = consensus.try_reconstruct_payload(view).unwrap().unwrap();
+    assert_eq!(
+        payload.to_vec(),
+        TestTransaction::encode(&views[0].transactions)
+    );
+
+    // Shares for a different payload than the DA proposal's are rejected.
+    let view = ViewNumber::new(2);
+    consensus.update_da_payload_commitments(
+        view,
+        da_payload_commitment(&quorum_membership, views[1].transactions.clone()),
+    );
+    for share in &views[0].vid_proposal.0[..threshold] {
+        consensus.update_vid_shares(view, share.clone());
+    }
+    assert!(consensus.try_reconstruct_payload(view).unwrap().is_err());
+}
+
+/// A leaf is returned for a view only if it was decided there and hasn't been garbage collected.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_decided_leaf_at() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let mut leaves = Vec::new();
+    let mut da_certs = Vec::new();
+    for view in (&mut generator).take(4).collect::<Vec<_>>().await {
+        leaves.push(view.leaf.clone());
+        da_certs.push(view.da_certificate.clone());
+    }
+
+    let consensus = handle.consensus();
+    let mut consensus = consensus.write().await;
+    for leaf in &leaves {
+        consensus
+            .update_validated_state_map(
+                leaf.view_number(),
+                View {
+                    view_inner: ViewInner::Leaf {
+                        leaf: leaf.commit(),
+                        state: Arc::new(TestValidatedState::default()),
+                        delta: None,
+                    },
+                },
+            )
+            .unwrap();
+        consensus.update_saved_leaves(leaf.clone());
+    }
+    for cert in &da_certs {
+        consensus.update_saved_da_certs(cert.view_number, cert.clone());
+    }
+    consensus
+        .update_last_decided_view(ViewNumber::new(3))
+        .unwrap();
+    consensus.update_locked_view(ViewNumber::new(3)).unwrap();
+
+    let decided_at = |consensus: &Consensus<TestTypes>, view| {
+        consensus
+            .decided_leaf_at(ViewNumber::new(view))
+            .map(|leaf| leaf.commit())
+    };
+    for (view, leaf) in (1..).zip(&leaves[..3]) {
+        assert_eq!(decided_at(&consensus, view), Some(leaf.commit()));
+    }
+    // We know of the leaf for view 4, but it hasn't been decided.
+    assert!(consensus.saved_leaves().contains_key(&leaves[3].commit()));
+    assert_eq!(decided_at(&consensus, 4), None);
+
+    // Views before the new anchor are pruned, except for the DA certificates from the old anchor.
+    consensus.collect_garbage(ViewNumber::new(0), ViewNumber::new(2));
+    assert_eq!(decided_at(&consensus, 1), None);
+    assert_eq!(decided_at(&consensus, 2), Some(leaves[1].commit()));
+    assert_eq!(decided_at(&consensus, 3), Some(leaves[2].commit()));
+    assert!(consensus.saved_da_certs().contains_key(&ViewNumber::new(1)));
+
+    // The next decide prunes them too.
+    consensus.collect_garbage(ViewNumber::new(2), ViewNumber::new(3));
+    assert!(!consensus.saved_da_certs().contains_key(&ViewNumber::new(1)));
+    assert!(consensus.saved_da_certs().contains_key(&ViewNumber::new(2)));
+}
+
+/// Explicit garbage collection keeps the configured number of views before the last decided view,
+/// and never removes views from the locked view onwards.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_garbage_collect_with_retention_depth() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let mut leaves = Vec::new();
+    let mut proposals = Vec::new();
+    for view in (&mut generator).take(6).collect::<Vec<_>>().await {
+        leaves.push(view.leaf.clone());
+        proposals.push(view.quorum_proposal.clone());
+    }
+
+    let consensus = handle.consensus();
+    let mut consensus = consensus.write().await;
+    for leaf in &leaves {
+        consensus
+            .update_validated_state_map(
+                leaf.view_number(),
+                View {
+                    view_inner: ViewInner::Leaf {
+                        leaf: leaf.commit(),
+                        state: Arc::new(TestValidatedState::default()),
+                        delta: None,
+                    },
+                },
+            )
+            .unwrap();
+        consensus.update_saved_leaves(leaf.clone());
+        consensus
+            .update_saved_payloads(leaf.view_number(), Arc::from(vec![0u8; 8]))
+            .unwrap();
+    }
+    consensus
+        .update_last_decided_view(ViewNumber::new(4))
+        .unwrap();
+    consensus.set_retention_depth(1);
+    let genesis_payload = consensus
+        .saved_payloads()
+        .get(&ViewNumber::genesis())
+        .map(|payload| payload.len());
+
+    // Nothing from the locked view onwards is removed, and we haven't locked anything yet.
+    assert_eq!(consensus.garbage_collect_now().entries, 0);
+
+    consensus.update_locked_view(ViewNumber::new(5)).unwrap();
+    let freed = consensus.garbage_collect_now();
+    // The states, leaves and payloads of the genesis view and views 1 and 2.
+    assert_eq!(freed.entries, 8 + usize::from(genesis_payload.is_some()));
+    assert_eq!(freed.payload_bytes, 16 + genesis_payload.unwrap_or(0));
+
+    // Decided views before the retention depth are pruned, while the last decided view, the one
+    // before it and the undecided views remain.
+    for view in 0..=2 {
+        assert!(!consensus
+            .validated_state_map()
+            .contains_key(&ViewNumber::new(view)));
+    }
+    assert_eq!(consensus.decided_leaf_at(ViewNumber::new(2)), None);
+    for (view, leaf) in (1..).zip(&leaves).skip(2) {
+        assert!(consensus
+            .validated_state_map()
+            .contains_key(&ViewNumber::new(view)));
+        assert!(consensus.saved_leaves().contains_key(&leaf.commit()));
+        assert!(consensus
+            .saved_payloads()
+            .contains_key(&ViewNumber::new(view)));
+    }
+    assert_eq!(
+        consensus
+            .decided_leaf_at(ViewNumber::new(3))
+            .map(|leaf| leaf.commit()),
+        Some(leaves[2].commit())
+    );
+
+    // Backfilling a proposal for a pruned view doesn't bring it back, unlike a retained view.
+    assert_eq!(consensus.oldest_retained_view(), ViewNumber::new(3));
+    assert!(!consensus.backfill_proposal(proposals[1].clone()));
+    assert!(consensus.backfill_proposal(proposals[2].clone()));
+
+    // An archival depth keeps everything that's left.
+    consensus.set_retention_depth(u64::MAX);
+    assert_eq!(consensus.garbage_collect_now(), GarbageCollected::default());
+}
+
+/// Only `LivenessOnly` skips the safety check; `Full` accepts on either check and `Strict`
+/// requires both.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_validation_level_checks() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let consensus = handle.consensus();
+    let consensus = consensus.read().await;
+    assert_eq!(consensus.locked_view(), ViewNumber::genesis());
+
+    // A justify QC for the locked genesis view fails liveness but passes safety.
+    let genesis = ViewNumber::genesis();
+    let full = check_safety_and_liveness(&consensus, genesis, ValidationLevel::Full);
+    assert!(!full.liveness);
+    assert!(matches!(full.safety, Some(Ok(()))));
+    assert!(full.accepted());
+
+    let strict = check_safety_and_liveness(&consensus, genesis, ValidationLevel::Strict);
+    assert!(matches!(strict.safety, Some(Ok(()))));
+    assert!(!strict.accepted());
+
+    let liveness_only =
+        check_safety_and_liveness(&consensus, genesis, ValidationLevel::LivenessOnly);
+    assert!(liveness_only.safety.is_none());
+    assert!(!liveness_only.accepted());
+
+    // A justify QC for a view we've never seen passes liveness but fails safety.
+    let unknown = ViewNumber::new(5);
+    let full = check_safety_and_liveness(&consensus, unknown, ValidationLevel::Full);
+    assert!(full.liveness);
+    assert!(matches!(full.safety, Some(Err(_))));
+    assert!(full.accepted());
+
+    let strict = check_safety_and_liveness(&consensus, unknown, ValidationLevel::Strict);
+    assert!(!strict.accepted());
+
+    let liveness_only =
+        check_safety_and_liveness(&consensus, unknown, ValidationLevel::LivenessOnly);
+    assert!(liveness_only.safety.is_none());
+    assert!(liveness_only.accepted());
+
+    assert_eq!(ValidationLevel::default(), ValidationLevel::Full);
+    assert_eq!(
+        handle.hotshot.config.validation_level,
+        ValidationLevel::Full
+    );
+}
+
+/// Advancing the high QC announces the transition, while a redundant or stale update announces
+/// nothing.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_high_qc_updates_are_announced() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+    let consensus = handle.consensus();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let mut qcs = Vec::new();
+    for view in (&mut generator).take(3).collect::<Vec<_>>().await {
+        qcs.push(view.quorum_proposal.data.justify_qc);
+    }
+    let (sender, mut receiver) = async_broadcast::broadcast(10);
+
+    // Genesis isn't newer than the genesis high QC.
+    assert!(update_high_qc(&consensus, qcs[0].clone(), &sender)
+        .await
+        .is_err());
+    assert!(receiver.try_recv().is_err());
+
+    for (previous, qc) in [(None, &qcs[1]), (Some(ViewNumber::new(1)), &qcs[2])] {
+        update_high_qc(&consensus, qc.clone(), &sender)
+            .await
+            .unwrap();
+        assert_eq!(
+            *receiver.try_recv().unwrap(),
+            HotShotEvent::HighQcUpdated {
+                from: previous,
+                to: qc.view_number,
+            }
+        );
+    }
+    assert_eq!(consensus.read().await.high_qc(), &qcs[2]);
+
+    // Neither the current high QC nor an older one changes anything.
+    for qc in [&qcs[2], &qcs[1]] {
+        assert!(update_high_qc(&consensus, qc.clone(), &sender)
+            .await
+            .is_err());
+        assert!(receiver.try_recv().is_err());
+    }
+    assert_eq!(consensus.read().await.high_qc(), &qcs[2]);
+}
+
+/// Through a long stretch without decides, only the states of the views within the compaction
+/// depth of the high QC are kept in full, while the compacted views keep their leaves so the chain
+/// can still be walked and decided, and get their full state back when validated again.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_state_compaction_without_decides() {
+    /// Number of views below the high QC whose states are kept in full
+    const DEPTH: u64 = 4;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let mut launcher =
+        TestDescription::default_multiple_rounds().gen_launcher::<TestTypes, MemoryImpl>(0);
+    launcher.resource_generator.config.state_compaction_depth = Some(DEPTH);
+    let handle = build_system_handle_from_launcher(&launcher, 1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let views = (&mut generator).take(31).collect::<Vec<_>>().await;
+    let full_view = |leaf: &Leaf<TestTypes>| View {
+        view_inner: ViewInner::Leaf {
+            leaf: leaf.commit(),
+            state: Arc::new(TestValidatedState::default()),
+            delta: Some(Arc::new(TestStateDelta {})),
+        },
+    };
+
+    let consensus = handle.consensus();
+    let mut consensus = consensus.write().await;
+    assert_eq!(consensus.state_compaction_depth(), Some(DEPTH));
+    for (view, next) in views.iter().zip(&views[1..]) {
+        consensus
+            .update_validated_state_map(view.view_number, full_view(&view.leaf))
+            .unwrap();
+        consensus.update_saved_leaves(view.leaf.clone());
+        consensus
+            .update_high_qc(next.quorum_proposal.data.justify_qc.clone())
+            .unwrap();
+
+        // Only the views within the compaction depth of the high QC keep their full state.
+        let full_states = consensus
+            .validated_state_map()
+            .iter()
+            .filter(|(view_number, _)| **view_number > ViewNumber::genesis())
+            .filter(|(view_number, _)| !consensus.is_state_compacted(**view_number))
+            .count();
+        assert!(full_states <= usize::try_from(DEPTH).unwrap() + 1);
+    }
+
+    let high_qc_view = consensus.high_qc().view_number;
+    for view in &views[..30] {
+        let compacted = view.view_number.u64() + DEPTH < high_qc_view.u64();
+        assert_eq!(consensus.is_state_compacted(view.view_number), compacted);
+        let (state, delta) = consensus.state_and_delta(view.view_number);
+        if compacted {
+            // The leaf is kept, and the state is the one rebuilt from its header.
+            assert_eq!(
+                consensus.validated_state_map()[&view.view_number].leaf_commitment(),
+                Some(view.leaf.commit())
+            );
+            assert_eq!(
+                state.as_deref(),
+                Some(
+                    &<TestValidatedState as ValidatedState<TestTypes>>::from_header(
+                        view.leaf.block_header()
+                    )
+                )
+            );
+            assert!(delta.is_none());
+        } else {
+            assert!(delta.is_some());
+        }
+    }
+
+    // The whole chain can still be walked back from the high QC, as on a decide.
+    let mut visited = Vec::new();
+    consensus
+        .visit_leaf_ancestors(
+            high_qc_view,
+            Terminator::Inclusive(ViewNumber::new(1)),
+            true,
+            |leaf, _, _| {
+                visited.push(leaf.commit());
+                true
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        visited,
+        views[..30]
+            .iter()
+            .rev()
+            .map(|view| view.leaf.commit())
+            .collect::<Vec<_>>()
+    );
+
+    // A compacted view which is validated again gets its full state back.
+    let revalidated = &views[10];
+    consensus
+        .update_validated_state_map(revalidated.view_number, full_view(&revalidated.leaf))
+        .unwrap();
+    assert!(!consensus.is_state_compacted(revalidated.view_number));
+    assert!(consensus
+        .state_and_delta(revalidated.view_number)
+        .1
+        .is_some());
+
+    // Once decided and garbage collected, the compacted views are forgotten.
+    consensus
+        .update_last_decided_view(ViewNumber::new(20))
+        .unwrap();
+    consensus.update_locked_view(ViewNumber::new(20)).unwrap();
+    consensus.garbage_collect_now();
+    for view in 1..20 {
+        assert!(!consensus.is_state_compacted(ViewNumber::new(view)));
+    }
+    assert!(consensus.is_state_compacted(ViewNumber::new(20)));
+    assert_eq!(consensus.compact_states(), 0);
+}
+
+/// Beyond the maximum number of undecided views after the locked view, the oldest have their
+/// states and leaves pruned, but never the locked view or the views before it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_prune_undecided() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let leaves = (&mut generator)
+        .take(6)
+        .map(|view| view.leaf)
+        .collect::<Vec<_>>()
+        .await;
+
+    let consensus = handle.consensus();
+    let mut consensus = consensus.write().await;
+    for leaf in &leaves {
+        consensus
+            .update_validated_state_map(
+                leaf.view_number(),
+                View {
+                    view_inner: ViewInner::Leaf {
+                        leaf: leaf.commit(),
+                        state: Arc::new(TestValidatedState::default()),
+                        delta: None,
+                    },
+                },
+            )
+            .unwrap();
+        consensus.update_saved_leaves(leaf.clone());
+    }
+    consensus.update_locked_view(ViewNumber::new(2)).unwrap();
+
+    // Nothing is pruned within the limit.
+    assert_eq!(consensus.prune_undecided(), None);
+
+    consensus.set_max_undecided_views(2);
+    assert_eq!(consensus.prune_undecided(), Some(ViewNumber::new(4)));
+    for (view, leaf) in (1..).zip(&leaves) {
+        let kept = [1, 2, 5, 6].contains(&view);
+        assert_eq!(
+            consensus
+                .validated_state_map()
+                .contains_key(&ViewNumber::new(view)),
+            kept
+        );
+        assert_eq!(consensus.saved_leaves().contains_key(&leaf.commit()), kept);
+    }
+    assert!(consensus
+        .validated_state_map()
+        .contains_key(&ViewNumber::genesis()));
+    assert_eq!(consensus.prune_undecided(), None);
+}
+
+/// Pruning keeps the chain of the high QC, so it can still be decided afterwards.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_decide_after_prune_undecided() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let views = (&mut generator).take(6).collect::<Vec<_>>().await;
+
+    let consensus = handle.consensus();
+    let mut consensus = consensus.write().await;
+    for view in &views {
+        consensus
+            .update_validated_state_map(
+                view.leaf.view_number(),
+                View {
+                    view_inner: ViewInner::Leaf {
+                        leaf: view.leaf.commit(),
+                        state: Arc::new(TestValidatedState::default()),
+                        delta: None,
+                    },
+                },
+            )
+            .unwrap();
+        consensus.update_saved_leaves(view.leaf.clone());
+    }
+    consensus.update_locked_view(ViewNumber::new(2)).unwrap();
+    // The QC for view 4 is justified by the proposal for view 5.
+    consensus
+        .update_high_qc(views[4].quorum_proposal.data.justify_qc.clone())
+        .unwrap();
+
+    // Views 3 and 4 are on the high QC's chain, so the newer views 5 and 6 go instead.
+    consensus.set_max_undecided_views(2);
+    assert_eq!(consensus.prune_undecided(), Some(ViewNumber::new(6)));
+    for view in 1..=6 {
+        assert_eq!(
+            consensus
+                .validated_state_map()
+                .contains_key(&ViewNumber::new(view)),
+            view <= 4
+        );
+    }
+
+    // Deciding the high QC's leaf walks its whole chain back to genesis.
+    let mut decided = Vec::new();
+    consensus
+        .visit_leaf_ancestors(
+            ViewNumber::new(4),
+            Terminator::Exclusive(ViewNumber::genesis()),
+            true,
+            |leaf, _, _| {
+                decided.push(leaf.view_number());
+                true
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        decided,
+        (1..=4).rev().map(ViewNumber::new).collect::<Vec<_>>()
+    );
+    consensus
+        .update_last_decided_view(ViewNumber::new(4))
+        .unwrap();
+}
@@ -5,6 +5,7 @@
 
 use std::{sync::Arc, time::Duration};
 
+use committable::Committable;
 use futures::StreamExt;
 use hotshot::tasks::task_state::CreateTaskState;
 use hotshot_example_types::{
@@ -110,6 +111,10 @@ async fn test_consensus_task() {
         Expectations::from_outputs(all_predicates![
             validated_state_updated(),
             exact(ViewChange(ViewNumber::new(2))),
+            exact(HighQcUpdated {
+                from: None,
+                to: ViewNumber::new(1),
+            }),
             quorum_proposal_validated(),
             quorum_proposal_send(),
         ]),
@@ -125,6 +130,89 @@ async fn test_consensus_task() {
     run_test![inputs, consensus_script].await;
 }
 
+/// The high QC and undecided leaves the consensus task persists can be read back through the
+/// handle.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_stored_consensus_data() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator =
+        TestViewGenerator::generate(quorum_membership.clone(), da_membership.clone());
+
+    let mut proposals = Vec::new();
+    let mut leaders = Vec::new();
+    let mut leaves = Vec::new();
+    let mut votes = Vec::new();
+    let mut dacs = Vec::new();
+    let mut vids = Vec::new();
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaders.push(view.leader_public_key);
+        leaves.push(view.leaf.clone());
+        votes.push(view.create_quorum_vote(&handle));
+        dacs.push(view.da_certificate.clone());
+        vids.push(view.vid_proposal.clone());
+    }
+
+    // Nothing has been persisted before the run.
+    assert!(handle.stored_high_qc().await.unwrap().is_none());
+    assert!(handle.stored_undecided_leaves().await.unwrap().is_empty());
+
+    let inputs = vec![
+        random![
+            QuorumProposalRecv(proposals[0].clone(), leaders[0]),
+            DaCertificateRecv(dacs[0].clone()),
+            VidShareRecv(vid_share(&vids[0].0, handle.public_key())),
+        ],
+        serial![QuorumProposalRecv(proposals[1].clone(), leaders[1])],
+    ];
+
+    let expectations = vec![
+        Expectations::from_outputs(all_predicates![
+            validated_state_updated(),
+            exact(ViewChange(ViewNumber::new(1))),
+            quorum_proposal_validated(),
+            exact(QuorumVoteSend(votes[0].clone())),
+        ]),
+        Expectations::from_outputs(all_predicates![
+            validated_state_updated(),
+            exact(ViewChange(ViewNumber::new(2))),
+            exact(HighQcUpdated {
+                from: None,
+                to: ViewNumber::new(1),
+            }),
+            quorum_proposal_validated(),
+        ]),
+    ];
+
+    let consensus_state = ConsensusTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let mut consensus_script = TaskScript {
+        timeout: TIMEOUT,
+        state: consensus_state,
+        expectations,
+    };
+
+    run_test![inputs, consensus_script].await;
+
+    // The second proposal's justify QC became the high QC, and the leaf we voted for is undecided.
+    assert_eq!(
+        handle.stored_high_qc().await.unwrap(),
+        Some(proposals[1].data.justify_qc.clone())
+    );
+    assert!(handle
+        .stored_undecided_leaves()
+        .await
+        .unwrap()
+        .contains_key(&leaves[0].commit()));
+}
+
 #[cfg(test)]
 #[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
 #[cfg_attr(async_executor_impl = "async-std", async_std::test)]
@@ -179,6 +267,108 @@ async fn test_consensus_vote() {
     run_test![inputs, consensus_script].await;
 }
 
+/// The DA certificate for the parent's view, attached and signed by the next leader, lets us vote
+/// on the parent without a separate `DaCertificateRecv`, while a forged or re-signed certificate
+/// gets the proposal rejected.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_consensus_vote_with_parent_da_certificate() {
+    use hotshot_types::{data::ParentDaCertificate, traits::signature_key::InMemorySigner};
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator =
+        TestViewGenerator::generate(quorum_membership.clone(), da_membership.clone());
+
+    let mut proposals = Vec::new();
+    let mut leaders = Vec::new();
+    let mut votes = Vec::new();
+    let mut dacs = Vec::new();
+    let mut vids = Vec::new();
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaders.push(view.leader_public_key);
+        votes.push(view.create_quorum_vote(&handle));
+        dacs.push(view.da_certificate.clone());
+        vids.push(view.vid_proposal.clone());
+    }
+
+    let leader_signer = InMemorySigner::new(key_pair_for_id(2).0);
+    let parent_da = ParentDaCertificate::new(&proposals[1].data, dacs[0].clone(), &leader_signer)
+        .await
+        .unwrap();
+
+    let inputs = vec![
+        serial![
+            VidShareRecv(vid_share(&vids[0].0, handle.public_key())),
+            QuorumProposalRecv(proposals[0].clone(), leaders[0]),
+        ],
+        serial![QuorumProposalWithParentDaRecv(
+            proposals[1].clone(),
+            parent_da,
+            leaders[1]
+        )],
+    ];
+
+    // Without the DAC for view 1 we can't vote on it until the view 2 proposal brings it along.
+    let expectations = vec![
+        Expectations::from_outputs(all_predicates![
+            validated_state_updated(),
+            exact(ViewChange(ViewNumber::new(1))),
+            quorum_proposal_validated(),
+        ]),
+        Expectations::from_outputs(all_predicates![
+            exact(QuorumVoteSend(votes[0].clone())),
+            exact(QuorumProposalRecv(proposals[1].clone(), leaders[1])),
+        ]),
+    ];
+
+    let consensus_state = ConsensusTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let mut consensus_script = TaskScript {
+        timeout: TIMEOUT,
+        state: consensus_state,
+        expectations,
+    };
+
+    run_test![inputs, consensus_script].await;
+
+    // Both views have the same empty payload, so relabelling the view 2 certificate gives one
+    // that matches the parent but whose signatures don't.
+    let mut forged_dac = dacs[1].clone();
+    forged_dac.view_number = ViewNumber::new(1);
+    let forged = ParentDaCertificate::new(&proposals[1].data, forged_dac, &leader_signer)
+        .await
+        .unwrap();
+    // A valid certificate that wasn't attached by the leader of view 2.
+    let other_signer = InMemorySigner::new(key_pair_for_id(3).0);
+    let resigned = ParentDaCertificate::new(&proposals[1].data, dacs[0].clone(), &other_signer)
+        .await
+        .unwrap();
+
+    let inputs = vec![serial![
+        QuorumProposalWithParentDaRecv(proposals[1].clone(), forged, leaders[1]),
+        QuorumProposalWithParentDaRecv(proposals[1].clone(), resigned, leaders[1]),
+    ]];
+
+    let expectations = vec![Expectations::from_outputs(vec![])];
+
+    let handle = build_system_handle(2).await.0;
+    let consensus_state = ConsensusTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let mut consensus_script = TaskScript {
+        timeout: TIMEOUT,
+        state: consensus_state,
+        expectations,
+    };
+
+    run_test![inputs, consensus_script].await;
+}
+
 #[cfg(test)]
 #[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
 #[cfg_attr(async_executor_impl = "async-std", async_std::test)]
@@ -299,6 +489,10 @@ async fn test_view_sync_finalize_propose() {
         Expectations::from_outputs(all_predicates![
             validated_state_updated(),
             exact(ViewChange(ViewNumber::new(4))),
+            exact(HighQcUpdated {
+                from: None,
+                to: ViewNumber::new(1),
+            }),
             quorum_proposal_validated(),
             quorum_proposal_send(),
         ]),
@@ -562,3 +756,429 @@ async fn test_vid_disperse_storage_failure() {
 
     run_test![inputs, consensus_script].await;
 }
+
+/// A proposal whose parent we can't find is voted on with only its liveness checked, which is
+/// counted and reported to the application as degraded validation.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_liveness_only_vote_is_reported() {
+    use hotshot::types::EventType;
+    use hotshot_task::task::TaskState;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let mut proposals = Vec::new();
+    let mut leaders = Vec::new();
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaders.push(view.leader_public_key);
+    }
+
+    let mut events = handle.event_stream_known_impl();
+    let mut consensus_state =
+        ConsensusTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let (sender, receiver) = async_broadcast::broadcast(100);
+
+    // We never saw the proposal for view 1, so the request for it goes unanswered and the
+    // proposal for view 2 can only be checked for liveness.
+    consensus_state
+        .handle_event(
+            Arc::new(QuorumProposalRecv(proposals[1].clone(), leaders[1])),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+
+    let mut degraded_views = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let EventType::DegradedValidation { view } = event.event {
+            degraded_views.push(view);
+        }
+    }
+    assert_eq!(degraded_views, vec![ViewNumber::new(2)]);
+    assert!(handle
+        .export_metrics_prometheus()
+        .lines()
+        .any(|line| line == "liveness_only_vote_count 1"));
+}
+
+/// Handling the proposal that decides a view more than once emits a single `Decide` event, and
+/// records how long the decided view's proposal took to decide once.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_decide_is_emitted_once() {
+    use hotshot::types::EventType;
+    use hotshot_example_types::state_types::TestValidatedState;
+    use hotshot_task::task::TaskState;
+    use hotshot_types::utils::{View, ViewInner};
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let mut proposals = Vec::new();
+    let mut leaves = Vec::new();
+    for view in (&mut generator).take(4).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaves.push(view.leaf.clone());
+    }
+
+    // We've validated the proposals for views 1 through 3, so the proposal for view 4 completes
+    // the 3-chain deciding view 1.
+    {
+        let consensus = handle.consensus();
+        let mut consensus = consensus.write().await;
+        for leaf in &leaves[..3] {
+            consensus
+                .update_validated_state_map(
+                    leaf.view_number(),
+                    View {
+                        view_inner: ViewInner::Leaf {
+                            leaf: leaf.commit(),
+                            state: Arc::new(TestValidatedState::default()),
+                            delta: None,
+                        },
+                    },
+                )
+                .unwrap();
+            consensus.update_saved_leaves(leaf.clone());
+        }
+    }
+
+    let mut events = handle.event_stream_known_impl();
+    let mut consensus_state =
+        ConsensusTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let (sender, receiver) = async_broadcast::broadcast(100);
+    for view in [1, 3] {
+        consensus_state
+            .proposal_send_times
+            .insert(ViewNumber::new(view), std::time::Instant::now());
+    }
+
+    // The same event is handled twice, as if it had been retried.
+    for _ in 0..2 {
+        consensus_state
+            .handle_event(
+                Arc::new(QuorumProposalValidated(
+                    proposals[3].data.clone(),
+                    leaves[2].clone(),
+                )),
+                &sender,
+                &receiver,
+            )
+            .await
+            .unwrap();
+    }
+
+    let mut decided_views = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let EventType::Decide { .. } = event.event {
+            decided_views.push(event.view_number);
+        }
+    }
+    assert_eq!(decided_views, vec![ViewNumber::new(1)]);
+    assert_eq!(
+        consensus_state
+            .proposal_send_times
+            .keys()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![ViewNumber::new(3)]
+    );
+    assert!(handle
+        .export_metrics_prometheus()
+        .lines()
+        .any(|line| line == "proposal_to_decide_ms_count 1"));
+}
+
+/// Waiting for a view's decide resolves with its leaf once it's decided, returns immediately for
+/// a view that's already decided, and times out for a view that isn't.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_wait_for_decide_on_view() {
+    use hotshot::HotShotError;
+    use hotshot_example_types::state_types::TestValidatedState;
+    use hotshot_task::task::TaskState;
+    use hotshot_types::utils::{View, ViewInner};
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    // Genesis is decided from the start.
+    let genesis = handle
+        .wait_for_decide_on_view(ViewNumber::genesis(), Duration::ZERO)
+        .await
+        .unwrap();
+    assert_eq!(genesis.commit(), handle.decided_leaf().await.commit());
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let mut proposals = Vec::new();
+    let mut leaves = Vec::new();
+    for view in (&mut generator).take(4).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaves.push(view.leaf.clone());
+    }
+
+    {
+        let consensus = handle.consensus();
+        let mut consensus = consensus.write().await;
+        for leaf in &leaves[..3] {
+            consensus
+                .update_validated_state_map(
+                    leaf.view_number(),
+                    View {
+                        view_inner: ViewInner::Leaf {
+                            leaf: leaf.commit(),
+                            state: Arc::new(TestValidatedState::default()),
+                            delta: None,
+                        },
+                    },
+                )
+                .unwrap();
+            consensus.update_saved_leaves(leaf.clone());
+        }
+    }
+
+    let mut consensus_state =
+        ConsensusTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let (sender, receiver) = async_broadcast::broadcast(100);
+
+    // The proposal for view 4 decides view 1 while we're waiting on it.
+    let (decided, ()) = futures::join!(
+        handle.wait_for_decide_on_view(ViewNumber::new(1), Duration::from_secs(5)),
+        async {
+            consensus_state
+                .handle_event(
+                    Arc::new(QuorumProposalValidated(
+                        proposals[3].data.clone(),
+                        leaves[2].clone(),
+                    )),
+                    &sender,
+                    &receiver,
+                )
+                .await
+                .unwrap();
+        }
+    );
+    assert_eq!(decided.unwrap().commit(), leaves[0].commit());
+
+    // Now that it's decided, waiting on it again returns immediately.
+    let decided = handle
+        .wait_for_decide_on_view(ViewNumber::new(1), Duration::ZERO)
+        .await
+        .unwrap();
+    assert_eq!(decided.commit(), leaves[0].commit());
+
+    assert!(matches!(
+        handle
+            .wait_for_decide_on_view(ViewNumber::new(10), Duration::from_millis(100))
+            .await,
+        Err(HotShotError::TimeoutError { .. })
+    ));
+}
+
+/// Each decided leaf is attributed to the signer of its proposal once we've verified the signature,
+/// here when backfilling it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_decided_leaf_proposer() {
+    use hotshot::types::{EventType, SignatureKey};
+    use hotshot_example_types::state_types::TestValidatedState;
+    use hotshot_task::task::TaskState;
+    use hotshot_task_impls::request::save_backfilled_proposal;
+    use hotshot_types::utils::{View, ViewInner};
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let mut proposals = Vec::new();
+    let mut leaves = Vec::new();
+    for view in (&mut generator).take(4).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaves.push(view.leaf.clone());
+    }
+
+    {
+        let consensus = handle.consensus();
+        let mut consensus = consensus.write().await;
+        for leaf in &leaves[..3] {
+            consensus
+                .update_validated_state_map(
+                    leaf.view_number(),
+                    View {
+                        view_inner: ViewInner::Leaf {
+                            leaf: leaf.commit(),
+                            state: Arc::new(TestValidatedState::default()),
+                            delta: None,
+                        },
+                    },
+                )
+                .unwrap();
+            consensus.update_saved_leaves(leaf.clone());
+        }
+    }
+
+    // A proposal not signed by its leader isn't backfilled, so its signer isn't recorded.
+    let mut forged = proposals[0].clone();
+    forged.signature = proposals[1].signature.clone();
+    assert!(save_backfilled_proposal(
+        &handle.consensus(),
+        &quorum_membership,
+        ViewNumber::new(1),
+        forged
+    )
+    .await
+    .is_err());
+    assert!(handle
+        .consensus()
+        .read()
+        .await
+        .proposer(ViewNumber::new(1))
+        .is_none());
+    save_backfilled_proposal(
+        &handle.consensus(),
+        &quorum_membership,
+        ViewNumber::new(1),
+        proposals[0].clone(),
+    )
+    .await
+    .unwrap();
+
+    let mut events = handle.event_stream_known_impl();
+    let mut consensus_state =
+        ConsensusTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let (sender, receiver) = async_broadcast::broadcast(100);
+    consensus_state
+        .handle_event(
+            Arc::new(QuorumProposalValidated(
+                proposals[3].data.clone(),
+                leaves[2].clone(),
+            )),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+
+    let mut decided = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let EventType::Decide {
+            leaf_chain,
+            decided_view_count,
+            ..
+        } = event.event
+        {
+            assert_eq!(decided_view_count, leaf_chain.len());
+            decided.extend(leaf_chain.iter().cloned());
+        }
+    }
+    assert_eq!(decided.len(), 1);
+    let leaf_info = &decided[0];
+    assert_eq!(leaf_info.leaf.commit(), leaves[0].commit());
+    let proposer = leaf_info.proposer.clone().unwrap();
+    assert_eq!(proposer, quorum_membership.leader(ViewNumber::new(1)));
+    assert!(proposer.validate(&proposals[0].signature, leaves[0].commit().as_ref()));
+}
+
+/// Every view change emits a `LeaderHandoff` from the leader of the view we left to the leader of
+/// the view we entered, flagging whether the outgoing leader proposed.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_leader_handoff_events() {
+    use hotshot::types::EventType;
+    use hotshot_example_types::state_types::TestValidatedState;
+    use hotshot_task::task::TaskState;
+    use hotshot_types::utils::{View, ViewInner};
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let leaves = (&mut generator)
+        .take(3)
+        .map(|view| view.leaf)
+        .collect::<Vec<_>>()
+        .await;
+
+    // We validated the proposals for views 1 and 3, but the leader of view 2 never proposed.
+    {
+        let consensus = handle.consensus();
+        let mut consensus = consensus.write().await;
+        for leaf in [&leaves[0], &leaves[2]] {
+            consensus
+                .update_validated_state_map(
+                    leaf.view_number(),
+                    View {
+                        view_inner: ViewInner::Leaf {
+                            leaf: leaf.commit(),
+                            state: Arc::new(TestValidatedState::default()),
+                            delta: None,
+                        },
+                    },
+                )
+                .unwrap();
+            consensus.update_saved_leaves(leaf.clone());
+        }
+    }
+
+    let mut events = handle.event_stream_known_impl();
+    let mut consensus_state =
+        ConsensusTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let (sender, receiver) = async_broadcast::broadcast(100);
+    for view in 1..=4 {
+        consensus_state
+            .handle_event(
+                Arc::new(ViewChange(ViewNumber::new(view))),
+                &sender,
+                &receiver,
+            )
+            .await
+            .unwrap();
+    }
+
+    let mut handoffs = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let EventType::LeaderHandoff {
+            view,
+            outgoing,
+            incoming,
+            outgoing_proposed,
+        } = event.event
+        {
+            assert_eq!(outgoing, quorum_membership.leader(view));
+            assert_eq!(incoming, quorum_membership.leader(view + 1));
+            handoffs.push((*view, outgoing_proposed));
+        }
+    }
+    // The genesis leaf counts as proposed.
+    assert_eq!(handoffs, vec![(0, true), (1, true), (2, false), (3, true)]);
+}
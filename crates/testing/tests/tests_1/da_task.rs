@@ -1,15 +1,29 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
+use async_compatibility_layer::art::async_timeout;
+use async_trait::async_trait;
+use committable::Committable;
 use futures::StreamExt;
-use hotshot::tasks::task_state::CreateTaskState;
+use hotshot::{
+    tasks::task_state::CreateTaskState,
+    types::{BLSPubKey, EventType, SignatureKey},
+};
 use hotshot_example_types::{
     block_types::{TestMetadata, TestTransaction},
     node_types::{MemoryImpl, TestTypes},
 };
 use hotshot_macros::{run_test, test_scripts};
+use hotshot_task::task::TaskState;
 use hotshot_task_impls::{da::DaTaskState, events::HotShotEvent::*};
 use hotshot_testing::{
-    helpers::build_system_handle,
+    helpers::{build_system_handle, key_pair_for_id},
     predicates::event::exact,
     script::{Expectations, InputOrder, TaskScript},
     serial,
@@ -17,10 +31,14 @@ use hotshot_testing::{
 };
 use hotshot_types::{
     data::{null_block, ViewNumber},
-    simple_vote::DaData,
+    message::TxPriority,
+    simple_vote::{DaData, DaVote},
     traits::{
-        block_contents::precompute_vid_commitment, election::Membership,
+        block_contents::precompute_vid_commitment,
+        election::Membership,
         node_implementation::ConsensusTime,
+        signature_key::{InMemorySigner, Signer},
+        storage::Storage,
     },
 };
 
@@ -103,6 +121,71 @@ async fn test_da_task() {
     run_test![inputs, da_script].await;
 }
 
+/// The DA proposals the DA task persists can be read back through the handle.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_stored_da_proposals() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let transactions = vec![TestTransaction::new(vec![0])];
+    let encoded_transactions = Arc::from(TestTransaction::encode(&transactions));
+    let (payload_commit, precompute) = precompute_vid_commitment(
+        &encoded_transactions,
+        handle.hotshot.memberships.quorum_membership.total_nodes(),
+    );
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    generator.next().await;
+    generator.add_transactions(transactions);
+    let view = generator.next().await.unwrap();
+    let proposal = view.da_proposal.clone();
+    let leader = view.leader_public_key;
+    let vote = view.create_da_vote(DaData { payload_commit }, &handle);
+
+    assert!(handle.stored_da_proposals().await.unwrap().is_empty());
+
+    let inputs = vec![
+        serial![
+            ViewChange(ViewNumber::new(1)),
+            ViewChange(ViewNumber::new(2)),
+            BlockRecv(
+                encoded_transactions,
+                TestMetadata,
+                ViewNumber::new(2),
+                null_block::builder_fee(quorum_membership.total_nodes()).unwrap(),
+                precompute,
+            ),
+        ],
+        serial![DaProposalRecv(proposal.clone(), leader)],
+    ];
+
+    let da_state = DaTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let mut da_script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state: da_state,
+        expectations: vec![
+            Expectations::from_outputs(vec![exact(DaProposalSend(proposal.clone(), leader))]),
+            Expectations::from_outputs(vec![
+                exact(DaProposalValidated(proposal.clone(), leader)),
+                exact(DaVoteSend(vote)),
+            ]),
+        ],
+    };
+
+    run_test![inputs, da_script].await;
+
+    // Only the proposal we voted on was persisted.
+    assert_eq!(
+        handle.stored_da_proposals().await.unwrap(),
+        BTreeMap::from([(ViewNumber::new(2), proposal)])
+    );
+}
+
 #[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
 #[cfg_attr(async_executor_impl = "async-std", async_std::test)]
 async fn test_da_task_storage_failure() {
@@ -187,3 +270,314 @@ async fn test_da_task_storage_failure() {
 
     run_test![inputs, da_script].await;
 }
+
+/// A signer standing in for a remote signing service, counting the messages it signs.
+#[derive(Debug)]
+struct CountingSigner {
+    inner: InMemorySigner<BLSPubKey>,
+    signed: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Signer<BLSPubKey> for CountingSigner {
+    async fn sign(
+        &self,
+        message: &[u8],
+    ) -> anyhow::Result<<BLSPubKey as SignatureKey>::PureAssembledSignatureType> {
+        self.signed.fetch_add(1, Ordering::SeqCst);
+        self.inner.sign(message).await
+    }
+}
+
+/// The DA task signs its proposals and votes through whichever signer it is given.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_da_task_signs_with_signer() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let transactions = vec![TestTransaction::new(vec![0])];
+    let encoded_transactions = Arc::from(TestTransaction::encode(&transactions));
+    let (payload_commit, precompute) = precompute_vid_commitment(
+        &encoded_transactions,
+        handle.hotshot.memberships.quorum_membership.total_nodes(),
+    );
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    generator.next().await;
+    generator.add_transactions(transactions);
+    let view = generator.next().await.unwrap();
+    let proposal = view.da_proposal.clone();
+    let leader = view.leader_public_key;
+    let vote = view.create_da_vote(DaData { payload_commit }, &handle);
+
+    let inputs = vec![
+        serial![
+            ViewChange(ViewNumber::new(1)),
+            ViewChange(ViewNumber::new(2)),
+            BlockRecv(
+                encoded_transactions,
+                TestMetadata,
+                ViewNumber::new(2),
+                null_block::builder_fee(quorum_membership.total_nodes()).unwrap(),
+                precompute,
+            ),
+        ],
+        serial![DaProposalRecv(proposal.clone(), leader)],
+    ];
+
+    let signed = Arc::new(AtomicUsize::new(0));
+    let mut da_state = DaTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    da_state.signer = Arc::new(CountingSigner {
+        inner: InMemorySigner::new(key_pair_for_id(handle.hotshot.id).0),
+        signed: Arc::clone(&signed),
+    });
+    let mut da_script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state: da_state,
+        expectations: vec![
+            Expectations::from_outputs(vec![exact(DaProposalSend(proposal.clone(), leader))]),
+            Expectations::from_outputs(vec![
+                exact(DaProposalValidated(proposal.clone(), leader)),
+                exact(DaVoteSend(vote)),
+            ]),
+        ],
+    };
+
+    run_test![inputs, da_script].await;
+
+    // At least the proposal and the vote were signed remotely; our VID share may be too.
+    assert!(signed.load(Ordering::SeqCst) >= 2);
+}
+
+/// A block payload larger than the maximum block size is rejected rather than proposed.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_da_task_rejects_oversized_block() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+
+    let transactions = vec![TestTransaction::new(vec![0; 64])];
+    let encoded_transactions: Arc<[u8]> = Arc::from(TestTransaction::encode(&transactions));
+    let size = encoded_transactions.len() as u64;
+    let (_, precompute) =
+        precompute_vid_commitment(&encoded_transactions, quorum_membership.total_nodes());
+
+    let inputs = vec![serial![
+        ViewChange(ViewNumber::new(1)),
+        ViewChange(ViewNumber::new(2)),
+        BlockRecv(
+            encoded_transactions,
+            TestMetadata,
+            ViewNumber::new(2),
+            null_block::builder_fee(quorum_membership.total_nodes()).unwrap(),
+            precompute,
+        ),
+    ]];
+
+    let mut da_state = DaTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    da_state.max_block_size = size - 1;
+    let mut da_script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state: da_state,
+        expectations: vec![Expectations::from_outputs(vec![exact(BlockRejected(
+            ViewNumber::new(2),
+            format!(
+                "payload of {size} bytes exceeds the maximum block size of {} bytes",
+                size - 1
+            ),
+        ))])],
+    };
+
+    run_test![inputs, da_script].await;
+}
+
+/// A DA leader persists the votes it collects when it shuts down, so after restarting it forms the
+/// certificate once the remaining votes arrive, and then drops the persisted votes.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_da_votes_survive_restart() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    // Node 2 leads the DA committee in view 2.
+    let handle = build_system_handle(2).await.0;
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+    let view = ViewNumber::new(2);
+    let threshold = da_membership.success_threshold().get();
+
+    let transactions = vec![TestTransaction::new(vec![0])];
+    let (payload_commit, _) = precompute_vid_commitment(
+        &TestTransaction::encode(&transactions),
+        handle.hotshot.memberships.quorum_membership.total_nodes(),
+    );
+    let votes: Vec<_> = (0..threshold)
+        .map(|id| {
+            let (private_key, public_key) = key_pair_for_id(id);
+            DaVote::create_signed_vote(DaData { payload_commit }, view, &public_key, &private_key)
+                .unwrap()
+        })
+        .collect();
+    let (last_vote, first_votes) = votes.split_last().unwrap();
+
+    let (sender, mut receiver) = async_broadcast::broadcast(16);
+    let mut da_state = DaTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    for vote in first_votes {
+        da_state
+            .handle(Arc::new(DaVoteRecv(vote.clone())), sender.clone())
+            .await;
+    }
+    assert!(receiver.try_recv().is_err());
+    // Fewer votes than make a batch are only persisted when the task shuts down.
+    da_state.cancel_subtasks().await;
+    assert_eq!(
+        handle
+            .storage()
+            .read()
+            .await
+            .load_da_votes(view)
+            .await
+            .unwrap()
+            .len(),
+        first_votes.len()
+    );
+
+    // The restarted task only has the storage to go on.
+    let mut da_state = DaTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    da_state
+        .handle(Arc::new(DaVoteRecv(last_vote.clone())), sender)
+        .await;
+    let event = receiver.try_recv().unwrap();
+    assert!(matches!(
+        event.as_ref(),
+        DacSend(cert, key) if cert.view_number == view && *key == handle.public_key()
+    ));
+    assert!(handle
+        .storage()
+        .read()
+        .await
+        .load_da_votes(view)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+/// A DA proposal of the payload we submitted ourselves is voted on with the payload commitment
+/// computed when we dispersed it, rather than recomputing it.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_da_task_reuses_submitted_payload_commitment() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let transactions = vec![TestTransaction::new(vec![0])];
+    let encoded_transactions: Arc<[u8]> = Arc::from(TestTransaction::encode(&transactions));
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    generator.next().await;
+    generator.add_transactions(transactions);
+    let view = generator.next().await.unwrap();
+    let proposal = view.da_proposal.clone();
+    let leader = view.leader_public_key;
+
+    // Record the payload as submitted with a commitment recomputing it could never produce, so
+    // the vote shows which one was used.
+    let (submitted_commit, _) =
+        precompute_vid_commitment(&[1, 2, 3], quorum_membership.total_nodes());
+    handle
+        .hotshot
+        .consensus()
+        .write()
+        .await
+        .update_submitted_payloads(ViewNumber::new(2), encoded_transactions, submitted_commit);
+    let vote = view.create_da_vote(
+        DaData {
+            payload_commit: submitted_commit,
+        },
+        &handle,
+    );
+
+    let inputs = vec![serial![
+        ViewChange(ViewNumber::new(1)),
+        ViewChange(ViewNumber::new(2)),
+        DaProposalRecv(proposal.clone(), leader),
+    ]];
+
+    let da_state = DaTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let mut da_script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state: da_state,
+        expectations: vec![Expectations::from_outputs(vec![
+            exact(DaProposalValidated(proposal.clone(), leader)),
+            exact(DaVoteSend(vote)),
+        ])],
+    };
+
+    run_test![inputs, da_script].await;
+}
+
+/// The DA proposals reported to builders carry the priorities their transactions were submitted to
+/// us with, for the transactions we received.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_da_proposal_reports_transaction_priorities() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let mut events = handle.event_stream_known_impl();
+
+    let submitted = TestTransaction::new(vec![1]);
+    let mut generator = TestViewGenerator::generate(
+        handle.hotshot.memberships.quorum_membership.clone(),
+        handle.hotshot.memberships.da_membership.clone(),
+    );
+    generator.next().await;
+    generator.add_transactions(vec![submitted.clone(), TestTransaction::new(vec![2])]);
+    let view = generator.next().await.unwrap();
+
+    let (sender, _receiver) = async_broadcast::broadcast(16);
+    let mut da_state = DaTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    da_state
+        .handle(
+            Arc::new(TransactionsRecv(vec![(
+                submitted.clone(),
+                TxPriority::High,
+            )])),
+            sender.clone(),
+        )
+        .await;
+    da_state
+        .handle(
+            Arc::new(DaProposalValidated(
+                view.da_proposal.clone(),
+                view.leader_public_key,
+            )),
+            sender,
+        )
+        .await;
+
+    let priorities = async_timeout(Duration::from_secs(5), async {
+        loop {
+            if let EventType::DaProposal { priorities, .. } =
+                events.recv_direct().await.unwrap().event
+            {
+                return priorities;
+            }
+        }
+    })
+    .await
+    .expect("Timed out waiting for the DA proposal event");
+    assert_eq!(priorities, vec![(submitted.commit(), TxPriority::High)]);
+}
@@ -0,0 +1,62 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_lock::RwLock;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes};
+use hotshot_testing::{
+    overall_safety_task::{
+        OverallSafetyPropertiesDescription, OverallSafetyTask, OverallSafetyTaskErr, RoundCtx,
+    },
+    test_task::{TestResult, TestTaskState},
+};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+/// A safety task requiring `min_views_per_second`, which saw views 1 through 10 decided over
+/// `elapsed`.
+fn safety_task(
+    min_views_per_second: f64,
+    elapsed: Duration,
+) -> OverallSafetyTask<TestTypes, MemoryImpl> {
+    let mut ctx = RoundCtx::default();
+    let start = Instant::now();
+    ctx.decide_times.insert(ViewNumber::new(1), start);
+    ctx.decide_times
+        .insert(ViewNumber::new(10), start + elapsed);
+    OverallSafetyTask {
+        handles: Arc::new(RwLock::new(Vec::new())),
+        ctx,
+        properties: OverallSafetyPropertiesDescription {
+            num_successful_views: 0,
+            min_views_per_second: Some(min_views_per_second),
+            ..Default::default()
+        },
+        error: None,
+        test_sender: async_broadcast::broadcast(1).0,
+    }
+}
+
+/// Decides at or above the required rate pass, while slower ones fail with the observed rate.
+#[test]
+fn test_min_views_per_second() {
+    assert_eq!(
+        safety_task(1.0, Duration::from_secs(3))
+            .ctx
+            .views_per_second(),
+        Some(3.0)
+    );
+    assert!(matches!(
+        safety_task(3.0, Duration::from_secs(3)).check(),
+        TestResult::Pass
+    ));
+
+    let TestResult::Fail(error) = safety_task(4.0, Duration::from_secs(3)).check() else {
+        panic!("Decides slower than required passed");
+    };
+    assert!(matches!(
+        error.downcast_ref::<OverallSafetyTaskErr<TestTypes>>(),
+        Some(OverallSafetyTaskErr::TooSlow { observed, required })
+            if *observed == 3.0 && *required == 4.0
+    ));
+}
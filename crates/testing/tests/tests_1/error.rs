@@ -0,0 +1,92 @@
+use std::{future::pending, num::NonZeroU64, time::Duration};
+
+use async_compatibility_layer::art::async_timeout;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::ViewNumber,
+    error::{HotShotError, RoundTimedoutState},
+    traits::{network::NetworkError, node_implementation::ConsensusTime},
+};
+
+/// Each error's message includes the context an operator needs to act on it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_error_display_includes_context() {
+    let view_number = ViewNumber::new(42);
+    let timeout = async_timeout(Duration::ZERO, pending::<()>())
+        .await
+        .unwrap_err();
+
+    let cases: Vec<(HotShotError<TestTypes>, &[&str])> = vec![
+        (
+            HotShotError::FailedToSerialize {
+                view_number,
+                source: anyhow::anyhow!("message too large"),
+            },
+            &["Failed to serialize message", "42", "message too large"],
+        ),
+        (
+            HotShotError::FailedToMessageLeader {
+                source: NetworkError::ShutDown,
+            },
+            &["message leader", "ShutDown"],
+        ),
+        (
+            HotShotError::FailedToBroadcast {
+                source: NetworkError::NoSuchNode,
+            },
+            &["broadcast", "NoSuchNode"],
+        ),
+        (
+            HotShotError::NetworkBroadcast {
+                view_number,
+                source: NetworkError::ShutDown,
+            },
+            &["broadcast a transaction", "42", "ShutDown"],
+        ),
+        (
+            HotShotError::LeafNotFound {
+                context: "the leaf decided in view 42 is no longer retained".to_string(),
+            },
+            &["Leaf not found", "view 42"],
+        ),
+        (
+            HotShotError::TimeoutError {
+                context: "waiting for view 42 to be decided".to_string(),
+                source: timeout,
+            },
+            &["Timed out waiting for view 42 to be decided"],
+        ),
+        (
+            HotShotError::ViewTimeoutError {
+                view_number,
+                state: RoundTimedoutState::ReplicaWaitingForPrepare,
+            },
+            &["42", "timed out", "ReplicaWaitingForPrepare"],
+        ),
+        (
+            HotShotError::InsufficientValidSignatures {
+                num_valid_signatures: 3,
+                threshold: NonZeroU64::new(7).unwrap(),
+            },
+            &["threshold is 7", "only 3"],
+        ),
+        (
+            HotShotError::Misc {
+                context: "Event stream closed before view 42 was decided".to_string(),
+            },
+            &["Event stream closed before view 42 was decided"],
+        ),
+    ];
+
+    for (error, expected) in cases {
+        let message = error.to_string();
+        for context in expected {
+            assert!(
+                message.contains(context),
+                "{message:?} doesn't contain {context:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::{collections::HashMap, sync::Arc};
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::{node_types::TestTypes, state_types::TestValidatedState};
+use hotshot_testing::{
+    helpers::build_system_handle,
+    overall_safety_task::{OverallSafetyTaskErr, RoundResult, ViewStatus},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{data::Leaf, event::LeafInfo, traits::election::Membership};
+
+/// When a node decides a different leaf than the others, the failure reports which nodes decided
+/// each leaf.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_mismatched_leaf_is_reported() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let leaves: Vec<Leaf<TestTypes>> = (&mut generator)
+        .take(2)
+        .map(|view| view.leaf)
+        .collect()
+        .await;
+
+    // Nodes 0 and 1 decide the first leaf, while node 2 forks onto the second.
+    let mut result = RoundResult::default();
+    for (id, leaf) in [(0, &leaves[0]), (2, &leaves[1]), (1, &leaves[0])] {
+        let leaf_chain = vec![LeafInfo::new(
+            leaf.clone(),
+            Arc::new(TestValidatedState::default()),
+            None,
+            None,
+            Some(quorum_membership.leader(leaf.view_number())),
+        )];
+        result.insert_into_result(id, (leaf_chain, leaf.justify_qc()), None);
+    }
+    result.update_status(2, 3, &leaves[0], true, false, 0, None);
+
+    let ViewStatus::Err(OverallSafetyTaskErr::MismatchedLeaf {
+        view,
+        leaves: report,
+    }) = result.status
+    else {
+        panic!("Forked leaves weren't reported");
+    };
+    assert_eq!(view, leaves[0].view_number());
+    assert_eq!(
+        report,
+        HashMap::from([
+            (leaves[0].commit(), vec![0, 1]),
+            (leaves[1].commit(), vec![2]),
+        ])
+    );
+}
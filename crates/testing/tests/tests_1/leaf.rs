@@ -0,0 +1,40 @@
+use futures::StreamExt;
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::traits::election::Membership;
+
+/// A leaf is only accepted if its header's builder and VID commitments refer to its payload.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_leaf_verify_commitments() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+    let num_storage_nodes = quorum_membership.total_nodes();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let mut leaves = Vec::new();
+    for transactions in [vec![], vec![TestTransaction::new(vec![0])]] {
+        generator.add_transactions(transactions);
+        leaves.push(generator.next().await.unwrap().leaf);
+    }
+
+    // Consistent commitments are accepted.
+    for leaf in &leaves {
+        assert!(leaf.verify_commitments(num_storage_nodes).is_ok());
+    }
+
+    // The builder committed to a different payload than the one that was dispersed.
+    let mut leaf = leaves[1].clone();
+    leaf.block_header_mut().builder_commitment = leaves[0].builder_commitment();
+    assert!(leaf.verify_commitments(num_storage_nodes).is_err());
+
+    // The payload doesn't match either commitment in the header.
+    let mut leaf = leaves[1].clone();
+    leaf.fill_block_payload_unchecked(leaves[0].block_payload().unwrap());
+    assert!(leaf.verify_commitments(num_storage_nodes).is_err());
+}
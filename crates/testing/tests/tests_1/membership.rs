@@ -0,0 +1,197 @@
+use std::collections::{BTreeSet, HashSet};
+
+use hotshot::{
+    traits::election::static_committee::StaticCommittee,
+    types::{BLSPubKey, SignatureKey},
+};
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::StakeTableEntryType,
+    },
+    ValidatorConfig,
+};
+
+/// A zero-stake committee member is an observer: it's sent consensus messages, but never leads,
+/// votes or changes any threshold.
+#[test]
+fn test_observer_membership() {
+    let staked_nodes: Vec<_> = (0..4)
+        .map(|index| {
+            ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], index, 1, false)
+                .public_config()
+        })
+        .collect();
+    let observer =
+        ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], 4, 0, false);
+    let mut all_nodes = staked_nodes.clone();
+    all_nodes.push(observer.public_config());
+
+    let without_observer =
+        <TestTypes as NodeType>::Membership::create_election(staked_nodes.clone(), staked_nodes, 0);
+    let with_observer =
+        <TestTypes as NodeType>::Membership::create_election(all_nodes.clone(), all_nodes, 0);
+
+    let view = ViewNumber::new(1);
+    assert!(with_observer.is_observer(&observer.public_key));
+    assert!(!with_observer.has_stake(&observer.public_key));
+    assert_eq!(
+        with_observer.observers(view),
+        BTreeSet::from([observer.public_key])
+    );
+    assert!(with_observer
+        .whole_committee(view)
+        .contains(&observer.public_key));
+    for key in with_observer.staked_committee(view) {
+        assert!(!with_observer.is_observer(&key));
+    }
+    for view in 0..10 {
+        assert_ne!(
+            with_observer.leader(ViewNumber::new(view)),
+            observer.public_key
+        );
+    }
+
+    assert_eq!(with_observer.total_nodes(), without_observer.total_nodes());
+    assert_eq!(
+        with_observer.success_threshold(),
+        without_observer.success_threshold()
+    );
+    assert_eq!(
+        with_observer.failure_threshold(),
+        without_observer.failure_threshold()
+    );
+    assert_eq!(
+        with_observer.upgrade_threshold(),
+        without_observer.upgrade_threshold()
+    );
+}
+
+/// Leaders are picked in proportion to their stake and thresholds count stake rather than nodes,
+/// while nodes with equal stake still take turns leading.
+#[test]
+fn test_stake_weighted_membership() {
+    // The last node holds as much stake as the other three together.
+    let nodes: Vec<_> = [1, 1, 1, 3]
+        .into_iter()
+        .zip(0..)
+        .map(|(stake, index)| {
+            ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed(
+                [0u8; 32], index, stake, false,
+            )
+            .public_config()
+        })
+        .collect();
+    let keys: Vec<_> = nodes
+        .iter()
+        .map(|node| BLSPubKey::public_key(&node.stake_table_entry))
+        .collect();
+    let membership = <TestTypes as NodeType>::Membership::create_election(nodes.clone(), nodes, 0);
+
+    assert_eq!(membership.total_nodes(), 4);
+    assert_eq!(membership.success_threshold().get(), 5);
+    assert_eq!(membership.failure_threshold().get(), 3);
+    assert_eq!(membership.upgrade_threshold().get(), 6);
+    assert!(membership.has_stake(&keys[3]));
+    assert_eq!(membership.stake(&keys[3]).unwrap().stake().as_u64(), 3);
+
+    // The heavy node leads about half of the views, and every node leads some.
+    let leaders: Vec<_> = (0..1000)
+        .map(|view| membership.leader(ViewNumber::new(view)))
+        .collect();
+    let heavy_views = leaders.iter().filter(|leader| **leader == keys[3]).count();
+    assert!((400..600).contains(&heavy_views), "{heavy_views}");
+    for key in &keys {
+        assert!(leaders.contains(key));
+    }
+
+    let equal = StaticCommittee::<TestTypes>::new_equal_stake(&keys, Vec::new(), 0);
+    assert_eq!(equal.success_threshold().get(), 3);
+    for (view, key) in (0..8).zip(keys.iter().cycle()) {
+        assert_eq!(equal.leader(ViewNumber::new(view)), *key);
+    }
+}
+
+/// Thresholds saturate rather than panic when the committee's stake doesn't fit in a `u64`.
+#[test]
+fn test_stake_threshold_saturates() {
+    let keys: Vec<_> = (0..2)
+        .map(|index| BLSPubKey::generated_from_seed_indexed([0u8; 32], index).0)
+        .collect();
+    let entries = keys
+        .iter()
+        .map(|key| key.stake_table_entry(u64::MAX))
+        .collect();
+    let membership = StaticCommittee::<TestTypes>::new(&keys, entries, Vec::new(), 0);
+
+    assert_eq!(membership.success_threshold().get(), u64::MAX);
+    assert_eq!(membership.upgrade_threshold().get(), u64::MAX);
+    assert_eq!(membership.failure_threshold().get(), u64::MAX / 3 * 2 + 1);
+}
+
+/// A DA committee spans as many distinct groups as it can, and every node selects the same one.
+#[test]
+fn test_diverse_da_committee() {
+    // Groups of 6, 3, 2 and 1 nodes.
+    let group_sizes = [6, 3, 2, 1];
+    let nodes: Vec<_> = (0..)
+        .take(group_sizes.iter().sum())
+        .map(|index| {
+            ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], index, 1, false)
+                .public_config()
+        })
+        .collect();
+    let groups: Vec<_> = group_sizes
+        .iter()
+        .enumerate()
+        .flat_map(|(group, size)| std::iter::repeat(format!("dc-{group}")).take(*size))
+        .zip(&nodes)
+        .map(|(group, node)| (BLSPubKey::public_key(&node.stake_table_entry), group))
+        .collect();
+    let memberships: Vec<_> = (0..2)
+        .map(|_| {
+            <TestTypes as NodeType>::Membership::create_election(nodes.clone(), nodes.clone(), 0)
+                .with_node_groups(groups.clone())
+        })
+        .collect();
+
+    let mut committees = HashSet::new();
+    for view in (0..20).map(ViewNumber::new) {
+        for size in [2, 4, 6] {
+            let committee = memberships[0].diverse_committee(view, size);
+            assert_eq!(committee.len(), size);
+            let distinct_groups: HashSet<_> = committee
+                .iter()
+                .map(|key| memberships[0].node_group(key).unwrap())
+                .collect();
+            assert_eq!(distinct_groups.len(), size.min(group_sizes.len()));
+            assert_eq!(committee, memberships[1].diverse_committee(view, size));
+            committees.insert(committee);
+        }
+    }
+    // The committee rotates across views.
+    assert!(committees.len() > 3);
+
+    // A DA membership chosen out of all the nodes spans more than one group, rather than taking
+    // the first nodes, which all share a data center.
+    let da_memberships: Vec<_> = memberships
+        .into_iter()
+        .map(|membership| membership.with_diverse_committee(4))
+        .collect();
+    let view = ViewNumber::new(1);
+    let da_committee = da_memberships[0].staked_committee(view);
+    assert_eq!(da_committee.len(), 4);
+    assert_eq!(da_memberships[0].total_nodes(), 4);
+    let distinct_groups: HashSet<_> = da_committee
+        .iter()
+        .map(|key| da_memberships[0].node_group(key).unwrap())
+        .collect();
+    assert_eq!(distinct_groups.len(), group_sizes.len());
+    assert_eq!(da_committee, da_memberships[1].staked_committee(view));
+    for (key, _) in &groups {
+        assert_eq!(da_memberships[0].has_stake(key), da_committee.contains(key));
+    }
+}
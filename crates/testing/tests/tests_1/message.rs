@@ -2,9 +2,18 @@
 use std::marker::PhantomData;
 
 use committable::Committable;
-use hotshot_example_types::node_types::TestTypes;
+use futures::StreamExt;
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use hotshot_testing::{
+    helpers::{build_system_handle, key_pair_for_id},
+    view_generator::TestViewGenerator,
+};
 use hotshot_types::{
-    message::{GeneralConsensusMessage, Message, MessageKind, SequencingMessage},
+    data::{Leaf, QuorumProposal, ViewNumber},
+    message::{
+        DataMessage, EquivocationProof, GeneralConsensusMessage, LenientMessage, Message,
+        MessageKind, Proposal, SequencingMessage, VersionedMessage,
+    },
     signature_key::BLSPubKey,
     simple_certificate::SimpleCertificate,
     simple_vote::ViewSyncCommitData,
@@ -56,3 +65,109 @@ fn version_number_at_start_of_serialization() {
     assert_eq!(version.major, version_read.major);
     assert_eq!(version.minor, version_read.minor);
 }
+
+/// Signs `proposal` with the key of node `signer`.
+fn sign_proposal(
+    proposal: QuorumProposal<TestTypes>,
+    signer: u64,
+) -> Proposal<TestTypes, QuorumProposal<TestTypes>> {
+    let (private_key, _) = key_pair_for_id(signer);
+    let signature = BLSPubKey::sign(
+        &private_key,
+        Leaf::from_quorum_proposal(&proposal).commit().as_ref(),
+    )
+    .unwrap();
+    Proposal {
+        data: proposal,
+        signature,
+        _pd: PhantomData,
+    }
+}
+
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_equivocation_proof() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    generator.next().await.unwrap();
+    let view = generator.next().await.unwrap();
+    let next_view = generator.next().await.unwrap();
+    let leader = *view.view_number;
+
+    let honest = view.quorum_proposal.clone();
+    let mut conflicting = honest.data.clone();
+    conflicting.block_header.block_number += 1;
+
+    // Two different leaves for the same view, both signed by the leader.
+    let proof = EquivocationProof {
+        first: honest.clone(),
+        second: sign_proposal(conflicting.clone(), leader),
+    };
+    assert!(proof.verify(&quorum_membership));
+
+    // The same leaf twice is not an equivocation.
+    let proof = EquivocationProof {
+        first: honest.clone(),
+        second: honest.clone(),
+    };
+    assert!(!proof.verify(&quorum_membership));
+
+    // The conflicting proposal must be signed by the leader of the view.
+    let proof = EquivocationProof {
+        first: honest.clone(),
+        second: sign_proposal(conflicting, leader + 1),
+    };
+    assert!(!proof.verify(&quorum_membership));
+
+    // Proposals for different views don't conflict.
+    let proof = EquivocationProof {
+        first: honest,
+        second: next_view.quorum_proposal,
+    };
+    assert!(!proof.verify(&quorum_membership));
+}
+
+/// A message a version ahead of us is rejected by `deserialize`, but decoded under the base
+/// version by `deserialize_lenient` and marked stale. One of an older unsupported version is
+/// rejected by both.
+#[test]
+fn test_deserialize_lenient() {
+    let sender = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0).0;
+    let message = Message::<TestTypes> {
+        sender,
+        kind: MessageKind::Data(DataMessage::SubmitTransaction(
+            TestTransaction::new(vec![1, 2, 3]),
+            ViewNumber::new(1),
+        )),
+    };
+
+    let current = message.serialize(&None).unwrap();
+    assert_eq!(
+        Message::deserialize_lenient(&current, &None).unwrap(),
+        LenientMessage {
+            message: message.clone(),
+            stale_version: false,
+        }
+    );
+
+    let newer = Serializer::<StaticVersion<0, 3>>::serialize(&message).unwrap();
+    let strict: anyhow::Result<Message<TestTypes>> = VersionedMessage::deserialize(&newer, &None);
+    assert!(strict.is_err());
+    assert_eq!(
+        Message::deserialize_lenient(&newer, &None).unwrap(),
+        LenientMessage {
+            message: message.clone(),
+            stale_version: true,
+        }
+    );
+
+    let older = Serializer::<StaticVersion<0, 0>>::serialize(&message).unwrap();
+    assert!(Message::<TestTypes>::deserialize_lenient(&older, &None).is_err());
+}
@@ -0,0 +1,173 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use hotshot::traits::implementations::Libp2pMetricsValue;
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::traits::metrics::NoMetrics;
+
+/// Whether `name` is a valid Prometheus metric or label name.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Parses a label set, without its braces, into label names and values.
+fn parse_labels(mut labels: &str) -> Vec<(String, String)> {
+    let mut parsed = Vec::new();
+    while !labels.is_empty() {
+        let (name, rest) = labels.split_once("=\"").expect("Label without a value");
+        assert!(is_valid_name(name), "Invalid label name {name:?}");
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next().expect("Unterminated label value") {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next().expect("Unterminated escape").1 {
+                    'n' => value.push('\n'),
+                    c @ ('\\' | '"') => value.push(c),
+                    c => panic!("Invalid escape \\{c}"),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        parsed.push((name.to_string(), value));
+        labels = &rest[end + 1..];
+        labels = labels.strip_prefix(',').unwrap_or(labels);
+    }
+    parsed
+}
+
+/// Parses `exported` as the Prometheus text exposition format, returning the value of each sample
+/// keyed by its name and labels. Panics if the format is invalid.
+fn parse_exposition(exported: &str) -> BTreeMap<(String, Vec<(String, String)>), f64> {
+    let mut types = BTreeMap::new();
+    let mut samples = BTreeMap::new();
+    for line in exported.lines() {
+        if let Some(comment) = line.strip_prefix("# ") {
+            let mut parts = comment.splitn(3, ' ');
+            let (keyword, name) = (parts.next().unwrap(), parts.next().unwrap());
+            assert!(is_valid_name(name), "Invalid metric name {name:?}");
+            if keyword == "TYPE" {
+                let kind = parts.next().unwrap();
+                assert!(
+                    ["counter", "gauge", "summary", "histogram", "untyped"].contains(&kind),
+                    "Invalid metric type {kind:?}"
+                );
+                assert!(
+                    types.insert(name.to_string(), kind).is_none(),
+                    "Duplicate type for {name}"
+                );
+            }
+            continue;
+        }
+
+        let (series, value) = line.rsplit_once(' ').expect("Sample without a value");
+        let value = match value {
+            "+Inf" => f64::INFINITY,
+            "-Inf" => f64::NEG_INFINITY,
+            value => value.parse().expect("Invalid sample value"),
+        };
+        let (name, labels) = match series.split_once('{') {
+            Some((name, labels)) => (
+                name,
+                parse_labels(labels.strip_suffix('}').expect("Unterminated label set")),
+            ),
+            None => (series, Vec::new()),
+        };
+        assert!(is_valid_name(name), "Invalid metric name {name:?}");
+
+        // Every sample belongs to a metric whose type was declared before it.
+        if !types.contains_key(name) {
+            let (metric, kinds) = match name.strip_suffix("_bucket") {
+                Some(metric) => (metric, &["histogram"][..]),
+                None => (
+                    name.strip_suffix("_sum")
+                        .or_else(|| name.strip_suffix("_count"))
+                        .unwrap_or(name),
+                    &["summary", "histogram"][..],
+                ),
+            };
+            assert!(
+                types.get(metric).is_some_and(|kind| kinds.contains(kind)),
+                "Sample {name} has no declared type"
+            );
+        }
+
+        assert!(
+            samples.insert((name.to_string(), labels), value).is_none(),
+            "Duplicate sample {series}"
+        );
+    }
+    samples
+}
+
+/// The exported metrics include every consensus metric and any network metrics registered with
+/// them, in valid Prometheus text format.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_export_metrics_prometheus() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let metrics = Arc::clone(&handle.consensus().read().await.metrics);
+    let libp2p_metrics = Libp2pMetricsValue::new(&*metrics.exporter.tee(&NoMetrics));
+
+    metrics.view_duration_as_leader.add_point(1.5);
+    metrics.view_duration_as_leader.add_point(2.0);
+    metrics
+        .inbound_messages
+        .create(vec!["test \"quoted\"".to_string()])
+        .add(3);
+    libp2p_metrics.num_connected_peers.set(4);
+    libp2p_metrics.num_failed_messages.add(2);
+
+    let exported = handle.export_metrics_prometheus();
+    for name in [
+        "current_view",
+        "last_decided_view",
+        "number_of_timeouts",
+        "outstanding_transactions",
+        "view_duration_as_leader",
+        "inbound_messages",
+        "libp2p_num_connected_peers",
+    ] {
+        assert!(
+            exported.contains(&format!("# TYPE {name} ")),
+            "{name} is missing from:\n{exported}"
+        );
+    }
+
+    let samples = parse_exposition(&exported);
+    let sample = |name: &str, labels: &[(&str, &str)]| {
+        let labels: Vec<(String, String)> = labels
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        samples[&(name.to_string(), labels)]
+    };
+    assert!(exported.contains("# TYPE view_duration_as_leader histogram\n"));
+    assert_eq!(sample("view_duration_as_leader_count", &[]), 2.0);
+    assert_eq!(sample("view_duration_as_leader_sum", &[]), 3.5);
+    assert_eq!(
+        sample("view_duration_as_leader_bucket", &[("le", "1")]),
+        0.0
+    );
+    assert_eq!(
+        sample("view_duration_as_leader_bucket", &[("le", "2.5")]),
+        2.0
+    );
+    assert_eq!(
+        sample("view_duration_as_leader_bucket", &[("le", "+Inf")]),
+        2.0
+    );
+    assert_eq!(
+        sample("inbound_messages", &[("purpose", "test \"quoted\"")]),
+        3.0
+    );
+    assert_eq!(sample("libp2p_num_connected_peers", &[]), 4.0);
+    assert_eq!(sample("libp2p_num_failed_messages", &[]), 2.0);
+}
@@ -1,25 +1,62 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use async_broadcast::Sender;
-use async_compatibility_layer::art::async_timeout;
+use async_compatibility_layer::art::{async_sleep, async_timeout};
 use async_lock::RwLock;
-use hotshot::traits::implementations::MemoryNetwork;
-use hotshot_example_types::node_types::{MemoryImpl, TestTypes};
-use hotshot_task::task::{ConsensusTaskRegistry, Task};
+use async_trait::async_trait;
+use committable::Committable;
+use futures::{channel::mpsc, SinkExt};
+use hotshot::{
+    tasks::add_network_change_task,
+    traits::implementations::MemoryNetwork,
+    types::{BLSPubKey, EventType},
+};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes},
+    storage_types::TestStorage,
+};
+use hotshot_task::{
+    cancellation::CancellationToken,
+    task::{ConsensusTaskRegistry, Task, TaskState},
+};
 use hotshot_task_impls::{
     events::HotShotEvent,
-    network::{self, NetworkEventTaskState},
+    network::{self, NetworkEventTaskState, NetworkMessageTaskState},
 };
 use hotshot_testing::{
-    test_builder::TestDescription, test_task::add_network_message_test_task,
+    helpers::{build_system_handle, build_upgrade_certificate, key_pair_for_id},
+    test_builder::TestDescription,
+    test_task::add_network_message_test_task,
     view_generator::TestViewGenerator,
 };
 use hotshot_types::{
+    boxed_sync,
+    consensus::ConsensusMetricsValue,
     data::ViewNumber,
+    message::{
+        DataMessage, GeneralConsensusMessage, Message, MessageKind, SequencingMessage,
+        TransactionSubmission, TxPriority,
+    },
+    simple_vote::{
+        QuorumData, QuorumVote, TimeoutData, TimeoutVote, ViewSyncCommitData, ViewSyncCommitVote,
+    },
     traits::{
         election::Membership,
+        metrics::{
+            Counter, CounterFamily, Gauge, GaugeFamily, Histogram, HistogramFamily, Metrics,
+            MetricsFamily, NoMetrics, TextFamily,
+        },
+        network::{BroadcastDelay, ConnectedNetwork, NetworkChange, NetworkError},
         node_implementation::{ConsensusTime, NodeType},
+        storage::Storage,
     },
+    transaction_pow::{is_valid_pow, solve_pow},
+    BoxSyncFuture,
 };
 
 // Test that the event task sends a message, and the message task receives it
@@ -59,12 +96,14 @@ async fn test_network_task() {
             membership: membership.clone(),
             filter: network::quorum_filter,
             decided_upgrade_certificate: None,
+            broadcast_timeout: Duration::from_secs(1),
+            parent_da_certificates: BTreeMap::new(),
             storage,
         };
     let (tx, rx) = async_broadcast::broadcast(10);
     let mut task_reg = ConsensusTaskRegistry::new();
 
-    let task = Task::new(network_state, tx.clone(), rx);
+    let task = Task::new(network_state, tx.clone(), rx, CancellationToken::new());
     task_reg.run_task(task);
 
     let mut generator = TestViewGenerator::generate(membership.clone(), membership);
@@ -124,12 +163,14 @@ async fn test_network_storage_fail() {
             membership: membership.clone(),
             filter: network::quorum_filter,
             decided_upgrade_certificate: None,
+            broadcast_timeout: Duration::from_secs(1),
+            parent_da_certificates: BTreeMap::new(),
             storage,
         };
     let (tx, rx) = async_broadcast::broadcast(10);
     let mut task_reg = ConsensusTaskRegistry::new();
 
-    let task = Task::new(network_state, tx.clone(), rx);
+    let task = Task::new(network_state, tx.clone(), rx, CancellationToken::new());
     task_reg.run_task(task);
 
     let mut generator = TestViewGenerator::generate(membership.clone(), membership);
@@ -147,3 +188,539 @@ async fn test_network_storage_fail() {
     let res = async_timeout(Duration::from_millis(100), out_rx.recv_direct()).await;
     assert!(res.is_err());
 }
+
+/// Metrics which only record counters, keyed by their name and labels.
+#[derive(Clone, Debug, Default)]
+struct CountingMetrics {
+    /// Name of the metric, including the labels of counter families
+    prefix: String,
+    /// Shared counter values
+    counters: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl CountingMetrics {
+    fn sub(&self, name: String) -> Self {
+        let prefix = if self.prefix.is_empty() {
+            name
+        } else {
+            format!("{}-{name}", self.prefix)
+        };
+        Self {
+            prefix,
+            counters: Arc::clone(&self.counters),
+        }
+    }
+
+    fn count(&self, name: &str) -> usize {
+        self.counters
+            .lock()
+            .unwrap()
+            .get(name)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl Metrics for CountingMetrics {
+    fn create_counter(&self, name: String, _: Option<String>) -> Box<dyn Counter> {
+        Box::new(self.sub(name))
+    }
+
+    fn create_gauge(&self, _: String, _: Option<String>) -> Box<dyn Gauge> {
+        Box::new(NoMetrics)
+    }
+
+    fn create_histogram(&self, _: String, _: Option<String>) -> Box<dyn Histogram> {
+        Box::new(NoMetrics)
+    }
+
+    fn create_text(&self, _: String) {}
+
+    fn counter_family(&self, name: String, _: Vec<String>) -> Box<dyn CounterFamily> {
+        Box::new(self.sub(name))
+    }
+
+    fn gauge_family(&self, _: String, _: Vec<String>) -> Box<dyn GaugeFamily> {
+        Box::new(NoMetrics)
+    }
+
+    fn histogram_family(&self, _: String, _: Vec<String>) -> Box<dyn HistogramFamily> {
+        Box::new(NoMetrics)
+    }
+
+    fn text_family(&self, _: String, _: Vec<String>) -> Box<dyn TextFamily> {
+        Box::new(NoMetrics)
+    }
+
+    fn subgroup(&self, subgroup_name: String) -> Box<dyn Metrics> {
+        Box::new(self.sub(subgroup_name))
+    }
+}
+
+impl Counter for CountingMetrics {
+    fn add(&self, amount: usize) {
+        *self
+            .counters
+            .lock()
+            .unwrap()
+            .entry(self.prefix.clone())
+            .or_default() += amount;
+    }
+}
+
+impl MetricsFamily<Box<dyn Counter>> for CountingMetrics {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Counter> {
+        Box::new(self.sub(labels.join("-")))
+    }
+}
+
+/// Every inbound message is counted exactly once under its `MessagePurpose`.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_network_message_purpose_metrics() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let metrics = CountingMetrics::default();
+    let (tx, _rx) = async_broadcast::broadcast(10);
+    let mut state = NetworkMessageTaskState::<TestTypes> {
+        event_stream: tx,
+        metrics: Arc::new(ConsensusMetricsValue::new(&metrics)),
+        transaction_pow_difficulty: 0,
+        decided_upgrade_certificate: None,
+    };
+
+    let (private_key, sender) = key_pair_for_id(0);
+    let view = ViewNumber::new(1);
+    let mut messages = Vec::new();
+    for i in 0..3 {
+        messages.push(MessageKind::Data(DataMessage::SubmitTransaction(
+            TestTransaction::new(vec![i]),
+            view,
+        )));
+    }
+    for i in 0..2 {
+        let vote = TimeoutVote::create_signed_vote(
+            TimeoutData { view: view + i },
+            view + i,
+            &sender,
+            &private_key,
+        )
+        .unwrap();
+        messages.push(MessageKind::Consensus(SequencingMessage::General(
+            GeneralConsensusMessage::TimeoutVote(vote),
+        )));
+    }
+    let vote = ViewSyncCommitVote::create_signed_vote(
+        ViewSyncCommitData {
+            relay: 0,
+            round: view,
+        },
+        view,
+        &sender,
+        &private_key,
+    )
+    .unwrap();
+    messages.push(MessageKind::Consensus(SequencingMessage::General(
+        GeneralConsensusMessage::ViewSyncCommitVote(vote),
+    )));
+
+    state
+        .handle_messages(
+            messages
+                .into_iter()
+                .map(|kind| Message { sender, kind })
+                .collect(),
+        )
+        .await;
+
+    assert_eq!(metrics.count("inbound_messages-Data"), 3);
+    assert_eq!(metrics.count("inbound_messages-Vote"), 2);
+    assert_eq!(metrics.count("inbound_messages-ViewSyncVote"), 1);
+    assert_eq!(metrics.count("inbound_messages-Proposal"), 0);
+}
+
+/// With a proof of work difficulty set, once the network has upgraded a transaction with a valid
+/// proof of work is accepted, and one with an insufficient proof of work or none at all is
+/// dropped and counted. Before the upgrade, transactions without a proof of work are accepted,
+/// with the normal priority since they don't carry one.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_transaction_pow() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    const DIFFICULTY: u32 = 12;
+
+    let upgrade_view = ViewNumber::new(5);
+    let metrics = CountingMetrics::default();
+    let (tx, mut rx) = async_broadcast::broadcast(10);
+    let mut state = NetworkMessageTaskState::<TestTypes> {
+        event_stream: tx,
+        metrics: Arc::new(ConsensusMetricsValue::new(&metrics)),
+        transaction_pow_difficulty: DIFFICULTY,
+        decided_upgrade_certificate: Some(build_upgrade_certificate(upgrade_view)),
+    };
+
+    let sender = key_pair_for_id(0).1;
+    let legacy = TestTransaction::new(vec![0]);
+    let valid = TestTransaction::new(vec![1]);
+    let valid_nonce = solve_pow(&valid, DIFFICULTY).unwrap();
+    assert!(is_valid_pow(&valid, valid_nonce, DIFFICULTY));
+    let invalid = TestTransaction::new(vec![2]);
+    let invalid_nonce = (0..)
+        .find(|&nonce| !is_valid_pow(&invalid, nonce, DIFFICULTY))
+        .unwrap();
+    // Every nonce is valid without a proof of work.
+    assert!(is_valid_pow(&invalid, invalid_nonce, 0));
+
+    let mut messages = vec![
+        DataMessage::SubmitTransaction(legacy.clone(), upgrade_view - 1),
+        DataMessage::SubmitTransaction(legacy.clone(), upgrade_view),
+    ];
+    for (transaction, nonce) in [(valid.clone(), valid_nonce), (invalid, invalid_nonce)] {
+        messages.push(DataMessage::SubmitTransactionWithPow(TransactionSubmission {
+            transaction,
+            view: upgrade_view,
+            nonce,
+            priority: TxPriority::High,
+        }));
+    }
+    state
+        .handle_messages(
+            messages
+                .into_iter()
+                .map(|message| Message {
+                    sender,
+                    kind: MessageKind::Data(message),
+                })
+                .collect(),
+        )
+        .await;
+
+    assert!(matches!(
+        rx.try_recv().unwrap().as_ref(),
+        HotShotEvent::TransactionsRecv(transactions)
+            if *transactions == vec![(legacy, TxPriority::Normal), (valid, TxPriority::High)]
+    ));
+    assert!(rx.try_recv().is_err());
+    assert_eq!(metrics.count("invalid_pow_transactions"), 2);
+}
+
+/// A network which reports the connectivity changes sent on a channel, and otherwise does nothing.
+#[derive(Clone)]
+struct TopologyNetwork {
+    /// The connectivity changes to report, taken by the network change task
+    changes: Arc<Mutex<Option<mpsc::Receiver<NetworkChange<BLSPubKey>>>>>,
+}
+
+#[async_trait]
+impl ConnectedNetwork<BLSPubKey> for TopologyNetwork {
+    fn pause(&self) {}
+
+    fn resume(&self) {}
+
+    async fn wait_for_ready(&self) {}
+
+    fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        boxed_sync(async {})
+    }
+
+    async fn broadcast_message(
+        &self,
+        _message: Vec<u8>,
+        _recipients: BTreeSet<BLSPubKey>,
+        _broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        Ok(())
+    }
+
+    async fn da_broadcast_message(
+        &self,
+        _message: Vec<u8>,
+        _recipients: BTreeSet<BLSPubKey>,
+        _broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        Ok(())
+    }
+
+    async fn direct_message(
+        &self,
+        _message: Vec<u8>,
+        _recipient: BLSPubKey,
+    ) -> Result<(), NetworkError> {
+        Ok(())
+    }
+
+    async fn recv_msgs(&self) -> Result<Vec<Vec<u8>>, NetworkError> {
+        Ok(vec![])
+    }
+
+    async fn network_change_receiver(&self) -> Option<mpsc::Receiver<NetworkChange<BLSPubKey>>> {
+        self.changes.lock().unwrap().take()
+    }
+}
+
+/// Connectivity changes reported by the network reach the external event stream, in order.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_network_changes_reach_event_stream() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let mut handle = build_system_handle(1).await.0;
+    let mut events = handle.event_stream_known_impl();
+
+    let (mut change_sender, change_receiver) = mpsc::channel(2);
+    let network = TopologyNetwork {
+        changes: Arc::new(Mutex::new(Some(change_receiver))),
+    };
+    add_network_change_task(&mut handle, Arc::new(network)).await;
+
+    let peer = key_pair_for_id(2).1;
+    let changes = vec![
+        NetworkChange::NodeConnected(peer),
+        NetworkChange::NodeDisconnected(peer),
+    ];
+    for change in changes.clone() {
+        change_sender.send(change).await.unwrap();
+    }
+
+    let received = async_timeout(Duration::from_secs(2), async {
+        let mut received = Vec::new();
+        while received.len() < changes.len() {
+            if let EventType::ConnectionChange { change } =
+                events.recv_direct().await.unwrap().event
+            {
+                received.push(change);
+            }
+        }
+        received
+    })
+    .await
+    .expect("Timed out waiting for the connectivity changes");
+
+    assert_eq!(received, changes);
+}
+
+/// A network which delivers direct messages to the `reachable` nodes, and never finishes sending
+/// to any other node, as if we crashed partway through sending.
+#[derive(Clone)]
+struct CrashingNetwork {
+    /// The nodes messages are delivered to
+    reachable: HashSet<BLSPubKey>,
+    /// The recipients of the messages delivered so far
+    sent: Arc<Mutex<Vec<BLSPubKey>>>,
+}
+
+#[async_trait]
+impl ConnectedNetwork<BLSPubKey> for CrashingNetwork {
+    fn pause(&self) {}
+
+    fn resume(&self) {}
+
+    async fn wait_for_ready(&self) {}
+
+    fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        boxed_sync(async {})
+    }
+
+    async fn broadcast_message(
+        &self,
+        _message: Vec<u8>,
+        _recipients: BTreeSet<BLSPubKey>,
+        _broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        Ok(())
+    }
+
+    async fn da_broadcast_message(
+        &self,
+        _message: Vec<u8>,
+        _recipients: BTreeSet<BLSPubKey>,
+        _broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        Ok(())
+    }
+
+    async fn direct_message(
+        &self,
+        _message: Vec<u8>,
+        recipient: BLSPubKey,
+    ) -> Result<(), NetworkError> {
+        if !self.reachable.contains(&recipient) {
+            futures::future::pending::<()>().await;
+        }
+        self.sent.lock().unwrap().push(recipient);
+        Ok(())
+    }
+
+    async fn recv_msgs(&self) -> Result<Vec<Vec<u8>>, NetworkError> {
+        Ok(vec![])
+    }
+}
+
+/// After crashing with half of a VID dispersal sent, the remaining shares are sent on restart, and
+/// every node receives its share exactly once.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_vid_dispersal_resumes_after_crash() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let membership = handle.hotshot.memberships.quorum_membership.clone();
+    let mut generator = TestViewGenerator::generate(membership.clone(), membership.clone());
+    let view = generator.next().await.unwrap();
+    let recipients: Vec<_> = view.vid_disperse.data.shares.keys().copied().collect();
+    let (sent_before_crash, sent_after_restart) = recipients.split_at(recipients.len() / 2);
+
+    let storage = Arc::new(RwLock::new(TestStorage::<TestTypes>::default()));
+    let crashing_network = CrashingNetwork {
+        reachable: sent_before_crash.iter().copied().collect(),
+        sent: Arc::default(),
+    };
+    let mut state = NetworkEventTaskState {
+        channel: Arc::new(crashing_network.clone()),
+        view: ViewNumber::new(0),
+        membership,
+        filter: network::vid_filter,
+        decided_upgrade_certificate: None,
+        broadcast_timeout: Duration::from_secs(1),
+        parent_da_certificates: BTreeMap::new(),
+        storage: Arc::clone(&storage),
+    };
+    let (sender, receiver) = async_broadcast::broadcast(10);
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::VidDisperseSend(
+                view.vid_disperse.clone(),
+                view.leader_public_key,
+            )),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+
+    // Wait until the shares sent before the crash are recorded as sent.
+    async_timeout(Duration::from_secs(2), async {
+        loop {
+            let pending = storage
+                .read()
+                .await
+                .load_pending_vid_dispersals()
+                .await
+                .unwrap();
+            if pending
+                .get(&view.view_number)
+                .is_some_and(|messages| messages.len() == sent_after_restart.len())
+            {
+                break;
+            }
+            async_sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Timed out waiting for the shares sent before the crash");
+
+    let restarted_network = CrashingNetwork {
+        reachable: recipients.iter().copied().collect(),
+        sent: Arc::default(),
+    };
+    network::resume_vid_dispersals(&restarted_network, &storage).await;
+
+    let mut sent = crashing_network.sent.lock().unwrap().clone();
+    assert_eq!(sent.len(), sent_before_crash.len());
+    let resent = restarted_network.sent.lock().unwrap().clone();
+    assert_eq!(
+        resent.iter().copied().collect::<HashSet<_>>(),
+        sent_after_restart.iter().copied().collect()
+    );
+    sent.extend(resent);
+    sent.sort();
+    assert_eq!(sent, recipients);
+    assert!(storage
+        .read()
+        .await
+        .load_pending_vid_dispersals()
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+/// A quorum vote is only sent once per view: storage refuses to record a second vote in the same
+/// view, and the network task doesn't send a vote it couldn't record.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_network_refuses_double_vote() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let membership = handle.hotshot.memberships.quorum_membership.clone();
+    let mut generator = TestViewGenerator::generate(membership.clone(), membership.clone());
+    let view = generator.next().await.unwrap();
+    let (private_key, public_key) = key_pair_for_id(1);
+    let vote = QuorumVote::<TestTypes>::create_signed_vote(
+        QuorumData {
+            leaf_commit: view.leaf.commit(),
+        },
+        view.view_number,
+        &public_key,
+        &private_key,
+    )
+    .unwrap();
+
+    let network = CrashingNetwork {
+        reachable: membership
+            .whole_committee(view.view_number + 1)
+            .into_iter()
+            .collect(),
+        sent: Arc::default(),
+    };
+    let mut state = NetworkEventTaskState {
+        channel: Arc::new(network.clone()),
+        view: ViewNumber::new(0),
+        membership,
+        filter: network::quorum_filter,
+        decided_upgrade_certificate: None,
+        broadcast_timeout: Duration::from_secs(1),
+        parent_da_certificates: BTreeMap::new(),
+        storage: Arc::new(RwLock::new(TestStorage::<TestTypes>::default())),
+    };
+    let (sender, receiver) = async_broadcast::broadcast(10);
+    for _ in 0..2 {
+        state
+            .handle_event(
+                Arc::new(HotShotEvent::QuorumVoteSend(vote.clone())),
+                &sender,
+                &receiver,
+            )
+            .await
+            .unwrap();
+    }
+
+    async_timeout(Duration::from_secs(2), async {
+        while network.sent.lock().unwrap().is_empty() {
+            async_sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Timed out waiting for the vote to be sent");
+    async_sleep(Duration::from_millis(100)).await;
+    assert_eq!(network.sent.lock().unwrap().len(), 1);
+}
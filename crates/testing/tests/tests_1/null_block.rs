@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use hotshot_types::{
+    data::null_block,
+    traits::block_contents::{precompute_vid_commitment, vid_commitment},
+    vid::vid_scheme,
+};
+use jf_vid::VidScheme;
+
+/// The null block commitment is cached per number of storage nodes, for a bounded number of them,
+/// and is identical to the commitment computed for an empty payload from scratch.
+#[test]
+fn test_null_block_commitment_is_cached() {
+    for num_storage_nodes in [1, 7, 10] {
+        let fresh = vid_scheme(num_storage_nodes)
+            .commit_only(Vec::new())
+            .unwrap();
+
+        let vid = null_block::vid(num_storage_nodes).unwrap();
+        assert_eq!(vid.commitment, fresh);
+        assert_eq!(null_block::commitment(num_storage_nodes), Some(fresh));
+        assert_eq!(vid_commitment(&[], num_storage_nodes), fresh);
+        assert_eq!(precompute_vid_commitment(&[], num_storage_nodes).0, fresh);
+
+        // Later lookups share the value computed by the first one.
+        assert!(Arc::ptr_eq(
+            &vid,
+            &null_block::vid(num_storage_nodes).unwrap()
+        ));
+    }
+
+    // Different numbers of storage nodes are cached separately.
+    assert_ne!(null_block::commitment(7), null_block::commitment(10));
+
+    // Only the most recently used are kept.
+    let evicted = null_block::vid(1).unwrap();
+    for num_storage_nodes in 2..=11 {
+        assert!(null_block::vid(num_storage_nodes).is_some());
+    }
+    assert!(!Arc::ptr_eq(&evicted, &null_block::vid(1).unwrap()));
+}
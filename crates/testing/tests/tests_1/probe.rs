@@ -0,0 +1,128 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use committable::Commitment;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes};
+use hotshot_testing::{
+    helpers::{build_system_handle_from_launcher, build_upgrade_certificate, key_pair_for_id},
+    test_builder::TestDescription,
+};
+use hotshot_types::{
+    data::ViewNumber,
+    message::{DataMessage, Message, MessageKind, VersionedMessage},
+    probe::{PeerProbe, ProbeMessage},
+    traits::{network::ConnectedNetwork, node_implementation::ConsensusTime},
+};
+
+/// A network of three staked nodes
+fn description() -> TestDescription {
+    TestDescription {
+        num_nodes_with_stake: 3,
+        da_staked_committee_size: 3,
+        ..TestDescription::default()
+    }
+}
+
+/// When every node is configured the same, each one reaches and agrees with all of its peers, once
+/// the network has upgraded to the version peers understand probes in.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_probe_passes() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let launcher = description().gen_launcher::<TestTypes, MemoryImpl>(0);
+    let mut handles = Vec::new();
+    for node_id in 0..3 {
+        handles.push(
+            build_system_handle_from_launcher(&launcher, node_id)
+                .await
+                .0,
+        );
+    }
+
+    assert!(handles[0]
+        .hotshot
+        .probe(Duration::from_secs(1))
+        .await
+        .is_err());
+
+    for handle in &handles {
+        handle
+            .decide_upgrade_certificate(build_upgrade_certificate(ViewNumber::genesis()))
+            .await;
+    }
+    for handle in &handles {
+        let report = handle.hotshot.probe(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(report.peers.len(), 2);
+        assert!(report.passed(), "{report:?}");
+    }
+}
+
+/// A peer which computed a different genesis is reported as disagreeing, and a peer which never
+/// came up as unreachable.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_probe_reports_genesis_mismatch() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let launcher = description().gen_launcher::<TestTypes, MemoryImpl>(0);
+    let handle = build_system_handle_from_launcher(&launcher, 0).await.0;
+    let upgrade_certificate = Some(build_upgrade_certificate(ViewNumber::genesis()));
+    handle
+        .decide_upgrade_certificate(upgrade_certificate.clone().unwrap())
+        .await;
+
+    // Node 1 runs on the same network, but answers probes with another genesis.
+    let (_, peer_key) = key_pair_for_id(1);
+    let peer_network = (launcher.resource_generator.channel_generator)(1).await.0;
+    let answer_probe = async {
+        loop {
+            for message in peer_network.recv_msgs().await.unwrap() {
+                let message: Message<TestTypes> =
+                    VersionedMessage::deserialize(&message, &upgrade_certificate).unwrap();
+                let MessageKind::Data(DataMessage::Probe(ProbeMessage::Request(
+                    view,
+                    mut descriptor,
+                ))) = message.kind
+                else {
+                    continue;
+                };
+                descriptor.genesis = Commitment::default_commitment_no_preimage();
+                let reply = Message {
+                    sender: peer_key,
+                    kind: MessageKind::Data(DataMessage::Probe(ProbeMessage::Reply(
+                        view, descriptor,
+                    ))),
+                };
+                peer_network
+                    .direct_message(
+                        reply.serialize(&upgrade_certificate).unwrap(),
+                        message.sender,
+                    )
+                    .await
+                    .unwrap();
+                return;
+            }
+        }
+    };
+    let (report, ()) = futures::join!(handle.hotshot.probe(Duration::from_secs(1)), answer_probe);
+    let report = report.unwrap();
+
+    assert!(!report.passed());
+    assert_eq!(
+        report.peers,
+        BTreeMap::from([
+            (
+                peer_key,
+                PeerProbe::Reached {
+                    genesis_agrees: false,
+                    committee_agrees: true,
+                }
+            ),
+            (key_pair_for_id(2).1, PeerProbe::Unreachable),
+        ])
+    );
+}
@@ -0,0 +1,55 @@
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::state_types::{TestInstanceState, TestValidatedState};
+use hotshot_task_impls::consensus::validate_proposal_chain;
+use hotshot_testing::{
+    helpers::build_system_handle_from_description, test_builder::TestDescription,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::data::Leaf;
+
+/// A run of catch-up proposals validates up to the first one that doesn't extend its predecessor.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_validate_proposal_chain() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    // The view generator signs each view's proposal as the node with the view's index, so we need
+    // a node for every view for it to be signed by the view's leader.
+    let num_nodes = 21;
+    let description = TestDescription {
+        num_nodes_with_stake: num_nodes,
+        start_nodes: num_nodes,
+        num_bootstrap_nodes: num_nodes,
+        da_staked_committee_size: num_nodes,
+        ..TestDescription::default()
+    };
+    let handle = build_system_handle_from_description(description, 1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let views = (&mut generator).take(20).collect::<Vec<_>>().await;
+    let proposals: Vec<_> = views
+        .iter()
+        .map(|view| view.quorum_proposal.clone())
+        .collect();
+    let expected: Vec<_> = views.iter().map(|view| view.leaf.commit()).collect();
+    let genesis = Leaf::genesis(&TestValidatedState::default(), &TestInstanceState {}).await;
+    let commits = |leaves: Vec<Leaf<_>>| leaves.iter().map(Committable::commit).collect::<Vec<_>>();
+
+    // The whole chain is valid.
+    let leaves = validate_proposal_chain(&proposals, &genesis, &quorum_membership).unwrap();
+    assert_eq!(commits(leaves), expected);
+
+    // With a proposal missing from the middle, only the proposals before the gap are valid.
+    let mut broken = proposals.clone();
+    broken.remove(10);
+    let leaves = validate_proposal_chain(&broken, &genesis, &quorum_membership).unwrap();
+    assert_eq!(commits(leaves), expected[..10]);
+
+    // A chain that doesn't extend the starting parent is rejected outright.
+    assert!(validate_proposal_chain(&proposals[1..], &genesis, &quorum_membership).is_err());
+}
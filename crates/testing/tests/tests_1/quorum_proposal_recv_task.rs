@@ -75,6 +75,10 @@ async fn test_quorum_proposal_recv_task() {
 
     let expectations = vec![Expectations::from_outputs(vec![
         exact(ViewChange(ViewNumber::new(2))),
+        exact(HighQcUpdated {
+            from: None,
+            to: ViewNumber::new(1),
+        }),
         exact(UpdateHighQc(proposals[1].data.justify_qc.clone())),
         exact(ValidatedStateUpdated(
             ViewNumber::new(2),
@@ -204,3 +208,405 @@ async fn test_quorum_proposal_recv_task_liveness_check() {
     };
     run_test![inputs, script].await;
 }
+
+/// A leader signing a second, conflicting proposal for a view is caught equivocating, and the
+/// second proposal isn't validated.
+#[cfg(test)]
+#[cfg(feature = "dependency-tasks")]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_recv_task_detects_equivocation() {
+    use std::time::Duration;
+
+    use hotshot_testing::{
+        helpers::build_fake_view_with_leaf,
+        script::{Expectations, TaskScript},
+    };
+    use hotshot_types::{data::Leaf, message::EquivocationProof};
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+    let consensus = handle.hotshot.consensus();
+    let mut consensus_writer = consensus.write().await;
+
+    let mut generator = TestViewGenerator::generate_with_equivocation(
+        quorum_membership.clone(),
+        da_membership,
+        vec![ViewNumber::new(2)],
+    );
+    let views = (&mut generator).take(2).collect::<Vec<_>>().await;
+    for view in &views {
+        consensus_writer
+            .update_saved_leaves(Leaf::from_quorum_proposal(&view.quorum_proposal.data));
+        consensus_writer
+            .update_validated_state_map(
+                view.quorum_proposal.data.view_number,
+                build_fake_view_with_leaf(view.leaf.clone()),
+            )
+            .unwrap();
+    }
+    drop(consensus_writer);
+
+    let honest = views[1].quorum_proposal.clone();
+    let conflicting = views[1].quorum_proposal_conflicting.clone().unwrap();
+    let leader = views[1].leader_public_key;
+
+    let inputs = vec![
+        serial![QuorumProposalRecv(honest.clone(), leader)],
+        serial![QuorumProposalRecv(conflicting.clone(), leader)],
+    ];
+
+    let expectations = vec![
+        Expectations::from_outputs(vec![
+            exact(ViewChange(ViewNumber::new(2))),
+            exact(HighQcUpdated {
+                from: None,
+                to: ViewNumber::new(1),
+            }),
+            exact(UpdateHighQc(honest.data.justify_qc.clone())),
+            exact(ValidatedStateUpdated(
+                ViewNumber::new(2),
+                build_fake_view_with_leaf_and_state(
+                    views[1].leaf.clone(),
+                    <TestValidatedState as ValidatedState<TestTypes>>::from_header(
+                        &honest.data.block_header,
+                    ),
+                ),
+            )),
+            exact(QuorumProposalValidated(
+                honest.data.clone(),
+                views[0].leaf.clone(),
+            )),
+        ]),
+        Expectations::from_outputs(vec![exact(Equivocation(
+            ViewNumber::new(2),
+            leader,
+            EquivocationProof {
+                first: honest,
+                second: conflicting,
+            },
+        ))]),
+    ];
+
+    let state = QuorumProposalRecvTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
+
+/// A view change reaches the external event stream as exactly one `LeaderHandoff`, although both
+/// the task receiving the proposal and the consensus task update their view.
+#[cfg(test)]
+#[cfg(feature = "dependency-tasks")]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_one_leader_handoff_per_view_change() {
+    use std::sync::Arc;
+
+    use hotshot::types::EventType;
+    use hotshot_task::task::TaskState;
+    use hotshot_task_impls::consensus2::Consensus2TaskState;
+    use hotshot_testing::helpers::build_fake_view_with_leaf;
+    use hotshot_types::data::Leaf;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let views = (&mut generator).take(2).collect::<Vec<_>>().await;
+    {
+        let consensus = handle.hotshot.consensus();
+        let mut consensus_writer = consensus.write().await;
+        for view in &views {
+            consensus_writer
+                .update_saved_leaves(Leaf::from_quorum_proposal(&view.quorum_proposal.data));
+            consensus_writer
+                .update_validated_state_map(
+                    view.quorum_proposal.data.view_number,
+                    build_fake_view_with_leaf(view.leaf.clone()),
+                )
+                .unwrap();
+        }
+    }
+
+    let mut events = handle.event_stream_known_impl();
+    let mut recv_state =
+        QuorumProposalRecvTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let mut consensus_state =
+        Consensus2TaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let (sender, mut receiver) = async_broadcast::broadcast(100);
+
+    recv_state
+        .handle_event(
+            Arc::new(QuorumProposalRecv(
+                views[1].quorum_proposal.clone(),
+                views[1].leader_public_key,
+            )),
+            &sender,
+            &receiver.clone(),
+        )
+        .await
+        .unwrap();
+
+    // Hand the view change on to the consensus task, as the task registry would.
+    let mut view_changes = 0;
+    while let Ok(event) = receiver.try_recv() {
+        if let ViewChange(_) = event.as_ref() {
+            view_changes += 1;
+            consensus_state
+                .handle_event(event, &sender, &receiver.clone())
+                .await
+                .unwrap();
+        }
+    }
+    assert_eq!(view_changes, 1);
+
+    let mut handoffs = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let EventType::LeaderHandoff { view, .. } = event.event {
+            handoffs.push(view);
+        }
+    }
+    assert_eq!(handoffs, vec![ViewNumber::genesis()]);
+}
+
+/// Moving to a version other than the decided upgrade's revokes the decided upgrade certificate,
+/// while moving to its own version keeps it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_recv_task_revokes_upgrade_certificate() {
+    use std::sync::Arc;
+
+    use hotshot_testing::helpers::{build_cert, key_pair_for_id};
+    use hotshot_types::{
+        simple_certificate::UpgradeCertificate,
+        simple_vote::{UpgradeProposalData, UpgradeVote},
+    };
+    use vbs::version::Version;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let membership = handle.hotshot.memberships.quorum_membership.clone();
+    let (private_key, public_key) = key_pair_for_id(2);
+    let new_version = Version { major: 0, minor: 2 };
+    let cert = build_cert::<
+        TestTypes,
+        UpgradeProposalData<TestTypes>,
+        UpgradeVote<TestTypes>,
+        UpgradeCertificate<TestTypes>,
+    >(
+        UpgradeProposalData {
+            old_version: Version { major: 0, minor: 1 },
+            new_version,
+            decide_by: ViewNumber::new(6),
+            new_version_hash: [0u8; 12].to_vec(),
+            old_version_last_view: ViewNumber::new(6),
+            new_version_first_view: ViewNumber::new(7),
+        },
+        &membership,
+        ViewNumber::new(5),
+        &public_key,
+        &private_key,
+    );
+
+    let mut state =
+        QuorumProposalRecvTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let (sender, _receiver) = async_broadcast::broadcast(100);
+
+    state
+        .handle(Arc::new(UpgradeDecided(cert.clone())), sender.clone())
+        .await;
+    state
+        .handle(Arc::new(VersionUpgrade(new_version)), sender.clone())
+        .await;
+    assert_eq!(state.decided_upgrade_cert, Some(cert));
+
+    state
+        .handle(
+            Arc::new(VersionUpgrade(Version { major: 0, minor: 1 })),
+            sender,
+        )
+        .await;
+    assert!(state.decided_upgrade_cert.is_none());
+}
+
+/// Whichever of the equivocating proposals the task receives first is validated, and the proof
+/// names it first.
+#[cfg(test)]
+#[cfg(feature = "dependency-tasks")]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_recv_task_detects_equivocation_in_either_order() {
+    use std::time::Duration;
+
+    use hotshot_testing::{
+        helpers::build_fake_view_with_leaf,
+        predicates::event::{quorum_proposal_validated, validated_state_updated, view_change},
+        script::{Expectations, TaskScript},
+    };
+    use hotshot_types::{data::Leaf, message::EquivocationProof};
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+    let consensus = handle.hotshot.consensus();
+    let mut consensus_writer = consensus.write().await;
+
+    let mut generator = TestViewGenerator::generate_with_equivocation(
+        quorum_membership.clone(),
+        da_membership,
+        vec![ViewNumber::new(2)],
+    );
+    let views = (&mut generator).take(2).collect::<Vec<_>>().await;
+    for view in &views {
+        consensus_writer
+            .update_saved_leaves(Leaf::from_quorum_proposal(&view.quorum_proposal.data));
+        consensus_writer
+            .update_validated_state_map(
+                view.quorum_proposal.data.view_number,
+                build_fake_view_with_leaf(view.leaf.clone()),
+            )
+            .unwrap();
+    }
+    drop(consensus_writer);
+
+    let honest = views[1].quorum_proposal.clone();
+    let conflicting = views[1].quorum_proposal_conflicting.clone().unwrap();
+    let leader = views[1].leader_public_key;
+
+    // The conflicting proposal arrives first this time.
+    let inputs = vec![
+        serial![QuorumProposalRecv(conflicting.clone(), leader)],
+        serial![QuorumProposalRecv(honest.clone(), leader)],
+    ];
+
+    let expectations = vec![
+        Expectations::from_outputs(vec![
+            view_change(),
+            exact(HighQcUpdated {
+                from: None,
+                to: ViewNumber::new(1),
+            }),
+            exact(UpdateHighQc(conflicting.data.justify_qc.clone())),
+            validated_state_updated(),
+            quorum_proposal_validated(),
+        ]),
+        Expectations::from_outputs(vec![exact(Equivocation(
+            ViewNumber::new(2),
+            leader,
+            EquivocationProof {
+                first: conflicting,
+                second: honest,
+            },
+        ))]),
+    ];
+
+    let state = QuorumProposalRecvTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
+
+/// A proposal for the first view of a new version whose parent, proposed between versions, isn't
+/// a null block is rejected once the upgrade is decided.
+#[cfg(test)]
+#[cfg(feature = "dependency-tasks")]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_recv_task_validates_parent_version() {
+    use std::sync::Arc;
+
+    use hotshot_example_types::block_types::TestTransaction;
+    use hotshot_testing::helpers::build_fake_view_with_leaf;
+    use hotshot_types::{data::Leaf, simple_vote::UpgradeProposalData};
+    use vbs::version::Version;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    // Views 1 and 2 are before the upgrade, view 3 is between versions and view 4 is the first
+    // view of the new version. View 3 isn't a null block.
+    let upgrade_data: UpgradeProposalData<TestTypes> = UpgradeProposalData {
+        old_version: Version { major: 0, minor: 1 },
+        new_version: Version { major: 0, minor: 2 },
+        decide_by: ViewNumber::new(2),
+        new_version_hash: [0u8; 12].to_vec(),
+        old_version_last_view: ViewNumber::new(2),
+        new_version_first_view: ViewNumber::new(4),
+    };
+
+    for decided in [true, false] {
+        let handle = build_system_handle(2).await.0;
+        let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+        let da_membership = handle.hotshot.memberships.da_membership.clone();
+        let consensus = handle.hotshot.consensus();
+
+        let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+        let mut proposals = Vec::new();
+        let mut leaders = Vec::new();
+        let mut upgrade_cert = None;
+        for view_number in 1..=4 {
+            if view_number == 2 {
+                generator.add_upgrade(upgrade_data.clone());
+            }
+            if view_number == 3 {
+                generator.add_transactions(vec![TestTransaction::new(vec![0])]);
+            }
+            let view = generator.next().await.unwrap();
+            upgrade_cert = upgrade_cert.or(view.quorum_proposal.data.upgrade_certificate.clone());
+            proposals.push(view.quorum_proposal.clone());
+            leaders.push(view.leader_public_key);
+
+            let mut consensus_writer = consensus.write().await;
+            consensus_writer
+                .update_saved_leaves(Leaf::from_quorum_proposal(&view.quorum_proposal.data));
+            consensus_writer
+                .update_validated_state_map(
+                    view.quorum_proposal.data.view_number,
+                    build_fake_view_with_leaf(view.leaf.clone()),
+                )
+                .unwrap();
+        }
+
+        let mut state =
+            QuorumProposalRecvTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+        if decided {
+            state.decided_upgrade_cert =
+                Some(upgrade_cert.expect("The upgrade certificate should be attached"));
+        }
+        let (sender, mut receiver) = async_broadcast::broadcast(100);
+        state
+            .handle(
+                Arc::new(QuorumProposalRecv(proposals[3].clone(), leaders[3])),
+                sender,
+            )
+            .await;
+
+        // The high QC is only updated once the parent passes the version check.
+        let mut updated_high_qc = false;
+        while let Ok(event) = receiver.try_recv() {
+            updated_high_qc |= matches!(event.as_ref(), UpdateHighQc(_));
+        }
+        assert_eq!(updated_high_qc, !decided);
+    }
+}
@@ -1,6 +1,6 @@
 #![cfg(feature = "dependency-tasks")]
 
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 use futures::StreamExt;
 use hotshot::tasks::task_state::CreateTaskState;
 use hotshot::traits::ValidatedState;
@@ -30,11 +30,13 @@ use hotshot_testing::{
     view_generator::TestViewGenerator,
 };
 use hotshot_types::{
+    constants::{Base, Upgrade},
     data::{null_block, Leaf, ViewChangeEvidence, ViewNumber},
     simple_vote::{TimeoutData, ViewSyncFinalizeData},
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeType},
+        storage::Storage,
     },
     utils::BuilderCommitment,
     vid::VidSchemeType,
@@ -42,6 +44,7 @@ use hotshot_types::{
 };
 use jf_vid::VidScheme;
 use sha2::Digest;
+use vbs::version::StaticVersionType;
 
 const TIMEOUT: Duration = Duration::from_millis(35);
 
@@ -273,18 +276,34 @@ async fn test_quorum_proposal_task_quorum_proposal_view_gt_1() {
 
     let expectations = vec![
         Expectations::from_outputs(all_predicates![exact(UpdateHighQc(genesis_cert.clone()))]),
-        Expectations::from_outputs(all_predicates![exact(UpdateHighQc(
-            proposals[1].data.justify_qc.clone(),
-        ))]),
+        Expectations::from_outputs(all_predicates![
+            exact(UpdateHighQc(proposals[1].data.justify_qc.clone())),
+            exact(HighQcUpdated {
+                from: None,
+                to: ViewNumber::new(1),
+            }),
+        ]),
         Expectations::from_outputs(all_predicates![
             exact(UpdateHighQc(proposals[2].data.justify_qc.clone())),
+            exact(HighQcUpdated {
+                from: Some(ViewNumber::new(1)),
+                to: ViewNumber::new(2),
+            }),
             quorum_proposal_send(),
         ]),
-        Expectations::from_outputs(all_predicates![exact(UpdateHighQc(
-            proposals[3].data.justify_qc.clone(),
-        ))]),
+        Expectations::from_outputs(all_predicates![
+            exact(UpdateHighQc(proposals[3].data.justify_qc.clone())),
+            exact(HighQcUpdated {
+                from: Some(ViewNumber::new(2)),
+                to: ViewNumber::new(3),
+            }),
+        ]),
         Expectations::from_outputs(all_predicates![
             exact(UpdateHighQc(proposals[4].data.justify_qc.clone())),
+            exact(HighQcUpdated {
+                from: Some(ViewNumber::new(3)),
+                to: ViewNumber::new(4),
+            }),
         ]),
     ];
 
@@ -593,18 +612,34 @@ async fn test_quorum_proposal_task_liveness_check() {
 
     let expectations = vec![
         Expectations::from_outputs(vec![exact(UpdateHighQc(genesis_cert.clone()))]),
-        Expectations::from_outputs(vec![exact(UpdateHighQc(
-            proposals[1].data.justify_qc.clone(),
-        ))]),
+        Expectations::from_outputs(vec![
+            exact(UpdateHighQc(proposals[1].data.justify_qc.clone())),
+            exact(HighQcUpdated {
+                from: None,
+                to: ViewNumber::new(1),
+            }),
+        ]),
         Expectations::from_outputs(all_predicates![
             exact(UpdateHighQc(proposals[2].data.justify_qc.clone())),
+            exact(HighQcUpdated {
+                from: Some(ViewNumber::new(1)),
+                to: ViewNumber::new(2),
+            }),
             quorum_proposal_send(),
         ]),
-        Expectations::from_outputs(vec![exact(UpdateHighQc(
-            proposals[3].data.justify_qc.clone(),
-        ))]),
+        Expectations::from_outputs(vec![
+            exact(UpdateHighQc(proposals[3].data.justify_qc.clone())),
+            exact(HighQcUpdated {
+                from: Some(ViewNumber::new(2)),
+                to: ViewNumber::new(3),
+            }),
+        ]),
         Expectations::from_outputs(vec![
             exact(UpdateHighQc(proposals[4].data.justify_qc.clone())),
+            exact(HighQcUpdated {
+                from: Some(ViewNumber::new(3)),
+                to: ViewNumber::new(4),
+            }),
         ]),
     ];
 
@@ -659,3 +694,368 @@ async fn test_quorum_proposal_task_with_incomplete_events() {
     run_test![inputs, script].await;
 }
 
+/// A paused task holds back the events it receives, and handles them once resumed.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_task_pause_resume() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let genesis_cert = generator.next().await.unwrap().quorum_proposal.data.justify_qc;
+
+    let inputs = vec![
+        serial![Pause, QcFormed(either::Left(genesis_cert.clone()))],
+        serial![Resume],
+    ];
+
+    let expectations = vec![
+        Expectations::from_outputs(vec![]),
+        Expectations::from_outputs(vec![exact(UpdateHighQc(genesis_cert))]),
+    ];
+
+    let quorum_proposal_task_state =
+        QuorumProposalTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+
+    let mut script = TaskScript {
+        timeout: TIMEOUT,
+        state: quorum_proposal_task_state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
+
+/// Without the VID share, the dependencies for proposing are never met, so the task gives up on
+/// them once they time out.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_task_dependency_timeout() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let node_id = 1;
+    let handle = build_system_handle(node_id).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let payload_commitment = make_payload_commitment(&quorum_membership, ViewNumber::new(node_id));
+    let builder_commitment = BuilderCommitment::from_raw_digest(sha2::Sha256::new().finalize());
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let genesis_cert = generator.next().await.unwrap().quorum_proposal.data.justify_qc;
+
+    // Everything needed to propose in view 1, except for the VID share.
+    let inputs = vec![random![
+        QcFormed(either::Left(genesis_cert.clone())),
+        SendPayloadCommitmentAndMetadata(
+            payload_commitment,
+            builder_commitment,
+            TestMetadata,
+            ViewNumber::new(1),
+            null_block::builder_fee(quorum_membership.total_nodes()).unwrap(),
+        ),
+    ]];
+
+    let expectations = vec![Expectations::from_outputs(all_predicates![
+        exact(UpdateHighQc(genesis_cert)),
+        exact(ProposalDependencyTimeout(ViewNumber::new(1))),
+    ])];
+
+    let mut quorum_proposal_task_state =
+        QuorumProposalTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    quorum_proposal_task_state.timeout = 10;
+
+    let mut script = TaskScript {
+        timeout: TIMEOUT,
+        state: quorum_proposal_task_state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
+
+/// When configured to wait for the DA certificate, the leader only proposes once it arrives, even
+/// with every other dependency met.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_task_waits_for_dac() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let node_id = 1;
+    let handle = build_system_handle(node_id).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let payload_commitment = make_payload_commitment(&quorum_membership, ViewNumber::new(node_id));
+    let builder_commitment = BuilderCommitment::from_raw_digest(sha2::Sha256::new().finalize());
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let view = generator.next().await.unwrap();
+    let genesis_cert = view.quorum_proposal.data.justify_qc.clone();
+    handle
+        .hotshot
+        .consensus()
+        .write()
+        .await
+        .update_saved_leaves(Leaf::from_quorum_proposal(&view.quorum_proposal.data));
+
+    let inputs = vec![
+        random![
+            VidDisperseSend(view.vid_disperse.clone(), handle.public_key()),
+            QcFormed(either::Left(genesis_cert.clone())),
+            SendPayloadCommitmentAndMetadata(
+                payload_commitment,
+                builder_commitment,
+                TestMetadata,
+                ViewNumber::new(1),
+                null_block::builder_fee(quorum_membership.total_nodes()).unwrap(),
+            ),
+            ValidatedStateUpdated(
+                view.quorum_proposal.data.view_number(),
+                build_fake_view_with_leaf(view.leaf.clone()),
+            ),
+        ],
+        serial![DaCertificateRecv(view.da_certificate.clone())],
+    ];
+
+    let expectations = vec![
+        Expectations::from_outputs(vec![exact(UpdateHighQc(genesis_cert))]),
+        Expectations::from_outputs(vec![quorum_proposal_send()]),
+    ];
+
+    let mut quorum_proposal_task_state =
+        QuorumProposalTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    quorum_proposal_task_state.wait_for_dac_to_propose = true;
+
+    let mut script = TaskScript {
+        timeout: TIMEOUT,
+        state: quorum_proposal_task_state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
+
+/// High QCs updated in quick succession are written to storage once, and only the highest of them
+/// is. Shutting the task down flushes the write rather than dropping it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_task_coalesces_high_qc_writes() {
+    use hotshot_task::task::TaskState;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let qcs = (&mut generator)
+        .take(3)
+        .map(|view| view.quorum_proposal.data.justify_qc)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut state = QuorumProposalTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    // Long enough that the scheduled write never fires during the test.
+    state.round_start_delay = 60_000;
+    let (sender, receiver) = async_broadcast::broadcast(16);
+
+    for qc in [&qcs[1], &qcs[2], &qcs[1]] {
+        state
+            .handle(
+                Arc::new(UpdateHighQc(qc.clone())),
+                receiver.clone(),
+                sender.clone(),
+            )
+            .await;
+    }
+    assert!(state.storage.read().await.load_high_qc().await.unwrap().is_none());
+
+    state.cancel_subtasks().await;
+    assert!(state.high_qc_write_task.is_none());
+    assert_eq!(
+        state.storage.read().await.load_high_qc().await.unwrap(),
+        Some(qcs[2].clone())
+    );
+}
+
+/// A task started at a given version keeps it until a `VersionUpgrade` event.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_task_with_version() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let mut state = QuorumProposalTaskState::<TestTypes, MemoryImpl>::create_from(&handle)
+        .await
+        .with_version(Upgrade::VERSION);
+    assert_eq!(state.version, Upgrade::VERSION);
+
+    let (sender, receiver) = async_broadcast::broadcast(16);
+    state
+        .handle(Arc::new(VersionUpgrade(Base::VERSION)), receiver, sender)
+        .await;
+    assert_eq!(state.version, Base::VERSION);
+}
+
+/// A task started at a given version builds the block header of its proposal for that version.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_task_proposes_with_version() {
+    use hotshot_testing::predicates::event::quorum_proposal_send_with_version;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let node_id = 1;
+    let handle = build_system_handle(node_id).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let payload_commitment = make_payload_commitment(&quorum_membership, ViewNumber::new(node_id));
+    let builder_commitment = BuilderCommitment::from_raw_digest(sha2::Sha256::new().finalize());
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let view = generator.next().await.unwrap();
+    let genesis_cert = view.quorum_proposal.data.justify_qc.clone();
+    handle
+        .hotshot
+        .consensus()
+        .write()
+        .await
+        .update_saved_leaves(Leaf::from_quorum_proposal(&view.quorum_proposal.data));
+
+    let inputs = vec![random![
+        VidDisperseSend(view.vid_disperse.clone(), handle.public_key()),
+        QcFormed(either::Left(genesis_cert.clone())),
+        SendPayloadCommitmentAndMetadata(
+            payload_commitment,
+            builder_commitment,
+            TestMetadata,
+            ViewNumber::new(1),
+            null_block::builder_fee(quorum_membership.total_nodes()).unwrap(),
+        ),
+        ValidatedStateUpdated(
+            view.quorum_proposal.data.view_number(),
+            build_fake_view_with_leaf(view.leaf.clone()),
+        ),
+    ]];
+
+    let expectations = vec![Expectations::from_outputs(all_predicates![
+        exact(UpdateHighQc(genesis_cert)),
+        quorum_proposal_send_with_version(Upgrade::VERSION),
+    ])];
+
+    let quorum_proposal_task_state =
+        QuorumProposalTaskState::<TestTypes, MemoryImpl>::create_from(&handle)
+            .await
+            .with_version(Upgrade::VERSION);
+
+    let mut script = TaskScript {
+        timeout: TIMEOUT,
+        state: quorum_proposal_task_state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
+
+/// A leader which also leads the DA committee proposes once it forms the DA certificate from the
+/// votes it collects, rather than waiting to receive a certificate it sent itself.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_proposal_task_waits_for_formed_dac() {
+    use hotshot_task_impls::da::DaTaskState;
+    use hotshot_testing::helpers::key_pair_for_id;
+    use hotshot_types::simple_vote::{DaData, DaVote};
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    // Node 1 leads both the quorum and the DA committee in view 1.
+    let node_id = 1;
+    let handle = build_system_handle(node_id).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let payload_commitment = make_payload_commitment(&quorum_membership, ViewNumber::new(node_id));
+    let builder_commitment = BuilderCommitment::from_raw_digest(sha2::Sha256::new().finalize());
+
+    // Collect the DA votes for view 1 to form the certificate.
+    let (da_sender, mut da_receiver) = async_broadcast::broadcast(16);
+    let mut da_state = DaTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    for id in 0..da_membership.success_threshold().get() {
+        let (private_key, public_key) = key_pair_for_id(id);
+        let vote = DaVote::create_signed_vote(
+            DaData {
+                payload_commit: payload_commitment,
+            },
+            ViewNumber::new(node_id),
+            &public_key,
+            &private_key,
+        )
+        .unwrap();
+        da_state
+            .handle(Arc::new(DaVoteRecv(vote)), da_sender.clone())
+            .await;
+    }
+    let dac_send = da_receiver.try_recv().unwrap();
+    assert!(matches!(dac_send.as_ref(), DacSend(..)));
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let view = generator.next().await.unwrap();
+    let genesis_cert = view.quorum_proposal.data.justify_qc.clone();
+    handle
+        .hotshot
+        .consensus()
+        .write()
+        .await
+        .update_saved_leaves(Leaf::from_quorum_proposal(&view.quorum_proposal.data));
+
+    let inputs = vec![
+        random![
+            VidDisperseSend(view.vid_disperse.clone(), handle.public_key()),
+            QcFormed(either::Left(genesis_cert.clone())),
+            SendPayloadCommitmentAndMetadata(
+                payload_commitment,
+                builder_commitment,
+                TestMetadata,
+                ViewNumber::new(1),
+                null_block::builder_fee(quorum_membership.total_nodes()).unwrap(),
+            ),
+            ValidatedStateUpdated(
+                view.quorum_proposal.data.view_number(),
+                build_fake_view_with_leaf(view.leaf.clone()),
+            ),
+        ],
+        serial![dac_send.as_ref().clone()],
+    ];
+
+    let expectations = vec![
+        Expectations::from_outputs(vec![exact(UpdateHighQc(genesis_cert))]),
+        Expectations::from_outputs(vec![quorum_proposal_send()]),
+    ];
+
+    let mut quorum_proposal_task_state =
+        QuorumProposalTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    quorum_proposal_task_state.wait_for_dac_to_propose = true;
+
+    let mut script = TaskScript {
+        timeout: TIMEOUT,
+        state: quorum_proposal_task_state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
@@ -270,3 +270,123 @@ async fn test_quorum_vote_task_incorrect_dependency() {
     };
     run_test![inputs, script].await;
 }
+
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_vote_task_payload_mismatch() {
+    use std::sync::Arc;
+
+    use hotshot_example_types::block_types::TestTransaction;
+    use hotshot_task_impls::{events::HotShotEvent::*, quorum_vote::QuorumVoteTaskState};
+    use hotshot_testing::{
+        helpers::build_system_handle, predicates::event::exact, view_generator::TestViewGenerator,
+    };
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+
+    let mut proposals = Vec::new();
+    let mut leaves = Vec::new();
+    let mut dacs = Vec::new();
+    let mut vids = Vec::new();
+    let consensus = handle.hotshot.consensus().clone();
+    let mut consensus_writer = consensus.write().await;
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaves.push(view.leaf.clone());
+        dacs.push(view.da_certificate.clone());
+        vids.push(view.vid_proposal.clone());
+        consensus_writer
+            .update_validated_state_map(
+                view.quorum_proposal.data.view_number(),
+                build_fake_view_with_leaf(view.leaf.clone()),
+            )
+            .unwrap();
+        consensus_writer.update_saved_leaves(view.leaf.clone());
+    }
+
+    // The payload we got from the DA proposal isn't the one the quorum proposal commits to.
+    consensus_writer
+        .update_saved_payloads(
+            ViewNumber::new(2),
+            Arc::from(TestTransaction::encode(&[TestTransaction::new(vec![0])])),
+        )
+        .unwrap();
+    drop(consensus_writer);
+
+    // All the dependencies complete, but we must not vote for the mismatched payload.
+    let inputs = vec![random![
+        QuorumProposalValidated(proposals[1].data.clone(), leaves[0].clone()),
+        DaCertificateRecv(dacs[1].clone()),
+        VidShareRecv(vids[1].0[0].clone()),
+    ]];
+
+    let expectations = vec![Expectations::from_outputs(all_predicates![
+        exact(DaCertificateValidated(dacs[1].clone())),
+        exact(VidShareValidated(vids[1].0[0].clone())),
+        exact(QuorumVoteDependenciesValidated(ViewNumber::new(2))),
+    ])];
+
+    let quorum_vote_state =
+        QuorumVoteTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+
+    let mut script = TaskScript {
+        timeout: TIMEOUT,
+        state: quorum_vote_state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
+
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_vote_task_duplicate_vid_share() {
+    use hotshot_task_impls::{events::HotShotEvent::*, quorum_vote::QuorumVoteTaskState};
+    use hotshot_testing::{
+        helpers::build_system_handle, predicates::event::exact, view_generator::TestViewGenerator,
+    };
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+
+    let mut vids = Vec::new();
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        vids.push(view.vid_proposal.clone());
+    }
+    let share = vids[1].0[0].clone();
+
+    // The same share arrives on its own and in a batch, twice each, but is only handled once.
+    let inputs = vec![serial![
+        VidShareRecv(share.clone()),
+        VidShareRecv(share.clone()),
+        VidSharesRecv(vec![share.clone(), share.clone()]),
+    ]];
+
+    let expectations = vec![Expectations::from_outputs(vec![exact(VidShareValidated(
+        share,
+    ))])];
+
+    let quorum_vote_state =
+        QuorumVoteTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+
+    let mut script = TaskScript {
+        timeout: TIMEOUT,
+        state: quorum_vote_state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
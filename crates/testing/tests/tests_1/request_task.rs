@@ -0,0 +1,267 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_compatibility_layer::art::async_timeout;
+use futures::{future::join_all, StreamExt};
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes};
+use hotshot_task::task::{Task, TaskState};
+use hotshot_task_impls::{
+    events::{HotShotEvent, ProposalMissing},
+    request::{CatchupLimiter, NetworkRequestState},
+};
+use hotshot_testing::{
+    helpers::{build_system_handle, build_upgrade_certificate},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{
+        network::{RequestKind, MAX_VID_RANGE},
+        node_implementation::ConsensusTime,
+    },
+};
+
+/// Maximum catch-up requests per second in the test.
+const CATCHUP_RATE: u32 = 10;
+
+/// How long tasks and requesters may take to stop once cancelled.
+const CANCELLATION_BOUND: Duration = Duration::from_secs(1);
+
+/// Catch-up requests are paced to the configured rate, while live requests are sent straight away.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_catchup_requests_are_rate_limited() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let mut state = NetworkRequestState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    state.catchup_limiter = Arc::new(CatchupLimiter::new(CATCHUP_RATE.into()));
+
+    let (sender, receiver) = async_broadcast::broadcast(100);
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::ViewChange(ViewNumber::new(100))),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+
+    // The memory network can't serve requests, so every request is answered with `None` as soon
+    // as it's sent.
+    let (catchup_sender, mut catchup_receiver) = async_broadcast::broadcast(100);
+    let (live_sender, mut live_receiver) = async_broadcast::broadcast(100);
+    let start = Instant::now();
+    for (views, response_chan) in [(1..=20, &catchup_sender), (98..=102, &live_sender)] {
+        for view in views {
+            let missing = ProposalMissing {
+                view: ViewNumber::new(view),
+                response_chan: response_chan.clone(),
+            };
+            state
+                .handle_event(
+                    Arc::new(HotShotEvent::QuorumProposalRequest(missing)),
+                    &sender,
+                    &receiver,
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    for _ in 98..=102 {
+        async_timeout(Duration::from_millis(250), live_receiver.recv_direct())
+            .await
+            .expect("Live request was throttled")
+            .unwrap();
+    }
+
+    let interval = Duration::from_secs(1) / CATCHUP_RATE;
+    for sent in 0..20 {
+        async_timeout(Duration::from_secs(5), catchup_receiver.recv_direct())
+            .await
+            .expect("Timed out waiting for a catch-up request")
+            .unwrap();
+        assert!(start.elapsed() >= interval * sent);
+    }
+}
+
+/// Cancelling the handle's token mid-run stops its tasks, and the requesters they've spawned,
+/// promptly, even while a requester is waiting out its delay.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_cancellation_stops_tasks_and_requesters() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let mut handle = build_system_handle(2).await.0;
+    let cancellation = handle.cancellation_token();
+    let mut state = NetworkRequestState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    // Without cancellation, the requester would wait far longer than the test's bound.
+    state.delay = Duration::from_secs(60);
+
+    let mut generator = TestViewGenerator::generate(
+        handle.hotshot.memberships.quorum_membership.clone(),
+        handle.hotshot.memberships.da_membership.clone(),
+    );
+    let view = generator.next().await.unwrap();
+    let (sender, receiver) = async_broadcast::broadcast(100);
+    state
+        .handle_event(
+            Arc::new(HotShotEvent::QuorumProposalValidated(
+                view.quorum_proposal.data.clone(),
+                view.leaf.clone(),
+            )),
+            &sender,
+            &receiver,
+        )
+        .await
+        .unwrap();
+    // Take the requesters, so they can only be stopped by the token rather than aborted.
+    let requesters: Vec<_> = std::mem::take(&mut state.spawned_tasks)
+        .into_values()
+        .flatten()
+        .collect();
+    // One each for our VID share and the view's DA proposal.
+    assert_eq!(requesters.len(), 2);
+    let task = Task::new(state, sender, receiver, cancellation.clone()).run();
+
+    cancellation.cancel();
+
+    let _state = async_timeout(CANCELLATION_BOUND, task)
+        .await
+        .expect("Request task didn't stop after cancellation");
+    async_timeout(CANCELLATION_BOUND, join_all(requesters))
+        .await
+        .expect("Requester didn't stop after cancellation");
+    async_timeout(CANCELLATION_BOUND, handle.shut_down_tasks())
+        .await
+        .expect("Consensus tasks didn't stop after cancellation");
+}
+
+/// A node outside the DA committee doesn't request a view's DA proposal unless a DAC shows the
+/// payload is available and it has no VID share to get the payload from, while a DA member that
+/// missed the proposal always requests it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_da_proposal_requested_only_when_needed() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    // Nodes 0 to 5 are the DA committee of the default test network.
+    for (node_id, with_dac, expected_requests) in [(2, false, 2), (8, false, 1), (8, true, 2)] {
+        let handle = build_system_handle(node_id).await.0;
+        let mut state = NetworkRequestState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+        state.delay = Duration::from_secs(60);
+
+        let mut generator = TestViewGenerator::generate(
+            handle.hotshot.memberships.quorum_membership.clone(),
+            handle.hotshot.memberships.da_membership.clone(),
+        );
+        let view = generator.next().await.unwrap();
+        if with_dac {
+            handle
+                .consensus()
+                .write()
+                .await
+                .update_saved_da_certs(view.view_number, view.da_certificate.clone());
+        }
+
+        let (sender, receiver) = async_broadcast::broadcast(100);
+        state
+            .handle_event(
+                Arc::new(HotShotEvent::QuorumProposalValidated(
+                    view.quorum_proposal.data.clone(),
+                    view.leaf.clone(),
+                )),
+                &sender,
+                &receiver,
+            )
+            .await
+            .unwrap();
+        let requesters = state
+            .spawned_tasks
+            .get(&view.view_number)
+            .map_or(0, Vec::len);
+        assert_eq!(
+            requesters, expected_requests,
+            "node {node_id}, with a DAC: {with_dac}"
+        );
+        state.cancel_subtasks().await;
+    }
+}
+
+/// Missing VID shares for the views before a view are requested along with it in a single ranged
+/// request, which stops at a view we've already requested, at `MAX_VID_RANGE` views, and at the
+/// view the network upgraded in.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_vid_requests_are_coalesced() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    // A node outside the DA committee, so only its VID share is requested.
+    let handle = build_system_handle(8).await.0;
+    let public_key = handle.public_key();
+    let mut state = NetworkRequestState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+
+    // Before the upgrade, peers don't understand ranged requests.
+    assert_eq!(
+        state.build_requests(ViewNumber::new(5)).await,
+        vec![RequestKind::Vid(ViewNumber::new(5), public_key.clone())]
+    );
+    *state.decided_upgrade_certificate.write().await =
+        Some(build_upgrade_certificate(ViewNumber::new(3)));
+    assert_eq!(
+        state.build_requests(ViewNumber::new(5)).await,
+        vec![RequestKind::VidRange(
+            ViewNumber::new(3),
+            ViewNumber::new(5),
+            public_key.clone()
+        )]
+    );
+
+    *state.decided_upgrade_certificate.write().await =
+        Some(build_upgrade_certificate(ViewNumber::new(1)));
+    assert_eq!(
+        state.build_requests(ViewNumber::new(5)).await,
+        vec![RequestKind::VidRange(
+            ViewNumber::new(1),
+            ViewNumber::new(5),
+            public_key.clone()
+        )]
+    );
+
+    // Views we've already spawned requests for aren't requested again.
+    state.spawned_tasks.insert(ViewNumber::new(3), Vec::new());
+    assert_eq!(
+        state.build_requests(ViewNumber::new(5)).await,
+        vec![RequestKind::VidRange(
+            ViewNumber::new(4),
+            ViewNumber::new(5),
+            public_key.clone()
+        )]
+    );
+    assert_eq!(
+        state.build_requests(ViewNumber::new(4)).await,
+        vec![RequestKind::Vid(ViewNumber::new(4), public_key.clone())]
+    );
+
+    let view = 5 + MAX_VID_RANGE;
+    assert_eq!(
+        state.build_requests(ViewNumber::new(view)).await,
+        vec![RequestKind::VidRange(
+            ViewNumber::new(view - MAX_VID_RANGE + 1),
+            ViewNumber::new(view),
+            public_key
+        )]
+    );
+}
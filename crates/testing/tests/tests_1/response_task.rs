@@ -0,0 +1,858 @@
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use async_broadcast::broadcast;
+use async_compatibility_layer::art::async_timeout;
+use async_lock::RwLock;
+use futures::{
+    channel::{mpsc, oneshot},
+    future::join_all,
+    SinkExt, StreamExt,
+};
+use hotshot::{
+    tasks::task_state::CreateTaskState,
+    types::{BLSPubKey, SignatureKey},
+};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes},
+};
+use hotshot_task::cancellation::CancellationToken;
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    request::{
+        save_backfilled_proposal, save_requested_da_proposal, verify_response, NetworkRequestState,
+    },
+    response::{run_response_task, run_response_task_until_cancelled, NetworkResponseState},
+};
+use hotshot_testing::{
+    helpers::{
+        build_system_handle, build_system_handle_from_description,
+        build_system_handle_from_launcher, build_upgrade_certificate, key_pair_for_id,
+    },
+    test_builder::TestDescription,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    consensus::Consensus,
+    data::ViewNumber,
+    message::{
+        DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message, MessageKind,
+        SequencingMessage,
+    },
+    traits::{
+        election::Membership,
+        network::{
+            DataRequest, RequestKind, ResponseChannel, ResponseMessage, SignedResponse,
+            MAX_VID_RANGE,
+        },
+        node_implementation::ConsensusTime,
+        signature_key::InMemorySigner,
+    },
+    vid::vid_scheme,
+};
+use jf_vid::VidScheme;
+use sha2::{Digest, Sha256};
+
+/// Number of invalid requests sent ahead of the valid one.
+const FLOOD_SIZE: usize = 500;
+
+/// Builds a serialized request from node 1 for the proposal in `view`, with the signature taken
+/// over a request for `signed_view`.
+fn proposal_request(view: u64, signed_view: u64) -> Vec<u8> {
+    let (private_key, public_key) = key_pair_for_id(1);
+    let signed = RequestKind::<TestTypes>::Proposal(ViewNumber::new(signed_view));
+    let signature = BLSPubKey::sign(
+        &private_key,
+        &Sha256::digest(bincode::serialize(&signed).unwrap()),
+    )
+    .unwrap();
+    let message = Message::<TestTypes> {
+        sender: public_key,
+        kind: MessageKind::Data(DataMessage::RequestData(DataRequest {
+            request: RequestKind::Proposal(ViewNumber::new(view)),
+            view: ViewNumber::new(view),
+            signature,
+        })),
+    };
+    bincode::serialize(&message).unwrap()
+}
+
+/// Builds a serialized request from node `id` for its own VID share in `view`.
+fn vid_share_request(id: u64, view: u64) -> Vec<u8> {
+    let (private_key, public_key) = key_pair_for_id(id);
+    let request = RequestKind::<TestTypes>::Vid(ViewNumber::new(view), public_key);
+    let signature = BLSPubKey::sign(
+        &private_key,
+        &Sha256::digest(bincode::serialize(&request).unwrap()),
+    )
+    .unwrap();
+    let message = Message::<TestTypes> {
+        sender: public_key,
+        kind: MessageKind::Data(DataMessage::RequestData(DataRequest {
+            request,
+            view: ViewNumber::new(view),
+            signature,
+        })),
+    };
+    bincode::serialize(&message).unwrap()
+}
+
+/// Builds a serialized request from node `id` for its own VID shares from `start` to `end`.
+fn vid_range_request(id: u64, start: u64, end: u64) -> Vec<u8> {
+    let (private_key, public_key) = key_pair_for_id(id);
+    let request = RequestKind::<TestTypes>::VidRange(
+        ViewNumber::new(start),
+        ViewNumber::new(end),
+        public_key,
+    );
+    let signature = BLSPubKey::sign(
+        &private_key,
+        &Sha256::digest(bincode::serialize(&request).unwrap()),
+    )
+    .unwrap();
+    let message = Message::<TestTypes> {
+        sender: public_key,
+        kind: MessageKind::Data(DataMessage::RequestData(DataRequest {
+            request,
+            view: ViewNumber::new(start),
+            signature,
+        })),
+    };
+    bincode::serialize(&message).unwrap()
+}
+
+/// Builds a serialized request from node 1 for the DA proposal in `view`.
+fn da_proposal_request(view: u64) -> Vec<u8> {
+    let (private_key, public_key) = key_pair_for_id(1);
+    let request = RequestKind::<TestTypes>::DaProposal(ViewNumber::new(view));
+    let signature = BLSPubKey::sign(
+        &private_key,
+        &Sha256::digest(bincode::serialize(&request).unwrap()),
+    )
+    .unwrap();
+    let message = Message::<TestTypes> {
+        sender: public_key,
+        kind: MessageKind::Data(DataMessage::RequestData(DataRequest {
+            request,
+            view: ViewNumber::new(view),
+            signature,
+        })),
+    };
+    bincode::serialize(&message).unwrap()
+}
+
+/// Deserializes the response message sent by the responder.
+fn response_message(bytes: &[u8]) -> DataMessage<TestTypes> {
+    let message: Message<TestTypes> = bincode::deserialize(bytes).unwrap();
+    match message.kind {
+        MessageKind::Data(
+            response @ (DataMessage::DataResponse(_) | DataMessage::SignedDataResponse(_)),
+        ) => response,
+        kind => panic!("Expected a data response, got {kind:?}"),
+    }
+}
+
+/// Deserializes a signed response from the responder.
+fn signed_response(bytes: &[u8]) -> SignedResponse<TestTypes> {
+    match response_message(bytes) {
+        DataMessage::SignedDataResponse(response) => response,
+        message => panic!("Expected a signed data response, got {message:?}"),
+    }
+}
+
+/// Deserializes a response from the responder, without checking its signature.
+fn response(bytes: &[u8]) -> ResponseMessage<TestTypes> {
+    match response_message(bytes) {
+        DataMessage::DataResponse(response) => response,
+        DataMessage::SignedDataResponse(signed) => signed.response,
+        _ => unreachable!(),
+    }
+}
+
+/// Sends `request` to the responder and waits for its response.
+async fn serve(
+    request_sender: &mut mpsc::Sender<(Vec<u8>, ResponseChannel<Vec<u8>>)>,
+    request: Vec<u8>,
+) -> Vec<u8> {
+    let (sender, receiver) = oneshot::channel();
+    request_sender
+        .send((request, ResponseChannel { sender }))
+        .await
+        .unwrap();
+    async_timeout(Duration::from_secs(2), receiver)
+        .await
+        .expect("Timed out waiting for the response")
+        .unwrap()
+}
+
+/// A responder created with its request receiver and private key serves requests until it gets
+/// `HotShotEvent::Shutdown`.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_response_task_stops_on_shutdown() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let (private_key, public_key) = key_pair_for_id(2);
+    let (mut request_sender, request_receiver) = mpsc::channel(1);
+    let state = NetworkResponseState::<TestTypes>::new(
+        handle.hotshot.consensus(),
+        request_receiver,
+        handle.hotshot.memberships.quorum_membership.clone().into(),
+        public_key,
+        private_key,
+    );
+    let (event_sender, event_receiver) = broadcast(1);
+    let task = run_response_task(state, event_receiver);
+
+    let served = serve(&mut request_sender, proposal_request(1, 1)).await;
+    assert!(matches!(response(&served), ResponseMessage::NotFound));
+
+    event_sender
+        .broadcast(Arc::new(HotShotEvent::Shutdown))
+        .await
+        .unwrap();
+    assert!(
+        async_timeout(Duration::from_secs(2), task).await.is_ok(),
+        "Timed out waiting for the response task to shut down"
+    );
+}
+
+/// A flood of requests with invalid signatures is denied without starving a valid request sent
+/// after it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_response_task_serves_valid_request_during_flood() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let (private_key, public_key) = key_pair_for_id(2);
+    let state = NetworkResponseState::<TestTypes>::with_signer(
+        handle.hotshot.consensus(),
+        handle.hotshot.memberships.quorum_membership.clone().into(),
+        handle.hotshot.memberships.vid_membership.clone().into(),
+        public_key,
+        Arc::new(InMemorySigner::new(private_key)),
+    );
+    let (mut request_sender, request_receiver) = mpsc::channel(FLOOD_SIZE + 1);
+    let _task =
+        run_response_task_until_cancelled(state, request_receiver, CancellationToken::new());
+
+    let mut denied = Vec::new();
+    for _ in 0..FLOOD_SIZE {
+        let (sender, receiver) = oneshot::channel();
+        request_sender
+            .send((proposal_request(1, 2), ResponseChannel { sender }))
+            .await
+            .unwrap();
+        denied.push(receiver);
+    }
+    let (sender, receiver) = oneshot::channel();
+    request_sender
+        .send((proposal_request(1, 1), ResponseChannel { sender }))
+        .await
+        .unwrap();
+
+    // We haven't proposed anything, but the request is served rather than denied.
+    let served = async_timeout(Duration::from_secs(2), receiver)
+        .await
+        .expect("Timed out waiting for the valid request to be served")
+        .unwrap();
+    assert!(matches!(response(&served), ResponseMessage::NotFound));
+
+    for receiver in denied {
+        assert!(matches!(
+            response(&receiver.await.unwrap()),
+            ResponseMessage::Denied
+        ));
+    }
+}
+
+/// VID shares are dispersed to exactly the VID storage nodes, including the unstaked VID-only
+/// nodes, and are served to those nodes but not to nodes outside the VID membership.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_response_task_serves_vid_membership() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    // Nodes 0 to 9 are staked, and nodes 10 and 11 only store VID shares.
+    let description = TestDescription {
+        vid_only_nodes: 2,
+        ..TestDescription::default_multiple_rounds()
+    };
+    let handle = build_system_handle_from_description(description, 2).await.0;
+    let view = ViewNumber::new(1);
+    let vid_membership = handle.hotshot.memberships.vid_membership.clone();
+    let vid_nodes: BTreeSet<_> = (0..12).map(|id| key_pair_for_id(id).1).collect();
+    assert_eq!(vid_membership.staked_committee(view), vid_nodes);
+    assert_eq!(
+        handle
+            .hotshot
+            .memberships
+            .quorum_membership
+            .staked_committee(view)
+            .len(),
+        10
+    );
+
+    handle
+        .hotshot
+        .consensus()
+        .write()
+        .await
+        .update_saved_payloads(
+            view,
+            Arc::from(TestTransaction::encode(&[TestTransaction::new(vec![0])])),
+        )
+        .unwrap();
+
+    let (private_key, public_key) = key_pair_for_id(2);
+    let state = NetworkResponseState::<TestTypes>::with_signer(
+        handle.hotshot.consensus(),
+        handle.hotshot.memberships.quorum_membership.clone().into(),
+        vid_membership.clone().into(),
+        public_key,
+        Arc::new(InMemorySigner::new(private_key)),
+    );
+    let (mut request_sender, request_receiver) = mpsc::channel(2);
+    let _task =
+        run_response_task_until_cancelled(state, request_receiver, CancellationToken::new());
+
+    // A VID-only node is served its share.
+    let (sender, receiver) = oneshot::channel();
+    request_sender
+        .send((vid_share_request(11, 1), ResponseChannel { sender }))
+        .await
+        .unwrap();
+    let served = async_timeout(Duration::from_secs(2), receiver)
+        .await
+        .expect("Timed out waiting for the VID share to be served")
+        .unwrap();
+    let ResponseMessage::Found(SequencingMessage::Da(DaConsensusMessage::VidDisperseMsg(share))) =
+        response(&served)
+    else {
+        panic!("Expected the VID share to be served");
+    };
+    assert_eq!(share.data.recipient_key, key_pair_for_id(11).1);
+    assert!(vid_scheme(vid_membership.total_nodes())
+        .verify_share(
+            &share.data.share,
+            &share.data.common,
+            &share.data.payload_commitment
+        )
+        .unwrap()
+        .is_ok());
+
+    // The shares were dispersed to exactly the VID storage nodes.
+    let dispersed: BTreeSet<_> = handle
+        .hotshot
+        .consensus()
+        .read()
+        .await
+        .vid_shares()
+        .get(&view)
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    assert_eq!(dispersed, vid_nodes);
+
+    // A node outside the VID membership is denied.
+    let (sender, receiver) = oneshot::channel();
+    request_sender
+        .send((vid_share_request(12, 1), ResponseChannel { sender }))
+        .await
+        .unwrap();
+    assert!(matches!(
+        response(&receiver.await.unwrap()),
+        ResponseMessage::Denied
+    ));
+}
+
+/// A response signed by the committee member that was asked is accepted, while a forged,
+/// tampered, replayed or non-member response is rejected.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_response_signature_is_verified() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let membership = handle.hotshot.memberships.da_membership.clone();
+    let (private_key, public_key) = key_pair_for_id(2);
+    let state = NetworkResponseState::<TestTypes>::with_signer(
+        handle.hotshot.consensus(),
+        handle.hotshot.memberships.quorum_membership.clone().into(),
+        handle.hotshot.memberships.vid_membership.clone().into(),
+        public_key,
+        Arc::new(InMemorySigner::new(private_key)),
+    )
+    .with_decided_upgrade_certificate(Arc::new(RwLock::new(Some(build_upgrade_certificate(
+        ViewNumber::new(1),
+    )))));
+    let (mut request_sender, request_receiver) = mpsc::channel(1);
+    let _task =
+        run_response_task_until_cancelled(state, request_receiver, CancellationToken::new());
+
+    let served = serve(&mut request_sender, proposal_request(1, 1)).await;
+    let request = RequestKind::<TestTypes>::Proposal(ViewNumber::new(1));
+
+    // The response from the node we asked is accepted.
+    let legitimate = signed_response(&served);
+    assert_eq!(
+        legitimate
+            .clone()
+            .verify(&request, &public_key, &membership)
+            .unwrap(),
+        ResponseMessage::NotFound
+    );
+
+    // It isn't accepted as coming from another node, or in answer to another request.
+    assert!(legitimate
+        .clone()
+        .verify(&request, &key_pair_for_id(3).1, &membership)
+        .is_err());
+    assert!(legitimate
+        .clone()
+        .verify(
+            &RequestKind::Proposal(ViewNumber::new(2)),
+            &public_key,
+            &membership
+        )
+        .is_err());
+
+    // A response altered after signing is rejected.
+    let tampered = SignedResponse {
+        response: ResponseMessage::Denied,
+        ..legitimate
+    };
+    assert!(tampered.verify(&request, &public_key, &membership).is_err());
+
+    // A response properly signed by a node outside the committee is rejected.
+    let (outsider_private_key, outsider_public_key) = key_pair_for_id(100);
+    let outsider = SignedResponse::new(
+        &request,
+        ResponseMessage::NotFound,
+        &InMemorySigner::new(outsider_private_key),
+    )
+    .await
+    .unwrap();
+    assert!(outsider
+        .verify(&request, &outsider_public_key, &membership)
+        .is_err());
+}
+
+/// Responses are only signed once the network has upgraded by the view of the request, and the
+/// requester only accepts unsigned responses before then.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_responses_are_signed_only_after_upgrade() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let membership = handle.hotshot.memberships.da_membership.clone();
+    let upgrade_certificate = Some(build_upgrade_certificate(ViewNumber::new(2)));
+    let (private_key, public_key) = key_pair_for_id(2);
+    let state = NetworkResponseState::<TestTypes>::with_signer(
+        handle.hotshot.consensus(),
+        handle.hotshot.memberships.quorum_membership.clone().into(),
+        handle.hotshot.memberships.vid_membership.clone().into(),
+        public_key,
+        Arc::new(InMemorySigner::new(private_key)),
+    )
+    .with_decided_upgrade_certificate(Arc::new(RwLock::new(upgrade_certificate.clone())));
+    let (mut request_sender, request_receiver) = mpsc::channel(1);
+    let _task =
+        run_response_task_until_cancelled(state, request_receiver, CancellationToken::new());
+
+    // Before the upgrade, the response is sent unsigned, and accepted as such.
+    let before = response_message(&serve(&mut request_sender, proposal_request(1, 1)).await);
+    assert!(matches!(
+        before,
+        DataMessage::DataResponse(ResponseMessage::NotFound)
+    ));
+    let before_request = RequestKind::<TestTypes>::Proposal(ViewNumber::new(1));
+    assert_eq!(
+        verify_response(
+            &bincode::serialize(&before).unwrap(),
+            &before_request,
+            &public_key,
+            &membership,
+            &upgrade_certificate,
+        ),
+        Some(ResponseMessage::NotFound)
+    );
+
+    // From the upgrade, the response is signed, and an unsigned one is rejected.
+    let after = response_message(&serve(&mut request_sender, proposal_request(2, 2)).await);
+    assert!(matches!(after, DataMessage::SignedDataResponse(_)));
+    let after_request = RequestKind::<TestTypes>::Proposal(ViewNumber::new(2));
+    assert_eq!(
+        verify_response(
+            &bincode::serialize(&after).unwrap(),
+            &after_request,
+            &public_key,
+            &membership,
+            &upgrade_certificate,
+        ),
+        Some(ResponseMessage::NotFound)
+    );
+    assert_eq!(
+        verify_response(
+            &bincode::serialize(&DataMessage::<TestTypes>::DataResponse(
+                ResponseMessage::NotFound
+            ))
+            .unwrap(),
+            &after_request,
+            &public_key,
+            &membership,
+            &upgrade_certificate,
+        ),
+        None
+    );
+}
+
+/// A restarted node requests the proposals it's missing for the views before its start view from
+/// their leaders over the network, keeps only the valid ones, and then serves them to peers
+/// catching up.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_backfilled_proposals_are_served() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let launcher =
+        TestDescription::default_multiple_rounds().gen_launcher::<TestTypes, MemoryImpl>(0);
+    let handle = build_system_handle_from_launcher(&launcher, 2).await.0;
+    let consensus = handle.hotshot.consensus();
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator =
+        TestViewGenerator::generate(quorum_membership.clone(), da_membership.clone());
+    let mut proposals = Vec::new();
+    for view in (&mut generator).take(3).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+    }
+
+    // A proposal for the wrong view, or with a signature over another proposal, isn't saved.
+    assert!(save_backfilled_proposal(
+        &consensus,
+        &quorum_membership,
+        ViewNumber::new(1),
+        proposals[2].clone()
+    )
+    .await
+    .is_err());
+    let mut forged = proposals[0].clone();
+    forged.signature = proposals[2].signature.clone();
+    assert!(
+        save_backfilled_proposal(&consensus, &quorum_membership, ViewNumber::new(1), forged)
+            .await
+            .is_err()
+    );
+    assert!(!consensus
+        .read()
+        .await
+        .last_proposals()
+        .contains_key(&ViewNumber::new(1)));
+
+    // The leaders of views 1 and 3, on the same network, still have their proposals.
+    let mut leaders = Vec::new();
+    for (view, proposal) in [(1, &proposals[0]), (3, &proposals[2])] {
+        let leader = da_membership.leader(ViewNumber::new(view));
+        let id = (0..).find(|id| key_pair_for_id(*id).1 == leader).unwrap();
+        let leader_handle = build_system_handle_from_launcher(&launcher, id).await.0;
+        assert!(leader_handle
+            .hotshot
+            .consensus()
+            .write()
+            .await
+            .backfill_proposal(proposal.clone()));
+        leaders.push(leader_handle);
+    }
+
+    // Restarting at view 4 with the proposal for view 2 already saved, we request the others.
+    assert!(consensus
+        .write()
+        .await
+        .backfill_proposal(proposals[1].clone()));
+    let mut state = NetworkRequestState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    state.backfill_proposals(ViewNumber::new(4), 10).await;
+    assert_eq!(
+        state.backfill_tasks.keys().copied().collect::<Vec<_>>(),
+        vec![ViewNumber::new(1), ViewNumber::new(3)]
+    );
+    // Backfills aren't requests for the views' data, so they don't hold up those requests.
+    assert!(state.spawned_tasks.is_empty());
+    let backfills: Vec<_> = std::mem::take(&mut state.backfill_tasks)
+        .into_values()
+        .collect();
+    async_timeout(Duration::from_secs(5), join_all(backfills))
+        .await
+        .expect("Timed out backfilling the proposals");
+    for (view, proposal) in [(1, &proposals[0]), (3, &proposals[2])] {
+        assert_eq!(
+            consensus
+                .read()
+                .await
+                .last_proposals()
+                .get(&ViewNumber::new(view)),
+            Some(proposal)
+        );
+    }
+
+    let (private_key, public_key) = key_pair_for_id(2);
+    let response_state = NetworkResponseState::<TestTypes>::with_signer(
+        Arc::clone(&consensus),
+        quorum_membership.into(),
+        handle.hotshot.memberships.vid_membership.clone().into(),
+        public_key,
+        Arc::new(InMemorySigner::new(private_key)),
+    );
+    let (mut request_sender, request_receiver) = mpsc::channel(1);
+    let _task = run_response_task_until_cancelled(
+        response_state,
+        request_receiver,
+        CancellationToken::new(),
+    );
+
+    let (sender, receiver) = oneshot::channel();
+    request_sender
+        .send((proposal_request(3, 3), ResponseChannel { sender }))
+        .await
+        .unwrap();
+    let served = async_timeout(Duration::from_secs(2), receiver)
+        .await
+        .expect("Timed out waiting for the backfilled proposal to be served")
+        .unwrap();
+    assert_eq!(
+        response(&served),
+        ResponseMessage::Found(SequencingMessage::General(
+            GeneralConsensusMessage::Proposal(proposals[2].clone())
+        ))
+    );
+}
+
+/// A node missing the payload for a view keeps only a DA proposal signed by the view's DA leader,
+/// saving its payload, and serves the proposal to peers which missed it too.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_requested_da_proposals_are_served() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let consensus = handle.hotshot.consensus();
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator =
+        TestViewGenerator::generate(quorum_membership.clone(), da_membership.clone());
+    let mut proposals = Vec::new();
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        proposals.push(view.da_proposal.clone());
+    }
+
+    // A proposal for the wrong view, or with a signature over another payload, isn't saved.
+    assert!(save_requested_da_proposal(
+        &consensus,
+        &da_membership,
+        ViewNumber::new(1),
+        proposals[1].clone()
+    )
+    .await
+    .is_err());
+    let mut forged = proposals[0].clone();
+    forged.data.encoded_transactions =
+        Arc::from(TestTransaction::encode(&[TestTransaction::new(vec![0])]));
+    assert!(
+        save_requested_da_proposal(&consensus, &da_membership, ViewNumber::new(1), forged)
+            .await
+            .is_err()
+    );
+    assert!(!consensus
+        .read()
+        .await
+        .saved_payloads()
+        .contains_key(&ViewNumber::new(1)));
+
+    save_requested_da_proposal(
+        &consensus,
+        &da_membership,
+        ViewNumber::new(1),
+        proposals[0].clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        consensus
+            .read()
+            .await
+            .saved_payloads()
+            .get(&ViewNumber::new(1)),
+        Some(&proposals[0].data.encoded_transactions)
+    );
+
+    let (private_key, public_key) = key_pair_for_id(2);
+    let response_state = NetworkResponseState::<TestTypes>::with_signer(
+        Arc::clone(&consensus),
+        quorum_membership.into(),
+        handle.hotshot.memberships.vid_membership.clone().into(),
+        public_key,
+        Arc::new(InMemorySigner::new(private_key)),
+    );
+    let (mut request_sender, request_receiver) = mpsc::channel(2);
+    let _task = run_response_task_until_cancelled(
+        response_state,
+        request_receiver,
+        CancellationToken::new(),
+    );
+
+    let (sender, receiver) = oneshot::channel();
+    request_sender
+        .send((da_proposal_request(1), ResponseChannel { sender }))
+        .await
+        .unwrap();
+    let served = async_timeout(Duration::from_secs(2), receiver)
+        .await
+        .expect("Timed out waiting for the DA proposal to be served")
+        .unwrap();
+    assert_eq!(
+        response(&served),
+        ResponseMessage::Found(SequencingMessage::Da(DaConsensusMessage::DaProposal(
+            proposals[0].clone()
+        )))
+    );
+
+    // We never got the proposal for view 2.
+    let (sender, receiver) = oneshot::channel();
+    request_sender
+        .send((da_proposal_request(2), ResponseChannel { sender }))
+        .await
+        .unwrap();
+    assert_eq!(
+        response(&receiver.await.unwrap()),
+        ResponseMessage::NotFound
+    );
+}
+
+/// A ranged VID request is answered with the shares we already calculated for the views in the
+/// range, capped at `MAX_VID_RANGE` views, without calculating any from stored payloads. It's
+/// denied before the upgrade.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_response_task_serves_vid_range() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let consensus = handle.hotshot.consensus();
+
+    let (private_key, public_key) = key_pair_for_id(2);
+    let signer = Arc::new(InMemorySigner::new(private_key));
+    let vid_membership = Arc::new(handle.hotshot.memberships.vid_membership.clone());
+
+    // We have the payloads for every view but view 2, and for a view beyond the cap, but only
+    // calculated the shares for those other than view 3.
+    let last_view = MAX_VID_RANGE + 1;
+    for view in (1..=last_view).filter(|view| *view != 2) {
+        consensus
+            .write()
+            .await
+            .update_saved_payloads(
+                ViewNumber::new(view),
+                Arc::from(TestTransaction::encode(&[TestTransaction::new(vec![
+                    u8::try_from(view).unwrap(),
+                ])])),
+            )
+            .unwrap();
+        if view != 3 {
+            Consensus::calculate_and_update_vid(
+                Arc::clone(&consensus),
+                ViewNumber::new(view),
+                Arc::clone(&vid_membership),
+                signer.as_ref(),
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    let decided_upgrade_certificate = Arc::new(RwLock::new(Some(build_upgrade_certificate(
+        ViewNumber::new(1),
+    ))));
+    let state = NetworkResponseState::<TestTypes>::with_signer(
+        Arc::clone(&consensus),
+        handle.hotshot.memberships.quorum_membership.clone().into(),
+        vid_membership,
+        public_key,
+        signer,
+    )
+    .with_decided_upgrade_certificate(Arc::clone(&decided_upgrade_certificate));
+    let (mut request_sender, request_receiver) = mpsc::channel(2);
+    let _task =
+        run_response_task_until_cancelled(state, request_receiver, CancellationToken::new());
+
+    let (sender, receiver) = oneshot::channel();
+    request_sender
+        .send((
+            vid_range_request(1, 1, last_view),
+            ResponseChannel { sender },
+        ))
+        .await
+        .unwrap();
+    let served = async_timeout(Duration::from_secs(10), receiver)
+        .await
+        .expect("Timed out waiting for the VID shares to be served")
+        .unwrap();
+    let ResponseMessage::FoundMany(messages) = response(&served) else {
+        panic!("Expected the VID shares to be served");
+    };
+    let views: Vec<_> = messages
+        .into_iter()
+        .map(|message| {
+            let SequencingMessage::Da(DaConsensusMessage::VidDisperseMsg(share)) = message else {
+                panic!("Expected a VID share, got {message:?}");
+            };
+            assert_eq!(share.data.recipient_key, key_pair_for_id(1).1);
+            *share.data.view_number
+        })
+        .collect();
+    assert_eq!(
+        views,
+        (1..=MAX_VID_RANGE)
+            .filter(|view| *view != 2 && *view != 3)
+            .collect::<Vec<_>>()
+    );
+
+    // Nothing is served for a range we have no shares in, even if we have its payload.
+    let (sender, receiver) = oneshot::channel();
+    request_sender
+        .send((vid_range_request(1, 2, 3), ResponseChannel { sender }))
+        .await
+        .unwrap();
+    assert_eq!(
+        response(&receiver.await.unwrap()),
+        ResponseMessage::NotFound
+    );
+
+    // Without the upgrade, the range isn't served at all.
+    *decided_upgrade_certificate.write().await = None;
+    assert_eq!(
+        response(&serve(&mut request_sender, vid_range_request(1, 1, last_view)).await),
+        ResponseMessage::Denied
+    );
+}
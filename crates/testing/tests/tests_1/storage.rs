@@ -0,0 +1,296 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::{
+    node_types::TestTypes, state_types::TestValidatedState, storage_types::TestStorage,
+};
+use hotshot_testing::{
+    helpers::{build_system_handle, key_pair_for_id},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    consensus::CommitmentMap,
+    data::{DaProposal, Leaf, ViewNumber},
+    event::HotShotAction,
+    message::Proposal,
+    simple_certificate::QuorumCertificate,
+    simple_vote::{DaData, DaVote},
+    traits::{node_implementation::ConsensusTime, storage::Storage},
+    utils::{View, ViewInner},
+    vote::Vote,
+};
+
+/// Everything a node persists that can be loaded back.
+struct StoredData {
+    high_qc: QuorumCertificate<TestTypes>,
+    leaves: CommitmentMap<Leaf<TestTypes>>,
+    state: BTreeMap<ViewNumber, View<TestTypes>>,
+    das: BTreeMap<ViewNumber, Proposal<TestTypes, DaProposal<TestTypes>>>,
+    da_votes: BTreeMap<ViewNumber, Vec<DaVote<TestTypes>>>,
+}
+
+/// Generate a few views worth of data to store.
+async fn stored_data() -> StoredData {
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let mut views = Vec::new();
+    for _ in 0..3 {
+        views.push(generator.next().await.unwrap());
+    }
+
+    let mut data = StoredData {
+        high_qc: views[2].quorum_proposal.data.justify_qc.clone(),
+        leaves: CommitmentMap::new(),
+        state: BTreeMap::new(),
+        das: BTreeMap::new(),
+        da_votes: BTreeMap::new(),
+    };
+    for view in views {
+        let payload_commit = view.vid_proposal.0[0].data.payload_commitment;
+        let mut votes: Vec<_> = (0..3)
+            .map(|id| {
+                let (private_key, public_key) = key_pair_for_id(id);
+                DaVote::create_signed_vote(
+                    DaData { payload_commit },
+                    view.view_number,
+                    &public_key,
+                    &private_key,
+                )
+                .unwrap()
+            })
+            .collect();
+        votes.sort_by_key(|vote| vote.signing_key());
+        data.da_votes.insert(view.view_number, votes);
+        data.leaves.insert(view.leaf.commit(), view.leaf.clone());
+        data.state.insert(
+            view.view_number,
+            View {
+                view_inner: ViewInner::Leaf {
+                    leaf: view.leaf.commit(),
+                    state: Arc::new(TestValidatedState::default()),
+                    delta: None,
+                },
+            },
+        );
+        data.das.insert(view.view_number, view.da_proposal);
+    }
+    data
+}
+
+/// Write `data` to `storage`.
+async fn store(storage: &impl Storage<TestTypes>, data: &StoredData) {
+    storage.update_high_qc(data.high_qc.clone()).await.unwrap();
+    storage
+        .update_undecided_state(data.leaves.clone(), data.state.clone())
+        .await
+        .unwrap();
+    for proposal in data.das.values() {
+        storage.append_da(proposal).await.unwrap();
+    }
+    for (view, votes) in &data.da_votes {
+        // Votes are flushed a few at a time as they arrive.
+        let (first, rest) = votes.split_at(1);
+        storage.append_da_votes(*view, first).await.unwrap();
+        storage.append_da_votes(*view, rest).await.unwrap();
+    }
+}
+
+/// Check that `storage` loads back exactly `data`.
+async fn assert_stored(storage: &impl Storage<TestTypes>, data: &StoredData) {
+    assert_eq!(
+        storage.load_high_qc().await.unwrap(),
+        Some(data.high_qc.clone())
+    );
+    assert_eq!(
+        storage.load_undecided_state().await.unwrap(),
+        Some((data.leaves.clone(), data.state.clone()))
+    );
+    assert_eq!(storage.load_da_proposals().await.unwrap(), data.das);
+    for (view, votes) in &data.da_votes {
+        let mut loaded = storage.load_da_votes(*view).await.unwrap();
+        loaded.sort_by_key(|vote| vote.signing_key());
+        assert_eq!(&loaded, votes);
+    }
+    assert!(storage
+        .load_da_votes(ViewNumber::new(100))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+/// Check that `storage` journals a dispersal and forgets each share once it has been sent, and
+/// refuses a second vote in the same view.
+async fn assert_journals(storage: &impl Storage<TestTypes>) {
+    let view = ViewNumber::new(3);
+    let messages: HashMap<_, _> = (0..4)
+        .map(|id| {
+            let (_, public_key) = key_pair_for_id(id);
+            (public_key, vec![u8::try_from(id).unwrap(); 16])
+        })
+        .collect();
+    storage.append_vid_dispersal(view, &messages).await.unwrap();
+
+    let sent = messages.keys().next().unwrap().clone();
+    storage.record_vid_share_sent(view, &sent).await.unwrap();
+    let mut pending = messages.clone();
+    pending.remove(&sent);
+    assert_eq!(
+        storage.load_pending_vid_dispersals().await.unwrap(),
+        BTreeMap::from([(view, pending)])
+    );
+
+    storage
+        .record_action(view, HotShotAction::Vote)
+        .await
+        .unwrap();
+    assert!(storage
+        .record_action(view, HotShotAction::Vote)
+        .await
+        .is_err());
+}
+
+/// Check that garbage collecting the journal only drops dispersals of earlier views.
+async fn assert_journal_gc(storage: &impl Storage<TestTypes>) {
+    let view = ViewNumber::new(3);
+    storage.gc_vid_dispersals(view).await.unwrap();
+    assert!(storage
+        .load_pending_vid_dispersals()
+        .await
+        .unwrap()
+        .contains_key(&view));
+
+    storage.gc_vid_dispersals(view + 1).await.unwrap();
+    assert!(storage
+        .load_pending_vid_dispersals()
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+/// Check that garbage collecting DA votes only drops the votes of earlier views.
+async fn assert_da_vote_gc(storage: &impl Storage<TestTypes>, data: &StoredData) {
+    let (&last_view, last_votes) = data.da_votes.last_key_value().unwrap();
+    storage.gc_da_votes(last_view).await.unwrap();
+    for view in data.da_votes.keys().filter(|view| **view < last_view) {
+        assert!(storage.load_da_votes(*view).await.unwrap().is_empty());
+    }
+    assert_eq!(
+        storage.load_da_votes(last_view).await.unwrap().len(),
+        last_votes.len()
+    );
+
+    storage.gc_da_votes(last_view + 1).await.unwrap();
+    assert!(storage.load_da_votes(last_view).await.unwrap().is_empty());
+}
+
+/// The in-memory storage loads back what was stored.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_test_storage_round_trip() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let data = stored_data().await;
+    let storage = TestStorage::<TestTypes>::default();
+    assert_eq!(storage.load_high_qc().await.unwrap(), None);
+    assert_eq!(storage.load_undecided_state().await.unwrap(), None);
+
+    store(&storage, &data).await;
+    assert_stored(&storage, &data).await;
+    assert_journals(&storage).await;
+    assert_journal_gc(&storage).await;
+    assert_da_vote_gc(&storage, &data).await;
+}
+
+#[cfg(feature = "rocksdb")]
+mod rocksdb_storage {
+    use std::path::PathBuf;
+
+    use hotshot_example_types::rocksdb_storage::RocksDbStorage;
+
+    use super::*;
+
+    /// A fresh directory for a database, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            Self(
+                std::env::temp_dir()
+                    .join(format!("hotshot-rocksdb-storage-{}", rand::random::<u64>())),
+            )
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// The RocksDB storage loads back what was stored, just like the in-memory storage.
+    #[cfg(test)]
+    #[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+    #[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+    async fn test_rocksdb_storage_round_trip() {
+        async_compatibility_layer::logging::setup_logging();
+        async_compatibility_layer::logging::setup_backtrace();
+
+        let dir = TempDir::new();
+        let data = stored_data().await;
+        let storage = RocksDbStorage::<TestTypes>::open(&dir.0).unwrap();
+        assert_eq!(storage.load_high_qc().await.unwrap(), None);
+        assert_eq!(storage.load_undecided_state().await.unwrap(), None);
+
+        store(&storage, &data).await;
+        assert_stored(&storage, &data).await;
+        assert_journals(&storage).await;
+        assert_journal_gc(&storage).await;
+        assert_da_vote_gc(&storage, &data).await;
+        assert_eq!(storage.last_voted_view().unwrap(), Some(ViewNumber::new(3)));
+    }
+
+    /// Everything stored is recovered when the storage is reopened after a simulated restart, and
+    /// the node still can't vote twice in a view it voted in before the restart.
+    #[cfg(test)]
+    #[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+    #[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+    async fn test_rocksdb_storage_crash_recovery() {
+        async_compatibility_layer::logging::setup_logging();
+        async_compatibility_layer::logging::setup_backtrace();
+
+        let dir = TempDir::new();
+        let data = stored_data().await;
+        let pending = {
+            let storage = RocksDbStorage::<TestTypes>::open(&dir.0).unwrap();
+            store(&storage, &data).await;
+            assert_journals(&storage).await;
+            storage.load_pending_vid_dispersals().await.unwrap()
+            // Dropping the storage closes the database, as the process exiting would.
+        };
+
+        let storage = RocksDbStorage::<TestTypes>::open(&dir.0).unwrap();
+        assert_stored(&storage, &data).await;
+        assert_eq!(
+            storage.load_pending_vid_dispersals().await.unwrap(),
+            pending
+        );
+        assert_eq!(storage.last_voted_view().unwrap(), Some(ViewNumber::new(3)));
+        assert!(storage
+            .record_action(ViewNumber::new(3), HotShotAction::Vote)
+            .await
+            .is_err());
+        storage
+            .record_action(ViewNumber::new(4), HotShotAction::Vote)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use std::{collections::HashSet, sync::Arc};
+
+use futures::StreamExt;
+use hotshot::HotShotError;
+use hotshot_example_types::{node_types::TestTypes, state_types::TestValidatedState};
+use hotshot_testing::{
+    helpers::build_system_handle,
+    overall_safety_task::{OverallSafetyTaskErr, RoundResult, ViewStatus},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    data::Leaf, error::RoundTimedoutState, event::LeafInfo, signature_key::BLSPubKey,
+    traits::election::Membership,
+};
+
+/// A round of three nodes in which `deciders` decided `leaf`, proposed by `proposer`, checked
+/// requiring all of them to.
+fn round(
+    leaf: &Leaf<TestTypes>,
+    proposer: BLSPubKey,
+    deciders: &[usize],
+) -> RoundResult<TestTypes> {
+    let mut result = RoundResult::default();
+    for id in deciders {
+        let leaf_chain = vec![LeafInfo::new(
+            leaf.clone(),
+            Arc::new(TestValidatedState::default()),
+            None,
+            None,
+            Some(proposer),
+        )];
+        result.insert_into_result(*id, (leaf_chain, leaf.justify_qc()), None);
+    }
+    update(&mut result, leaf);
+    result
+}
+
+/// Update the status of `result`, requiring all three nodes to decide `leaf`.
+fn update(result: &mut RoundResult<TestTypes>, leaf: &Leaf<TestTypes>) {
+    result.update_status(2, 3, leaf, true, true, 0, Some(&HashSet::from([0, 1, 2])));
+}
+
+/// A view decided by enough nodes only succeeds once every expected node decides it, and fails
+/// once a missing node reports it failed the view.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_node_missed_view() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    let leaf = generator.next().await.unwrap().leaf;
+    let proposer = quorum_membership.leader(leaf.view_number());
+
+    assert!(matches!(
+        round(&leaf, proposer, &[0, 1, 2]).status,
+        ViewStatus::Ok
+    ));
+
+    let mut result = round(&leaf, proposer, &[0, 1]);
+    assert!(matches!(result.status, ViewStatus::InProgress));
+    assert_eq!(result.straggler, Some(2));
+
+    result.failed_nodes.insert(
+        2,
+        Arc::new(HotShotError::ViewTimeoutError {
+            view_number: leaf.view_number(),
+            state: RoundTimedoutState::TestCollectRoundEventsTimedOut,
+        }),
+    );
+    update(&mut result, &leaf);
+    assert!(matches!(
+        result.status,
+        ViewStatus::Err(OverallSafetyTaskErr::NodeMissedView { node_id: 2, view })
+            if view == leaf.view_number()
+    ));
+}
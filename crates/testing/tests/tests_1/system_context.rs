@@ -0,0 +1,541 @@
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc, time::Duration};
+
+use async_compatibility_layer::art::async_timeout;
+use committable::Committable;
+use hotshot::{types::EventType, HotShotError, HotShotInitializer, Networks, SystemContext};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes},
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_task_impls::events::HotShotEvent;
+use hotshot_testing::{
+    helpers::{
+        build_cert, build_system_handle, build_system_handle_from_description,
+        build_system_handle_from_launcher, key_pair_for_id,
+    },
+    test_builder::TestDescription,
+};
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    constants::MAX_TRANSACTION_POW_DIFFICULTY,
+    data::{Leaf, ViewChangeEvidence, ViewNumber},
+    event::EventFilter,
+    message::TxPriority,
+    simple_certificate::{QuorumCertificate, TimeoutCertificate, UpgradeCertificate},
+    simple_vote::{TimeoutData, TimeoutVote, UpgradeProposalData, UpgradeVote},
+    traits::{
+        consensus_api::ConsensusApi,
+        network::{ConnectedNetwork, NetworkError},
+        node_implementation::ConsensusTime,
+        states::ValidatedState,
+    },
+};
+use vbs::version::Version;
+
+/// Transactions submitted before consensus has started are queued, and published with their
+/// priority once the node becomes ready rather than being silently dropped.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_transactions_queued_until_ready() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let mut events = handle.event_stream_known_impl();
+
+    assert!(!handle.hotshot.is_ready().await);
+
+    let transaction = TestTransaction::new(vec![1, 2, 3]);
+    handle
+        .submit_transaction_with_priority(transaction.clone(), TxPriority::High)
+        .await
+        .expect("Transactions should be queued before the node is ready");
+    assert_eq!(handle.hotshot.num_pending_transactions().await, 1);
+
+    handle.hotshot.start_consensus().await.unwrap();
+
+    assert!(handle.hotshot.is_ready().await);
+    assert_eq!(handle.hotshot.num_pending_transactions().await, 0);
+
+    // The queued transaction is published once we're ready.
+    let published = async_timeout(Duration::from_secs(5), async {
+        while let Ok(event) = events.recv_direct().await {
+            if let EventType::Transactions { transactions } = event.event {
+                return transactions;
+            }
+        }
+        vec![]
+    })
+    .await
+    .expect("Timed out waiting for the queued transaction to be published");
+
+    assert_eq!(published, vec![(transaction, TxPriority::High)]);
+}
+
+/// Only the configured number of transactions are queued before the node is ready; submitting
+/// more fails until it is.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_pending_transactions_bounded() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let launcher = TestDescription::default()
+        .gen_launcher::<TestTypes, MemoryImpl>(1)
+        .modify_default_config(|config| config.max_pending_transactions = 2);
+    let handle = build_system_handle_from_launcher(&launcher, 1).await.0;
+
+    for id in 0..2 {
+        handle
+            .submit_transaction(TestTransaction::new(vec![id]))
+            .await
+            .expect("Transactions should be queued before the node is ready");
+    }
+    assert!(matches!(
+        handle
+            .submit_transaction(TestTransaction::new(vec![2]))
+            .await,
+        Err(HotShotError::PendingTransactionsFull { max: 2 })
+    ));
+    assert_eq!(handle.hotshot.num_pending_transactions().await, 2);
+
+    // Once ready, the queue is flushed and transactions are published directly.
+    handle.hotshot.start_consensus().await.unwrap();
+    assert_eq!(handle.hotshot.num_pending_transactions().await, 0);
+    handle
+        .submit_transaction(TestTransaction::new(vec![2]))
+        .await
+        .expect("Transactions should be published once the node is ready");
+}
+
+/// A single node leads every view, so it can propose for consecutive views on demand; a node
+/// that doesn't lead the next view can't.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_propose_now() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let description = TestDescription {
+        num_nodes_with_stake: 1,
+        start_nodes: 1,
+        num_bootstrap_nodes: 1,
+        da_staked_committee_size: 1,
+        ..TestDescription::default()
+    };
+    let handle = build_system_handle_from_description(description, 0).await.0;
+
+    for view in 1..=3 {
+        assert_eq!(handle.propose_now().await.unwrap(), ViewNumber::new(view));
+    }
+    let consensus = handle.consensus();
+    let proposed_views: Vec<_> = consensus
+        .read()
+        .await
+        .last_proposals()
+        .keys()
+        .copied()
+        .collect();
+    assert_eq!(
+        proposed_views,
+        (1..=3).map(ViewNumber::new).collect::<Vec<_>>()
+    );
+
+    // Node 1 leads view 1, so node 2 can't propose for it.
+    let handle = build_system_handle(2).await.0;
+    assert!(handle.propose_now().await.is_err());
+    assert!(handle.consensus().read().await.last_proposals().is_empty());
+}
+
+/// A failure to seed the genesis events is reported to the caller rather than panicking.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_start_consensus_with_closed_channel() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let (handle, internal_sender, _internal_receiver) = build_system_handle(1).await;
+    internal_sender.close();
+
+    assert!(matches!(
+        handle.hotshot.start_consensus().await,
+        Err(HotShotError::StartupFailed { .. })
+    ));
+    assert!(!handle.hotshot.is_ready().await);
+}
+
+/// The exported leader schedule agrees view-by-view with leader selection.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_leader_schedule() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+
+    let schedule = handle.leader_schedule(ViewNumber::new(3), 25);
+    assert_eq!(schedule.len(), 25);
+    for ((view, leader), expected_view) in schedule.into_iter().zip(3..) {
+        assert_eq!(view, ViewNumber::new(expected_view));
+        assert_eq!(leader, handle.leader(view).await);
+    }
+
+    assert!(handle.leader_schedule(ViewNumber::new(3), 0).is_empty());
+}
+
+/// Events rejected by the installed filter never reach subscribers, while the others still do.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_event_filter_drops_rejected_events() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let mut events = handle.event_stream_known_impl();
+    handle
+        .set_event_filter(EventFilter::new(|event| {
+            !matches!(event, EventType::Transactions { .. })
+        }))
+        .await;
+
+    // One transaction is published when consensus starts, after the genesis decide, and one
+    // straight away.
+    handle
+        .submit_transaction(TestTransaction::new(vec![1]))
+        .await
+        .unwrap();
+    handle.hotshot.start_consensus().await.unwrap();
+    handle
+        .submit_transaction(TestTransaction::new(vec![2]))
+        .await
+        .unwrap();
+    assert_eq!(handle.hotshot.num_pending_transactions().await, 0);
+
+    // The transactions' events are sent from spawned tasks, so keep receiving until none has
+    // arrived for a while rather than only draining what's already there.
+    let mut decides = 0;
+    while let Ok(Ok(event)) = async_timeout(Duration::from_secs(1), events.recv_direct()).await {
+        match event.event {
+            EventType::Transactions { .. } => panic!("A filtered out event was emitted"),
+            EventType::Decide { .. } => decides += 1,
+            _ => {}
+        }
+    }
+    assert_eq!(decides, 1);
+}
+
+/// The genesis decide sent when consensus starts counts the one view it decides.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_genesis_decide_view_count() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let mut events = handle.event_stream_known_impl();
+    handle.hotshot.start_consensus().await.unwrap();
+
+    let (leaf_chain, decided_view_count) = loop {
+        let event = async_timeout(Duration::from_secs(1), events.recv_direct())
+            .await
+            .expect("Timed out waiting for the genesis decide")
+            .unwrap();
+        if let EventType::Decide {
+            leaf_chain,
+            decided_view_count,
+            ..
+        } = event.event
+        {
+            break (leaf_chain, decided_view_count);
+        }
+    };
+    assert_eq!(decided_view_count, 1);
+    assert_eq!(decided_view_count, leaf_chain.len());
+}
+
+/// A view change can only be forced with a valid certificate for the target view.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_force_view_change() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let (handle, _internal_sender, mut internal_receiver) = build_system_handle(1).await;
+    let membership = handle.hotshot.memberships.quorum_membership.clone();
+    let (private_key, public_key) = key_pair_for_id(1);
+    let timeout_cert = |view: u64| {
+        build_cert::<
+            TestTypes,
+            TimeoutData<TestTypes>,
+            TimeoutVote<TestTypes>,
+            TimeoutCertificate<TestTypes>,
+        >(
+            TimeoutData {
+                view: ViewNumber::new(view),
+            },
+            &membership,
+            ViewNumber::new(view),
+            &public_key,
+            &private_key,
+        )
+    };
+
+    // The certificate is for a different view.
+    assert!(handle
+        .force_view_change(
+            ViewNumber::new(6),
+            ViewChangeEvidence::Timeout(timeout_cert(4))
+        )
+        .await
+        .is_err());
+
+    // The certificate's signatures aren't for its data.
+    let mut forged_cert = timeout_cert(4);
+    forged_cert.vote_commitment = TimeoutData::<TestTypes> {
+        view: ViewNumber::new(5),
+    }
+    .commit();
+    assert!(handle
+        .force_view_change(ViewNumber::new(5), ViewChangeEvidence::Timeout(forged_cert))
+        .await
+        .is_err());
+
+    // The view isn't ahead of the current one.
+    assert!(handle
+        .force_view_change(
+            ViewNumber::genesis(),
+            ViewChangeEvidence::Timeout(timeout_cert(4))
+        )
+        .await
+        .is_err());
+
+    // A timeout certificate for the previous view justifies the view change.
+    handle
+        .force_view_change(
+            ViewNumber::new(5),
+            ViewChangeEvidence::Timeout(timeout_cert(4)),
+        )
+        .await
+        .unwrap();
+    async_timeout(Duration::from_secs(5), async {
+        while let Ok(event) = internal_receiver.recv_direct().await {
+            if let HotShotEvent::ViewChange(view) = event.as_ref() {
+                assert_eq!(*view, ViewNumber::new(5));
+                return;
+            }
+        }
+    })
+    .await
+    .expect("Timed out waiting for the view change");
+}
+
+/// A node started from a snapshot has the payloads it saved, so it can answer VID requests for
+/// their views right away.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_snapshot_restores_saved_payloads() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let hotshot = &handle.hotshot;
+
+    let (validated_state, _) = TestValidatedState::genesis(&TestInstanceState {});
+    let payload: Arc<[u8]> = Arc::from(vec![1, 2, 3]);
+    let initializer = HotShotInitializer::<TestTypes>::from_snapshot(
+        Leaf::genesis(&validated_state, &TestInstanceState {}).await,
+        TestInstanceState {},
+        None,
+        ViewNumber::genesis(),
+        BTreeMap::new(),
+        QuorumCertificate::genesis(&validated_state, &TestInstanceState {}).await,
+        Vec::new(),
+        BTreeMap::new(),
+        BTreeMap::from([(ViewNumber::new(3), Arc::clone(&payload))]),
+    );
+
+    let (private_key, public_key) = key_pair_for_id(1);
+    let restored = SystemContext::<TestTypes, MemoryImpl>::new(
+        public_key,
+        private_key,
+        1,
+        hotshot.config.clone(),
+        (*hotshot.memberships).clone(),
+        Networks {
+            quorum_network: Arc::clone(&hotshot.networks.quorum_network),
+            da_network: Arc::clone(&hotshot.networks.da_network),
+            _pd: PhantomData,
+        },
+        initializer,
+        ConsensusMetricsValue::default(),
+        hotshot.storage.read().await.clone(),
+    )
+    .await
+    .unwrap();
+
+    let consensus = restored.consensus();
+    assert_eq!(
+        consensus
+            .read()
+            .await
+            .saved_payloads()
+            .get(&ViewNumber::new(3)),
+        Some(&payload)
+    );
+}
+
+/// A node refuses to start with a transaction proof of work difficulty too high to ever solve in
+/// reasonable time.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_transaction_pow_difficulty_bound() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let hotshot = &handle.hotshot;
+
+    let mut config = hotshot.config.clone();
+    config.transaction_pow_difficulty = MAX_TRANSACTION_POW_DIFFICULTY + 1;
+    let (private_key, public_key) = key_pair_for_id(1);
+    let result = SystemContext::<TestTypes, MemoryImpl>::new(
+        public_key,
+        private_key,
+        1,
+        config,
+        (*hotshot.memberships).clone(),
+        Networks {
+            quorum_network: Arc::clone(&hotshot.networks.quorum_network),
+            da_network: Arc::clone(&hotshot.networks.da_network),
+            _pd: PhantomData,
+        },
+        HotShotInitializer::<TestTypes>::from_genesis(TestInstanceState {})
+            .await
+            .unwrap(),
+        ConsensusMetricsValue::default(),
+        hotshot.storage.read().await.clone(),
+    )
+    .await;
+    assert!(matches!(result, Err(HotShotError::StartupFailed { .. })));
+}
+
+/// A blocking transaction submission reports a broadcast the network failed to send, so the
+/// application can retry it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_publish_transaction_blocking_reports_broadcast_failure() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    handle.hotshot.start_consensus().await.unwrap();
+
+    let transaction = TestTransaction::new(vec![1, 2, 3]);
+    handle
+        .submit_transaction_blocking(transaction.clone())
+        .await
+        .expect("The broadcast should succeed while the network is up");
+
+    handle.hotshot.networks.da_network.shut_down().await;
+    let result = handle.submit_transaction_blocking(transaction).await;
+    assert!(
+        matches!(
+            result,
+            Err(HotShotError::NetworkBroadcast {
+                source: NetworkError::ShutDown,
+                ..
+            })
+        ),
+        "Expected the failed broadcast to be reported, got {result:?}"
+    );
+}
+
+/// Revoking a decided upgrade certificate clears it, and tells the tasks to go back to the base
+/// version.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_revoke_upgrade_certificate() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let (handle, _internal_sender, mut internal_receiver) = build_system_handle(1).await;
+    let membership = handle.hotshot.memberships.quorum_membership.clone();
+    let (private_key, public_key) = key_pair_for_id(1);
+    let base_version = Version { major: 0, minor: 1 };
+    let cert = build_cert::<
+        TestTypes,
+        UpgradeProposalData<TestTypes>,
+        UpgradeVote<TestTypes>,
+        UpgradeCertificate<TestTypes>,
+    >(
+        UpgradeProposalData {
+            old_version: base_version,
+            new_version: Version { major: 0, minor: 2 },
+            decide_by: ViewNumber::new(6),
+            new_version_hash: [0u8; 12].to_vec(),
+            old_version_last_view: ViewNumber::new(6),
+            new_version_first_view: ViewNumber::new(7),
+        },
+        &membership,
+        ViewNumber::new(5),
+        &public_key,
+        &private_key,
+    );
+    *handle.hotshot.decided_upgrade_certificate.write().await = Some(cert);
+
+    handle.hotshot.revoke_upgrade_certificate().await;
+
+    assert!(handle
+        .hotshot
+        .decided_upgrade_certificate
+        .read()
+        .await
+        .is_none());
+    async_timeout(Duration::from_secs(5), async {
+        while let Ok(event) = internal_receiver.recv_direct().await {
+            if let HotShotEvent::VersionUpgrade(version) = event.as_ref() {
+                assert_eq!(*version, base_version);
+                return;
+            }
+        }
+    })
+    .await
+    .expect("Timed out waiting for the version to be reset");
+}
+
+/// The handle reports the view consensus is on.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_consensus_api_cur_view() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    assert_eq!(ConsensusApi::cur_view(&handle).await, ViewNumber::genesis());
+
+    handle
+        .consensus()
+        .write()
+        .await
+        .update_view(ViewNumber::new(3))
+        .unwrap();
+    assert_eq!(ConsensusApi::cur_view(&handle).await, ViewNumber::new(3));
+    assert_eq!(
+        ConsensusApi::cur_view(&handle).await,
+        handle.cur_view().await
+    );
+}
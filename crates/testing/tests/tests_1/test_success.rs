@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use hotshot_example_types::{
     node_types::{Libp2pImpl, MemoryImpl, PushCdnImpl},
@@ -8,6 +8,7 @@ use hotshot_macros::cross_tests;
 use hotshot_testing::{
     block_builder::SimpleBuilderImplementation,
     completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    overall_safety_task::OverallSafetyPropertiesDescription,
     test_builder::TestDescription,
 };
 cross_tests!(
@@ -27,3 +28,26 @@ cross_tests!(
         }
     },
 );
+
+// Observers hold no stake, but must still reach every decide the staked nodes do.
+cross_tests!(
+    TestName: test_success_with_observers,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Ignore: false,
+    Metadata: {
+        TestDescription {
+            observer_nodes: 2,
+            overall_safety_properties: OverallSafetyPropertiesDescription {
+                threshold_calculator: Arc::new(|_num_live, num_total| num_total),
+                ..Default::default()
+            },
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            ..TestDescription::default()
+        }
+    },
+);
@@ -0,0 +1,198 @@
+use std::{sync::Arc, time::Duration};
+
+use async_lock::RwLock;
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes},
+    state_types::TestValidatedState,
+};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    helpers::build_system_handle,
+    overall_safety_task::{
+        OverallSafetyPropertiesDescription, OverallSafetyTask, OverallSafetyTaskErr, RoundCtx,
+    },
+    test_builder::TestDescription,
+    test_task::{TestEvent, TestResult, TestTaskState},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    data::{Leaf, ViewNumber},
+    event::{Event, EventType, LeafInfo},
+    message::TxPriority,
+    signature_key::BLSPubKey,
+    traits::{election::Membership, node_implementation::ConsensusTime},
+};
+
+// Every transaction submitted during the test is decided in exactly one block.
+cross_tests!(
+    TestName: test_transaction_inclusion,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription {
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            ..TestDescription::default()
+        };
+        metadata.overall_safety_properties.transaction_inclusion_views = Some(10);
+        metadata
+    },
+);
+
+/// A safety task checking transaction inclusion, fed events by hand.
+fn safety_task() -> (
+    OverallSafetyTask<TestTypes, MemoryImpl>,
+    async_broadcast::Receiver<TestEvent>,
+) {
+    let (test_sender, test_receiver) = async_broadcast::broadcast(16);
+    let task = OverallSafetyTask {
+        handles: Arc::new(RwLock::new(Vec::new())),
+        ctx: RoundCtx::default(),
+        properties: OverallSafetyPropertiesDescription {
+            num_successful_views: 0,
+            transaction_inclusion_views: Some(10),
+            ..Default::default()
+        },
+        error: None,
+        test_sender,
+    };
+    (task, test_receiver)
+}
+
+/// A `Transactions` event for `transaction` submitted in `view`.
+fn submitted(view: u64, transaction: &TestTransaction) -> Event<TestTypes> {
+    Event {
+        view_number: ViewNumber::new(view),
+        event: EventType::Transactions {
+            transactions: vec![(transaction.clone(), TxPriority::Normal)],
+        },
+    }
+}
+
+/// A `Decide` event for `leaf`, proposed by `proposer`.
+fn decided(leaf: &Leaf<TestTypes>, proposer: BLSPubKey) -> Event<TestTypes> {
+    Event {
+        view_number: leaf.view_number(),
+        event: EventType::Decide {
+            leaf_chain: Arc::new(vec![LeafInfo::new(
+                leaf.clone(),
+                Arc::new(TestValidatedState::default()),
+                None,
+                None,
+                Some(proposer),
+            )]),
+            qc: Arc::new(leaf.justify_qc()),
+            block_size: None,
+            decided_view_count: 1,
+        },
+    }
+}
+
+/// A transaction decided in two different blocks is reported as a duplicate.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_duplicate_transaction_is_detected() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    // The transaction is carried over into both views 2 and 3.
+    let transaction = TestTransaction::new(vec![1, 2, 3]);
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    generator.next().await.unwrap();
+    generator.add_transactions(vec![transaction.clone()]);
+    let leaves = (&mut generator)
+        .take(2)
+        .map(|view| view.leaf)
+        .collect::<Vec<_>>()
+        .await;
+
+    let (mut task, _test_receiver) = safety_task();
+    task.handle_event((submitted(1, &transaction), 0))
+        .await
+        .unwrap();
+    for leaf in &leaves {
+        let proposer = quorum_membership.leader(leaf.view_number());
+        task.handle_event((decided(leaf, proposer), 0))
+            .await
+            .unwrap();
+    }
+
+    assert!(matches!(
+        task.error.as_deref(),
+        Some(OverallSafetyTaskErr::DuplicateTransaction { transaction: duplicate })
+            if *duplicate == transaction.commit()
+    ));
+    assert!(matches!(task.check(), TestResult::Fail(_)));
+}
+
+/// A transaction decided once passes, while one never decided long after it was submitted is
+/// reported as missing.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_missing_transaction_is_detected() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let included = TestTransaction::new(vec![1]);
+    let dropped = TestTransaction::new(vec![2]);
+    let mut generator = TestViewGenerator::generate(quorum_membership.clone(), da_membership);
+    generator.next().await.unwrap();
+    generator.add_transactions(vec![included.clone()]);
+    let included_leaf = generator.next().await.unwrap().leaf;
+    generator.add_transactions(Vec::new());
+    let late_leaf = (&mut generator).skip(9).next().await.unwrap().leaf;
+
+    let (mut task, _test_receiver) = safety_task();
+    for transaction in [&included, &dropped] {
+        task.handle_event((submitted(1, transaction), 0))
+            .await
+            .unwrap();
+    }
+    task.handle_event((
+        decided(
+            &included_leaf,
+            quorum_membership.leader(included_leaf.view_number()),
+        ),
+        0,
+    ))
+    .await
+    .unwrap();
+
+    // Not missing yet, as it may still be decided.
+    assert!(task.error.is_none());
+    assert!(matches!(task.check(), TestResult::Pass));
+
+    task.handle_event((
+        decided(
+            &late_leaf,
+            quorum_membership.leader(late_leaf.view_number()),
+        ),
+        0,
+    ))
+    .await
+    .unwrap();
+    assert_eq!(
+        task.ctx.missing_transaction(10),
+        Some((dropped.commit(), ViewNumber::new(1)))
+    );
+    assert!(matches!(task.check(), TestResult::Fail(_)));
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_compatibility_layer::art::{async_sleep, async_timeout};
+
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes};
+use hotshot_orchestrator::config::RandomBuilderConfig;
+use hotshot_task_impls::{
+    builder::BuilderClient, events::HotShotEvent, transactions::TransactionTaskState,
+    vid::VidTaskState,
+};
+use hotshot_testing::{
+    block_builder::{BuilderTask, RandomBuilderImplementation, TestBuilderImplementation},
+    helpers::build_system_handle,
+};
+use hotshot_types::{
+    constants::Base,
+    data::{null_block, ViewNumber},
+    traits::{election::Membership, node_implementation::ConsensusTime},
+};
+use tide_disco::Url;
+
+/// Wait for the transaction task to finish prefetching the block for `view`
+async fn wait_for_prefetched_block(
+    state: &TransactionTaskState<TestTypes, MemoryImpl, Base>,
+    view: ViewNumber,
+) {
+    async_timeout(Duration::from_secs(10), async {
+        while !state.prefetched_blocks.lock().await.contains_key(&view) {
+            async_sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Block was not prefetched");
+}
+
+/// With prefetching enabled, a leader's block is fetched a view early, so it's ready without
+/// querying the builder when the leader needs it. Blocks for skipped views are discarded.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_leader_proposes_prefetched_block() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let membership = handle.hotshot.memberships.quorum_membership.clone();
+    let num_nodes = membership.total_nodes() as u64;
+    let leader_view = (3..)
+        .map(ViewNumber::new)
+        .find(|view| membership.leader(*view) == *handle.public_key())
+        .unwrap();
+
+    let (builder_task, builder_url): (Box<dyn BuilderTask<TestTypes>>, Url) =
+        RandomBuilderImplementation::start(
+            membership.total_nodes(),
+            RandomBuilderConfig {
+                blocks_per_second: u32::MAX,
+                ..Default::default()
+            },
+            HashMap::new(),
+        )
+        .await;
+    builder_task.start(Box::new(futures::stream::empty()));
+
+    let mut state = TransactionTaskState::<TestTypes, MemoryImpl, Base>::create_from(&handle).await;
+    state.builder_clients = vec![BuilderClient::new(builder_url.clone())];
+    state.builder_prefetch_depth = 1;
+    let builder_timeout = state.builder_timeout;
+    let (sender, mut receiver) = async_broadcast::broadcast(16);
+
+    // Two views ahead of our turn, the block for our view is fetched ahead of time.
+    state
+        .handle(
+            Arc::new(HotShotEvent::ViewChange(leader_view - 2)),
+            sender.clone(),
+        )
+        .await;
+    wait_for_prefetched_block(&state, leader_view).await;
+
+    // Without any builders to query, the block we propose must be the prefetched one.
+    state.builder_clients = Vec::new();
+    let start = Instant::now();
+    state
+        .handle(
+            Arc::new(HotShotEvent::ViewChange(leader_view - 1)),
+            sender.clone(),
+        )
+        .await;
+    assert!(start.elapsed() < builder_timeout);
+    match receiver.try_recv().unwrap().as_ref() {
+        HotShotEvent::BlockRecv(_, _, view, fee, _) => {
+            assert_eq!(*view, leader_view);
+            assert_ne!(fee.fee_amount, 0, "Proposed the null block");
+        }
+        event => panic!("Expected a block, got {event:?}"),
+    }
+    assert!(state.prefetched_blocks.lock().await.is_empty());
+
+    // A block prefetched for a view we skip past is discarded.
+    state.builder_clients = vec![BuilderClient::new(builder_url)];
+    let next_leader_view = leader_view + num_nodes;
+    state
+        .handle(
+            Arc::new(HotShotEvent::ViewChange(next_leader_view - 2)),
+            sender.clone(),
+        )
+        .await;
+    wait_for_prefetched_block(&state, next_leader_view).await;
+    state
+        .handle(
+            Arc::new(HotShotEvent::ViewChange(next_leader_view + 1)),
+            sender,
+        )
+        .await;
+    assert!(state.prefetched_blocks.lock().await.is_empty());
+}
+
+/// A builder block larger than the maximum block size is replaced by the null block before it
+/// leaves the transaction task, so the VID task never commits to or disperses it.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_oversized_builder_block_falls_back_to_null_block() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let membership = handle.hotshot.memberships.quorum_membership.clone();
+    let num_storage_nodes = handle.hotshot.memberships.vid_membership.total_nodes();
+    let leader_view = (2..)
+        .map(ViewNumber::new)
+        .find(|view| membership.leader(*view) == *handle.public_key())
+        .unwrap();
+
+    let (builder_task, builder_url): (Box<dyn BuilderTask<TestTypes>>, Url) =
+        RandomBuilderImplementation::start(
+            membership.total_nodes(),
+            RandomBuilderConfig {
+                blocks_per_second: u32::MAX,
+                ..Default::default()
+            },
+            HashMap::new(),
+        )
+        .await;
+    builder_task.start(Box::new(futures::stream::empty()));
+
+    let mut state = TransactionTaskState::<TestTypes, MemoryImpl, Base>::create_from(&handle).await;
+    state.builder_clients = vec![BuilderClient::new(builder_url)];
+    // Every transaction the random builder makes is larger than this.
+    state.max_block_size = 1;
+    let (sender, mut receiver) = async_broadcast::broadcast(16);
+
+    state
+        .handle(Arc::new(HotShotEvent::ViewChange(leader_view - 1)), sender)
+        .await;
+    let block_recv = receiver.try_recv().unwrap();
+    match block_recv.as_ref() {
+        HotShotEvent::BlockRecv(encoded_transactions, _, view, fee, _) => {
+            assert!(encoded_transactions.is_empty());
+            assert_eq!(*view, leader_view);
+            assert_eq!(
+                Some(fee.clone()),
+                null_block::builder_fee(num_storage_nodes)
+            );
+        }
+        event => panic!("Expected the null block, got {event:?}"),
+    }
+    assert!(receiver.try_recv().is_err());
+
+    // Downstream, only the null block is committed to and dispersed.
+    let mut vid_state = VidTaskState::<TestTypes, MemoryImpl>::create_from(&handle).await;
+    let (vid_sender, mut vid_receiver) = async_broadcast::broadcast(16);
+    vid_state.handle(block_recv, vid_sender).await;
+    let null_commitment = null_block::commitment(num_storage_nodes);
+    let mut outputs = 0;
+    while let Ok(event) = vid_receiver.try_recv() {
+        match event.as_ref() {
+            HotShotEvent::SendPayloadCommitmentAndMetadata(commitment, ..) => {
+                assert_eq!(Some(*commitment), null_commitment);
+            }
+            HotShotEvent::BlockReady(vid_disperse, _) => {
+                assert_eq!(Some(vid_disperse.payload_commitment), null_commitment);
+            }
+            event => panic!("Unexpected event {event:?}"),
+        }
+        outputs += 1;
+    }
+    assert_eq!(outputs, 2);
+}
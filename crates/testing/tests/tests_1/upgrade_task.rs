@@ -12,7 +12,9 @@ use hotshot_example_types::{
 };
 use hotshot_macros::test_scripts;
 use hotshot_task_impls::{
-    consensus::ConsensusTaskState, events::HotShotEvent::*, upgrade::UpgradeTaskState,
+    consensus::{validate_header_app_version, validate_parent_version, ConsensusTaskState},
+    events::HotShotEvent::*,
+    upgrade::UpgradeTaskState,
 };
 use hotshot_testing::{
     helpers::{build_fake_view_with_leaf, vid_share},
@@ -124,6 +126,10 @@ async fn test_consensus_task_upgrade() {
         Expectations {
             output_asserts: vec![
                 exact(ViewChange(ViewNumber::new(2))),
+                exact(HighQcUpdated {
+                    from: None,
+                    to: ViewNumber::new(1),
+                }),
                 validated_state_updated(),
                 quorum_proposal_validated(),
                 exact(QuorumVoteSend(votes[1].clone())),
@@ -133,6 +139,10 @@ async fn test_consensus_task_upgrade() {
         Expectations {
             output_asserts: vec![
                 exact(ViewChange(ViewNumber::new(3))),
+                exact(HighQcUpdated {
+                    from: Some(ViewNumber::new(1)),
+                    to: ViewNumber::new(2),
+                }),
                 validated_state_updated(),
                 quorum_proposal_validated(),
                 exact(QuorumVoteSend(votes[2].clone())),
@@ -142,6 +152,10 @@ async fn test_consensus_task_upgrade() {
         Expectations {
             output_asserts: vec![
                 exact(ViewChange(ViewNumber::new(4))),
+                exact(HighQcUpdated {
+                    from: Some(ViewNumber::new(2)),
+                    to: ViewNumber::new(3),
+                }),
                 validated_state_updated(),
                 quorum_proposal_validated(),
                 leaf_decided(),
@@ -152,6 +166,10 @@ async fn test_consensus_task_upgrade() {
         Expectations {
             output_asserts: vec![
                 exact(ViewChange(ViewNumber::new(5))),
+                exact(HighQcUpdated {
+                    from: Some(ViewNumber::new(3)),
+                    to: ViewNumber::new(4),
+                }),
                 validated_state_updated(),
                 quorum_proposal_validated(),
                 leaf_decided(),
@@ -162,6 +180,10 @@ async fn test_consensus_task_upgrade() {
         Expectations {
             output_asserts: vec![
                 exact(ViewChange(ViewNumber::new(6))),
+                exact(HighQcUpdated {
+                    from: Some(ViewNumber::new(4)),
+                    to: ViewNumber::new(5),
+                }),
                 validated_state_updated(),
                 quorum_proposal_validated(),
                 upgrade_decided(),
@@ -297,6 +319,10 @@ async fn test_upgrade_and_consensus_task() {
             Expectations {
                 output_asserts: vec![
                     exact::<TestTypes>(ViewChange(ViewNumber::new(2))),
+                    exact(HighQcUpdated {
+                        from: None,
+                        to: ViewNumber::new(1),
+                    }),
                     validated_state_updated(),
                     quorum_proposal_validated::<TestTypes>(),
                     quorum_vote_send(),
@@ -304,7 +330,13 @@ async fn test_upgrade_and_consensus_task() {
                 task_state_asserts: vec![],
             },
             Expectations {
-                output_asserts: vec![quorum_proposal_send_with_upgrade_certificate::<TestTypes>()],
+                output_asserts: vec![
+                    exact(HighQcUpdated {
+                        from: Some(ViewNumber::new(1)),
+                        to: ViewNumber::new(2),
+                    }),
+                    quorum_proposal_send_with_upgrade_certificate::<TestTypes>(),
+                ],
                 task_state_asserts: vec![],
             },
         ],
@@ -537,6 +569,10 @@ async fn test_upgrade_and_consensus_task_blank_blocks() {
             Expectations {
                 output_asserts: vec![
                     exact(ViewChange(ViewNumber::new(2))),
+                    exact(HighQcUpdated {
+                        from: None,
+                        to: ViewNumber::new(1),
+                    }),
                     validated_state_updated(),
                     quorum_proposal_validated(),
                     quorum_vote_send(),
@@ -546,6 +582,10 @@ async fn test_upgrade_and_consensus_task_blank_blocks() {
             Expectations {
                 output_asserts: vec![
                     exact(ViewChange(ViewNumber::new(3))),
+                    exact(HighQcUpdated {
+                        from: Some(ViewNumber::new(1)),
+                        to: ViewNumber::new(2),
+                    }),
                     validated_state_updated(),
                     quorum_proposal_validated(),
                     quorum_vote_send(),
@@ -555,6 +595,10 @@ async fn test_upgrade_and_consensus_task_blank_blocks() {
             Expectations {
                 output_asserts: vec![
                     exact(ViewChange(ViewNumber::new(4))),
+                    exact(HighQcUpdated {
+                        from: Some(ViewNumber::new(2)),
+                        to: ViewNumber::new(3),
+                    }),
                     validated_state_updated(),
                     quorum_proposal_validated(),
                     leaf_decided(),
@@ -565,6 +609,10 @@ async fn test_upgrade_and_consensus_task_blank_blocks() {
             Expectations {
                 output_asserts: vec![
                     exact(ViewChange(ViewNumber::new(5))),
+                    exact(HighQcUpdated {
+                        from: Some(ViewNumber::new(3)),
+                        to: ViewNumber::new(4),
+                    }),
                     validated_state_updated(),
                     quorum_proposal_validated(),
                     upgrade_decided(),
@@ -577,6 +625,10 @@ async fn test_upgrade_and_consensus_task_blank_blocks() {
             Expectations {
                 output_asserts: vec![
                     exact(ViewChange(ViewNumber::new(6))),
+                    exact(HighQcUpdated {
+                        from: Some(ViewNumber::new(4)),
+                        to: ViewNumber::new(5),
+                    }),
                     validated_state_updated(),
                     quorum_proposal_validated(),
                     quorum_proposal_send_with_null_block(
@@ -590,6 +642,10 @@ async fn test_upgrade_and_consensus_task_blank_blocks() {
             Expectations {
                 output_asserts: vec![
                     exact(ViewChange(ViewNumber::new(7))),
+                    exact(HighQcUpdated {
+                        from: Some(ViewNumber::new(5)),
+                        to: ViewNumber::new(6),
+                    }),
                     validated_state_updated(),
                     quorum_proposal_validated(),
                     leaf_decided(),
@@ -637,3 +693,125 @@ async fn test_upgrade_and_consensus_task_blank_blocks() {
 
     test_scripts![inputs, consensus_script, upgrade_script].await;
 }
+
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+/// Tests that a proposal crossing the upgrade boundary has its parent validated under the
+/// parent's version rather than its own.
+async fn test_parent_validated_across_upgrade_boundary() {
+    use hotshot_testing::helpers::build_system_handle;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let upgrade_data: UpgradeProposalData<TestTypes> = UpgradeProposalData {
+        old_version: Version { major: 0, minor: 1 },
+        new_version: Version { major: 0, minor: 2 },
+        decide_by: ViewNumber::new(2),
+        new_version_hash: [0u8; 12].to_vec(),
+        old_version_last_view: ViewNumber::new(2),
+        new_version_first_view: ViewNumber::new(4),
+    };
+
+    // Views 1 and 2 are before the upgrade, view 3 is between versions and view 4 is the first
+    // view of the new version. Only the first chain fills view 3 with a null block.
+    let mut chains = [Vec::new(), Vec::new()];
+    let mut upgrade_cert = None;
+    for (null_block, leaves) in [true, false].into_iter().zip(chains.iter_mut()) {
+        let mut generator =
+            TestViewGenerator::generate(quorum_membership.clone(), da_membership.clone());
+        for view_number in 1..=4 {
+            if view_number == 2 {
+                generator.add_upgrade(upgrade_data.clone());
+            }
+            if view_number == 3 && !null_block {
+                generator.add_transactions(vec![TestTransaction::new(vec![0])]);
+            }
+            let view = generator.next().await.unwrap();
+            upgrade_cert = upgrade_cert.or(view.quorum_proposal.data.upgrade_certificate.clone());
+            leaves.push(view.leaf);
+        }
+    }
+    let [null_chain, chain] = chains;
+    let decided = Some(upgrade_cert.expect("The upgrade certificate should be attached"));
+    let membership = &quorum_membership;
+
+    // A null block parent between versions may be extended by the first view of the new version.
+    assert!(
+        validate_parent_version(&null_chain[2], ViewNumber::new(4), &decided, membership).is_ok()
+    );
+
+    // A parent that wasn't a null block can't be, even though the new version has no such rule.
+    assert!(validate_parent_version(&chain[2], ViewNumber::new(4), &decided, membership).is_err());
+    assert!(validate_parent_version(&chain[2], ViewNumber::new(4), &None, membership).is_ok());
+
+    // A parent can't be from a later version than its child.
+    assert!(validate_parent_version(&chain[3], ViewNumber::new(3), &decided, membership).is_err());
+}
+
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+/// Tests that a proposal is rejected if its header records an app version other than the one in
+/// effect for its view, and that headers without a version aren't checked.
+async fn test_header_app_version_validated_across_upgrade_boundary() {
+    use hotshot_testing::helpers::build_system_handle;
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(1).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let old_version = Version { major: 0, minor: 1 };
+    let new_version = Version { major: 0, minor: 2 };
+    let upgrade_data: UpgradeProposalData<TestTypes> = UpgradeProposalData {
+        old_version,
+        new_version,
+        decide_by: ViewNumber::new(2),
+        new_version_hash: [0u8; 12].to_vec(),
+        old_version_last_view: ViewNumber::new(2),
+        new_version_first_view: ViewNumber::new(4),
+    };
+
+    let mut generator = TestViewGenerator::generate(quorum_membership, da_membership);
+    let mut proposals = Vec::new();
+    let mut upgrade_cert = None;
+    for view_number in 1..=4 {
+        if view_number == 2 {
+            generator.add_upgrade(upgrade_data.clone());
+        }
+        let view = generator.next().await.unwrap();
+        upgrade_cert = upgrade_cert.or(view.quorum_proposal.data.upgrade_certificate.clone());
+        proposals.push(view.quorum_proposal.data);
+    }
+    let decided = Some(upgrade_cert.expect("The upgrade certificate should be attached"));
+
+    let with_app_version = |index: usize, version: Option<Version>| {
+        let mut proposal = proposals[index].clone();
+        proposal.block_header.app_version = version;
+        proposal
+    };
+
+    // A header without a version isn't checked.
+    assert!(validate_header_app_version(&with_app_version(3, None), &decided).is_ok());
+
+    // View 1 is before the upgrade, and view 4 is the first view of the new version.
+    assert!(validate_header_app_version(&with_app_version(0, Some(old_version)), &decided).is_ok());
+    assert!(validate_header_app_version(&with_app_version(3, Some(new_version)), &decided).is_ok());
+
+    // A header built under the other version is rejected.
+    assert!(
+        validate_header_app_version(&with_app_version(0, Some(new_version)), &decided).is_err()
+    );
+    assert!(
+        validate_header_app_version(&with_app_version(3, Some(old_version)), &decided).is_err()
+    );
+
+    // Without a decided upgrade, only the old version is in effect.
+    assert!(validate_header_app_version(&with_app_version(3, Some(new_version)), &None).is_err());
+}
@@ -0,0 +1,112 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::StreamExt;
+use hotshot_example_types::block_types::TestTransaction;
+use hotshot_task_impls::helpers::{
+    valid_vid_shares, verify_concurrently, MAX_CONCURRENT_VID_SHARE_VERIFICATIONS,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::traits::election::Membership;
+
+/// In a batch of VID shares, the valid shares are all accepted in order and the invalid one is
+/// rejected on its own.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_vid_share_batch_verification() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let public_key = handle.public_key();
+    let quorum_membership = Arc::new(handle.hotshot.memberships.quorum_membership.clone());
+    let da_membership = Arc::new(handle.hotshot.memberships.da_membership.clone());
+    let num_storage_nodes = handle.hotshot.memberships.vid_membership.total_nodes();
+
+    let mut generator =
+        TestViewGenerator::generate((*quorum_membership).clone(), (*da_membership).clone());
+    let mut shares = Vec::new();
+    let mut payload = 0u8;
+    while let Some(view) = generator.next().await {
+        shares.extend(view.vid_proposal.0);
+        if shares.len() >= 40 {
+            break;
+        }
+        payload += 1;
+        generator.add_transactions(vec![TestTransaction::new(vec![payload; 16])]);
+    }
+
+    // Swap in the share of a different payload, keeping the signature over the original one.
+    let invalid_index = shares.len() / 2;
+    let mut expected = shares.clone();
+    let invalid = expected.remove(invalid_index);
+    shares[invalid_index].data.share = shares
+        .iter()
+        .find(|share| share.data.payload_commitment != invalid.data.payload_commitment)
+        .unwrap()
+        .data
+        .share
+        .clone();
+
+    let valid = valid_vid_shares(
+        shares,
+        &public_key,
+        &quorum_membership,
+        &da_membership,
+        num_storage_nodes,
+    )
+    .await;
+
+    assert_eq!(valid, expected);
+}
+
+/// Verifications run concurrently, but never more of them at once than the bound.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_verification_concurrency_is_bounded() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let verify = {
+        let in_flight = Arc::clone(&in_flight);
+        let max_in_flight = Arc::clone(&max_in_flight);
+        move |item: &usize| {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(now, Ordering::SeqCst);
+            // Long enough for the other verifications to start meanwhile
+            std::thread::sleep(Duration::from_millis(50));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            item % 2 == 0
+        }
+    };
+
+    let items: Vec<usize> = (0..MAX_CONCURRENT_VID_SHARE_VERIFICATIONS * 4).collect();
+    let verified = verify_concurrently(
+        items.clone(),
+        MAX_CONCURRENT_VID_SHARE_VERIFICATIONS,
+        verify,
+    )
+    .await;
+
+    assert_eq!(
+        verified,
+        items
+            .into_iter()
+            .filter(|item| item % 2 == 0)
+            .collect::<Vec<_>>()
+    );
+    let max_in_flight = max_in_flight.load(Ordering::SeqCst);
+    assert!(
+        (2..=MAX_CONCURRENT_VID_SHARE_VERIFICATIONS).contains(&max_in_flight),
+        "{max_in_flight} verifications ran at once"
+    );
+}
@@ -9,7 +9,7 @@ use hotshot_example_types::{
 use hotshot_macros::{run_test, test_scripts};
 use hotshot_task_impls::{events::HotShotEvent::*, vid::VidTaskState};
 use hotshot_testing::{
-    helpers::{build_system_handle, vid_scheme_from_view_number},
+    helpers::{build_system_handle, key_pair_for_id, vid_scheme_from_view_number},
     predicates::event::exact,
     script::{Expectations, InputOrder, TaskScript},
     serial,
@@ -58,7 +58,7 @@ async fn test_vid_task() {
     let payload_commitment = vid_disperse.commit;
 
     let signature = <TestTypes as NodeType>::SignatureKey::sign(
-        handle.private_key(),
+        &key_pair_for_id(handle.hotshot.id).0,
         payload_commitment.as_ref(),
     )
     .expect("Failed to sign block payload!");
@@ -0,0 +1,78 @@
+use futures::StreamExt;
+use hotshot_testing::{
+    helpers::build_system_handle,
+    view_generator::{TestView, TestViewGenerator},
+};
+use hotshot_types::{
+    constants::Base, data::ViewNumber, message::EquivocationProof,
+    traits::node_implementation::ConsensusTime,
+};
+use vbs::{BinarySerializer, Serializer};
+
+/// Serializes the proposals of a view.
+fn proposal_bytes(view: &TestView) -> (Vec<u8>, Vec<u8>) {
+    (
+        Serializer::<Base>::serialize(&view.quorum_proposal).unwrap(),
+        Serializer::<Base>::serialize(&view.da_proposal).unwrap(),
+    )
+}
+
+/// Two generators with the same seed produce byte-identical proposals for every view.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_seeded_view_generator_is_reproducible() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generators = [42, 42, 43].map(|seed| {
+        TestViewGenerator::generate_seeded(quorum_membership.clone(), da_membership.clone(), seed)
+    });
+
+    for _ in 0..3 {
+        let mut views = Vec::new();
+        for generator in &mut generators {
+            views.push(generator.next().await.unwrap());
+            generator.add_random_transactions(4);
+        }
+        assert_eq!(proposal_bytes(&views[0]), proposal_bytes(&views[1]));
+    }
+
+    // A different seed yields different transactions, and therefore different proposals.
+    let a = generators[0].next().await.unwrap();
+    let c = generators[2].next().await.unwrap();
+    assert_ne!(proposal_bytes(&a), proposal_bytes(&c));
+}
+
+/// Views the generator is told to equivocate in carry a second, conflicting proposal signed by
+/// the same leader, and other views don't.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_view_generator_equivocates() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let quorum_membership = handle.hotshot.memberships.quorum_membership.clone();
+    let da_membership = handle.hotshot.memberships.da_membership.clone();
+
+    let mut generator = TestViewGenerator::generate_with_equivocation(
+        quorum_membership.clone(),
+        da_membership,
+        vec![ViewNumber::new(2)],
+    );
+    let views = (&mut generator).take(3).collect::<Vec<_>>().await;
+
+    assert!(views[0].quorum_proposal_conflicting.is_none());
+    assert!(views[2].quorum_proposal_conflicting.is_none());
+    let proof = EquivocationProof {
+        first: views[1].quorum_proposal.clone(),
+        second: views[1].quorum_proposal_conflicting.clone().unwrap(),
+    };
+    assert!(proof.verify(&quorum_membership));
+}
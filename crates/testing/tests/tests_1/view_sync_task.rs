@@ -27,7 +27,7 @@ async fn test_view_sync_task() {
         vote_data,
         <TestTypes as hotshot_types::traits::node_implementation::NodeType>::Time::new(4),
         hotshot_types::traits::consensus_api::ConsensusApi::public_key(&handle),
-        hotshot_types::traits::consensus_api::ConsensusApi::private_key(&handle),
+        &hotshot_testing::helpers::key_pair_for_id(handle.hotshot.id).0,
     )
     .expect("Failed to create a ViewSyncPreCommitVote!");
 
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use committable::Committable;
+use either::Either;
+use hotshot::traits::ValidatedState;
+use hotshot_example_types::{node_types::TestTypes, state_types::TestValidatedState};
+use hotshot_testing::helpers::{build_system_handle, key_pair_for_id};
+use hotshot_types::{
+    data::{Leaf, ViewNumber},
+    simple_certificate::QuorumCertificate,
+    simple_vote::{QuorumData, QuorumVote, VoteExtension},
+    traits::{election::Membership, node_implementation::ConsensusTime},
+    vote::{Certificate, VoteAccumulator},
+};
+
+/// Vote extensions are signed by the voter, rejected if tampered with or moved onto another vote,
+/// and surfaced by the accumulator once the certificate forms.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_quorum_vote_extensions() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+
+    let handle = build_system_handle(2).await.0;
+    let membership = handle.hotshot.memberships.quorum_membership.clone();
+
+    let (validated_state, _ /* state delta */) = <TestValidatedState as ValidatedState<
+        TestTypes,
+    >>::genesis(&*handle.hotshot.instance_state());
+    let leaf = Leaf::genesis(&validated_state, &*handle.hotshot.instance_state()).await;
+    let data = QuorumData {
+        leaf_commit: leaf.commit(),
+    };
+    let view = ViewNumber::new(1);
+
+    let (private_key, public_key) = key_pair_for_id(0);
+    let vote =
+        QuorumVote::<TestTypes>::create_signed_vote(data.clone(), view, &public_key, &private_key)
+            .unwrap();
+    let extension = VoteExtension::new(&vote, vec![0], &private_key).unwrap();
+    assert!(extension.is_valid_for(&vote));
+
+    // A forged extension doesn't verify, nor does one moved onto another voter's vote.
+    let mut forged = extension.clone();
+    forged.data = vec![1];
+    assert!(!forged.is_valid_for(&vote));
+    let (other_private_key, other_public_key) = key_pair_for_id(1);
+    let other_vote = QuorumVote::<TestTypes>::create_signed_vote(
+        data.clone(),
+        view,
+        &other_public_key,
+        &other_private_key,
+    )
+    .unwrap();
+    assert!(!extension.is_valid_for(&other_vote));
+
+    let mut accumulator =
+        VoteAccumulator::<TestTypes, QuorumVote<TestTypes>, QuorumCertificate<TestTypes>> {
+            vote_outcomes: HashMap::new(),
+            signers: HashMap::new(),
+            extensions: HashMap::new(),
+            phantom: PhantomData,
+        };
+    assert!(accumulator
+        .accumulate_with_extension(&vote, &forged, &membership)
+        .is_left());
+    assert!(!accumulator.contains(&data.commit(), &public_key));
+    assert!(accumulator.extensions(&data.commit()).is_empty());
+
+    // Votes with different extensions still aggregate into a single certificate.
+    let mut cert = None;
+    for node_id in 0..membership.total_nodes() as u64 {
+        let (private_key, public_key) = key_pair_for_id(node_id);
+        let vote = QuorumVote::<TestTypes>::create_signed_vote(
+            data.clone(),
+            view,
+            &public_key,
+            &private_key,
+        )
+        .unwrap();
+        let extension =
+            VoteExtension::new(&vote, node_id.to_le_bytes().to_vec(), &private_key).unwrap();
+        if let Either::Right(qc) =
+            accumulator.accumulate_with_extension(&vote, &extension, &membership)
+        {
+            cert = Some(qc);
+            break;
+        }
+    }
+    let cert = cert.expect("Certificate should form from the extended votes");
+    assert!(cert.is_valid_cert(membership.as_ref()));
+
+    let extensions = accumulator.extensions(&cert.date_commitment());
+    assert_eq!(
+        extensions.len() as u64,
+        QuorumCertificate::<TestTypes>::threshold(membership.as_ref())
+    );
+    for (key, extension) in &extensions {
+        let expected_id = (0..membership.total_nodes() as u64)
+            .find(|id| key_pair_for_id(*id).1 == *key)
+            .unwrap();
+        assert_eq!(*extension, expected_id.to_le_bytes().to_vec());
+    }
+}
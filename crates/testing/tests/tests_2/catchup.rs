@@ -286,3 +286,76 @@ async fn test_catchup_reload() {
         .run_test::<SimpleBuilderImplementation>()
         .await;
 }
+
+/// Restart every node in turn from its storage while the rest of the network keeps running. No
+/// node may double vote, and consensus must keep making progress and every restarted node must
+/// catch up and decide again.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_rolling_restart() {
+    use std::time::Duration;
+
+    use hotshot_example_types::node_types::{MemoryImpl, TestTypes};
+    use hotshot_testing::{
+        block_builder::SimpleBuilderImplementation,
+        completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+        overall_safety_task::OverallSafetyPropertiesDescription,
+        spinning_task::{ChangeNode, SpinningTaskDescription, UpDown},
+        test_builder::{TestDescription, TimingData},
+    };
+
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let timing_data = TimingData {
+        next_view_timeout: 2000,
+        ..Default::default()
+    };
+    let num_nodes = 10;
+    let mut metadata = TestDescription {
+        num_nodes_with_stake: num_nodes,
+        start_nodes: num_nodes,
+        num_bootstrap_nodes: num_nodes,
+        da_staked_committee_size: num_nodes,
+        timing_data,
+        ..Default::default()
+    };
+
+    metadata.view_sync_properties =
+        hotshot_testing::view_sync_task::ViewSyncTaskDescription::Threshold(0, num_nodes);
+
+    metadata.spinning_properties = SpinningTaskDescription {
+        // Restart one node every few views, so there is never more than one down at a time.
+        node_changes: (0..num_nodes)
+            .zip((5..).step_by(3))
+            .map(|(idx, view)| {
+                (
+                    view,
+                    vec![ChangeNode {
+                        idx,
+                        updown: UpDown::Restart,
+                    }],
+                )
+            })
+            .collect(),
+    };
+
+    metadata.completion_task_description =
+        CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(120),
+            },
+        );
+    metadata.overall_safety_properties = OverallSafetyPropertiesDescription {
+        // Keep committing rounds well past the last restart.
+        num_successful_views: 45,
+        num_failed_views: 10,
+        ..Default::default()
+    };
+
+    metadata
+        .gen_launcher::<TestTypes, MemoryImpl>(0)
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
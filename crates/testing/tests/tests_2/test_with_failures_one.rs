@@ -36,3 +36,30 @@ cross_tests!(
         metadata
     }
 );
+
+// Test one leader leaving the network, with the views it leads marked as expected failures.
+cross_tests!(
+    TestName: test_with_expected_failures,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription::default_more_nodes();
+        metadata.num_bootstrap_nodes = 19;
+        let dead_nodes = vec![ChangeNode {
+            idx: 19,
+            updown: UpDown::Down,
+        }];
+
+        metadata.spinning_properties = SpinningTaskDescription {
+            node_changes: vec![(5, dead_nodes)]
+        };
+        // Node 19 leads views 19 and 39, so those (and the views whose votes it would have
+        // collected) are allowed to fail without eating into the failure budget.
+        metadata.overall_safety_properties.num_failed_views = 0;
+        metadata.overall_safety_properties.expected_failed_views =
+            [18, 19, 20, 38, 39, 40].into_iter().collect();
+        metadata.overall_safety_properties.num_successful_views = 25;
+        metadata
+    }
+);
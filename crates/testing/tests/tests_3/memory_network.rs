@@ -1,14 +1,27 @@
 #![allow(clippy::panic)]
-use std::{collections::BTreeSet, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 
-use async_compatibility_layer::logging::setup_logging;
+use async_compatibility_layer::{
+    art::{async_spawn, async_timeout},
+    logging::setup_logging,
+};
+use cdn_proto::mnemonic;
 use hotshot::{
     traits::{
         election::static_committee::GeneralStaticCommittee,
-        implementations::{MasterMap, MemoryNetwork},
+        implementations::{MasterMap, MemoryNetwork, NetworkingMetricsValue, UnreliableNetwork},
         NodeImplementation,
     },
     types::SignatureKey,
+    Networks,
 };
 use hotshot_example_types::{
     block_types::{TestBlockHeader, TestBlockPayload, TestTransaction},
@@ -21,7 +34,11 @@ use hotshot_types::{
     message::{DataMessage, Message, MessageKind, VersionedMessage},
     signature_key::{BLSPubKey, BuilderKey},
     traits::{
-        network::{ConnectedNetwork, TestableNetworkingImplementation},
+        metrics::Counter,
+        network::{
+            AsynchronousNetwork, ConnectedNetwork, NetworkError, NetworkStats, PartitionedNetwork,
+            PerfectNetwork, TestableNetworkingImplementation,
+        },
         node_implementation::{ConsensusTime, NodeType},
     },
 };
@@ -75,8 +92,8 @@ impl NodeImplementation<Test> for TestImpl {
 /// data message
 fn fake_message_eq(message_1: Message<Test>, message_2: Message<Test>) {
     assert_eq!(message_1.sender, message_2.sender);
-    if let MessageKind::Data(DataMessage::SubmitTransaction(d_1, _)) = message_1.kind {
-        if let MessageKind::Data(DataMessage::SubmitTransaction(d_2, _)) = message_2.kind {
+    if let MessageKind::Data(DataMessage::SubmitTransaction(d_1, ..)) = message_1.kind {
+        if let MessageKind::Data(DataMessage::SubmitTransaction(d_2, ..)) = message_2.kind {
             assert_eq!(d_1, d_2);
         }
     } else {
@@ -344,3 +361,631 @@ async fn memory_network_test_in_flight_message_count() {
         Some(0)
     );
 }
+
+// Check that a broadcast scheduled for a time is delivered at that time and not before, while a
+// broadcast scheduled in the past is sent straight away
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn memory_network_broadcast_until() {
+    setup_logging();
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    trace!(?group);
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(pub_key_1, &group.clone(), Option::None);
+    let pub_key_2 = pubkey();
+    let network2 = MemoryNetwork::new(pub_key_2, &group, Option::None);
+
+    let message = gen_messages(1, 100, pub_key_1).pop().unwrap();
+    let serialized_message = VersionedMessage::serialize(&message, &None).unwrap();
+    let recipients = BTreeSet::from([pub_key_2]);
+
+    let release = SystemTime::now() + Duration::from_millis(500);
+    network1
+        .broadcast_message(
+            serialized_message.clone(),
+            recipients.clone(),
+            BroadcastDelay::Until(release),
+        )
+        .await
+        .expect("Failed to schedule broadcast");
+    let recv_messages = async_timeout(Duration::from_secs(5), network2.recv_msgs())
+        .await
+        .expect("Scheduled broadcast was never delivered")
+        .unwrap();
+    assert!(SystemTime::now() >= release);
+    assert!(release.elapsed().unwrap() < Duration::from_secs(1));
+    assert_eq!(recv_messages, vec![serialized_message.clone()]);
+
+    let release = SystemTime::now() - Duration::from_secs(1);
+    network1
+        .broadcast_message(
+            serialized_message.clone(),
+            recipients,
+            BroadcastDelay::Until(release),
+        )
+        .await
+        .expect("Failed to send broadcast");
+    let recv_messages = async_timeout(Duration::from_millis(100), network2.recv_msgs())
+        .await
+        .expect("Broadcast scheduled in the past was not sent immediately")
+        .unwrap();
+    assert_eq!(recv_messages, vec![serialized_message]);
+}
+
+// Check that acknowledged direct messages resolve once the recipient receives them, and time out
+// when nobody is receiving
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn memory_network_direct_message_ack() {
+    setup_logging();
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    trace!(?group);
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(pub_key_1, &group.clone(), Option::None);
+    let pub_key_2 = pubkey();
+    let network2 = MemoryNetwork::new(pub_key_2, &group.clone(), Option::None);
+    let pub_key_3 = pubkey();
+    let _network3 = MemoryNetwork::new(pub_key_3, &group, Option::None);
+
+    let message = gen_messages(1, 100, pub_key_1).pop().unwrap();
+    let serialized_message = VersionedMessage::serialize(&message, &None).unwrap();
+
+    // Network 2 is actively receiving, so it acknowledges the message
+    let receiver = async_spawn(async move {
+        network2
+            .recv_msgs()
+            .await
+            .expect("Failed to receive message")
+    });
+    network1
+        .direct_message_ack(
+            serialized_message.clone(),
+            pub_key_2,
+            Duration::from_secs(5),
+        )
+        .await
+        .expect("Message was not acknowledged");
+    #[cfg(async_executor_impl = "tokio")]
+    let recv_messages = receiver.await.unwrap();
+    #[cfg(async_executor_impl = "async-std")]
+    let recv_messages = receiver.await;
+    assert_eq!(recv_messages, vec![serialized_message.clone()]);
+
+    // Nobody is receiving on network 3, so the message is never acknowledged
+    let result = network1
+        .direct_message_ack(
+            serialized_message.clone(),
+            pub_key_3,
+            Duration::from_millis(200),
+        )
+        .await;
+    assert!(matches!(result, Err(NetworkError::Timeout { .. })));
+}
+
+// Check that a receiver handed the acknowledgement channel decides when the message is
+// acknowledged, and that dropping it means the message was never delivered
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn memory_network_direct_message_ack_after_processing() {
+    setup_logging();
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    trace!(?group);
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(pub_key_1, &group.clone(), Option::None);
+    let pub_key_2 = pubkey();
+    let network2 = MemoryNetwork::new(pub_key_2, &group, Option::None);
+
+    let message = gen_messages(1, 100, pub_key_1).pop().unwrap();
+    let serialized_message = VersionedMessage::serialize(&message, &None).unwrap();
+
+    // The message has been received but not processed, so the sender times out
+    let receiver = {
+        let network2 = network2.clone();
+        async_spawn(async move {
+            network2
+                .recv_msgs_with_acks()
+                .await
+                .expect("Failed to receive message")
+        })
+    };
+    let result = network1
+        .direct_message_ack(
+            serialized_message.clone(),
+            pub_key_2,
+            Duration::from_millis(200),
+        )
+        .await;
+    assert!(matches!(result, Err(NetworkError::Timeout { .. })));
+    #[cfg(async_executor_impl = "tokio")]
+    let recv_messages = receiver.await.unwrap();
+    #[cfg(async_executor_impl = "async-std")]
+    let recv_messages = receiver.await;
+    assert_eq!(recv_messages.len(), 1);
+    assert!(recv_messages[0].1.is_some());
+
+    // Acknowledging once the message is processed resolves the send
+    let receiver = {
+        let network2 = network2.clone();
+        async_spawn(async move {
+            for (_, ack) in network2
+                .recv_msgs_with_acks()
+                .await
+                .expect("Failed to receive message")
+            {
+                ack.expect("Message should be acknowledgeable")
+                    .sender
+                    .send(())
+                    .unwrap();
+            }
+        })
+    };
+    network1
+        .direct_message_ack(
+            serialized_message.clone(),
+            pub_key_2,
+            Duration::from_secs(5),
+        )
+        .await
+        .expect("Message was not acknowledged");
+    #[cfg(async_executor_impl = "tokio")]
+    receiver.await.unwrap();
+    #[cfg(async_executor_impl = "async-std")]
+    receiver.await;
+
+    // Dropping the channel without acknowledging means the message wasn't delivered
+    let receiver = async_spawn(async move {
+        drop(
+            network2
+                .recv_msgs_with_acks()
+                .await
+                .expect("Failed to receive message"),
+        );
+    });
+    let result = network1
+        .direct_message_ack(serialized_message, pub_key_2, Duration::from_secs(5))
+        .await;
+    assert!(matches!(result, Err(NetworkError::CouldNotDeliver)));
+    #[cfg(async_executor_impl = "tokio")]
+    receiver.await.unwrap();
+    #[cfg(async_executor_impl = "async-std")]
+    receiver.await;
+}
+
+// Check that a broadcast to a node which stopped receiving times out once its queue is full,
+// reporting how many recipients the stalled broadcast had
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn memory_network_broadcast_timeout() {
+    setup_logging();
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    trace!(?group);
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(pub_key_1, &group.clone(), Option::None);
+    let pub_key_2 = pubkey();
+    let _network2 = MemoryNetwork::new(pub_key_2, &group, Option::None);
+
+    let message = gen_messages(1, 100, pub_key_1).pop().unwrap();
+    let serialized_message = VersionedMessage::serialize(&message, &None).unwrap();
+    let recipients = BTreeSet::from([pub_key_2]);
+
+    // Nobody is receiving on network 2, so its queue eventually fills up and broadcasts block
+    let mut result = Ok(());
+    for _ in 0..1000 {
+        result = network1
+            .broadcast_message_timeout(
+                serialized_message.clone(),
+                recipients.clone(),
+                BroadcastDelay::None,
+                Duration::from_millis(100),
+            )
+            .await;
+        if result.is_err() {
+            break;
+        }
+    }
+    assert!(matches!(
+        result,
+        Err(NetworkError::Timeout {
+            num_recipients: Some(1),
+            ..
+        })
+    ));
+}
+
+// Check that a direct message to a node which isn't registered is an error, not silently dropped
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn memory_network_direct_message_unknown_node() {
+    setup_logging();
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    trace!(?group);
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(pub_key_1, &group.clone(), Option::None);
+    let pub_key_2 = pubkey();
+    let _network2 = MemoryNetwork::new(pub_key_2, &group, Option::None);
+    // Not part of the network
+    let pub_key_3 = pubkey();
+
+    let message = gen_messages(1, 100, pub_key_1).pop().unwrap();
+    let serialized_message = VersionedMessage::serialize(&message, &None).unwrap();
+    let result = network1
+        .direct_message(serialized_message.clone(), pub_key_3)
+        .await;
+    assert!(matches!(result, Err(NetworkError::NoSuchNode)));
+    assert_eq!(network1.stats().messages_failed, Some(1));
+}
+
+// Check that a VID broadcast reports exactly which recipients it failed to reach
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn memory_network_vid_broadcast_failures() {
+    setup_logging();
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    trace!(?group);
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(pub_key_1, &group.clone(), Option::None);
+    let pub_key_2 = pubkey();
+    let network2 = MemoryNetwork::new(pub_key_2, &group, Option::None);
+    // Not part of the network
+    let pub_key_3 = pubkey();
+
+    let message = gen_messages(1, 100, pub_key_1).pop().unwrap();
+    let serialized_message = VersionedMessage::serialize(&message, &None).unwrap();
+    let result = network1
+        .vid_broadcast_message(HashMap::from([
+            (pub_key_2, serialized_message.clone()),
+            (pub_key_3, serialized_message.clone()),
+        ]))
+        .await;
+
+    let Err(NetworkError::PartialBroadcast { failures }) = result else {
+        panic!("Expected a partial broadcast, got {result:?}");
+    };
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0, mnemonic(&pub_key_3));
+    assert!(matches!(*failures[0].1, NetworkError::NoSuchNode));
+    let recv_messages = network2
+        .recv_msgs()
+        .await
+        .expect("Failed to receive message");
+    assert_eq!(recv_messages, vec![serialized_message]);
+}
+
+/// A counter recording its total, for checking what a network reports
+#[derive(Clone, Debug, Default)]
+struct TotalCounter(Arc<AtomicUsize>);
+
+impl Counter for TotalCounter {
+    fn add(&self, amount: usize) {
+        self.0.fetch_add(amount, Ordering::Relaxed);
+    }
+}
+
+// Check that messages larger than the configured maximum are dropped on receipt and counted
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn memory_network_drops_oversized_messages() {
+    setup_logging();
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    trace!(?group);
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(pub_key_1, &group.clone(), Option::None);
+    let pub_key_2 = pubkey();
+    let num_oversized_messages = TotalCounter::default();
+    let network2 = MemoryNetwork::new_with_metrics(
+        pub_key_2,
+        &group,
+        Option::None,
+        NetworkingMetricsValue {
+            num_oversized_messages: Box::new(num_oversized_messages.clone()),
+        },
+    );
+
+    let message = gen_messages(1, 100, pub_key_1).pop().unwrap();
+    let serialized_message = VersionedMessage::serialize(&message, &None).unwrap();
+    network2.set_max_inbound_message_size(serialized_message.len());
+
+    // Send an oversized frame followed by one that fits
+    network1
+        .direct_message(vec![0; serialized_message.len() + 1], pub_key_2)
+        .await
+        .expect("Failed to message node");
+    network1
+        .direct_message(serialized_message.clone(), pub_key_2)
+        .await
+        .expect("Failed to message node");
+
+    let mut recv_messages = Vec::new();
+    while recv_messages.is_empty() {
+        recv_messages = network2
+            .recv_msgs()
+            .await
+            .expect("Failed to receive message");
+    }
+    assert_eq!(recv_messages, vec![serialized_message]);
+    assert_eq!(num_oversized_messages.0.load(Ordering::Relaxed), 1);
+}
+
+/// Number of messages sent through the unreliable network
+const NUM_MESSAGES: usize = 1000;
+
+/// Send `NUM_MESSAGES` copies of `message` from `network` to `recipient` on `receiver`, returning
+/// the number of messages received once nothing has arrived for a second
+async fn count_delivered<N: ConnectedNetwork<BLSPubKey>>(
+    network: &N,
+    receiver: &MemoryNetwork<<Test as NodeType>::SignatureKey>,
+    recipient: BLSPubKey,
+    message: &[u8],
+) -> usize {
+    let network = network.clone();
+    let message = message.to_vec();
+    async_spawn(async move {
+        for _ in 0..NUM_MESSAGES {
+            network
+                .direct_message(message.clone(), recipient)
+                .await
+                .expect("Failed to message node");
+        }
+    });
+
+    let mut count = 0;
+    while let Ok(messages) = async_timeout(Duration::from_secs(1), receiver.recv_msgs()).await {
+        count += messages.expect("Failed to receive message").len();
+    }
+    count
+}
+
+// Check that a wrapped network drops messages at the rate its reliability model is configured
+// with, and passes every message through without one
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn unreliable_network_drops_messages_at_configured_rate() {
+    setup_logging();
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    trace!(?group);
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(pub_key_1, &group.clone(), Option::None);
+    let pub_key_2 = pubkey();
+    let network2 = MemoryNetwork::new(pub_key_2, &group, Option::None);
+
+    let message = gen_messages(1, 100, pub_key_1).pop().unwrap();
+    let serialized_message = VersionedMessage::serialize(&message, &None).unwrap();
+
+    let reliable = UnreliableNetwork::new(network1.clone(), None);
+    assert_eq!(
+        count_delivered(&reliable, &network2, pub_key_2, &serialized_message).await,
+        NUM_MESSAGES
+    );
+
+    // Keep half the messages
+    let lossy = UnreliableNetwork::new(
+        network1,
+        Some(Box::new(AsynchronousNetwork {
+            keep_numerator: 1,
+            keep_denominator: 2,
+            delay_low_ms: 0,
+            delay_high_ms: 0,
+            ..Default::default()
+        })),
+    );
+    let delivered = count_delivered(&lossy, &network2, pub_key_2, &serialized_message).await;
+    assert!(
+        (400..=600).contains(&delivered),
+        "Expected about half of {NUM_MESSAGES} messages, received {delivered}"
+    );
+}
+
+// Check that the bounded and VID broadcasts go through the reliability model too
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn unreliable_network_drops_bounded_and_vid_broadcasts() {
+    setup_logging();
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    trace!(?group);
+    let pub_key_1 = pubkey();
+    let network1 = MemoryNetwork::new(pub_key_1, &group.clone(), Option::None);
+    let pub_key_2 = pubkey();
+    let network2 = MemoryNetwork::new(pub_key_2, &group, Option::None);
+
+    let message = gen_messages(1, 100, pub_key_1).pop().unwrap();
+    let serialized_message = VersionedMessage::serialize(&message, &None).unwrap();
+    let recipients = BTreeSet::from([pub_key_2]);
+    let timeout = Duration::from_secs(1);
+
+    // Drop every message
+    let lossy = UnreliableNetwork::new(
+        network1.clone(),
+        Some(Box::new(AsynchronousNetwork {
+            keep_numerator: 0,
+            keep_denominator: 1,
+            delay_low_ms: 0,
+            delay_high_ms: 0,
+            ..Default::default()
+        })),
+    );
+    lossy
+        .broadcast_message_timeout(
+            serialized_message.clone(),
+            recipients.clone(),
+            BroadcastDelay::None,
+            timeout,
+        )
+        .await
+        .expect("Failed to broadcast message");
+    lossy
+        .da_broadcast_message_timeout(
+            serialized_message.clone(),
+            recipients.clone(),
+            BroadcastDelay::None,
+            timeout,
+        )
+        .await
+        .expect("Failed to broadcast message");
+    lossy
+        .vid_broadcast_message(HashMap::from([(pub_key_2, serialized_message.clone())]))
+        .await
+        .expect("Failed to send VID shares");
+    assert!(
+        async_timeout(Duration::from_secs(1), network2.recv_msgs())
+            .await
+            .is_err(),
+        "Dropped messages were delivered"
+    );
+
+    // Without a reliability model each of them gets through
+    let reliable = UnreliableNetwork::new(network1, None);
+    reliable
+        .broadcast_message_timeout(
+            serialized_message.clone(),
+            recipients.clone(),
+            BroadcastDelay::None,
+            timeout,
+        )
+        .await
+        .expect("Failed to broadcast message");
+    reliable
+        .da_broadcast_message_timeout(
+            serialized_message.clone(),
+            recipients,
+            BroadcastDelay::None,
+            timeout,
+        )
+        .await
+        .expect("Failed to broadcast message");
+    reliable
+        .vid_broadcast_message(HashMap::from([(pub_key_2, serialized_message)]))
+        .await
+        .expect("Failed to send VID shares");
+    let mut count = 0;
+    while let Ok(messages) = async_timeout(Duration::from_secs(1), network2.recv_msgs()).await {
+        count += messages.expect("Failed to receive message").len();
+    }
+    assert_eq!(count, 3);
+}
+
+// Check that the message stats of the quorum and DA networks are added together while their
+// connected peers aren't, and that stats a network doesn't report don't hide the ones the other
+// network does
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn memory_network_stats_are_aggregated() {
+    setup_logging();
+    let quorum_group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    let quorum_key_1 = pubkey();
+    let quorum_network_1 = MemoryNetwork::new(quorum_key_1, &quorum_group, Option::None);
+    let quorum_key_2 = pubkey();
+    let quorum_network_2 = MemoryNetwork::new(quorum_key_2, &quorum_group, Option::None);
+
+    let da_group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    let da_network = MemoryNetwork::new(pubkey(), &da_group, Option::None);
+    let da_keys: BTreeSet<_> = (0..2).map(|_| pubkey()).collect();
+    for key in &da_keys {
+        MemoryNetwork::new(*key, &da_group, Option::None);
+    }
+
+    let networks = Networks::<Test, TestImpl> {
+        quorum_network: Arc::new(quorum_network_1.clone()),
+        da_network: Arc::new(da_network.clone()),
+        _pd: PhantomData,
+    };
+    let message =
+        VersionedMessage::serialize(&gen_messages(1, 100, quorum_key_1)[0], &None).unwrap();
+
+    // Two messages sent on the quorum network, and one received.
+    for _ in 0..2 {
+        quorum_network_1
+            .direct_message(message.clone(), quorum_key_2)
+            .await
+            .unwrap();
+    }
+    quorum_network_2
+        .direct_message(message.clone(), quorum_key_1)
+        .await
+        .unwrap();
+    assert_eq!(quorum_network_1.recv_msgs().await.unwrap().len(), 1);
+
+    // A broadcast to two nodes on the DA network, and a message to a node that doesn't exist.
+    da_network
+        .da_broadcast_message(message.clone(), da_keys, BroadcastDelay::None)
+        .await
+        .unwrap();
+    assert!(da_network
+        .direct_message(message, quorum_key_2)
+        .await
+        .is_err());
+
+    assert_eq!(
+        networks.stats(),
+        NetworkStats {
+            messages_sent: Some(5),
+            messages_received: Some(1),
+            messages_failed: Some(1),
+            in_flight_messages: Some(0),
+            connected_peers: Some(2),
+        }
+    );
+
+    // A network which doesn't report a stat leaves the other network's value.
+    let partial = NetworkStats {
+        messages_sent: Some(1),
+        ..NetworkStats::default()
+    };
+    assert_eq!(partial.combine(networks.stats()).messages_sent, Some(6));
+    assert_eq!(partial.combine(NetworkStats::default()), partial);
+    assert_eq!(
+        NetworkStats::default().combine(NetworkStats::default()),
+        NetworkStats::default()
+    );
+}
+
+/// Generated networks are told apart by the reliability config by their node id, whatever order
+/// they join the group in.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn memory_network_indexes_generated_nodes_by_id() {
+    setup_logging();
+
+    let generator = <MemoryNetwork<BLSPubKey> as TestableNetworkingImplementation<Test>>::generator(
+        3,
+        0,
+        0,
+        0,
+        false,
+        Some(Box::new(PartitionedNetwork::isolating(
+            &[0],
+            3,
+            Box::new(PerfectNetwork {}),
+        ))),
+        Duration::ZERO,
+    );
+    // Join in reverse, so the order of joining differs from the node ids.
+    let (network2, _) = generator(2).await;
+    let (network1, _) = generator(1).await;
+    let (network0, _) = generator(0).await;
+    let key = |id| BLSPubKey::generated_from_seed_indexed([0u8; 32], id).0;
+
+    let message = VersionedMessage::serialize(&gen_messages(1, 100, key(1))[0], &None).unwrap();
+    network1
+        .direct_message(message.clone(), key(0))
+        .await
+        .unwrap();
+    network1.direct_message(message, key(2)).await.unwrap();
+
+    // Node 2 is on node 1's side of the partition, but node 0 is cut off.
+    assert_eq!(network2.recv_msgs().await.unwrap().len(), 1);
+    assert!(
+        async_timeout(Duration::from_millis(100), network0.recv_msgs())
+            .await
+            .is_err()
+    );
+}
@@ -1,5 +1,6 @@
-use std::time::Duration;
+use std::{collections::BTreeSet, time::Duration};
 
+use hotshot::traits::implementations::union_connected_peers;
 use hotshot_example_types::node_types::{CombinedImpl, TestTypes};
 use hotshot_testing::{
     block_builder::SimpleBuilderImplementation,
@@ -266,3 +267,24 @@ async fn test_stress_combined_network_fuzzy() {
         .run_test::<SimpleBuilderImplementation>()
         .await;
 }
+
+/// The combined network reports the peers reachable on either network, and a network that can't
+/// tell doesn't hide the peers reported by the other.
+#[test]
+fn test_combined_network_connected_peers_union() {
+    let primary = BTreeSet::from([1, 2]);
+    let secondary = BTreeSet::from([2, 3]);
+    assert_eq!(
+        union_connected_peers(Some(primary.clone()), Some(secondary.clone())),
+        Some(BTreeSet::from([1, 2, 3]))
+    );
+    assert_eq!(
+        union_connected_peers(Some(primary.clone()), None),
+        Some(primary)
+    );
+    assert_eq!(
+        union_connected_peers(None, Some(secondary)),
+        Some(BTreeSet::from([2, 3]))
+    );
+    assert_eq!(union_connected_peers::<u64>(None, None), None);
+}
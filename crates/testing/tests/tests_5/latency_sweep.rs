@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    latency_sweep::LatencySweep,
+    overall_safety_task::OverallSafetyPropertiesDescription,
+    test_builder::TestDescription,
+};
+use tracing::instrument;
+
+/// Sanity check of the sweep harness: the same number of views take longer to decide as the
+/// network delay increases.
+#[cfg(test)]
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+#[instrument]
+async fn test_memory_network_latency_sweep() {
+    async_compatibility_layer::logging::setup_logging();
+    async_compatibility_layer::logging::setup_backtrace();
+    let sweep = LatencySweep {
+        description: TestDescription {
+            overall_safety_properties: OverallSafetyPropertiesDescription {
+                num_successful_views: 10,
+                ..TestDescription::default_multiple_rounds().overall_safety_properties
+            },
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: Duration::from_secs(120),
+                },
+            ),
+            ..TestDescription::default_multiple_rounds()
+        },
+        delays: [10, 50, 200]
+            .into_iter()
+            .map(Duration::from_millis)
+            .collect(),
+    };
+
+    let report = sweep
+        .run::<TestTypes, MemoryImpl, _, SimpleBuilderImplementation>()
+        .await;
+    tracing::info!("Latency sweep results:\n{report}");
+
+    assert_eq!(report.rows.len(), sweep.delays.len());
+    for rows in report.rows.windows(2) {
+        assert!(
+            rows[0].stats.throughput() > rows[1].stats.throughput(),
+            "Throughput didn't decrease from {:?} to {:?}:\n{report}",
+            rows[0].delay,
+            rows[1].delay,
+        );
+    }
+}
@@ -1,4 +1,10 @@
-use std::time::{Duration, Instant};
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use hotshot_example_types::node_types::{Libp2pImpl, TestTypes};
 use hotshot_testing::{
@@ -7,8 +13,12 @@ use hotshot_testing::{
     overall_safety_task::OverallSafetyPropertiesDescription,
     test_builder::{TestDescription, TimingData},
 };
-use hotshot_types::traits::network::{
-    AsynchronousNetwork, ChaosNetwork, PartiallySynchronousNetwork, SynchronousNetwork,
+use hotshot_types::{
+    traits::network::{
+        AsynchronousNetwork, ChaosNetwork, CompositeNetwork, NetworkReliability,
+        PartiallySynchronousNetwork, PartitionedNetwork, PerfectNetwork, SynchronousNetwork,
+    },
+    BoxSyncFuture,
 };
 use tracing::instrument;
 
@@ -31,6 +41,7 @@ async fn libp2p_network_sync() {
         unreliable_network: Some(Box::new(SynchronousNetwork {
             delay_high_ms: 30,
             delay_low_ms: 4,
+            ..Default::default()
         })),
         ..TestDescription::default_multiple_rounds()
     };
@@ -66,6 +77,7 @@ async fn test_memory_network_sync() {
         unreliable_network: Some(Box::new(SynchronousNetwork {
             delay_high_ms: 30,
             delay_low_ms: 4,
+            ..Default::default()
         })),
         ..TestDescription::default()
     };
@@ -104,6 +116,7 @@ async fn libp2p_network_async() {
             keep_denominator: 10,
             delay_low_ms: 4,
             delay_high_ms: 30,
+            ..Default::default()
         })),
         ..TestDescription::default_multiple_rounds()
     };
@@ -152,6 +165,7 @@ async fn test_memory_network_async() {
             keep_denominator: 100,
             delay_low_ms: 4,
             delay_high_ms: 30,
+            ..Default::default()
         })),
         ..TestDescription::default()
     };
@@ -197,13 +211,16 @@ async fn test_memory_network_partially_sync() {
                 keep_denominator: 10,
                 delay_low_ms: 4,
                 delay_high_ms: 30,
+                ..Default::default()
             },
             synchronous: SynchronousNetwork {
                 delay_high_ms: 30,
                 delay_low_ms: 4,
+                ..Default::default()
             },
             gst: std::time::Duration::from_millis(1000),
             start: Instant::now(),
+            ..Default::default()
         })),
         ..TestDescription::default()
     };
@@ -236,13 +253,16 @@ async fn libp2p_network_partially_sync() {
                 keep_denominator: 10,
                 delay_low_ms: 4,
                 delay_high_ms: 30,
+                ..Default::default()
             },
             synchronous: SynchronousNetwork {
                 delay_high_ms: 30,
                 delay_low_ms: 4,
+                ..Default::default()
             },
             gst: std::time::Duration::from_millis(1000),
             start: Instant::now(),
+            ..Default::default()
         })),
         ..TestDescription::default_multiple_rounds()
     };
@@ -283,6 +303,7 @@ async fn test_memory_network_chaos() {
             delay_high_ms: 30,
             repeat_low: 1,
             repeat_high: 5,
+            ..Default::default()
         })),
         ..TestDescription::default()
     };
@@ -317,6 +338,7 @@ async fn libp2p_network_chaos() {
             delay_high_ms: 30,
             repeat_low: 1,
             repeat_high: 5,
+            ..Default::default()
         })),
         ..TestDescription::default_multiple_rounds()
     };
@@ -327,3 +349,258 @@ async fn libp2p_network_chaos() {
         .run_test::<SimpleBuilderImplementation>()
         .await;
 }
+
+/// Two chaos networks with the same seed make the same keep, delay, repeat and reorder decisions.
+#[test]
+fn test_seeded_chaos_network_is_deterministic() {
+    let chaos = || {
+        ChaosNetwork {
+            keep_numerator: 8,
+            keep_denominator: 10,
+            delay_low_ms: 4,
+            delay_high_ms: 30,
+            repeat_low: 1,
+            repeat_high: 5,
+            reorder_probability: 0.5,
+            ..Default::default()
+        }
+        .with_seed(1234)
+    };
+    let samples = |network: &ChaosNetwork| {
+        (0..100)
+            .map(|_| {
+                (
+                    network.sample_keep(),
+                    network.sample_delay(),
+                    network.sample_repeat(),
+                    network.sample_reorder(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(samples(&chaos()), samples(&chaos()));
+}
+
+/// Copies of a seeded network share its randomness, continuing its sequence of samples rather
+/// than repeating it.
+#[test]
+fn test_seeded_network_copies_share_rng() {
+    let seeded = || AsynchronousNetwork::new(1, 2, 0, 1000).with_seed(1234);
+    let samples = |network: &AsynchronousNetwork, n| {
+        (0..n)
+            .map(|_| (network.sample_keep(), network.sample_delay()))
+            .collect::<Vec<_>>()
+    };
+
+    let network = seeded();
+    let copy = network;
+    let mut shared = samples(&network, 10);
+    shared.extend(samples(&copy, 10));
+    assert_eq!(shared, samples(&seeded(), 20));
+}
+
+/// Corrupts every message by inverting its first byte
+#[derive(Clone, Debug)]
+struct CorruptingNetwork;
+
+impl NetworkReliability for CorruptingNetwork {
+    fn scramble(&self, mut msg: Vec<u8>) -> Vec<u8> {
+        if let Some(byte) = msg.first_mut() {
+            *byte = !*byte;
+        }
+        msg
+    }
+}
+
+/// Sends three numbered copies of each message, the later copies with shorter delays
+#[derive(Clone, Debug)]
+struct NumberingNetwork {
+    /// whether to reorder the copies
+    reorder: bool,
+    /// how many copies have been made
+    copies: Arc<AtomicU8>,
+    /// how many delays have been sampled
+    delays: Arc<AtomicU64>,
+}
+
+impl NetworkReliability for NumberingNetwork {
+    fn sample_delay(&self) -> Duration {
+        Duration::from_millis(40 - 10 * self.delays.fetch_add(1, Ordering::SeqCst))
+    }
+
+    fn scramble(&self, mut msg: Vec<u8>) -> Vec<u8> {
+        msg.push(self.copies.fetch_add(1, Ordering::SeqCst));
+        msg
+    }
+
+    fn sample_repeat(&self) -> usize {
+        3
+    }
+
+    fn sample_reorder(&self) -> bool {
+        self.reorder
+    }
+}
+
+/// The copies of a message are sent in order after a single delay, unless they're reordered, in
+/// which case each is sent after its own delay.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_reordered_copies_are_shuffled() {
+    let run = |reorder| async move {
+        let network = NumberingNetwork {
+            reorder,
+            copies: Arc::default(),
+            delays: Arc::default(),
+        };
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let send_fn = {
+            let received = Arc::clone(&received);
+            Arc::new(move |msg: Vec<u8>| -> BoxSyncFuture<'static, ()> {
+                received.lock().unwrap().push(msg);
+                Box::pin(async {})
+            })
+        };
+        network.chaos_send_msg(vec![7], send_fn).await;
+        let received = received.lock().unwrap().clone();
+        received
+    };
+
+    assert_eq!(run(false).await, vec![vec![7, 0], vec![7, 1], vec![7, 2]]);
+    assert_eq!(run(true).await, vec![vec![7, 2], vec![7, 1], vec![7, 0]]);
+}
+
+/// A chaos network shuffles reordered messages with each other, not just the copies of each one,
+/// and sends every message it's given exactly once.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_chaos_network_reorders_across_messages() {
+    let run = |reorder_probability| async move {
+        let network = ChaosNetwork {
+            reorder_probability,
+            ..Default::default()
+        }
+        .with_seed(1234);
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let send_fn = {
+            let received = Arc::clone(&received);
+            Arc::new(move |msg: Vec<u8>| -> BoxSyncFuture<'static, ()> {
+                received.lock().unwrap().push(msg);
+                Box::pin(async {})
+            })
+        };
+        // Every message is handed to the network before any of them is sent.
+        let sends: Vec<_> = (0..20_u8)
+            .map(|i| network.chaos_send_msg(vec![i], send_fn.clone()))
+            .collect();
+        for send in sends {
+            send.await;
+        }
+        let received = received.lock().unwrap().clone();
+        received
+    };
+
+    let in_order: Vec<_> = (0..20_u8).map(|i| vec![i]).collect();
+    assert_eq!(run(0.0).await, in_order);
+
+    let mut reordered = run(1.0).await;
+    assert_ne!(reordered, in_order);
+    reordered.sort();
+    assert_eq!(reordered, in_order);
+}
+
+/// A composition of lossy and corrupting networks both drops and corrupts messages, and makes the
+/// same decisions for the same seed.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_composite_network_combines_effects() {
+    let run = || async {
+        let network = CompositeNetwork::new(vec![
+            Box::new(AsynchronousNetwork::new(1, 2, 0, 0).with_seed(1234)),
+            Box::new(CorruptingNetwork),
+        ]);
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let send_fn = {
+            let received = Arc::clone(&received);
+            Arc::new(move |msg: Vec<u8>| -> BoxSyncFuture<'static, ()> {
+                received.lock().unwrap().push(msg);
+                Box::pin(async {})
+            })
+        };
+        for i in 0..100_u8 {
+            network.chaos_send_msg(vec![i], send_fn.clone()).await;
+        }
+        let received = received.lock().unwrap().clone();
+        received
+    };
+
+    let received = run().await;
+    assert!(!received.is_empty() && received.len() < 100);
+    for msg in &received {
+        assert!(
+            (0..100_u8).any(|i| *msg == vec![!i]),
+            "{msg:?} wasn't corrupted"
+        );
+    }
+
+    assert_eq!(received, run().await);
+}
+
+/// A partitioned network drops messages either way between the isolated node and the rest, also
+/// when composed with other models, while messages within each side get through.
+#[cfg_attr(async_executor_impl = "tokio", tokio::test(flavor = "multi_thread"))]
+#[cfg_attr(async_executor_impl = "async-std", async_std::test)]
+async fn test_partitioned_network_blocks_pairs() {
+    let partitioned = PartitionedNetwork::isolating(&[0], 3, Box::new(PerfectNetwork {}));
+    assert!(partitioned.is_blocked(1, 0));
+    assert!(!partitioned.is_blocked(1, 2));
+
+    let composite = CompositeNetwork::new(vec![
+        Box::new(PerfectNetwork {}),
+        Box::new(partitioned.clone()),
+    ]);
+    let networks: [Box<dyn NetworkReliability>; 2] = [Box::new(partitioned), Box::new(composite)];
+    for network in networks {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let send_fn = {
+            let received = Arc::clone(&received);
+            Arc::new(move |msg: Vec<u8>| -> BoxSyncFuture<'static, ()> {
+                received.lock().unwrap().push(msg);
+                Box::pin(async {})
+            })
+        };
+        for (from, to, msg) in [(0, 1, 0), (2, 0, 1), (1, 2, 2), (0, 0, 3)] {
+            network
+                .chaos_send_msg_between(from, to, vec![msg], send_fn.clone())
+                .await;
+        }
+        // Without the nodes, nothing can be blocked.
+        network.chaos_send_msg(vec![4], send_fn).await;
+
+        assert_eq!(*received.lock().unwrap(), vec![vec![2], vec![3], vec![4]]);
+    }
+}
+
+/// Triggering GST on a partially synchronous network makes it, and its copies, synchronous
+/// straight away.
+#[test]
+fn test_triggered_gst_is_synchronous() {
+    // Before GST every packet is "dropped" until a GST an hour away.
+    let network = PartiallySynchronousNetwork::new(
+        AsynchronousNetwork::new(0, 1, 0, 0),
+        SynchronousNetwork::new(30, 4),
+        Duration::from_secs(3600),
+    );
+    let copy = network;
+    assert!(!copy.gst_reached());
+    assert!(copy.sample_delay() >= Duration::from_secs(3600));
+
+    network.trigger_gst();
+
+    assert!(copy.gst_reached());
+    for _ in 0..100 {
+        let delay = copy.sample_delay();
+        assert!(Duration::from_millis(4) <= delay && delay <= Duration::from_millis(30));
+    }
+}
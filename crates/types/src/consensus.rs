@@ -1,30 +1,36 @@
 //! Provides the core consensus types
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::Bound,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
-use anyhow::{bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 use async_lock::{RwLock, RwLockUpgradableReadGuard};
 use committable::{Commitment, Committable};
+use jf_vid::VidScheme;
 use tracing::{debug, error};
 
 pub use crate::utils::{View, ViewInner};
 use crate::{
-    data::{Leaf, QuorumProposal, VidDisperse, VidDisperseShare},
+    data::{DaProposal, Leaf, QuorumProposal, VidDisperse, VidDisperseShare},
     error::HotShotError,
     message::Proposal,
     simple_certificate::{DaCertificate, QuorumCertificate, UpgradeCertificate},
     traits::{
-        block_contents::BuilderFee,
-        metrics::{Counter, Gauge, Histogram, Metrics, NoMetrics},
+        block_contents::{vid_commitment, BuilderFee},
+        metrics::{
+            Counter, CounterFamily, Gauge, Histogram, Metrics, NoMetrics, PrometheusMetrics,
+        },
         node_implementation::{ConsensusTime, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
         BlockPayload, ValidatedState,
     },
     utils::{BuilderCommitment, StateAndDelta, Terminator},
-    vid::VidCommitment,
+    vid::{vid_recovery_threshold, vid_scheme, VidCommitment, VidCommon, VidSchemeType},
     vote::HasViewNumber,
 };
 
@@ -40,6 +46,15 @@ pub type VidShares<TYPES> = BTreeMap<
 /// Type alias for consensus state wrapped in a lock.
 pub type LockedConsensusState<TYPES> = Arc<RwLock<Consensus<TYPES>>>;
 
+/// What a garbage collection of [`Consensus`] freed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GarbageCollected {
+    /// Number of entries removed across all of the stores
+    pub entries: usize,
+    /// Bytes of saved payloads removed
+    pub payload_bytes: usize,
+}
+
 /// A reference to the consensus algorithm
 ///
 /// This will contain the state of all rounds.
@@ -55,6 +70,13 @@ pub struct Consensus<TYPES: NodeType> {
     /// view -> DA cert
     saved_da_certs: HashMap<TYPES::Time, DaCertificate<TYPES>>,
 
+    /// Payload commitments of the DA proposals we've validated.
+    /// view -> payload commitment
+    ///
+    /// A VID share is paired with the DA proposal for its view when their payload commitments
+    /// match, whichever of the two arrived first.
+    da_payload_commitments: BTreeMap<TYPES::Time, VidCommitment>,
+
     /// View number that is currently on.
     cur_view: TYPES::Time,
 
@@ -62,12 +84,48 @@ pub struct Consensus<TYPES: NodeType> {
     /// Prevents duplicate proposals, and can be served to those trying to catchup
     last_proposals: BTreeMap<TYPES::Time, Proposal<TYPES, QuorumProposal<TYPES>>>,
 
+    /// DA proposals we've validated, which can be served to those trying to catch up on the
+    /// payloads they missed
+    last_da_proposals: BTreeMap<TYPES::Time, Proposal<TYPES, DaProposal<TYPES>>>,
+
+    /// The signers of the proposals whose signatures we've verified, to which their leaves are
+    /// attributed when decided.
+    /// view -> proposer
+    proposers: BTreeMap<TYPES::Time, TYPES::SignatureKey>,
+
     /// last view had a successful decide event
     last_decided_view: TYPES::Time,
 
+    /// Wall-clock time of the last successful decide, or of startup if nothing has been decided
+    /// yet.
+    last_decided_at: Instant,
+
+    /// The view of the last `Decide` event we emitted, None if we haven't emitted one yet.
+    /// Prevents emitting a `Decide` twice for the same view if the decide path runs again.
+    last_emitted_decide_view: Option<TYPES::Time>,
+
     /// The `locked_qc` view number
     locked_view: TYPES::Time,
 
+    /// Number of views before the last decided view kept when garbage collecting. 0 keeps only
+    /// the last decided view onwards.
+    retention_depth: u64,
+
+    /// Number of views below the high QC beyond which the validated states of undecided views are
+    /// compacted, None if they are never compacted.
+    state_compaction_depth: Option<u64>,
+
+    /// Undecided views whose validated state has been compacted to the state rebuilt from their
+    /// leaf's block header.
+    compacted_states: BTreeSet<TYPES::Time>,
+
+    /// The view below which every undecided view has already been considered for compaction, so
+    /// that each high QC update only scans the views it newly brings beyond the depth.
+    compaction_ceiling: TYPES::Time,
+
+    /// Maximum number of undecided views whose states and leaves are kept.
+    max_undecided_views: usize,
+
     /// Map of leaf hash -> leaf
     /// - contains undecided leaves
     /// - includes the MOST RECENT decided leaf
@@ -78,6 +136,11 @@ pub struct Consensus<TYPES: NodeType> {
     /// Encoded transactions for every view if we got a payload for that view.
     saved_payloads: BTreeMap<TYPES::Time, Arc<[u8]>>,
 
+    /// Payloads we submitted as DA leader, with the payload commitment computed when dispersing
+    /// them.
+    /// view -> (encoded transactions, payload commitment)
+    submitted_payloads: BTreeMap<TYPES::Time, (Arc<[u8]>, VidCommitment)>,
+
     /// the highqc per spec
     high_qc: QuorumCertificate<TYPES>,
 
@@ -111,8 +174,12 @@ pub struct ConsensusMetricsValue {
     pub number_of_views_since_last_decide: Box<dyn Gauge>,
     /// Number of views that are in-flight since the last anchor view
     pub number_of_views_per_decide_event: Box<dyn Histogram>,
+    /// Number of seconds since the last decide, updated on every view change
+    pub seconds_since_last_decide: Box<dyn Gauge>,
     /// Duration of views as leader
     pub view_duration_as_leader: Box<dyn Histogram>,
+    /// Milliseconds from sending one of our proposals until it was decided
+    pub proposal_to_decide_ms: Box<dyn Histogram>,
     /// Number of invalid QCs we've seen since the last commit.
     pub invalid_qc: Box<dyn Gauge>,
     /// Number of outstanding transactions
@@ -125,12 +192,31 @@ pub struct ConsensusMetricsValue {
     pub number_of_timeouts_as_leader: Box<dyn Counter>,
     /// The number of empty blocks that have been proposed
     pub number_of_empty_blocks_proposed: Box<dyn Counter>,
+    /// Number of proposals voted on with only their liveness checked, as their parent was missing
+    pub liveness_only_vote_count: Box<dyn Counter>,
+    /// Number of messages received from the network, labelled by `MessagePurpose`
+    pub inbound_messages: Box<dyn CounterFamily>,
+    /// Number of transactions received from the network dropped for lacking a valid proof of work
+    pub invalid_pow_transactions: Box<dyn Counter>,
+    /// A record of the metrics above, which can be rendered for a Prometheus scrape. Other metrics,
+    /// such as the network's, are added to it by creating them through it or through
+    /// [`PrometheusMetrics::tee`].
+    pub exporter: PrometheusMetrics,
 }
 
 impl ConsensusMetricsValue {
     /// Create a new instance of this [`ConsensusMetricsValue`] struct, setting all the counters and gauges
     #[must_use]
     pub fn new(metrics: &dyn Metrics) -> Self {
+        Self::new_with_exporter(metrics, PrometheusMetrics::default())
+    }
+
+    /// Create a new instance of this [`ConsensusMetricsValue`] struct which also records its
+    /// metrics in `exporter`, alongside any metrics, such as the network's, already created
+    /// through it.
+    #[must_use]
+    pub fn new_with_exporter(metrics: &dyn Metrics, exporter: PrometheusMetrics) -> Self {
+        let metrics = exporter.tee(metrics);
         Self {
             last_synced_block_height: metrics
                 .create_gauge(String::from("last_synced_block_height"), None),
@@ -141,8 +227,12 @@ impl ConsensusMetricsValue {
                 .create_gauge(String::from("number_of_views_since_last_decide"), None),
             number_of_views_per_decide_event: metrics
                 .create_histogram(String::from("number_of_views_per_decide_event"), None),
+            seconds_since_last_decide: metrics
+                .create_gauge(String::from("seconds_since_last_decide"), None),
             view_duration_as_leader: metrics
                 .create_histogram(String::from("view_duration_as_leader"), None),
+            proposal_to_decide_ms: metrics
+                .create_histogram(String::from("proposal_to_decide_ms"), None),
             invalid_qc: metrics.create_gauge(String::from("invalid_qc"), None),
             outstanding_transactions: metrics
                 .create_gauge(String::from("outstanding_transactions"), None),
@@ -153,6 +243,15 @@ impl ConsensusMetricsValue {
                 .create_counter(String::from("number_of_timeouts_as_leader"), None),
             number_of_empty_blocks_proposed: metrics
                 .create_counter(String::from("number_of_empty_blocks_proposed"), None),
+            liveness_only_vote_count: metrics
+                .create_counter(String::from("liveness_only_vote_count"), None),
+            inbound_messages: metrics.counter_family(
+                String::from("inbound_messages"),
+                vec![String::from("purpose")],
+            ),
+            invalid_pow_transactions: metrics
+                .create_counter(String::from("invalid_pow_transactions"), None),
+            exporter,
         }
     }
 }
@@ -181,12 +280,23 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             validated_state_map,
             vid_shares: BTreeMap::new(),
             saved_da_certs: HashMap::new(),
+            da_payload_commitments: BTreeMap::new(),
             cur_view,
             last_decided_view,
+            last_decided_at: Instant::now(),
+            last_emitted_decide_view: None,
             last_proposals,
+            last_da_proposals: BTreeMap::new(),
+            proposers: BTreeMap::new(),
             locked_view,
+            retention_depth: 0,
+            state_compaction_depth: None,
+            compacted_states: BTreeSet::new(),
+            compaction_ceiling: TYPES::Time::genesis(),
+            max_undecided_views: usize::MAX,
             saved_leaves,
             saved_payloads,
+            submitted_payloads: BTreeMap::new(),
             high_qc,
             metrics,
             dontuse_decided_upgrade_cert: None,
@@ -204,6 +314,13 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         self.last_decided_view
     }
 
+    /// Get the wall-clock time elapsed since the last decide.
+    ///
+    /// Before the first decide, this is the time elapsed since `Consensus` was created.
+    pub fn time_since_last_decide(&self) -> Duration {
+        self.last_decided_at.elapsed()
+    }
+
     /// Get the locked view.
     pub fn locked_view(&self) -> TYPES::Time {
         self.locked_view
@@ -244,6 +361,16 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         &self.last_proposals
     }
 
+    /// Get the map of the DA proposals we've validated
+    pub fn last_da_proposals(&self) -> &BTreeMap<TYPES::Time, Proposal<TYPES, DaProposal<TYPES>>> {
+        &self.last_da_proposals
+    }
+
+    /// Get the verified signer of the proposal for `view_number`, None if we haven't verified one.
+    pub fn proposer(&self, view_number: TYPES::Time) -> Option<&TYPES::SignatureKey> {
+        self.proposers.get(&view_number)
+    }
+
     /// Update the current view.
     /// # Errors
     /// Can return an error when the new view_number is not higher than the existing view number.
@@ -277,6 +404,28 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         Ok(())
     }
 
+    /// Save a proposal for a view before the last one we proposed for, such as one backfilled
+    /// from its leader after a restart, so we can serve it to peers catching up. Returns whether
+    /// it was saved; a proposal we already have for the view is kept, and one for a view before
+    /// the [`oldest_retained_view`](Self::oldest_retained_view) isn't saved, as it would only be
+    /// garbage collected.
+    pub fn backfill_proposal(&mut self, proposal: Proposal<TYPES, QuorumProposal<TYPES>>) -> bool {
+        let view = proposal.data.view_number();
+        if view < self.oldest_retained_view() || self.last_proposals.contains_key(&view) {
+            return false;
+        }
+        self.last_proposals.insert(view, proposal);
+        true
+    }
+
+    /// Save a DA proposal we've validated, so we can serve it to peers catching up. A proposal we
+    /// already have for the view is kept.
+    pub fn update_last_da_proposals(&mut self, proposal: Proposal<TYPES, DaProposal<TYPES>>) {
+        self.last_da_proposals
+            .entry(proposal.data.view_number())
+            .or_insert(proposal);
+    }
+
     /// Update the last decided view.
     ///
     /// # Errors
@@ -287,6 +436,22 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             "New view isn't newer than the previously decided view."
         );
         self.last_decided_view = view_number;
+        self.last_decided_at = Instant::now();
+        Ok(())
+    }
+
+    /// Record that we are emitting the `Decide` event for `view_number`.
+    ///
+    /// # Errors
+    /// Can return an error when we've already emitted a `Decide` for this view or a later one, in
+    /// which case the event must not be emitted again.
+    pub fn update_last_emitted_decide_view(&mut self, view_number: TYPES::Time) -> Result<()> {
+        ensure!(
+            self.last_emitted_decide_view < Some(view_number),
+            "Already emitted a Decide for view {:?} or later.",
+            view_number
+        );
+        self.last_emitted_decide_view = Some(view_number);
         Ok(())
     }
 
@@ -318,7 +483,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
                 match view.view_inner {
                     ViewInner::Leaf { ref delta, .. } => {
                         ensure!(
-                            delta.is_some(),
+                            delta.is_some() || self.compacted_states.contains(&view_number),
                             "Skipping the state update to not override a `Leaf` view with `None` state delta."
                         );
                     }
@@ -328,10 +493,16 @@ impl<TYPES: NodeType> Consensus<TYPES> {
                 }
             }
         }
+        self.compacted_states.remove(&view_number);
         self.validated_state_map.insert(view_number, view);
         Ok(())
     }
 
+    /// Record `proposer` as the verified signer of the proposal for `view_number`.
+    pub fn update_proposers(&mut self, view_number: TYPES::Time, proposer: TYPES::SignatureKey) {
+        self.proposers.insert(view_number, proposer);
+    }
+
     /// Update the saved leaves with a new leaf.
     pub fn update_saved_leaves(&mut self, leaf: Leaf<TYPES>) {
         self.saved_leaves.insert(leaf.commit(), leaf);
@@ -354,18 +525,47 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         Ok(())
     }
 
-    /// Update the high QC if given a newer one.
+    /// Record the payload we submitted as DA leader for `view_number`, and its payload commitment.
+    pub fn update_submitted_payloads(
+        &mut self,
+        view_number: TYPES::Time,
+        encoded_transactions: Arc<[u8]>,
+        payload_commitment: VidCommitment,
+    ) {
+        self.submitted_payloads
+            .insert(view_number, (encoded_transactions, payload_commitment));
+    }
+
+    /// Get the payload commitment of the payload we submitted for `view_number`, if
+    /// `encoded_transactions` is that payload.
+    pub fn submitted_payload_commitment(
+        &self,
+        view_number: TYPES::Time,
+        encoded_transactions: &[u8],
+    ) -> Option<VidCommitment> {
+        self.submitted_payloads
+            .get(&view_number)
+            .filter(|(submitted, _)| **submitted == *encoded_transactions)
+            .map(|(_, payload_commitment)| *payload_commitment)
+    }
+
+    /// Update the high QC if given a newer one, returning the view of the high QC it replaced, or
+    /// `None` if that was the genesis QC.
     /// # Errors
     /// Can return an error when the provided high_qc is not newer than the existing entry.
-    pub fn update_high_qc(&mut self, high_qc: QuorumCertificate<TYPES>) -> Result<()> {
+    pub fn update_high_qc(
+        &mut self,
+        high_qc: QuorumCertificate<TYPES>,
+    ) -> Result<Option<TYPES::Time>> {
         ensure!(
             high_qc.view_number > self.high_qc.view_number,
             "High QC with an equal or higher view exists."
         );
         debug!("Updating high QC");
-        self.high_qc = high_qc;
+        let previous = std::mem::replace(&mut self.high_qc, high_qc);
+        self.compact_states();
 
-        Ok(())
+        Ok((previous.view_number != TYPES::Time::genesis()).then_some(previous.view_number))
     }
 
     /// Add a new entry to the vid_shares map.
@@ -380,6 +580,94 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             .insert(disperse.data.recipient_key.clone(), disperse);
     }
 
+    /// Record the payload commitment of the DA proposal we've validated for `view_number`.
+    pub fn update_da_payload_commitments(
+        &mut self,
+        view_number: TYPES::Time,
+        payload_commitment: VidCommitment,
+    ) {
+        self.da_payload_commitments
+            .insert(view_number, payload_commitment);
+    }
+
+    /// Get the VID share for `key` which belongs to the DA proposal for `view_number`.
+    ///
+    /// Returns `None` until we have both the DA proposal and the share, or if the share commits to
+    /// a different payload than the proposal.
+    pub fn paired_vid_share(
+        &self,
+        view_number: TYPES::Time,
+        key: &TYPES::SignatureKey,
+    ) -> Option<&Proposal<TYPES, VidDisperseShare<TYPES>>> {
+        let payload_commitment = self.da_payload_commitments.get(&view_number)?;
+        self.vid_shares
+            .get(&view_number)?
+            .get(key)
+            .filter(|share| share.data.payload_commitment == *payload_commitment)
+    }
+
+    /// Try to recover the payload for `view_number` from the VID shares we've stored for it.
+    ///
+    /// This lets a node that missed the DA proposal recover the payload from shares instead. The
+    /// payload is checked against the DA proposal's payload commitment if we've seen it, and
+    /// against the commitment the shares were dispersed for otherwise.
+    ///
+    /// Returns `None` if we don't have enough shares to recover the payload yet.
+    ///
+    /// # Errors
+    /// Returns an error if the shares are inconsistent with each other or with the commitment.
+    pub fn try_reconstruct_payload(&self, view_number: TYPES::Time) -> Option<Result<Arc<[u8]>>> {
+        let shares = self.vid_shares.get(&view_number)?;
+        let first = &shares.values().next()?.data;
+        let num_storage_nodes = VidSchemeType::get_num_storage_nodes(&first.common);
+        if shares.len() < vid_recovery_threshold(num_storage_nodes) as usize {
+            return None;
+        }
+
+        let payload_commitment = self
+            .da_payload_commitments
+            .get(&view_number)
+            .copied()
+            .unwrap_or(first.payload_commitment);
+        Some(Self::reconstruct_payload(
+            shares.values().map(|share| &share.data),
+            &first.common,
+            payload_commitment,
+            num_storage_nodes as usize,
+        ))
+    }
+
+    /// Recover a payload from `shares` and check that it's the payload for `payload_commitment`.
+    fn reconstruct_payload<'a>(
+        shares: impl Iterator<Item = &'a VidDisperseShare<TYPES>>,
+        common: &VidCommon,
+        payload_commitment: VidCommitment,
+        num_storage_nodes: usize,
+    ) -> Result<Arc<[u8]>> {
+        let shares = shares
+            .map(|share| {
+                ensure!(
+                    share.payload_commitment == payload_commitment && share.common == *common,
+                    "VID share from {:?} was dispersed for a different payload",
+                    share.recipient_key
+                );
+                Ok(share.share.clone())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        VidSchemeType::is_consistent(&payload_commitment, common)
+            .map_err(|err| anyhow!("VID common data is inconsistent with the commitment: {err}"))?;
+
+        let payload = vid_scheme(num_storage_nodes)
+            .recover_payload(&shares, common)
+            .map_err(|err| anyhow!("Failed to recover the payload from VID shares: {err}"))?;
+        ensure!(
+            vid_commitment(&payload, num_storage_nodes) == payload_commitment,
+            "Payload recovered from VID shares does not match the payload commitment"
+        );
+
+        Ok(payload.into())
+    }
+
     /// Add a new entry to the da_certs map.
     pub fn update_saved_da_certs(&mut self, view_number: TYPES::Time, cert: DaCertificate<TYPES>) {
         self.saved_da_certs.insert(view_number, cert);
@@ -449,11 +737,16 @@ impl<TYPES: NodeType> Consensus<TYPES> {
                 });
             }
         }
-        Err(HotShotError::LeafNotFound {})
+        Err(HotShotError::LeafNotFound {
+            context: format!(
+                "walking back from view {start_from:?}, stopped at leaf {next_leaf} before reaching {terminator:?}"
+            ),
+        })
     }
 
-    /// Garbage collects based on state change right now, this removes from both the
-    /// `saved_payloads` and `validated_state_map` fields of `Consensus`.
+    /// Garbage collects on a decide moving the anchor from `old_anchor_view` to
+    /// `new_anchor_view`, removing the views older than the retention depth allows from every
+    /// store of `Consensus`. DA certificates are kept from the old anchor, rather than the new one.
     /// # Panics
     /// On inconsistent stored entries
     pub fn collect_garbage(&mut self, old_anchor_view: TYPES::Time, new_anchor_view: TYPES::Time) {
@@ -463,24 +756,226 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             .iter()
             .next()
             .expect("INCONSISTENT STATE: anchor leaf not in state map!");
-        if *anchor_entry.0 != old_anchor_view {
+        if self.retention_depth == 0 && *anchor_entry.0 != old_anchor_view {
             error!(
                 "Something about GC has failed. Older leaf exists than the previous anchor leaf."
             );
         }
         // perform gc
-        self.saved_da_certs
-            .retain(|view_number, _| *view_number >= old_anchor_view);
-        self.validated_state_map
-            .range(old_anchor_view..new_anchor_view)
-            .filter_map(|(_view_number, view)| view.leaf_commitment())
-            .for_each(|leaf| {
+        self.prune_views_before(
+            self.retention_floor(new_anchor_view),
+            self.retention_floor(old_anchor_view),
+        );
+    }
+
+    /// Set how many views before the last decided view are kept when garbage collecting, e.g. a
+    /// large depth for an archival node or a small one for a memory-constrained node. Takes effect
+    /// on the next garbage collection.
+    pub fn set_retention_depth(&mut self, views: u64) {
+        self.retention_depth = views;
+    }
+
+    /// Get how many views before the last decided view are kept when garbage collecting.
+    #[must_use]
+    pub fn retention_depth(&self) -> u64 {
+        self.retention_depth
+    }
+
+    /// Compact the validated states of undecided views more than `depth` views below the high QC,
+    /// e.g. to bound memory through a long stretch without decides, or never compact them with
+    /// `None`. Takes effect on the next high QC update.
+    pub fn set_state_compaction_depth(&mut self, depth: Option<u64>) {
+        self.state_compaction_depth = depth;
+    }
+
+    /// Get how many views below the high QC the validated states of undecided views are kept in
+    /// full, None if they are never compacted.
+    #[must_use]
+    pub fn state_compaction_depth(&self) -> Option<u64> {
+        self.state_compaction_depth
+    }
+
+    /// Whether the validated state of `view_number` has been compacted.
+    #[must_use]
+    pub fn is_state_compacted(&self, view_number: TYPES::Time) -> bool {
+        self.compacted_states.contains(&view_number)
+    }
+
+    /// Compact the validated states of the undecided views more than the state compaction depth
+    /// below the high QC, returning how many states were compacted.
+    ///
+    /// A compacted view keeps its leaf commitment, but its state is replaced with the lightweight
+    /// state rebuilt from its leaf's block header by [`ValidatedState::from_header`], and its delta
+    /// is dropped. If the view later matters, e.g. as the parent of a proposal, the state is
+    /// reconstructed on demand from there the same way as when catching up, and a view whose
+    /// proposal is validated again gets its full state back in
+    /// [`Consensus::update_validated_state_map`].
+    ///
+    /// Only the views between the previous compaction and the new ceiling are visited, so a view
+    /// that is saved below the ceiling afterwards, e.g. a fetched parent, keeps its state.
+    pub fn compact_states(&mut self) -> usize {
+        let Some(depth) = self.state_compaction_depth else {
+            return 0;
+        };
+        let ceiling = TYPES::Time::new(self.high_qc.view_number.u64().saturating_sub(depth));
+        let start = if self.compaction_ceiling > self.last_decided_view {
+            Bound::Included(self.compaction_ceiling)
+        } else {
+            Bound::Excluded(self.last_decided_view)
+        };
+        if ceiling <= self.last_decided_view || ceiling <= self.compaction_ceiling {
+            return 0;
+        }
+        self.compaction_ceiling = ceiling;
+
+        let mut compacted = 0;
+        for (view_number, view) in self
+            .validated_state_map
+            .range_mut((start, Bound::Excluded(ceiling)))
+        {
+            if self.compacted_states.contains(view_number) {
+                continue;
+            }
+            let ViewInner::Leaf { leaf, state, delta } = &mut view.view_inner else {
+                continue;
+            };
+            let Some(leaf) = self.saved_leaves.get(leaf) else {
+                continue;
+            };
+            *state = Arc::new(TYPES::ValidatedState::from_header(leaf.block_header()));
+            *delta = None;
+            self.compacted_states.insert(*view_number);
+            compacted += 1;
+        }
+        compacted
+    }
+
+    /// Set how many undecided views the states and leaves are kept of, e.g. to bound memory while
+    /// partitioned from the rest of the network. Takes effect on the next
+    /// [`Consensus::prune_undecided`].
+    pub fn set_max_undecided_views(&mut self, views: usize) {
+        self.max_undecided_views = views;
+    }
+
+    /// Get how many undecided views the states and leaves are kept of.
+    #[must_use]
+    pub fn max_undecided_views(&self) -> usize {
+        self.max_undecided_views
+    }
+
+    /// Remove the states and leaves of the oldest undecided views after the locked view beyond the
+    /// newest `max_undecided_views` of them, returning the newest view removed, if any. The locked
+    /// view and the views before it are never removed, nor are the ancestors of the high QC's
+    /// leaf, so the chain which may still be decided stays intact.
+    pub fn prune_undecided(&mut self) -> Option<TYPES::Time> {
+        // There can't be more undecided views than states, so don't scan until there are enough.
+        if self.validated_state_map.len() <= self.max_undecided_views {
+            return None;
+        }
+
+        let floor = self.locked_view.max(self.last_decided_view);
+        let undecided: Vec<_> = self
+            .validated_state_map
+            .range((Bound::Excluded(floor), Bound::Unbounded))
+            .map(|(view_number, _)| *view_number)
+            .collect();
+        let excess = undecided.len().saturating_sub(self.max_undecided_views);
+        if excess == 0 {
+            return None;
+        }
+
+        let mut high_qc_chain = BTreeSet::new();
+        let mut next_leaf = self.high_qc.data.leaf_commit;
+        while let Some(leaf) = self.saved_leaves.get(&next_leaf) {
+            if leaf.view_number() <= floor {
+                break;
+            }
+            high_qc_chain.insert(leaf.view_number());
+            next_leaf = leaf.parent_commitment();
+        }
+        let pruned: Vec<_> = undecided
+            .into_iter()
+            .filter(|view_number| !high_qc_chain.contains(view_number))
+            .take(excess)
+            .collect();
+
+        for view_number in &pruned {
+            self.compacted_states.remove(view_number);
+            self.proposers.remove(view_number);
+            if let Some(leaf) = self
+                .validated_state_map
+                .remove(view_number)
+                .and_then(|view| view.leaf_commitment())
+            {
                 self.saved_leaves.remove(&leaf);
-            });
-        self.validated_state_map = self.validated_state_map.split_off(&new_anchor_view);
-        self.saved_payloads = self.saved_payloads.split_off(&new_anchor_view);
-        self.vid_shares = self.vid_shares.split_off(&new_anchor_view);
-        self.last_proposals = self.last_proposals.split_off(&new_anchor_view);
+            }
+        }
+        pruned.last().copied()
+    }
+
+    /// Garbage collect right away, e.g. under memory pressure, removing the views older than the
+    /// retention depth allows from every store. Views from the locked view onwards are never
+    /// removed. DA certificates are left to the garbage collection on the next decide, which keeps
+    /// them from the previous anchor.
+    pub fn garbage_collect_now(&mut self) -> GarbageCollected {
+        self.prune_views_before(self.oldest_retained_view(), TYPES::Time::genesis())
+    }
+
+    /// The oldest view garbage collection keeps as of the last decided view.
+    #[must_use]
+    pub fn oldest_retained_view(&self) -> TYPES::Time {
+        self.retention_floor(self.last_decided_view)
+    }
+
+    /// The oldest view kept when garbage collecting with `anchor_view` as the last decided view:
+    /// `retention_depth` views before it, but never after the anchor or the locked view.
+    fn retention_floor(&self, anchor_view: TYPES::Time) -> TYPES::Time {
+        TYPES::Time::new(anchor_view.u64().saturating_sub(self.retention_depth))
+            .min(anchor_view)
+            .min(self.locked_view)
+    }
+
+    /// Remove every view before `floor` from all of the stores, except the DA certificates, which
+    /// are removed before `da_cert_floor`.
+    fn prune_views_before(
+        &mut self,
+        floor: TYPES::Time,
+        da_cert_floor: TYPES::Time,
+    ) -> GarbageCollected {
+        /// Split off the entries of `map` before `floor`, returning them.
+        fn split_before<K: Ord, V>(map: &mut BTreeMap<K, V>, floor: &K) -> BTreeMap<K, V> {
+            let retained = map.split_off(floor);
+            std::mem::replace(map, retained)
+        }
+
+        let da_certs = self.saved_da_certs.len();
+        self.saved_da_certs
+            .retain(|view_number, _| *view_number >= da_cert_floor);
+        let mut entries = da_certs - self.saved_da_certs.len();
+
+        let states = split_before(&mut self.validated_state_map, &floor);
+        self.compacted_states = self.compacted_states.split_off(&floor);
+        entries += states.len();
+        entries += states
+            .values()
+            .filter_map(|view| view.leaf_commitment())
+            .filter(|leaf| self.saved_leaves.remove(leaf).is_some())
+            .count();
+
+        let payloads = split_before(&mut self.saved_payloads, &floor);
+        let payload_bytes = payloads.values().map(|payload| payload.len()).sum();
+        entries += payloads.len();
+        entries += split_before(&mut self.submitted_payloads, &floor).len();
+        entries += split_before(&mut self.vid_shares, &floor).len();
+        entries += split_before(&mut self.da_payload_commitments, &floor).len();
+        entries += split_before(&mut self.last_proposals, &floor).len();
+        entries += split_before(&mut self.last_da_proposals, &floor).len();
+        entries += split_before(&mut self.proposers, &floor).len();
+
+        GarbageCollected {
+            entries,
+            payload_bytes,
+        }
     }
 
     /// Gets the last decided leaf.
@@ -498,6 +993,30 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         self.saved_leaves.get(&leaf).unwrap().clone()
     }
 
+    /// Gets the leaf decided in `view`, if it's still retained.
+    ///
+    /// Returns `None` if no leaf was decided in `view`, even if we know of a leaf for that view
+    /// which isn't on the decided chain, or if the decided leaf has been garbage collected.
+    #[must_use]
+    pub fn decided_leaf_at(&self, view: TYPES::Time) -> Option<Leaf<TYPES>> {
+        if view > self.last_decided_view {
+            return None;
+        }
+        let mut next_leaf = self
+            .validated_state_map
+            .get(&self.last_decided_view)?
+            .leaf_commitment()?;
+        // Walk back along the decided chain, which skips the views that failed.
+        while let Some(leaf) = self.saved_leaves.get(&next_leaf) {
+            match leaf.view_number().cmp(&view) {
+                Ordering::Greater => next_leaf = leaf.parent_commitment(),
+                Ordering::Equal => return Some(leaf.clone()),
+                Ordering::Less => return None,
+            }
+        }
+        None
+    }
+
     /// Gets the validated state with the given view number, if in the state map.
     #[must_use]
     pub fn state(&self, view_number: TYPES::Time) -> Option<&Arc<TYPES::ValidatedState>> {
@@ -516,6 +1035,23 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         }
     }
 
+    /// Gets the validated states for the views in `[start, end]`, skipping views whose state
+    /// hasn't been resolved.
+    #[must_use]
+    pub fn states_in_range(
+        &self,
+        start: TYPES::Time,
+        end: TYPES::Time,
+    ) -> Vec<(TYPES::Time, Arc<TYPES::ValidatedState>)> {
+        if start > end {
+            return vec![];
+        }
+        self.validated_state_map
+            .range(start..=end)
+            .filter_map(|(view_number, view)| Some((*view_number, Arc::clone(view.state()?))))
+            .collect()
+    }
+
     /// Gets the last decided validated state.
     ///
     /// # Panics
@@ -538,7 +1074,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         consensus: LockedConsensusState<TYPES>,
         view: <TYPES as NodeType>::Time,
         membership: Arc<TYPES::Membership>,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &dyn Signer<TYPES::SignatureKey>,
     ) -> Option<()> {
         let consensus = consensus.upgradable_read().await;
         let txns = consensus.saved_payloads().get(&view)?;
@@ -547,7 +1083,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         let shares = VidDisperseShare::from_vid_disperse(vid);
         let mut consensus = RwLockUpgradableReadGuard::upgrade(consensus).await;
         for share in shares {
-            if let Some(prop) = share.to_proposal(private_key) {
+            if let Some(prop) = share.to_proposal_with_signer(signer).await {
                 consensus.update_vid_shares(view, prop);
             }
         }
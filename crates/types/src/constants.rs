@@ -8,6 +8,40 @@ pub const LOOK_AHEAD: u64 = 5;
 /// the default kademlia record republication interval (in seconds)
 pub const KAD_DEFAULT_REPUB_INTERVAL_SEC: u64 = 28800;
 
+/// the default maximum size (in bytes) of a message accepted from the network, large enough to
+/// carry a DA proposal or VID disperse for a full block
+pub const DEFAULT_MAX_INBOUND_MESSAGE_SIZE: usize = 512 * 1024 * 1024;
+
+/// the default maximum number of requests per second a node sends for data from views it is
+/// catching up on
+pub const DEFAULT_CATCHUP_REQUESTS_PER_SECOND: u64 = 20;
+
+/// the default number of views ahead for which a leader prefetches blocks from the builder
+pub const DEFAULT_BUILDER_PREFETCH_DEPTH: usize = 0;
+
+/// the default number of views before its start view for which a restarted node backfills the
+/// proposals it serves to peers catching up
+pub const DEFAULT_PROPOSAL_BACKFILL_VIEWS: u64 = 0;
+
+/// the default number of leading zero bits required of the proof of work on a submitted
+/// transaction, which disables the proof of work
+pub const DEFAULT_TRANSACTION_POW_DIFFICULTY: u32 = 0;
+
+/// the largest proof of work difficulty a node accepts, beyond which submitting a transaction
+/// would take hours of hashing
+pub const MAX_TRANSACTION_POW_DIFFICULTY: u32 = 40;
+
+/// the default maximum number of undecided views a node keeps the states and leaves of, large
+/// enough to never be reached by a live network
+pub const DEFAULT_MAX_UNDECIDED_VIEWS: usize = 100_000;
+
+/// the default maximum size (in bytes) of an encoded block payload a DA leader proposes, leaving
+/// room for a DA proposal carrying it within the default maximum inbound message size
+pub const DEFAULT_MAX_BLOCK_SIZE: u64 = 256 * 1024 * 1024;
+
+/// the default maximum number of transactions queued while a node isn't ready to broadcast them
+pub const DEFAULT_MAX_PENDING_TRANSACTIONS: usize = 10_000;
+
 /// the number of messages to cache in the combined network
 pub const COMBINED_NETWORK_CACHE_SIZE: usize = 1000;
 
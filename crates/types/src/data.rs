@@ -21,6 +21,7 @@ use derivative::Derivative;
 use jf_vid::{precomputable::Precomputable, VidDisperse as JfVidDisperse, VidScheme};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use snafu::Snafu;
 #[cfg(async_executor_impl = "tokio")]
 use tokio::task::spawn_blocking;
@@ -29,7 +30,8 @@ use tracing::error;
 use crate::{
     message::Proposal,
     simple_certificate::{
-        QuorumCertificate, TimeoutCertificate, UpgradeCertificate, ViewSyncFinalizeCertificate2,
+        DaCertificate, QuorumCertificate, TimeoutCertificate, UpgradeCertificate,
+        ViewSyncFinalizeCertificate2,
     },
     simple_vote::{QuorumData, UpgradeProposalData},
     traits::{
@@ -38,11 +40,11 @@ use crate::{
         },
         election::Membership,
         node_implementation::{ConsensusTime, NodeType},
-        signature_key::SignatureKey,
+        signature_key::{SignatureKey, Signer},
         states::TestableState,
         BlockPayload,
     },
-    utils::bincode_opts,
+    utils::{bincode_opts, BuilderCommitment},
     vid::{vid_scheme, VidCommitment, VidCommon, VidPrecomputeData, VidSchemeType, VidShare},
     vote::{Certificate, HasViewNumber},
 };
@@ -281,6 +283,22 @@ impl<TYPES: NodeType> VidDisperseShare<TYPES> {
         })
     }
 
+    /// Consume `self` and return a `Proposal` signed by `signer`
+    pub async fn to_proposal_with_signer(
+        self,
+        signer: &dyn Signer<TYPES::SignatureKey>,
+    ) -> Option<Proposal<TYPES, Self>> {
+        let Ok(signature) = signer.sign(self.payload_commitment.as_ref()).await else {
+            error!("VID: failed to sign dispersal share payload");
+            return None;
+        };
+        Some(Proposal {
+            signature,
+            _pd: PhantomData,
+            data: self,
+        })
+    }
+
     /// Create `VidDisperse` out of an iterator to `VidDisperseShare`s
     pub fn to_vid_disperse<'a, I>(mut it: I) -> Option<VidDisperse<TYPES>>
     where
@@ -353,6 +371,81 @@ pub struct QuorumProposal<TYPES: NodeType> {
     pub proposal_certificate: Option<ViewChangeEvidence<TYPES>>,
 }
 
+/// The DA certificate for the view of a quorum proposal's parent, which the leader may attach to
+/// its proposal so that replicas can confirm the parent's data is available without waiting for
+/// the certificate separately.
+///
+/// Only attached once the network has upgraded to the [`Upgrade`](crate::constants::Upgrade)
+/// version. The leader signs the proposed leaf and the certificate together, so the certificate
+/// can't be attached to, or stripped from, the proposal by anyone else.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct ParentDaCertificate<TYPES: NodeType> {
+    /// The DA certificate for the parent's view
+    pub certificate: DaCertificate<TYPES>,
+    /// The leader's signature over the Sha256 hash of the proposed leaf's commitment and the
+    /// certificate together
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> ParentDaCertificate<TYPES> {
+    /// Attach `certificate` to `proposal`, signing the two together.
+    ///
+    /// # Errors
+    /// If the certificate can't be serialized or `signer` fails to sign it
+    pub async fn new(
+        proposal: &QuorumProposal<TYPES>,
+        certificate: DaCertificate<TYPES>,
+        signer: &dyn Signer<TYPES::SignatureKey>,
+    ) -> Result<Self> {
+        let signature = signer.sign(&Self::digest(proposal, &certificate)?).await?;
+        Ok(Self {
+            certificate,
+            signature,
+        })
+    }
+
+    /// Check that `leader` attached this certificate to `proposal`, and that it is a valid DA
+    /// certificate for the view of the proposal's parent.
+    ///
+    /// # Errors
+    /// If the signature isn't `leader`'s over `proposal` and the certificate, or the certificate
+    /// isn't for the parent's view or doesn't validate against `da_membership`
+    pub fn verify(
+        &self,
+        proposal: &QuorumProposal<TYPES>,
+        leader: &TYPES::SignatureKey,
+        da_membership: &TYPES::Membership,
+    ) -> Result<()> {
+        ensure!(
+            leader.validate(&self.signature, &Self::digest(proposal, &self.certificate)?),
+            "Parent DA certificate with an invalid signature from {leader:?}"
+        );
+        let parent_view = proposal.justify_qc.view_number;
+        ensure!(
+            self.certificate.view_number == parent_view,
+            "DA certificate for view {:?} is not for the parent's view {parent_view:?}",
+            self.certificate.view_number
+        );
+        ensure!(
+            self.certificate.is_valid_cert(da_membership),
+            "DA certificate for view {parent_view:?} was invalid"
+        );
+        Ok(())
+    }
+
+    /// The Sha256 hash of the proposed leaf's commitment and `certificate` together, which the
+    /// leader signs.
+    fn digest(
+        proposal: &QuorumProposal<TYPES>,
+        certificate: &DaCertificate<TYPES>,
+    ) -> Result<Vec<u8>> {
+        let leaf_commitment = Leaf::from_quorum_proposal(proposal).commit();
+        let data = bincode::serialize(&(leaf_commitment, certificate))?;
+        Ok(Sha256::digest(data).to_vec())
+    }
+}
+
 impl<TYPES: NodeType> HasViewNumber<TYPES> for DaProposal<TYPES> {
     fn view_number(&self) -> TYPES::Time {
         self.view_number
@@ -605,6 +698,34 @@ impl<TYPES: NodeType> Leaf<TYPES> {
         self.block_header().payload_commitment()
     }
 
+    /// The builder's commitment to the block payload contained in this leaf.
+    pub fn builder_commitment(&self) -> BuilderCommitment {
+        self.block_header().builder_commitment()
+    }
+
+    /// Check that the header's VID payload commitment and builder commitment both commit to the
+    /// block payload in this leaf, so the builder committed to the payload that was dispersed.
+    ///
+    /// There's nothing to check if the leaf hasn't been filled with its payload.
+    ///
+    /// # Errors
+    /// Returns an error if either commitment doesn't match the payload.
+    pub fn verify_commitments(&self, num_storage_nodes: usize) -> Result<()> {
+        let Some(ref payload) = self.block_payload else {
+            return Ok(());
+        };
+        ensure!(
+            vid_commitment(&payload.encode(), num_storage_nodes) == self.payload_commitment(),
+            "The payload commitment in the header does not match the block payload."
+        );
+        ensure!(
+            payload.builder_commitment(self.block_header.metadata()) == self.builder_commitment(),
+            "The builder commitment in the header does not match the block payload."
+        );
+
+        Ok(())
+    }
+
     /// Validate that a leaf has the right upgrade certificate to be the immediate child of another leaf
     ///
     /// This may not be a complete function. Please double-check that it performs the checks you expect before subtituting validation logic with it.
@@ -744,32 +865,74 @@ impl<TYPES: NodeType> Leaf<TYPES> {
 pub mod null_block {
     #![allow(missing_docs)]
 
-    use jf_vid::VidScheme;
-    use memoize::memoize;
+    use std::{
+        num::NonZeroUsize,
+        sync::{Arc, Mutex},
+    };
+
+    use jf_vid::precomputable::Precomputable;
+    use lazy_static::lazy_static;
+    use lru::LruCache;
 
     use crate::{
         traits::{
             block_contents::BuilderFee, node_implementation::NodeType,
             signature_key::BuilderSignatureKey, BlockPayload,
         },
-        vid::{vid_scheme, VidCommitment},
+        vid::{vid_scheme, VidCommitment, VidPrecomputeData},
     };
 
-    /// The commitment for a null block payload.
+    /// The VID commitment for a null block payload, along with the data precomputed while
+    /// computing it.
+    #[derive(Clone, Debug)]
+    pub struct NullBlockVid {
+        /// The payload commitment
+        pub commitment: VidCommitment,
+        /// The precompute data for dispersing the payload
+        pub precompute_data: VidPrecomputeData,
+    }
+
+    /// How many numbers of storage nodes the null block VID data is cached for
+    const VID_CACHE_SIZE: usize = 10;
+
+    lazy_static! {
+        /// The null block VID data for the most recently used numbers of storage nodes
+        static ref VID_CACHE: Mutex<LruCache<usize, Arc<NullBlockVid>>> =
+            Mutex::new(LruCache::new(NonZeroUsize::new(VID_CACHE_SIZE).unwrap()));
+    }
+
+    /// The VID commitment and precompute data for a null block payload.
     ///
     /// Note: the commitment depends on the network (via `num_storage_nodes`),
     /// and may change (albeit rarely) during execution.
     ///
-    /// We memoize the result to avoid having to recalculate it.
-    #[memoize(SharedCache, Capacity: 10)]
+    /// The result is cached for the 10 most recently used numbers of storage nodes, and shared by
+    /// every call while it's cached.
     #[must_use]
-    pub fn commitment(num_storage_nodes: usize) -> Option<VidCommitment> {
-        let vid_result = vid_scheme(num_storage_nodes).commit_only(&Vec::new());
-
-        match vid_result {
-            Ok(r) => Some(r),
-            Err(_) => None,
+    pub fn vid(num_storage_nodes: usize) -> Option<Arc<NullBlockVid>> {
+        let mut cache = VID_CACHE.lock().ok()?;
+        if let Some(vid) = cache.get(&num_storage_nodes) {
+            return Some(Arc::clone(vid));
         }
+        let (commitment, precompute_data) = vid_scheme(num_storage_nodes)
+            .commit_only_precompute(Vec::new())
+            .ok()?;
+        let vid = Arc::new(NullBlockVid {
+            commitment,
+            precompute_data,
+        });
+        cache.put(num_storage_nodes, Arc::clone(&vid));
+
+        Some(vid)
+    }
+
+    /// The commitment for a null block payload.
+    ///
+    /// Note: the commitment depends on the network (via `num_storage_nodes`),
+    /// and may change (albeit rarely) during execution.
+    #[must_use]
+    pub fn commitment(num_storage_nodes: usize) -> Option<VidCommitment> {
+        vid(num_storage_nodes).map(|vid| vid.commitment)
     }
 
     /// Builder fee data for a null block payload
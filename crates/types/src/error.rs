@@ -29,11 +29,21 @@ pub enum HotShotError<TYPES: NodeType> {
         source: crate::traits::network::NetworkError,
     },
     /// Failed to broadcast a message on the network
-    #[snafu(display("Failed to broadcast a message"))]
+    #[snafu(display("Failed to broadcast a message: {source}"))]
     FailedToBroadcast {
         /// The underlying network fault
         source: crate::traits::network::NetworkError,
     },
+    /// Failed to broadcast a transaction to the DA committee
+    #[snafu(display(
+        "Failed to broadcast a transaction submitted in view {view_number:?}: {source}"
+    ))]
+    NetworkBroadcast {
+        /// The view the transaction was submitted in
+        view_number: TYPES::Time,
+        /// The underlying network fault
+        source: crate::traits::network::NetworkError,
+    },
     /// Failure in the block.
     #[snafu(display("Failed to build or verify a block: {source}"))]
     BlockError {
@@ -47,7 +57,11 @@ pub enum HotShotError<TYPES: NodeType> {
         source: crate::traits::network::NetworkError,
     },
     /// Item was not present in storage
-    LeafNotFound {/* TODO we should create a way to to_string */},
+    #[snafu(display("Leaf not found: {context}"))]
+    LeafNotFound {
+        /// Which leaf was missing, and where from
+        context: String,
+    },
     /// Error accessing storage
     /// Invalid state machine state
     #[snafu(display("Invalid state machine state: {}", context))]
@@ -56,11 +70,15 @@ pub enum HotShotError<TYPES: NodeType> {
         context: String,
     },
     /// HotShot timed out waiting for msgs
+    #[snafu(display("Timed out {context}: {source}"))]
     TimeoutError {
+        /// What we were waiting for
+        context: String,
         /// source of error
         source: TimeoutError,
     },
     /// HotShot timed out during round
+    #[snafu(display("View {view_number:?} timed out in state {state:?}"))]
     ViewTimeoutError {
         /// view number
         view_number: TYPES::Time,
@@ -78,13 +96,33 @@ pub enum HotShotError<TYPES: NodeType> {
     /// Miscellaneous error
     /// TODO fix this with
     /// #181 <https://github.com/EspressoSystems/HotShot/issues/181>
+    #[snafu(display("{context}"))]
     Misc {
         /// source of error
         context: String,
     },
     /// Failed to serialize message
-    FailedToSerialize,
+    #[snafu(display("Failed to serialize message for view {view_number:?}: {source}"))]
+    FailedToSerialize {
+        /// The view of the message
+        view_number: TYPES::Time,
+        /// The underlying serialization error
+        source: anyhow::Error,
+    },
+    /// Too many transactions are already queued for the node to broadcast once it's ready
+    #[snafu(display("The node isn't ready, and already has {max} transactions queued"))]
+    PendingTransactionsFull {
+        /// The maximum number of transactions queued
+        max: usize,
+    },
+    /// Failed to start consensus
+    #[snafu(display("Failed to start consensus: {}", context))]
+    StartupFailed {
+        /// Context
+        context: String,
+    },
     /// Internal value used to drive the state machine
+    #[snafu(display("Continue to the next step of the state machine"))]
     Continue,
 }
 /// Contains information about what the state of the hotshot-consensus was when a round timed out
@@ -1,15 +1,16 @@
 //! Events that a `HotShot` instance can emit
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
+use committable::Commitment;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     data::{DaProposal, Leaf, QuorumProposal, UpgradeProposal, VidDisperseShare},
     error::HotShotError,
-    message::Proposal,
+    message::{Proposal, TxPriority},
     simple_certificate::QuorumCertificate,
-    traits::{node_implementation::NodeType, ValidatedState},
+    traits::{network::NetworkChange, node_implementation::NodeType, ValidatedState},
 };
 /// A status event emitted by a `HotShot` instance
 ///
@@ -36,6 +37,10 @@ pub struct LeafInfo<TYPES: NodeType> {
     pub delta: Option<Arc<<<TYPES as NodeType>::ValidatedState as ValidatedState<TYPES>>::Delta>>,
     /// Optional VID share data.
     pub vid_share: Option<VidDisperseShare<TYPES>>,
+    /// The signer of the leaf's proposal, for attributing the block to its proposer. None if we
+    /// never verified the signature of the proposal, e.g. if we only learned of the leaf through a
+    /// later proposal extending it.
+    pub proposer: Option<TYPES::SignatureKey>,
 }
 
 impl<TYPES: NodeType> LeafInfo<TYPES> {
@@ -45,12 +50,14 @@ impl<TYPES: NodeType> LeafInfo<TYPES> {
         state: Arc<<TYPES as NodeType>::ValidatedState>,
         delta: Option<Arc<<<TYPES as NodeType>::ValidatedState as ValidatedState<TYPES>>::Delta>>,
         vid_share: Option<VidDisperseShare<TYPES>>,
+        proposer: Option<TYPES::SignatureKey>,
     ) -> Self {
         Self {
             leaf,
             state,
             delta,
             vid_share,
+            proposer,
         }
     }
 }
@@ -117,6 +124,9 @@ pub enum EventType<TYPES: NodeType> {
         qc: Arc<QuorumCertificate<TYPES>>,
         /// Optional information of the number of transactions in the block, for logging purposes.
         block_size: Option<u64>,
+        /// The number of views newly decided by this decision, i.e. the length of `leaf_chain`,
+        /// so progress can be tracked without walking the chain.
+        decided_view_count: usize,
     },
     /// A replica task was canceled by a timeout interrupt
     ReplicaViewTimeout {
@@ -136,8 +146,8 @@ pub enum EventType<TYPES: NodeType> {
     /// New transactions were received from the network
     /// or submitted to the network by us
     Transactions {
-        /// The list of transactions
-        transactions: Vec<TYPES::Transaction>,
+        /// The list of transactions, each with the priority it was submitted with
+        transactions: Vec<(TYPES::Transaction, TxPriority)>,
     },
     /// DA proposal was received from the network
     /// or submitted to the network by us
@@ -146,6 +156,9 @@ pub enum EventType<TYPES: NodeType> {
         proposal: Proposal<TYPES, DaProposal<TYPES>>,
         /// Public key of the leader submitting the proposal
         sender: TYPES::SignatureKey,
+        /// The priorities the proposal's transactions were submitted to us with, for those we
+        /// received ourselves, so builders can order the transactions they still have to include
+        priorities: Vec<(Commitment<TYPES::Transaction>, TxPriority)>,
     },
     /// Quorum proposal was received from the network
     /// or submitted to the network by us
@@ -163,7 +176,74 @@ pub enum EventType<TYPES: NodeType> {
         /// Public key of the leader submitting the proposal
         sender: TYPES::SignatureKey,
     },
+    /// We connected to or disconnected from another node
+    ///
+    /// Only emitted by networks which can report their topology.
+    ConnectionChange {
+        /// The node and whether it connected or disconnected
+        change: NetworkChange<TYPES::SignatureKey>,
+    },
+    /// We voted on a proposal whose parent we couldn't find, having only checked its liveness
+    ///
+    /// Emitted for every such vote; frequent occurrences mean this node is lagging behind.
+    DegradedValidation {
+        /// The view of the proposal
+        view: TYPES::Time,
+    },
+    /// Leadership passed from the leader of the view we left to the leader of the view we entered
+    ///
+    /// Emitted on every view change, giving an audit trail of the leadership flow.
+    LeaderHandoff {
+        /// The view we left
+        view: TYPES::Time,
+        /// The leader of `view`
+        outgoing: TYPES::SignatureKey,
+        /// The leader of the view we entered
+        incoming: TYPES::SignatureKey,
+        /// Whether the outgoing leader proposed in `view`, as far as we've seen
+        outgoing_proposed: bool,
+    },
+    /// We formed a quorum certificate from votes carrying application data
+    QuorumVoteExtensions {
+        /// The view the certificate was formed for
+        view_number: TYPES::Time,
+        /// The application data attached to each signer's vote
+        extensions: BTreeMap<TYPES::SignatureKey, Vec<u8>>,
+    },
+}
+
+/// A predicate over [`EventType`] deciding which events are emitted to the application.
+///
+/// Events the filter rejects are dropped where they're emitted, so applications which never
+/// consume some kinds of events don't pay for sending them. The default filter passes everything.
+#[derive(Clone)]
+pub struct EventFilter<TYPES: NodeType>(Arc<dyn Fn(&EventType<TYPES>) -> bool + Send + Sync>);
+
+impl<TYPES: NodeType> EventFilter<TYPES> {
+    /// Create a filter passing the events for which `predicate` returns `true`.
+    pub fn new(predicate: impl Fn(&EventType<TYPES>) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(predicate))
+    }
+
+    /// Whether `event` passes the filter.
+    #[must_use]
+    pub fn allows(&self, event: &EventType<TYPES>) -> bool {
+        (self.0)(event)
+    }
 }
+
+impl<TYPES: NodeType> Default for EventFilter<TYPES> {
+    fn default() -> Self {
+        Self::new(|_| true)
+    }
+}
+
+impl<TYPES: NodeType> std::fmt::Debug for EventFilter<TYPES> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventFilter").finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 /// A list of actions that we track for nodes
 pub enum HotShotAction {
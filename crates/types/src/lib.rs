@@ -18,12 +18,14 @@ pub mod error;
 pub mod event;
 pub mod light_client;
 pub mod message;
+pub mod probe;
 pub mod qc;
 pub mod signature_key;
 pub mod simple_certificate;
 pub mod simple_vote;
 pub mod stake_table;
 pub mod traits;
+pub mod transaction_pow;
 pub mod utils;
 pub mod vid;
 pub mod vote;
@@ -58,6 +60,43 @@ pub enum ExecutionType {
     Incremental,
 }
 
+/// How thoroughly a replica checks incoming quorum proposals before voting on them.
+///
+/// Anything other than [`ValidationLevel::Full`] departs from the protocol's voting rule; the
+/// lenient setting exists only for benchmarking and must never be used in production.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum ValidationLevel {
+    /// Require both the liveness and the safety check to pass
+    Strict,
+    /// Require either the liveness or the safety check to pass, as the protocol specifies
+    #[default]
+    Full,
+    /// Only run the liveness check, skipping the traversal back to the locked leaf. Unsafe.
+    LivenessOnly,
+}
+
+impl ValidationLevel {
+    /// Whether this level runs the safety check at all.
+    #[must_use]
+    pub fn checks_safety(self) -> bool {
+        !matches!(self, Self::LivenessOnly)
+    }
+
+    /// Whether a proposal is accepted given the outcome of the liveness check and, if it was run,
+    /// the safety check.
+    #[must_use]
+    pub fn accepts(self, liveness: bool, safety: Option<bool>) -> bool {
+        let safety = safety.unwrap_or(false);
+        match self {
+            Self::Strict => liveness && safety,
+            Self::Full => liveness || safety,
+            Self::LivenessOnly => liveness,
+        }
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Derivative, Display)]
 #[serde(bound(deserialize = ""))]
 #[derivative(Debug(bound = ""))]
@@ -174,6 +213,9 @@ pub struct HotShotConfig<KEY: SignatureKey> {
     pub known_nodes_with_stake: Vec<PeerConfig<KEY>>,
     /// All public keys known to be DA nodes
     pub known_da_nodes: Vec<PeerConfig<KEY>>,
+    /// All public keys known to be VID storage nodes, which are dispersed VID shares and serve
+    /// them. If empty, the staked nodes are the VID storage nodes.
+    pub known_vid_nodes: Vec<PeerConfig<KEY>>,
     /// List of known non-staking nodes' public keys
     pub known_nodes_without_stake: Vec<KEY>,
     /// My own validator config, including my public key, private key, stake value, serving as private parameter
@@ -182,6 +224,9 @@ pub struct HotShotConfig<KEY: SignatureKey> {
     pub da_staked_committee_size: usize,
     /// List of DA committee nodes (non-staking)nodes for static DA committee
     pub da_non_staked_committee_size: usize,
+    /// Number of staked nodes the orchestrator makes VID storage nodes, in the order they
+    /// register. 0 leaves every staked node a VID storage node.
+    pub vid_storage_committee_size: usize,
     /// Number of fixed leaders for GPU VID, normally it will be 0, it's only used when running GPU VID
     pub fixed_leader_for_gpuvid: usize,
     /// Base duration for next-view timeout, in milliseconds
@@ -210,4 +255,50 @@ pub struct HotShotConfig<KEY: SignatureKey> {
     pub start_voting_view: u64,
     /// View to stop voting on an upgrade. To prevent voting on an upgrade, set stop_voting_view <= start_voting_view.
     pub stop_voting_view: u64,
+    /// Maximum size in bytes of a message accepted from the network; larger messages are dropped
+    pub max_inbound_message_size: usize,
+    /// Maximum number of requests per second for data from views we're catching up on. Requests
+    /// for live views aren't limited. 0 disables the limit.
+    pub catchup_requests_per_second: u64,
+    /// Number of views beyond the next one for which a leader fetches its blocks from the builder
+    /// ahead of time, so they're ready when it proposes. 0 disables prefetching.
+    pub builder_prefetch_depth: usize,
+    /// How thoroughly incoming quorum proposals are validated before voting
+    pub validation_level: ValidationLevel,
+    /// Number of views before the start view for which a node requests the proposals from their
+    /// leaders on startup, so it can serve them to peers catching up. 0 disables backfilling.
+    pub proposal_backfill_views: u64,
+    /// Number of leading zero bits the proof of work on a submitted transaction must have for the
+    /// transaction to be accepted from the network, once the network has upgraded to the
+    /// [`constants::Upgrade`] version. 0 disables the proof of work.
+    pub transaction_pow_difficulty: u32,
+    /// Whether a leader waits for the DA certificate of its view, on top of its payload and VID
+    /// shares, before proposing. Only honoured with `dependency-tasks`; the legacy consensus task
+    /// proposes without waiting for it.
+    pub wait_for_dac_to_propose: bool,
+    /// Maximum number of undecided views whose states and leaves are kept; the oldest beyond it
+    /// are pruned, e.g. to bound memory while partitioned from the rest of the network
+    pub max_undecided_views: usize,
+    /// Number of views below the high QC beyond which the validated states of undecided views
+    /// are compacted to the states rebuilt from their headers, None to never compact them
+    pub state_compaction_depth: Option<u64>,
+    /// Maximum size, in bytes, of an encoded block payload the DA leader proposes; larger
+    /// payloads from the builder are rejected
+    pub max_block_size: u64,
+    /// Maximum number of transactions queued while the node isn't ready to broadcast them; more
+    /// are rejected until it is
+    pub max_pending_transactions: usize,
+}
+
+impl<KEY: SignatureKey> HotShotConfig<KEY> {
+    /// The VID storage nodes: the known VID nodes if any are configured, otherwise the staked
+    /// nodes.
+    #[must_use]
+    pub fn vid_nodes(&self) -> Vec<PeerConfig<KEY>> {
+        if self.known_vid_nodes.is_empty() {
+            self.known_nodes_with_stake.clone()
+        } else {
+            self.known_vid_nodes.clone()
+        }
+    }
 }
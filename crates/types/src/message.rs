@@ -17,18 +17,21 @@ use vbs::{
 
 use crate::{
     constants::{Base, Upgrade},
-    data::{DaProposal, Leaf, QuorumProposal, UpgradeProposal, VidDisperseShare},
+    data::{
+        DaProposal, Leaf, ParentDaCertificate, QuorumProposal, UpgradeProposal, VidDisperseShare,
+    },
+    probe::ProbeMessage,
     simple_certificate::{
         DaCertificate, UpgradeCertificate, ViewSyncCommitCertificate2,
         ViewSyncFinalizeCertificate2, ViewSyncPreCommitCertificate2,
     },
     simple_vote::{
         DaVote, QuorumVote, TimeoutVote, UpgradeVote, ViewSyncCommitVote, ViewSyncFinalizeVote,
-        ViewSyncPreCommitVote,
+        ViewSyncPreCommitVote, VoteExtension,
     },
     traits::{
         election::Membership,
-        network::{DataRequest, ResponseMessage, ViewMessage},
+        network::{DataRequest, ResponseMessage, SignedResponse, ViewMessage},
         node_implementation::{ConsensusTime, NodeType},
         signature_key::SignatureKey,
     },
@@ -46,6 +49,27 @@ pub struct Message<TYPES: NodeType> {
     pub kind: MessageKind<TYPES>,
 }
 
+/// The protocol version in effect for `view`, given an optional decided upgrade certificate.
+///
+/// # Errors
+///
+/// Errors if the network has upgraded by `view` to a version we do not support.
+pub fn version_for_view<TYPES: NodeType>(
+    view: TYPES::Time,
+    upgrade_certificate: &Option<UpgradeCertificate<TYPES>>,
+) -> Result<Version> {
+    match upgrade_certificate {
+        Some(ref cert) if view >= cert.data.new_version_first_view => {
+            ensure!(
+                cert.data.new_version == Upgrade::VERSION,
+                "The network has upgraded to a new version that we do not support!"
+            );
+            Ok(Upgrade::VERSION)
+        }
+        _ => Ok(Base::VERSION),
+    }
+}
+
 /// Trait for messages that have a versioned serialization.
 pub trait VersionedMessage<'a, TYPES>
 where
@@ -63,22 +87,7 @@ where
     ) -> Result<Vec<u8>> {
         let view = self.view_number();
 
-        let version = match upgrade_certificate {
-            Some(ref cert) => {
-                if view >= cert.data.new_version_first_view
-                    && cert.data.new_version == Upgrade::VERSION
-                {
-                    Upgrade::VERSION
-                } else if view >= cert.data.new_version_first_view
-                    && cert.data.new_version != Upgrade::VERSION
-                {
-                    bail!("The network has upgraded to a new version that we do not support!");
-                } else {
-                    Base::VERSION
-                }
-            }
-            None => Base::VERSION,
-        };
+        let version = version_for_view(view, upgrade_certificate)?;
 
         let serialized_message = match version {
             Base::VERSION => Serializer::<Base>::serialize(&self),
@@ -115,22 +124,7 @@ where
 
         let view = deserialized_message.view_number();
 
-        let expected_version = match upgrade_certificate {
-            Some(ref cert) => {
-                if view >= cert.data.new_version_first_view
-                    && cert.data.new_version == Upgrade::VERSION
-                {
-                    Upgrade::VERSION
-                } else if view >= cert.data.new_version_first_view
-                    && cert.data.new_version != Upgrade::VERSION
-                {
-                    bail!("The network has upgraded to a new version that we do not support!");
-                } else {
-                    Base::VERSION
-                }
-            }
-            None => Base::VERSION,
-        };
+        let expected_version = version_for_view(view, upgrade_certificate)?;
 
         ensure!(
             version == expected_version,
@@ -139,10 +133,60 @@ where
 
         Ok(deserialized_message)
     }
+
+    /// Deserialize a message as [`deserialize`](Self::deserialize) does, except that a message
+    /// with a version newer than any we support is decoded under [`Base`] rather than rejected,
+    /// and marked as having a stale version. It may not have been decoded faithfully, so it
+    /// should be logged and dropped rather than acted on.
+    ///
+    /// This is slower than [`deserialize`](Self::deserialize), which should be preferred on the
+    /// hot path.
+    ///
+    /// # Errors
+    ///
+    /// Errors if deserialization fails, or if the message has an older version we don't support
+    /// or a version invalid for its view.
+    fn deserialize_lenient(
+        message: &'a [u8],
+        upgrade_certificate: &Option<UpgradeCertificate<TYPES>>,
+    ) -> Result<LenientMessage<Self>> {
+        let (version, payload) =
+            Version::deserialize(message).context("Failed to read message version!")?;
+
+        let newest_supported = [Base::VERSION, Upgrade::VERSION]
+            .into_iter()
+            .map(|version| (version.major, version.minor))
+            .max()
+            .unwrap_or_default();
+        if (version.major, version.minor) <= newest_supported {
+            return Self::deserialize(message, upgrade_certificate).map(|message| LenientMessage {
+                message,
+                stale_version: false,
+            });
+        }
+
+        let message = Serializer::<Base>::deserialize_no_version(payload).with_context(|| {
+            format!("Failed to deserialize message of newer version {version} as the base version!")
+        })?;
+        Ok(LenientMessage {
+            message,
+            stale_version: true,
+        })
+    }
 }
 
 impl<'a, TYPES> VersionedMessage<'a, TYPES> for Message<TYPES> where TYPES: NodeType {}
 
+/// A message deserialized by [`VersionedMessage::deserialize_lenient`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LenientMessage<M> {
+    /// The message
+    pub message: M,
+    /// Whether the message had a version newer than any we support, and was decoded under the
+    /// base version instead
+    pub stale_version: bool,
+}
+
 impl<TYPES: NodeType> fmt::Debug for Message<TYPES> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("Message")
@@ -164,7 +208,7 @@ impl<TYPES: NodeType> HasViewNumber<TYPES> for Message<TYPES> {
 pub struct Messages<TYPES: NodeType>(pub Vec<Message<TYPES>>);
 
 /// A message type agnostic description of a message's purpose
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum MessagePurpose {
     /// Message with a [quorum/DA] proposal.
     Proposal,
@@ -219,16 +263,29 @@ impl<TYPES: NodeType> From<DataMessage<TYPES>> for MessageKind<TYPES> {
     }
 }
 
+/// The view a response to a data request relates to
+fn response_view_number<TYPES: NodeType>(response: &ResponseMessage<TYPES>) -> TYPES::Time {
+    match response {
+        ResponseMessage::Found(m) => m.view_number(),
+        ResponseMessage::FoundMany(messages) => messages
+            .first()
+            .map_or(TYPES::Time::new(1), SequencingMessage::view_number),
+        ResponseMessage::NotFound | ResponseMessage::Denied => TYPES::Time::new(1),
+    }
+}
+
 impl<TYPES: NodeType> ViewMessage<TYPES> for MessageKind<TYPES> {
     fn view_number(&self) -> TYPES::Time {
         match &self {
             MessageKind::Consensus(message) => message.view_number(),
             MessageKind::Data(DataMessage::SubmitTransaction(_, v)) => *v,
+            MessageKind::Data(DataMessage::SubmitTransactionWithPow(submission)) => submission.view,
             MessageKind::Data(DataMessage::RequestData(msg)) => msg.view,
-            MessageKind::Data(DataMessage::DataResponse(msg)) => match msg {
-                ResponseMessage::Found(m) => m.view_number(),
-                ResponseMessage::NotFound | ResponseMessage::Denied => TYPES::Time::new(1),
-            },
+            MessageKind::Data(DataMessage::DataResponse(msg)) => response_view_number(msg),
+            MessageKind::Data(DataMessage::SignedDataResponse(msg)) => {
+                response_view_number(&msg.response)
+            }
+            MessageKind::Data(DataMessage::Probe(message)) => message.view(),
         }
     }
 
@@ -276,6 +333,19 @@ pub enum GeneralConsensusMessage<TYPES: NodeType> {
 
     /// Message with an upgrade vote
     UpgradeVote(UpgradeVote<TYPES>),
+
+    /// Message with a quorum proposal and the DA certificate for its parent's view. Sent instead
+    /// of [`Proposal`](Self::Proposal) once the network has upgraded to the [`Upgrade`] version,
+    /// when the leader has the parent's DA certificate.
+    ProposalWithParentDa(
+        Proposal<TYPES, QuorumProposal<TYPES>>,
+        ParentDaCertificate<TYPES>,
+    ),
+
+    /// Message with a quorum vote and the application data the voter attached to it. Only sent
+    /// once the network has upgraded to the [`Upgrade`] version; before that the vote is sent
+    /// without its extension, as [`Vote`](Self::Vote).
+    ExtendedVote(QuorumVote<TYPES>, VoteExtension<TYPES>),
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Hash, Eq)]
@@ -315,12 +385,16 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
         match &self {
             SequencingMessage::General(general_message) => {
                 match general_message {
-                    GeneralConsensusMessage::Proposal(p) => {
+                    GeneralConsensusMessage::Proposal(p)
+                    | GeneralConsensusMessage::ProposalWithParentDa(p, _) => {
                         // view of leader in the leaf when proposal
                         // this should match replica upon receipt
                         p.data.view_number()
                     }
-                    GeneralConsensusMessage::Vote(vote_message) => vote_message.view_number(),
+                    GeneralConsensusMessage::Vote(vote_message)
+                    | GeneralConsensusMessage::ExtendedVote(vote_message, _) => {
+                        vote_message.view_number()
+                    }
                     GeneralConsensusMessage::TimeoutVote(message) => message.view_number(),
                     GeneralConsensusMessage::ViewSyncPreCommitVote(message) => {
                         message.view_number()
@@ -361,10 +435,11 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
     fn purpose(&self) -> MessagePurpose {
         match &self {
             SequencingMessage::General(general_message) => match general_message {
-                GeneralConsensusMessage::Proposal(_) => MessagePurpose::Proposal,
-                GeneralConsensusMessage::Vote(_) | GeneralConsensusMessage::TimeoutVote(_) => {
-                    MessagePurpose::Vote
-                }
+                GeneralConsensusMessage::Proposal(_)
+                | GeneralConsensusMessage::ProposalWithParentDa(..) => MessagePurpose::Proposal,
+                GeneralConsensusMessage::Vote(_)
+                | GeneralConsensusMessage::ExtendedVote(..)
+                | GeneralConsensusMessage::TimeoutVote(_) => MessagePurpose::Vote,
                 GeneralConsensusMessage::ViewSyncPreCommitVote(_)
                 | GeneralConsensusMessage::ViewSyncCommitVote(_)
                 | GeneralConsensusMessage::ViewSyncFinalizeVote(_) => MessagePurpose::ViewSyncVote,
@@ -388,6 +463,23 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
     }
 }
 
+/// How urgently a submitted transaction should be included, which builders can use to order the
+/// transactions within a block. Priorities are a hint from the submitter, and aren't checked by
+/// the proof of work. They are only sent to other nodes once the network has upgraded, and
+/// transactions submitted before that are [`Normal`](Self::Normal) to them.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum TxPriority {
+    /// Latency sensitive, to be included ahead of other transactions
+    High,
+    /// The priority of transactions submitted without one
+    #[default]
+    Normal,
+    /// Bulk, to be included after other transactions
+    Low,
+}
+
 #[derive(Serialize, Deserialize, Derivative, Clone, Debug, PartialEq, Eq, Hash)]
 #[serde(bound(deserialize = ""))]
 #[allow(clippy::large_enum_variant)]
@@ -402,6 +494,31 @@ pub enum DataMessage<TYPES: NodeType> {
     RequestData(DataRequest<TYPES>),
     /// A response to a data request
     DataResponse(ResponseMessage<TYPES>),
+    /// A pre-flight probe of a peer, see [`crate::probe`]. Only sent once the network has
+    /// upgraded to the [`Upgrade`] version.
+    Probe(ProbeMessage<TYPES>),
+    /// Contains a transaction to be submitted with its proof of work and priority. Sent instead of
+    /// [`SubmitTransaction`](Self::SubmitTransaction) once the network has upgraded to the
+    /// [`Upgrade`] version, from when nodes require the proof of work.
+    SubmitTransactionWithPow(TransactionSubmission<TYPES>),
+    /// A signed response to a data request. Sent instead of [`DataResponse`](Self::DataResponse)
+    /// once the network has upgraded to the [`Upgrade`] version by the view of the request.
+    SignedDataResponse(SignedResponse<TYPES>),
+}
+
+/// A transaction submitted with the nonce of its proof of work (see [`crate::transaction_pow`])
+/// and the priority it was submitted with
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct TransactionSubmission<TYPES: NodeType> {
+    /// The transaction
+    pub transaction: TYPES::Transaction,
+    /// The view it was submitted in
+    pub view: TYPES::Time,
+    /// The nonce of its proof of work
+    pub nonce: u64,
+    /// The priority it was submitted with
+    pub priority: TxPriority,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
@@ -438,3 +555,26 @@ where
         Ok(())
     }
 }
+
+/// Evidence that a leader signed two conflicting quorum proposals for the same view.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct EquivocationProof<TYPES: NodeType> {
+    /// The first proposal
+    pub first: Proposal<TYPES, QuorumProposal<TYPES>>,
+    /// The second proposal, conflicting with the first
+    pub second: Proposal<TYPES, QuorumProposal<TYPES>>,
+}
+
+impl<TYPES: NodeType> EquivocationProof<TYPES> {
+    /// Checks that both proposals are for the same view, propose different leaves, and are both
+    /// validly signed by the leader of that view.
+    #[must_use]
+    pub fn verify(&self, quorum_membership: &TYPES::Membership) -> bool {
+        self.first.data.view_number() == self.second.data.view_number()
+            && Leaf::from_quorum_proposal(&self.first.data).commit()
+                != Leaf::from_quorum_proposal(&self.second.data).commit()
+            && self.first.validate_signature(quorum_membership).is_ok()
+            && self.second.validate_signature(quorum_membership).is_ok()
+    }
+}
@@ -0,0 +1,141 @@
+//! Pre-flight checks that a node's peers are reachable and agree with it on genesis and the
+//! committee, run before starting consensus
+//!
+//! A node probing its peers broadcasts a [`ProbeMessage::Request`] carrying its own
+//! [`ProbeDescriptor`], and each peer answers directly with a [`ProbeMessage::Reply`] carrying
+//! theirs. Comparing the descriptors catches misconfigured nodes before launch, rather than as a
+//! network which doesn't make progress.
+//!
+//! Probe messages are only understood by nodes running the [`Upgrade`](crate::constants::Upgrade)
+//! version, so a node only probes once the network has upgraded by its current view.
+
+use std::collections::BTreeMap;
+
+use committable::{Commitment, Committable};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    data::Leaf,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+        states::ValidatedState,
+    },
+};
+
+/// What a node must agree on with its peers before starting consensus
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct ProbeDescriptor<TYPES: NodeType> {
+    /// The commitment of the genesis leaf the node computed
+    pub genesis: Commitment<Leaf<TYPES>>,
+    /// A digest of the quorum and DA committees and their thresholds, as the node sees them
+    pub committee: [u8; 32],
+}
+
+impl<TYPES: NodeType> ProbeDescriptor<TYPES> {
+    /// The descriptor of a node starting from `instance_state`, with the given memberships.
+    pub async fn new(
+        instance_state: &TYPES::InstanceState,
+        quorum_membership: &TYPES::Membership,
+        da_membership: &TYPES::Membership,
+    ) -> Self {
+        let (validated_state, _) = TYPES::ValidatedState::genesis(instance_state);
+        let genesis = Leaf::genesis(&validated_state, instance_state)
+            .await
+            .commit();
+
+        let mut hasher = Sha256::new();
+        for membership in [quorum_membership, da_membership] {
+            for key in membership.whole_committee(TYPES::Time::genesis()) {
+                hasher.update(key.to_bytes());
+            }
+            hasher.update(membership.success_threshold().get().to_le_bytes());
+        }
+
+        Self {
+            genesis,
+            committee: hasher.finalize().into(),
+        }
+    }
+
+    /// How a peer which replied with `theirs` compares to us.
+    #[must_use]
+    pub fn compare(&self, theirs: &Self) -> PeerProbe {
+        PeerProbe::Reached {
+            genesis_agrees: self.genesis == theirs.genesis,
+            committee_agrees: self.committee == theirs.committee,
+        }
+    }
+}
+
+/// Messages exchanged while probing peers
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub enum ProbeMessage<TYPES: NodeType> {
+    /// Broadcast by the probing node in its current view, with its descriptor
+    Request(TYPES::Time, ProbeDescriptor<TYPES>),
+    /// Sent back to the probing node by each peer, in the view of the request, with the peer's
+    /// descriptor
+    Reply(TYPES::Time, ProbeDescriptor<TYPES>),
+}
+
+impl<TYPES: NodeType> ProbeMessage<TYPES> {
+    /// The view the message is sent in, which decides the version it's sent under
+    #[must_use]
+    pub fn view(&self) -> TYPES::Time {
+        match self {
+            Self::Request(view, _) | Self::Reply(view, _) => *view,
+        }
+    }
+}
+
+/// The outcome of probing a single peer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerProbe {
+    /// The peer didn't reply before the probe timed out
+    Unreachable,
+    /// The peer replied
+    Reached {
+        /// Whether the peer computed the same genesis leaf as us
+        genesis_agrees: bool,
+        /// Whether the peer sees the same committees as us
+        committee_agrees: bool,
+    },
+}
+
+impl PeerProbe {
+    /// Whether the peer is reachable and agrees with us on everything.
+    #[must_use]
+    pub fn passed(self) -> bool {
+        matches!(
+            self,
+            Self::Reached {
+                genesis_agrees: true,
+                committee_agrees: true,
+            }
+        )
+    }
+}
+
+/// The outcome of probing every peer in the committee
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProbeReport<TYPES: NodeType> {
+    /// The outcome for each peer, other than ourselves
+    pub peers: BTreeMap<TYPES::SignatureKey, PeerProbe>,
+}
+
+impl<TYPES: NodeType> ProbeReport<TYPES> {
+    /// Whether every peer is reachable and agrees with us on everything.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.peers.values().all(|probe| probe.passed())
+    }
+
+    /// The peers which failed the probe, with their outcome.
+    pub fn failures(&self) -> impl Iterator<Item = (&TYPES::SignatureKey, &PeerProbe)> {
+        self.peers.iter().filter(|(_, probe)| !probe.passed())
+    }
+}
@@ -111,6 +111,13 @@ impl SignatureKey for BLSPubKey {
         signature.clone()
     }
 
+    fn qc_from_sig_proof(
+        signature: Self::PureAssembledSignatureType,
+        signers: BitVec,
+    ) -> Option<Self::QcType> {
+        Some((signature, signers))
+    }
+
     fn assemble(
         real_qc_pp: &Self::QcParams,
         signers: &BitSlice,
@@ -6,10 +6,12 @@ use std::{
     marker::PhantomData,
 };
 
-use anyhow::{ensure, Result};
+use anyhow::{ensure, Context, Result};
+use bincode::Options;
+use bitvec::{slice::BitSlice, vec::BitVec};
 use committable::{Commitment, Committable};
 use ethereum_types::U256;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
     data::serialize_signature2,
@@ -117,14 +119,11 @@ impl<TYPES: NodeType, VOTEABLE: Voteable + 'static, THRESHOLD: Threshold<TYPES>>
         if self.view_number == TYPES::Time::genesis() {
             return true;
         }
-        let real_qc_pp = <TYPES::SignatureKey as SignatureKey>::public_parameter(
-            membership.committee_qc_stake_table(),
-            U256::from(Self::threshold(membership)),
-        );
-        <TYPES::SignatureKey as SignatureKey>::check(
-            &real_qc_pp,
-            self.vote_commitment.as_ref(),
+        <TYPES::SignatureKey as SignatureKey>::verify_aggregate::<TYPES, _>(
             self.signatures.as_ref().unwrap(),
+            self.vote_commitment.as_ref(),
+            membership,
+            U256::from(Self::threshold(membership)),
         )
     }
     fn threshold<MEMBERSHIP: Membership<TYPES>>(membership: &MEMBERSHIP) -> u64 {
@@ -145,6 +144,176 @@ impl<TYPES: NodeType, VOTEABLE: Voteable + 'static, THRESHOLD: Threshold<TYPES>>
         self.view_number
     }
 }
+/// The largest stake table a compactly serialized certificate may describe, so that a malicious
+/// signer count can't exhaust our memory.
+const MAX_COMPACT_SIGNERS: usize = 1 << 24;
+
+/// The aggregate signature of a compactly serialized certificate, with its signers.
+type CompactSignatures<TYPES> = Option<(
+    <<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    CompactSigners,
+)>;
+
+/// The signers of a compactly serialized certificate, in whichever encoding is smallest.
+#[derive(Serialize, Deserialize)]
+enum CompactSigners {
+    /// One bit per stake table entry, packed eight to a byte
+    Bitmap {
+        /// Number of stake table entries
+        len: usize,
+        /// The packed bits
+        bytes: Vec<u8>,
+    },
+    /// The gaps between the indices of consecutive signers
+    Signers {
+        /// Number of stake table entries
+        len: usize,
+        /// The gaps
+        gaps: Vec<usize>,
+    },
+    /// The gaps between the indices of consecutive non-signers
+    NonSigners {
+        /// Number of stake table entries
+        len: usize,
+        /// The gaps
+        gaps: Vec<usize>,
+    },
+}
+
+impl CompactSigners {
+    /// Encode `signers` in whichever encoding is smallest.
+    fn new(signers: &BitSlice) -> Self {
+        let len = signers.len();
+        let mut bytes = vec![0u8; len.div_ceil(8)];
+        for index in signers.iter_ones() {
+            bytes[index / 8] |= 1 << (index % 8);
+        }
+        [
+            Self::Bitmap { len, bytes },
+            Self::Signers {
+                len,
+                gaps: gaps(signers.iter_ones()),
+            },
+            Self::NonSigners {
+                len,
+                gaps: gaps(signers.iter_zeros()),
+            },
+        ]
+        .into_iter()
+        .min_by_key(|encoding| compact_opts().serialized_size(encoding).unwrap_or(u64::MAX))
+        .expect("There is always a candidate encoding")
+    }
+
+    /// Decode the signers.
+    fn decompress(self) -> Result<BitVec> {
+        match self {
+            Self::Bitmap { len, bytes } => {
+                ensure!(
+                    bytes.len() == len.div_ceil(8),
+                    "Signer bitmap of {} bytes can't hold {len} signers",
+                    bytes.len()
+                );
+                Ok((0..len)
+                    .map(|index| ((bytes[index / 8] >> (index % 8)) & 1) == 1)
+                    .collect())
+            }
+            Self::Signers { len, gaps } => from_gaps(len, &gaps, true),
+            Self::NonSigners { len, gaps } => from_gaps(len, &gaps, false),
+        }
+    }
+}
+
+/// The gaps between consecutive `indices`, which must be increasing.
+fn gaps(indices: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut next = 0;
+    indices
+        .map(|index| {
+            let gap = index - next;
+            next = index + 1;
+            gap
+        })
+        .collect()
+}
+
+/// A bit vector of length `len`, with the bits at the indices described by `gaps` set to `value`
+/// and every other bit set to its negation.
+fn from_gaps(len: usize, gaps: &[usize], value: bool) -> Result<BitVec> {
+    ensure!(
+        len <= MAX_COMPACT_SIGNERS,
+        "Too many signers in compact certificate: {len}"
+    );
+    let mut bits = BitVec::repeat(!value, len);
+    let mut next: usize = 0;
+    for gap in gaps {
+        let index = next
+            .checked_add(*gap)
+            .filter(|index| *index < len)
+            .context("Signer index out of range in compact certificate")?;
+        bits.set(index, value);
+        next = index + 1;
+    }
+    Ok(bits)
+}
+
+/// The options for compact serialization: variable length integers and no trailing bytes.
+fn compact_opts() -> impl Options {
+    bincode::DefaultOptions::new().reject_trailing_bytes()
+}
+
+impl<TYPES: NodeType, VOTEABLE: Voteable + 'static, THRESHOLD: Threshold<TYPES>>
+    SimpleCertificate<TYPES, VOTEABLE, THRESHOLD>
+{
+    /// Serialize the certificate compactly, for storage or transmission: the aggregate signature
+    /// is kept as is, while the signers are encoded as a packed bitmap or a list of the signers or
+    /// non-signers, whichever is smallest.
+    ///
+    /// # Errors
+    /// If the certificate can't be serialized.
+    pub fn compact_bytes(&self) -> Result<Vec<u8>> {
+        let signatures: CompactSignatures<TYPES> = self.signatures.as_ref().map(|qc| {
+            let (signature, signers) = TYPES::SignatureKey::sig_proof(qc);
+            (signature, CompactSigners::new(&signers))
+        });
+        Ok(compact_opts().serialize(&(
+            &self.data,
+            &self.vote_commitment,
+            &self.view_number,
+            &signatures,
+        ))?)
+    }
+
+    /// Deserialize a certificate serialized with [`compact_bytes`](Self::compact_bytes).
+    ///
+    /// # Errors
+    /// If `bytes` isn't a compactly serialized certificate, or the signature scheme can't rebuild
+    /// its signatures from their compact form.
+    pub fn from_compact_bytes(bytes: &[u8]) -> Result<Self>
+    where
+        VOTEABLE: DeserializeOwned,
+    {
+        let (data, vote_commitment, view_number, signatures): (
+            VOTEABLE,
+            Commitment<VOTEABLE>,
+            TYPES::Time,
+            CompactSignatures<TYPES>,
+        ) = compact_opts().deserialize(bytes)?;
+        let signatures = match signatures {
+            Some((signature, signers)) => Some(
+                TYPES::SignatureKey::qc_from_sig_proof(signature, signers.decompress()?)
+                    .context("The signature scheme has no compact certificates")?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            data,
+            vote_commitment,
+            view_number,
+            signatures,
+            _pd: PhantomData,
+        })
+    }
+}
+
 impl<TYPES: NodeType> Display for QuorumCertificate<TYPES> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "view: {:?}", self.view_number)
@@ -8,7 +8,10 @@ use vbs::version::Version;
 
 use crate::{
     data::Leaf,
-    traits::{node_implementation::NodeType, signature_key::SignatureKey},
+    traits::{
+        node_implementation::NodeType,
+        signature_key::{SignatureKey, Signer},
+    },
     vid::VidCommitment,
     vote::{HasViewNumber, Vote},
 };
@@ -160,6 +163,68 @@ impl<TYPES: NodeType, DATA: Voteable + 'static> SimpleVote<TYPES, DATA> {
             Err(e) => Err(e),
         }
     }
+
+    /// Creates a simple vote, signed by `signer`
+    /// # Errors
+    /// If `signer` is unable to sign the data
+    pub async fn create_vote_with_signer(
+        data: DATA,
+        view: TYPES::Time,
+        pub_key: &TYPES::SignatureKey,
+        signer: &dyn Signer<TYPES::SignatureKey>,
+    ) -> anyhow::Result<Self> {
+        let signature = signer.sign(data.commit().as_ref()).await?;
+        Ok(Self {
+            signature: (pub_key.clone(), signature),
+            data,
+            view_number: view,
+        })
+    }
+}
+
+/// Opaque application data attached to a vote, e.g. a `QuorumVote`.
+///
+/// The extension travels alongside the vote rather than inside it, so the vote and its signature
+/// are unchanged and votes with different extensions still aggregate into the same certificate.
+/// It carries the voter's own signature over the vote commitment, view and extension bytes, so it
+/// can't be forged or moved onto another vote.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
+#[serde(bound(deserialize = ""))]
+pub struct VoteExtension<TYPES: NodeType> {
+    /// The application data
+    pub data: Vec<u8>,
+    /// Signature of the voter over the extension
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> VoteExtension<TYPES> {
+    /// Attaches `data` to `vote`, signed with the voter's `private_key`.
+    /// # Errors
+    /// If we are unable to sign the extension
+    pub fn new<VOTE: Vote<TYPES>>(
+        vote: &VOTE,
+        data: Vec<u8>,
+        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    ) -> Result<Self, <TYPES::SignatureKey as SignatureKey>::SignError> {
+        let signature = TYPES::SignatureKey::sign(private_key, &Self::message(vote, &data))?;
+        Ok(Self { data, signature })
+    }
+
+    /// Whether the voter of `vote` attached this extension to it.
+    #[must_use]
+    pub fn is_valid_for<VOTE: Vote<TYPES>>(&self, vote: &VOTE) -> bool {
+        vote.signing_key()
+            .validate(&self.signature, &Self::message(vote, &self.data))
+    }
+
+    /// Builds the message signed for an extension of `vote`.
+    fn message<VOTE: Vote<TYPES>>(vote: &VOTE, data: &[u8]) -> Vec<u8> {
+        let mut message = b"Vote extension".to_vec();
+        message.extend_from_slice(vote.date_commitment().as_ref());
+        message.extend_from_slice(&vote.view_number().to_le_bytes());
+        message.extend_from_slice(data);
+        message
+    }
 }
 
 impl<TYPES: NodeType> Committable for QuorumData<TYPES> {
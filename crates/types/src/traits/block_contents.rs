@@ -19,7 +19,7 @@ use vbs::version::Version;
 
 use super::signature_key::BuilderSignatureKey;
 use crate::{
-    data::Leaf,
+    data::{null_block, Leaf},
     traits::{node_implementation::NodeType, states::InstanceState, ValidatedState},
     utils::BuilderCommitment,
     vid::{vid_scheme, VidCommitment, VidCommon, VidSchemeType},
@@ -129,6 +129,9 @@ pub trait TestableBlock<TYPES: NodeType>: BlockPayload<TYPES> + Debug {
 }
 
 /// Compute the VID payload commitment.
+///
+/// The commitment for an empty payload is cached per number of storage nodes, see
+/// [`null_block::vid`].
 /// TODO(Gus) delete this function?
 /// # Panics
 /// If the VID computation fails.
@@ -138,11 +141,18 @@ pub fn vid_commitment(
     encoded_transactions: &[u8],
     num_storage_nodes: usize,
 ) -> <VidSchemeType as VidScheme>::Commit {
+    if encoded_transactions.is_empty() {
+        if let Some(vid) = null_block::vid(num_storage_nodes) {
+            return vid.commitment;
+        }
+    }
     let encoded_tx_len = encoded_transactions.len();
     vid_scheme(num_storage_nodes).commit_only(encoded_transactions).unwrap_or_else(|err| panic!("VidScheme::commit_only failure:(num_storage_nodes,payload_byte_len)=({num_storage_nodes},{encoded_tx_len}) error: {err}"))
 }
 
 /// Compute the VID payload commitment along with precompute data reducing time in VID Disperse
+///
+/// As with [`vid_commitment`], the result for an empty payload is cached.
 /// # Panics
 /// If the VID computation fails.
 #[must_use]
@@ -154,6 +164,11 @@ pub fn precompute_vid_commitment(
     <VidSchemeType as VidScheme>::Commit,
     <VidSchemeType as Precomputable>::PrecomputeData,
 ) {
+    if encoded_transactions.is_empty() {
+        if let Some(vid) = null_block::vid(num_storage_nodes) {
+            return (vid.commitment, vid.precompute_data.clone());
+        }
+    }
     let encoded_tx_len = encoded_transactions.len();
     vid_scheme(num_storage_nodes).commit_only_precompute(encoded_transactions).unwrap_or_else(|err| panic!("VidScheme::commit_only failure:(num_storage_nodes,payload_byte_len)=({num_storage_nodes},{encoded_tx_len}) error: {err}"))
 }
@@ -216,4 +231,11 @@ pub trait BlockHeader<TYPES: NodeType>:
 
     /// Get the builder commitment
     fn builder_commitment(&self) -> BuilderCommitment;
+
+    /// Get the version of the application's header format this header was built under, if the
+    /// header records one. Proposals whose header records a version other than the one in effect
+    /// for their view are rejected.
+    fn app_version(&self) -> Option<Version> {
+        None
+    }
 }
@@ -1,6 +1,6 @@
 //! Contains the [`ConsensusApi`] trait.
 
-use std::{num::NonZeroUsize, time::Duration};
+use std::{num::NonZeroUsize, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 
@@ -8,7 +8,7 @@ use crate::{
     event::Event,
     traits::{
         node_implementation::{NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signature_key::Signer,
     },
 };
 
@@ -25,8 +25,11 @@ pub trait ConsensusApi<TYPES: NodeType, I: NodeImplementation<TYPES>>: Send + Sy
     /// Get a reference to the public key.
     fn public_key(&self) -> &TYPES::SignatureKey;
 
-    /// Get a reference to the private key.
-    fn private_key(&self) -> &<TYPES::SignatureKey as SignatureKey>::PrivateKey;
+    /// Get the signer that holds our private key.
+    fn signer(&self) -> Arc<dyn Signer<TYPES::SignatureKey>>;
+
+    /// The view this node is on.
+    async fn cur_view(&self) -> TYPES::Time;
 
     /// Notify the system of an event within `hotshot-consensus`.
     async fn send_event(&self, event: Event<TYPES>);
@@ -3,8 +3,15 @@
 // Needed to avoid the non-binding `let` warning.
 #![allow(clippy::let_underscore_untyped)]
 
-use std::{collections::BTreeSet, fmt::Debug, hash::Hash, num::NonZeroU64};
-
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Debug,
+    hash::Hash,
+    num::NonZeroU64,
+};
+
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use snafu::Snafu;
 
 use super::node_implementation::NodeType;
@@ -41,6 +48,21 @@ pub trait Membership<TYPES: NodeType>:
     /// The leader of the committee for view `view_number`.
     fn leader(&self, view_number: TYPES::Time) -> TYPES::SignatureKey;
 
+    /// The leaders of the `count` consecutive views starting at `from`, paired with their views.
+    ///
+    /// Leader selection only depends on the view and the membership, so this agrees view-by-view
+    /// with [`leader`](Self::leader).
+    fn leaders_ahead(
+        &self,
+        from: TYPES::Time,
+        count: usize,
+    ) -> Vec<(TYPES::Time, TYPES::SignatureKey)> {
+        std::iter::successors(Some(from), |view| Some(*view + 1))
+            .take(count)
+            .map(|view| (view, self.leader(view)))
+            .collect()
+    }
+
     /// The staked members of the committee for view `view_number`.
     fn staked_committee(&self, view_number: TYPES::Time) -> BTreeSet<TYPES::SignatureKey>;
 
@@ -50,9 +72,66 @@ pub trait Membership<TYPES: NodeType>:
     /// Get whole (staked + non-staked) committee for view `view_number`.
     fn whole_committee(&self, view_number: TYPES::Time) -> BTreeSet<TYPES::SignatureKey>;
 
+    /// The observers for view `view_number`: nodes which follow consensus and are sent its
+    /// messages, but hold no stake and never count towards a threshold. Defaults to the
+    /// non-staked committee.
+    fn observers(&self, view_number: TYPES::Time) -> BTreeSet<TYPES::SignatureKey> {
+        self.non_staked_committee(view_number)
+    }
+
+    /// The group `pub_key` belongs to, if it has one. Groups are opaque tags, such as a data
+    /// center, for nodes prone to failing together.
+    fn node_group(&self, _pub_key: &TYPES::SignatureKey) -> Option<&str> {
+        None
+    }
+
+    /// A DA committee of `size` staked members for view `view_number`, spread across as many
+    /// distinct groups (see [`node_group`](Self::node_group)) as possible. Nodes without a group
+    /// each count as a group of their own.
+    ///
+    /// The committee only depends on the view and the membership, so every node computes the same
+    /// committee for a view.
+    fn diverse_committee(
+        &self,
+        view_number: TYPES::Time,
+        size: usize,
+    ) -> BTreeSet<TYPES::SignatureKey> {
+        // Shuffle with the view as the seed, so the committee rotates from view to view.
+        let mut candidates: Vec<_> = self.staked_committee(view_number).into_iter().collect();
+        candidates.shuffle(&mut ChaCha20Rng::seed_from_u64(*view_number));
+
+        // Rank each candidate by how many members of its group come before it, so the first
+        // member of every group is picked before the second member of any.
+        let mut group_counts: HashMap<&str, usize> = HashMap::new();
+        let mut ranked: Vec<_> = candidates
+            .into_iter()
+            .enumerate()
+            .map(|(position, key)| {
+                let depth = self.node_group(&key).map_or(0, |group| {
+                    let count = group_counts.entry(group).or_default();
+                    *count += 1;
+                    *count - 1
+                });
+                (depth, position, key)
+            })
+            .collect();
+        ranked.sort_unstable_by_key(|(depth, position, _)| (*depth, *position));
+
+        ranked
+            .into_iter()
+            .take(size)
+            .map(|(_, _, key)| key)
+            .collect()
+    }
+
     /// Check if a key has stake
     fn has_stake(&self, pub_key: &TYPES::SignatureKey) -> bool;
 
+    /// Check if a key belongs to an observer, which by default is any key without stake
+    fn is_observer(&self, pub_key: &TYPES::SignatureKey) -> bool {
+        !self.has_stake(pub_key)
+    }
+
     /// Get the stake table entry for a public key, returns `None` if the
     /// key is not in the table
     fn stake(
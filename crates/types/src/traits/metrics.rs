@@ -5,8 +5,18 @@
 //! - [`Gauge`]: a value that store the latest value, and can go up and down (example usage: amount of users logged in)
 //! - [`Histogram`]: stores multiple float values based for a graph (example usage: CPU %)
 //! - text: stores a constant string in the collected metrics
-
-use std::fmt::Debug;
+//!
+//! [`PrometheusMetrics`] records the latest value of every metric created through it, and renders
+//! them in the Prometheus text exposition format.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+};
 
 use dyn_clone::DynClone;
 
@@ -225,6 +235,495 @@ dyn_clone::clone_trait_object!(Metrics);
 dyn_clone::clone_trait_object!(Gauge);
 dyn_clone::clone_trait_object!(Counter);
 dyn_clone::clone_trait_object!(Histogram);
+dyn_clone::clone_trait_object!(CounterFamily);
+dyn_clone::clone_trait_object!(GaugeFamily);
+dyn_clone::clone_trait_object!(HistogramFamily);
+dyn_clone::clone_trait_object!(TextFamily);
+
+/// Every metric recorded by a [`PrometheusMetrics`], keyed by name.
+type Registry = Arc<Mutex<BTreeMap<String, RecordedMetric>>>;
+
+/// Lock `registry`. The registry is only locked to add a metric or to render them all, and it's
+/// consistent even if a thread panicked while holding the lock.
+fn lock(registry: &Registry) -> MutexGuard<'_, BTreeMap<String, RecordedMetric>> {
+    registry.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The upper bounds of the histogram buckets. They span the ranges of the histograms recorded
+/// by consensus, from durations in seconds to durations in milliseconds.
+const HISTOGRAM_BUCKETS: [f64; 20] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0,
+    1000.0, 2500.0, 5000.0, 10000.0,
+];
+
+/// A [`Metrics`] implementation that keeps the latest value of every metric created through it, so
+/// that they can be rendered in the Prometheus text exposition format by [`Self::render`].
+///
+/// Subgroups share the registry of the metrics they were created from, with the subgroup name as a
+/// prefix of their metric names. To record metrics that are already sent to another implementation,
+/// create them through [`Self::tee`].
+///
+/// The registry is only locked when a metric is created or rendered; updating a metric only
+/// touches the atomics its handle points to.
+#[derive(Clone, Debug, Default)]
+pub struct PrometheusMetrics {
+    /// Prefix of the names of metrics created through this subgroup
+    prefix: String,
+    /// Every metric created so far, shared between subgroups
+    registry: Registry,
+}
+
+/// The Prometheus type of a recorded metric.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MetricKind {
+    /// A [`Counter`]
+    Counter,
+    /// A [`Gauge`], or a text metric
+    Gauge,
+    /// A [`Histogram`], exported with the buckets in [`HISTOGRAM_BUCKETS`]
+    Histogram,
+}
+
+impl MetricKind {
+    /// The name of this type in the exposition format
+    fn type_name(self) -> &'static str {
+        match self {
+            Self::Counter => "counter",
+            Self::Gauge => "gauge",
+            Self::Histogram => "histogram",
+        }
+    }
+}
+
+/// A metric, or family of metrics, recorded by [`PrometheusMetrics`].
+#[derive(Debug)]
+struct RecordedMetric {
+    /// The type of the metric
+    kind: MetricKind,
+    /// The unit of the metric's values, exported as its help text
+    unit_label: Option<String>,
+    /// The label names of a family, or empty for a single metric
+    label_names: Vec<String>,
+    /// The value of each metric in the family, keyed by label values
+    series: BTreeMap<Vec<String>, Arc<Series>>,
+}
+
+/// The value of a single recorded metric.
+#[derive(Debug, Default)]
+struct Series {
+    /// The value of a counter or gauge, or the number of points added to a histogram
+    value: AtomicI64,
+    /// The bits of the sum of the points added to a histogram
+    sum: AtomicU64,
+    /// The number of points added to a histogram which fell in each bucket, but not in an earlier
+    /// one
+    buckets: [AtomicU64; HISTOGRAM_BUCKETS.len()],
+}
+
+impl Series {
+    /// Add `delta` to the value, saturating at the bounds of an `i64`
+    fn add(&self, delta: i64) {
+        // The closure always returns `Some`, so this never fails.
+        let _ = self
+            .value
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |value| {
+                Some(value.saturating_add(delta))
+            });
+    }
+
+    /// Render this series as the samples of a metric of kind `kind` named `name`
+    fn render(&self, output: &mut String, name: &str, kind: MetricKind, labels: &[(&str, &str)]) {
+        if kind != MetricKind::Histogram {
+            let value = self.value.load(Ordering::Relaxed);
+            output.push_str(&format!("{name}{} {value}\n", render_labels(labels)));
+            return;
+        }
+
+        // Read the buckets before the count, which is updated first, so that no bucket is ever
+        // rendered above the count.
+        let bucket_labels = |bound: &str| {
+            let mut labels = labels.to_vec();
+            labels.push(("le", bound));
+            render_labels(&labels)
+        };
+        let mut cumulative = 0;
+        for (bound, bucket) in HISTOGRAM_BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Acquire);
+            let labels = bucket_labels(&render_float(*bound));
+            output.push_str(&format!("{name}_bucket{labels} {cumulative}\n"));
+        }
+        let sum = f64::from_bits(self.sum.load(Ordering::Acquire));
+        let count = self.value.load(Ordering::Acquire);
+        let labels = bucket_labels("+Inf");
+        output.push_str(&format!("{name}_bucket{labels} {count}\n"));
+        let labels = render_labels(labels);
+        output.push_str(&format!("{name}_sum{labels} {}\n", render_float(sum)));
+        output.push_str(&format!("{name}_count{labels} {count}\n"));
+    }
+}
+
+impl PrometheusMetrics {
+    /// Wrap `metrics`, so that every metric created through the result is recorded by both `metrics`
+    /// and `self`.
+    #[must_use]
+    pub fn tee(&self, metrics: &dyn Metrics) -> Box<dyn Metrics> {
+        Box::new(Tee(dyn_clone::clone_box(metrics), Box::new(self.clone())))
+    }
+
+    /// Render the current value of every recorded metric in the Prometheus text exposition format.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        for (name, metric) in lock(&self.registry).iter() {
+            if let Some(unit_label) = &metric.unit_label {
+                output.push_str(&format!("# HELP {name} {}\n", escape_help(unit_label)));
+            }
+            output.push_str(&format!("# TYPE {name} {}\n", metric.kind.type_name()));
+            for (label_values, series) in &metric.series {
+                let labels: Vec<(&str, &str)> = metric
+                    .label_names
+                    .iter()
+                    .map(String::as_str)
+                    .zip(label_values.iter().map(String::as_str))
+                    .collect();
+                series.render(&mut output, name, metric.kind, &labels);
+            }
+        }
+        output
+    }
+
+    /// The full name of the metric `name` in this subgroup, made into a valid Prometheus name
+    fn full_name(&self, name: &str) -> String {
+        let name = if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}_{name}", self.prefix)
+        };
+        let mut name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            name.insert(0, '_');
+        }
+        name
+    }
+
+    /// Add the metric `name` to the registry, returning its full name. A metric that is created
+    /// twice keeps its value.
+    fn register(
+        &self,
+        name: &str,
+        kind: MetricKind,
+        unit_label: Option<String>,
+        label_names: Vec<String>,
+    ) -> String {
+        let name = self.full_name(name);
+        lock(&self.registry)
+            .entry(name.clone())
+            .or_insert_with(|| RecordedMetric {
+                kind,
+                unit_label,
+                label_names,
+                series: BTreeMap::new(),
+            });
+        name
+    }
+
+    /// Register a single metric, returning a handle to its value
+    fn metric(&self, name: &str, kind: MetricKind, unit_label: Option<String>) -> SeriesHandle {
+        let name = self.register(name, kind, unit_label, Vec::new());
+        series(&self.registry, &name, Vec::new())
+    }
+
+    /// Register a family of metrics
+    fn family(&self, name: &str, kind: MetricKind, label_names: Vec<String>) -> FamilyHandle {
+        FamilyHandle {
+            registry: Arc::clone(&self.registry),
+            name: self.register(name, kind, None, label_names),
+        }
+    }
+}
+
+/// A handle to the metric `name` with the given label values, which is exported with a zero value
+/// until it's updated. Creating the same metric twice returns handles to the same value.
+fn series(registry: &Registry, name: &str, label_values: Vec<String>) -> SeriesHandle {
+    let series = lock(registry)
+        .get_mut(name)
+        .map(|metric| Arc::clone(metric.series.entry(label_values).or_default()))
+        // Metrics are never removed from the registry, so this is unreachable.
+        .unwrap_or_default();
+    SeriesHandle(series)
+}
+
+/// Escape `help` for a `# HELP` line
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Render the label set of a sample, which is empty for a metric that isn't in a family
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+/// Render a sample value, spelling infinities the way Prometheus does
+fn render_float(value: f64) -> String {
+    if value.is_infinite() {
+        let sign = if value.is_sign_positive() { '+' } else { '-' };
+        format!("{sign}Inf")
+    } else {
+        value.to_string()
+    }
+}
+
+/// A handle to the value of a single metric recorded by [`PrometheusMetrics`].
+#[derive(Clone, Debug)]
+struct SeriesHandle(Arc<Series>);
+
+impl Counter for SeriesHandle {
+    fn add(&self, amount: usize) {
+        self.0.add(i64::try_from(amount).unwrap_or(i64::MAX));
+    }
+}
+
+impl Gauge for SeriesHandle {
+    fn set(&self, amount: usize) {
+        self.0
+            .value
+            .store(i64::try_from(amount).unwrap_or(i64::MAX), Ordering::Relaxed);
+    }
+
+    fn update(&self, delta: i64) {
+        self.0.add(delta);
+    }
+}
+
+impl Histogram for SeriesHandle {
+    fn add_point(&self, point: f64) {
+        // Count the point before adding it to its bucket; see `Series::render`.
+        self.0.value.fetch_add(1, Ordering::Release);
+        let _ = self
+            .0
+            .sum
+            .fetch_update(Ordering::Release, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + point).to_bits())
+            });
+        if let Some(bucket) = HISTOGRAM_BUCKETS.iter().position(|bound| point <= *bound) {
+            self.0.buckets[bucket].fetch_add(1, Ordering::Release);
+        }
+    }
+}
+
+/// A handle to a family of metrics recorded by [`PrometheusMetrics`].
+#[derive(Clone, Debug)]
+struct FamilyHandle {
+    /// The registry the family is recorded in
+    registry: Registry,
+    /// The full name of the family
+    name: String,
+}
+
+impl MetricsFamily<Box<dyn Counter>> for FamilyHandle {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Counter> {
+        Box::new(series(&self.registry, &self.name, labels))
+    }
+}
+
+impl MetricsFamily<Box<dyn Gauge>> for FamilyHandle {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Gauge> {
+        Box::new(series(&self.registry, &self.name, labels))
+    }
+}
+
+impl MetricsFamily<Box<dyn Histogram>> for FamilyHandle {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Histogram> {
+        Box::new(series(&self.registry, &self.name, labels))
+    }
+}
+
+impl MetricsFamily<()> for FamilyHandle {
+    fn create(&self, labels: Vec<String>) {
+        Gauge::set(&series(&self.registry, &self.name, labels), 1);
+    }
+}
+
+impl Metrics for PrometheusMetrics {
+    fn create_counter(&self, name: String, unit_label: Option<String>) -> Box<dyn Counter> {
+        Box::new(self.metric(&name, MetricKind::Counter, unit_label))
+    }
+
+    fn create_gauge(&self, name: String, unit_label: Option<String>) -> Box<dyn Gauge> {
+        Box::new(self.metric(&name, MetricKind::Gauge, unit_label))
+    }
+
+    fn create_histogram(&self, name: String, unit_label: Option<String>) -> Box<dyn Histogram> {
+        Box::new(self.metric(&name, MetricKind::Histogram, unit_label))
+    }
+
+    fn create_text(&self, name: String) {
+        Gauge::set(&self.metric(&name, MetricKind::Gauge, None), 1);
+    }
+
+    fn counter_family(&self, name: String, labels: Vec<String>) -> Box<dyn CounterFamily> {
+        Box::new(self.family(&name, MetricKind::Counter, labels))
+    }
+
+    fn gauge_family(&self, name: String, labels: Vec<String>) -> Box<dyn GaugeFamily> {
+        Box::new(self.family(&name, MetricKind::Gauge, labels))
+    }
+
+    fn histogram_family(&self, name: String, labels: Vec<String>) -> Box<dyn HistogramFamily> {
+        Box::new(self.family(&name, MetricKind::Histogram, labels))
+    }
+
+    fn text_family(&self, name: String, labels: Vec<String>) -> Box<dyn TextFamily> {
+        Box::new(self.family(&name, MetricKind::Gauge, labels))
+    }
+
+    fn subgroup(&self, subgroup_name: String) -> Box<dyn Metrics> {
+        Box::new(Self {
+            prefix: self.full_name(&subgroup_name),
+            registry: Arc::clone(&self.registry),
+        })
+    }
+}
+
+/// A pair of metrics, or metrics implementations, that every update is applied to.
+#[derive(Clone, Debug)]
+struct Tee<M>(M, M);
+
+impl Metrics for Tee<Box<dyn Metrics>> {
+    fn create_counter(&self, name: String, unit_label: Option<String>) -> Box<dyn Counter> {
+        Box::new(Tee(
+            self.0.create_counter(name.clone(), unit_label.clone()),
+            self.1.create_counter(name, unit_label),
+        ))
+    }
+
+    fn create_gauge(&self, name: String, unit_label: Option<String>) -> Box<dyn Gauge> {
+        Box::new(Tee(
+            self.0.create_gauge(name.clone(), unit_label.clone()),
+            self.1.create_gauge(name, unit_label),
+        ))
+    }
+
+    fn create_histogram(&self, name: String, unit_label: Option<String>) -> Box<dyn Histogram> {
+        Box::new(Tee(
+            self.0.create_histogram(name.clone(), unit_label.clone()),
+            self.1.create_histogram(name, unit_label),
+        ))
+    }
+
+    fn create_text(&self, name: String) {
+        self.0.create_text(name.clone());
+        self.1.create_text(name);
+    }
+
+    fn counter_family(&self, name: String, labels: Vec<String>) -> Box<dyn CounterFamily> {
+        Box::new(Tee(
+            self.0.counter_family(name.clone(), labels.clone()),
+            self.1.counter_family(name, labels),
+        ))
+    }
+
+    fn gauge_family(&self, name: String, labels: Vec<String>) -> Box<dyn GaugeFamily> {
+        Box::new(Tee(
+            self.0.gauge_family(name.clone(), labels.clone()),
+            self.1.gauge_family(name, labels),
+        ))
+    }
+
+    fn histogram_family(&self, name: String, labels: Vec<String>) -> Box<dyn HistogramFamily> {
+        Box::new(Tee(
+            self.0.histogram_family(name.clone(), labels.clone()),
+            self.1.histogram_family(name, labels),
+        ))
+    }
+
+    fn text_family(&self, name: String, labels: Vec<String>) -> Box<dyn TextFamily> {
+        Box::new(Tee(
+            self.0.text_family(name.clone(), labels.clone()),
+            self.1.text_family(name, labels),
+        ))
+    }
+
+    fn subgroup(&self, subgroup_name: String) -> Box<dyn Metrics> {
+        Box::new(Tee(
+            self.0.subgroup(subgroup_name.clone()),
+            self.1.subgroup(subgroup_name),
+        ))
+    }
+}
+
+impl Counter for Tee<Box<dyn Counter>> {
+    fn add(&self, amount: usize) {
+        self.0.add(amount);
+        self.1.add(amount);
+    }
+}
+
+impl Gauge for Tee<Box<dyn Gauge>> {
+    fn set(&self, amount: usize) {
+        self.0.set(amount);
+        self.1.set(amount);
+    }
+
+    fn update(&self, delta: i64) {
+        self.0.update(delta);
+        self.1.update(delta);
+    }
+}
+
+impl Histogram for Tee<Box<dyn Histogram>> {
+    fn add_point(&self, point: f64) {
+        self.0.add_point(point);
+        self.1.add_point(point);
+    }
+}
+
+impl MetricsFamily<Box<dyn Counter>> for Tee<Box<dyn CounterFamily>> {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Counter> {
+        Box::new(Tee(self.0.create(labels.clone()), self.1.create(labels)))
+    }
+}
+
+impl MetricsFamily<Box<dyn Gauge>> for Tee<Box<dyn GaugeFamily>> {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Gauge> {
+        Box::new(Tee(self.0.create(labels.clone()), self.1.create(labels)))
+    }
+}
+
+impl MetricsFamily<Box<dyn Histogram>> for Tee<Box<dyn HistogramFamily>> {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Histogram> {
+        Box::new(Tee(self.0.create(labels.clone()), self.1.create(labels)))
+    }
+}
+
+impl MetricsFamily<()> for Tee<Box<dyn TextFamily>> {
+    fn create(&self, labels: Vec<String>) {
+        self.0.create(labels.clone());
+        self.1.create(labels);
+    }
+}
 
 #[cfg(test)]
 mod test {
@@ -2,7 +2,7 @@
 //!
 //! Contains types and traits used by `HotShot` to abstract over network access
 
-use async_compatibility_layer::art::async_sleep;
+use async_compatibility_layer::art::{async_sleep, async_timeout};
 #[cfg(async_executor_impl = "async-std")]
 use async_std::future::TimeoutError;
 use derivative::Derivative;
@@ -17,25 +17,38 @@ use tokio::time::error::Elapsed as TimeoutError;
 #[cfg(not(any(async_executor_impl = "async-std", async_executor_impl = "tokio")))]
 compile_error! {"Either config option \"async-std\" or \"tokio\" must be enabled for this crate."}
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet},
     fmt::Debug,
     hash::Hash,
     pin::Pin,
-    sync::Arc,
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::{Duration, SystemTime},
 };
 
+use anyhow::{ensure, Context};
 use async_compatibility_layer::channel::UnboundedSendError;
 use async_trait::async_trait;
+use cdn_proto::mnemonic;
 use futures::future::join_all;
 use rand::{
     distributions::{Bernoulli, Uniform},
     prelude::Distribution,
+    SeedableRng,
 };
+use rand_chacha::ChaCha20Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use snafu::Snafu;
+use tracing::{info, warn};
 
-use super::{node_implementation::NodeType, signature_key::SignatureKey};
+use super::{
+    election::Membership,
+    node_implementation::NodeType,
+    signature_key::{SignatureKey, Signer},
+};
 use crate::{
     data::ViewNumber,
     message::{MessagePurpose, SequencingMessage},
@@ -150,6 +163,8 @@ pub enum NetworkError {
     Timeout {
         /// Source of error
         source: TimeoutError,
+        /// The number of recipients of the message, if it was a broadcast that timed out
+        num_recipients: Option<usize>,
     },
     /// Error sending output to consumer of NetworkingImplementation
     /// TODO this should have more information
@@ -165,6 +180,11 @@ pub enum NetworkError {
         /// vec of errors
         errors: Vec<Box<NetworkError>>,
     },
+    /// A message sent to several recipients failed to reach some of them
+    PartialBroadcast {
+        /// The mnemonic of each recipient the message failed to reach, with the error
+        failures: Vec<(String, Box<NetworkError>)>,
+    },
 }
 
 /// common traits we would like our network messages to implement
@@ -191,11 +211,32 @@ pub trait ViewMessage<TYPES: NodeType> {
 }
 
 /// Wraps a oneshot channel for responding to requests
+#[derive(Debug)]
 pub struct ResponseChannel<M: NetworkMsg> {
     /// underlying sender for this channel
     pub sender: oneshot::Sender<M>,
 }
 
+/// A received message, with the channel to acknowledge it on if its sender asked for an
+/// acknowledgement
+pub type AckedMessage = (Vec<u8>, Option<ResponseChannel<()>>);
+
+/// Acknowledge every message that asked for it right away, for receivers that don't wait until
+/// they have processed them
+#[must_use]
+pub fn acknowledge_on_receipt(messages: Vec<AckedMessage>) -> Vec<Vec<u8>> {
+    messages
+        .into_iter()
+        .map(|(message, ack)| {
+            if let Some(ack) = ack {
+                // The sender may have already given up waiting
+                let _ = ack.sender.send(());
+            }
+            message
+        })
+        .collect()
+}
+
 /// A request for some data that the consensus layer is asking for.
 #[derive(Serialize, Deserialize, Derivative, Clone, Debug, PartialEq, Eq, Hash)]
 #[serde(bound(deserialize = ""))]
@@ -218,6 +259,26 @@ pub enum RequestKind<TYPES: NodeType> {
     DaProposal(TYPES::Time),
     /// Request for quorum proposal for a view
     Proposal(TYPES::Time),
+    /// Request VID data by our key for every view from the first view to the second, inclusive.
+    /// Responders serve at most [`MAX_VID_RANGE`] views, starting from the first. Only sent once
+    /// the network has upgraded by the first view; responders deny it before then.
+    VidRange(TYPES::Time, TYPES::Time, TYPES::SignatureKey),
+}
+
+/// The most views a [`RequestKind::VidRange`] is served for, so responses stay a reasonable size
+pub const MAX_VID_RANGE: u64 = 16;
+
+impl<TYPES: NodeType> RequestKind<TYPES> {
+    /// The view the request is for, or the first view for a ranged request
+    #[must_use]
+    pub fn view(&self) -> TYPES::Time {
+        match self {
+            RequestKind::Vid(view, _)
+            | RequestKind::DaProposal(view)
+            | RequestKind::Proposal(view)
+            | RequestKind::VidRange(view, _, _) => *view,
+        }
+    }
 }
 
 /// A response for a request.  `SequencingMessage` is the same as other network messages
@@ -233,6 +294,72 @@ pub enum ResponseMessage<TYPES: NodeType> {
     NotFound,
     /// The Request was denied
     Denied,
+    /// Peer returned us all the data it had for a ranged request. Only sent in answer to a
+    /// [`RequestKind::VidRange`], which is only sent once the network has upgraded.
+    FoundMany(Vec<SequencingMessage<TYPES>>),
+}
+
+/// A response to a [`DataRequest`], signed by the responder so that the requester can check the
+/// response came from the peer it asked, in answer to its request.
+#[derive(Serialize, Deserialize, Derivative, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct SignedResponse<TYPES: NodeType> {
+    /// The response
+    pub response: ResponseMessage<TYPES>,
+    /// Signature of the Sha256 hash of the request and the response together
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> SignedResponse<TYPES> {
+    /// Sign `response` in answer to `request`.
+    ///
+    /// # Errors
+    /// If the response can't be serialized or `signer` fails to sign it
+    pub async fn new(
+        request: &RequestKind<TYPES>,
+        response: ResponseMessage<TYPES>,
+        signer: &dyn Signer<TYPES::SignatureKey>,
+    ) -> anyhow::Result<Self> {
+        let signature = signer.sign(&Self::digest(request, &response)?).await?;
+        Ok(Self {
+            response,
+            signature,
+        })
+    }
+
+    /// Check that this is a response to `request` signed by `responder`, and that `responder` is
+    /// a member of `membership` for the view of the request, returning the response if so.
+    ///
+    /// # Errors
+    /// If `responder` isn't a member, or the signature isn't `responder`'s over this response to
+    /// `request`
+    pub fn verify(
+        self,
+        request: &RequestKind<TYPES>,
+        responder: &TYPES::SignatureKey,
+        membership: &TYPES::Membership,
+    ) -> anyhow::Result<ResponseMessage<TYPES>> {
+        let view = request.view();
+        ensure!(
+            membership.whole_committee(view).contains(responder),
+            "Response from {responder:?}, which isn't a member for view {view:?}"
+        );
+        ensure!(
+            responder.validate(&self.signature, &Self::digest(request, &self.response)?),
+            "Response with an invalid signature from {responder:?}"
+        );
+        Ok(self.response)
+    }
+
+    /// The Sha256 hash of `request` and `response` together, which the responder signs.
+    fn digest(
+        request: &RequestKind<TYPES>,
+        response: &ResponseMessage<TYPES>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let data =
+            bincode::serialize(&(request, response)).context("Failed to serialize response")?;
+        Ok(Sha256::digest(data).to_vec())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -244,6 +371,21 @@ pub enum BroadcastDelay {
     None,
     /// Delay the broadcast to a given view.
     View(u64),
+    /// Delay the broadcast until a given wall-clock time. A time in the past sends immediately.
+    Until(SystemTime),
+}
+
+impl BroadcastDelay {
+    /// Warn that `network` is sending a broadcast straight away, if it was scheduled for a time
+    /// the network can't schedule it for.
+    pub fn warn_if_unscheduled(&self, network: &str) {
+        if let Self::Until(time) = self {
+            warn!(
+                ?time,
+                "{network} can't schedule broadcasts; sending immediately"
+            );
+        }
+    }
 }
 
 #[async_trait]
@@ -258,6 +400,10 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     /// Resumes the underlying network
     fn resume(&self);
 
+    /// Sets the maximum size of a message received from the network. Larger messages are dropped
+    /// by the network before they are returned from `recv_msgs`.
+    fn set_max_inbound_message_size(&self, _max_size: usize) {}
+
     /// Blocks until the network is successfully initialized
     async fn wait_for_ready(&self);
 
@@ -267,6 +413,20 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
         'a: 'b,
         Self: 'b;
 
+    /// Blocks until the network is shut down, logging why it's being shut down so a shutdown
+    /// after a fault can be told apart from a graceful one.
+    fn shut_down_with<'a, 'b>(&'a self, reason: ShutdownReason) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        match reason {
+            ShutdownReason::Graceful => info!("Shutting down network"),
+            ShutdownReason::Fault(fault) => warn!("Shutting down network after a fault: {fault}"),
+        }
+        self.shut_down()
+    }
+
     /// broadcast message to some subset of nodes
     /// blocking
     async fn broadcast_message(
@@ -276,6 +436,34 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
         broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError>;
 
+    /// broadcast message to some subset of nodes, giving up once `timeout` has elapsed
+    ///
+    /// Networks whose broadcasts can be bounded more precisely should override this.
+    ///
+    /// # Errors
+    /// If the broadcast failed, or [`NetworkError::Timeout`] with the number of recipients if it
+    /// didn't finish within `timeout`.
+    async fn broadcast_message_timeout(
+        &self,
+        message: Vec<u8>,
+        recipients: BTreeSet<K>,
+        broadcast_delay: BroadcastDelay,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        let num_recipients = recipients.len();
+        async_timeout(
+            timeout,
+            self.broadcast_message(message, recipients, broadcast_delay),
+        )
+        .await
+        .unwrap_or_else(|source| {
+            Err(NetworkError::Timeout {
+                source,
+                num_recipients: Some(num_recipients),
+            })
+        })
+    }
+
     /// broadcast a message only to a DA committee
     /// blocking
     async fn da_broadcast_message(
@@ -285,44 +473,119 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
         broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError>;
 
+    /// broadcast a message only to a DA committee, giving up once `timeout` has elapsed
+    ///
+    /// Networks whose broadcasts can be bounded more precisely should override this.
+    ///
+    /// # Errors
+    /// If the broadcast failed, or [`NetworkError::Timeout`] with the number of recipients if it
+    /// didn't finish within `timeout`.
+    async fn da_broadcast_message_timeout(
+        &self,
+        message: Vec<u8>,
+        recipients: BTreeSet<K>,
+        broadcast_delay: BroadcastDelay,
+        timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        let num_recipients = recipients.len();
+        async_timeout(
+            timeout,
+            self.da_broadcast_message(message, recipients, broadcast_delay),
+        )
+        .await
+        .unwrap_or_else(|source| {
+            Err(NetworkError::Timeout {
+                source,
+                num_recipients: Some(num_recipients),
+            })
+        })
+    }
+
     /// send messages with vid shares to its recipients
     /// blocking
+    ///
+    /// # Errors
+    /// [`NetworkError::PartialBroadcast`], naming each recipient whose share failed to send.
     async fn vid_broadcast_message(
         &self,
         messages: HashMap<K, Vec<u8>>,
     ) -> Result<(), NetworkError> {
         let future_results = messages
             .into_iter()
-            .map(|(recipient_key, message)| self.direct_message(message, recipient_key));
+            .map(|(recipient_key, message)| async move {
+                let result = self.direct_message(message, recipient_key.clone()).await;
+                (recipient_key, result)
+            });
         let results = join_all(future_results).await;
 
-        let errors: Vec<_> = results
+        let failures: Vec<_> = results
             .into_iter()
-            .filter_map(|r| match r {
-                Err(error) => Some(Box::new(error)),
-                _ => None,
+            .filter_map(|(recipient_key, result)| {
+                result
+                    .err()
+                    .map(|error| (mnemonic(&recipient_key), Box::new(error)))
             })
             .collect();
 
-        if errors.is_empty() {
+        if failures.is_empty() {
             Ok(())
         } else {
-            Err(NetworkError::MultipleErrors { errors })
+            Err(NetworkError::PartialBroadcast { failures })
         }
     }
 
     /// Sends a direct message to a specific node
     /// blocking
+    ///
+    /// # Errors
+    /// If the message could not be delivered, including [`NetworkError::NoSuchNode`] if the
+    /// network knows the recipient isn't one of its nodes.
     async fn direct_message(&self, message: Vec<u8>, recipient: K) -> Result<(), NetworkError>;
 
+    /// Sends a direct message to a specific node, resolving only once the recipient has
+    /// acknowledged receipt of the message or `timeout` has elapsed.
+    ///
+    /// The recipient gets the acknowledgement channel with the message from
+    /// [`recv_msgs_with_acks`](Self::recv_msgs_with_acks). Networks that cannot confirm delivery
+    /// fall back to [`direct_message`](Self::direct_message).
+    ///
+    /// # Errors
+    /// If the message could not be delivered, or was not acknowledged within `timeout`.
+    async fn direct_message_ack(
+        &self,
+        message: Vec<u8>,
+        recipient: K,
+        _timeout: Duration,
+    ) -> Result<(), NetworkError> {
+        self.direct_message(message, recipient).await
+    }
+
     /// Receive one or many messages from the underlying network.
     ///
     /// # Errors
     /// If there is a network-related failure.
     async fn recv_msgs(&self) -> Result<Vec<Vec<u8>>, NetworkError>;
 
-    /// Ask request the network for some data.  Returns the request ID for that data,
-    /// The ID returned can be used for cancelling the request
+    /// Receive one or many messages from the underlying network, each with the channel to
+    /// acknowledge it on if it was sent with [`direct_message_ack`](Self::direct_message_ack).
+    /// The receiver should only acknowledge a message once it has processed it.
+    ///
+    /// Networks that cannot confirm delivery never hand out a channel.
+    ///
+    /// # Errors
+    /// If there is a network-related failure.
+    async fn recv_msgs_with_acks(&self) -> Result<Vec<AckedMessage>, NetworkError> {
+        Ok(self
+            .recv_msgs()
+            .await?
+            .into_iter()
+            .map(|message| (message, None))
+            .collect())
+    }
+
+    /// Ask request the network for some data.  Returns the serialized response
+    /// [`DataMessage`](crate::message::DataMessage), either a `DataResponse` or a
+    /// `SignedDataResponse`, for the requester to verify
     async fn request_data<TYPES: NodeType>(
         &self,
         _request: Vec<u8>,
@@ -363,6 +626,75 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     fn is_primary_down(&self) -> bool {
         false
     }
+
+    /// The peers this network currently believes are reachable, for observability.
+    ///
+    /// Returns `None` if the network can't tell which of its peers are reachable.
+    async fn connected_peers(&self) -> Option<BTreeSet<K>> {
+        None
+    }
+
+    /// Take the receiving end of a channel of connectivity changes to other nodes.
+    ///
+    /// Returns `None` if the network can't report its topology, or the receiver was already taken.
+    async fn network_change_receiver(&self) -> Option<mpsc::Receiver<NetworkChange<K>>> {
+        None
+    }
+
+    /// Statistics about the messages this network has handled and the peers it is connected to.
+    ///
+    /// Networks report `None` for the statistics they don't track.
+    fn stats(&self) -> NetworkStats {
+        NetworkStats::default()
+    }
+}
+
+/// Transport statistics of a network, as reported by [`ConnectedNetwork::stats`].
+///
+/// Each statistic is `None` if the network doesn't track it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkStats {
+    /// Messages sent, counting each recipient of a broadcast separately
+    pub messages_sent: Option<usize>,
+    /// Messages received
+    pub messages_received: Option<usize>,
+    /// Messages which could not be sent
+    pub messages_failed: Option<usize>,
+    /// Messages sent to us which we haven't received yet
+    pub in_flight_messages: Option<usize>,
+    /// Peers we are connected to
+    pub connected_peers: Option<usize>,
+}
+
+impl NetworkStats {
+    /// Add up the statistics of two networks reaching the same nodes. Their message counts are
+    /// added, while the peers they connect to are the same nodes, so the larger count is taken.
+    ///
+    /// A statistic is known in the result if either network tracks it, and `None` only if neither
+    /// does.
+    #[must_use]
+    pub fn combine(self, other: Self) -> Self {
+        /// Add two statistics, either of which may be missing
+        fn add(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.saturating_add(b)),
+                (a, b) => a.or(b),
+            }
+        }
+
+        /// The larger of two statistics, either of which may be missing
+        fn max(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+            a.max(b)
+        }
+
+        Self {
+            messages_sent: add(self.messages_sent, other.messages_sent),
+            messages_received: add(self.messages_received, other.messages_received),
+            messages_failed: add(self.messages_failed, other.messages_failed),
+            in_flight_messages: add(self.in_flight_messages, other.in_flight_messages),
+            connected_peers: max(self.connected_peers, other.connected_peers),
+        }
+    }
 }
 
 /// A channel generator for types that need asynchronous execution
@@ -391,8 +723,18 @@ where
     fn in_flight_message_count(&self) -> Option<usize>;
 }
 
+/// Why a network is being shut down
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// The node is shutting down normally
+    Graceful,
+    /// The node is shutting down because of an unrecoverable error, described by the string
+    Fault(String),
+}
+
 /// Changes that can occur in the network
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
 pub enum NetworkChange<P: SignatureKey> {
     /// A node is connected
     NodeConnected(P),
@@ -430,10 +772,17 @@ pub trait NetworkReliability: Debug + Sync + std::marker::Send + DynClone + 'sta
         1
     }
 
+    /// whether to reorder the copies of a packet, giving each its own delay rather than
+    /// sending them all in order after a single delay
+    fn sample_reorder(&self) -> bool {
+        false
+    }
+
     /// given a message and a way to send the message,
     /// decide whether or not to send the message
     /// how long to delay the message
     /// whether or not to send duplicates
+    /// whether or not to reorder the duplicates
     /// and whether or not to include noise with the message
     /// then send the message
     /// note: usually self is stored in a rwlock
@@ -447,26 +796,110 @@ pub trait NetworkReliability: Debug + Sync + std::marker::Send + DynClone + 'sta
         let sample_keep = self.sample_keep();
         let delay = self.sample_delay();
         let repeats = self.sample_repeat();
+        let reorder = self.sample_reorder();
         let mut msgs = Vec::new();
         for _idx in 0..repeats {
             let scrambled = self.scramble(msg.clone());
-            msgs.push(scrambled);
+            let msg_delay = if reorder { self.sample_delay() } else { delay };
+            msgs.push((msg_delay, scrambled));
         }
+        // buffer the messages and send them in the order their delays run out, which shuffles
+        // them when they were each given their own delay
+        msgs.sort_by_key(|(msg_delay, _)| *msg_delay);
         let closure = async move {
             if sample_keep {
-                async_sleep(delay).await;
-                for msg in msgs {
+                let mut elapsed = Duration::ZERO;
+                for (msg_delay, msg) in msgs {
+                    async_sleep(msg_delay - elapsed).await;
+                    elapsed = msg_delay;
                     send_fn(msg).await;
                 }
             }
         };
         Box::pin(closure)
     }
+
+    /// like `chaos_send_msg`, for a message sent from node `from` to node `to`, so that models
+    /// can treat the link between two nodes differently. By default the nodes are ignored.
+    fn chaos_send_msg_between(
+        &self,
+        _from: usize,
+        _to: usize,
+        msg: Vec<u8>,
+        send_fn: Arc<dyn Send + Sync + 'static + Fn(Vec<u8>) -> BoxSyncFuture<'static, ()>>,
+    ) -> BoxSyncFuture<'static, ()> {
+        self.chaos_send_msg(msg, send_fn)
+    }
 }
 
 // hack to get clone
 dyn_clone::clone_trait_object!(NetworkReliability);
 
+/// The randomness a reliability model samples from.
+///
+/// By default this is `thread_rng`. A seeded rng reproduces the same sequence of samples for a
+/// given seed, so a failing run can be replayed. Copies share the seeded rng's state, which is
+/// leaked so that the reliability models stay `Copy`; they're created once per network.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReliabilityRng(Option<&'static Mutex<ChaCha20Rng>>);
+
+impl ReliabilityRng {
+    /// create a `ReliabilityRng` seeded with `seed`
+    #[must_use]
+    pub fn seeded(seed: u64) -> Self {
+        Self(Some(Box::leak(Box::new(Mutex::new(
+            ChaCha20Rng::seed_from_u64(seed),
+        )))))
+    }
+
+    /// sample a value from `distribution`
+    pub fn sample<T, D: Distribution<T>>(&self, distribution: &D) -> T {
+        match self.0 {
+            Some(rng) => {
+                distribution.sample(&mut *rng.lock().unwrap_or_else(PoisonError::into_inner))
+            }
+            None => distribution.sample(&mut rand::thread_rng()),
+        }
+    }
+}
+
+/// A message held back by a [`ReorderBuffer`], with the function to send it with
+type BufferedMessage = (
+    Vec<u8>,
+    Arc<dyn Send + Sync + 'static + Fn(Vec<u8>) -> BoxSyncFuture<'static, ()>>,
+);
+
+/// Messages a reliability model holds back to send in shuffled order, across every message
+/// it's asked to send rather than only among the copies of one. Clones share the buffer.
+#[derive(Clone, Default)]
+pub struct ReorderBuffer(Arc<Mutex<Vec<BufferedMessage>>>);
+
+impl Debug for ReorderBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReorderBuffer").finish_non_exhaustive()
+    }
+}
+
+impl ReorderBuffer {
+    /// hold back `msgs` until they're taken
+    fn push(&self, msgs: impl IntoIterator<Item = BufferedMessage>) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(msgs);
+    }
+
+    /// take a message held back by any sender, chosen with `rng`
+    fn take_random(&self, rng: &ReliabilityRng) -> Option<BufferedMessage> {
+        let mut msgs = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if msgs.is_empty() {
+            return None;
+        }
+        let idx = rng.sample(&Uniform::new(0, msgs.len()));
+        Some(msgs.swap_remove(idx))
+    }
+}
+
 /// ideal network
 #[derive(Clone, Copy, Debug, Default)]
 pub struct PerfectNetwork {}
@@ -481,6 +914,8 @@ pub struct SynchronousNetwork {
     pub delay_high_ms: u64,
     /// Lowest value in milliseconds that a packet may be delayed
     pub delay_low_ms: u64,
+    /// randomness for sampling delays
+    pub rng: ReliabilityRng,
 }
 
 impl NetworkReliability for SynchronousNetwork {
@@ -489,10 +924,10 @@ impl NetworkReliability for SynchronousNetwork {
         true
     }
     fn sample_delay(&self) -> Duration {
-        Duration::from_millis(
-            Uniform::new_inclusive(self.delay_low_ms, self.delay_high_ms)
-                .sample(&mut rand::thread_rng()),
-        )
+        Duration::from_millis(self.rng.sample(&Uniform::new_inclusive(
+            self.delay_low_ms,
+            self.delay_high_ms,
+        )))
     }
 }
 
@@ -511,19 +946,20 @@ pub struct AsynchronousNetwork {
     pub delay_low_ms: u64,
     /// highest value in milliseconds that a packet may be delayed
     pub delay_high_ms: u64,
+    /// randomness for sampling drops and delays
+    pub rng: ReliabilityRng,
 }
 
 impl NetworkReliability for AsynchronousNetwork {
     fn sample_keep(&self) -> bool {
-        Bernoulli::from_ratio(self.keep_numerator, self.keep_denominator)
-            .unwrap()
-            .sample(&mut rand::thread_rng())
+        self.rng
+            .sample(&Bernoulli::from_ratio(self.keep_numerator, self.keep_denominator).unwrap())
     }
     fn sample_delay(&self) -> Duration {
-        Duration::from_millis(
-            Uniform::new_inclusive(self.delay_low_ms, self.delay_high_ms)
-                .sample(&mut rand::thread_rng()),
-        )
+        Duration::from_millis(self.rng.sample(&Uniform::new_inclusive(
+            self.delay_low_ms,
+            self.delay_high_ms,
+        )))
     }
 }
 
@@ -541,6 +977,10 @@ pub struct PartiallySynchronousNetwork {
     pub gst: std::time::Duration,
     /// when the network was started
     pub start: std::time::Instant,
+    /// set by [`PartiallySynchronousNetwork::trigger_gst`] to reach GST early. Shared between
+    /// copies, so triggering GST on one triggers it on all of them. It's leaked so that the
+    /// network stays `Copy`
+    pub forced_gst: &'static AtomicBool,
 }
 
 impl NetworkReliability for PartiallySynchronousNetwork {
@@ -550,7 +990,7 @@ impl NetworkReliability for PartiallySynchronousNetwork {
     }
     fn sample_delay(&self) -> Duration {
         // act asynchronous before gst
-        if self.start.elapsed() < self.gst {
+        if !self.gst_reached() {
             if self.asynchronous.sample_keep() {
                 self.asynchronous.sample_delay()
             } else {
@@ -572,6 +1012,7 @@ impl Default for AsynchronousNetwork {
             keep_denominator: 1,
             delay_low_ms: 0,
             delay_high_ms: 0,
+            rng: ReliabilityRng::default(),
         }
     }
 }
@@ -583,6 +1024,7 @@ impl Default for PartiallySynchronousNetwork {
             asynchronous: AsynchronousNetwork::default(),
             gst: std::time::Duration::new(0, 0),
             start: std::time::Instant::now(),
+            forced_gst: Box::leak(Box::default()),
         }
     }
 }
@@ -594,6 +1036,16 @@ impl SynchronousNetwork {
         SynchronousNetwork {
             delay_high_ms: timeout,
             delay_low_ms,
+            rng: ReliabilityRng::default(),
+        }
+    }
+
+    /// sample from an rng seeded with `seed` rather than `thread_rng`
+    #[must_use]
+    pub fn with_seed(self, seed: u64) -> Self {
+        SynchronousNetwork {
+            rng: ReliabilityRng::seeded(seed),
+            ..self
         }
     }
 }
@@ -612,6 +1064,16 @@ impl AsynchronousNetwork {
             keep_denominator,
             delay_low_ms,
             delay_high_ms,
+            rng: ReliabilityRng::default(),
+        }
+    }
+
+    /// sample from an rng seeded with `seed` rather than `thread_rng`
+    #[must_use]
+    pub fn with_seed(self, seed: u64) -> Self {
+        AsynchronousNetwork {
+            rng: ReliabilityRng::seeded(seed),
+            ..self
         }
     }
 }
@@ -630,6 +1092,29 @@ impl PartiallySynchronousNetwork {
             synchronous,
             gst,
             start: std::time::Instant::now(),
+            forced_gst: Box::leak(Box::default()),
+        }
+    }
+
+    /// reach GST now, rather than waiting for `gst` to pass, so the network behaves synchronously
+    /// from here on
+    pub fn trigger_gst(&self) {
+        self.forced_gst.store(true, Ordering::Relaxed);
+    }
+
+    /// whether GST has been reached, either by `gst` passing or by being triggered
+    #[must_use]
+    pub fn gst_reached(&self) -> bool {
+        self.forced_gst.load(Ordering::Relaxed) || self.start.elapsed() >= self.gst
+    }
+
+    /// sample both portions of the network from rngs derived from `seed` rather than `thread_rng`
+    #[must_use]
+    pub fn with_seed(self, seed: u64) -> Self {
+        PartiallySynchronousNetwork {
+            asynchronous: self.asynchronous.with_seed(seed),
+            synchronous: self.synchronous.with_seed(seed.wrapping_add(1)),
+            ..self
         }
     }
 }
@@ -649,23 +1134,265 @@ pub struct ChaosNetwork {
     pub repeat_low: usize,
     /// highest value of repeats for a message
     pub repeat_high: usize,
+    /// probability that a message is reordered. Its copies are buffered along with those of the
+    /// other reordered messages, and each time one of their own delays runs out, a random copy
+    /// from the buffer is sent
+    pub reorder_probability: f64,
+    /// the copies of reordered messages waiting to be sent
+    pub reorder_buffer: ReorderBuffer,
+    /// randomness for sampling drops, delays, repeats and reorders
+    pub rng: ReliabilityRng,
 }
 
 impl NetworkReliability for ChaosNetwork {
     fn sample_keep(&self) -> bool {
-        Bernoulli::from_ratio(self.keep_numerator, self.keep_denominator)
-            .unwrap()
-            .sample(&mut rand::thread_rng())
+        self.rng
+            .sample(&Bernoulli::from_ratio(self.keep_numerator, self.keep_denominator).unwrap())
     }
 
     fn sample_delay(&self) -> Duration {
-        Duration::from_millis(
-            Uniform::new_inclusive(self.delay_low_ms, self.delay_high_ms)
-                .sample(&mut rand::thread_rng()),
-        )
+        Duration::from_millis(self.rng.sample(&Uniform::new_inclusive(
+            self.delay_low_ms,
+            self.delay_high_ms,
+        )))
+    }
+
+    fn sample_repeat(&self) -> usize {
+        self.rng
+            .sample(&Uniform::new_inclusive(self.repeat_low, self.repeat_high))
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `self.reorder_probability` isn't between 0 and 1
+    fn sample_reorder(&self) -> bool {
+        self.reorder_probability > 0.0
+            && self
+                .rng
+                .sample(&Bernoulli::new(self.reorder_probability).unwrap())
+    }
+
+    /// like the default, except that the copies of a reordered message are shuffled with those
+    /// of every other reordered message rather than only with each other
+    fn chaos_send_msg(
+        &self,
+        msg: Vec<u8>,
+        send_fn: Arc<dyn Send + Sync + 'static + Fn(Vec<u8>) -> BoxSyncFuture<'static, ()>>,
+    ) -> BoxSyncFuture<'static, ()> {
+        let sample_keep = self.sample_keep();
+        let delay = self.sample_delay();
+        let repeats = self.sample_repeat();
+        let reorder = self.sample_reorder();
+        if !sample_keep {
+            return Box::pin(async {});
+        }
+        let msgs: Vec<_> = (0..repeats)
+            .map(|_idx| self.scramble(msg.clone()))
+            .collect();
+        if !reorder {
+            return Box::pin(async move {
+                async_sleep(delay).await;
+                for msg in msgs {
+                    send_fn(msg).await;
+                }
+            });
+        }
+        // buffer the copies, and each time one of their delays runs out send whichever buffered
+        // copy is picked, which may belong to any reordered message
+        let mut delays: Vec<_> = msgs.iter().map(|_msg| self.sample_delay()).collect();
+        delays.sort();
+        self.reorder_buffer
+            .push(msgs.into_iter().map(|msg| (msg, Arc::clone(&send_fn))));
+        let buffer = self.reorder_buffer.clone();
+        let rng = self.rng;
+        Box::pin(async move {
+            let mut elapsed = Duration::ZERO;
+            for msg_delay in delays {
+                async_sleep(msg_delay - elapsed).await;
+                elapsed = msg_delay;
+                if let Some((msg, send_fn)) = buffer.take_random(&rng) {
+                    send_fn(msg).await;
+                }
+            }
+        })
+    }
+}
+
+impl Default for ChaosNetwork {
+    // disable all chance of failure
+    fn default() -> Self {
+        ChaosNetwork {
+            keep_numerator: 1,
+            keep_denominator: 1,
+            delay_low_ms: 0,
+            delay_high_ms: 0,
+            repeat_low: 1,
+            repeat_high: 1,
+            reorder_probability: 0.0,
+            reorder_buffer: ReorderBuffer::default(),
+            rng: ReliabilityRng::default(),
+        }
+    }
+}
+
+impl ChaosNetwork {
+    /// sample from an rng seeded with `seed` rather than `thread_rng`
+    #[must_use]
+    pub fn with_seed(self, seed: u64) -> Self {
+        ChaosNetwork {
+            rng: ReliabilityRng::seeded(seed),
+            ..self
+        }
+    }
+}
+
+/// A network combining the effects of several reliability models, applied to every message in
+/// order.
+///
+/// The first model gets each message, and every copy of it that model sends, after its delay, goes
+/// on to the second model, and so on, until the last model sends its copies for real. So drops
+/// compound, delays add up, each repeat goes through the rest of the models on its own, and
+/// scrambles are applied first model to last. An empty composition is a perfect network.
+#[derive(Clone, Debug, Default)]
+pub struct CompositeNetwork {
+    /// the models to apply, in order
+    pub models: Vec<Box<dyn NetworkReliability>>,
+}
+
+impl CompositeNetwork {
+    /// create a `CompositeNetwork` applying `models` in order
+    #[must_use]
+    pub fn new(models: Vec<Box<dyn NetworkReliability>>) -> Self {
+        CompositeNetwork { models }
+    }
+}
+
+impl NetworkReliability for CompositeNetwork {
+    fn chaos_send_msg(
+        &self,
+        msg: Vec<u8>,
+        mut send_fn: Arc<dyn Send + Sync + 'static + Fn(Vec<u8>) -> BoxSyncFuture<'static, ()>>,
+    ) -> BoxSyncFuture<'static, ()> {
+        // Each model sends through the ones after it, so wrap `send_fn` starting from the last.
+        for model in self.models.iter().rev() {
+            let model = model.clone();
+            let next = send_fn;
+            send_fn = Arc::new(move |msg: Vec<u8>| model.chaos_send_msg(msg, Arc::clone(&next)));
+        }
+        send_fn(msg)
+    }
+
+    fn chaos_send_msg_between(
+        &self,
+        from: usize,
+        to: usize,
+        msg: Vec<u8>,
+        mut send_fn: Arc<dyn Send + Sync + 'static + Fn(Vec<u8>) -> BoxSyncFuture<'static, ()>>,
+    ) -> BoxSyncFuture<'static, ()> {
+        for model in self.models.iter().rev() {
+            let model = model.clone();
+            let next = send_fn;
+            send_fn = Arc::new(move |msg: Vec<u8>| {
+                model.chaos_send_msg_between(from, to, msg, Arc::clone(&next))
+            });
+        }
+        send_fn(msg)
+    }
+}
+
+/// A network which cuts the links between some pairs of nodes, dropping every message sent either
+/// way between them, and otherwise behaves like the `underlying` model.
+///
+/// Nodes are only told apart by messages sent with `chaos_send_msg_between`, so a plain
+/// `chaos_send_msg` always goes through the `underlying` model.
+#[derive(Clone, Debug)]
+pub struct PartitionedNetwork {
+    /// the pairs of nodes which can't reach each other, in either order
+    pub blocked_pairs: HashSet<(usize, usize)>,
+    /// the model for messages between nodes which aren't blocked
+    pub underlying: Box<dyn NetworkReliability>,
+}
+
+impl PartitionedNetwork {
+    /// create a `PartitionedNetwork` blocking `blocked_pairs` on top of `underlying`
+    #[must_use]
+    #[allow(clippy::implicit_hasher)]
+    pub fn new(
+        blocked_pairs: HashSet<(usize, usize)>,
+        underlying: Box<dyn NetworkReliability>,
+    ) -> Self {
+        PartitionedNetwork {
+            blocked_pairs,
+            underlying,
+        }
+    }
+
+    /// create a `PartitionedNetwork` cutting every node in `partition` off from every node in
+    /// `0..num_nodes` outside of it, on top of `underlying`
+    #[must_use]
+    pub fn isolating(
+        partition: &[usize],
+        num_nodes: usize,
+        underlying: Box<dyn NetworkReliability>,
+    ) -> Self {
+        let blocked_pairs = partition
+            .iter()
+            .flat_map(|inside| {
+                (0..num_nodes)
+                    .filter(|outside| !partition.contains(outside))
+                    .map(move |outside| (*inside, outside))
+            })
+            .collect();
+        Self::new(blocked_pairs, underlying)
+    }
+
+    /// whether messages between `from` and `to` are dropped
+    #[must_use]
+    pub fn is_blocked(&self, from: usize, to: usize) -> bool {
+        self.blocked_pairs.contains(&(from, to)) || self.blocked_pairs.contains(&(to, from))
+    }
+}
+
+impl NetworkReliability for PartitionedNetwork {
+    fn sample_keep(&self) -> bool {
+        self.underlying.sample_keep()
+    }
+
+    fn sample_delay(&self) -> Duration {
+        self.underlying.sample_delay()
+    }
+
+    fn scramble(&self, msg: Vec<u8>) -> Vec<u8> {
+        self.underlying.scramble(msg)
     }
 
     fn sample_repeat(&self) -> usize {
-        Uniform::new_inclusive(self.repeat_low, self.repeat_high).sample(&mut rand::thread_rng())
+        self.underlying.sample_repeat()
+    }
+
+    fn sample_reorder(&self) -> bool {
+        self.underlying.sample_reorder()
+    }
+
+    fn chaos_send_msg(
+        &self,
+        msg: Vec<u8>,
+        send_fn: Arc<dyn Send + Sync + 'static + Fn(Vec<u8>) -> BoxSyncFuture<'static, ()>>,
+    ) -> BoxSyncFuture<'static, ()> {
+        self.underlying.chaos_send_msg(msg, send_fn)
+    }
+
+    fn chaos_send_msg_between(
+        &self,
+        from: usize,
+        to: usize,
+        msg: Vec<u8>,
+        send_fn: Arc<dyn Send + Sync + 'static + Fn(Vec<u8>) -> BoxSyncFuture<'static, ()>>,
+    ) -> BoxSyncFuture<'static, ()> {
+        if self.is_blocked(from, to) {
+            return Box::pin(async {});
+        }
+        self.underlying
+            .chaos_send_msg_between(from, to, msg, send_fn)
     }
 }
@@ -1,17 +1,20 @@
 //! Minimal compatibility over public key signatures
 use std::{
+    collections::{BTreeMap, HashMap},
     fmt::{Debug, Display},
     hash::Hash,
 };
 
+use anyhow::{ensure, Context};
 use ark_serialize::SerializationError;
+use async_trait::async_trait;
 use bitvec::prelude::*;
 use ethereum_types::U256;
 use jf_vid::VidScheme;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tagged_base64::TaggedBase64;
 
-use super::EncodeBytes;
+use super::{election::Membership, node_implementation::NodeType, EncodeBytes};
 use crate::{utils::BuilderCommitment, vid::VidSchemeType};
 
 /// Type representing stake table entries in a `StakeTable`
@@ -134,6 +137,15 @@ pub trait SignatureKey:
     /// get the assembled signature and the `BitVec` separately from the assembled signature
     fn sig_proof(signature: &Self::QcType) -> (Self::PureAssembledSignatureType, BitVec);
 
+    /// rebuild the assembled signature from the parts returned by [`sig_proof`](Self::sig_proof),
+    /// or `None` if the scheme can't, in which case its certificates have no compact serialization
+    fn qc_from_sig_proof(
+        _signature: Self::PureAssembledSignatureType,
+        _signers: BitVec,
+    ) -> Option<Self::QcType> {
+        None
+    }
+
     /// assemble the signature from the partial signature and the indication of signers in `BitVec`
     fn assemble(
         real_qc_pp: &Self::QcParams,
@@ -144,6 +156,111 @@ pub trait SignatureKey:
     /// generates the genesis public key. Meant to be dummy/filler
     #[must_use]
     fn genesis_proposer_pk() -> Self;
+
+    /// Aggregate the `signatures` of members of `membership` over the same message into a single
+    /// signature, which [`verify_aggregate`](Self::verify_aggregate) accepts for `threshold` if
+    /// every one of the signatures is valid.
+    ///
+    /// # Errors
+    /// If a signer isn't in the quorum stake table, signed more than once, or if the signers'
+    /// total stake doesn't reach `threshold`.
+    fn aggregate<TYPES: NodeType<SignatureKey = Self>, MEMBERSHIP: Membership<TYPES>>(
+        signatures: &[(Self, Self::PureAssembledSignatureType)],
+        membership: &MEMBERSHIP,
+        threshold: U256,
+    ) -> anyhow::Result<Self::QcType> {
+        let stake_table = membership.committee_qc_stake_table();
+        let mut indices = HashMap::with_capacity(stake_table.len());
+        for (index, entry) in stake_table.iter().enumerate() {
+            indices.entry(entry).or_insert(index);
+        }
+        let mut by_index = BTreeMap::new();
+        let mut total_stake = U256::zero();
+        for (key, signature) in signatures {
+            let entry = membership
+                .stake(key)
+                .context("Signer is not in the stake table")?;
+            let index = *indices
+                .get(&entry)
+                .context("Signer is not in the quorum stake table")?;
+            ensure!(
+                by_index.insert(index, signature.clone()).is_none(),
+                "Signer {index} signed more than once"
+            );
+            total_stake += entry.stake();
+        }
+        ensure!(
+            total_stake >= threshold,
+            "Signers' stake {total_stake} is below the threshold {threshold}"
+        );
+
+        let mut signers = bitvec![0; stake_table.len()];
+        for index in by_index.keys() {
+            signers.set(*index, true);
+        }
+        let signatures: Vec<_> = by_index.into_values().collect();
+        Ok(Self::assemble(
+            &Self::public_parameter(stake_table, threshold),
+            &signers,
+            &signatures,
+        ))
+    }
+
+    /// Check that `aggregate` is a valid aggregate signature over `message` by members of
+    /// `membership` whose total stake reaches `threshold`.
+    fn verify_aggregate<TYPES: NodeType<SignatureKey = Self>, MEMBERSHIP: Membership<TYPES>>(
+        aggregate: &Self::QcType,
+        message: &[u8],
+        membership: &MEMBERSHIP,
+        threshold: U256,
+    ) -> bool {
+        Self::check(
+            &Self::public_parameter(membership.committee_qc_stake_table(), threshold),
+            message,
+            aggregate,
+        )
+    }
+}
+
+/// Signs messages on behalf of a node, so that consensus never has to hold the node's private key
+/// itself. The key may be kept in memory, or in custody elsewhere, such as in a hardware security
+/// module or a remote key management service.
+#[async_trait]
+pub trait Signer<KEY: SignatureKey>: Send + Sync + Debug + 'static {
+    /// Sign `message` with the node's private key.
+    ///
+    /// # Errors
+    /// If the key's custodian is unavailable or refuses to sign
+    async fn sign(&self, message: &[u8]) -> anyhow::Result<KEY::PureAssembledSignatureType>;
+}
+
+/// A [`Signer`] for a private key held in memory
+#[derive(Clone)]
+pub struct InMemorySigner<KEY: SignatureKey> {
+    /// The key we sign with
+    private_key: KEY::PrivateKey,
+}
+
+impl<KEY: SignatureKey> InMemorySigner<KEY> {
+    /// Create a signer for `private_key`
+    #[must_use]
+    pub fn new(private_key: KEY::PrivateKey) -> Self {
+        Self { private_key }
+    }
+}
+
+impl<KEY: SignatureKey> Debug for InMemorySigner<KEY> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't leak the key into logs.
+        f.debug_struct("InMemorySigner").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<KEY: SignatureKey + 'static> Signer<KEY> for InMemorySigner<KEY> {
+    async fn sign(&self, message: &[u8]) -> anyhow::Result<KEY::PureAssembledSignatureType> {
+        Ok(KEY::sign(&self.private_key, message)?)
+    }
 }
 
 /// Builder Signature Key trait with minimal requirements
@@ -3,7 +3,7 @@
 //! This modules provides the [`Storage`] trait.
 //!
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -15,6 +15,7 @@ use crate::{
     event::HotShotAction,
     message::Proposal,
     simple_certificate::QuorumCertificate,
+    simple_vote::DaVote,
 };
 
 /// Abstraction for storing a variety of consensus payload datum.
@@ -29,7 +30,31 @@ pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
         &self,
         proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
     ) -> Result<()>;
+    /// Journal a VID dispersal before sending it, with the serialized share message for each
+    /// recipient, so the dispersal can be resumed if we crash before every share is sent.
+    async fn append_vid_dispersal(
+        &self,
+        view: TYPES::Time,
+        messages: &HashMap<TYPES::SignatureKey, Vec<u8>>,
+    ) -> Result<()>;
+    /// Record that the share for `recipient` of the dispersal journaled for `view` has been sent.
+    async fn record_vid_share_sent(
+        &self,
+        view: TYPES::Time,
+        recipient: &TYPES::SignatureKey,
+    ) -> Result<()>;
+    /// Load the share messages of journaled dispersals which haven't been sent yet, keyed by view.
+    async fn load_pending_vid_dispersals(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Time, HashMap<TYPES::SignatureKey, Vec<u8>>>>;
+    /// Remove the journaled dispersals for views before `view`.
+    async fn gc_vid_dispersals(&self, _view: TYPES::Time) -> Result<()> {
+        Ok(())
+    }
     /// Record a HotShotAction taken.
+    ///
+    /// Returns an error if `action` is a quorum vote in a view we have already voted in, including
+    /// before a restart, and the caller must not send the vote then.
     async fn record_action(&self, view: TYPES::Time, action: HotShotAction) -> Result<()>;
     /// Update the current high QC in storage.
     async fn update_high_qc(&self, high_qc: QuorumCertificate<TYPES>) -> Result<()>;
@@ -40,4 +65,39 @@ pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
         leafs: CommitmentMap<Leaf<TYPES>>,
         state: BTreeMap<TYPES::Time, View<TYPES>>,
     ) -> Result<()>;
+    /// Load the high QC last written with [`update_high_qc`](Self::update_high_qc), if any.
+    async fn load_high_qc(&self) -> Result<Option<QuorumCertificate<TYPES>>> {
+        Ok(None)
+    }
+    /// Load the undecided leaf chain and state last written with
+    /// [`update_undecided_state`](Self::update_undecided_state), if any.
+    async fn load_undecided_state(
+        &self,
+    ) -> Result<
+        Option<(
+            CommitmentMap<Leaf<TYPES>>,
+            BTreeMap<TYPES::Time, View<TYPES>>,
+        )>,
+    > {
+        Ok(None)
+    }
+    /// Load the stored DA proposals, keyed by view.
+    async fn load_da_proposals(
+        &self,
+    ) -> Result<BTreeMap<TYPES::Time, Proposal<TYPES, DaProposal<TYPES>>>> {
+        Ok(BTreeMap::new())
+    }
+    /// Add DA votes we've accumulated towards the DA certificate we're forming in `view`, so a
+    /// restarted leader can resume collecting them.
+    async fn append_da_votes(&self, _view: TYPES::Time, _votes: &[DaVote<TYPES>]) -> Result<()> {
+        Ok(())
+    }
+    /// Load the DA votes stored for `view` with [`append_da_votes`](Self::append_da_votes).
+    async fn load_da_votes(&self, _view: TYPES::Time) -> Result<Vec<DaVote<TYPES>>> {
+        Ok(Vec::new())
+    }
+    /// Remove the stored DA votes for views before `view`.
+    async fn gc_da_votes(&self, _view: TYPES::Time) -> Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,50 @@
+//! Proof of work on submitted transactions, to throttle spam from submitters we can't identify
+//!
+//! A transaction submitted with nonce `n` carries enough work for a difficulty of `d` if the
+//! SHA-256 hash of the transaction's commitment followed by the little-endian bytes of `n` starts
+//! with at least `d` zero bits.
+
+use committable::Committable;
+use sha2::{Digest, Sha256};
+
+/// The hash a submitter searches over for a transaction with the given commitment and nonce
+fn pow_hash(commitment: &[u8], nonce: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(commitment);
+    hasher.update(nonce.to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// The number of leading zero bits of `hash`
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// Whether `nonce` is a proof of work for `transaction` of at least `difficulty` leading zero bits.
+///
+/// Every nonce is valid for a difficulty of 0.
+#[must_use]
+pub fn is_valid_pow<T: Committable>(transaction: &T, nonce: u64, difficulty: u32) -> bool {
+    difficulty == 0
+        || leading_zero_bits(&pow_hash(transaction.commit().as_ref(), nonce)) >= difficulty
+}
+
+/// Find the smallest nonce which is a proof of work for `transaction` of `difficulty` leading zero
+/// bits.
+///
+/// This takes about `2^difficulty` hashes, so the difficulty should stay small. Returns `None` if
+/// no nonce is valid.
+#[must_use]
+pub fn solve_pow<T: Committable>(transaction: &T, difficulty: u32) -> Option<u64> {
+    let commitment = transaction.commit();
+    (0..=u64::MAX).find(|&nonce| {
+        difficulty == 0 || leading_zero_bits(&pow_hash(commitment.as_ref(), nonce)) >= difficulty
+    })
+}
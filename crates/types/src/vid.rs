@@ -50,11 +50,6 @@ use crate::constants::SRS_DEGREE;
 /// When the construction fails for the underlying VID scheme.
 #[must_use]
 pub fn vid_scheme(num_storage_nodes: usize) -> VidSchemeType {
-    // recovery_threshold is currently num_storage_nodes rounded down to a power of two
-    // TODO recovery_threshold should be a function of the desired erasure code rate
-    // https://github.com/EspressoSystems/HotShot/issues/2152
-    let recovery_threshold = 1 << num_storage_nodes.ilog2();
-
     #[allow(clippy::panic)]
     let num_storage_nodes = u32::try_from(num_storage_nodes).unwrap_or_else(|err| {
         panic!(
@@ -62,6 +57,7 @@ pub fn vid_scheme(num_storage_nodes: usize) -> VidSchemeType {
                 error: {err}"
         )
     });
+    let recovery_threshold = vid_recovery_threshold(num_storage_nodes);
 
     // TODO panic, return `Result`, or make `new` infallible upstream (eg. by panicking)?
     #[allow(clippy::panic)]
@@ -76,11 +72,11 @@ pub fn vid_scheme(num_storage_nodes: usize) -> VidSchemeType {
 /// Similar to [`vid_scheme()`], but with `KZG_SRS_TEST` for testing purpose only.
 #[cfg(feature = "test-srs")]
 pub fn vid_scheme_for_test(num_storage_nodes: usize) -> VidSchemeType {
-    let recovery_threshold = 1 << num_storage_nodes.ilog2();
     #[allow(clippy::panic)]
     let num_storage_nodes = u32::try_from(num_storage_nodes).unwrap_or_else(|err| {
         panic!("num_storage_nodes {num_storage_nodes} should fit into u32; error: {err}")
     });
+    let recovery_threshold = vid_recovery_threshold(num_storage_nodes);
     #[allow(clippy::panic)]
     VidSchemeType(
         Advz::new(num_storage_nodes, recovery_threshold, &*KZG_SRS_TEST).unwrap_or_else(|err| {
@@ -90,6 +86,15 @@ pub fn vid_scheme_for_test(num_storage_nodes: usize) -> VidSchemeType {
     )
 }
 
+/// The number of VID shares needed to recover a payload dispersed to `num_storage_nodes`.
+#[must_use]
+pub fn vid_recovery_threshold(num_storage_nodes: u32) -> u32 {
+    // recovery_threshold is currently num_storage_nodes rounded down to a power of two
+    // TODO recovery_threshold should be a function of the desired erasure code rate
+    // https://github.com/EspressoSystems/HotShot/issues/2152
+    1 << num_storage_nodes.ilog2()
+}
+
 /// VID commitment type
 pub type VidCommitment = <VidSchemeType as VidScheme>::Commit;
 /// VID common type
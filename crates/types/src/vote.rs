@@ -15,7 +15,7 @@ use crate::{
     data::{Leaf, QuorumProposal, VidDisperseShare},
     message::Proposal,
     simple_certificate::{DaCertificate, Threshold},
-    simple_vote::Voteable,
+    simple_vote::{VoteExtension, Voteable},
     traits::{
         election::Membership,
         node_implementation::NodeType,
@@ -75,14 +75,10 @@ pub trait Certificate<TYPES: NodeType>: HasViewNumber<TYPES> {
     /// Get the vote commitment which the votes commit to
     fn date_commitment(&self) -> Commitment<Self::Voteable>;
 }
-/// Mapping of vote commitment to signatures and bitvec
-type SignersMap<COMMITMENT, KEY> = HashMap<
-    COMMITMENT,
-    (
-        BitVec,
-        Vec<<KEY as SignatureKey>::PureAssembledSignatureType>,
-    ),
->;
+/// Mapping of vote commitment to the bitvec of nodes which voted for it
+type SignersMap<COMMITMENT> = HashMap<COMMITMENT, BitVec>;
+/// Mapping of vote commitment to the vote extensions of each signer
+type ExtensionsMap<COMMITMENT, KEY> = HashMap<COMMITMENT, BTreeMap<KEY, Vec<u8>>>;
 /// Accumulates votes until a certificate is formed.  This implementation works for all simple vote and certificate pairs
 pub struct VoteAccumulator<
     TYPES: NodeType,
@@ -96,8 +92,9 @@ pub struct VoteAccumulator<
         <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
     >,
     /// A bitvec to indicate which node is active and send out a valid signature for certificate aggregation, this automatically do uniqueness check
-    /// And a list of valid signatures for certificate aggregation
-    pub signers: SignersMap<Commitment<VOTE::Commitment>, TYPES::SignatureKey>,
+    pub signers: SignersMap<Commitment<VOTE::Commitment>>,
+    /// Application data attached to the accumulated votes, by signer
+    pub extensions: ExtensionsMap<Commitment<VOTE::Commitment>, TYPES::SignatureKey>,
     /// Phantom data to specify the types this accumulator is for
     pub phantom: PhantomData<(TYPES, VOTE, CERT)>,
 }
@@ -127,9 +124,6 @@ impl<TYPES: NodeType, VOTE: Vote<TYPES>, CERT: Certificate<TYPES, Voteable = VOT
             return Either::Left(());
         };
 
-        let original_signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType =
-            vote.signature();
-
         let (total_stake_casted, total_vote_map) = self
             .vote_outcomes
             .entry(vote_commitment)
@@ -139,16 +133,15 @@ impl<TYPES: NodeType, VOTE: Vote<TYPES>, CERT: Certificate<TYPES, Voteable = VOT
         if total_vote_map.contains_key(&key) {
             return Either::Left(());
         }
-        let (signers, sig_list) = self
+        let signers = self
             .signers
             .entry(vote_commitment)
-            .or_insert((bitvec![0; membership.total_nodes()], Vec::new()));
+            .or_insert(bitvec![0; membership.total_nodes()]);
         if signers.get(vote_node_id).as_deref() == Some(&true) {
             error!("Node id is already in signers list");
             return Either::Left(());
         }
         signers.set(vote_node_id, true);
-        sig_list.push(original_signature);
 
         // TODO: Get the stake from the stake table entry.
         *total_stake_casted += stake_table_entry.stake();
@@ -156,17 +149,21 @@ impl<TYPES: NodeType, VOTE: Vote<TYPES>, CERT: Certificate<TYPES, Voteable = VOT
 
         if *total_stake_casted >= CERT::threshold(membership).into() {
             // Assemble QC
-            let real_qc_pp: <<TYPES as NodeType>::SignatureKey as SignatureKey>::QcParams =
-                <TYPES::SignatureKey as SignatureKey>::public_parameter(
-                    stake_table,
-                    U256::from(CERT::threshold(membership)),
-                );
-
-            let real_qc_sig = <TYPES::SignatureKey as SignatureKey>::assemble(
-                &real_qc_pp,
-                signers.as_bitslice(),
-                &sig_list[..],
-            );
+            let signatures: Vec<_> = total_vote_map
+                .iter()
+                .map(|(key, (signature, _))| (key.clone(), signature.clone()))
+                .collect();
+            let real_qc_sig = match <TYPES::SignatureKey as SignatureKey>::aggregate::<TYPES, _>(
+                &signatures,
+                membership,
+                U256::from(CERT::threshold(membership)),
+            ) {
+                Ok(real_qc_sig) => real_qc_sig,
+                Err(e) => {
+                    error!("Failed to aggregate votes: {e:#}");
+                    return Either::Left(());
+                }
+            };
 
             let cert = CERT::create_signed_certificate(
                 vote.date_commitment(),
@@ -178,6 +175,52 @@ impl<TYPES: NodeType, VOTE: Vote<TYPES>, CERT: Certificate<TYPES, Voteable = VOT
         }
         Either::Left(())
     }
+
+    /// Like [`Self::accumulate`], for a vote carrying application data. The extension is kept, by
+    /// signer, if the vote is counted.
+    pub fn accumulate_with_extension(
+        &mut self,
+        vote: &VOTE,
+        extension: &VoteExtension<TYPES>,
+        membership: &TYPES::Membership,
+    ) -> Either<(), CERT> {
+        if !extension.is_valid_for(vote) {
+            error!("Invalid vote extension! Vote Data {:?}", vote.date());
+            return Either::Left(());
+        }
+
+        let (commitment, key) = (vote.date_commitment(), vote.signing_key());
+        let is_new = !self.contains(&commitment, &key);
+        let result = self.accumulate(vote, membership);
+        if is_new && (result.is_right() || self.contains(&commitment, &key)) {
+            self.extensions
+                .entry(commitment)
+                .or_default()
+                .insert(key, extension.data.clone());
+        }
+        result
+    }
+
+    /// The application data attached to the votes accumulated for `commitment`, by signer.
+    #[must_use]
+    pub fn extensions(
+        &self,
+        commitment: &Commitment<VOTE::Commitment>,
+    ) -> BTreeMap<TYPES::SignatureKey, Vec<u8>> {
+        self.extensions.get(commitment).cloned().unwrap_or_default()
+    }
+
+    /// Whether a vote by `key` for `commitment` has been accumulated.
+    #[must_use]
+    pub fn contains(
+        &self,
+        commitment: &Commitment<VOTE::Commitment>,
+        key: &TYPES::SignatureKey,
+    ) -> bool {
+        self.vote_outcomes
+            .get(commitment)
+            .is_some_and(|(_, votes)| votes.contains_key(key))
+    }
 }
 
 /// Mapping of commitments to vote tokens by key.